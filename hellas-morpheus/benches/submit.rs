@@ -0,0 +1,56 @@
+//! Admitting transactions one at a time versus in batches
+use criterion::{BatchSize, Criterion, black_box, criterion_group, criterion_main};
+use hellas_morpheus::Identity;
+use hellas_morpheus::test_harness::{MockHarness, TestTransaction};
+
+const TXS: usize = 10_000;
+
+fn transactions() -> Vec<TestTransaction> {
+    (0..TXS)
+        .map(|i| TestTransaction((i as u64).to_le_bytes().to_vec()))
+        .collect()
+}
+
+fn submit_benchmark(c: &mut Criterion) {
+    let harness = MockHarness::create_test_setup(1);
+    let process = harness.processes[&Identity(1)].clone();
+
+    let mut group = c.benchmark_group("submit");
+
+    group.bench_function("per_tx", |b| {
+        b.iter_batched(
+            || (process.clone(), transactions()),
+            |(mut process, txs)| {
+                for tx in txs {
+                    process.submit(tx).unwrap();
+                }
+                black_box(process.ready_transactions.len())
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.bench_function("batch", |b| {
+        b.iter_batched(
+            || (process.clone(), transactions()),
+            |(mut process, txs)| black_box(process.submit_batch(txs).admitted),
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.bench_function("stream", |b| {
+        b.iter_batched(
+            || process.clone(),
+            |mut process| {
+                let stream = (0..TXS).map(|i| TestTransaction((i as u64).to_le_bytes().to_vec()));
+                black_box(process.submit_stream(stream, 1024).admitted)
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, submit_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,342 @@
+//! A counter built on Morpheus, with a wallet that signs changes to it
+//!
+//! The application is the smallest one worth ordering: a shared counter that
+//! wallets increment or decrement. A [`Wallet`] signs each change with its own
+//! key and numbers it, the cluster orders the encoded changes like any other
+//! transaction, and every node applies what it finalizes to a [`Counter`],
+//! which checks the signature and drops replays. Once a change is final, the
+//! wallet asks a node for an [`InclusionProof`] and checks it against the
+//! committee's public keys alone.
+//!
+//! Consensus only sees opaque bytes: the application decides what a
+//! transaction means, which is how anything built on this crate plugs in.
+//!
+//! Usage: `cargo run --example counter -- [--nodes N] [increment AMOUNT | decrement AMOUNT]...`
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::test_rng;
+use hellas_morpheus::test_harness::{MockHarness, TestTransaction};
+use hellas_morpheus::{Block, BlockData, BlockKey, FinishedQC, Identity, KeyBook, MerkleProof};
+
+/// Steps to wait for everything submitted to be finalized
+const MAX_STEPS: usize = 500;
+
+/// A signed change to the counter, as carried in a transaction
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+struct CounterTx {
+    wallet: hints::PublicKey,
+    /// Distinguishes otherwise identical changes by the same wallet
+    nonce: u64,
+    delta: i64,
+    signature: hints::PartialSignature,
+}
+
+impl CounterTx {
+    fn signed_message(wallet: &hints::PublicKey, nonce: u64, delta: i64) -> Vec<u8> {
+        let mut buf = b"counter-example-v1".to_vec();
+        wallet.serialize_compressed(&mut buf).unwrap();
+        nonce.serialize_compressed(&mut buf).unwrap();
+        delta.serialize_compressed(&mut buf).unwrap();
+        buf
+    }
+
+    fn encode(&self) -> TestTransaction {
+        let mut buf = Vec::new();
+        self.serialize_compressed(&mut buf).unwrap();
+        TestTransaction(buf)
+    }
+
+    /// The change carried by `tx`, if it is one
+    fn decode(tx: &TestTransaction) -> Option<Self> {
+        Self::deserialize_compressed(tx.0.as_slice()).ok()
+    }
+}
+
+/// Holds a key and signs changes to the counter with it
+struct Wallet {
+    key: hints::SecretKey,
+    public: hints::PublicKey,
+    next_nonce: u64,
+}
+
+impl Wallet {
+    fn new(key: hints::SecretKey, global: &hints::GlobalData) -> Self {
+        Wallet {
+            public: key.public(global),
+            key,
+            next_nonce: 0,
+        }
+    }
+
+    fn increment(&mut self, amount: u32) -> CounterTx {
+        self.sign(amount as i64)
+    }
+
+    fn decrement(&mut self, amount: u32) -> CounterTx {
+        self.sign(-(amount as i64))
+    }
+
+    fn sign(&mut self, delta: i64) -> CounterTx {
+        let nonce = self.next_nonce;
+        self.next_nonce += 1;
+        let signature = hints::sign(
+            &self.key,
+            &CounterTx::signed_message(&self.public, nonce, delta),
+        );
+        CounterTx {
+            wallet: self.public.clone(),
+            nonce,
+            delta,
+            signature,
+        }
+    }
+}
+
+/// The application state every node derives from the blocks it finalizes
+///
+/// Changes add up the same in any order, so nodes agree on the count as soon
+/// as they agree on which blocks are final, without agreeing on an order
+/// within them. An application whose transactions don't commute would apply
+/// them in the order of the DAG instead.
+#[derive(Default)]
+struct Counter {
+    value: i64,
+    applied: BTreeSet<(Vec<u8>, u64)>,
+}
+
+impl Counter {
+    /// Applies `tx` if it's a validly signed change not applied before
+    fn apply(&mut self, tx: &TestTransaction, global: &hints::GlobalData) -> bool {
+        let Some(change) = CounterTx::decode(tx) else {
+            return false;
+        };
+        let message = CounterTx::signed_message(&change.wallet, change.nonce, change.delta);
+        if !hints::verify_partial(global, &change.wallet, &message, &change.signature) {
+            return false;
+        }
+        let mut wallet = Vec::new();
+        change.wallet.serialize_compressed(&mut wallet).unwrap();
+        if !self.applied.insert((wallet, change.nonce)) {
+            return false;
+        }
+        self.value += change.delta;
+        true
+    }
+}
+
+/// Where a transaction was included, and the certificate on that block
+///
+/// Carries the block's header rather than the block, so its size doesn't
+/// grow with the number of other transactions in the block.
+struct InclusionProof {
+    header: Block<TestTransaction>,
+    path: MerkleProof,
+    qc: FinishedQC,
+}
+
+impl InclusionProof {
+    /// Checks that this shows `tx` in a block the committee certified
+    ///
+    /// n-f members voted for the block's key, which carries the hash of the
+    /// header, which carries the Merkle root the path leads to. Honest
+    /// members vote for one block per author and slot, so no other block can
+    /// be certified in its place.
+    fn verify(&self, tx: &CounterTx, committee: &KeyBook, quorum: u32) -> bool {
+        let header = &self.header;
+        header.key.hash == Some(header.content_hash())
+            && header
+                .tx_root()
+                .is_some_and(|root| self.path.verify(&tx.encode(), root))
+            && self.qc.data.for_which == header.key
+            && self
+                .qc
+                .verify(&committee.hints_setup, &committee.chain_id, quorum)
+    }
+}
+
+/// A node's view of the counter, fed with the blocks it finalizes
+struct Node {
+    id: Identity,
+    counter: Counter,
+    applied_blocks: BTreeSet<BlockKey>,
+}
+
+impl Node {
+    fn catch_up(&mut self, harness: &MockHarness) {
+        let process = &harness.processes[&self.id];
+        for key in process.finalized_blocks() {
+            if self.applied_blocks.contains(&key) {
+                continue;
+            }
+            let Some(block) = process.index.blocks.get(&key) else {
+                continue;
+            };
+            self.applied_blocks.insert(key);
+            if let BlockData::Tr { transactions, .. } = &block.data.data {
+                for tx in transactions {
+                    self.counter.apply(tx, &process.kb.hints_setup.global);
+                }
+            }
+        }
+    }
+
+    /// Proof that `tx` is in a block this node has finalized
+    fn prove(&self, harness: &MockHarness, tx: &TestTransaction) -> Option<InclusionProof> {
+        let process = &harness.processes[&self.id];
+        let finalized = process.finalized_blocks();
+        process.index.blocks.values().find_map(|block| {
+            let BlockData::Tr { transactions, .. } = &block.data.data else {
+                return None;
+            };
+            let position = transactions.iter().position(|t| t == tx)?;
+            if !finalized.contains(&block.data.key) {
+                return None;
+            }
+            // the most votes we have for it: a 2-QC if there is one
+            let qc = process
+                .qcs
+                .iter()
+                .filter(|qc| qc.data.for_which == block.data.key)
+                .max_by_key(|qc| qc.data.z)?
+                .clone();
+            Some(InclusionProof {
+                header: block.data.header(),
+                path: block.data.tx_proof(position)?,
+                qc,
+            })
+        })
+    }
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: counter [--nodes N] [increment AMOUNT | decrement AMOUNT]...\n\
+         e.g.   counter increment 5 decrement 2"
+    );
+    std::process::exit(2);
+}
+
+fn main() {
+    let mut nodes = 4;
+    let mut ops = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || -> u32 {
+            args.next()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or_else(|| usage())
+        };
+        match arg.as_str() {
+            "--nodes" => nodes = value() as usize,
+            "increment" | "inc" => ops.push(value() as i64),
+            "decrement" | "dec" => ops.push(-(value() as i64)),
+            _ => usage(),
+        }
+    }
+    if nodes == 0 {
+        usage();
+    }
+    if ops.is_empty() {
+        ops = vec![5, -2, 1];
+    }
+
+    let mut harness = MockHarness::create_test_setup(nodes);
+    let committee = harness.processes[&Identity(1)].kb.clone();
+    let quorum = {
+        let process = &harness.processes[&Identity(1)];
+        process.n - process.f
+    };
+    let mut wallet = Wallet::new(
+        hints::SecretKey::random(&mut test_rng()),
+        &committee.hints_setup.global,
+    );
+
+    // submit each change to a different node
+    let ids: Vec<Identity> = harness.processes.keys().cloned().collect();
+    let mut submitted = Vec::new();
+    for (i, delta) in ops.iter().enumerate() {
+        let tx = if *delta >= 0 {
+            wallet.increment(*delta as u32)
+        } else {
+            wallet.decrement(delta.unsigned_abs() as u32)
+        };
+        let node = &ids[i % ids.len()];
+        harness
+            .processes
+            .get_mut(node)
+            .unwrap()
+            .submit(tx.encode())
+            .expect("transaction serializes");
+        println!(
+            "submitted {:+} (nonce {}) to node {}",
+            delta, tx.nonce, node.0
+        );
+        submitted.push(tx);
+    }
+
+    let mut replicas: BTreeMap<Identity, Node> = ids
+        .iter()
+        .map(|id| {
+            let node = Node {
+                id: id.clone(),
+                counter: Counter::default(),
+                applied_blocks: BTreeSet::new(),
+            };
+            (id.clone(), node)
+        })
+        .collect();
+    let expected: i64 = ops.iter().sum();
+    for _ in 0..MAX_STEPS {
+        harness.step();
+        for node in replicas.values_mut() {
+            node.catch_up(&harness);
+        }
+        if replicas
+            .values()
+            .all(|node| node.counter.applied.len() == submitted.len())
+        {
+            break;
+        }
+    }
+
+    for node in replicas.values() {
+        println!(
+            "node {}: counter = {} ({} of {} changes applied)",
+            node.id.0,
+            node.counter.value,
+            node.counter.applied.len(),
+            submitted.len()
+        );
+    }
+
+    let mut all_proven = true;
+    let prover = &replicas[&Identity(1)];
+    for tx in &submitted {
+        match prover.prove(&harness, &tx.encode()) {
+            Some(proof) => {
+                let valid = proof.verify(tx, &committee, quorum);
+                println!(
+                    "nonce {}: in block {:?} at position {}, {}-QC, proof {}",
+                    tx.nonce,
+                    proof.header.key,
+                    proof.path.index,
+                    proof.qc.data.z + 1,
+                    if valid { "valid" } else { "INVALID" }
+                );
+                all_proven &= valid;
+            }
+            None => {
+                println!("nonce {}: not finalized yet", tx.nonce);
+                all_proven = false;
+            }
+        }
+    }
+
+    let agreed = replicas.values().all(|node| node.counter.value == expected);
+    if !agreed || !all_proven {
+        std::process::exit(1);
+    }
+    println!("all nodes agree: counter = {}", expected);
+}
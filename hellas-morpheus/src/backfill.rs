@@ -0,0 +1,196 @@
+//! Checking a long range of synced history in parallel
+//!
+//! A node catching up receives blocks and QCs for many views at once. Fed
+//! through `process_message` one by one, each QC's aggregate check waits on
+//! the one before, and initial sync takes as long as verifying the whole
+//! chain on one core. None of those checks depend on each other, though,
+//! only on which key each author held in each view. So
+//! [`MorpheusProcess::verify_history`] first applies the key rotations in
+//! the range, in the order they were announced, which fixes the key every
+//! member held in every view; then it checks blocks and QCs in batches spread
+//! over several threads, emitting a `BackfillProgress` event after each
+//! round of batches.
+//!
+//! Only signatures and content hashes are checked here; the blocks and QCs
+//! still go through `process_message` (or `StateIndex::rebuild`) to be
+//! recorded, which can then skip repeating the checks by way of a
+//! `CachingVerifier`.
+
+use std::fmt;
+use std::sync::Arc;
+use std::thread;
+
+use crate::*;
+
+/// A range of history fetched from peers
+#[derive(Clone, Debug)]
+pub struct History<Tr: Transaction> {
+    pub blocks: Vec<Arc<Signed<Block<Tr>>>>,
+    pub qcs: Vec<FinishedQC>,
+    pub rotations: Vec<Arc<Signed<KeyRotation>>>,
+}
+
+impl<Tr: Transaction> Default for History<Tr> {
+    fn default() -> Self {
+        History {
+            blocks: Vec::new(),
+            qcs: Vec::new(),
+            rotations: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BackfillConfig {
+    /// Blocks and QCs checked by one thread before reporting back
+    pub batch_size: usize,
+    /// Batches checked at the same time
+    pub threads: usize,
+}
+
+impl Default for BackfillConfig {
+    fn default() -> Self {
+        BackfillConfig {
+            batch_size: 64,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+}
+
+/// The first thing in a range of history that didn't check out
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BackfillError {
+    InvalidRotation {
+        author: Identity,
+        effective: ViewNum,
+    },
+    InvalidBlock(BlockKey),
+    InvalidQc(VoteData),
+}
+
+impl fmt::Display for BackfillError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackfillError::InvalidRotation { author, effective } => write!(
+                f,
+                "invalid key rotation by {:?} effective in view {}",
+                author, effective.0
+            ),
+            BackfillError::InvalidBlock(key) => write!(f, "invalid block {:?}", key),
+            BackfillError::InvalidQc(data) => write!(f, "invalid QC for {:?}", data),
+        }
+    }
+}
+
+impl std::error::Error for BackfillError {}
+
+enum Item<'a, Tr: Transaction> {
+    Block(&'a Signed<Block<Tr>>),
+    Qc(&'a FinishedQC),
+}
+
+impl<Tr: Transaction> Item<'_, Tr> {
+    fn view(&self) -> ViewNum {
+        match self {
+            Item::Block(block) => block.data.key.view,
+            Item::Qc(qc) => qc.data.for_which.view,
+        }
+    }
+
+    fn check(&self, verifier: &dyn Verifier, threshold: u32) -> Result<(), BackfillError> {
+        match self {
+            Item::Block(signed) => {
+                let block = &signed.data;
+                let valid = block.key.author.as_ref() == Some(&signed.author)
+                    && block.key.hash.as_ref() == Some(&block.content_hash())
+                    && signed.valid_signature_at(verifier, block.key.view);
+                if !valid {
+                    return Err(BackfillError::InvalidBlock(block.key.clone()));
+                }
+            }
+            Item::Qc(qc) => {
+                if !qc.valid_signature(verifier, threshold) {
+                    return Err(BackfillError::InvalidQc(qc.data.clone()));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<Tr: Transaction> MorpheusProcess<Tr> {
+    /// Checks every signature in `history`, recording its key rotations
+    ///
+    /// Returns how many blocks and QCs were checked. On an error, rotations
+    /// already recorded stay recorded: each was valid on its own.
+    pub fn verify_history(
+        &mut self,
+        history: &History<Tr>,
+        config: BackfillConfig,
+    ) -> Result<usize, BackfillError> {
+        let mut rotations = history.rotations.iter().collect::<Vec<_>>();
+        rotations.sort_by_key(|rotation| (rotation.data.announced, rotation.data.effective));
+        for rotation in rotations {
+            if !self.key_rotation_valid(rotation) {
+                return Err(BackfillError::InvalidRotation {
+                    author: rotation.author.clone(),
+                    effective: rotation.data.effective,
+                });
+            }
+            self.kb
+                .rotations
+                .entry(rotation.author.clone())
+                .or_default()
+                .insert(rotation.data.effective, rotation.data.new_key.clone());
+        }
+
+        let mut items = history
+            .blocks
+            .iter()
+            .filter(|block| block.data.key.type_ != BlockType::Genesis)
+            .map(|block| Item::Block(block))
+            .chain(
+                history
+                    .qcs
+                    .iter()
+                    .filter(|qc| **qc != self.genesis_qc)
+                    .map(Item::Qc),
+            )
+            .collect::<Vec<_>>();
+        items.sort_by_key(Item::view);
+
+        let total = items.len();
+        let threshold = self.n - self.f;
+        let batch_size = config.batch_size.max(1);
+        let mut verified = 0;
+        for round in items.chunks(batch_size * config.threads.max(1)) {
+            let verifier = self.verifier();
+            let failed = thread::scope(|scope| {
+                let workers = round
+                    .chunks(batch_size)
+                    .map(|batch| {
+                        scope.spawn(move || {
+                            batch
+                                .iter()
+                                .try_for_each(|item| item.check(verifier, threshold))
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                workers
+                    .into_iter()
+                    .map(|worker| worker.join().expect("backfill worker panicked"))
+                    .find_map(Result::err)
+            });
+            if let Some(error) = failed {
+                return Err(error);
+            }
+            verified += round.len();
+            self.emit(ProtocolEvent::BackfillProgress {
+                verified,
+                total,
+                through: round.last().map_or(ViewNum(0), Item::view),
+            });
+        }
+        Ok(total)
+    }
+}
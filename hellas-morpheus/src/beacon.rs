@@ -0,0 +1,44 @@
+use ark_serialize::CanonicalSerialize;
+use sha2::{Digest, Sha256};
+
+use crate::*;
+
+/// Domain separator for beacon outputs, so they can't collide with other hashes
+const BEACON_DOMAIN: &[u8] = b"morpheus-beacon-v1";
+
+impl<Tr: Transaction> MorpheusProcess<Tr> {
+    /// Returns the shared random beacon output for `view`
+    ///
+    /// The beacon is the hash of the earliest (lowest slot) finalized leader
+    /// block of the view, including the leader's signature over it. It is
+    /// only taken once the view is final, that is once the finalized history
+    /// (the ancestors of every block finalized so far, not only the blocks
+    /// whose 2-QCs this process saw) reaches a block of a later view. A
+    /// leader's blocks each point to its previous one, so by then the earliest
+    /// leader block of the view is settled, and all correct processes derive
+    /// the same value, which never changes afterwards. Nobody but the view's
+    /// leader can predict it before the leader block is produced; the leader
+    /// can, and can choose between blocks to steer it.
+    ///
+    /// Returns `None` until the view is final, or if no leader block of it
+    /// was finalized.
+    pub fn beacon(&self, view: ViewNum) -> Option<[u8; 32]> {
+        let finalized = self.finalized_blocks();
+        if !finalized.iter().any(|key| key.view > view) {
+            return None;
+        }
+        let key = finalized
+            .iter()
+            .filter(|key| key.type_ == BlockType::Lead && key.view == view)
+            .min_by_key(|key| key.slot)?;
+        let block = self.index.blocks.get(key)?;
+
+        let mut hasher = Sha256::new();
+        hasher.update(BEACON_DOMAIN);
+        hasher.update(view.0.to_le_bytes());
+        let mut buf = Vec::new();
+        block.serialize_compressed(&mut buf).unwrap();
+        hasher.update(&buf);
+        Some(hasher.finalize().into())
+    }
+}
@@ -0,0 +1,218 @@
+//! Drives a simulated cluster with client load and reports the latency
+//! clients observe, from submitting a transaction to a node until that node
+//! finalizes it.
+//!
+//! Open-loop mode submits a fixed number of transactions every step whatever
+//! happens to earlier ones; closed-loop mode keeps a fixed number in flight,
+//! submitting a new one as each is finalized. Latencies are in steps of the
+//! simulation, each of which is one Δ.
+//!
+//! Usage: `morpheus-loadgen [--nodes N] [--steps S] [--open RATE | --closed IN_FLIGHT] [--json]`
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use hellas_morpheus::test_harness::{MockHarness, TestTransaction};
+use hellas_morpheus::{BlockData, BlockKey, Identity};
+use serde::Serialize;
+
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Mode {
+    /// This many new transactions every step
+    Open { rate: usize },
+    /// Keep this many transactions in flight
+    Closed { in_flight: usize },
+}
+
+struct Options {
+    nodes: usize,
+    steps: usize,
+    mode: Mode,
+    json: bool,
+}
+
+/// Latencies bucketed by powers of two: bucket `i` holds those below `2^i`
+/// (and at least `2^(i-1)`)
+#[derive(Default, Serialize)]
+struct Histogram {
+    buckets: BTreeMap<u32, usize>,
+    #[serde(skip)]
+    samples: Vec<usize>,
+}
+
+impl Histogram {
+    fn record(&mut self, latency: usize) {
+        let bucket = usize::BITS - latency.leading_zeros();
+        *self.buckets.entry(bucket).or_default() += 1;
+        self.samples.push(latency);
+    }
+
+    fn percentile(&self, p: f64) -> Option<usize> {
+        let mut sorted = self.samples.clone();
+        sorted.sort_unstable();
+        let rank = ((sorted.len() as f64 * p).ceil() as usize).max(1);
+        sorted.get(rank - 1).copied()
+    }
+}
+
+#[derive(Serialize)]
+struct Report {
+    mode: Mode,
+    nodes: usize,
+    steps: usize,
+    submitted: usize,
+    finalized: usize,
+    /// Finalized transactions per step
+    throughput: f64,
+    p50: Option<usize>,
+    p90: Option<usize>,
+    p99: Option<usize>,
+    max: Option<usize>,
+    histogram: Histogram,
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: morpheus-loadgen [--nodes N] [--steps S] [--open RATE | --closed IN_FLIGHT] [--json]"
+    );
+    std::process::exit(2);
+}
+
+fn parse_options() -> Options {
+    let mut options = Options {
+        nodes: 4,
+        steps: 500,
+        mode: Mode::Closed { in_flight: 8 },
+        json: false,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || -> usize {
+            args.next()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or_else(|| usage())
+        };
+        match arg.as_str() {
+            "--nodes" => options.nodes = value(),
+            "--steps" => options.steps = value(),
+            "--open" => options.mode = Mode::Open { rate: value() },
+            "--closed" => options.mode = Mode::Closed { in_flight: value() },
+            "--json" => options.json = true,
+            _ => usage(),
+        }
+    }
+    if options.nodes == 0 {
+        usage();
+    }
+    options
+}
+
+fn run(options: &Options) -> Report {
+    let mut harness = MockHarness::create_test_setup(options.nodes);
+    let nodes = harness.processes.keys().cloned().collect::<Vec<_>>();
+
+    let mut next_tx = 0u64;
+    // transaction -> (node it was submitted to, step it was submitted at)
+    let mut in_flight: BTreeMap<TestTransaction, (Identity, usize)> = BTreeMap::new();
+    let mut seen: BTreeMap<Identity, BTreeSet<BlockKey>> = BTreeMap::new();
+    let mut histogram = Histogram::default();
+
+    for step in 0..options.steps {
+        let wanted = match options.mode {
+            Mode::Open { rate } => rate,
+            Mode::Closed { in_flight: target } => target.saturating_sub(in_flight.len()),
+        };
+        for _ in 0..wanted {
+            let node = nodes[next_tx as usize % nodes.len()].clone();
+            let tx = TestTransaction(next_tx.to_le_bytes().to_vec());
+            next_tx += 1;
+            let process = harness.processes.get_mut(&node).unwrap();
+            if process.submit(tx.clone()).is_ok() {
+                in_flight.insert(tx, (node, step));
+            }
+        }
+
+        harness.step();
+
+        for node in &nodes {
+            let process = &harness.processes[node];
+            let seen_here = seen.entry(node.clone()).or_default();
+            let new: Vec<BlockKey> = process
+                .finalized_blocks()
+                .into_iter()
+                .filter(|key| !seen_here.contains(key))
+                .collect();
+            seen_here.extend(new.iter().cloned());
+
+            for key in new {
+                let Some(block) = process.index.blocks.get(&key) else {
+                    continue;
+                };
+                let BlockData::Tr { transactions, .. } = &block.data.data else {
+                    continue;
+                };
+                for tx in transactions {
+                    if in_flight.get(tx).is_some_and(|(to, _)| to == node) {
+                        let (_, submitted) = in_flight.remove(tx).unwrap();
+                        histogram.record(step + 1 - submitted);
+                    }
+                }
+            }
+        }
+    }
+
+    let finalized = histogram.samples.len();
+    Report {
+        mode: options.mode,
+        nodes: options.nodes,
+        steps: options.steps,
+        submitted: next_tx as usize,
+        finalized,
+        throughput: finalized as f64 / options.steps.max(1) as f64,
+        p50: histogram.percentile(0.5),
+        p90: histogram.percentile(0.9),
+        p99: histogram.percentile(0.99),
+        max: histogram.samples.iter().max().copied(),
+        histogram,
+    }
+}
+
+fn print_report(report: &Report) {
+    let show = |v: Option<usize>| v.map_or("-".to_string(), |v| v.to_string());
+    println!(
+        "{:?} on {} nodes for {} steps",
+        report.mode, report.nodes, report.steps
+    );
+    println!(
+        "submitted {}, finalized {} ({:.2} per step)",
+        report.submitted, report.finalized, report.throughput
+    );
+    println!(
+        "latency (steps): p50 {}  p90 {}  p99 {}  max {}",
+        show(report.p50),
+        show(report.p90),
+        show(report.p99),
+        show(report.max)
+    );
+    for (bucket, count) in &report.histogram.buckets {
+        let low = if *bucket == 0 {
+            0
+        } else {
+            1usize << (bucket - 1)
+        };
+        println!("  [{:>5}, {:>5})  {}", low, 1usize << bucket, count);
+    }
+}
+
+fn main() {
+    let options = parse_options();
+    let report = run(&options);
+    if options.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).expect("report serializes")
+        );
+    } else {
+        print_report(&report);
+    }
+}
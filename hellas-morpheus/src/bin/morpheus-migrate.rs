@@ -0,0 +1,52 @@
+//! Brings a store written by an older build up to this build's format, the
+//! way opening it would, or with `--dry-run` only prints the migrations that
+//! would run.
+//!
+//! Usage: `morpheus-migrate [--dry-run] <sign-guard|vote-wal> <path>`
+
+use std::path::PathBuf;
+
+use hellas_morpheus::{MigrationRegistry, SignGuard, StoreKind, VoteWal};
+
+fn usage() -> ! {
+    eprintln!("usage: morpheus-migrate [--dry-run] <sign-guard|vote-wal> <path>");
+    std::process::exit(2);
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1).collect::<Vec<_>>();
+    let dry_run = args.iter().any(|arg| arg == "--dry-run");
+    args.retain(|arg| arg != "--dry-run");
+    let [store, path] = args.as_slice() else {
+        usage();
+    };
+    let store = match store.as_str() {
+        "sign-guard" => StoreKind::SignGuard,
+        "vote-wal" => StoreKind::VoteWal,
+        _ => usage(),
+    };
+    let path = PathBuf::from(path);
+
+    let plan = match MigrationRegistry::builtin().plan_file(store, &path) {
+        Ok(plan) => plan,
+        Err(e) => {
+            eprintln!("{}: {e}", path.display());
+            std::process::exit(1);
+        }
+    };
+    println!("{}: {plan}", path.display());
+    if dry_run || plan.is_empty() {
+        return;
+    }
+
+    // opening a store migrates it, under its lock
+    let opened = match store {
+        StoreKind::SignGuard => SignGuard::open(&path).map(drop).map_err(|e| e.to_string()),
+        StoreKind::VoteWal => VoteWal::open(&path).map(drop).map_err(|e| e.to_string()),
+    };
+    if let Err(e) = opened {
+        eprintln!("{}: {e}", path.display());
+        std::process::exit(1);
+    }
+    println!("{}: migrated to version {}", path.display(), plan.to);
+}
@@ -0,0 +1,82 @@
+//! Runs a matrix of simulations in parallel and prints how they compare.
+//!
+//! Each axis takes a comma-separated list of values and every combination is
+//! run for the same number of steps; see `hellas_morpheus::sweep`. The table
+//! goes to stdout, or the whole report as JSON with `--json`.
+//!
+//! Usage: `morpheus-sweep [--nodes 4,7] [--delta 100,200] [--loss 0,0.05] [--tx-every 1,2] [--steps S] [--threads T] [--json]`
+
+use std::str::FromStr;
+
+use hellas_morpheus::sweep::SweepMatrix;
+
+struct Options {
+    matrix: SweepMatrix,
+    threads: usize,
+    json: bool,
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: morpheus-sweep [--nodes 4,7] [--delta 100,200] [--loss 0,0.05] [--tx-every 1,2] [--steps S] [--threads T] [--json]"
+    );
+    std::process::exit(2);
+}
+
+fn list<T: FromStr>(value: Option<String>) -> Vec<T> {
+    let values = value
+        .unwrap_or_else(|| usage())
+        .split(',')
+        .map(|v| v.trim().parse().unwrap_or_else(|_| usage()))
+        .collect::<Vec<_>>();
+    if values.is_empty() {
+        usage();
+    }
+    values
+}
+
+fn parse_options() -> Options {
+    let mut options = Options {
+        matrix: SweepMatrix::default(),
+        threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        json: false,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--nodes" => options.matrix.nodes = list(args.next()),
+            "--delta" => options.matrix.deltas = list(args.next()),
+            "--loss" => options.matrix.loss_rates = list(args.next()),
+            "--tx-every" => options.matrix.tx_every = list(args.next()),
+            "--steps" => options.matrix.steps = list(args.next())[0],
+            "--threads" => options.threads = list(args.next())[0],
+            "--json" => options.json = true,
+            _ => usage(),
+        }
+    }
+    options
+}
+
+fn main() {
+    let options = parse_options();
+    let report = match options.matrix.run(options.threads) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("morpheus-sweep: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if options.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).expect("report serializes")
+        );
+    } else {
+        println!(
+            "{} scenarios, {} steps each",
+            report.results.len(),
+            report.steps
+        );
+        print!("{}", report.table());
+    }
+}
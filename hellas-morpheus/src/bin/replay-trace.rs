@@ -0,0 +1,73 @@
+//! Replays recorded execution traces (JSON, as written from
+//! `MockHarness::trace`) against this build, and exits non-zero if any of
+//! them no longer behaves as recorded.
+//!
+//! Usage: `replay-trace <trace.json>...`
+
+use hellas_morpheus::test_harness::TestTransaction;
+use hellas_morpheus::trace::{ExecutionTrace, ReplayDivergence, replay};
+
+fn main() {
+    let paths = std::env::args().skip(1).collect::<Vec<_>>();
+    if paths.is_empty() {
+        eprintln!("usage: replay-trace <trace.json>...");
+        std::process::exit(2);
+    }
+
+    let mut incompatible = 0;
+    for path in &paths {
+        let json = match std::fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(e) => {
+                eprintln!("{path}: {e}");
+                std::process::exit(2);
+            }
+        };
+        let trace: ExecutionTrace<TestTransaction> = match serde_json::from_str(&json) {
+            Ok(trace) => trace,
+            Err(e) => {
+                eprintln!("{path}: not a trace: {e}");
+                std::process::exit(2);
+            }
+        };
+
+        let report = replay(&trace);
+        println!(
+            "{path}: recorded with {}, replayed {}/{} entries",
+            report.recorded_with,
+            report.entries_replayed,
+            trace.entries.len()
+        );
+        for divergence in &report.divergences {
+            match divergence {
+                ReplayDivergence::Messages {
+                    entry,
+                    process,
+                    recorded,
+                    replayed,
+                } => println!(
+                    "  entry {entry}: {process:?} sent {replayed:?}, recorded {recorded:?}"
+                ),
+                ReplayDivergence::Finalization {
+                    entry,
+                    process,
+                    recorded,
+                    replayed,
+                } => println!(
+                    "  entry {entry}: {process:?} finalized {replayed:?}, recorded {recorded:?}"
+                ),
+                ReplayDivergence::UnknownProcess { entry, process } => {
+                    println!("  entry {entry}: {process:?} is not in the trace's setup")
+                }
+            }
+        }
+        if !report.is_compatible() {
+            incompatible += 1;
+        }
+    }
+
+    if incompatible > 0 {
+        println!("{incompatible} of {} traces diverged", paths.len());
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,15 @@
+//! Prints the message handler's transition table, as JSON by default or as a
+//! markdown table with `--markdown`.
+
+use hellas_morpheus::transitions::{TRANSITIONS, to_markdown};
+
+fn main() {
+    if std::env::args().any(|arg| arg == "--markdown") {
+        print!("{}", to_markdown());
+    } else {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(TRANSITIONS).expect("table serializes")
+        );
+    }
+}
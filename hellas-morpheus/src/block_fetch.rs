@@ -0,0 +1,93 @@
+//! Fetching blocks that others point to but we never received
+//!
+//! A block's prev pointers are QCs, and a process can hold a QC without the
+//! block it certifies: the block's broadcast to it may have been dropped
+//! while the votes of others still formed the QC. Such a block leaves a gap
+//! in the DAG, and `observes` walks into it without finding anything.
+//!
+//! So a valid block whose prev pointers name blocks we don't hold is held
+//! back in `awaiting_ancestors`, and a `GetBlock` for each missing block goes
+//! to every member: at least one correct member voted for it and so holds
+//! it. Whoever holds it answers with a `BlockResponse`, which is handled
+//! like the block's original broadcast, and once the last missing ancestor
+//! of a held-back block is recorded, the held-back block is accepted too.
+//! Our own blocks are never held back, since we produced them from what we
+//! had.
+
+use std::sync::Arc;
+
+use crate::*;
+
+impl<Tr: Transaction> MorpheusProcess<Tr> {
+    /// The blocks `block`'s prev pointers name that we don't hold
+    pub fn missing_ancestors(&self, block: &Signed<Block<Tr>>) -> Vec<BlockKey> {
+        block
+            .data
+            .prev
+            .iter()
+            .map(|qc| &qc.data.for_which)
+            .filter(|key| key.type_ != BlockType::Genesis && !self.index.blocks.contains_key(key))
+            .cloned()
+            .collect()
+    }
+
+    /// Holds `block` back until `missing` arrive, asking everyone for those
+    /// we haven't asked for yet
+    pub(crate) fn await_ancestors(
+        &mut self,
+        block: Arc<Signed<Block<Tr>>>,
+        missing: Vec<BlockKey>,
+        to_send: &mut Vec<(Message<Tr>, Option<Identity>)>,
+    ) {
+        for key in missing {
+            tracing::debug!(
+                target: "awaiting_ancestor",
+                process_id = ?self.id,
+                block_key = ?block.data.key,
+                missing = ?key,
+            );
+            if self.requested_blocks.insert(key.clone()) {
+                to_send.push((Message::GetBlock(key.clone()), None));
+            }
+            self.awaiting_ancestors
+                .entry(key)
+                .or_default()
+                .push(block.clone());
+        }
+    }
+
+    /// Records and votes for a valid block whose ancestors we hold, then
+    /// accepts any held-back blocks that were waiting only for it
+    pub(crate) fn accept_block(
+        &mut self,
+        block: &Arc<Signed<Block<Tr>>>,
+        to_send: &mut Vec<(Message<Tr>, Option<Identity>)>,
+    ) {
+        tracing::debug!(
+            target: "valid_block",
+            block_key = ?block.data.key,
+        );
+        // recorded before voting, since in a committee of one our vote
+        // completes the 0-QC, which needs the block to find its place among the tips
+        self.record_block(block);
+        self.try_vote(
+            0,
+            &block.data.key,
+            Some(block.data.key.author.clone().expect("validated")),
+            to_send,
+        );
+
+        let waiting = self
+            .awaiting_ancestors
+            .remove(&block.data.key)
+            .unwrap_or_default();
+        for waiter in waiting {
+            // still held back if other ancestors are missing, under their keys
+            if !self.index.blocks.contains_key(&waiter.data.key)
+                && self.missing_ancestors(&waiter).is_empty()
+            {
+                self.accept_block(&waiter, to_send);
+            }
+        }
+    }
+}
@@ -1,9 +1,94 @@
 use std::{cmp::Ordering, sync::Arc};
 
+use ark_serialize::{SerializationError, Valid};
+
 use crate::*;
 
+/// What happened to a batch of submitted transactions
+#[derive(Debug, Default)]
+pub struct SubmitReceipt {
+    /// Transactions now waiting for our next transaction block
+    pub admitted: usize,
+    /// Position in the batch of each transaction that failed validation
+    pub rejected: Vec<(usize, SerializationError)>,
+}
+
 impl<Tr: Transaction> MorpheusProcess<Tr> {
+    /// Queues one transaction for our next transaction block
+    pub fn submit(&mut self, tx: Tr) -> Result<(), SerializationError> {
+        tx.check()?;
+        self.ready_transactions.push(tx);
+        Ok(())
+    }
+
+    /// Queues many transactions at once
+    ///
+    /// The whole batch is validated with a single `Valid::batch_check`; only if
+    /// that fails is each transaction checked on its own, so that the valid
+    /// ones are still admitted.
+    pub fn submit_batch(&mut self, batch: Vec<Tr>) -> SubmitReceipt {
+        if Tr::batch_check(batch.iter()).is_ok() {
+            let admitted = batch.len();
+            self.ready_transactions.extend(batch);
+            return SubmitReceipt {
+                admitted,
+                rejected: Vec::new(),
+            };
+        }
+
+        let mut receipt = SubmitReceipt::default();
+        for (i, tx) in batch.into_iter().enumerate() {
+            match tx.check() {
+                Ok(()) => {
+                    self.ready_transactions.push(tx);
+                    receipt.admitted += 1;
+                }
+                Err(e) => receipt.rejected.push((i, e)),
+            }
+        }
+        receipt
+    }
+
+    /// [`submit_batch`](Self::submit_batch) over a stream, taking `chunk`
+    /// transactions at a time so a load generator never materializes the whole stream
+    pub fn submit_stream(
+        &mut self,
+        stream: impl IntoIterator<Item = Tr>,
+        chunk: usize,
+    ) -> SubmitReceipt {
+        let chunk = chunk.max(1);
+        let mut receipt = SubmitReceipt::default();
+        let mut stream = stream.into_iter().peekable();
+        let mut offset = 0;
+        while stream.peek().is_some() {
+            let batch: Vec<Tr> = stream.by_ref().take(chunk).collect();
+            let len = batch.len();
+            let part = self.submit_batch(batch);
+            receipt.admitted += part.admitted;
+            receipt
+                .rejected
+                .extend(part.rejected.into_iter().map(|(i, e)| (offset + i, e)));
+            offset += len;
+        }
+        receipt
+    }
+
+    /// Drops transactions that can no longer be included in a block of the
+    /// current view: expired ones, and config changes that are invalid or
+    /// activate too soon
+    pub fn evict_expired(&mut self) -> usize {
+        let view = self.view_i;
+        let before = self.ready_transactions.len();
+        self.ready_transactions.retain(|tx| tx.includable_at(view));
+        let evicted = before - self.ready_transactions.len();
+        if evicted > 0 {
+            tracing::debug!(target: "mempool", process_id = ?self.id, view = view.0, evicted);
+        }
+        evicted
+    }
+
     pub fn try_produce_blocks(&mut self, to_send: &mut Vec<(Message<Tr>, Option<Identity>)>) {
+        self.evict_expired();
         if self.payload_ready() {
             self.make_tr_block(to_send);
         }
@@ -11,10 +96,12 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
         if self.id == self.lead(self.view_i)
             && self.leader_ready()
             && self.phase_i.get(&self.view_i).unwrap_or(&Phase::High) == &Phase::High
-            && self.index.tips.len() > 1
+            && self.index.tips.len() >= self.governed_params().leader_min_tips
         {
             self.make_leader_block(to_send);
         }
+
+        self.assert_invariants(InvariantLevel::Paranoid);
     }
 
     fn payload_ready(&self) -> bool {
@@ -79,26 +166,29 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
             height,
             author: Some(self.id.clone()),
             slot,
-            hash: Some(BlockHash(self.id.0 as u64 * 0x100 + self.slot_i_tr.0)),
+            hash: None,
         };
 
         let block = Block {
-            key: block_key.clone(),
+            key: block_key,
             prev: prev_qcs,
             one: max_1qc.clone(),
-            data: BlockData::Tr {
-                transactions: std::mem::take(&mut self.ready_transactions),
-            },
-        };
+            data: BlockData::tr(std::mem::take(&mut self.ready_transactions)),
+        }
+        .hashed();
 
         crate::tracing_setup::block_created(&self.id, "transaction", &block.key);
+        self.emit(ProtocolEvent::BlockCreated {
+            key: block.key.clone(),
+        });
 
-        let signed_block = Arc::new(Signed::from_data(block, &self.kb));
-
+        // the slot is used up even if signing fails: the signer may have signed anyway
         self.slot_i_tr = SlotNum(self.slot_i_tr.0 + 1);
         self.index.latest_tr_qc = None;
 
-        self.send_msg(to_send, (Message::Block(signed_block.clone()), None));
+        if let Some(signed_block) = self.sign(block) {
+            self.send_msg(to_send, (Message::Block(Arc::new(signed_block)), None));
+        }
     }
 
     fn leader_ready(&self) -> bool {
@@ -142,7 +232,14 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
         let slot = self.slot_i_lead;
         let view = self.view_i;
 
-        let mut prev_qcs: Vec<FinishedQC> = self.index.tips.clone();
+        let mut prev_qcs = self.leader_policy.select_tips(
+            self.index.tips.clone(),
+            &TipContext {
+                view,
+                n: self.n,
+                seen_at: &self.tips_seen_at,
+            },
+        );
 
         if !slot.is_zero() {
             if let Some(prev_qc) = self
@@ -211,22 +308,30 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
             height,
             author: Some(self.id.clone()),
             slot,
-            hash: Some(BlockHash(self.slot_i_lead.0)),
+            hash: None,
         };
 
+        let extension = self.produce_extension(&block_key);
         let block = Block {
-            key: block_key.clone(),
+            key: block_key,
             prev: prev_qcs,
             one: one_qc,
-            data: BlockData::Lead { justification },
-        };
+            data: BlockData::Lead {
+                justification,
+                extension,
+            },
+        }
+        .hashed();
 
         crate::tracing_setup::block_created(&self.id, "leader", &block.key);
-
-        let signed_block = Arc::new(Signed::from_data(block, &self.kb));
-
-        self.send_msg(to_send, (Message::Block(signed_block), None));
+        self.emit(ProtocolEvent::BlockCreated {
+            key: block.key.clone(),
+        });
 
         self.slot_i_lead = SlotNum(self.slot_i_lead.0 + 1);
+
+        if let Some(signed_block) = self.sign(block) {
+            self.send_msg(to_send, (Message::Block(Arc::new(signed_block)), None));
+        }
     }
 }
@@ -1,6 +1,61 @@
+use crate::format::{format_block_hash, format_merkle_root};
 use crate::*;
+use ark_serialize::CanonicalSerialize;
+use serde::{Deserialize, Serialize};
 use std::{fmt, sync::Arc};
 
+/// Limits on what a leader block may carry, bounding both block size and how
+/// much a leader can censor
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeaderBudget {
+    /// Most StartView messages a justification may contain
+    pub max_justification: usize,
+
+    /// Most QCs a leader block may point to
+    pub max_prev: usize,
+
+    /// Largest compressed serialized size of the signed block, in bytes
+    pub max_size: usize,
+
+    /// A leader block must observe every transaction-block tip we have held
+    /// for at least this many Δ, since by then the leader provably had it too.
+    /// `None` disables the check, and is the default.
+    ///
+    /// Whether a block passes depends on when this process saw each tip, so
+    /// processes can disagree on it: an author that sends its transaction
+    /// block's QC to validators ahead of the leader gets correct leader blocks
+    /// rejected at some of them. Only opt in where every process is trusted
+    /// to relay promptly.
+    pub known_tip_delays: Option<u128>,
+}
+
+impl LeaderBudget {
+    /// Generous defaults for a committee of `n` processes: one StartView per
+    /// process, and for prev pointers one transaction-block chain per process,
+    /// doubled to leave room for forks caused by equivocation, plus the
+    /// leader's own chain. The known tip check stays off.
+    pub fn for_committee(n: u32) -> Self {
+        LeaderBudget {
+            max_justification: n as usize,
+            max_prev: 2 * n as usize + 1,
+            max_size: 4 << 20,
+            known_tip_delays: None,
+        }
+    }
+}
+
+/// No limits, as processes enforced before budgets existed
+impl Default for LeaderBudget {
+    fn default() -> Self {
+        LeaderBudget {
+            max_justification: usize::MAX,
+            max_prev: usize::MAX,
+            max_size: usize::MAX,
+            known_tip_delays: None,
+        }
+    }
+}
+
 /// Represents the different ways a block validation can fail
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum BlockValidationError {
@@ -19,6 +74,11 @@ pub enum BlockValidationError {
 
     // Block structure validation
     EmptyPrevPointers,
+    /// The key's hash isn't the hash of the block's contents
+    ContentHashMismatch {
+        key: BlockKey,
+        expected: BlockHash,
+    },
 
     // QC validation
     PrevQcViewGreaterThanBlockView {
@@ -26,8 +86,8 @@ pub enum BlockValidationError {
         block_view: ViewNum,
     },
     PrevQcHeightGreaterOrEqualBlockHeight {
-        prev_height: usize,
-        block_height: usize,
+        prev_height: u64,
+        block_height: u64,
     },
 
     // One-QC validation
@@ -35,14 +95,14 @@ pub enum BlockValidationError {
         z: u8,
     },
     OneQcHeightGreaterOrEqualBlockHeight {
-        qc_height: usize,
-        block_height: usize,
+        qc_height: u64,
+        block_height: u64,
     },
 
     // Height consistency
     InvalidHeight {
-        block_height: usize,
-        max_prev_height: usize,
+        block_height: u64,
+        max_prev_height: u64,
     },
 
     // Block type-specific validation
@@ -56,6 +116,18 @@ pub enum BlockValidationError {
         slot: SlotNum,
     },
     EmptyTransactions,
+    TxRootMismatch {
+        expected: MerkleRoot,
+    },
+    ExpiredTransaction {
+        index: usize,
+        expired_after: ViewNum,
+        block_view: ViewNum,
+    },
+    InvalidConfigChange {
+        index: usize,
+        error: GovernanceError,
+    },
 
     // Leader block validation
     NotLeader {
@@ -78,6 +150,33 @@ pub enum BlockValidationError {
     InvalidPrevQcSignature,
     InvalidOneQcSignature,
     InvalidGenesisOneQc,
+
+    // Leader block budget
+    TooManyJustifications {
+        count: usize,
+        max: usize,
+    },
+    TooManyPrevPointers {
+        count: usize,
+        max: usize,
+    },
+    BlockTooLarge {
+        size: usize,
+        max: usize,
+    },
+    OmitsKnownTip {
+        tip: VoteData,
+    },
+
+    // Leader block extension
+    ExtensionTooLarge {
+        size: usize,
+        max: usize,
+    },
+    InvalidExtension {
+        version: u16,
+        reason: String,
+    },
 }
 
 impl fmt::Display for BlockValidationError {
@@ -93,6 +192,13 @@ impl fmt::Display for BlockValidationError {
 
             Self::EmptyPrevPointers => write!(f, "Block has empty prev pointers"),
 
+            Self::ContentHashMismatch { key, expected } => write!(
+                f,
+                "Block key {:?} doesn't carry its content hash {}",
+                key,
+                format_block_hash(expected)
+            ),
+
             Self::PrevQcViewGreaterThanBlockView {
                 prev_view,
                 block_view,
@@ -148,6 +254,30 @@ impl fmt::Display for BlockValidationError {
 
             Self::EmptyTransactions => write!(f, "Transaction block has no transactions"),
 
+            Self::TxRootMismatch { expected } => write!(
+                f,
+                "Transaction block doesn't carry the Merkle root of its transactions {}",
+                format_merkle_root(expected)
+            ),
+
+            Self::ExpiredTransaction {
+                index,
+                expired_after,
+                block_view,
+            } => write!(
+                f,
+                "Transaction {} expired after view {} but is included in view {}",
+                index, expired_after.0, block_view.0
+            ),
+
+            Self::InvalidConfigChange { index, error } => {
+                write!(
+                    f,
+                    "Transaction {} is an invalid config change: {}",
+                    index, error
+                )
+            }
+
             Self::NotLeader { leader, view } => write!(
                 f,
                 "Block author {} is not the leader for view {}",
@@ -183,6 +313,42 @@ impl fmt::Display for BlockValidationError {
             Self::InvalidPrevQcSignature => write!(f, "Prev QC has invalid signature"),
             Self::InvalidOneQcSignature => write!(f, "One-QC has invalid signature"),
             Self::InvalidGenesisOneQc => write!(f, "One-QC referring to genesis block is invalid"),
+
+            Self::TooManyJustifications { count, max } => write!(
+                f,
+                "Leader block justification has {} entries, more than the budget of {}",
+                count, max
+            ),
+
+            Self::TooManyPrevPointers { count, max } => write!(
+                f,
+                "Leader block points to {} QCs, more than the budget of {}",
+                count, max
+            ),
+
+            Self::BlockTooLarge { size, max } => write!(
+                f,
+                "Leader block is {} bytes, more than the budget of {}",
+                size, max
+            ),
+
+            Self::OmitsKnownTip { tip } => write!(
+                f,
+                "Leader block does not observe tip {:?}, which the leader must have known",
+                tip
+            ),
+
+            Self::ExtensionTooLarge { size, max } => write!(
+                f,
+                "Leader block extension is {} bytes, more than the limit of {}",
+                size, max
+            ),
+
+            Self::InvalidExtension { version, reason } => write!(
+                f,
+                "Leader block extension version {} is invalid: {}",
+                version, reason
+            ),
         }
     }
 }
@@ -220,7 +386,15 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
             }
         };
 
-        if !signed_block.valid_signature(&self.kb) {
+        let expected = block.content_hash();
+        if block.key.hash.as_ref() != Some(&expected) {
+            return Err(BlockValidationError::ContentHashMismatch {
+                key: block.key.clone(),
+                expected,
+            });
+        }
+
+        if !signed_block.valid_signature_at(self.verifier(), block.key.view) {
             return Err(BlockValidationError::InvalidSignature);
         }
 
@@ -243,7 +417,7 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
                     },
                 );
             }
-            if prev != &self.genesis_qc && !prev.valid_signature(&self.kb, self.n - self.f) {
+            if prev != &self.genesis_qc && !prev.valid_signature(self.verifier(), self.n - self.f) {
                 return Err(BlockValidationError::InvalidPrevQcSignature);
             }
         }
@@ -262,7 +436,7 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
         }
 
         if block.one.data.for_which.type_ != BlockType::Genesis {
-            if !block.one.valid_signature(&self.kb, self.n - self.f) {
+            if !block.one.valid_signature(self.verifier(), self.n - self.f) {
                 return Err(BlockValidationError::InvalidOneQcSignature);
             }
         } else {
@@ -285,7 +459,7 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
 
         match &block.data {
             BlockData::Genesis => unreachable!("genesis blocks are validated above"),
-            BlockData::Tr { transactions } => {
+            BlockData::Tr { transactions, root } => {
                 if block.key.type_ != BlockType::Tr {
                     return Err(BlockValidationError::BlockDataTypeMismatch {
                         key_type: block.key.type_,
@@ -306,8 +480,34 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
                 if transactions.is_empty() {
                     return Err(BlockValidationError::EmptyTransactions);
                 }
+                let expected = merkle_root(transactions);
+                if *root != expected {
+                    return Err(BlockValidationError::TxRootMismatch { expected });
+                }
+                if let Some((index, tx)) = transactions
+                    .iter()
+                    .enumerate()
+                    .find(|(_, tx)| tx.expired_at(block.key.view))
+                {
+                    return Err(BlockValidationError::ExpiredTransaction {
+                        index,
+                        expired_after: tx.expires_after().unwrap(),
+                        block_view: block.key.view,
+                    });
+                }
+                for (index, tx) in transactions.iter().enumerate() {
+                    if let Some(Err(error)) = tx
+                        .config_change()
+                        .map(|change| change.validate(block.key.view))
+                    {
+                        return Err(BlockValidationError::InvalidConfigChange { index, error });
+                    }
+                }
             }
-            BlockData::Lead { justification } => {
+            BlockData::Lead {
+                justification,
+                extension,
+            } => {
                 if block.key.type_ != BlockType::Lead {
                     return Err(BlockValidationError::BlockDataTypeMismatch {
                         key_type: block.key.type_,
@@ -323,6 +523,12 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
                     });
                 }
 
+                self.leader_block_within_budget(signed_block)?;
+
+                if let Some(extension) = extension {
+                    self.extension_valid(&block.key, extension)?;
+                }
+
                 let prev_leader_for: Vec<&Arc<ThreshSigned<VoteData>>> = block
                     .prev
                     .iter()
@@ -363,7 +569,10 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
                         });
                     }
 
-                    if !just.iter().all(|j| j.valid_signature(&self.kb)) {
+                    if !just
+                        .iter()
+                        .all(|j| j.valid_signature_at(self.verifier(), j.data.view))
+                    {
                         return Err(BlockValidationError::InvalidJustificationSignature);
                     }
 
@@ -378,4 +587,70 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
 
         Ok(())
     }
+
+    /// Checks a leader block against `self.leader_budget` and the governed size cap
+    fn leader_block_within_budget(
+        &self,
+        signed_block: &Signed<Block<Tr>>,
+    ) -> Result<(), BlockValidationError> {
+        let block = &signed_block.data;
+        let budget = &self.leader_budget;
+
+        if let BlockData::Lead { justification, .. } = &block.data {
+            if justification.len() > budget.max_justification {
+                return Err(BlockValidationError::TooManyJustifications {
+                    count: justification.len(),
+                    max: budget.max_justification,
+                });
+            }
+        }
+
+        if block.prev.len() > budget.max_prev {
+            return Err(BlockValidationError::TooManyPrevPointers {
+                count: block.prev.len(),
+                max: budget.max_prev,
+            });
+        }
+
+        let size = signed_block.compressed_size();
+        let max_size = self.max_block_size_at(block.key.view);
+        if size > max_size {
+            return Err(BlockValidationError::BlockTooLarge {
+                size,
+                max: max_size,
+            });
+        }
+
+        // Only blocks for our current view: a tip that arrived after a late
+        // block was produced says nothing about what its leader knew.
+        if let Some(delays) = budget.known_tip_delays {
+            if block.key.view == self.view_i {
+                let deadline = delays * self.current_delta();
+                for tip in &self.index.tips {
+                    if tip.data.for_which.type_ != BlockType::Tr
+                        || tip.data.for_which.view > block.key.view
+                    {
+                        continue;
+                    }
+                    let Some(seen_at) = self.tips_seen_at.get(&tip.data) else {
+                        continue;
+                    };
+                    if self.current_time.saturating_sub(*seen_at) < deadline {
+                        continue;
+                    }
+                    if !block
+                        .prev
+                        .iter()
+                        .any(|prev| self.observes(prev.data.clone(), &tip.data))
+                    {
+                        return Err(BlockValidationError::OmitsKnownTip {
+                            tip: tip.data.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
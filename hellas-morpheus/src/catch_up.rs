@@ -0,0 +1,133 @@
+//! Catching up on finalized history after being offline
+//!
+//! A process that was down misses the blocks and QCs broadcast meanwhile, and
+//! nobody sends them again. Every `StartView` carries the sender's
+//! `ProgressReport`, which advertises the height of its finalized head, so a
+//! process coming back learns from the first `StartView`s it receives which
+//! peers are ahead and by how much. [`MorpheusProcess::sync_request`] then
+//! picks the peer furthest ahead and asks it for the next range of finalized
+//! heights; the peer answers with [`MorpheusProcess::serve_sync`], and
+//! [`MorpheusProcess::ingest_sync`] feeds the blocks and QCs through
+//! `process_message` in height order, so state tracking records them exactly
+//! as if they had arrived live and every signature is checked as usual.
+//!
+//! Requests and responses are a subprotocol of their own rather than
+//! `Message`s: they go to one peer, carry no signature of their own, and
+//! don't take part in consensus.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+/// Most heights one request asks for, and one response answers
+pub const MAX_SYNC_HEIGHTS: u64 = 256;
+
+/// A request for the finalized blocks at heights `from..=to`, and their QCs
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncRequest {
+    pub from: u64,
+    pub to: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncResponse<Tr: Transaction> {
+    pub blocks: Vec<Arc<Signed<Block<Tr>>>>,
+    pub qcs: Vec<FinishedQC>,
+}
+
+impl<Tr: Transaction> MorpheusProcess<Tr> {
+    /// What to ask of which peer next, if any peer has advertised a taller
+    /// finalized head than ours
+    pub fn sync_request(&self) -> Option<(Identity, SyncRequest)> {
+        let ours = self.finalized_head().height;
+        let (peer, theirs) = self
+            .peer_progress
+            .iter()
+            .map(|(peer, progress)| (peer, progress.finalized_head.height))
+            .filter(|(peer, _)| **peer != self.id)
+            .max_by_key(|(_, height)| *height)?;
+        if theirs <= ours {
+            return None;
+        }
+        let request = SyncRequest {
+            from: ours + 1,
+            to: theirs.min(ours + MAX_SYNC_HEIGHTS),
+        };
+        Some((peer.clone(), request))
+    }
+
+    /// The finalized blocks `request` asks for that we hold, with every QC
+    /// we have for them, at most [`MAX_SYNC_HEIGHTS`] heights' worth
+    pub fn serve_sync(&self, request: &SyncRequest) -> SyncResponse<Tr> {
+        let to = request
+            .to
+            .min(request.from.saturating_add(MAX_SYNC_HEIGHTS - 1));
+        let keys = self
+            .finalized_blocks()
+            .into_iter()
+            .filter(|key| key.type_ != BlockType::Genesis)
+            .filter(|key| (request.from..=to).contains(&key.height))
+            .collect::<BTreeSet<_>>();
+        let mut blocks = keys
+            .iter()
+            .filter_map(|key| self.index.blocks.get(key).cloned())
+            .collect::<Vec<_>>();
+        blocks.sort_by_key(|block| (block.data.key.height, block.data.key.clone()));
+        let qcs = self
+            .qcs
+            .iter()
+            .filter(|qc| keys.contains(&qc.data.for_which))
+            .cloned()
+            .collect();
+        SyncResponse { blocks, qcs }
+    }
+
+    /// Handles a response from `sender`, lowest height first and each block
+    /// before its QCs
+    ///
+    /// Returns how many of its blocks and QCs were accepted; anything
+    /// already known or failing validation is skipped.
+    pub fn ingest_sync(
+        &mut self,
+        sender: &Identity,
+        response: SyncResponse<Tr>,
+        to_send: &mut Vec<(Message<Tr>, Option<Identity>)>,
+    ) -> usize {
+        let mut by_height: BTreeMap<u64, (Vec<Message<Tr>>, Vec<Message<Tr>>)> = BTreeMap::new();
+        for block in response.blocks {
+            by_height
+                .entry(block.data.key.height)
+                .or_default()
+                .0
+                .push(Message::Block(block));
+        }
+        for qc in response.qcs {
+            by_height
+                .entry(qc.data.for_which.height)
+                .or_default()
+                .1
+                .push(Message::QC(qc));
+        }
+
+        let mut accepted = 0;
+        for (blocks, qcs) in by_height.into_values() {
+            for message in blocks.into_iter().chain(qcs) {
+                let known = match &message {
+                    Message::Block(block) => self.index.blocks.contains_key(&block.data.key),
+                    Message::QC(qc) => self.qcs.contains(qc),
+                    _ => false,
+                };
+                if known {
+                    continue;
+                }
+                if self.process_message(message, sender.clone(), to_send) {
+                    accepted += 1;
+                }
+            }
+        }
+        accepted
+    }
+}
@@ -0,0 +1,106 @@
+//! Running a simulation against tokio's clock instead of the simulated one
+//!
+//! `MockHarness` moves time forward by exactly `time_step` per step, so every
+//! timeout fires on the step the protocol says it should. A node reads a real
+//! clock instead, through the same `set_now`. [`run_realtime`] drives a
+//! harness step by step but sets every process's clock from a tokio
+//! `Instant`, with each `time_step` of protocol time taking `step_duration`
+//! of real time, which scales Δ down to whatever a test can afford to wait.
+//! [`check_parity`] runs a scenario both ways and compares what each process
+//! finalized, in order.
+//!
+//! Under a paused tokio clock (`tokio::time::pause`, or `start_paused` in
+//! tests) real time advances exactly as requested, so the two runs must agree
+//! step for step: any difference is logic that reads a clock the harness
+//! doesn't model. Under a live clock the readings run late and unevenly, and
+//! a difference points at a timeout that depends on steps landing exactly on
+//! their deadlines.
+//!
+//! Only built with the `realtime` feature.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use tokio::time::{Instant, sleep_until};
+
+use crate::test_harness::MockHarness;
+use crate::*;
+
+/// The blocks each process regards as final, in the order of
+/// `MockHarness::finalized_prefix`
+pub type Decisions = BTreeMap<Identity, Vec<BlockKey>>;
+
+pub fn decisions(harness: &MockHarness) -> Decisions {
+    harness
+        .processes
+        .keys()
+        .map(|id| (id.clone(), harness.finalized_prefix(id)))
+        .collect()
+}
+
+/// Runs `steps` steps of `harness`, each taking `step_duration` of tokio time,
+/// with process clocks read from tokio after each one
+pub async fn run_realtime(harness: &mut MockHarness, steps: usize, step_duration: Duration) {
+    let start = Instant::now();
+    let origin = harness.time;
+    let nanos_per_step = step_duration.as_nanos().max(1);
+    for step in 1..=steps {
+        harness.step();
+        sleep_until(start + step_duration * step as u32).await;
+        let elapsed = start.elapsed().as_nanos();
+        harness.set_time(origin + elapsed * harness.time_step / nanos_per_step);
+    }
+}
+
+/// What the same scenario decided under each clock
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParityReport {
+    pub simulated: Decisions,
+    pub realtime: Decisions,
+}
+
+impl ParityReport {
+    /// The first process, in identity order, whose two decision sequences
+    /// differ, with the first position where they do
+    ///
+    /// A sequence that stops short of the other differs where it stops.
+    pub fn first_mismatch(&self) -> Option<(Identity, usize)> {
+        let empty = Vec::new();
+        self.simulated
+            .keys()
+            .chain(self.realtime.keys())
+            .find_map(|id| {
+                let simulated = self.simulated.get(id).unwrap_or(&empty);
+                let realtime = self.realtime.get(id).unwrap_or(&empty);
+                let common = simulated
+                    .iter()
+                    .zip(realtime)
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                (common < simulated.len().max(realtime.len())).then(|| (id.clone(), common))
+            })
+    }
+
+    pub fn is_consistent(&self) -> bool {
+        self.first_mismatch().is_none()
+    }
+}
+
+/// Runs the scenario `build` sets up for `steps` steps under the simulated
+/// clock, then again under tokio's, see [`run_realtime`]
+pub async fn check_parity(
+    build: impl Fn() -> MockHarness,
+    steps: usize,
+    step_duration: Duration,
+) -> ParityReport {
+    let mut simulated = build();
+    simulated.run(steps);
+
+    let mut realtime = build();
+    run_realtime(&mut realtime, steps, step_duration).await;
+
+    ParityReport {
+        simulated: decisions(&simulated),
+        realtime: decisions(&realtime),
+    }
+}
@@ -0,0 +1,103 @@
+//! Optional zstd compression of block messages on the wire
+//!
+//! Transaction blocks are most of what a node sends, and their payloads
+//! are whatever the application put in them, which often compresses well.
+//! Votes, QCs and certificates are mostly signatures and curve points and
+//! don't, so only `Block` and `BlockResponse` are ever compressed.
+//!
+//! A compressed message has [`COMPRESSED`] set in its kind tag and a zstd
+//! frame of the payload in place of the payload:
+//!
+//! ```text
+//! version: u8 | kind: u8 | COMPRESSED | zstd(payload)
+//! ```
+//!
+//! Uncompressed messages encode exactly as before, so [`WIRE_VERSION`]
+//! stays the same, but a node that doesn't know the flag rejects compressed
+//! messages with [`WireError::UnknownKind`]. Compression is therefore only
+//! used towards peers that said they accept it, see [`negotiate`], and only
+//! when it saves bytes. Decompressing is capped at [`MAX_DECOMPRESSED_LEN`],
+//! so a small frame can't make a node allocate without bound.
+//!
+//! zstd is C code and doesn't build for every target, so it sits behind the
+//! `compression` feature. Without it, [`WireCompression`] is still there but
+//! messages are always sent uncompressed, and compressed ones are rejected
+//! with [`WireError::CompressionUnsupported`].
+
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+/// Set in the kind tag of a message whose payload is zstd-compressed
+pub const COMPRESSED: u8 = 0x80;
+
+/// The most a compressed payload may decompress to: well above the largest
+/// block governance allows
+pub const MAX_DECOMPRESSED_LEN: usize = 128 << 20;
+
+/// The zstd level used when none is configured
+pub const DEFAULT_COMPRESSION_LEVEL: i32 = 3;
+
+/// How to compress block messages towards peers that accept it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WireCompression {
+    /// The zstd level, 1 (fastest) to 22 (smallest)
+    pub level: i32,
+}
+
+impl Default for WireCompression {
+    fn default() -> Self {
+        WireCompression {
+            level: DEFAULT_COMPRESSION_LEVEL,
+        }
+    }
+}
+
+impl WireCompression {
+    pub fn new(level: i32) -> Self {
+        WireCompression {
+            level: level.clamp(1, 22),
+        }
+    }
+
+    /// Whether this build can decompress, and so can tell peers it accepts
+    /// compressed messages
+    pub fn available() -> bool {
+        cfg!(feature = "compression")
+    }
+}
+
+/// What to send a peer with: `ours` if we compress at all and the peer
+/// accepts compressed messages, otherwise nothing
+pub fn negotiate(ours: Option<WireCompression>, peer_accepts: bool) -> Option<WireCompression> {
+    ours.filter(|_| peer_accepts && WireCompression::available())
+}
+
+/// Whether messages of `kind` are worth compressing
+pub fn compressible(kind: MessageKind) -> bool {
+    matches!(kind, MessageKind::Block | MessageKind::BlockResponse)
+}
+
+/// `payload` compressed at `level`, if that makes it smaller
+#[cfg(feature = "compression")]
+pub(crate) fn compress(payload: &[u8], level: i32) -> Option<Vec<u8>> {
+    zstd::bulk::compress(payload, level)
+        .ok()
+        .filter(|compressed| compressed.len() < payload.len())
+}
+
+#[cfg(not(feature = "compression"))]
+pub(crate) fn compress(_payload: &[u8], _level: i32) -> Option<Vec<u8>> {
+    None
+}
+
+#[cfg(feature = "compression")]
+pub(crate) fn decompress(frame: &[u8]) -> Result<Vec<u8>, WireError> {
+    zstd::bulk::decompress(frame, MAX_DECOMPRESSED_LEN)
+        .map_err(|e| WireError::Malformed(format!("bad zstd frame: {}", e)))
+}
+
+#[cfg(not(feature = "compression"))]
+pub(crate) fn decompress(_frame: &[u8]) -> Result<Vec<u8>, WireError> {
+    Err(WireError::CompressionUnsupported)
+}
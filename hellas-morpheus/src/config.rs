@@ -0,0 +1,237 @@
+//! Everything needed to build a simulation, as plain data
+//!
+//! [`MockHarness::create_test_setup`] always builds the same cluster: keys from
+//! a fixed seed, `f = (n - 1) / 3`, a step of 100 and no transactions. A
+//! [`SimulationConfig`] describes an arbitrary experiment instead, and can be
+//! handed over as JSON by a frontend that builds simulations from a form.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use ark_std::rand::{SeedableRng, rngs::StdRng};
+use serde::{Deserialize, Serialize};
+
+use crate::presets::UnknownPreset;
+use crate::test_harness::{MessageLoss, MockHarness, TxGenPolicy};
+use crate::topology::UnknownTopology;
+use crate::*;
+
+/// Parameters of the simulated network
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// One of the built-in [`crate::topology::TOPOLOGIES`], or `None` for
+    /// next-round delivery between all processes
+    pub topology: Option<String>,
+    /// Processes that are down from the start
+    pub crashed: BTreeSet<Identity>,
+    /// Processes that equivocate from the start
+    pub equivocators: BTreeSet<Identity>,
+    /// Capacity of each process's ingress dedup cache, or `None` to disable it
+    pub dedup_capacity: Option<usize>,
+    /// Fraction of messages lost on the way, between 0 and 1
+    pub loss_rate: f64,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        NetworkConfig {
+            topology: None,
+            crashed: BTreeSet::new(),
+            equivocators: BTreeSet::new(),
+            dedup_capacity: Some(DEFAULT_DEDUP_CAPACITY),
+            loss_rate: 0.0,
+        }
+    }
+}
+
+/// A simulated cluster and how it is driven
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimulationConfig {
+    /// Seed for the committee's keys (and so for VRF leader draws)
+    pub seed: u64,
+    pub num_processes: u32,
+    /// Number of faults tolerated, or `None` for the largest safe value
+    pub f: Option<u32>,
+    /// Δ, which is also the simulated time that passes each step
+    pub delta: u128,
+    /// Policy for processes missing from `tx_gen_policy`
+    pub default_tx_gen_policy: TxGenPolicy,
+    pub tx_gen_policy: BTreeMap<Identity, TxGenPolicy>,
+    pub network: NetworkConfig,
+    /// One of the built-in [`crate::presets::PRESETS`], loaded before the
+    /// policies above are applied
+    pub preset: Option<String>,
+    /// Run the single process in dev mode, see [`MorpheusProcess::dev`];
+    /// needs `num_processes` to be 1
+    pub dev: bool,
+    /// How processes check the votes they receive
+    pub vote_verification: VoteVerification,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        SimulationConfig {
+            seed: 0,
+            num_processes: 4,
+            f: None,
+            delta: 100,
+            default_tx_gen_policy: TxGenPolicy::Never,
+            tx_gen_policy: BTreeMap::new(),
+            network: NetworkConfig::default(),
+            preset: None,
+            dev: false,
+            vote_verification: VoteVerification::default(),
+        }
+    }
+}
+
+/// Why a [`SimulationConfig`] doesn't describe a cluster that can be built
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SimulationConfigError {
+    Quorum(QuorumConfigError),
+    ZeroDelta,
+    UnknownTopology(UnknownTopology),
+    UnknownPreset(UnknownPreset),
+    /// A policy or fault names a process outside the committee
+    UnknownProcess(Identity),
+    /// Dev mode is for a committee of one
+    DevNeedsOneProcess(u32),
+    /// Vote checking the simulated network can't support
+    TrustModel(TrustModelError),
+    /// A loss rate outside 0 to 1
+    InvalidLossRate,
+}
+
+impl fmt::Display for SimulationConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimulationConfigError::Quorum(e) => write!(f, "{}", e),
+            SimulationConfigError::ZeroDelta => write!(f, "delta must be at least 1"),
+            SimulationConfigError::UnknownTopology(e) => write!(f, "{}", e),
+            SimulationConfigError::UnknownPreset(e) => write!(f, "{}", e),
+            SimulationConfigError::UnknownProcess(id) => {
+                write!(f, "{:?} is not a member of the committee", id)
+            }
+            SimulationConfigError::DevNeedsOneProcess(n) => {
+                write!(f, "dev mode runs a single process, not {}", n)
+            }
+            SimulationConfigError::TrustModel(e) => write!(f, "{}", e),
+            SimulationConfigError::InvalidLossRate => {
+                write!(f, "loss rate must be between 0 and 1")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SimulationConfigError {}
+
+impl SimulationConfig {
+    /// A single process in dev mode, submitting a transaction every step
+    pub fn dev() -> Self {
+        SimulationConfig {
+            num_processes: 1,
+            f: Some(0),
+            default_tx_gen_policy: TxGenPolicy::EveryNSteps { n: 1 },
+            dev: true,
+            ..SimulationConfig::default()
+        }
+    }
+
+    /// The number of faults used when `f` isn't given
+    pub fn effective_f(&self) -> u32 {
+        self.f.unwrap_or(self.num_processes.saturating_sub(1) / 3)
+    }
+
+    /// Build the harness this configuration describes
+    ///
+    /// The same configuration always builds the same cluster, keys included.
+    pub fn build(&self) -> Result<MockHarness, SimulationConfigError> {
+        let n = self.num_processes;
+        let f = self.effective_f();
+        QuorumConfig::uniform(n, f)
+            .check()
+            .map_err(SimulationConfigError::Quorum)?;
+        if self.delta == 0 {
+            return Err(SimulationConfigError::ZeroDelta);
+        }
+        if self.dev && n != 1 {
+            return Err(SimulationConfigError::DevNeedsOneProcess(n));
+        }
+        self.vote_verification
+            .validate(MockHarness::LINK_SECURITY)
+            .map_err(SimulationConfigError::TrustModel)?;
+        if !(0.0..=1.0).contains(&self.network.loss_rate) {
+            return Err(SimulationConfigError::InvalidLossRate);
+        }
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let processes = KeyBook::committee_setup(n as usize, &mut rng)
+            .into_iter()
+            .map(|kb| {
+                let id = kb.me_identity.clone();
+                MorpheusProcess::try_new(kb, id, n, f).map(|mut process| {
+                    process.dev_mode = self.dev;
+                    process.vote_verification = self.vote_verification;
+                    process
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(SimulationConfigError::Quorum)?;
+        let mut harness = MockHarness::new(processes, self.delta);
+
+        let named = self
+            .tx_gen_policy
+            .keys()
+            .chain(&self.network.crashed)
+            .chain(&self.network.equivocators);
+        for id in named {
+            if !harness.processes.contains_key(id) {
+                return Err(SimulationConfigError::UnknownProcess(id.clone()));
+            }
+        }
+
+        if let Some(preset) = &self.preset {
+            harness
+                .load_preset(preset)
+                .map_err(SimulationConfigError::UnknownPreset)?;
+        } else {
+            let ids = harness.processes.keys().cloned().collect::<Vec<_>>();
+            for id in ids {
+                harness
+                    .tx_gen_policy
+                    .insert(id, unshared(&self.default_tx_gen_policy));
+            }
+        }
+        for (id, policy) in &self.tx_gen_policy {
+            harness.tx_gen_policy.insert(id.clone(), unshared(policy));
+        }
+
+        if let Some(topology) = &self.network.topology {
+            harness
+                .load_topology(topology)
+                .map_err(SimulationConfigError::UnknownTopology)?;
+        }
+        harness.faults.crashed = self.network.crashed.clone();
+        harness.faults.equivocators = self.network.equivocators.clone();
+        if self.network.loss_rate > 0.0 {
+            harness.faults.loss = Some(MessageLoss::new(self.network.loss_rate, self.seed));
+        }
+        harness.set_dedup(self.network.dedup_capacity);
+
+        Ok(harness)
+    }
+}
+
+/// A copy of `policy` that doesn't share its view tracking with the original,
+/// so each process sees its own view changes
+fn unshared(policy: &TxGenPolicy) -> TxGenPolicy {
+    match policy {
+        TxGenPolicy::OncePerView { prev_view } => TxGenPolicy::OncePerView {
+            prev_view: Arc::new(RwLock::new(*prev_view.read().unwrap())),
+        },
+        other => other.clone(),
+    }
+}
@@ -0,0 +1,185 @@
+//! Consistency pass over restored process state
+//!
+//! A node restarting from stored state runs [`MorpheusProcess::startup_check`]
+//! before handing the process any input. Problems that can be fixed without
+//! trusting anything beyond signatures are repaired in place; anything else
+//! is reported and the node must not start.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::format::format_block_key;
+use crate::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConsistencyIssue {
+    /// A stored block whose key, content hash or signature doesn't check out
+    CorruptBlock {
+        key: BlockKey,
+    },
+    /// A QC referenced from the index but missing from `qcs`
+    OrphanedQc {
+        qc: VoteData,
+    },
+    /// An orphaned QC whose signature doesn't verify, so it can't be restored
+    InvalidQc {
+        qc: VoteData,
+    },
+    /// A block that isn't final points to a block we don't have
+    MissingParent {
+        block: BlockKey,
+        parent: BlockKey,
+    },
+    /// A block in our finalized history that we don't have
+    MissingFinalizedBlock {
+        key: BlockKey,
+    },
+    /// A block marked final without a stored 2-QC for it
+    UnjustifiedFinalization {
+        key: BlockKey,
+    },
+    Invariant(InvariantViolation),
+}
+
+impl fmt::Display for ConsistencyIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CorruptBlock { key } => {
+                write!(f, "Stored block {} is corrupt", format_block_key(key))
+            }
+            Self::OrphanedQc { qc } => write!(f, "QC {:?} is indexed but not in qcs", qc),
+            Self::InvalidQc { qc } => write!(f, "Indexed QC {:?} has an invalid signature", qc),
+            Self::MissingParent { block, parent } => write!(
+                f,
+                "Block {} points to missing block {}",
+                format_block_key(block),
+                format_block_key(parent)
+            ),
+            Self::MissingFinalizedBlock { key } => write!(
+                f,
+                "Finalized history includes block {}, which is not stored",
+                format_block_key(key)
+            ),
+            Self::UnjustifiedFinalization { key } => write!(
+                f,
+                "Block {} is marked final but no 2-QC for it is stored",
+                format_block_key(key)
+            ),
+            Self::Invariant(violation) => write!(f, "{}", violation),
+        }
+    }
+}
+
+/// What [`MorpheusProcess::startup_check`] found
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConsistencyReport {
+    /// Fixed in place
+    pub repaired: Vec<ConsistencyIssue>,
+    /// Harmless: the protocol copes with these at runtime
+    pub tolerated: Vec<ConsistencyIssue>,
+    /// The process must not be started
+    pub fatal: Vec<ConsistencyIssue>,
+}
+
+impl ConsistencyReport {
+    pub fn is_ok(&self) -> bool {
+        self.fatal.is_empty()
+    }
+}
+
+impl<Tr: Transaction> MorpheusProcess<Tr> {
+    /// Checks state restored from storage, repairing what is provably safe
+    ///
+    /// The only repair is putting indexed QCs with valid signatures back into
+    /// `qcs`. Blocks that aren't final may be missing parents, as they can be
+    /// after out-of-order delivery. Anything else, including any invariant
+    /// violation left after repair, is fatal and returned as `Err`.
+    pub fn startup_check(&mut self) -> Result<ConsistencyReport, ConsistencyReport> {
+        let mut report = ConsistencyReport::default();
+
+        for (key, block) in &self.index.blocks {
+            if &block.data.key != key
+                || (key.type_ != BlockType::Genesis
+                    && (key.hash != Some(block.data.content_hash())
+                        || !block.valid_signature_at(self.verifier(), key.view)))
+            {
+                report
+                    .fatal
+                    .push(ConsistencyIssue::CorruptBlock { key: key.clone() });
+            }
+        }
+
+        let indexed = self
+            .index
+            .tips
+            .iter()
+            .chain(self.index.unfinalized.values().flatten())
+            .chain(&self.index.unfinalized_2qc)
+            .chain([&self.index.max_1qc, &self.index.max_view.1])
+            .chain(&self.index.latest_leader_1qc)
+            .chain(&self.index.latest_leader_qc)
+            .chain(&self.index.latest_tr_qc)
+            .filter(|qc| !self.qcs.contains(*qc))
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        for qc in indexed {
+            if qc == self.genesis_qc || qc.valid_signature(self.verifier(), self.n - self.f) {
+                report.repaired.push(ConsistencyIssue::OrphanedQc {
+                    qc: qc.data.clone(),
+                });
+                self.qcs.insert(qc);
+            } else {
+                report.fatal.push(ConsistencyIssue::InvalidQc {
+                    qc: qc.data.clone(),
+                });
+            }
+        }
+
+        let finalized = self.finalized_blocks();
+        for key in &finalized {
+            if !self.index.blocks.contains_key(key) {
+                report
+                    .fatal
+                    .push(ConsistencyIssue::MissingFinalizedBlock { key: key.clone() });
+            }
+        }
+        for (key, block) in &self.index.blocks {
+            if finalized.contains(key) {
+                continue;
+            }
+            for prev in &block.data.prev {
+                if !self.index.blocks.contains_key(&prev.data.for_which) {
+                    report.tolerated.push(ConsistencyIssue::MissingParent {
+                        block: key.clone(),
+                        parent: prev.data.for_which.clone(),
+                    });
+                }
+            }
+        }
+
+        for key in &self.index.finalized {
+            if key != &GEN_BLOCK_KEY
+                && !self
+                    .qcs
+                    .iter()
+                    .any(|qc| qc.data.z == 2 && &qc.data.for_which == key)
+            {
+                report
+                    .fatal
+                    .push(ConsistencyIssue::UnjustifiedFinalization { key: key.clone() });
+            }
+        }
+
+        report.fatal.extend(
+            self.check_invariants_at(InvariantLevel::Full)
+                .into_iter()
+                .map(ConsistencyIssue::Invariant),
+        );
+
+        if report.is_ok() {
+            Ok(report)
+        } else {
+            Err(report)
+        }
+    }
+}
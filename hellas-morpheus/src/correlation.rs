@@ -0,0 +1,91 @@
+//! Correlation ids that follow one piece of work across processes
+//!
+//! Every message and protocol event about the same block (the block itself,
+//! its votes, its QCs and its finalization), or about the same view change
+//! (end-views, the certificate, start-views), carries the same
+//! [`CorrelationId`]. The id is derived from the content rather than attached
+//! to it, so relays and re-broadcasts keep it without any change to the wire
+//! format, and every process computes the same id for the same message.
+
+use std::fmt;
+
+use ark_serialize::CanonicalSerialize;
+use sha2::{Digest, Sha256};
+
+use crate::*;
+
+/// Domain separator, so correlation ids can't collide with other hashes
+const CORRELATION_DOMAIN: &[u8] = b"morpheus-correlation-v1";
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct CorrelationId(pub u64);
+
+impl CorrelationId {
+    /// The id shared by a block, its votes, QCs and finalization
+    pub fn for_block(key: &BlockKey) -> Self {
+        let mut buf = Vec::new();
+        key.serialize_compressed(&mut buf).unwrap();
+        Self::hash(b"block", &buf)
+    }
+
+    /// The id shared by everything that moves processes into view `to`
+    pub fn for_view_change(to: ViewNum) -> Self {
+        Self::hash(b"view", &to.0.to_le_bytes())
+    }
+
+    /// The id of a key rotation by `author` taking effect in `effective`
+    pub fn for_key_rotation(author: &Identity, effective: ViewNum) -> Self {
+        let mut buf = author.0.to_le_bytes().to_vec();
+        buf.extend_from_slice(&effective.0.to_le_bytes());
+        Self::hash(b"key-rotation", &buf)
+    }
+
+    fn hash(tag: &[u8], data: &[u8]) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(CORRELATION_DOMAIN);
+        hasher.update(tag);
+        hasher.update(data);
+        let digest = hasher.finalize();
+        CorrelationId(u64::from_le_bytes(digest[..8].try_into().unwrap()))
+    }
+}
+
+impl fmt::Display for CorrelationId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl<Tr: Transaction> Message<Tr> {
+    pub fn correlation_id(&self) -> CorrelationId {
+        match self {
+            Message::Block(block) => CorrelationId::for_block(&block.data.key),
+            Message::NewVote(vote) => CorrelationId::for_block(&vote.data.for_which),
+            Message::QC(qc) => CorrelationId::for_block(&qc.data.for_which),
+            Message::EndView(end_view) => CorrelationId::for_view_change(end_view.data.incr()),
+            Message::EndViewCert(cert) => CorrelationId::for_view_change(cert.data.incr()),
+            Message::StartView(start_view) => CorrelationId::for_view_change(start_view.data.view),
+            Message::KeyRotation(rotation) => {
+                CorrelationId::for_key_rotation(&rotation.author, rotation.data.effective)
+            }
+            Message::GetBlock(key) => CorrelationId::for_block(key),
+            Message::BlockResponse(block) => CorrelationId::for_block(&block.data.key),
+        }
+    }
+}
+
+impl ProtocolEvent {
+    /// The id of the messages that led to this event, if it is about a block or view change
+    pub fn correlation_id(&self) -> Option<CorrelationId> {
+        match self {
+            ProtocolEvent::BlockCreated { key } | ProtocolEvent::BlockFinalized { key } => {
+                Some(CorrelationId::for_block(key))
+            }
+            ProtocolEvent::QcFormed { data } => Some(CorrelationId::for_block(&data.for_which)),
+            ProtocolEvent::ViewChanged { to, .. } => Some(CorrelationId::for_view_change(*to)),
+            ProtocolEvent::PhaseChanged { .. }
+            | ProtocolEvent::PeerAhead { .. }
+            | ProtocolEvent::BackfillProgress { .. } => None,
+        }
+    }
+}
@@ -1,7 +1,14 @@
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Valid};
+use ark_std::rand::{CryptoRng, RngCore};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::borrow::Borrow;
 use std::collections::BTreeMap;
 
+use crate::{
+    Block, KeyRotation, MetadataRegistry, SignError, StartView, Transaction, ViewNum, VoteData,
+};
+
 /// A unique identifier for a process
 #[derive(
     PartialEq,
@@ -18,6 +25,52 @@ use std::collections::BTreeMap;
 )]
 pub struct Identity(pub u32);
 
+/// Domain separator for chain ids
+const CHAIN_ID_DOMAIN: &[u8] = b"morpheus-chain-v1";
+
+/// Names the network a committee runs, and goes into everything it signs
+///
+/// A signature made for one chain never verifies on another, so messages
+/// from a testnet can't be replayed on a network with the same members.
+#[derive(
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Debug,
+    Serialize,
+    Deserialize,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+)]
+pub struct ChainId(pub [u8; 32]);
+
+impl ChainId {
+    /// The id of the chain `name` run by the committee holding `keys`
+    pub fn genesis(name: &str, keys: &BTreeMap<Identity, hints::PublicKey>) -> Self {
+        let mut buf = (name.len() as u64).to_le_bytes().to_vec();
+        buf.extend_from_slice(name.as_bytes());
+        for (id, key) in keys {
+            id.serialize_compressed(&mut buf).unwrap();
+            key.serialize_compressed(&mut buf).unwrap();
+        }
+        ChainId(
+            Sha256::new()
+                .chain_update(CHAIN_ID_DOMAIN)
+                .chain_update(&buf)
+                .finalize()
+                .into(),
+        )
+    }
+}
+
+/// Chain name `KeyBook::committee_setup` derives the chain id from
+pub const DEFAULT_CHAIN_NAME: &str = "morpheus";
+
 /// Collects the public keys of all identities.
 #[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 pub struct KeyBook {
@@ -25,8 +78,243 @@ pub struct KeyBook {
     pub identities: BTreeMap<hints::PublicKey, Identity>,
     pub me_identity: Identity,
     pub me_pub_key: hints::PublicKey,
-    pub me_sec_key: hints::SecretKey,
+    /// Left out when serialized, see `secret.rs`
+    pub me_sec_key: SecretKey,
     pub hints_setup: hints::UniverseSetup,
+    /// Human-readable names and locations of the identities above
+    pub metadata: MetadataRegistry,
+    /// Keys announced with a `KeyRotation`, by the view they take effect in;
+    /// before the first of them an identity signs with its key in `keys`
+    #[serde(default)]
+    pub rotations: BTreeMap<Identity, BTreeMap<ViewNum, hints::PublicKey>>,
+    /// Shared by the committee to draw leaders under `LeaderElection::Vrf`
+    #[serde(default)]
+    pub election_key: Option<SecretKey>,
+    /// Checks a `LeaderProof`; may be handed to outsiders
+    #[serde(default)]
+    pub election_public: Option<hints::PublicKey>,
+    /// The chain everything signed with these keys is bound to
+    #[serde(default)]
+    pub chain_id: ChainId,
+}
+
+impl KeyBook {
+    /// Key shares for a committee of `n`, identities `1..=n`, from a trusted
+    /// setup run with `rng`
+    ///
+    /// Every member gets its own BLS12-381 key, and the hinTS universe built
+    /// from all of their hints, so any n-f (or f+1) partial signatures
+    /// aggregate into a `ThreshSigned` that anyone holding `hints_setup` can
+    /// check. The result is indexed by identity minus one, and runs the chain
+    /// [`DEFAULT_CHAIN_NAME`]; see [`Self::on_chain`] for another.
+    pub fn committee_setup(n: usize, rng: &mut (impl RngCore + CryptoRng)) -> Vec<KeyBook> {
+        // the universe needs one slot more than the committee, rounded up
+        let domain_max = (1 + n).next_power_of_two();
+        let gd = hints::GlobalData::new(domain_max, rng).unwrap();
+        let privs: Vec<hints::SecretKey> = (0..domain_max - 1)
+            .map(|_| hints::SecretKey::random(rng))
+            .collect();
+        let pubkeys: Vec<hints::PublicKey> = privs.iter().map(|sk| sk.public(&gd)).collect();
+        let weights = vec![hints::F::from(1); domain_max - 1];
+        let hints = (0..domain_max - 1)
+            .map(|i| hints::generate_hint(&gd, &privs[i], domain_max, i).unwrap())
+            .collect::<Vec<_>>();
+        let setup = hints::setup_universe(&gd, pubkeys.clone(), &hints, weights).unwrap();
+        let election_key = hints::SecretKey::random(rng);
+        let election_public = election_key.public(&gd);
+
+        let keys: BTreeMap<Identity, hints::PublicKey> = (0..n)
+            .map(|i| (Identity(i as u32 + 1), pubkeys[i].clone()))
+            .collect();
+        let identities: BTreeMap<hints::PublicKey, Identity> = (0..n)
+            .map(|i| (pubkeys[i].clone(), Identity(i as u32 + 1)))
+            .collect();
+        let chain_id = ChainId::genesis(DEFAULT_CHAIN_NAME, &keys);
+
+        (0..n)
+            .map(|i| KeyBook {
+                keys: keys.clone(),
+                identities: identities.clone(),
+                me_identity: Identity(i as u32 + 1),
+                me_pub_key: pubkeys[i].clone(),
+                me_sec_key: SecretKey::new(privs[i].clone()),
+                hints_setup: setup.clone(),
+                metadata: MetadataRegistry::default(),
+                rotations: BTreeMap::new(),
+                election_key: Some(SecretKey::new(election_key.clone())),
+                election_public: Some(election_public.clone()),
+                chain_id,
+            })
+            .collect()
+    }
+
+    /// These keys, signing for and accepting only messages of the chain `name`
+    pub fn on_chain(mut self, name: &str) -> Self {
+        self.chain_id = ChainId::genesis(name, &self.keys);
+        self
+    }
+
+    /// The key `id` signs `Signed` messages of `view` with
+    pub fn identity_key(&self, id: &Identity, view: ViewNum) -> Option<&hints::PublicKey> {
+        self.rotations
+            .get(id)
+            .and_then(|rotations| rotations.range(..=view).next_back())
+            .map(|(_, key)| key)
+            .or_else(|| self.keys.get(id))
+    }
+}
+
+/// Produces this process's partial signatures
+///
+/// The default is [`LocalSigner`] over `KeyBook::me_sec_key`; operators
+/// keeping the key in an HSM or a separate process plug in their own (see
+/// `signer.rs`).
+pub trait Signer: Send + Sync {
+    fn sign(&self, message: &[u8]) -> Result<hints::PartialSignature, SignError>;
+}
+
+/// Signs with a key held in memory
+pub struct LocalSigner(pub SecretKey);
+
+impl Signer for LocalSigner {
+    fn sign(&self, message: &[u8]) -> Result<hints::PartialSignature, SignError> {
+        let key = self.0.expose().ok_or(SignError::MissingKey)?;
+        Ok(hints::sign(key, message))
+    }
+}
+
+/// Checks signatures made by members of the committee
+pub trait Verifier: Send + Sync {
+    /// Members are identities `1..=committee_size()`
+    fn committee_size(&self) -> u32;
+
+    /// The chain signatures are checked for, see [`signing_bytes`]
+    fn chain_id(&self) -> ChainId;
+
+    /// Whether `signature` is `author`'s over `message`; false for non-members
+    fn verify_partial(
+        &self,
+        author: &Identity,
+        message: &[u8],
+        signature: &hints::PartialSignature,
+    ) -> bool;
+
+    /// [`Self::verify_partial`] against the key `author` held in `view`, for
+    /// signatures made with a key that can be rotated
+    fn verify_partial_at(
+        &self,
+        author: &Identity,
+        _view: ViewNum,
+        message: &[u8],
+        signature: &hints::PartialSignature,
+    ) -> bool {
+        self.verify_partial(author, message, signature)
+    }
+
+    /// Whether `signature` aggregates at least `threshold` members' signatures over `message`
+    fn verify_aggregate(
+        &self,
+        message: &[u8],
+        signature: &hints::Signature,
+        threshold: u32,
+    ) -> bool;
+}
+
+impl Verifier for KeyBook {
+    fn committee_size(&self) -> u32 {
+        self.keys.len() as u32
+    }
+
+    fn chain_id(&self) -> ChainId {
+        self.chain_id
+    }
+
+    fn verify_partial(
+        &self,
+        author: &Identity,
+        message: &[u8],
+        signature: &hints::PartialSignature,
+    ) -> bool {
+        self.keys.get(author).is_some_and(|key| {
+            hints::verify_partial(&self.hints_setup.global, key, message, signature)
+        })
+    }
+
+    fn verify_partial_at(
+        &self,
+        author: &Identity,
+        view: ViewNum,
+        message: &[u8],
+        signature: &hints::PartialSignature,
+    ) -> bool {
+        self.identity_key(author, view).is_some_and(|key| {
+            hints::verify_partial(&self.hints_setup.global, key, message, signature)
+        })
+    }
+
+    fn verify_aggregate(
+        &self,
+        message: &[u8],
+        signature: &hints::Signature,
+        threshold: u32,
+    ) -> bool {
+        aggregate_valid(&self.hints_setup, message, signature, threshold)
+    }
+}
+
+fn aggregate_valid(
+    setup: &hints::UniverseSetup,
+    message: &[u8],
+    signature: &hints::Signature,
+    threshold: u32,
+) -> bool {
+    hints::verify_aggregate(&setup.verifier(), signature, message).is_ok()
+        && signature.threshold >= hints::F::from(threshold)
+}
+
+/// Data that gets signed, under a domain separation tag of its own
+///
+/// The bytes signed are the tag followed by the canonical encoding, see
+/// [`signing_bytes`], so a signature over one type of payload never
+/// verifies as a signature over another whose encoding happens to coincide:
+/// an end-view for view 7 can't be passed off as something else that
+/// serializes to the same eight bytes.
+pub trait SigningPayload: CanonicalSerialize {
+    /// Unique among all payload types
+    const DOMAIN: &'static [u8];
+}
+
+impl SigningPayload for VoteData {
+    const DOMAIN: &'static [u8] = b"morpheus-sig-vote-v1";
+}
+
+impl<Tr: Transaction> SigningPayload for Block<Tr> {
+    const DOMAIN: &'static [u8] = b"morpheus-sig-block-v1";
+}
+
+/// The only `ViewNum` we sign is the one in our end-view message
+impl SigningPayload for ViewNum {
+    const DOMAIN: &'static [u8] = b"morpheus-sig-end-view-v1";
+}
+
+impl SigningPayload for StartView {
+    const DOMAIN: &'static [u8] = b"morpheus-sig-start-view-v1";
+}
+
+impl SigningPayload for KeyRotation {
+    const DOMAIN: &'static [u8] = b"morpheus-sig-key-rotation-v1";
+}
+
+/// The bytes a signature over `data` on `chain` is made on: the length of
+/// its domain tag, the tag, the chain id, then its compressed canonical
+/// encoding
+pub fn signing_bytes<T: SigningPayload>(chain: &ChainId, data: &T) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(1 + T::DOMAIN.len() + 32 + data.compressed_size());
+    buf.push(T::DOMAIN.len() as u8);
+    buf.extend_from_slice(T::DOMAIN);
+    buf.extend_from_slice(&chain.0);
+    data.serialize_compressed(&mut buf).unwrap();
+    buf
 }
 
 #[derive(
@@ -65,6 +353,73 @@ pub struct Signed<T: Valid + CanonicalSerialize + CanonicalDeserialize> {
 pub struct ThreshSigned<T: Valid + CanonicalSerialize + CanonicalDeserialize> {
     pub data: T,
     pub signature: hints::Signature,
+    /// Who contributed to `signature`, as claimed by whoever aggregated it;
+    /// not covered by the signature, see [`SignerBitmap`]
+    #[serde(default)]
+    pub signers: SignerBitmap,
+}
+
+/// One bit per committee member, set for those whose partial signature went
+/// into an aggregate; `Identity(i)` is bit `i - 1`
+///
+/// The bitmap is unauthenticated. Checking an aggregate proves that enough
+/// members signed, not that the ones named here did: anyone relaying a
+/// certificate can rewrite its bitmap and it still verifies, as long as it
+/// names enough members of the committee. It serves diagnostics and
+/// participation stats, and must never be taken as proof that a member
+/// signed something, e.g. as evidence against it.
+#[derive(
+    Clone,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Debug,
+    Serialize,
+    Deserialize,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+)]
+pub struct SignerBitmap(pub Vec<u8>);
+
+impl SignerBitmap {
+    pub fn from_signers(signers: impl IntoIterator<Item = impl Borrow<Identity>>) -> Self {
+        let mut bits = Vec::new();
+        for id in signers {
+            let i = id.borrow().0 as usize - 1;
+            if bits.len() <= i / 8 {
+                bits.resize(i / 8 + 1, 0);
+            }
+            bits[i / 8] |= 1 << (i % 8);
+        }
+        SignerBitmap(bits)
+    }
+
+    pub fn contains(&self, id: &Identity) -> bool {
+        let Some(i) = (id.0 as usize).checked_sub(1) else {
+            return false;
+        };
+        self.0
+            .get(i / 8)
+            .is_some_and(|byte| byte & (1 << (i % 8)) != 0)
+    }
+
+    pub fn count(&self) -> u32 {
+        self.0.iter().map(|byte| byte.count_ones()).sum()
+    }
+
+    pub fn signers(&self) -> impl Iterator<Item = Identity> + '_ {
+        (0..self.0.len() * 8)
+            .filter(|i| self.0[i / 8] & (1 << (i % 8)) != 0)
+            .map(|i| Identity(i as u32 + 1))
+    }
+
+    /// Whether every set bit belongs to one of `n` members, with no padding beyond them
+    pub fn within(&self, n: u32) -> bool {
+        self.0.len() <= (n as usize).div_ceil(8) && self.signers().all(|id| id.0 <= n)
+    }
 }
 
 #[derive(
@@ -86,21 +441,38 @@ pub struct ThreshPartial<T: Valid + CanonicalSerialize + CanonicalDeserialize> {
     pub signature: hints::PartialSignature,
 }
 
-impl<T: CanonicalSerialize + CanonicalDeserialize> ThreshSigned<T> {
-    pub fn valid_signature(&self, keybook: &KeyBook, threshold: u32) -> bool {
-        let verifier = keybook.hints_setup.verifier();
-        let mut buf = Vec::new();
-        T::serialize_compressed(&self.data, &mut buf).unwrap();
-        hints::verify_aggregate(&verifier, &self.signature, &buf).is_ok()
-            && self.signature.threshold >= hints::F::from(threshold)
+impl<T: SigningPayload + CanonicalDeserialize> ThreshSigned<T> {
+    /// Whether at least `threshold` members signed, and the bitmap names as
+    /// many members of the committee; which ones it names is unchecked, see
+    /// [`SignerBitmap`]
+    pub fn valid_signature(&self, verifier: &(impl Verifier + ?Sized), threshold: u32) -> bool {
+        if self.signers.count() < threshold || !self.signers.within(verifier.committee_size()) {
+            return false;
+        }
+        let buf = signing_bytes(&verifier.chain_id(), &self.data);
+        verifier.verify_aggregate(&buf, &self.signature, threshold)
+    }
+
+    /// Checks the aggregate against the committee's public setup alone, so a
+    /// third party without a `KeyBook` can verify a QC
+    ///
+    /// The aggregate proves that at least `threshold` members signed. The
+    /// bitmap has to name at least that many, but who it names isn't checked
+    /// (see [`SignerBitmap`]).
+    pub fn verify(&self, setup: &hints::UniverseSetup, chain: &ChainId, threshold: u32) -> bool {
+        if self.signers.count() < threshold {
+            return false;
+        }
+        let buf = signing_bytes(chain, &self.data);
+        aggregate_valid(setup, &buf, &self.signature, threshold)
     }
 }
 
-impl<T: CanonicalSerialize + CanonicalDeserialize> ThreshPartial<T> {
+impl<T: SigningPayload + CanonicalDeserialize> ThreshPartial<T> {
+    /// Signs `data` with `kb.me_sec_key`, which must be loaded
     pub fn from_data(data: T, kb: &KeyBook) -> Self {
-        let mut buf = Vec::new();
-        T::serialize_compressed(&data, &mut buf).unwrap();
-        let sig = hints::sign(&kb.me_sec_key, &buf);
+        let buf = signing_bytes(&kb.chain_id, &data);
+        let sig = hints::sign(kb.me_sec_key.expose().expect("no secret key loaded"), &buf);
         Self {
             data,
             author: kb.me_identity.clone(),
@@ -108,27 +480,17 @@ impl<T: CanonicalSerialize + CanonicalDeserialize> ThreshPartial<T> {
         }
     }
 
-    pub fn valid_signature(&self, keybook: &KeyBook) -> bool {
-        let their_key = keybook
-            .keys
-            .get(&self.author)
-            .expect("author not in keybook");
-        let mut buf = Vec::new();
-        T::serialize_compressed(&self.data, &mut buf).unwrap();
-        hints::verify_partial(
-            &keybook.hints_setup.global,
-            &their_key,
-            &buf,
-            &self.signature,
-        )
+    pub fn valid_signature(&self, verifier: &(impl Verifier + ?Sized)) -> bool {
+        let buf = signing_bytes(&verifier.chain_id(), &self.data);
+        verifier.verify_partial(&self.author, &buf, &self.signature)
     }
 }
 
-impl<T: CanonicalSerialize + CanonicalDeserialize> Signed<T> {
+impl<T: SigningPayload + CanonicalDeserialize> Signed<T> {
+    /// Signs `data` with `kb.me_sec_key`, which must be loaded
     pub fn from_data(data: T, kb: &KeyBook) -> Self {
-        let mut buf = Vec::new();
-        T::serialize_compressed(&data, &mut buf).unwrap();
-        let sig = hints::sign(&kb.me_sec_key, &buf);
+        let buf = signing_bytes(&kb.chain_id, &data);
+        let sig = hints::sign(kb.me_sec_key.expose().expect("no secret key loaded"), &buf);
         Self {
             data,
             author: kb.me_identity.clone(),
@@ -136,18 +498,14 @@ impl<T: CanonicalSerialize + CanonicalDeserialize> Signed<T> {
         }
     }
 
-    pub fn valid_signature(&self, keybook: &KeyBook) -> bool {
-        let their_key = keybook
-            .keys
-            .get(&self.author)
-            .expect("author not in keybook");
-        let mut buf = Vec::new();
-        T::serialize_compressed(&self.data, &mut buf).unwrap();
-        hints::verify_partial(
-            &keybook.hints_setup.global,
-            &their_key,
-            &buf,
-            &self.signature,
-        )
+    pub fn valid_signature(&self, verifier: &(impl Verifier + ?Sized)) -> bool {
+        let buf = signing_bytes(&verifier.chain_id(), &self.data);
+        verifier.verify_partial(&self.author, &buf, &self.signature)
+    }
+
+    /// [`Self::valid_signature`] against the key the author held in `view`
+    pub fn valid_signature_at(&self, verifier: &(impl Verifier + ?Sized), view: ViewNum) -> bool {
+        let buf = signing_bytes(&verifier.chain_id(), &self.data);
+        verifier.verify_partial_at(&self.author, view, &buf, &self.signature)
     }
 }
@@ -0,0 +1,84 @@
+//! Shape of the block DAG as one process sees it
+//!
+//! How wide the DAG grows, how many pointers blocks carry and how many blocks
+//! are left behind all depend on network conditions, so these numbers are
+//! what to compare across harness runs or watch on a live node.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+/// Samples of the tip count kept by a process
+pub const TIP_HISTORY: usize = 256;
+
+/// `(time, tip count)` samples, oldest first
+pub type TipHistory = VecDeque<(u128, usize)>;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DagStats {
+    /// Blocks held at each height, genesis included
+    pub width: BTreeMap<u64, usize>,
+    /// Tallest block held
+    pub depth: u64,
+    /// Mean number of prev pointers, per block type
+    pub mean_prev: BTreeMap<BlockType, f64>,
+    /// Blocks that nothing points to yet, and whose QC isn't a current tip
+    pub orphans: usize,
+    /// `(time, tip count)` each time the number of tips changed, oldest first
+    pub tips_over_time: Vec<(u128, usize)>,
+}
+
+impl DagStats {
+    /// Largest number of blocks at any one height
+    pub fn max_width(&self) -> usize {
+        self.width.values().copied().max().unwrap_or(0)
+    }
+}
+
+impl<Tr: Transaction> MorpheusProcess<Tr> {
+    pub fn dag_stats(&self) -> DagStats {
+        let mut stats = DagStats::default();
+        let mut prev_totals: BTreeMap<BlockType, (usize, usize)> = BTreeMap::new();
+
+        for (key, block) in &self.index.blocks {
+            *stats.width.entry(key.height).or_default() += 1;
+            stats.depth = stats.depth.max(key.height);
+            if key.type_ != BlockType::Genesis {
+                let (blocks, pointers) = prev_totals.entry(key.type_).or_default();
+                *blocks += 1;
+                *pointers += block.data.prev.len();
+            }
+
+            let pointed_to = self
+                .index
+                .block_pointed_by
+                .get(key)
+                .is_some_and(|by| !by.is_empty());
+            let is_tip = self.index.tips.iter().any(|tip| &tip.data.for_which == key);
+            if !pointed_to && !is_tip && key.type_ != BlockType::Genesis {
+                stats.orphans += 1;
+            }
+        }
+
+        stats.mean_prev = prev_totals
+            .into_iter()
+            .map(|(type_, (blocks, pointers))| (type_, pointers as f64 / blocks as f64))
+            .collect();
+        stats.tips_over_time = self.tip_history.iter().copied().collect();
+        stats
+    }
+
+    /// Notes the tip count if it changed, forgetting the oldest sample past [`TIP_HISTORY`]
+    pub(crate) fn sample_tips(&mut self) {
+        let count = self.index.tips.len();
+        if self.tip_history.back().map(|&(_, c)| c) == Some(count) {
+            return;
+        }
+        self.tip_history.push_back((self.current_time, count));
+        while self.tip_history.len() > TIP_HISTORY {
+            self.tip_history.pop_front();
+        }
+    }
+}
@@ -0,0 +1,210 @@
+//! Everything worth attaching to a bug report, in one file
+//!
+//! [`MorpheusProcess::debug_bundle`] packs a [`DebugReport`] into a gzipped
+//! tar archive of JSON files, one per section, so a report can be read with
+//! nothing but `tar xzf` and a text editor:
+//!
+//! - `digest.json`: where the process is and how much it holds
+//! - `views.json`: blocks, QCs and phase of the latest views
+//! - `tips.json`: the current tips
+//! - `pending_votes.json`: pending votes and what holds each one up
+//! - `events.json`: the latest protocol events
+//! - `config.json`: the parameters the process runs with
+//!
+//! Nothing secret goes in: keys stay out, and so do the transactions inside
+//! blocks.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::*;
+
+/// How many views, counting back from the current one, `views.json` covers
+pub const DEBUG_VIEWS: usize = 16;
+
+/// Where a process is, and how much state it holds
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateDigest {
+    pub id: Identity,
+    pub view_i: ViewNum,
+    pub phase: Phase,
+    pub slot_i_lead: SlotNum,
+    pub slot_i_tr: SlotNum,
+    pub current_time: u128,
+    pub view_entry_time: u128,
+    pub max_view: ViewNum,
+    pub max_height: u64,
+    pub finalized_head: BlockKey,
+    pub blocks: usize,
+    pub qcs: usize,
+    pub finalized: usize,
+    pub received_messages: u64,
+    pub awaiting_ancestors: usize,
+    pub view_churn: ViewChurn,
+    /// SHA-256 over the keys of every block held, in order, so two processes
+    /// holding the same DAG can be told apart from two that don't at a glance
+    pub blocks_hash: String,
+}
+
+/// What a process saw of one view
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ViewSummary {
+    pub phase: Option<Phase>,
+    /// Blocks held of each type
+    pub blocks: BTreeMap<BlockType, usize>,
+    /// QCs held for blocks of this view, by z
+    pub qcs: BTreeMap<u8, usize>,
+    pub start_views: usize,
+    pub end_view_cert: bool,
+}
+
+/// The parameters a process runs with
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessConfig {
+    pub n: u32,
+    pub f: u32,
+    pub delta: u128,
+    pub chain_id: ChainId,
+    pub vote_aggregation: VoteAggregation,
+    pub leader_election: LeaderElection,
+    pub leader_budget: LeaderBudget,
+    pub invariant_level: InvariantLevel,
+    /// Governed parameters in force in the current view
+    pub governed: GovernedParams,
+    pub crate_version: String,
+}
+
+/// The files of a debug bundle, each named after its field of [`DebugReport`]
+pub const DEBUG_SECTIONS: [&str; 6] = [
+    "digest",
+    "views",
+    "tips",
+    "pending_votes",
+    "events",
+    "config",
+];
+
+/// The sections of a debug bundle, before archiving
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DebugReport {
+    pub digest: StateDigest,
+    #[serde(with = "serde_json_any_key::any_key_map")]
+    pub views: BTreeMap<ViewNum, ViewSummary>,
+    pub tips: Vec<VoteData>,
+    pub pending_votes: Vec<PendingVotesSnapshot>,
+    pub events: Vec<ProtocolEvent>,
+    pub config: ProcessConfig,
+}
+
+impl DebugReport {
+    /// The report as a gzipped tar archive, one JSON file per section
+    pub fn to_archive(&self) -> std::io::Result<Vec<u8>> {
+        let report = serde_json::to_value(self)?;
+        let gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        let mut archive = tar::Builder::new(gz);
+        for section in DEBUG_SECTIONS {
+            let contents = serde_json::to_vec_pretty(&report[section])?;
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            archive.append_data(&mut header, format!("{section}.json"), &contents[..])?;
+        }
+        archive.into_inner()?.finish()
+    }
+}
+
+impl<Tr: Transaction> MorpheusProcess<Tr> {
+    /// A gzipped tar archive of [`Self::debug_report`], for bug reports
+    pub fn debug_bundle(&self) -> Vec<u8> {
+        self.debug_report()
+            .to_archive()
+            .expect("archiving into memory doesn't fail")
+    }
+
+    pub fn debug_report(&self) -> DebugReport {
+        DebugReport {
+            digest: self.state_digest(),
+            views: self.view_summaries(DEBUG_VIEWS),
+            tips: self.index.tips.iter().map(|qc| qc.data.clone()).collect(),
+            pending_votes: self.pending_votes_snapshot(),
+            events: self.recent_events(),
+            config: ProcessConfig {
+                n: self.n,
+                f: self.f,
+                delta: self.delta,
+                chain_id: self.kb.chain_id.clone(),
+                vote_aggregation: self.vote_aggregation,
+                leader_election: self.leader_election,
+                leader_budget: self.leader_budget,
+                invariant_level: self.invariant_level,
+                governed: self.governed_params(),
+                crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            },
+        }
+    }
+
+    pub fn state_digest(&self) -> StateDigest {
+        let mut hasher = Sha256::new();
+        for key in self.index.blocks.keys() {
+            hasher.update(serde_json::to_vec(key).expect("block keys serialize"));
+        }
+        StateDigest {
+            id: self.id.clone(),
+            view_i: self.view_i,
+            phase: self
+                .phase_i
+                .get(&self.view_i)
+                .copied()
+                .unwrap_or(Phase::High),
+            slot_i_lead: self.slot_i_lead,
+            slot_i_tr: self.slot_i_tr,
+            current_time: self.current_time,
+            view_entry_time: self.view_entry_time,
+            max_view: self.index.max_view.0,
+            max_height: self.index.max_height.0,
+            finalized_head: self.finalized_head(),
+            blocks: self.index.blocks.len(),
+            qcs: self.qcs.len(),
+            finalized: self.index.finalized.len(),
+            received_messages: self.received_messages.total(),
+            awaiting_ancestors: self.awaiting_ancestors.len(),
+            view_churn: self.view_churn.clone(),
+            blocks_hash: hasher
+                .finalize()
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect(),
+        }
+    }
+
+    /// What we saw of each of the last `views` views up to the current one
+    pub fn view_summaries(&self, views: usize) -> BTreeMap<ViewNum, ViewSummary> {
+        let first = ViewNum(self.view_i.0.saturating_sub(views as i64 - 1).max(0));
+        let mut summaries = (first.0..=self.view_i.0)
+            .map(|view| {
+                let view = ViewNum(view);
+                let summary = ViewSummary {
+                    phase: self.phase_i.get(&view).copied(),
+                    start_views: self.start_views.get(&view).map_or(0, Vec::len),
+                    end_view_cert: self.end_view_certs.contains_key(&view),
+                    ..ViewSummary::default()
+                };
+                (view, summary)
+            })
+            .collect::<BTreeMap<_, _>>();
+        for key in self.index.blocks.keys() {
+            if let Some(summary) = summaries.get_mut(&key.view) {
+                *summary.blocks.entry(key.type_).or_default() += 1;
+            }
+        }
+        for qc in &self.qcs {
+            if let Some(summary) = summaries.get_mut(&qc.data.for_which.view) {
+                *summary.qcs.entry(qc.data.z).or_default() += 1;
+            }
+        }
+        summaries
+    }
+}
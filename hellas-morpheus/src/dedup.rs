@@ -0,0 +1,171 @@
+//! Dropping repeated messages before they reach validation
+//!
+//! A process receives the same block or QC several times over: broadcasts
+//! are relayed, QCs are formed and sent by every process that collects the
+//! votes, and complaints resend what a peer may already have. Each copy
+//! would otherwise be validated again, signature checks and all, only to be
+//! recognised as a duplicate afterwards. A [`DedupCache`] remembers the
+//! content hashes of the most recent messages a driver handed to a process,
+//! forgetting the least recently seen first, so the driver can drop repeats
+//! at ingress. [`DedupStats`] counts how often that paid off.
+//!
+//! A message evicted from the cache and received again is handed to the
+//! process, which still recognises it as a duplicate; the cache only saves
+//! work and is never needed for correctness.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use ark_serialize::CanonicalSerialize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::*;
+
+/// Domain separator, so message hashes can't collide with other hashes
+const DEDUP_DOMAIN: &[u8] = b"morpheus-dedup-v1";
+
+/// Messages remembered per process unless configured otherwise
+pub const DEFAULT_DEDUP_CAPACITY: usize = 4096;
+
+/// SHA-256 of a message's kind and canonical encoding
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MessageHash(pub [u8; 32]);
+
+impl fmt::Debug for MessageHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in &self.0[..8] {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<Tr: Transaction> Message<Tr> {
+    /// The same for any two copies of a message, and different for any two
+    /// messages that differ
+    pub fn content_hash(&self) -> MessageHash {
+        let mut buf = Vec::new();
+        match self {
+            Message::Block(block) => block.serialize_compressed(&mut buf),
+            Message::NewVote(vote) => vote.serialize_compressed(&mut buf),
+            Message::QC(qc) => qc.serialize_compressed(&mut buf),
+            Message::EndView(end_view) => end_view.serialize_compressed(&mut buf),
+            Message::EndViewCert(cert) => cert.serialize_compressed(&mut buf),
+            Message::StartView(start_view) => start_view.serialize_compressed(&mut buf),
+            Message::KeyRotation(rotation) => rotation.serialize_compressed(&mut buf),
+            Message::GetBlock(key) => key.serialize_compressed(&mut buf),
+            Message::BlockResponse(block) => block.serialize_compressed(&mut buf),
+        }
+        .unwrap();
+
+        let mut hasher = Sha256::new();
+        hasher.update(DEDUP_DOMAIN);
+        hasher.update([self.kind() as u8]);
+        hasher.update(&buf);
+        MessageHash(hasher.finalize().into())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DedupStats {
+    /// Messages dropped as repeats
+    pub hits: u64,
+    /// Messages let through
+    pub misses: u64,
+    /// Hashes forgotten to make room
+    pub evictions: u64,
+}
+
+impl DedupStats {
+    /// Fraction of messages dropped as repeats, 0 before any arrived
+    pub fn hit_rate(&self) -> f64 {
+        let seen = self.hits + self.misses;
+        if seen == 0 {
+            0.0
+        } else {
+            self.hits as f64 / seen as f64
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DedupCache {
+    capacity: usize,
+    /// When each remembered hash was last seen, by our own clock
+    last_seen: HashMap<MessageHash, u64>,
+    /// The same, ordered by when, to find the least recently seen
+    by_age: BTreeMap<u64, MessageHash>,
+    clock: u64,
+    stats: DedupStats,
+}
+
+impl Default for DedupCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_DEDUP_CAPACITY)
+    }
+}
+
+impl DedupCache {
+    pub fn new(capacity: usize) -> Self {
+        DedupCache {
+            capacity: capacity.max(1),
+            last_seen: HashMap::new(),
+            by_age: BTreeMap::new(),
+            clock: 0,
+            stats: DedupStats::default(),
+        }
+    }
+
+    /// Whether `message` should be handed on: false if it was seen recently
+    ///
+    /// Requests are always handed on, since the same request from two peers
+    /// needs an answer to each.
+    pub fn admit<Tr: Transaction>(&mut self, message: &Message<Tr>) -> bool {
+        if let Message::GetBlock(_) = message {
+            return true;
+        }
+        self.admit_hash(message.content_hash())
+    }
+
+    /// [`Self::admit`] for a hash computed elsewhere
+    pub fn admit_hash(&mut self, hash: MessageHash) -> bool {
+        self.clock += 1;
+        if let Some(seen) = self.last_seen.insert(hash, self.clock) {
+            self.by_age.remove(&seen);
+            self.by_age.insert(self.clock, hash);
+            self.stats.hits += 1;
+            return false;
+        }
+
+        self.by_age.insert(self.clock, hash);
+        self.stats.misses += 1;
+        while self.last_seen.len() > self.capacity {
+            let (_, oldest) = self.by_age.pop_first().unwrap();
+            self.last_seen.remove(&oldest);
+            self.stats.evictions += 1;
+        }
+        true
+    }
+
+    pub fn contains<Tr: Transaction>(&self, message: &Message<Tr>) -> bool {
+        self.contains_hash(&message.content_hash())
+    }
+
+    /// [`Self::contains`] for a hash computed elsewhere
+    pub fn contains_hash(&self, hash: &MessageHash) -> bool {
+        self.last_seen.contains_key(hash)
+    }
+
+    pub fn len(&self) -> usize {
+        self.last_seen.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.last_seen.is_empty()
+    }
+
+    pub fn stats(&self) -> DedupStats {
+        self.stats
+    }
+}
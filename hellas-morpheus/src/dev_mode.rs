@@ -0,0 +1,31 @@
+//! A committee of one, for developing applications against a real process
+//!
+//! With n = 1 and f = 0 the quorum arithmetic holds, but the protocol never
+//! gets anywhere: a single author has a single chain of transaction blocks,
+//! so there is never more than one tip for a leader block to join, and
+//! without a leader block nothing is 1- or 2-voted, let alone finalized. All
+//! that happens is a view change every time the timeouts run out.
+//!
+//! A process in dev mode, built with [`MorpheusProcess::dev`], skips all of
+//! that. Nobody else can certify a conflicting block, so every block it holds
+//! a QC for is final as soon as the QC forms, which for its own transaction
+//! blocks is as soon as it has 0-voted them. It never times out, complains or
+//! changes view, so it sits in view 0 producing a block whenever it has
+//! transactions.
+//!
+//! Dev mode offers no fault tolerance at all and exists for local testing;
+//! anything with peers runs the protocol proper.
+
+use ark_std::rand::{SeedableRng, rngs::StdRng};
+
+use crate::*;
+
+impl<Tr: Transaction> MorpheusProcess<Tr> {
+    /// The only member of a committee of one, with keys dealt from `seed`
+    pub fn dev(seed: u64) -> Self {
+        let kb = KeyBook::committee_setup(1, &mut StdRng::seed_from_u64(seed)).remove(0);
+        let mut process = MorpheusProcess::new(kb, Identity(1), 1, 0);
+        process.dev_mode = true;
+        process
+    }
+}
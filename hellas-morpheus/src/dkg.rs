@@ -0,0 +1,233 @@
+//! Setting up the committee's threshold keys without a trusted dealer
+//!
+//! [`KeyBook::committee_setup`] generates every member's key in one place,
+//! which is fine for tests but means whoever ran it holds everyone's keys.
+//! hinTS doesn't need that: each member draws its own key and publishes a
+//! *hint* computed from it, and the universe setup that aggregates and
+//! checks `ThreshSigned` QCs is a deterministic function of the public keys
+//! and hints alone. A [`KeySetup`] runs one member's side of this: it
+//! announces its [`HintAnnouncement`], collects everyone else's, and once it
+//! has all `n` builds its `KeyBook`. Every member that saw the same
+//! announcements ends up with the same `hints_setup` and chain id, and no
+//! secret key ever leaves the member that drew it.
+//!
+//! The only shared input is `hints::GlobalData`, the public powers-of-tau
+//! parameters, which must come from a ceremony (or a transparent source)
+//! agreed on beforehand. Universe slots beyond the committee, which hinTS
+//! needs to round the domain up, get keys derived from a public seed and a
+//! weight of zero, so knowing their keys adds nothing to an aggregate.
+//!
+//! Announcements are not signed: there is no key to sign them with yet. The
+//! transport carrying them must say who sent each one, and a member that
+//! announces two different hints is reported rather than picked from.
+//! There is also no shared secret to derive, so the resulting key books have
+//! no `election_key`, and `LeaderElection::Vrf` isn't available to them.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use ark_serialize::CanonicalSerialize;
+use ark_std::rand::{CryptoRng, RngCore, SeedableRng, rngs::StdRng};
+
+use crate::*;
+
+/// Seed for the keys of universe slots no member occupies
+const PADDING_SEED: u64 = 0x6d6f_7270_6865_7573;
+
+/// One member's contribution to the universe setup
+#[derive(Clone, Debug)]
+pub struct HintAnnouncement {
+    pub from: Identity,
+    pub public: hints::PublicKey,
+    pub hint: hints::Hint,
+}
+
+/// Why a key setup couldn't go on
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KeySetupError {
+    /// An announcement from outside identities `1..=n`
+    UnknownMember(Identity),
+    /// An announcement whose `from` isn't who the transport says sent it
+    WrongSender { claimed: Identity, sender: Identity },
+    /// A member announced two different keys or hints
+    Conflicting(Identity),
+    /// Finishing before every member has announced
+    Missing(Vec<Identity>),
+    /// The hint library rejected a hint or the universe built from them
+    Hints(String),
+}
+
+impl fmt::Display for KeySetupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownMember(id) => write!(f, "{:?} is not a member of the committee", id),
+            Self::WrongSender { claimed, sender } => {
+                write!(
+                    f,
+                    "{:?} sent an announcement claiming to be {:?}",
+                    sender, claimed
+                )
+            }
+            Self::Conflicting(id) => write!(f, "{:?} announced two different hints", id),
+            Self::Missing(ids) => write!(f, "no announcement yet from {:?}", ids),
+            Self::Hints(e) => write!(f, "hint setup failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for KeySetupError {}
+
+/// One member's side of a dealerless key setup for a committee of `n`
+pub struct KeySetup {
+    pub id: Identity,
+    pub n: usize,
+    secret: SecretKey,
+    own: HintAnnouncement,
+    received: BTreeMap<Identity, HintAnnouncement>,
+}
+
+/// Size of the hinTS domain for a committee of `n`: one slot more than the
+/// committee, rounded up, as in `KeyBook::committee_setup`
+fn domain_max(n: usize) -> usize {
+    (1 + n).next_power_of_two()
+}
+
+impl KeySetup {
+    /// Draws `id`'s key and computes its hint
+    pub fn new(
+        id: Identity,
+        n: usize,
+        gd: &hints::GlobalData,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<Self, KeySetupError> {
+        if id.0 == 0 || id.0 as usize > n {
+            return Err(KeySetupError::UnknownMember(id));
+        }
+        let secret = hints::SecretKey::random(rng);
+        let hint = hints::generate_hint(gd, &secret, domain_max(n), id.0 as usize - 1)
+            .map_err(|e| KeySetupError::Hints(format!("{:?}", e)))?;
+        let own = HintAnnouncement {
+            from: id.clone(),
+            public: secret.public(gd),
+            hint,
+        };
+        let mut received = BTreeMap::new();
+        received.insert(id.clone(), own.clone());
+        Ok(KeySetup {
+            id,
+            n,
+            secret: SecretKey::new(secret),
+            own,
+            received,
+        })
+    }
+
+    /// What to broadcast to every other member
+    pub fn announcement(&self) -> &HintAnnouncement {
+        &self.own
+    }
+
+    /// Records an announcement `sender` sent; repeats are ignored
+    pub fn receive(
+        &mut self,
+        sender: &Identity,
+        announcement: HintAnnouncement,
+    ) -> Result<(), KeySetupError> {
+        if &announcement.from != sender {
+            return Err(KeySetupError::WrongSender {
+                claimed: announcement.from,
+                sender: sender.clone(),
+            });
+        }
+        if sender.0 == 0 || sender.0 as usize > self.n {
+            return Err(KeySetupError::UnknownMember(sender.clone()));
+        }
+        match self.received.get(sender) {
+            Some(known)
+                if known.public != announcement.public || !same_hint(known, &announcement) =>
+            {
+                Err(KeySetupError::Conflicting(sender.clone()))
+            }
+            Some(_) => Ok(()),
+            None => {
+                self.received.insert(sender.clone(), announcement);
+                Ok(())
+            }
+        }
+    }
+
+    /// Members not heard from yet
+    pub fn missing(&self) -> Vec<Identity> {
+        (1..=self.n as u32)
+            .map(Identity)
+            .filter(|id| !self.received.contains_key(id))
+            .collect()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.received.len() == self.n
+    }
+
+    /// Builds this member's key book on the chain `name` from everyone's
+    /// announcements
+    pub fn finish(self, gd: &hints::GlobalData, name: &str) -> Result<KeyBook, KeySetupError> {
+        let missing = self.missing();
+        if !missing.is_empty() {
+            return Err(KeySetupError::Missing(missing));
+        }
+
+        let domain_max = domain_max(self.n);
+        let mut pubkeys = Vec::with_capacity(domain_max - 1);
+        let mut hints = Vec::with_capacity(domain_max - 1);
+        let mut weights = Vec::with_capacity(domain_max - 1);
+        for announcement in self.received.values() {
+            pubkeys.push(announcement.public.clone());
+            hints.push(announcement.hint.clone());
+            weights.push(hints::F::from(1));
+        }
+        let mut padding = StdRng::seed_from_u64(PADDING_SEED);
+        for i in self.n..domain_max - 1 {
+            let key = hints::SecretKey::random(&mut padding);
+            pubkeys.push(key.public(gd));
+            hints.push(
+                hints::generate_hint(gd, &key, domain_max, i)
+                    .map_err(|e| KeySetupError::Hints(format!("{:?}", e)))?,
+            );
+            weights.push(hints::F::from(0));
+        }
+        let setup = hints::setup_universe(gd, pubkeys, &hints, weights)
+            .map_err(|e| KeySetupError::Hints(format!("{:?}", e)))?;
+
+        let keys: BTreeMap<Identity, hints::PublicKey> = self
+            .received
+            .iter()
+            .map(|(id, announcement)| (id.clone(), announcement.public.clone()))
+            .collect();
+        let identities = keys
+            .iter()
+            .map(|(id, key)| (key.clone(), id.clone()))
+            .collect();
+        Ok(KeyBook {
+            chain_id: ChainId::genesis(name, &keys),
+            keys,
+            identities,
+            me_identity: self.id,
+            me_pub_key: self.own.public,
+            me_sec_key: self.secret,
+            hints_setup: setup,
+            metadata: MetadataRegistry::default(),
+            rotations: BTreeMap::new(),
+            election_key: None,
+            election_public: None,
+        })
+    }
+}
+
+/// Whether two announcements carry the same hint, by its encoding
+fn same_hint(a: &HintAnnouncement, b: &HintAnnouncement) -> bool {
+    let mut a_bytes = Vec::new();
+    let mut b_bytes = Vec::new();
+    a.hint.serialize_compressed(&mut a_bytes).unwrap();
+    b.hint.serialize_compressed(&mut b_bytes).unwrap();
+    a_bytes == b_bytes
+}
@@ -0,0 +1,286 @@
+//! Typed protocol events that consumers can subscribe to
+//!
+//! The `tracing_setup` functions log these same moments for humans; a
+//! [`Subscription`] delivers them as values, so the harness, a node driver or
+//! an RPC stream can react to them without parsing logs.
+//!
+//! Each subscription queues at most its capacity of undelivered events; when
+//! a subscriber falls behind, the oldest are dropped and counted in
+//! [`Subscription::dropped`], so a subscriber that stops draining can't grow
+//! the process's memory. Subscriptions belong to the process they were made
+//! on: a clone of the process starts with none.
+
+use std::{
+    collections::{BTreeSet, VecDeque},
+    ops::RangeInclusive,
+    sync::{Arc, Mutex, Weak},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+/// How many of its latest events a process keeps, for `recent_events`
+pub const EVENT_HISTORY: usize = 256;
+
+/// How many undelivered events a [`Subscription`] holds by default
+pub const SUBSCRIPTION_CAPACITY: usize = 1024;
+
+/// The kinds of [`ProtocolEvent`], used for filtering
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum EventKind {
+    BlockCreated,
+    QcFormed,
+    BlockFinalized,
+    ViewChanged,
+    PhaseChanged,
+    PeerAhead,
+    BackfillProgress,
+}
+
+/// Something that happened inside a [`MorpheusProcess`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProtocolEvent {
+    /// This process produced a block
+    BlockCreated { key: BlockKey },
+    /// A QC was recorded for the first time
+    QcFormed { data: VoteData },
+    /// The block's 2-QC has been observed by another QC
+    BlockFinalized { key: BlockKey },
+    /// This process entered a new view
+    ViewChanged { from: ViewNum, to: ViewNum },
+    /// The throughput phase of `view` changed
+    PhaseChanged {
+        view: ViewNum,
+        from: Phase,
+        to: Phase,
+    },
+    /// A peer's `StartView` showed it further along than this process
+    PeerAhead {
+        peer: Identity,
+        progress: ProgressReport,
+    },
+    /// Another batch of synced history checked out; `through` is the
+    /// highest view among everything verified so far
+    BackfillProgress {
+        verified: usize,
+        total: usize,
+        through: ViewNum,
+    },
+}
+
+impl ProtocolEvent {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            ProtocolEvent::BlockCreated { .. } => EventKind::BlockCreated,
+            ProtocolEvent::QcFormed { .. } => EventKind::QcFormed,
+            ProtocolEvent::BlockFinalized { .. } => EventKind::BlockFinalized,
+            ProtocolEvent::ViewChanged { .. } => EventKind::ViewChanged,
+            ProtocolEvent::PhaseChanged { .. } => EventKind::PhaseChanged,
+            ProtocolEvent::PeerAhead { .. } => EventKind::PeerAhead,
+            ProtocolEvent::BackfillProgress { .. } => EventKind::BackfillProgress,
+        }
+    }
+
+    /// The view this event belongs to
+    pub fn view(&self) -> ViewNum {
+        match self {
+            ProtocolEvent::BlockCreated { key } | ProtocolEvent::BlockFinalized { key } => key.view,
+            ProtocolEvent::QcFormed { data } => data.for_which.view,
+            ProtocolEvent::ViewChanged { to, .. } => *to,
+            ProtocolEvent::PhaseChanged { view, .. } => *view,
+            ProtocolEvent::PeerAhead { progress, .. } => progress.max_view,
+            ProtocolEvent::BackfillProgress { through, .. } => *through,
+        }
+    }
+
+    /// The author of the block this event is about, if any
+    pub fn block_author(&self) -> Option<&Identity> {
+        match self {
+            ProtocolEvent::BlockCreated { key } | ProtocolEvent::BlockFinalized { key } => {
+                key.author.as_ref()
+            }
+            ProtocolEvent::QcFormed { data } => data.for_which.author.as_ref(),
+            ProtocolEvent::ViewChanged { .. }
+            | ProtocolEvent::PhaseChanged { .. }
+            | ProtocolEvent::PeerAhead { .. }
+            | ProtocolEvent::BackfillProgress { .. } => None,
+        }
+    }
+}
+
+/// Selects which events a [`Subscription`] receives
+///
+/// The default filter accepts everything; each restriction narrows it further.
+#[derive(Clone, Debug, Default)]
+pub struct EventFilter {
+    pub views: Option<RangeInclusive<ViewNum>>,
+    pub block_author: Option<Identity>,
+    pub kinds: Option<BTreeSet<EventKind>>,
+}
+
+impl EventFilter {
+    pub fn views(mut self, views: RangeInclusive<ViewNum>) -> Self {
+        self.views = Some(views);
+        self
+    }
+
+    /// Only events about blocks by `author`; events not about a block are dropped
+    pub fn block_author(mut self, author: Identity) -> Self {
+        self.block_author = Some(author);
+        self
+    }
+
+    pub fn kinds(mut self, kinds: impl IntoIterator<Item = EventKind>) -> Self {
+        self.kinds = Some(kinds.into_iter().collect());
+        self
+    }
+
+    pub fn matches(&self, event: &ProtocolEvent) -> bool {
+        if let Some(views) = &self.views {
+            if !views.contains(&event.view()) {
+                return false;
+            }
+        }
+        if let Some(author) = &self.block_author {
+            if event.block_author() != Some(author) {
+                return false;
+            }
+        }
+        if let Some(kinds) = &self.kinds {
+            if !kinds.contains(&event.kind()) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// The events waiting for one subscriber
+#[derive(Debug)]
+struct EventQueue {
+    events: VecDeque<ProtocolEvent>,
+    capacity: usize,
+    dropped: u64,
+}
+
+impl EventQueue {
+    fn push(&mut self, event: ProtocolEvent) {
+        if self.events.len() >= self.capacity {
+            self.events.pop_front();
+            self.dropped += 1;
+        }
+        self.events.push_back(event);
+    }
+}
+
+/// Receives the events matching its filter, in the order they happened
+///
+/// Dropping the subscription unsubscribes it.
+#[derive(Debug)]
+pub struct Subscription {
+    queue: Arc<Mutex<EventQueue>>,
+}
+
+impl Subscription {
+    /// Takes the oldest undelivered event
+    pub fn next(&self) -> Option<ProtocolEvent> {
+        self.queue.lock().unwrap().events.pop_front()
+    }
+
+    /// Takes all undelivered events
+    pub fn drain(&self) -> Vec<ProtocolEvent> {
+        self.queue.lock().unwrap().events.drain(..).collect()
+    }
+
+    /// How many events were dropped undelivered because the queue was full
+    pub fn dropped(&self) -> u64 {
+        self.queue.lock().unwrap().dropped
+    }
+}
+
+/// The subscriber side held by a process, with the latest events whether
+/// anyone subscribed or not
+#[derive(Debug, Default)]
+pub struct Subscribers {
+    subscribers: Vec<(EventFilter, Weak<Mutex<EventQueue>>)>,
+    recent: VecDeque<ProtocolEvent>,
+}
+
+/// A clone keeps the recent events but none of the subscribers, so what a
+/// copy of a process does never reaches the original's subscriptions
+impl Clone for Subscribers {
+    fn clone(&self) -> Self {
+        Subscribers {
+            subscribers: Vec::new(),
+            recent: self.recent.clone(),
+        }
+    }
+}
+
+impl Subscribers {
+    pub fn is_empty(&self) -> bool {
+        self.subscribers.is_empty()
+    }
+
+    fn publish(&mut self, event: ProtocolEvent) {
+        self.subscribers
+            .retain(|(filter, queue)| match queue.upgrade() {
+                Some(queue) => {
+                    if filter.matches(&event) {
+                        queue.lock().unwrap().push(event.clone());
+                    }
+                    true
+                }
+                None => false,
+            });
+    }
+}
+
+impl<Tr: Transaction> MorpheusProcess<Tr> {
+    /// Subscribe to the events of this process that match `filter`, holding
+    /// up to [`SUBSCRIPTION_CAPACITY`] undelivered ones
+    pub fn subscribe(&mut self, filter: EventFilter) -> Subscription {
+        self.subscribe_with_capacity(filter, SUBSCRIPTION_CAPACITY)
+    }
+
+    /// [`Self::subscribe`], holding up to `capacity` undelivered events
+    pub fn subscribe_with_capacity(
+        &mut self,
+        filter: EventFilter,
+        capacity: usize,
+    ) -> Subscription {
+        let queue = Arc::new(Mutex::new(EventQueue {
+            events: VecDeque::new(),
+            capacity: capacity.max(1),
+            dropped: 0,
+        }));
+        self.subscribers
+            .subscribers
+            .push((filter, Arc::downgrade(&queue)));
+        Subscription { queue }
+    }
+
+    /// The last [`EVENT_HISTORY`] events, oldest first
+    ///
+    /// Kept in memory only, so a restored process starts with none.
+    pub fn recent_events(&self) -> Vec<ProtocolEvent> {
+        self.subscribers.recent.iter().cloned().collect()
+    }
+
+    pub(crate) fn emit(&mut self, event: ProtocolEvent) {
+        tracing::debug!(
+            target: "protocol_event",
+            process_id = ?self.id,
+            kind = ?event.kind(),
+            correlation = ?event.correlation_id().map(|id| id.to_string()),
+        );
+        if self.subscribers.recent.len() == EVENT_HISTORY {
+            self.subscribers.recent.pop_front();
+        }
+        self.subscribers.recent.push_back(event.clone());
+        if !self.subscribers.is_empty() {
+            self.subscribers.publish(event);
+        }
+    }
+}
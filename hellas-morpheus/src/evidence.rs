@@ -0,0 +1,201 @@
+//! Proof that a member equivocated
+//!
+//! A correct process signs at most one block per (type, slot), and votes at
+//! each level for at most one block per (type, author, slot). Two signed
+//! messages that break either rule are all it takes to convict their signer,
+//! and nothing else need be trusted: [`EquivocationEvidence`] bundles the
+//! two, and [`EquivocationEvidence::verify`] checks them against the
+//! committee's keys alone, so the evidence can be handed to someone outside
+//! the committee (a slashing contract, an operator) as is.
+//!
+//! Evidence is only ever built from individually signed messages. A
+//! certificate's `signers` bitmap isn't covered by its aggregate signature
+//! (see [`SignerBitmap`]), so naming a member there proves nothing against it.
+//!
+//! [`MorpheusProcess::equivocation_evidence`] lists the evidence a process
+//! holds: conflicting blocks from `index.equivocations`, and conflicting
+//! votes among those it collected.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::format::format_identity;
+use crate::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EquivocationEvidence<Tr: Transaction> {
+    /// Two blocks by the same author at the same position
+    Blocks {
+        first: Arc<Signed<Block<Tr>>>,
+        second: Arc<Signed<Block<Tr>>>,
+    },
+    /// Two votes at the same level by the same voter, for different blocks
+    /// at the same position
+    Votes {
+        first: Arc<ThreshPartial<VoteData>>,
+        second: Arc<ThreshPartial<VoteData>>,
+    },
+}
+
+/// Why evidence doesn't convict anyone
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EvidenceError {
+    /// The two messages have different signers
+    DifferentSigners,
+    /// A block signed by someone other than its author
+    NotAuthor,
+    /// The two messages are for different positions, so don't conflict
+    DifferentPositions,
+    /// The two messages are about the same block
+    SameBlock,
+    /// The first (0) or second (1) signature doesn't verify
+    InvalidSignature(usize),
+}
+
+impl fmt::Display for EvidenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DifferentSigners => write!(f, "the messages have different signers"),
+            Self::NotAuthor => write!(f, "a block is signed by someone other than its author"),
+            Self::DifferentPositions => write!(f, "the messages are for different positions"),
+            Self::SameBlock => write!(f, "the messages are about the same block"),
+            Self::InvalidSignature(i) => write!(f, "signature {} doesn't verify", i),
+        }
+    }
+}
+
+impl std::error::Error for EvidenceError {}
+
+impl<Tr: Transaction> EquivocationEvidence<Tr> {
+    /// Who equivocated, if the evidence holds
+    pub fn offender(&self) -> &Identity {
+        match self {
+            Self::Blocks { first, .. } => &first.author,
+            Self::Votes { first, .. } => &first.author,
+        }
+    }
+
+    /// Whether this convicts [`Self::offender`], going by `verifier` alone
+    pub fn verify(&self, verifier: &(impl Verifier + ?Sized)) -> Result<(), EvidenceError> {
+        match self {
+            Self::Blocks { first, second } => {
+                if first.author != second.author {
+                    return Err(EvidenceError::DifferentSigners);
+                }
+                let (a, b) = (&first.data.key, &second.data.key);
+                if a.author.as_ref() != Some(&first.author)
+                    || b.author.as_ref() != Some(&second.author)
+                {
+                    return Err(EvidenceError::NotAuthor);
+                }
+                if SlotKey::from(a) != SlotKey::from(b) {
+                    return Err(EvidenceError::DifferentPositions);
+                }
+                if a == b {
+                    return Err(EvidenceError::SameBlock);
+                }
+                if !first.valid_signature_at(verifier, a.view) {
+                    return Err(EvidenceError::InvalidSignature(0));
+                }
+                if !second.valid_signature_at(verifier, b.view) {
+                    return Err(EvidenceError::InvalidSignature(1));
+                }
+            }
+            Self::Votes { first, second } => {
+                if first.author != second.author {
+                    return Err(EvidenceError::DifferentSigners);
+                }
+                let (a, b) = (&first.data, &second.data);
+                let position = VoteKey::for_block(a.z, &a.for_which);
+                if position.is_none() || position != VoteKey::for_block(b.z, &b.for_which) {
+                    return Err(EvidenceError::DifferentPositions);
+                }
+                if a.for_which == b.for_which {
+                    return Err(EvidenceError::SameBlock);
+                }
+                if !first.valid_signature(verifier) {
+                    return Err(EvidenceError::InvalidSignature(0));
+                }
+                if !second.valid_signature(verifier) {
+                    return Err(EvidenceError::InvalidSignature(1));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<Tr: Transaction> MorpheusProcess<Tr> {
+    /// Evidence against every member we have seen equivocate: for each
+    /// position, the first message we saw paired with each conflicting one
+    pub fn equivocation_evidence(&self) -> Vec<EquivocationEvidence<Tr>> {
+        let mut evidence = Vec::new();
+
+        for (slot, keys) in &self.index.equivocations {
+            let blocks: Vec<_> = keys
+                .iter()
+                .filter_map(|key| self.index.blocks.get(key))
+                .collect();
+            let first = self
+                .index
+                .block_at_slot
+                .get(slot)
+                .and_then(|key| self.index.blocks.get(key));
+            if let Some(first) = first {
+                for second in blocks.into_iter().filter(|block| *block != first) {
+                    evidence.push(EquivocationEvidence::Blocks {
+                        first: first.clone(),
+                        second: second.clone(),
+                    });
+                }
+            }
+        }
+
+        let mut votes: BTreeMap<(Identity, VoteKey), Vec<&Arc<ThreshPartial<VoteData>>>> =
+            BTreeMap::new();
+        for (data, by_voter) in &self.vote_tracker.votes {
+            if let Some(position) = VoteKey::for_block(data.z, &data.for_which) {
+                for (voter, vote) in by_voter {
+                    votes
+                        .entry((voter.clone(), position.clone()))
+                        .or_default()
+                        .push(vote);
+                }
+            }
+        }
+        for conflicting in votes.into_values().filter(|votes| votes.len() > 1) {
+            for second in &conflicting[1..] {
+                evidence.push(EquivocationEvidence::Votes {
+                    first: conflicting[0].clone(),
+                    second: (*second).clone(),
+                });
+            }
+        }
+
+        evidence
+    }
+}
+
+impl<Tr: Transaction> fmt::Display for EquivocationEvidence<Tr> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Blocks { first, second } => write!(
+                f,
+                "{} signed blocks {:?} and {:?}",
+                format_identity(&first.author),
+                first.data.key,
+                second.data.key
+            ),
+            Self::Votes { first, second } => write!(
+                f,
+                "{} voted for {:?} and {:?}",
+                format_identity(&first.author),
+                first.data,
+                second.data
+            ),
+        }
+    }
+}
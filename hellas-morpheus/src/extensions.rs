@@ -0,0 +1,137 @@
+//! Application data carried by leader blocks
+//!
+//! A leader block orders transaction blocks and nothing else, but protocols
+//! built on top sometimes want the leader to attest to something along with
+//! the ordering, e.g. which transaction blocks it holds the data of. Rather
+//! than fork [`BlockData`], they can put it in the leader block's extension
+//! slot: a [`BlockExtension`] of opaque bytes tagged with a version the
+//! application assigns.
+//!
+//! The slot is filled and checked by an [`ExtensionPolicy`]. The leader asks
+//! it for an extension when producing a block, and every process asks it
+//! whether a leader block's extension is valid before voting for the block.
+//! The extension is covered by the block's content hash and signature, and
+//! is never larger than [`MAX_EXTENSION_LEN`] bytes.
+//!
+//! Validity is part of consensus: every member must run the same policy, or
+//! they will disagree about which leader blocks to vote for. The default,
+//! [`NoExtensions`], never produces one and rejects every block that carries
+//! one.
+
+use std::sync::Arc;
+
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+/// The largest payload an extension may carry, in bytes
+pub const MAX_EXTENSION_LEN: usize = 64 << 10;
+
+/// Application bytes attached to a leader block
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+)]
+pub struct BlockExtension {
+    /// Assigned by the application, so it can change the payload's format
+    /// without misreading old blocks
+    pub version: u16,
+    pub payload: Vec<u8>,
+}
+
+/// The leader block an extension is produced for or found in
+pub struct ExtensionContext {
+    pub view: ViewNum,
+    pub slot: SlotNum,
+    pub height: u64,
+    pub author: Identity,
+}
+
+pub trait ExtensionPolicy: Send + Sync {
+    /// The extension to attach to our leader block, if any
+    fn produce(&self, ctx: &ExtensionContext) -> Option<BlockExtension>;
+
+    /// Whether a leader block may carry `extension`, and if not, why
+    fn validate(&self, ctx: &ExtensionContext, extension: &BlockExtension) -> Result<(), String>;
+}
+
+/// Leader blocks carry no extensions; the default
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoExtensions;
+
+impl ExtensionPolicy for NoExtensions {
+    fn produce(&self, _: &ExtensionContext) -> Option<BlockExtension> {
+        None
+    }
+
+    fn validate(&self, _: &ExtensionContext, extension: &BlockExtension) -> Result<(), String> {
+        Err(format!(
+            "no extensions are accepted, got version {}",
+            extension.version
+        ))
+    }
+}
+
+pub(crate) fn default_extension_policy() -> Arc<dyn ExtensionPolicy> {
+    Arc::new(NoExtensions)
+}
+
+impl ExtensionContext {
+    pub(crate) fn of(key: &BlockKey) -> Self {
+        ExtensionContext {
+            view: key.view,
+            slot: key.slot,
+            height: key.height,
+            author: key.author.clone().expect("leader blocks have an author"),
+        }
+    }
+}
+
+impl<Tr: Transaction> MorpheusProcess<Tr> {
+    /// What our policy attaches to a leader block at `key`, dropped if it
+    /// is too large for anyone to accept
+    pub(crate) fn produce_extension(&self, key: &BlockKey) -> Option<BlockExtension> {
+        let extension = self.extension_policy.produce(&ExtensionContext::of(key))?;
+        if extension.payload.len() > MAX_EXTENSION_LEN {
+            tracing::warn!(
+                target: "oversized_extension",
+                process_id = ?self.id,
+                size = extension.payload.len(),
+                max = MAX_EXTENSION_LEN,
+            );
+            return None;
+        }
+        Some(extension)
+    }
+
+    /// Checks the extension of a leader block at `key` against the size
+    /// bound and our policy
+    pub(crate) fn extension_valid(
+        &self,
+        key: &BlockKey,
+        extension: &BlockExtension,
+    ) -> Result<(), BlockValidationError> {
+        if extension.payload.len() > MAX_EXTENSION_LEN {
+            return Err(BlockValidationError::ExtensionTooLarge {
+                size: extension.payload.len(),
+                max: MAX_EXTENSION_LEN,
+            });
+        }
+        self.extension_policy
+            .validate(&ExtensionContext::of(key), extension)
+            .map_err(|reason| BlockValidationError::InvalidExtension {
+                version: extension.version,
+                reason,
+            })
+    }
+}
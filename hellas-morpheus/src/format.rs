@@ -1,6 +1,8 @@
 //! Tools for formatting Morpheus protocol types for logging and debugging.
 
+use std::fmt;
 use std::fmt::Write;
+use std::str::FromStr;
 
 use ark_serialize::CanonicalDeserialize;
 use ark_serialize::CanonicalSerialize;
@@ -8,6 +10,8 @@ use ark_serialize::Valid;
 
 use crate::Transaction;
 use crate::crypto::*;
+use crate::merkle::{MerkleRoot, leaf_hash};
+use crate::tracing_setup::{PayloadLogging, payload_logging};
 use crate::types::*;
 
 /// Format a BlockType in a concise way
@@ -36,7 +40,20 @@ pub fn format_identity(identity: &Identity) -> String {
 
 /// Format a BlockHash in a concise way
 pub fn format_block_hash(hash: &BlockHash) -> String {
-    format!("#{:x}", hash.0)
+    let mut result = String::from("#");
+    for byte in &hash.0[..4] {
+        write!(result, "{:02x}", byte).unwrap();
+    }
+    result
+}
+
+/// Format a MerkleRoot in a concise way
+pub fn format_merkle_root(root: &MerkleRoot) -> String {
+    let mut result = String::from("#");
+    for byte in &root.0[..4] {
+        write!(result, "{:02x}", byte).unwrap();
+    }
+    result
 }
 
 /// Format a BlockKey in a concise way
@@ -159,7 +176,7 @@ pub fn format_start_view(start_view: &StartView, verbose: bool) -> String {
 pub fn format_block_data<Tr: Transaction>(data: &BlockData<Tr>, verbose: bool) -> String {
     match data {
         BlockData::Genesis => "Genesis".to_string(),
-        BlockData::Tr { transactions } => {
+        BlockData::Tr { transactions, .. } => {
             if verbose {
                 let tx_strs: Vec<_> = transactions
                     .iter()
@@ -170,7 +187,7 @@ pub fn format_block_data<Tr: Transaction>(data: &BlockData<Tr>, verbose: bool) -
                 format!("Tr[{} txs]", transactions.len())
             }
         }
-        BlockData::Lead { justification } => {
+        BlockData::Lead { justification, .. } => {
             if verbose {
                 let just_strs: Vec<_> = justification
                     .iter()
@@ -185,8 +202,20 @@ pub fn format_block_data<Tr: Transaction>(data: &BlockData<Tr>, verbose: bool) -
 }
 
 /// Format a Transaction in a concise way
+///
+/// Shows only a hash of the transaction unless the redaction in
+/// `tracing_setup` allows it in full where it is being logged.
 pub fn format_transaction<Tr: Transaction>(tx: &Tr, _verbose: bool) -> String {
-    format!("Tx({:?})", tx)
+    match payload_logging() {
+        PayloadLogging::Full => format!("Tx({:?})", tx),
+        PayloadLogging::HashOnly => {
+            let mut result = String::from("Tx#");
+            for byte in &leaf_hash(tx)[..4] {
+                write!(result, "{:02x}", byte).unwrap();
+            }
+            result
+        }
+    }
 }
 
 /// Format a Block in a concise way
@@ -289,6 +318,15 @@ pub fn format_message<Tr: Transaction>(message: &Message<Tr>, verbose: bool) ->
                 )
             }
         }
+        Message::KeyRotation(rotation) => format!(
+            "KeyRotation({},{})",
+            format_identity(&rotation.author),
+            format_view_num(&rotation.data.effective)
+        ),
+        Message::GetBlock(key) => format!("GetBlock({})", format_block_key(key)),
+        Message::BlockResponse(block) => {
+            format!("BlockResponse({})", format_block_key(&block.data.key))
+        }
     }
 }
 
@@ -300,6 +338,180 @@ pub fn format_phase(phase: &Phase) -> String {
     }
 }
 
+/// Why a protocol object couldn't be parsed from its textual form
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// Doesn't have the shape of the object, e.g. `Tr:3:v2:s7:h4`
+    Malformed {
+        input: String,
+        expected: &'static str,
+    },
+    /// A component that should be a number isn't one, or doesn't fit
+    BadNumber { input: String },
+    /// A hash that isn't 64 hex digits
+    BadHash { input: String },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Malformed { input, expected } => {
+                write!(f, "can't parse {input:?}, expected {expected}")
+            }
+            ParseError::BadNumber { input } => write!(f, "{input:?} is not a valid number"),
+            ParseError::BadHash { input } => {
+                write!(f, "{input:?} is not a block hash of 64 hex digits")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+const VIEW_FORM: &str = "v<view>";
+const BLOCK_KEY_FORM: &str = "Gen or <Lead|Tr>:<author>:v<view>:s<slot>:h<height>[:<hash>]";
+const VOTE_DATA_FORM: &str = "<z>-<block key>";
+
+fn parse_number<T: FromStr>(input: &str) -> Result<T, ParseError> {
+    input.parse().map_err(|_| ParseError::BadNumber {
+        input: input.to_string(),
+    })
+}
+
+/// `input` with `prefix` stripped, then parsed as a number
+fn parse_prefixed<T: FromStr>(
+    input: &str,
+    prefix: char,
+    expected: &'static str,
+) -> Result<T, ParseError> {
+    let number = input.strip_prefix(prefix).ok_or(ParseError::Malformed {
+        input: input.to_string(),
+        expected,
+    })?;
+    parse_number(number)
+}
+
+fn parse_block_hash(input: &str) -> Result<BlockHash, ParseError> {
+    let bad = || ParseError::BadHash {
+        input: input.to_string(),
+    };
+    if input.len() != 64 || !input.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(bad());
+    }
+    let mut hash = [0u8; 32];
+    for (i, byte) in hash.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&input[2 * i..2 * i + 2], 16).unwrap();
+    }
+    Ok(BlockHash(hash))
+}
+
+/// `v<view>`, e.g. `v2`
+impl fmt::Display for ViewNum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format_view_num(self))
+    }
+}
+
+impl FromStr for ViewNum {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_prefixed(s, 'v', VIEW_FORM).map(ViewNum)
+    }
+}
+
+/// `Gen` for genesis, otherwise `<type>:<author>:v<view>:s<slot>:h<height>`
+/// followed by `:<hash>` in full hex if the key has one, e.g. `Tr:3:v2:s7:h4`
+///
+/// Unlike the [`Debug`](fmt::Debug) form this leaves nothing out, so it
+/// parses back to the same key and can name a block in paths and CLI
+/// arguments.
+impl fmt::Display for BlockKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.type_ == BlockType::Genesis {
+            return write!(f, "{}", format_block_type(&self.type_));
+        }
+        write!(f, "{}:", format_block_type(&self.type_))?;
+        match &self.author {
+            Some(author) => write!(f, "{}", author.0)?,
+            None => write!(f, "_")?,
+        }
+        write!(
+            f,
+            ":{}:{}:h{}",
+            format_view_num(&self.view),
+            format_slot_num(&self.slot),
+            self.height
+        )?;
+        if let Some(hash) = &self.hash {
+            write!(f, ":")?;
+            for byte in &hash.0 {
+                write!(f, "{byte:02x}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for BlockKey {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let malformed = || ParseError::Malformed {
+            input: s.to_string(),
+            expected: BLOCK_KEY_FORM,
+        };
+        let parts: Vec<&str> = s.split(':').collect();
+        let type_ = match parts[0] {
+            "Gen" if parts.len() == 1 => return Ok(GEN_BLOCK_KEY),
+            "Lead" => BlockType::Lead,
+            "Tr" => BlockType::Tr,
+            _ => return Err(malformed()),
+        };
+        let [_, author, view, slot, height, hash @ ..] = parts.as_slice() else {
+            return Err(malformed());
+        };
+        let hash = match hash {
+            [] => None,
+            [hash] => Some(parse_block_hash(hash)?),
+            _ => return Err(malformed()),
+        };
+        Ok(BlockKey {
+            type_,
+            view: parse_prefixed(view, 'v', BLOCK_KEY_FORM).map(ViewNum)?,
+            height: parse_prefixed(height, 'h', BLOCK_KEY_FORM)?,
+            author: match *author {
+                "_" => None,
+                author => Some(Identity(parse_number(author)?)),
+            },
+            slot: parse_prefixed(slot, 's', BLOCK_KEY_FORM).map(SlotNum)?,
+            hash,
+        })
+    }
+}
+
+/// `<z>-<block key>`, e.g. `1-Tr:3:v2:s7:h4` for a vote towards a 2-QC
+impl fmt::Display for VoteData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.z, self.for_which)
+    }
+}
+
+impl FromStr for VoteData {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (z, for_which) = s.split_once('-').ok_or(ParseError::Malformed {
+            input: s.to_string(),
+            expected: VOTE_DATA_FORM,
+        })?;
+        Ok(VoteData {
+            z: parse_number(z)?,
+            for_which: for_which.parse()?,
+        })
+    }
+}
+
 // Add logging macros that use our custom formatters
 #[macro_export]
 macro_rules! protocol_log {
@@ -0,0 +1,347 @@
+//! Changing protocol parameters cluster-wide through finalized transactions
+//!
+//! A transaction whose [`Transaction::config_change`] is `Some` proposes
+//! setting one parameter to a new value from an activation view on. Once the
+//! block carrying it is finalized, every process schedules the change, and
+//! from the activation view on runs with the new value. Since the schedule is
+//! built from finalized blocks only, and ordered by activation view, block
+//! and position in the block rather than by when each process learned of
+//! it, every process arrives at the same parameters for every view.
+//!
+//! A change is checked against the view of the block that includes it: the
+//! parameter must be one of [`Parameter`], its value within the parameter's
+//! safe range, and the activation at least [`GOVERNANCE_MIN_LEAD`] views
+//! later, so that it is finalized everywhere well before it applies. A
+//! transaction block with a change failing these checks is invalid. A
+//! process that learns of a change only after its activation view applies
+//! it at once, and checks blocks of earlier views against the old values.
+//!
+//! Only parameters whose values may briefly differ between processes are
+//! governed: timeouts, the leader's pace, and the block size cap, which is
+//! checked against the value in force in each block's own view.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+/// How many views after the block including it a change may activate, at the earliest
+pub const GOVERNANCE_MIN_LEAD: i64 = 4;
+
+/// Prefix of a config change encoded with [`ConfigChange::encode`]
+const CONFIG_CHANGE_MAGIC: &[u8] = b"morpheus-config-v1";
+
+/// A parameter governance may change
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Parameter {
+    /// Δ used for timeouts is the configured Δ times this
+    DeltaMultiplier,
+    /// Largest compressed size of a signed leader block, in bytes
+    MaxBlockSize,
+    /// Tips a leader waits for before producing a leader block
+    LeaderMinTips,
+}
+
+impl Parameter {
+    pub const ALL: [Parameter; 3] = [
+        Parameter::DeltaMultiplier,
+        Parameter::MaxBlockSize,
+        Parameter::LeaderMinTips,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Parameter::DeltaMultiplier => "delta_multiplier",
+            Parameter::MaxBlockSize => "max_block_size",
+            Parameter::LeaderMinTips => "leader_min_tips",
+        }
+    }
+
+    /// The values considered safe, inclusive
+    pub fn safe_range(self) -> (u64, u64) {
+        match self {
+            Parameter::DeltaMultiplier => (1, 16),
+            Parameter::MaxBlockSize => (64 << 10, 64 << 20),
+            Parameter::LeaderMinTips => (2, 64),
+        }
+    }
+}
+
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Parameter {
+    type Err = GovernanceError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Parameter::ALL
+            .into_iter()
+            .find(|param| param.name() == name)
+            .ok_or_else(|| GovernanceError::UnknownParameter(name.to_string()))
+    }
+}
+
+/// A proposal to set `param` to `value` from view `activation` on
+///
+/// The parameter is named rather than typed, so that a change naming a
+/// parameter this version doesn't know is still read, and rejected.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ConfigChange {
+    pub param: String,
+    pub value: u64,
+    pub activation: ViewNum,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GovernanceError {
+    UnknownParameter(String),
+    Unsafe {
+        param: Parameter,
+        value: u64,
+        min: u64,
+        max: u64,
+    },
+    /// Activates before `GOVERNANCE_MIN_LEAD` views after the including block's view
+    TooSoon {
+        activation: ViewNum,
+        earliest: ViewNum,
+    },
+}
+
+impl fmt::Display for GovernanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GovernanceError::UnknownParameter(name) => {
+                write!(f, "unknown parameter {name:?}")
+            }
+            GovernanceError::Unsafe {
+                param,
+                value,
+                min,
+                max,
+            } => write!(
+                f,
+                "{param} = {value} is outside the safe range {min}..={max}"
+            ),
+            GovernanceError::TooSoon {
+                activation,
+                earliest,
+            } => write!(
+                f,
+                "activation in view {} is too soon, the earliest is view {}",
+                activation.0, earliest.0
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GovernanceError {}
+
+impl ConfigChange {
+    pub fn new(param: Parameter, value: u64, activation: ViewNum) -> Self {
+        ConfigChange {
+            param: param.name().to_string(),
+            value,
+            activation,
+        }
+    }
+
+    /// The parameter changed, if this change may be included in a block of `view`
+    pub fn validate(&self, view: ViewNum) -> Result<Parameter, GovernanceError> {
+        let param = self.param.parse::<Parameter>()?;
+        let (min, max) = param.safe_range();
+        if !(min..=max).contains(&self.value) {
+            return Err(GovernanceError::Unsafe {
+                param,
+                value: self.value,
+                min,
+                max,
+            });
+        }
+        let earliest = ViewNum(view.0 + GOVERNANCE_MIN_LEAD);
+        if self.activation < earliest {
+            return Err(GovernanceError::TooSoon {
+                activation: self.activation,
+                earliest,
+            });
+        }
+        Ok(param)
+    }
+
+    /// A byte encoding for transaction types that carry raw bytes: a magic
+    /// prefix, the parameter name prefixed by its length, then the value and
+    /// the activation view, little endian
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = CONFIG_CHANGE_MAGIC.to_vec();
+        buf.push(self.param.len() as u8);
+        buf.extend_from_slice(self.param.as_bytes());
+        buf.extend_from_slice(&self.value.to_le_bytes());
+        buf.extend_from_slice(&self.activation.0.to_le_bytes());
+        buf
+    }
+
+    /// The change `bytes` encode, if they are one
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let rest = bytes.strip_prefix(CONFIG_CHANGE_MAGIC)?;
+        let (&len, rest) = rest.split_first()?;
+        let (param, rest) = rest.split_at_checked(len as usize)?;
+        let (value, rest) = rest.split_first_chunk::<8>()?;
+        let activation = <[u8; 8]>::try_from(rest).ok()?;
+        Some(ConfigChange {
+            param: String::from_utf8(param.to_vec()).ok()?,
+            value: u64::from_le_bytes(*value),
+            activation: ViewNum(i64::from_le_bytes(activation)),
+        })
+    }
+}
+
+/// Values of the governed parameters in some view
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GovernedParams {
+    pub delta_multiplier: u32,
+    /// `None` keeps `LeaderBudget::max_size`
+    pub max_block_size: Option<usize>,
+    pub leader_min_tips: usize,
+}
+
+impl Default for GovernedParams {
+    fn default() -> Self {
+        GovernedParams {
+            delta_multiplier: 1,
+            max_block_size: None,
+            leader_min_tips: 2,
+        }
+    }
+}
+
+impl GovernedParams {
+    fn apply(&mut self, param: Parameter, value: u64) {
+        match param {
+            Parameter::DeltaMultiplier => self.delta_multiplier = value as u32,
+            Parameter::MaxBlockSize => self.max_block_size = Some(value as usize),
+            Parameter::LeaderMinTips => self.leader_min_tips = value as usize,
+        }
+    }
+}
+
+/// Finalized config changes, and which finalized blocks were looked through for them
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Governance {
+    /// By activation view, then by including block and position in it
+    #[serde(with = "serde_json_any_key::any_key_map")]
+    scheduled: BTreeMap<ViewNum, BTreeMap<(BlockKey, usize), (Parameter, u64)>>,
+    scanned: BTreeSet<BlockKey>,
+    /// Finalized blocks we don't hold yet, looked through once they arrive
+    #[serde(default)]
+    missing: BTreeSet<BlockKey>,
+}
+
+impl Governance {
+    /// The parameters in force in `view`
+    pub fn params_at(&self, view: ViewNum) -> GovernedParams {
+        let mut params = GovernedParams::default();
+        for changes in self.scheduled.range(..=view).map(|(_, changes)| changes) {
+            for &(param, value) in changes.values() {
+                params.apply(param, value);
+            }
+        }
+        params
+    }
+
+    /// Every change scheduled, by activation view, in the order applied
+    pub fn scheduled(&self) -> BTreeMap<ViewNum, Vec<(Parameter, u64)>> {
+        self.scheduled
+            .iter()
+            .map(|(view, changes)| (*view, changes.values().copied().collect()))
+            .collect()
+    }
+}
+
+impl<Tr: Transaction> MorpheusProcess<Tr> {
+    /// The governed parameters in force in the current view
+    pub fn governed_params(&self) -> GovernedParams {
+        self.governance.params_at(self.view_i)
+    }
+
+    /// Δ for timeouts in the current view: `delta` times the governed multiplier
+    pub fn current_delta(&self) -> u128 {
+        self.delta * self.governed_params().delta_multiplier as u128
+    }
+
+    /// Schedules the config changes of `key`, just recorded, if it was
+    /// finalized before we held it
+    pub(crate) fn schedule_late_config_changes(&mut self, key: &BlockKey) {
+        if self.governance.missing.contains(key) {
+            self.schedule_config_changes(vec![key.clone()]);
+        }
+    }
+
+    /// Largest compressed size allowed for a signed block of `view`
+    pub fn max_block_size_at(&self, view: ViewNum) -> usize {
+        self.governance
+            .params_at(view)
+            .max_block_size
+            .unwrap_or(self.leader_budget.max_size)
+    }
+
+    /// Schedules the config changes in `finalized` and in every ancestor
+    /// of it not looked through yet
+    ///
+    /// A finalized block we don't hold yet is remembered, and looked through
+    /// when [`Self::record_block`] records it, so that a 2-QC arriving
+    /// before its block doesn't lose the block's changes.
+    pub(crate) fn schedule_config_changes(&mut self, finalized: Vec<BlockKey>) {
+        let mut to_visit = finalized;
+        while let Some(key) = to_visit.pop() {
+            if self.governance.scanned.contains(&key) {
+                continue;
+            }
+            let Some(block) = self.index.blocks.get(&key) else {
+                self.governance.missing.insert(key);
+                continue;
+            };
+            self.governance.missing.remove(&key);
+            self.governance.scanned.insert(key.clone());
+            to_visit.extend(block.data.prev.iter().map(|qc| qc.data.for_which.clone()));
+            let BlockData::Tr { transactions, .. } = &block.data.data else {
+                continue;
+            };
+            for (index, tx) in transactions.iter().enumerate() {
+                let Some(change) = tx.config_change() else {
+                    continue;
+                };
+                // validated with the block, so this only fails for blocks
+                // finalized before we could check them
+                match change.validate(key.view) {
+                    Ok(param) => {
+                        tracing::info!(
+                            target: "config_change",
+                            process_id = ?self.id,
+                            block = ?key,
+                            param = %param,
+                            value = change.value,
+                            activation = change.activation.0,
+                        );
+                        self.governance
+                            .scheduled
+                            .entry(change.activation)
+                            .or_default()
+                            .insert((key.clone(), index), (param, change.value));
+                    }
+                    Err(error) => tracing::warn!(
+                        target: "config_change",
+                        process_id = ?self.id,
+                        block = ?key,
+                        %error,
+                    ),
+                }
+            }
+        }
+    }
+}
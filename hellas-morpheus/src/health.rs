@@ -0,0 +1,122 @@
+//! Whether a process is fit to serve, for orchestrators gating traffic on it
+//!
+//! A process that is merely running may still be replaying history or cut
+//! off from its peers. [`MorpheusProcess::readiness`] reports the three
+//! things that make it useful: it is within `max_lag` views of the furthest
+//! view it knows of (from QCs it has seen and from peers' `StartView`
+//! progress reports), its storage accepts writes, and it is connected to
+//! enough peers to form a quorum with them.
+//!
+//! The process knows nothing of connections or disks, so the node running it
+//! supplies those two facts.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+/// Default for how many views a ready process may trail the best known one by
+pub const DEFAULT_MAX_VIEW_LAG: i64 = 2;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Readiness {
+    /// The view this process is in
+    pub view: ViewNum,
+    /// The highest view this process knows anyone to have reached
+    pub best_known_view: ViewNum,
+    pub max_lag: i64,
+    pub connected_peers: usize,
+    /// Peers needed to make up a quorum with this process
+    pub peers_needed: usize,
+    pub storage_writable: bool,
+}
+
+/// A reason a process isn't ready
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NotReady {
+    Lagging { lag: i64, max_lag: i64 },
+    StorageUnwritable,
+    TooFewPeers { connected: usize, needed: usize },
+}
+
+impl fmt::Display for NotReady {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotReady::Lagging { lag, max_lag } => {
+                write!(
+                    f,
+                    "{} views behind the best known view, at most {} allowed",
+                    lag, max_lag
+                )
+            }
+            NotReady::StorageUnwritable => write!(f, "storage is not writable"),
+            NotReady::TooFewPeers { connected, needed } => {
+                write!(
+                    f,
+                    "connected to {} peers, {} needed for a quorum",
+                    connected, needed
+                )
+            }
+        }
+    }
+}
+
+impl Readiness {
+    /// How many views this process trails the best known view by
+    pub fn lag(&self) -> i64 {
+        (self.best_known_view.0 - self.view.0).max(0)
+    }
+
+    /// Everything keeping this process from being ready
+    pub fn problems(&self) -> Vec<NotReady> {
+        let mut problems = Vec::new();
+        if self.lag() > self.max_lag {
+            problems.push(NotReady::Lagging {
+                lag: self.lag(),
+                max_lag: self.max_lag,
+            });
+        }
+        if !self.storage_writable {
+            problems.push(NotReady::StorageUnwritable);
+        }
+        if self.connected_peers < self.peers_needed {
+            problems.push(NotReady::TooFewPeers {
+                connected: self.connected_peers,
+                needed: self.peers_needed,
+            });
+        }
+        problems
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.problems().is_empty()
+    }
+}
+
+impl<Tr: Transaction> MorpheusProcess<Tr> {
+    /// Whether this process is ready to serve, given how many peers it is
+    /// connected to and whether its storage accepts writes
+    pub fn readiness(
+        &self,
+        max_lag: i64,
+        connected_peers: usize,
+        storage_writable: bool,
+    ) -> Readiness {
+        let best_known_view = self
+            .peer_progress
+            .values()
+            .map(|progress| progress.max_view)
+            .chain([self.view_i, self.index.max_view.0])
+            .max()
+            .unwrap_or(self.view_i);
+        Readiness {
+            view: self.view_i,
+            best_known_view,
+            max_lag,
+            connected_peers,
+            peers_needed: (self.n - self.f) as usize - 1,
+            storage_writable,
+        }
+    }
+}
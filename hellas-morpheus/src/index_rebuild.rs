@@ -0,0 +1,142 @@
+//! Rebuilding a `StateIndex` from stored blocks and QCs
+//!
+//! `record_block` and `record_qc` keep the index up to date one message at a
+//! time, and what they leave behind depends only on the blocks and QCs they
+//! were given, not on much else. [`StateIndex::rebuild`] computes the same
+//! indices directly from those two sets, using the definitions from the
+//! paper rather than the incremental updates. A restarting node can use it
+//! to recover from its block and QC stores alone, and tests use it as an
+//! oracle for the incremental version.
+//!
+//! Where the incremental index keeps whichever of several equal candidates
+//! arrived first (the first block at a position, the first QC at the
+//! maximum view), the rebuilt one keeps the least by key order. The
+//! `latest_*` QCs are about our own slots, which the stores don't know, so
+//! they are left empty for the process to fill in.
+
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use crate::*;
+
+impl<Tr: Transaction> StateIndex<Tr> {
+    /// Reconstructs every derived index from `blocks` and `qcs`, which must
+    /// include the genesis block and its QC
+    pub fn rebuild(
+        blocks: impl IntoIterator<Item = Arc<Signed<Block<Tr>>>>,
+        qcs: impl IntoIterator<Item = FinishedQC>,
+    ) -> Self {
+        let mut blocks: Vec<_> = blocks.into_iter().collect();
+        blocks.sort_by(|a, b| a.data.key.cmp(&b.data.key));
+        let qcs: BTreeSet<FinishedQC> = qcs.into_iter().collect();
+
+        let genesis_block = blocks
+            .iter()
+            .find(|block| block.data.key == GEN_BLOCK_KEY)
+            .expect("the block store holds the genesis block")
+            .clone();
+        let genesis_qc = qcs
+            .iter()
+            .find(|qc| qc.data.for_which.type_ == BlockType::Genesis)
+            .expect("the QC store holds the genesis QC")
+            .clone();
+        let mut index = StateIndex::new(genesis_qc, genesis_block);
+
+        // the blocks first, since observes follows their pointers
+        for block in blocks
+            .iter()
+            .filter(|block| block.data.key.type_ != BlockType::Genesis)
+        {
+            let key = &block.data.key;
+            let slot = SlotKey::from(key);
+            match index.block_at_slot.get(&slot) {
+                Some(first) => {
+                    index
+                        .equivocations
+                        .entry(slot)
+                        .or_insert_with(|| BTreeSet::from([first.clone()]))
+                        .insert(key.clone());
+                }
+                None => {
+                    index.block_at_slot.insert(slot, key.clone());
+                }
+            }
+            if key.height > index.max_height.0 {
+                index.max_height = (key.height, key.clone());
+            }
+            if key.type_ == BlockType::Lead {
+                index.contains_lead_by_view.insert(key.view, true);
+            }
+            for qc in &block.data.prev {
+                index
+                    .block_pointed_by
+                    .entry(qc.data.for_which.clone())
+                    .or_default()
+                    .insert(key.clone());
+            }
+            index.blocks.insert(key.clone(), block.clone());
+        }
+
+        // "The tips of Q_i are those q ∈ Q_i such that there does not exist
+        // q' ∈ Q_i with q' ≻ q"
+        index.tips = qcs
+            .iter()
+            .filter(|qc| {
+                !qcs.iter().any(|other| {
+                    other.data != qc.data
+                        && index.observes(other.data.clone(), &qc.data)
+                        && !index.observes(qc.data.clone(), &other.data)
+                })
+            })
+            .cloned()
+            .collect();
+
+        // "p_i regards q ∈ Q_i (and q.b) as final if there exists q' ∈ Q_i
+        // such that q' ⪰ q and q is a 2-QC"
+        for qc in qcs.iter().filter(|qc| qc.data.z == 2) {
+            if qcs
+                .iter()
+                .any(|other| other.data != qc.data && index.observes(other.data.clone(), &qc.data))
+            {
+                index.finalized.insert(qc.data.for_which.clone());
+            }
+        }
+
+        for qc in &qcs {
+            let key = &qc.data.for_which;
+            if key.type_ == BlockType::Genesis {
+                continue;
+            }
+            if qc.data.z == 1 && index.max_1qc.data.compare_qc(&qc.data) != Ordering::Greater {
+                index.max_1qc = qc.clone();
+            }
+            if key.view > index.max_view.0 {
+                index.max_view = (key.view, qc.clone());
+            }
+            if index.finalized.contains(key) {
+                continue;
+            }
+            index
+                .unfinalized
+                .entry(key.clone())
+                .or_default()
+                .insert(qc.clone());
+            if qc.data.z == 2 {
+                index.unfinalized_2qc.insert(qc.clone());
+            }
+        }
+
+        for key in index.blocks.keys() {
+            if key.type_ == BlockType::Lead && !index.finalized.contains(key) {
+                index
+                    .unfinalized_lead_by_view
+                    .entry(key.view)
+                    .or_default()
+                    .insert(key.clone());
+            }
+        }
+
+        index
+    }
+}
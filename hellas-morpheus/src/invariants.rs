@@ -1,6 +1,7 @@
 use crate::format::*;
 use crate::*;
 
+use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::sync::Arc;
@@ -115,7 +116,8 @@ pub enum InvariantViolation {
 
     // Vote tracking consistency
     UntrackedVote {
-        vote_data: ThreshPartial<VoteData>,
+        vote_data: VoteData,
+        author: Identity,
     },
     VoteCountMismatch {
         vote_data: VoteData,
@@ -317,11 +319,11 @@ impl fmt::Display for InvariantViolation {
                 leader.0, view.0
             ),
 
-            Self::UntrackedVote { vote_data } => write!(
+            Self::UntrackedVote { vote_data, author } => write!(
                 f,
                 "VoteData {:?} received in NewVote message from {:?} but not found in vote_tracker",
-                format_vote_data(&vote_data.data, false),
-                vote_data.author
+                format_vote_data(vote_data, false),
+                author
             ),
 
             Self::VoteCountMismatch {
@@ -393,14 +395,53 @@ impl fmt::Display for InvariantViolation {
     }
 }
 
+/// How thoroughly internal state is checked while the protocol runs
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum InvariantLevel {
+    /// No checks at all
+    Off,
+    /// Only the checks that are roughly linear in the size of the state
+    Cheap,
+    /// Every check, after each message a process handles (in debug builds)
+    #[default]
+    Full,
+    /// Every check, also after timeouts and block production
+    Paranoid,
+}
+
 impl<Tr: Transaction> MorpheusProcess<Tr> {
     /// Checks key protocol invariants and returns a list of invariant violations
     ///
     /// This method is intended for testing purposes to ensure protocol invariants
     /// are maintained throughout execution.
     pub fn check_invariants(&self) -> Vec<InvariantViolation> {
+        self.check_invariants_at(InvariantLevel::Full)
+    }
+
+    /// In debug builds, panics if the invariants selected by `invariant_level`
+    /// are violated, as long as that level is at least `min_level`
+    pub(crate) fn assert_invariants(&self, min_level: InvariantLevel) {
+        if cfg!(debug_assertions) && self.invariant_level >= min_level {
+            let violations = self.check_invariants_at(self.invariant_level);
+            assert!(
+                violations.is_empty(),
+                "Process {} has invariant violations: {:?}",
+                self.id.0,
+                violations
+            );
+        }
+    }
+
+    /// Checks the protocol invariants selected by `level`
+    pub fn check_invariants_at(&self, level: InvariantLevel) -> Vec<InvariantViolation> {
         let mut violations = Vec::new();
 
+        if level == InvariantLevel::Off {
+            return violations;
+        }
+
         // Check view and phase consistency
         if !self.phase_i.contains_key(&self.view_i) {
             violations.push(InvariantViolation::ViewHasNoPhase(self.view_i));
@@ -499,78 +540,82 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
             }
         }
 
-        // Check tips consistency using self.observes() relation
-        // "The tips of Q_i are those q ∈ Q_i such that there does not exist q' ∈ Q_i with q' ≻ q"
-        let mut computed_tips = Vec::new();
-        for (qc_data, qc) in &qcs {
-            let is_tip = !q_i_qcs.iter().any(|qc_data2| {
-                // Is there any QC that observes this one and is not the same?
-                qc_data != *qc_data2
-                    && self.observes((*qc_data2).clone(), qc_data)
-                    && !self.observes((*qc_data).clone(), qc_data2)
-            });
+        // The observes-based checks below are quadratic (or worse) in the
+        // number of QCs, so only run them from the Full level up
+        if level >= InvariantLevel::Full {
+            // Check tips consistency using self.observes() relation
+            // "The tips of Q_i are those q ∈ Q_i such that there does not exist q' ∈ Q_i with q' ≻ q"
+            let mut computed_tips = Vec::new();
+            for (qc_data, qc) in &qcs {
+                let is_tip = !q_i_qcs.iter().any(|qc_data2| {
+                    // Is there any QC that observes this one and is not the same?
+                    qc_data != *qc_data2
+                        && self.observes((*qc_data2).clone(), qc_data)
+                        && !self.observes((*qc_data).clone(), qc_data2)
+                });
 
-            if is_tip {
-                computed_tips.push(Arc::clone(qc));
+                if is_tip {
+                    computed_tips.push(Arc::clone(qc));
+                }
             }
-        }
 
-        // Check if our computed tips match the actual tips
-        let actual_tips_set: BTreeSet<FinishedQC> = self.index.tips.iter().cloned().collect();
-        let computed_tips_set: BTreeSet<FinishedQC> = computed_tips.into_iter().collect();
-
-        if actual_tips_set != computed_tips_set {
-            // Find elements in computed_tips but not in actual_tips
-            let missing_tips: Vec<_> = computed_tips_set
-                .difference(&actual_tips_set)
-                .cloned()
-                .collect();
-
-            // Find elements in actual_tips but not in computed_tips
-            let extra_tips: Vec<_> = actual_tips_set
-                .difference(&computed_tips_set)
-                .cloned()
-                .collect();
-
-            if !missing_tips.is_empty() {
-                violations.push(InvariantViolation::TipsMissingQCs { missing_tips });
-            }
+            // Check if our computed tips match the actual tips
+            let actual_tips_set: BTreeSet<FinishedQC> = self.index.tips.iter().cloned().collect();
+            let computed_tips_set: BTreeSet<FinishedQC> = computed_tips.into_iter().collect();
+
+            if actual_tips_set != computed_tips_set {
+                // Find elements in computed_tips but not in actual_tips
+                let missing_tips: Vec<_> = computed_tips_set
+                    .difference(&actual_tips_set)
+                    .cloned()
+                    .collect();
+
+                // Find elements in actual_tips but not in computed_tips
+                let extra_tips: Vec<_> = actual_tips_set
+                    .difference(&computed_tips_set)
+                    .cloned()
+                    .collect();
+
+                if !missing_tips.is_empty() {
+                    violations.push(InvariantViolation::TipsMissingQCs { missing_tips });
+                }
 
-            if !extra_tips.is_empty() {
-                violations.push(InvariantViolation::TipsContainsExtraQCs { extra_tips });
+                if !extra_tips.is_empty() {
+                    violations.push(InvariantViolation::TipsContainsExtraQCs { extra_tips });
+                }
             }
-        }
-
-        // Check finalization according to pseudocode definition:
-        // "Process p_i regards q ∈ Q_i (and q.b) as final if there exists q' ∈ Q_i such
-        // that q' ⪰ q and q is a 2-QC (for any block)."
-        for (vote_data, _) in &qcs {
-            // Only check 2-QCs for finalization
-            if vote_data.z == 2 {
-                let block_key = &vote_data.for_which;
 
-                // Check if any QC observes this 2-QC
-                let observed_by_any = qcs.iter().any(|(q_data, _)| {
-                    q_data != vote_data && self.observes(q_data.clone(), vote_data)
-                });
+            // Check finalization according to pseudocode definition:
+            // "Process p_i regards q ∈ Q_i (and q.b) as final if there exists q' ∈ Q_i such
+            // that q' ⪰ q and q is a 2-QC (for any block)."
+            for (vote_data, _) in &qcs {
+                // Only check 2-QCs for finalization
+                if vote_data.z == 2 {
+                    let block_key = &vote_data.for_which;
+
+                    // Check if any QC observes this 2-QC
+                    let observed_by_any = qcs.iter().any(|(q_data, _)| {
+                        q_data != vote_data && self.observes(q_data.clone(), vote_data)
+                    });
 
-                // According to pseudocode, this 2-QC should be final if observed by any other QC
-                let should_be_final = observed_by_any;
+                    // According to pseudocode, this 2-QC should be final if observed by any other QC
+                    let should_be_final = observed_by_any;
 
-                // Check if it's actually marked as final
-                let is_marked_final = self.index.finalized.contains(block_key);
+                    // Check if it's actually marked as final
+                    let is_marked_final = self.index.finalized.contains(block_key);
 
-                if should_be_final && !is_marked_final {
-                    violations.push(InvariantViolation::BlockWithObserved2QcNotFinalized {
-                        block: block_key.clone(),
-                    });
-                }
+                    if should_be_final && !is_marked_final {
+                        violations.push(InvariantViolation::BlockWithObserved2QcNotFinalized {
+                            block: block_key.clone(),
+                        });
+                    }
 
-                // Also check the opposite - blocks marked as final should satisfy the definition
-                if is_marked_final && !should_be_final && !observed_by_any {
-                    violations.push(InvariantViolation::FinalizedBlockNot2QcObserved {
-                        block: block_key.clone(),
-                    });
+                    // Also check the opposite - blocks marked as final should satisfy the definition
+                    if is_marked_final && !should_be_final && !observed_by_any {
+                        violations.push(InvariantViolation::FinalizedBlockNot2QcObserved {
+                            block: block_key.clone(),
+                        });
+                    }
                 }
             }
         }
@@ -663,28 +708,18 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
             });
         }
 
-        // Count all the voting messages manually and check that a QC is present for each with quorum
-        let mut vote_counts = BTreeMap::new();
-        for msg in &self.received_messages {
-            match msg {
-                Message::NewVote(vote) => {
-                    *vote_counts.entry(vote.data.clone()).or_insert(0usize) += 1;
-                    if !self
-                        .vote_tracker
-                        .votes
-                        .get(&vote.data)
-                        .unwrap()
-                        .contains_key(&vote.author)
-                    {
-                        violations.push(InvariantViolation::UntrackedVote {
-                            vote_data: ThreshPartial::clone(&vote),
-                        });
-                    }
+        // Count the votes we handled and check that a QC is present for each with quorum
+        for (vote_data, voters) in self.received_messages.voters() {
+            let tracked = self.vote_tracker.votes.get(vote_data);
+            for author in voters {
+                if !tracked.is_some_and(|tracked| tracked.contains_key(author)) {
+                    violations.push(InvariantViolation::UntrackedVote {
+                        vote_data: vote_data.clone(),
+                        author: author.clone(),
+                    });
                 }
-                _ => {}
             }
-        }
-        for (vote_data, &received_count) in &vote_counts {
+            let received_count = voters.len();
             if received_count >= (self.n - self.f) as usize {
                 if !qcs.iter().any(|(qc_data, _)| qc_data == vote_data) {
                     violations.push(InvariantViolation::MissingQCDespiteQuorum {
@@ -692,12 +727,7 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
                     });
                 }
             }
-            let tracked_count = self
-                .vote_tracker
-                .votes
-                .get(&vote_data)
-                .unwrap_or(&BTreeMap::new())
-                .len();
+            let tracked_count = tracked.map_or(0, BTreeMap::len);
             if received_count != tracked_count {
                 violations.push(InvariantViolation::VoteCountMismatch {
                     vote_data: vote_data.clone(),
@@ -726,12 +756,10 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
                     });
                 }
 
-                if self.voted_i.contains(&(
-                    1,
-                    block_key.type_,
-                    block_key.slot,
-                    block_key.author.clone().unwrap(),
-                )) {
+                if self
+                    .voted_i
+                    .contains(&VoteKey::for_block(1, block_key).unwrap())
+                {
                     violations.push(InvariantViolation::PendingVotesAlreadyVoted {
                         view: *view,
                         block_key: block_key.clone(),
@@ -757,12 +785,10 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
                         vote_type: "tr_2".to_string(),
                     });
                 }
-                if self.voted_i.contains(&(
-                    2,
-                    block_key.type_,
-                    block_key.slot,
-                    block_key.author.clone().unwrap(),
-                )) {
+                if self
+                    .voted_i
+                    .contains(&VoteKey::for_block(2, block_key).unwrap())
+                {
                     violations.push(InvariantViolation::PendingVotesAlreadyVoted {
                         view: *view,
                         block_key: block_key.clone(),
@@ -789,12 +815,10 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
                     });
                 }
 
-                if self.voted_i.contains(&(
-                    1,
-                    block_key.type_,
-                    block_key.slot,
-                    block_key.author.clone().unwrap(),
-                )) {
+                if self
+                    .voted_i
+                    .contains(&VoteKey::for_block(1, block_key).unwrap())
+                {
                     violations.push(InvariantViolation::PendingVotesAlreadyVoted {
                         view: *view,
                         block_key: block_key.clone(),
@@ -821,12 +845,10 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
                     });
                 }
 
-                if self.voted_i.contains(&(
-                    2,
-                    block_key.type_,
-                    block_key.slot,
-                    block_key.author.clone().unwrap(),
-                )) {
+                if self
+                    .voted_i
+                    .contains(&VoteKey::for_block(2, block_key).unwrap())
+                {
                     violations.push(InvariantViolation::PendingVotesAlreadyVoted {
                         view: *view,
                         block_key: block_key.clone(),
@@ -836,7 +858,7 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
             }
 
             // For the current view, check if all eligible blocks are in pending_votes
-            if *view == self.view_i {
+            if *view == self.view_i && level >= InvariantLevel::Full {
                 for (block_key, _) in &self.index.blocks {
                     if block_key.type_ == BlockType::Tr
                         && block_key.view == self.view_i
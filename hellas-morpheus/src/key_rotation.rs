@@ -0,0 +1,122 @@
+//! Rotating a validator's signing key while it runs
+//!
+//! A validator announces a new key with a [`KeyRotation`] message, signed
+//! with its current key and naming a later view from which the new key is
+//! used. Every process records the announcement in `KeyBook::rotations` and,
+//! from then on, checks `Signed` messages of each view against the key their
+//! author held in that view, so signatures made with the old key stay valid
+//! for the views before the switch.
+//!
+//! An announcement must be signed with the newest key known for its author,
+//! so someone holding a retired key can't use it to announce a key of their
+//! own.
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::*;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KeyRotationError {
+    /// The new key must take effect in a later view than the current one
+    NotInFuture { effective: ViewNum, view: ViewNum },
+    /// Our previous rotation hasn't taken effect yet
+    Pending { effective: ViewNum },
+    /// Signing the announcement failed; see the `sign` log
+    SigningFailed,
+}
+
+impl fmt::Display for KeyRotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyRotationError::NotInFuture { effective, view } => write!(
+                f,
+                "a key rotation effective in view {} must come after the current view {}",
+                effective.0, view.0
+            ),
+            KeyRotationError::Pending { effective } => write!(
+                f,
+                "the previous key rotation only takes effect in view {}",
+                effective.0
+            ),
+            KeyRotationError::SigningFailed => write!(f, "could not sign the key rotation"),
+        }
+    }
+}
+
+impl std::error::Error for KeyRotationError {}
+
+impl<Tr: Transaction> MorpheusProcess<Tr> {
+    /// Announces `new_key` as our signing key from view `effective` on
+    pub fn rotate_key(
+        &mut self,
+        new_key: hints::SecretKey,
+        effective: ViewNum,
+        to_send: &mut Vec<(Message<Tr>, Option<Identity>)>,
+    ) -> Result<(), KeyRotationError> {
+        if effective <= self.view_i {
+            return Err(KeyRotationError::NotInFuture {
+                effective,
+                view: self.view_i,
+            });
+        }
+        if let Some((pending, _)) = self
+            .kb
+            .rotations
+            .get(&self.id)
+            .and_then(|known| known.last_key_value())
+            .filter(|(pending, _)| **pending > self.view_i)
+        {
+            return Err(KeyRotationError::Pending {
+                effective: *pending,
+            });
+        }
+        let rotation = KeyRotation::new(
+            &self.id,
+            self.view_i,
+            effective,
+            &new_key,
+            &self.kb.hints_setup.global,
+        );
+        let signed = self.sign(rotation).ok_or(KeyRotationError::SigningFailed)?;
+        self.rotated_keys.insert(effective, SecretKey::new(new_key));
+        self.send_msg(to_send, (Message::KeyRotation(Arc::new(signed)), None));
+        Ok(())
+    }
+
+    /// Whether `rotation` may be recorded
+    pub(crate) fn key_rotation_valid(&self, rotation: &Signed<KeyRotation>) -> bool {
+        let data = &rotation.data;
+        if data.effective <= data.announced {
+            return false;
+        }
+        let known = self.kb.rotations.get(&rotation.author);
+        // signed with the newest key we know of
+        if known
+            .and_then(|known| known.last_key_value())
+            .is_some_and(|(newest, _)| data.announced < *newest)
+        {
+            return false;
+        }
+        // and not contradicting what we already have for that view
+        if known
+            .and_then(|known| known.get(&data.effective))
+            .is_some_and(|key| *key != data.new_key)
+        {
+            return false;
+        }
+        let possession = KeyRotation::possession_message(
+            &rotation.author,
+            data.announced,
+            data.effective,
+            &data.new_key,
+        );
+        rotation.valid_signature_at(self.verifier(), data.announced)
+            && hints::verify_partial(
+                &self.kb.hints_setup.global,
+                &data.new_key,
+                &possession,
+                &data.possession,
+            )
+    }
+}
@@ -0,0 +1,139 @@
+//! Choosing the leader of each view
+//!
+//! By default leaders take turns round robin, so anyone can tell who leads
+//! any future view and take them offline just before their turn. With
+//! [`LeaderElection::Vrf`] the leader of a view is drawn from a VRF evaluated
+//! over the view number with the committee's election key (`KeyBook::election_key`).
+//! The evaluation is a BLS signature, of which there is exactly one valid
+//! value per view, so the leader can't be steered by whoever computes it, and
+//! a [`LeaderProof`] lets anyone holding the election public key check it.
+//!
+//! The election key is dealt to every member at setup, alongside its hinTS
+//! share, so members agree on each leader without exchanging messages while
+//! outsiders only learn it from a proof. A member that leaks the key makes
+//! the schedule predictable again, but no less correct.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::*;
+
+/// Domain separator for the VRF input, so it can't collide with other signed data
+const LEADER_VRF_DOMAIN: &[u8] = b"morpheus-leader-vrf-v1";
+
+/// How many views' proofs a process keeps
+const LEADER_CACHE: usize = 64;
+
+/// How the leader of each view is chosen; every member must use the same
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LeaderElection {
+    /// Identity `view % n + 1`
+    #[default]
+    RoundRobin,
+    /// Drawn from a VRF over the view number; needs `KeyBook::election_key`
+    Vrf,
+}
+
+/// The VRF evaluation that picks the leader of `view`
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+)]
+pub struct LeaderProof {
+    pub view: ViewNum,
+    pub output: hints::PartialSignature,
+}
+
+impl LeaderProof {
+    fn input(view: ViewNum) -> Vec<u8> {
+        let mut buf = LEADER_VRF_DOMAIN.to_vec();
+        view.serialize_compressed(&mut buf).unwrap();
+        buf
+    }
+
+    pub fn evaluate(view: ViewNum, election_key: &hints::SecretKey) -> Self {
+        LeaderProof {
+            view,
+            output: hints::sign(election_key, &Self::input(view)),
+        }
+    }
+
+    /// Whether this is the evaluation for `view` under `election_public`
+    pub fn verify(&self, global: &hints::GlobalData, election_public: &hints::PublicKey) -> bool {
+        hints::verify_partial(
+            global,
+            election_public,
+            &Self::input(self.view),
+            &self.output,
+        )
+    }
+
+    /// The member this proof elects in a committee of `n`
+    pub fn leader(&self, n: u32) -> Identity {
+        let mut output = Vec::new();
+        self.output.serialize_compressed(&mut output).unwrap();
+        let digest = Sha256::new()
+            .chain_update(LEADER_VRF_DOMAIN)
+            .chain_update(&output)
+            .finalize();
+        let draw = u64::from_le_bytes(digest[..8].try_into().unwrap());
+        Identity((draw % n.max(1) as u64) as u32 + 1)
+    }
+}
+
+/// Proofs already computed and checked, by view
+#[derive(Default)]
+pub(crate) struct LeaderCache(Mutex<BTreeMap<ViewNum, LeaderProof>>);
+
+impl Clone for LeaderCache {
+    fn clone(&self) -> Self {
+        LeaderCache(Mutex::new(self.0.lock().unwrap().clone()))
+    }
+}
+
+impl<Tr: Transaction> MorpheusProcess<Tr> {
+    /// The proof electing the leader of `view`, if we hold the election key
+    pub fn leader_proof(&self, view: ViewNum) -> Option<LeaderProof> {
+        if let Some(proof) = self.leader_proofs.0.lock().unwrap().get(&view) {
+            return Some(proof.clone());
+        }
+        let proof = LeaderProof::evaluate(view, self.kb.election_key.as_ref()?.expose()?);
+        let public = self.kb.election_public.as_ref()?;
+        if !proof.verify(&self.kb.hints_setup.global, public) {
+            tracing::error!(
+                target: "leader_election",
+                process_id = ?self.id,
+                ?view,
+                "election key doesn't match the committee's election public key"
+            );
+            return None;
+        }
+
+        let mut cache = self.leader_proofs.0.lock().unwrap();
+        cache.insert(view, proof.clone());
+        while cache.len() > LEADER_CACHE {
+            cache.pop_first();
+        }
+        Some(proof)
+    }
+
+    /// Checks a proof, received from elsewhere, that `author` leads `proof.view`
+    pub fn verify_leader_proof(&self, author: &Identity, proof: &LeaderProof) -> bool {
+        self.kb
+            .election_public
+            .as_ref()
+            .is_some_and(|public| proof.verify(&self.kb.hints_setup.global, public))
+            && proof.leader(self.n) == *author
+    }
+}
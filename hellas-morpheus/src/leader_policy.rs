@@ -0,0 +1,72 @@
+//! Which tips a leader block references, and in what order
+//!
+//! A leader block points to the tips it knows of, and the order of its prev
+//! pointers is the order in which the transaction blocks behind them are
+//! sequenced. A [`LeaderPolicy`] decides that order, so deployments can tune
+//! it without touching `block_production.rs`. The pointer to the leader's own
+//! previous block is added after the policy runs, whatever it returns.
+//!
+//! Validators reject leader blocks that point to too many QCs and, if they
+//! opt in, ones that leave out a tip the leader must have known (see
+//! [`LeaderBudget::known_tip_delays`]), so policies should reorder tips rather
+//! than drop them.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::*;
+
+/// What a policy may consult besides the tips themselves
+pub struct TipContext<'a> {
+    pub view: ViewNum,
+    pub n: u32,
+    /// When each tip became a tip at the leader
+    pub seen_at: &'a BTreeMap<QcKey, u128>,
+}
+
+pub trait LeaderPolicy: Send + Sync {
+    /// Returns the tips a new leader block references, in order
+    fn select_tips(&self, tips: Vec<FinishedQC>, ctx: &TipContext) -> Vec<FinishedQC>;
+}
+
+/// Tips in the order the tip index holds them; the default
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IndexOrder;
+
+impl LeaderPolicy for IndexOrder {
+    fn select_tips(&self, tips: Vec<FinishedQC>, _: &TipContext) -> Vec<FinishedQC> {
+        tips
+    }
+}
+
+/// Oldest tip first, so blocks are sequenced in the order the leader learned of them
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ReceiptOrder;
+
+impl LeaderPolicy for ReceiptOrder {
+    fn select_tips(&self, mut tips: Vec<FinishedQC>, ctx: &TipContext) -> Vec<FinishedQC> {
+        tips.sort_by_key(|tip| ctx.seen_at.get(&tip.data).copied().unwrap_or(u128::MAX));
+        tips
+    }
+}
+
+/// Orders tips by author, starting from a different author each view, so no
+/// process is always sequenced first
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AuthorRotation;
+
+impl LeaderPolicy for AuthorRotation {
+    fn select_tips(&self, mut tips: Vec<FinishedQC>, ctx: &TipContext) -> Vec<FinishedQC> {
+        let n = ctx.n.max(1) as i64;
+        let first = ctx.view.0.rem_euclid(n);
+        tips.sort_by_key(|tip| match &tip.data.for_which.author {
+            Some(author) => (author.0 as i64 - 1 - first).rem_euclid(n),
+            None => n,
+        });
+        tips
+    }
+}
+
+pub(crate) fn default_leader_policy() -> Arc<dyn LeaderPolicy> {
+    Arc::new(IndexOrder)
+}
@@ -19,10 +19,59 @@
 //! ## Implementation Structure
 //!
 //! - `process.rs`: Defines the core `MorpheusProcess` struct and message handling
+//! - `quorum.rs`: Checking that a committee's thresholds guarantee quorum intersection
 //! - `block_production.rs`: Implements block creation logic
+//! - `signer.rs`: Signing through a key held outside the process
+//! - `remote_signer.rs`: A signer that forwards requests over a local socket
+//! - `dkg.rs`: Setting up the committee's threshold keys without a trusted dealer
+//! - `secret.rs`: Secret keys that stay out of logs and snapshots and are zeroed on drop
+//! - `sign_guard.rs`: Double-sign protection persisted across restarts
+//! - `vote_wal.rs`: A write-ahead log of our votes, replayed into `voted_i` on startup
+//! - `migrations.rs`: Version stamps on persisted stores, and upgrading stores older builds wrote
+//! - `storage_faults.rs`: A guard store that fails writes and corrupts reads, for tests
+//! - `key_rotation.rs`: Announcing and recording new signing keys
+//! - `send_queue.rs`: Bounded per-peer outbound queues, drained round robin
+//! - `leader_election.rs`: Round-robin or VRF-drawn leaders for each view
+//! - `leader_policy.rs`: Which tips leader blocks reference, and in what order
+//! - `phase_policy.rs`: When to enter the low throughput phase
+//! - `extensions.rs`: Application data in leader blocks, produced and checked by a hook
+//! - `health.rs`: Whether a process is synced and connected enough to serve
+//! - `dev_mode.rs`: A committee of one with instant finality, for local development
+//! - `consistency.rs`: Checking and repairing restored state before startup
+//! - `correlation.rs`: Ids that tie messages and events about the same block or view change together
+//! - `beacon.rs`: Per-view shared randomness derived from finalized leader blocks
+//! - `governance.rs`: Protocol parameter changes proposed by finalized transactions
+//! - `events.rs`: Typed protocol events and filtered subscriptions to them
+//! - `evidence.rs`: Self-contained proof that a member equivocated
+//! - `merkle.rs`: Merkle commitments to block transactions, and inclusion proofs
+//! - `dedup.rs`: Dropping repeated messages at ingress, before validation
+//! - `link_auth.rs`: Trusting authenticated links for votes, binding peer ids to members, and MACs for point-to-point links
+//! - `rate_limit.rs`: Per-sender limits on the messages handled, with optional bans
+//! - `received.rs`: Counts and recent history of the messages a process handled
+//! - `verify_cache.rs`: Remembering signature checks that passed, to skip repeating them
+//! - `dag_stats.rs`: Width, depth and pointer statistics of the block DAG
+//! - `debug_bundle.rs`: One archive of state, recent events and config for bug reports
+//! - `vote_diagnostics.rs`: Which pending votes are held up, and by what
+//! - `catch_up.rs`: Fetching finalized blocks and QCs missed while offline
+//! - `block_fetch.rs`: Fetching missing ancestors before accepting a block that points to them
 //! - `state_tracking.rs`: Manages protocol state (blocks, QCs, DAG structure)
+//! - `backfill.rs`: Checking a long range of synced history in parallel batches
+//! - `index_rebuild.rs`: Recomputing the state index from stored blocks and QCs
+//! - `payloads.rs`: Ready-made transaction types: CBOR payloads and signed transfers
 //! - `types.rs`: Defines protocol data types
 //! - `mock_harness.rs`: Testing framework for the protocol
+//! - `config.rs`: Building a simulation from a serializable description
+//! - `presets.rs`: Built-in simulation scenarios with fault schedules
+//! - `sweep.rs`: Running matrices of simulations in parallel and comparing their statistics
+//! - `topology.rs`: Regions, latencies and bandwidth caps for the simulated network
+//! - `testkit.rs`: Assertions about consensus properties of a simulated cluster
+//! - `clock_parity.rs`: Checking that a simulation decides the same under tokio's real clock
+//! - `muchin_bridge.rs`: Running processes over muchin's TCP model in its action runner
+//! - `transitions.rs`: The message handler's transitions as a machine-readable table
+//! - `transport.rs`: The network interface a node drives its process through
+//! - `wire.rs`: The versioned binary encoding of messages between nodes
+//! - `compression.rs`: Optional zstd compression of block messages on the wire
+//! - `trace.rs`: Recording executions and replaying them against the current code
 //! - `tracing_setup.rs`: Structured logging with tracing-rs
 //! - `hades/`: Web-based visualization and debugging interface
 //!
@@ -33,33 +82,176 @@
 //! - **Observes relation**: Defines the DAG structure and block ordering
 //! - **View changes**: Allow progress when a leader is faulty
 
+mod backfill;
+mod beacon;
+mod block_fetch;
 mod block_production;
 mod block_validation;
+mod catch_up;
+mod compression;
+mod consistency;
+mod correlation;
 mod crypto;
+mod dag_stats;
+mod debug_bundle;
+mod dedup;
+mod dev_mode;
+mod dkg;
+mod events;
+mod evidence;
+mod extensions;
+mod governance;
+mod health;
+mod index_rebuild;
 mod invariants;
+mod key_rotation;
+mod leader_election;
+mod leader_policy;
+mod link_auth;
+mod merkle;
 mod message_handling;
+mod metadata;
+mod migrations;
+mod payloads;
+mod phase_policy;
 mod process;
+mod quorum;
+mod rate_limit;
+mod received;
+#[cfg(unix)]
+mod remote_signer;
+mod secret;
+mod send_queue;
+mod sign_guard;
+mod signer;
 mod state_tracking;
+mod storage_faults;
 mod types;
+mod verify_cache;
 mod view_management;
+mod vote_diagnostics;
+mod vote_wal;
 mod voting;
+mod wire;
 
+#[cfg(feature = "realtime")]
+pub mod clock_parity;
+pub mod config;
 pub mod format;
+#[cfg(feature = "muchin")]
+pub mod muchin_bridge;
+pub mod presets;
+pub mod sweep;
 pub mod test_harness;
+pub mod testkit;
+pub mod topology;
+pub mod trace;
 pub mod tracing_setup;
+pub mod transitions;
+pub mod transport;
 
 use std::{fmt::Debug, hash::Hash};
 
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Valid};
-pub use block_validation::BlockValidationError;
+pub use backfill::{BackfillConfig, BackfillError, History};
+pub use block_production::SubmitReceipt;
+pub use block_validation::{BlockValidationError, LeaderBudget};
+pub use catch_up::{MAX_SYNC_HEIGHTS, SyncRequest, SyncResponse};
+pub use compression::{
+    COMPRESSED, DEFAULT_COMPRESSION_LEVEL, MAX_DECOMPRESSED_LEN, WireCompression, compressible,
+    negotiate,
+};
+pub use consistency::{ConsistencyIssue, ConsistencyReport};
+pub use correlation::CorrelationId;
 pub use crypto::*;
-pub use invariants::InvariantViolation;
+pub use dag_stats::{DagStats, TIP_HISTORY, TipHistory};
+pub use debug_bundle::{
+    DEBUG_SECTIONS, DEBUG_VIEWS, DebugReport, ProcessConfig, StateDigest, ViewSummary,
+};
+pub use dedup::{DEFAULT_DEDUP_CAPACITY, DedupCache, DedupStats, MessageHash};
+pub use dkg::{HintAnnouncement, KeySetup, KeySetupError};
+pub use events::{
+    EVENT_HISTORY, EventFilter, EventKind, ProtocolEvent, SUBSCRIPTION_CAPACITY, Subscription,
+};
+pub use evidence::{EquivocationEvidence, EvidenceError};
+pub use extensions::{
+    BlockExtension, ExtensionContext, ExtensionPolicy, MAX_EXTENSION_LEN, NoExtensions,
+};
+pub use governance::{
+    ConfigChange, GOVERNANCE_MIN_LEAD, Governance, GovernanceError, GovernedParams, Parameter,
+};
+pub use health::{DEFAULT_MAX_VIEW_LAG, NotReady, Readiness};
+pub use invariants::{InvariantLevel, InvariantViolation};
+pub use key_rotation::KeyRotationError;
+pub(crate) use leader_election::LeaderCache;
+pub use leader_election::{LeaderElection, LeaderProof};
+pub use leader_policy::{AuthorRotation, IndexOrder, LeaderPolicy, ReceiptOrder, TipContext};
+pub use link_auth::{
+    LINK_MAC_OVERHEAD, LinkMac, LinkSecurity, MacError, PeerBinding, TrustModelError,
+    VoteVerification,
+};
+pub use merkle::{MerkleProof, MerkleRoot, merkle_root};
+pub use metadata::{IdentityMetadata, MetadataRegistry};
+pub use migrations::{
+    Migration, MigrationError, MigrationPlan, MigrationRegistry, StoreKind, StoreSchema,
+};
+pub use payloads::{CborPayload, SignedTransfer, Transfer};
+pub use phase_policy::{LowLoadOnly, PaperPhase, PhaseContext, PhasePolicy};
 pub use process::*;
+pub use quorum::{QuorumConfig, QuorumConfigError};
+pub use rate_limit::{Admission, PeerRateStats, RateLimiter, RateLimits};
+pub use received::{RECEIVED_HISTORY, ReceivedMessages};
+#[cfg(unix)]
+pub use remote_signer::{MAX_SIGN_PAYLOAD, RemoteSigner, SignerService};
+pub use secret::SecretKey;
+pub use send_queue::{DEFAULT_SEND_QUEUE, PeerBacklog, SendQueueError, SendQueues};
+pub use sign_guard::{
+    FileStore, GUARD_FORMAT, GUARD_WINDOW, GuardError, GuardStore, MemoryStore, Positioned,
+    SignGuard, SignPosition, SignStream,
+};
+pub use signer::{SignError, WorkerSigner};
 pub use state_tracking::{PendingVotes, StateIndex};
+pub use storage_faults::{FaultCounts, FaultyStore, StorageFaults};
 pub use types::*;
+pub use verify_cache::{CachingVerifier, DEFAULT_VERIFY_CACHE_CAPACITY, VerifyCacheStats};
+pub use view_management::{
+    MAX_VIEW_BACKOFF, MAX_VIEW_CHANGES_PER_WINDOW, START_VIEW_WINDOW, ViewChurn,
+};
+pub use vote_diagnostics::{PendingCandidate, PendingVoteKind, PendingVotesSnapshot, VoteBlocker};
+pub use vote_wal::{FileWal, MemoryWal, VoteWal, WAL_FORMAT, WalError, WalStore};
 pub use voting::*;
+pub use wire::{WIRE_VERSION, WireError};
 
 pub trait Transaction:
     Sync + Clone + Eq + Ord + Hash + Valid + CanonicalDeserialize + CanonicalSerialize + Debug
 {
+    /// The last view whose blocks may include this transaction, or `None` if
+    /// it never expires
+    ///
+    /// Views are the chain's clock: a block's view is agreed on by everyone
+    /// who validates it, where local time is not. Expired transactions are
+    /// dropped from `ready_transactions` and transaction blocks of a later view
+    /// that include them are rejected.
+    fn expires_after(&self) -> Option<ViewNum> {
+        None
+    }
+
+    /// Whether this transaction may no longer be included in blocks of `view`
+    fn expired_at(&self, view: ViewNum) -> bool {
+        self.expires_after().is_some_and(|last| view > last)
+    }
+
+    /// The protocol parameter change this transaction proposes, if it is a
+    /// governance transaction; see `governance.rs`
+    fn config_change(&self) -> Option<ConfigChange> {
+        None
+    }
+
+    /// Whether this transaction may be included in a block of `view`
+    fn includable_at(&self, view: ViewNum) -> bool {
+        !self.expired_at(view)
+            && self
+                .config_change()
+                .is_none_or(|change| change.validate(view).is_ok())
+    }
 }
@@ -0,0 +1,324 @@
+//! Trusting authenticated links instead of checking every vote's signature
+//!
+//! Every vote carries its author's partial signature, and by default each
+//! one is checked on arrival: with n members voting on every block, that is
+//! the bulk of a node's verification work. Deployments whose transport
+//! already authenticates each link, with a Noise handshake or a MAC keyed per
+//! pair of members ([`LinkMac`]), know who sent a message without looking at
+//! its signature, and can run under [`VoteVerification::AuthenticatedLinks`]:
+//!
+//! - a vote that arrives directly from its author is recorded unchecked;
+//! - a vote relayed by someone else is checked as before, since the link only
+//!   vouches for the relayer;
+//! - once n-f votes are in, the aggregate is checked once, in place of the
+//!   n-f partial signatures it is made of. If it doesn't verify, the partial
+//!   signatures are checked one by one, the bad ones dropped, and the QC
+//!   waits for more votes.
+//!
+//! The partial signatures are still sent, since a QC is their aggregate and
+//! has to convince everyone, links or not. Only their checking is deferred.
+//!
+//! This is only sound if the transport's sender identities can't be forged.
+//! A transport says whether they can with
+//! [`NetworkTransport::link_security`](crate::transport::NetworkTransport::link_security),
+//! and [`MorpheusProcess::trust_links`] refuses to switch a process over for
+//! a transport that doesn't authenticate. A process trusting links that is
+//! polled through an unauthenticated transport anyway checks every vote.
+//!
+//! A transport that authenticates its own peer ids, as libp2p does, can tell
+//! which member is behind each one from a [`PeerBinding`]: the member signs
+//! the peer id it runs under, and whoever holds the committee's keys can
+//! check it. Messages from a peer id are then attributed to the member that
+//! bound it, whatever author they claim.
+
+use std::fmt;
+
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::*;
+
+/// Whether a transport's sender identities can be trusted
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LinkSecurity {
+    /// The sender is whatever the other end claims
+    #[default]
+    Unauthenticated,
+    /// Each link is authenticated, so a message from a member came from it
+    Authenticated,
+}
+
+/// How received votes are checked
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VoteVerification {
+    /// Every vote's partial signature, on arrival
+    #[default]
+    EveryVote,
+    /// Votes from their author over an authenticated link go unchecked until
+    /// their aggregate is, see the module docs
+    AuthenticatedLinks,
+}
+
+/// Why a process can't trust the links it runs over
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TrustModelError {
+    /// [`VoteVerification::AuthenticatedLinks`] over links anyone can claim
+    /// to send on
+    UnauthenticatedLinks,
+}
+
+impl fmt::Display for TrustModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrustModelError::UnauthenticatedLinks => write!(
+                f,
+                "trusting links for votes needs a transport that authenticates senders"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TrustModelError {}
+
+impl VoteVerification {
+    /// Whether this mode is safe over links with `security`
+    pub fn validate(self, security: LinkSecurity) -> Result<(), TrustModelError> {
+        match (self, security) {
+            (VoteVerification::AuthenticatedLinks, LinkSecurity::Unauthenticated) => {
+                Err(TrustModelError::UnauthenticatedLinks)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether a vote by `author` that `sender` delivered can skip its
+    /// signature check
+    pub fn trusts(self, sender: &Identity, author: &Identity) -> bool {
+        self == VoteVerification::AuthenticatedLinks && sender == author
+    }
+}
+
+impl<Tr: Transaction> MorpheusProcess<Tr> {
+    /// Checks received votes as `verification` says, over links with
+    /// `security`
+    pub fn trust_links(
+        &mut self,
+        verification: VoteVerification,
+        security: LinkSecurity,
+    ) -> Result<(), TrustModelError> {
+        verification.validate(security)?;
+        self.vote_verification = verification;
+        Ok(())
+    }
+
+    /// Drops the votes for `data` whose signatures don't verify, after their
+    /// aggregate didn't
+    pub(crate) fn evict_invalid_votes(&mut self, data: &VoteData) {
+        let verifier = self.verifier();
+        let Some(votes) = self.vote_tracker.votes.get(data) else {
+            return;
+        };
+        let invalid = votes
+            .iter()
+            .filter(|(_, vote)| !vote.valid_signature(verifier))
+            .map(|(author, _)| author.clone())
+            .collect::<Vec<_>>();
+        let votes = self.vote_tracker.votes.get_mut(data).unwrap();
+        for author in invalid {
+            tracing::error!(
+                target: "invalid_vote",
+                process_id = ?self.id,
+                vote_data = ?data,
+                author = ?author,
+            );
+            votes.remove(&author);
+        }
+    }
+}
+
+/// A transport-level peer id, signed by the member that runs under it
+#[derive(
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Debug,
+    Serialize,
+    Deserialize,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+)]
+pub struct PeerBinding {
+    /// The peer id's bytes, e.g. a libp2p `PeerId::to_bytes`
+    pub peer: Vec<u8>,
+}
+
+impl SigningPayload for PeerBinding {
+    const DOMAIN: &'static [u8] = b"morpheus-sig-peer-binding-v1";
+}
+
+impl Signed<PeerBinding> {
+    /// The member `peer` runs, if this binds it and is validly signed
+    ///
+    /// Checked against the author's original key, which is what
+    /// [`MorpheusProcess::bind_peer`] signs with.
+    pub fn member_of(&self, peer: &[u8], verifier: &(impl Verifier + ?Sized)) -> Option<Identity> {
+        (self.data.peer == peer && self.valid_signature(verifier)).then(|| self.author.clone())
+    }
+}
+
+impl<Tr: Transaction> MorpheusProcess<Tr> {
+    /// Signs `peer` as the peer id we run under
+    ///
+    /// Not checked against the sign guard: a binding conflicts with nothing,
+    /// and a process that restarts under a new peer id signs a new one.
+    pub fn bind_peer(&self, peer: &[u8]) -> Result<Signed<PeerBinding>, SignError> {
+        let data = PeerBinding {
+            peer: peer.to_vec(),
+        };
+        let buf = signing_bytes(&self.kb.chain_id, &data);
+        let signature = match &self.signer {
+            Some(signer) => signer.sign(&buf)?,
+            None => hints::sign(
+                self.kb.me_sec_key.expose().ok_or(SignError::MissingKey)?,
+                &buf,
+            ),
+        };
+        Ok(Signed {
+            data,
+            author: self.id.clone(),
+            signature,
+        })
+    }
+}
+
+/// Domain separator for link MAC keys
+const LINK_MAC_DOMAIN: &[u8] = b"morpheus-link-mac-v1";
+
+/// Bytes a MAC adds to each frame: the counter before it and the tag after
+pub const LINK_MAC_OVERHEAD: usize = 8 + 32;
+
+/// Why a frame didn't pass [`LinkMac::open`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MacError {
+    /// Shorter than a counter and a tag
+    Truncated,
+    /// Not sealed with this link's key, or changed since
+    BadTag,
+    /// A counter at or below one already opened
+    Replayed { counter: u64 },
+}
+
+impl fmt::Display for MacError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MacError::Truncated => write!(f, "frame too short to carry a MAC"),
+            MacError::BadTag => write!(f, "MAC doesn't match"),
+            MacError::Replayed { counter } => write!(f, "frame {} was replayed", counter),
+        }
+    }
+}
+
+impl std::error::Error for MacError {}
+
+/// HMAC-SHA256 over one direction of a point-to-point link
+///
+/// Both ends build it from a secret they share, from a Noise handshake or
+/// configured out of band: the sender with [`LinkMac::new`]`(secret, chain,
+/// sender, receiver)` to seal, the receiver with the same arguments to open.
+/// The two directions get different keys, so a frame can't be reflected
+/// back at its sender, and a frame is `counter: u64 LE | payload | tag` with
+/// counters increasing, so it can't be replayed either.
+#[derive(Clone)]
+pub struct LinkMac {
+    key: [u8; 32],
+    sent: u64,
+    opened: Option<u64>,
+}
+
+impl fmt::Debug for LinkMac {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LinkMac")
+            .field("sent", &self.sent)
+            .field("opened", &self.opened)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LinkMac {
+    pub fn new(secret: &[u8; 32], chain: &ChainId, from: &Identity, to: &Identity) -> Self {
+        let key = hmac(
+            secret,
+            &[
+                LINK_MAC_DOMAIN,
+                &chain.0,
+                &from.0.to_le_bytes(),
+                &to.0.to_le_bytes(),
+            ],
+        );
+        LinkMac {
+            key,
+            sent: 0,
+            opened: None,
+        }
+    }
+
+    /// `payload` framed with the next counter and its tag
+    pub fn seal(&mut self, payload: &[u8]) -> Vec<u8> {
+        let counter = self.sent.to_le_bytes();
+        self.sent += 1;
+        let mut frame = Vec::with_capacity(payload.len() + LINK_MAC_OVERHEAD);
+        frame.extend_from_slice(&counter);
+        frame.extend_from_slice(payload);
+        frame.extend_from_slice(&hmac(&self.key, &[&counter, payload]));
+        frame
+    }
+
+    /// The payload of a frame sealed on the other end, if its tag matches and
+    /// it is newer than every frame opened before
+    pub fn open<'a>(&mut self, frame: &'a [u8]) -> Result<&'a [u8], MacError> {
+        if frame.len() < LINK_MAC_OVERHEAD {
+            return Err(MacError::Truncated);
+        }
+        let (counter_bytes, rest) = frame.split_at(8);
+        let (payload, tag) = rest.split_at(rest.len() - 32);
+        let expected = hmac(&self.key, &[counter_bytes, payload]);
+        // compared without stopping at the first difference
+        if expected
+            .iter()
+            .zip(tag)
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            != 0
+        {
+            return Err(MacError::BadTag);
+        }
+        let counter = u64::from_le_bytes(counter_bytes.try_into().unwrap());
+        if self.opened.is_some_and(|opened| counter <= opened) {
+            return Err(MacError::Replayed { counter });
+        }
+        self.opened = Some(counter);
+        Ok(payload)
+    }
+}
+
+/// HMAC-SHA256 of the concatenation of `parts`
+fn hmac(key: &[u8; 32], parts: &[&[u8]]) -> [u8; 32] {
+    let mut inner_pad = [0x36; 64];
+    let mut outer_pad = [0x5c; 64];
+    for (i, byte) in key.iter().enumerate() {
+        inner_pad[i] ^= byte;
+        outer_pad[i] ^= byte;
+    }
+    let mut inner = Sha256::new().chain_update(inner_pad);
+    for part in parts {
+        inner.update(part);
+    }
+    Sha256::new()
+        .chain_update(outer_pad)
+        .chain_update(inner.finalize())
+        .finalize()
+        .into()
+}
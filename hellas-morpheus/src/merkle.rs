@@ -0,0 +1,202 @@
+//! Merkle commitments to the transactions of a block
+//!
+//! A transaction block carries the Merkle root of its transactions, and its
+//! content hash covers that root rather than the transactions themselves. A
+//! light client holding a block's header, a QC for its key and a
+//! [`MerkleProof`] can therefore check that one transaction was included
+//! without downloading the others: the QC vouches for the key, the key's hash
+//! for the header, the header's root for the proof.
+//!
+//! The tree follows RFC 9162: leaves and inner nodes are hashed with
+//! different prefixes, so a leaf can't pass for an inner node, and a level
+//! with an odd number of nodes is split at the largest power of two rather
+//! than padded by duplicating its last node, so no two transaction lists
+//! share a root.
+
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::*;
+
+/// Domain separator, so Merkle hashes can't collide with other hashes
+const MERKLE_DOMAIN: &[u8] = b"morpheus-merkle-v1";
+
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+/// Root of the Merkle tree over a block's transactions
+#[derive(
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Debug,
+    Serialize,
+    Deserialize,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+)]
+pub struct MerkleRoot(pub [u8; 32]);
+
+pub(crate) fn leaf_hash<Tr: CanonicalSerialize>(tx: &Tr) -> [u8; 32] {
+    let mut buf = Vec::new();
+    tx.serialize_compressed(&mut buf).unwrap();
+    Sha256::new()
+        .chain_update(MERKLE_DOMAIN)
+        .chain_update([LEAF_PREFIX])
+        .chain_update(&buf)
+        .finalize()
+        .into()
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(MERKLE_DOMAIN)
+        .chain_update([NODE_PREFIX])
+        .chain_update(left)
+        .chain_update(right)
+        .finalize()
+        .into()
+}
+
+/// Where a tree of `n > 1` leaves is split: the largest power of two below `n`
+fn split(n: usize) -> usize {
+    1 << (usize::BITS - 1 - (n - 1).leading_zeros())
+}
+
+fn subtree_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    match leaves {
+        [] => Sha256::new().chain_update(MERKLE_DOMAIN).finalize().into(),
+        [leaf] => *leaf,
+        _ => {
+            let k = split(leaves.len());
+            node_hash(&subtree_root(&leaves[..k]), &subtree_root(&leaves[k..]))
+        }
+    }
+}
+
+/// The root committing to `transactions`, in order
+pub fn merkle_root<Tr: CanonicalSerialize>(transactions: &[Tr]) -> MerkleRoot {
+    let leaves: Vec<_> = transactions.iter().map(leaf_hash).collect();
+    MerkleRoot(subtree_root(&leaves))
+}
+
+/// Proof that a transaction is at `index` in a list of `len` with a given root
+#[derive(
+    Clone,
+    PartialEq,
+    Eq,
+    Hash,
+    Debug,
+    Serialize,
+    Deserialize,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+)]
+pub struct MerkleProof {
+    pub index: u64,
+    pub len: u64,
+    /// Hashes of the sibling subtrees on the way up, nearest the leaf first
+    pub siblings: Vec<[u8; 32]>,
+}
+
+impl MerkleProof {
+    /// The proof for the transaction at `index`, if there is one
+    pub fn new<Tr: CanonicalSerialize>(transactions: &[Tr], index: usize) -> Option<Self> {
+        if index >= transactions.len() {
+            return None;
+        }
+        let leaves: Vec<_> = transactions.iter().map(leaf_hash).collect();
+        let mut siblings = Vec::new();
+        let (mut leaves, mut index_in) = (leaves.as_slice(), index);
+        while leaves.len() > 1 {
+            let k = split(leaves.len());
+            if index_in < k {
+                siblings.push(subtree_root(&leaves[k..]));
+                leaves = &leaves[..k];
+            } else {
+                siblings.push(subtree_root(&leaves[..k]));
+                leaves = &leaves[k..];
+                index_in -= k;
+            }
+        }
+        siblings.reverse();
+        Some(MerkleProof {
+            index: index as u64,
+            len: transactions.len() as u64,
+            siblings,
+        })
+    }
+
+    /// Whether this shows `tx` at `self.index` under `root`
+    pub fn verify<Tr: CanonicalSerialize>(&self, tx: &Tr, root: &MerkleRoot) -> bool {
+        if self.index >= self.len {
+            return false;
+        }
+        // RFC 9162, section 2.1.3.2
+        let (mut fn_, mut sn) = (self.index, self.len - 1);
+        let mut hash = leaf_hash(tx);
+        for sibling in &self.siblings {
+            if sn == 0 {
+                return false;
+            }
+            if fn_ & 1 == 1 || fn_ == sn {
+                hash = node_hash(sibling, &hash);
+                while fn_ & 1 == 0 && fn_ != 0 {
+                    fn_ >>= 1;
+                    sn >>= 1;
+                }
+            } else {
+                hash = node_hash(&hash, sibling);
+            }
+            fn_ >>= 1;
+            sn >>= 1;
+        }
+        sn == 0 && hash == root.0
+    }
+}
+
+impl<Tr: CanonicalSerialize> BlockData<Tr> {
+    /// Transaction block data, committing to `transactions`
+    pub fn tr(transactions: Vec<Tr>) -> Self {
+        BlockData::Tr {
+            root: merkle_root(&transactions),
+            transactions,
+        }
+    }
+}
+
+impl<Tr: Transaction> Block<Tr> {
+    /// The root of this block's transactions, if it is a transaction block
+    pub fn tx_root(&self) -> Option<&MerkleRoot> {
+        match &self.data {
+            BlockData::Tr { root, .. } => Some(root),
+            _ => None,
+        }
+    }
+
+    /// Proof that the transaction at `index` is in this block
+    pub fn tx_proof(&self, index: usize) -> Option<MerkleProof> {
+        match &self.data {
+            BlockData::Tr { transactions, .. } => MerkleProof::new(transactions, index),
+            _ => None,
+        }
+    }
+
+    /// This block without its transactions, which still has its content hash
+    ///
+    /// What a light client needs to check a [`MerkleProof`] against a QC.
+    /// It is not a valid block: processes check the root against the
+    /// transactions it came with.
+    pub fn header(&self) -> Self {
+        let mut header = self.clone();
+        if let BlockData::Tr { transactions, .. } = &mut header.data {
+            transactions.clear();
+        }
+        header
+    }
+}
@@ -1,7 +1,5 @@
 use std::sync::Arc;
 
-use ark_serialize::CanonicalSerialize;
-
 use crate::{format::format_message, *};
 
 impl<Tr: Transaction> MorpheusProcess<Tr> {
@@ -20,22 +18,44 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
         to_send.push(message);
     }
 
-    #[tracing::instrument(skip(self, sender, to_send), fields(process_id = ?self.id))]
+    #[tracing::instrument(
+        skip(self, sender, to_send),
+        fields(process_id = ?self.id, correlation = %message.correlation_id())
+    )]
     pub fn process_message(
         &mut self,
         message: Message<Tr>,
         sender: Identity,
         to_send: &mut Vec<(Message<Tr>, Option<Identity>)>,
     ) -> bool {
+        if !self.admit_from(&sender, message.kind()) {
+            // transition: rate-limited
+            // not recorded as received, so it is handled if it comes again
+            return false;
+        }
+
         // Check if we've seen this message before (duplicate detection)
         if cfg!(debug_assertions) {
             if self.received_messages.contains(&message) {
-                tracing::error!(
-                    target: "duplicate_message",
-                    sender = ?sender,
-                    full_message = format_message(&message, true),
-                    "Ignoring duplicate message: why did we receive it?"
-                );
+                // a block we fetched can still arrive by its broadcast, and
+                // everyone holding it answers our request
+                let fetched = match &message {
+                    Message::Block(block) | Message::BlockResponse(block) => {
+                        self.requested_blocks.contains(&block.data.key)
+                    }
+                    _ => false,
+                };
+                if fetched {
+                    return false;
+                }
+                crate::tracing_setup::in_target("duplicate_message", || {
+                    tracing::error!(
+                        target: "duplicate_message",
+                        sender = ?sender,
+                        full_message = format_message(&message, true),
+                        "Ignoring duplicate message: why did we receive it?"
+                    )
+                });
                 return false;
             }
         }
@@ -47,6 +67,7 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
         match message {
             Message::Block(block) => {
                 if let Err(error) = self.block_valid(&block) {
+                    // transition: block-invalid
                     tracing::error!(
                         target: "invalid_block",
                         process_id = ?self.id,
@@ -55,20 +76,38 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
                     );
                     return false;
                 }
-                self.try_vote(
-                    0,
-                    &block.data.key,
-                    Some(block.data.key.author.clone().expect("validated")),
-                    to_send,
-                );
-                tracing::debug!(
-                    target: "valid_block",
-                    block_key = ?block.data.key,
-                );
-                self.record_block(&block);
+                let missing = self.missing_ancestors(&block);
+                if !missing.is_empty() && block.author != self.id {
+                    // transition: block-awaiting-ancestors
+                    self.await_ancestors(block, missing, to_send);
+                    return false;
+                }
+                // transition: block-accepted
+                self.accept_block(&block, to_send);
+            }
+            Message::GetBlock(key) => {
+                let Some(block) = self.index.blocks.get(&key).cloned() else {
+                    // transition: get-block-unknown
+                    return false;
+                };
+                // transition: get-block-served
+                to_send.push((Message::BlockResponse(block), Some(sender)));
+            }
+            Message::BlockResponse(block) => {
+                if !self.requested_blocks.contains(&block.data.key)
+                    || self.index.blocks.contains_key(&block.data.key)
+                {
+                    // transition: block-response-unneeded
+                    return false;
+                }
+                // transition: block-response-accepted
+                return self.process_message(Message::Block(block), sender, to_send);
             }
             Message::NewVote(vote_data) => {
-                if !vote_data.valid_signature(&self.kb) {
+                // over authenticated links, checked as part of the aggregate
+                let trusted = self.vote_verification.trusts(&sender, &vote_data.author);
+                if !trusted && !vote_data.valid_signature(self.verifier()) {
+                    // transition: vote-invalid
                     tracing::error!(
                         target: "invalid_vote",
                         process_id = ?self.id,
@@ -76,10 +115,12 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
                     );
                     return false;
                 }
+                // transition: vote-recorded
                 self.record_vote(&vote_data, to_send);
             }
             Message::QC(qc) => {
-                if !qc.valid_signature(&self.kb, self.n - self.f) {
+                if !qc.valid_signature(self.verifier(), self.n - self.f) {
+                    // transition: qc-invalid
                     tracing::error!(
                         target: "invalid_qc",
                         process_id = ?self.id,
@@ -87,8 +128,10 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
                     );
                     return false;
                 }
+                // transition: qc-recorded
                 self.record_qc(qc);
                 if self.index.max_view.0 > self.view_i {
+                    // transition: qc-advances-view
                     self.end_view(
                         Message::QC(self.index.max_view.1.clone()),
                         self.index.max_view.0,
@@ -97,7 +140,8 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
                 }
             }
             Message::EndView(end_view) => {
-                if !end_view.valid_signature(&self.kb) {
+                if !end_view.valid_signature(self.verifier()) {
+                    // transition: end-view-invalid
                     tracing::error!(
                         target: "invalid_end_view",
                         process_id = ?self.id,
@@ -106,8 +150,21 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
                     return false;
                 }
                 match self.end_views.record_vote(end_view.clone()) {
+                    // transition: end-view-recorded
                     Ok(num_votes) => {
-                        if end_view.data >= self.view_i && num_votes >= self.f as usize + 1 {
+                        let cached = (end_view.data < self.view_i && end_view.author != self.id)
+                            .then(|| self.end_view_certs.get(&end_view.data).cloned())
+                            .flatten();
+                        if let Some(cert) = cached {
+                            // transition: end-view-serves-cert
+                            // the sender is still complaining about a view we
+                            // have left, so it missed the certificate
+                            self.send_msg(
+                                to_send,
+                                (Message::EndViewCert(cert), Some(end_view.author.clone())),
+                            );
+                        } else if end_view.data >= self.view_i && num_votes >= self.f as usize + 1 {
+                            // transition: end-view-cert-formed
                             let votes_now = self
                                 .end_views
                                 .votes
@@ -117,8 +174,7 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
                                 .map(|v| (v.author.0 as usize - 1, v.signature.clone()))
                                 .collect::<Vec<_>>();
                             let agg = self.kb.hints_setup.aggregator();
-                            let mut data = Vec::new();
-                            end_view.data.serialize_compressed(&mut data).unwrap();
+                            let data = signing_bytes(&self.kb.chain_id, &end_view.data);
                             let signed = hints::sign_aggregate(
                                 &agg,
                                 hints::F::from((self.f + 1) as u64),
@@ -126,23 +182,23 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
                                 &data,
                             )
                             .unwrap();
-                            self.send_msg(
-                                to_send,
-                                (
-                                    Message::EndViewCert(Arc::new(ThreshSigned {
-                                        data: end_view.data,
-                                        signature: signed,
-                                    })),
-                                    None,
-                                ),
-                            );
+                            // handled like a received certificate, which
+                            // caches it and relays it to everyone exactly once
+                            let cert = Message::EndViewCert(Arc::new(ThreshSigned {
+                                data: end_view.data,
+                                signature: signed,
+                                signers: self.end_views.signers(&end_view.data),
+                            }));
+                            self.process_message(cert, self.id.clone(), to_send);
                         }
                     }
+                    // transition: end-view-duplicate
                     Err(Duplicate) => return false,
                 }
             }
             Message::EndViewCert(end_view_cert) => {
-                if !end_view_cert.valid_signature(&self.kb, self.f + 1) {
+                if !end_view_cert.valid_signature(self.verifier(), self.f + 1) {
+                    // transition: end-view-cert-invalid
                     tracing::error!(
                         target: "invalid_end_view_cert",
                         process_id = ?self.id,
@@ -150,13 +206,21 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
                     );
                     return false;
                 }
+                if self.end_view_certs.contains_key(&end_view_cert.data) {
+                    // transition: end-view-cert-duplicate
+                    return false;
+                }
+                self.cache_end_view_cert(end_view_cert.clone());
+                // transition: end-view-cert-stale
                 let view = end_view_cert.data.incr();
                 if view >= self.view_i {
+                    // transition: end-view-cert-advances-view
                     self.end_view(Message::EndViewCert(end_view_cert), view, to_send);
                 }
             }
             Message::StartView(start_view) => {
-                if !start_view.valid_signature(&self.kb) {
+                if !start_view.valid_signature_at(self.verifier(), start_view.data.view) {
+                    // transition: start-view-invalid
                     tracing::error!(
                         target: "invalid_start_view",
                         process_id = ?self.id,
@@ -165,24 +229,74 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
                     return false;
                 }
                 if start_view.data.qc.data.z != 1 {
+                    // transition: start-view-not-1qc
+                    return false;
+                }
+                let view = start_view.data.view;
+                if view < self.view_i {
+                    // transition: start-view-stale
+                    return false;
+                }
+                if self.start_views.get(&view).is_some_and(|received| {
+                    received.iter().any(|sv| sv.author == start_view.author)
+                }) {
+                    // transition: start-view-duplicate
+                    return false;
+                }
+                if let Some(progress) = &start_view.data.progress {
+                    if self.is_behind(progress) {
+                        tracing::info!(
+                            target: "peer_ahead",
+                            peer = ?start_view.author,
+                            peer_max_view = ?progress.max_view,
+                            peer_finalized_head = ?progress.finalized_head,
+                        );
+                        self.emit(ProtocolEvent::PeerAhead {
+                            peer: start_view.author.clone(),
+                            progress: progress.clone(),
+                        });
+                    }
+                    self.peer_progress
+                        .insert(start_view.author.clone(), progress.clone());
+                }
+                if view.0 > self.view_i.0 + START_VIEW_WINDOW {
+                    // transition: start-view-too-far-ahead
+                    tracing::warn!(
+                        target: "start_view_too_far_ahead",
+                        process_id = ?self.id,
+                        author = ?start_view.author,
+                        view = ?view,
+                        our_view = ?self.view_i,
+                    );
                     return false;
                 }
+                // transition: start-view-recorded
                 self.start_views
                     .entry(start_view.data.view)
                     .or_insert(Vec::new())
                     .push(start_view);
             }
+            Message::KeyRotation(rotation) => {
+                if !self.key_rotation_valid(&rotation) {
+                    // transition: key-rotation-invalid
+                    tracing::error!(
+                        target: "invalid_key_rotation",
+                        process_id = ?self.id,
+                        author = ?rotation.author,
+                        effective = ?rotation.data.effective,
+                    );
+                    return false;
+                }
+                // transition: key-rotation-recorded
+                self.kb
+                    .rotations
+                    .entry(rotation.author.clone())
+                    .or_default()
+                    .insert(rotation.data.effective, rotation.data.new_key.clone());
+            }
         }
 
-        if cfg!(debug_assertions) {
-            let violations = self.check_invariants();
-            assert!(
-                violations.is_empty(),
-                "Process {} has invariant violations: {:?}",
-                self.id.0,
-                violations
-            );
-        }
+        self.assert_invariants(InvariantLevel::Cheap);
 
         // Re-evaluate any pending voting decisions
         self.reevaluate_pending_votes(to_send);
@@ -0,0 +1,42 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::format::format_identity;
+use crate::*;
+
+/// Human-readable information about a process
+///
+/// This is purely informational: it shows up in logs, snapshots and tooling,
+/// but the protocol never looks at it.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct IdentityMetadata {
+    /// Short name, like "node-eu-1"
+    pub name: Option<String>,
+    pub region: Option<String>,
+    pub operator_contact: Option<String>,
+}
+
+/// Metadata for the known identities, loaded alongside their keys
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MetadataRegistry {
+    #[serde(with = "serde_json_any_key::any_key_map")]
+    pub entries: BTreeMap<Identity, IdentityMetadata>,
+}
+
+impl MetadataRegistry {
+    pub fn insert(&mut self, id: Identity, metadata: IdentityMetadata) {
+        self.entries.insert(id, metadata);
+    }
+
+    pub fn get(&self, id: &Identity) -> Option<&IdentityMetadata> {
+        self.entries.get(id)
+    }
+
+    /// The configured name of `id`, falling back to the concise `p<n>` form
+    pub fn display_name(&self, id: &Identity) -> String {
+        self.get(id)
+            .and_then(|metadata| metadata.name.clone())
+            .unwrap_or_else(|| format_identity(id))
+    }
+}
@@ -0,0 +1,279 @@
+//! Upgrading persisted state written by older builds
+//!
+//! Everything a process keeps on disk carries a format version: the sign
+//! guard's state has a `version` field, and the vote log starts with a
+//! header naming its version. Files written before stores were stamped are
+//! recognised by their shape and count as version 0. A [`StoreSchema`]
+//! describes one store: how to tell which version some bytes are in, the
+//! version this build writes, and a [`Migration`] from each older version
+//! to the next. The [`MigrationRegistry`] holds the schema of every store.
+//!
+//! Stores run their migrations when they are opened, one version at a time,
+//! and write the result back before they are used, so an upgraded node
+//! picks up where the old build left off. [`MigrationRegistry::plan`] and
+//! [`MigrationRegistry::plan_file`] only report which steps would run, for a
+//! dry run before an upgrade. Migrations only go forward: a store written by
+//! a newer build than this one is refused rather than guessed at.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// A persisted store whose format is versioned
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum StoreKind {
+    /// The [`SignGuard`](crate::SignGuard)'s state
+    SignGuard,
+    /// The [`VoteWal`](crate::VoteWal)'s log
+    VoteWal,
+}
+
+impl fmt::Display for StoreKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreKind::SignGuard => write!(f, "sign guard"),
+            StoreKind::VoteWal => write!(f, "vote log"),
+        }
+    }
+}
+
+/// Rewrites a store from version `from` to `from + 1`
+#[derive(Clone, Copy, Debug)]
+pub struct Migration {
+    pub from: u32,
+    pub description: &'static str,
+    pub apply: fn(&[u8]) -> Result<Vec<u8>, String>,
+}
+
+/// The versions of one store, and how to move between them
+#[derive(Clone, Debug)]
+pub struct StoreSchema {
+    /// The version this build writes
+    pub current: u32,
+    /// Which version a store's bytes are in; never called on empty bytes,
+    /// which hold nothing to migrate
+    pub detect: fn(&[u8]) -> Result<u32, String>,
+    pub migrations: Vec<Migration>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MigrationError {
+    Io(String),
+    /// The bytes aren't any version of the store
+    Unrecognized {
+        store: StoreKind,
+        reason: String,
+    },
+    /// Written by a newer build than this one
+    TooNew {
+        store: StoreKind,
+        found: u32,
+        supported: u32,
+    },
+    /// No migration is registered from this version
+    MissingStep {
+        store: StoreKind,
+        from: u32,
+    },
+    Failed {
+        store: StoreKind,
+        from: u32,
+        reason: String,
+    },
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::Io(e) => write!(f, "reading a store failed: {}", e),
+            MigrationError::Unrecognized { store, reason } => {
+                write!(f, "not a {} store: {}", store, reason)
+            }
+            MigrationError::TooNew {
+                store,
+                found,
+                supported,
+            } => write!(
+                f,
+                "{} is at version {}, this build only knows up to {}",
+                store, found, supported
+            ),
+            MigrationError::MissingStep { store, from } => {
+                write!(f, "no migration for the {} from version {}", store, from)
+            }
+            MigrationError::Failed {
+                store,
+                from,
+                reason,
+            } => write!(
+                f,
+                "migrating the {} from version {} failed: {}",
+                store, from, reason
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+impl From<io::Error> for MigrationError {
+    fn from(e: io::Error) -> Self {
+        MigrationError::Io(e.to_string())
+    }
+}
+
+/// The migrations that bring a store up to date, in the order they run
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MigrationPlan {
+    pub store: StoreKind,
+    pub from: u32,
+    pub to: u32,
+    /// Each step's description
+    pub steps: Vec<String>,
+}
+
+impl MigrationPlan {
+    /// Whether the store is already up to date
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+}
+
+impl fmt::Display for MigrationPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "{} is up to date at version {}", self.store, self.to);
+        }
+        write!(f, "{}: version {} to {}", self.store, self.from, self.to)?;
+        for (version, step) in (self.from..).zip(&self.steps) {
+            write!(f, "\n  {} -> {}: {}", version, version + 1, step)?;
+        }
+        Ok(())
+    }
+}
+
+/// The schema of every persisted store
+#[derive(Clone, Debug, Default)]
+pub struct MigrationRegistry {
+    schemas: BTreeMap<StoreKind, StoreSchema>,
+}
+
+impl MigrationRegistry {
+    /// The stores of this crate, with their migrations
+    pub fn builtin() -> Self {
+        let mut registry = MigrationRegistry::default();
+        registry.register(StoreKind::SignGuard, crate::sign_guard::schema());
+        registry.register(StoreKind::VoteWal, crate::vote_wal::schema());
+        registry
+    }
+
+    /// Adds `store`'s schema, replacing any it had
+    pub fn register(&mut self, store: StoreKind, schema: StoreSchema) {
+        self.schemas.insert(store, schema);
+    }
+
+    pub fn schema(&self, store: StoreKind) -> Option<&StoreSchema> {
+        self.schemas.get(&store)
+    }
+
+    /// The version `store` is written in by this build
+    pub fn current_version(&self, store: StoreKind) -> Option<u32> {
+        self.schema(store).map(|schema| schema.current)
+    }
+
+    /// Which version `bytes` are in, treating empty bytes as up to date
+    pub fn detect(&self, store: StoreKind, bytes: &[u8]) -> Result<u32, MigrationError> {
+        let schema = self.require(store)?;
+        if bytes.is_empty() {
+            return Ok(schema.current);
+        }
+        let found = (schema.detect)(bytes)
+            .map_err(|reason| MigrationError::Unrecognized { store, reason })?;
+        if found > schema.current {
+            return Err(MigrationError::TooNew {
+                store,
+                found,
+                supported: schema.current,
+            });
+        }
+        Ok(found)
+    }
+
+    /// What migrating `bytes` would do, without doing it
+    pub fn plan(&self, store: StoreKind, bytes: &[u8]) -> Result<MigrationPlan, MigrationError> {
+        let from = self.detect(store, bytes)?;
+        let to = self.require(store)?.current;
+        let steps = (from..to)
+            .map(|version| Ok(self.step(store, version)?.description.to_string()))
+            .collect::<Result<_, MigrationError>>()?;
+        Ok(MigrationPlan {
+            store,
+            from,
+            to,
+            steps,
+        })
+    }
+
+    /// [`plan`](Self::plan) for the store in the file at `path`; a file that
+    /// doesn't exist yet is up to date
+    pub fn plan_file(
+        &self,
+        store: StoreKind,
+        path: &Path,
+    ) -> Result<MigrationPlan, MigrationError> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        self.plan(store, &bytes)
+    }
+
+    /// `bytes` brought up to this build's version, and what that took
+    pub fn migrate(
+        &self,
+        store: StoreKind,
+        mut bytes: Vec<u8>,
+    ) -> Result<(Vec<u8>, MigrationPlan), MigrationError> {
+        let plan = self.plan(store, &bytes)?;
+        for version in plan.from..plan.to {
+            bytes = (self.step(store, version)?.apply)(&bytes).map_err(|reason| {
+                MigrationError::Failed {
+                    store,
+                    from: version,
+                    reason,
+                }
+            })?;
+        }
+        if !plan.is_empty() {
+            tracing::info!(
+                target: "migrations",
+                %store,
+                from = plan.from,
+                to = plan.to,
+                "Migrated store",
+            );
+        }
+        Ok((bytes, plan))
+    }
+
+    fn require(&self, store: StoreKind) -> Result<&StoreSchema, MigrationError> {
+        self.schema(store)
+            .ok_or_else(|| MigrationError::Unrecognized {
+                store,
+                reason: "no schema is registered".to_string(),
+            })
+    }
+
+    fn step(&self, store: StoreKind, from: u32) -> Result<&Migration, MigrationError> {
+        self.require(store)?
+            .migrations
+            .iter()
+            .find(|migration| migration.from == from)
+            .ok_or(MigrationError::MissingStep { store, from })
+    }
+}
@@ -0,0 +1,692 @@
+//! Running processes on muchin's TCP model
+//!
+//! The harness delivers messages by moving them between queues, so it never
+//! exercises the path a node's messages take through an action-based IO
+//! stack. [`MorpheusNodeState`] is a muchin pure model that owns a
+//! `MorpheusProcess` and carries its messages over muchin's `TcpServerState`
+//! and `TcpClientState` models, so a cluster of them can run inside muchin's
+//! `Runner`, one instance per member, and anything the two frameworks
+//! disagree on (timeouts, framing, ordering of callbacks) shows up as a
+//! failing run.
+//!
+//! Every member listens on its own address and dials the members with lower
+//! ids, so each pair shares exactly one connection. A connection carries
+//! frames of `len: u32 BE | payload`: first a hello whose payload is the
+//! dialer's `Identity` as a `u32 BE`, then messages in their wire encoding.
+//! Neither end authenticates the other, so the process sees the links as
+//! unauthenticated.
+//!
+//! On each tick the model reads muchin's clock, measured from the node's
+//! first tick, hands the process what arrived through
+//! [`MorpheusProcess::poll_transport`] and
+//! [`MorpheusProcess::tick_transport`], and queues what it sent on the
+//! connections it is addressed to. A message for a member we have no
+//! connection to is dropped, as a lossy network would.
+//!
+//! Only built with the `muchin` feature.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use muchin::automaton::{
+    Action, ActionKind, Dispatcher, ModelState, Objects, PureModel, RegisterModel, Runner,
+    RunnerBuilder, State, Timeout, Uid,
+};
+use muchin::callback;
+use muchin::models::pure::net::tcp::{action::TcpAction, state::TcpState};
+use muchin::models::pure::net::tcp_client::{action::TcpClientAction, state::TcpClientState};
+use muchin::models::pure::net::tcp_server::{action::TcpServerAction, state::TcpServerState};
+use muchin::models::pure::time::model::{get_current_time, update_time};
+use muchin::models::pure::time::state::TimeState;
+use muchin_model_state_derive::ModelState;
+use serde::{Deserialize, Serialize};
+use type_uuid::TypeUuid;
+
+use crate::test_harness::TestTransaction;
+use crate::transport::NetworkTransport;
+use crate::*;
+
+/// The largest frame accepted from a peer, in bytes
+pub const MAX_FRAME_LEN: usize = 16 << 20;
+
+/// How many bytes each receive asks for; fewer are returned on timeout
+const RECV_CHUNK: usize = 64 << 10;
+
+#[derive(Clone, Debug)]
+pub struct MorpheusNodeConfig {
+    pub listen_address: String,
+    /// Every other member's listen address
+    pub peers: BTreeMap<Identity, String>,
+    /// How long each tick waits for IO, in milliseconds
+    pub poll_timeout: u64,
+    /// How long a receive collects bytes before handing over what it has
+    pub recv_timeout: u64,
+    pub send_timeout: u64,
+    pub connect_timeout: u64,
+    /// How long to wait before dialing a member again after failing to
+    pub redial_interval: u64,
+}
+
+impl MorpheusNodeConfig {
+    /// A member listening on `addresses[id]` and dialing the rest of them
+    pub fn new(id: &Identity, addresses: &BTreeMap<Identity, String>) -> Self {
+        MorpheusNodeConfig {
+            listen_address: addresses[id].clone(),
+            peers: addresses
+                .iter()
+                .filter(|(peer, _)| *peer != id)
+                .map(|(peer, address)| (peer.clone(), address.clone()))
+                .collect(),
+            poll_timeout: 5,
+            recv_timeout: 10,
+            send_timeout: 1000,
+            connect_timeout: 1000,
+            redial_interval: 200,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeStatus {
+    Init,
+    Starting,
+    Running,
+}
+
+/// One connection to another member
+#[derive(Debug)]
+pub struct Link {
+    /// The member we dialed, or that dialed us once its hello arrives
+    pub member: Option<Identity>,
+    /// Whether we dialed, which decides the model its IO goes through
+    pub dialed: bool,
+    pub connected: bool,
+    /// Bytes received that don't make up a whole frame yet
+    inbox: Vec<u8>,
+    /// Frames waiting for the send in flight to finish
+    outbox: Vec<u8>,
+    recv: Option<Uid>,
+    send: Option<Uid>,
+}
+
+/// A process and the connections carrying its messages
+pub struct MorpheusNodeState {
+    pub process: MorpheusProcess<TestTransaction>,
+    pub config: MorpheusNodeConfig,
+    pub status: NodeStatus,
+    pub links: Objects<Link>,
+    /// The connection to each member whose identity we know
+    pub members: BTreeMap<Identity, Uid>,
+    /// Messages received and not yet handed to the process
+    received: VecDeque<(Identity, Message<TestTransaction>)>,
+    /// The connection each send or receive in flight is on
+    requests: BTreeMap<Uid, Uid>,
+    /// When we last dialed each member
+    dialed_at: BTreeMap<Identity, u128>,
+    /// muchin's clock at our first tick, which the process sees as 0
+    started_at: Option<u128>,
+}
+
+impl std::fmt::Debug for MorpheusNodeState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MorpheusNodeState")
+            .field("id", &self.process.id)
+            .field("config", &self.config)
+            .field("status", &self.status)
+            .field("links", &self.links)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Everything one member's instance of the runner holds
+#[derive(ModelState, Debug)]
+pub struct MorpheusNode {
+    pub time: TimeState,
+    pub tcp: TcpState,
+    pub tcp_server: TcpServerState,
+    pub tcp_client: TcpClientState,
+    pub node: MorpheusNodeState,
+}
+
+impl MorpheusNode {
+    pub fn new(process: MorpheusProcess<TestTransaction>, config: MorpheusNodeConfig) -> Self {
+        MorpheusNode {
+            time: TimeState::default(),
+            tcp: TcpState::new(),
+            tcp_server: TcpServerState::new(),
+            tcp_client: TcpClientState::new(),
+            node: MorpheusNodeState {
+                process,
+                config,
+                status: NodeStatus::Init,
+                links: Objects::new(),
+                members: BTreeMap::new(),
+                received: VecDeque::new(),
+                requests: BTreeMap::new(),
+                dialed_at: BTreeMap::new(),
+                started_at: None,
+            },
+        }
+    }
+}
+
+/// A runner with one instance per process, each listening on `127.0.0.1`
+/// at `base_port` plus its id
+pub fn local_cluster(
+    processes: Vec<MorpheusProcess<TestTransaction>>,
+    base_port: u16,
+) -> Runner<MorpheusNode> {
+    let addresses = processes
+        .iter()
+        .map(|p| {
+            (
+                p.id.clone(),
+                format!("127.0.0.1:{}", base_port as u32 + p.id.0),
+            )
+        })
+        .collect::<BTreeMap<_, _>>();
+    let mut builder = RunnerBuilder::<MorpheusNode>::new().register::<MorpheusNodeState>();
+    for process in processes {
+        let config = MorpheusNodeConfig::new(&process.id, &addresses);
+        builder = builder.instance(MorpheusNode::new(process, config), || {
+            MorpheusNodeAction::Tick.into()
+        });
+    }
+    builder.build()
+}
+
+#[derive(Clone, PartialEq, Eq, TypeUuid, Serialize, Deserialize, Debug)]
+#[uuid = "6e5628b6-b79d-40be-a511-9e69f6826873"]
+pub enum MorpheusNodeAction {
+    Tick,
+    InitSuccess { instance: Uid },
+    InitError { instance: Uid, error: String },
+    ListenerSuccess { listener: Uid },
+    ListenerError { listener: Uid, error: String },
+    ListenerClosed { listener: Uid },
+    Accepted { connection: Uid },
+    ConnectSuccess { connection: Uid },
+    ConnectTimeout { connection: Uid },
+    ConnectError { connection: Uid, error: String },
+    Closed { connection: Uid },
+    PollSuccess { uid: Uid },
+    PollError { uid: Uid, error: String },
+    SendSuccess { uid: Uid },
+    SendTimeout { uid: Uid },
+    SendError { uid: Uid, error: String },
+    RecvSuccess { uid: Uid, data: Vec<u8> },
+    RecvTimeout { uid: Uid, partial_data: Vec<u8> },
+    RecvError { uid: Uid, error: String },
+}
+
+impl Action for MorpheusNodeAction {
+    const KIND: ActionKind = ActionKind::Pure;
+}
+
+// Depends on `TcpServerState` for accepted connections and `TcpClientState`
+// for dialed ones; both share one `TcpState`.
+impl RegisterModel for MorpheusNodeState {
+    fn register<Substate: ModelState>(builder: RunnerBuilder<Substate>) -> RunnerBuilder<Substate> {
+        builder
+            .register::<TcpServerState>()
+            .register::<TcpClientState>()
+            .model_pure::<Self>()
+    }
+}
+
+impl PureModel for MorpheusNodeState {
+    type Action = MorpheusNodeAction;
+
+    fn process_pure<Substate: ModelState>(
+        state: &mut State<Substate>,
+        action: Self::Action,
+        dispatcher: &mut Dispatcher,
+    ) {
+        match action {
+            MorpheusNodeAction::Tick => {
+                if update_time(state, dispatcher) {
+                    return;
+                }
+                let now = get_current_time(state);
+                let node: &mut MorpheusNodeState = state.substate_mut();
+                match node.status {
+                    NodeStatus::Init => {
+                        node.status = NodeStatus::Starting;
+                        dispatcher.dispatch(TcpAction::Init {
+                            instance: state.new_uid(),
+                            on_success: callback!(|instance: Uid| MorpheusNodeAction::InitSuccess { instance }),
+                            on_error: callback!(|(instance: Uid, error: String)| MorpheusNodeAction::InitError { instance, error }),
+                        });
+                    }
+                    NodeStatus::Starting => (),
+                    NodeStatus::Running => {
+                        node.run_process(now);
+                        dial(state, dispatcher, now);
+                        start_sends(state, dispatcher);
+
+                        // Polling through the server drives IO on every
+                        // connection of the shared `TcpState`, dialed ones too
+                        let poll_timeout =
+                            state.substate::<MorpheusNodeState>().config.poll_timeout;
+                        dispatcher.dispatch(TcpServerAction::Poll {
+                            uid: state.new_uid(),
+                            timeout: Timeout::Millis(poll_timeout),
+                            on_success: callback!(|uid: Uid| MorpheusNodeAction::PollSuccess { uid }),
+                            on_error: callback!(|(uid: Uid, error: String)| MorpheusNodeAction::PollError { uid, error }),
+                        });
+                    }
+                }
+            }
+            MorpheusNodeAction::InitSuccess { .. } => {
+                let address = state
+                    .substate::<MorpheusNodeState>()
+                    .config
+                    .listen_address
+                    .clone();
+                let max_connections = state.substate::<MorpheusNodeState>().config.peers.len();
+                dispatcher.dispatch(TcpServerAction::New {
+                    address,
+                    listener: state.new_uid(),
+                    max_connections,
+                    on_success: callback!(|listener: Uid| MorpheusNodeAction::ListenerSuccess { listener }),
+                    on_error: callback!(|(listener: Uid, error: String)| MorpheusNodeAction::ListenerError { listener, error }),
+                    on_new_connection: callback!(|(listener: Uid, connection: Uid)| MorpheusNodeAction::Accepted { connection }),
+                    on_connection_closed: callback!(|(listener: Uid, connection: Uid)| MorpheusNodeAction::Closed { connection }),
+                    on_listener_closed: callback!(|listener: Uid| MorpheusNodeAction::ListenerClosed { listener }),
+                });
+            }
+            MorpheusNodeAction::InitError { error, .. } => {
+                panic!("TCP initialization failed: {}", error)
+            }
+            MorpheusNodeAction::ListenerSuccess { .. } => {
+                state.substate_mut::<MorpheusNodeState>().status = NodeStatus::Running;
+            }
+            MorpheusNodeAction::ListenerError { listener, error } => {
+                panic!("Listener {:?} initialization failed: {}", listener, error)
+            }
+            MorpheusNodeAction::ListenerClosed { listener } => {
+                let node: &MorpheusNodeState = state.substate();
+                tracing::error!(process_id = ?node.process.id, ?listener, "Listener closed");
+            }
+            MorpheusNodeAction::Accepted { connection } => {
+                let node: &mut MorpheusNodeState = state.substate_mut();
+                node.links.insert(connection, Link::accepted());
+            }
+            MorpheusNodeAction::ConnectSuccess { connection } => {
+                let node: &mut MorpheusNodeState = state.substate_mut();
+                if let Some(link) = node.links.get_mut(&connection) {
+                    link.connected = true;
+                    let member = link.member.clone().expect("dialed links have a member");
+                    node.members.insert(member, connection);
+                }
+            }
+            MorpheusNodeAction::ConnectTimeout { connection } => {
+                let node: &mut MorpheusNodeState = state.substate_mut();
+                let member = node.links.remove(&connection).and_then(|link| link.member);
+                tracing::debug!(process_id = ?node.process.id, ?member, "Connect timed out");
+            }
+            MorpheusNodeAction::ConnectError { connection, error } => {
+                let node: &mut MorpheusNodeState = state.substate_mut();
+                let member = node.links.remove(&connection).and_then(|link| link.member);
+                tracing::debug!(
+                    process_id = ?node.process.id,
+                    ?member,
+                    %error,
+                    "Connect failed"
+                );
+            }
+            MorpheusNodeAction::Closed { connection } => {
+                state
+                    .substate_mut::<MorpheusNodeState>()
+                    .remove_link(&connection);
+            }
+            MorpheusNodeAction::PollSuccess { .. } => start_recvs(state, dispatcher),
+            MorpheusNodeAction::PollError { uid, error } => {
+                panic!("Poll {:?} failed: {}", uid, error)
+            }
+            MorpheusNodeAction::SendSuccess { uid } => {
+                let node: &mut MorpheusNodeState = state.substate_mut();
+                if let Some(link) = node
+                    .requests
+                    .remove(&uid)
+                    .and_then(|connection| node.links.get_mut(&connection))
+                {
+                    link.send = None;
+                }
+            }
+            MorpheusNodeAction::SendTimeout { uid } => {
+                let node: &mut MorpheusNodeState = state.substate_mut();
+                if let Some(connection) = node.requests.remove(&uid) {
+                    tracing::warn!(process_id = ?node.process.id, ?connection, "Send timed out");
+                    close(node, dispatcher, connection);
+                }
+            }
+            MorpheusNodeAction::SendError { uid, error }
+            | MorpheusNodeAction::RecvError { uid, error } => {
+                // the connection's close event follows and removes the link
+                let node: &mut MorpheusNodeState = state.substate_mut();
+                let connection = node.requests.remove(&uid);
+                tracing::debug!(
+                    process_id = ?node.process.id,
+                    ?connection,
+                    %error,
+                    "Connection failed"
+                );
+            }
+            MorpheusNodeAction::RecvSuccess { uid, data }
+            | MorpheusNodeAction::RecvTimeout {
+                uid,
+                partial_data: data,
+            } => {
+                let node: &mut MorpheusNodeState = state.substate_mut();
+                let Some(connection) = node.requests.remove(&uid) else {
+                    return;
+                };
+                if let Err(error) = node.on_data(connection, &data) {
+                    tracing::warn!(
+                        process_id = ?node.process.id,
+                        ?connection,
+                        %error,
+                        "Dropping connection"
+                    );
+                    close(node, dispatcher, connection);
+                }
+            }
+        }
+    }
+}
+
+impl Link {
+    /// A connection another member dialed, which says who it is first
+    fn accepted() -> Self {
+        Link {
+            member: None,
+            dialed: false,
+            connected: true,
+            inbox: Vec::new(),
+            outbox: Vec::new(),
+            recv: None,
+            send: None,
+        }
+    }
+
+    /// A connection we are dialing to `member`, with our hello queued on it
+    fn dialing(member: Identity, me: &Identity) -> Self {
+        Link {
+            member: Some(member),
+            dialed: true,
+            connected: false,
+            inbox: Vec::new(),
+            outbox: frame(&me.0.to_be_bytes()),
+            recv: None,
+            send: None,
+        }
+    }
+}
+
+/// What the process sends and receives during one tick
+#[derive(Default)]
+struct Mailbox {
+    received: VecDeque<(Identity, Message<TestTransaction>)>,
+    sent: Vec<(Option<Identity>, Message<TestTransaction>)>,
+}
+
+impl NetworkTransport<TestTransaction> for Mailbox {
+    fn send(&mut self, to: &Identity, message: Message<TestTransaction>) {
+        self.sent.push((Some(to.clone()), message));
+    }
+
+    fn broadcast(&mut self, message: Message<TestTransaction>) {
+        self.sent.push((None, message));
+    }
+
+    fn receive(&mut self) -> Option<(Identity, Message<TestTransaction>)> {
+        self.received.pop_front()
+    }
+}
+
+impl MorpheusNodeState {
+    /// Hands the process what arrived and advances its clock to `now`,
+    /// queueing what it sends on the connections it is for
+    fn run_process(&mut self, now: u128) {
+        let started_at = *self.started_at.get_or_insert(now);
+        let mut mailbox = Mailbox {
+            received: std::mem::take(&mut self.received),
+            sent: Vec::new(),
+        };
+        self.process.poll_transport(&mut mailbox);
+        self.process
+            .tick_transport(now.saturating_sub(started_at), &mut mailbox);
+
+        for (to, message) in mailbox.sent {
+            let frame = frame(&message.to_wire());
+            let connections = match to {
+                Some(to) => self.members.get(&to).into_iter().copied().collect(),
+                None => self.members.values().copied().collect::<Vec<_>>(),
+            };
+            for connection in connections {
+                if let Some(link) = self.links.get_mut(&connection) {
+                    link.outbox.extend_from_slice(&frame);
+                }
+            }
+        }
+    }
+
+    /// Appends `data` received on `connection` to what it has buffered, and
+    /// takes out every whole frame
+    fn on_data(&mut self, connection: Uid, data: &[u8]) -> Result<(), String> {
+        let MorpheusNodeState {
+            process,
+            links,
+            members,
+            received,
+            ..
+        } = self;
+        let Some(link) = links.get_mut(&connection) else {
+            return Ok(());
+        };
+        link.recv = None;
+        link.inbox.extend_from_slice(data);
+        while let Some(payload) = take_frame(&mut link.inbox)? {
+            match &link.member {
+                Some(member) => {
+                    let message = Message::from_wire(&payload).map_err(|e| e.to_string())?;
+                    received.push_back((member.clone(), message));
+                }
+                None => {
+                    let bytes: [u8; 4] = payload
+                        .as_slice()
+                        .try_into()
+                        .map_err(|_| format!("hello of {} bytes", payload.len()))?;
+                    let member = Identity(u32::from_be_bytes(bytes));
+                    if member == process.id || !process.kb.keys.contains_key(&member) {
+                        return Err(format!("hello from {:?}, not another member", member));
+                    }
+                    if members.contains_key(&member) {
+                        return Err(format!("{:?} is already connected", member));
+                    }
+                    members.insert(member.clone(), connection);
+                    link.member = Some(member);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn remove_link(&mut self, connection: &Uid) {
+        let Some(link) = self.links.remove(connection) else {
+            return;
+        };
+        if let Some(member) = link.member {
+            if self.members.get(&member) == Some(connection) {
+                self.members.remove(&member);
+            }
+        }
+        self.requests.retain(|_, on| on != connection);
+    }
+}
+
+/// Dials every member with a lower id than ours that we aren't connected or
+/// connecting to, unless we tried it recently
+fn dial<Substate: ModelState>(state: &mut State<Substate>, dispatcher: &mut Dispatcher, now: u128) {
+    let node: &MorpheusNodeState = state.substate();
+    let due = node
+        .config
+        .peers
+        .iter()
+        .filter(|(member, _)| member.0 < node.process.id.0)
+        .filter(|(member, _)| {
+            !node
+                .links
+                .values()
+                .any(|link| link.member.as_ref() == Some(*member))
+        })
+        .filter(|(member, _)| {
+            node.dialed_at
+                .get(*member)
+                .is_none_or(|at| now >= at + node.config.redial_interval as u128)
+        })
+        .map(|(member, address)| (member.clone(), address.clone()))
+        .collect::<Vec<_>>();
+    let me = node.process.id.clone();
+    let timeout = node.config.connect_timeout;
+
+    for (member, address) in due {
+        let connection = state.new_uid();
+        let node: &mut MorpheusNodeState = state.substate_mut();
+        node.links
+            .insert(connection, Link::dialing(member.clone(), &me));
+        node.dialed_at.insert(member, now);
+
+        dispatcher.dispatch(TcpClientAction::Connect {
+            connection,
+            address,
+            timeout: Timeout::Millis(timeout),
+            on_success: callback!(|connection: Uid| MorpheusNodeAction::ConnectSuccess { connection }),
+            on_timeout: callback!(|connection: Uid| MorpheusNodeAction::ConnectTimeout { connection }),
+            on_error: callback!(|(connection: Uid, error: String)| MorpheusNodeAction::ConnectError { connection, error }),
+            on_close: callback!(|connection: Uid| MorpheusNodeAction::Closed { connection }),
+        });
+    }
+}
+
+/// Sends what each connection has queued, unless a send is still in flight
+/// on it, so frames are never interleaved
+fn start_sends<Substate: ModelState>(state: &mut State<Substate>, dispatcher: &mut Dispatcher) {
+    let node: &MorpheusNodeState = state.substate();
+    let ready = node
+        .links
+        .iter()
+        .filter(|(_, link)| link.connected && link.send.is_none() && !link.outbox.is_empty())
+        .map(|(connection, _)| *connection)
+        .collect::<Vec<_>>();
+    let timeout = Timeout::Millis(node.config.send_timeout);
+
+    for connection in ready {
+        let uid = state.new_uid();
+        let node: &mut MorpheusNodeState = state.substate_mut();
+        let link = node.links.get_mut(&connection).unwrap();
+        let data = std::mem::take(&mut link.outbox).into();
+        link.send = Some(uid);
+        node.requests.insert(uid, connection);
+
+        if link.dialed {
+            dispatcher.dispatch(TcpClientAction::Send {
+                uid,
+                connection,
+                data,
+                timeout: timeout.clone(),
+                on_success: callback!(|uid: Uid| MorpheusNodeAction::SendSuccess { uid }),
+                on_timeout: callback!(|uid: Uid| MorpheusNodeAction::SendTimeout { uid }),
+                on_error: callback!(|(uid: Uid, error: String)| MorpheusNodeAction::SendError { uid, error }),
+            });
+        } else {
+            dispatcher.dispatch(TcpServerAction::Send {
+                uid,
+                connection,
+                data,
+                timeout: timeout.clone(),
+                on_success: callback!(|uid: Uid| MorpheusNodeAction::SendSuccess { uid }),
+                on_timeout: callback!(|uid: Uid| MorpheusNodeAction::SendTimeout { uid }),
+                on_error: callback!(|(uid: Uid, error: String)| MorpheusNodeAction::SendError { uid, error }),
+            });
+        }
+    }
+}
+
+/// Starts a receive on every connection that has none in flight
+fn start_recvs<Substate: ModelState>(state: &mut State<Substate>, dispatcher: &mut Dispatcher) {
+    let node: &MorpheusNodeState = state.substate();
+    let idle = node
+        .links
+        .iter()
+        .filter(|(_, link)| link.connected && link.recv.is_none())
+        .map(|(connection, _)| *connection)
+        .collect::<Vec<_>>();
+    let timeout = Timeout::Millis(node.config.recv_timeout);
+
+    for connection in idle {
+        let uid = state.new_uid();
+        let node: &mut MorpheusNodeState = state.substate_mut();
+        let link = node.links.get_mut(&connection).unwrap();
+        link.recv = Some(uid);
+        node.requests.insert(uid, connection);
+
+        if link.dialed {
+            dispatcher.dispatch(TcpClientAction::Recv {
+                uid,
+                connection,
+                count: RECV_CHUNK,
+                timeout: timeout.clone(),
+                on_success: callback!(|(uid: Uid, data: Vec<u8>)| MorpheusNodeAction::RecvSuccess { uid, data }),
+                on_timeout: callback!(|(uid: Uid, partial_data: Vec<u8>)| MorpheusNodeAction::RecvTimeout { uid, partial_data }),
+                on_error: callback!(|(uid: Uid, error: String)| MorpheusNodeAction::RecvError { uid, error }),
+            });
+        } else {
+            dispatcher.dispatch(TcpServerAction::Recv {
+                uid,
+                connection,
+                count: RECV_CHUNK,
+                timeout: timeout.clone(),
+                on_success: callback!(|(uid: Uid, data: Vec<u8>)| MorpheusNodeAction::RecvSuccess { uid, data }),
+                on_timeout: callback!(|(uid: Uid, partial_data: Vec<u8>)| MorpheusNodeAction::RecvTimeout { uid, partial_data }),
+                on_error: callback!(|(uid: Uid, error: String)| MorpheusNodeAction::RecvError { uid, error }),
+            });
+        }
+    }
+}
+
+/// Closes `connection` through the model it was opened by; its close event
+/// removes the link
+fn close(node: &MorpheusNodeState, dispatcher: &mut Dispatcher, connection: Uid) {
+    match node.links.get(&connection) {
+        Some(link) if link.dialed => dispatcher.dispatch(TcpClientAction::Close { connection }),
+        Some(_) => dispatcher.dispatch(TcpServerAction::Close { connection }),
+        None => (),
+    }
+}
+
+/// `payload` prefixed with its length
+fn frame(payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(4 + payload.len());
+    frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// The payload of the first frame in `buf`, removed from it, if it has
+/// arrived whole
+fn take_frame(buf: &mut Vec<u8>) -> Result<Option<Vec<u8>>, String> {
+    let Some(header) = buf.first_chunk::<4>() else {
+        return Ok(None);
+    };
+    let len = u32::from_be_bytes(*header) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(format!("frame of {} bytes, at most {}", len, MAX_FRAME_LEN));
+    }
+    if buf.len() < 4 + len {
+        return Ok(None);
+    }
+    let payload = buf[4..4 + len].to_vec();
+    buf.drain(..4 + len);
+    Ok(Some(payload))
+}
@@ -0,0 +1,247 @@
+//! Ready-made transaction types
+//!
+//! Anything meeting the [`Transaction`] bounds can be ordered, but every
+//! application would otherwise write the same framing to get there. Two
+//! common shapes are provided:
+//!
+//! - [`CborPayload`] carries any serde type as CBOR. It is stored encoded,
+//!   so ordering, hashing and the Merkle commitment are over the exact bytes
+//!   that were submitted, and decoding it again can't fail: a payload that
+//!   doesn't decode as `T` fails `Valid::check` and is rejected with the
+//!   message carrying it.
+//! - [`SignedTransfer`] moves an amount between accounts named by public
+//!   key, with a per-sender nonce against replays and an optional last view,
+//!   signed by the sender's key.
+//!
+//! A transfer's signature can only be checked against the committee's
+//! `hints::GlobalData`, which the transaction itself doesn't carry, so
+//! `Valid::check` only sees that its keys and signature are well-formed.
+//! Whatever applies finalized transfers to balances calls
+//! [`SignedTransfer::verify`] and tracks nonces.
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, SerializationError, Valid, Validate,
+};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+const TRANSFER_DOMAIN: &[u8] = b"morpheus-transfer-v1";
+
+/// A `T` carried as its CBOR encoding
+pub struct CborPayload<T> {
+    bytes: Vec<u8>,
+    _payload: PhantomData<fn() -> T>,
+}
+
+impl<T: Serialize + DeserializeOwned> CborPayload<T> {
+    pub fn new(payload: &T) -> Self {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(payload, &mut bytes).expect("writing to a Vec doesn't fail");
+        CborPayload {
+            bytes,
+            _payload: PhantomData,
+        }
+    }
+
+    /// Takes `bytes` as they are, if they decode as a `T`
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, SerializationError> {
+        let payload = CborPayload {
+            bytes,
+            _payload: PhantomData,
+        };
+        payload.check()?;
+        Ok(payload)
+    }
+
+    pub fn decode(&self) -> T {
+        self.try_decode().expect("checked when constructed")
+    }
+
+    fn try_decode(&self) -> Result<T, SerializationError> {
+        ciborium::from_reader(&self.bytes[..]).map_err(|_| SerializationError::InvalidData)
+    }
+}
+
+impl<T> CborPayload<T> {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl<T> Clone for CborPayload<T> {
+    fn clone(&self) -> Self {
+        CborPayload {
+            bytes: self.bytes.clone(),
+            _payload: PhantomData,
+        }
+    }
+}
+
+impl<T> PartialEq for CborPayload<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.bytes == other.bytes
+    }
+}
+
+impl<T> Eq for CborPayload<T> {}
+
+impl<T> PartialOrd for CborPayload<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for CborPayload<T> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.bytes.cmp(&other.bytes)
+    }
+}
+
+impl<T> Hash for CborPayload<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.bytes.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for CborPayload<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CborPayload({} bytes)", self.bytes.len())
+    }
+}
+
+impl<T> CanonicalSerialize for CborPayload<T> {
+    fn serialize_with_mode<W: std::io::Write>(
+        &self,
+        writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.bytes.serialize_with_mode(writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.bytes.serialized_size(compress)
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> Valid for CborPayload<T> {
+    fn check(&self) -> Result<(), SerializationError> {
+        self.try_decode().map(|_| ())
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> CanonicalDeserialize for CborPayload<T> {
+    fn deserialize_with_mode<R: std::io::Read>(
+        reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let payload = CborPayload {
+            bytes: Vec::deserialize_with_mode(reader, compress, validate)?,
+            _payload: PhantomData,
+        };
+        if validate == Validate::Yes {
+            payload.check()?;
+        }
+        Ok(payload)
+    }
+}
+
+/// As the encoded bytes, so traces and snapshots keep them exactly
+impl<T> Serialize for CborPayload<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.bytes.serialize(serializer)
+    }
+}
+
+impl<'de, T: Serialize + DeserializeOwned> Deserialize<'de> for CborPayload<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = Vec::<u8>::deserialize(deserializer)?;
+        CborPayload::from_bytes(bytes).map_err(serde::de::Error::custom)
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> Transaction for CborPayload<T> {}
+
+/// Moving `amount` from one account to another
+#[derive(
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Debug,
+    Serialize,
+    Deserialize,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+)]
+pub struct Transfer {
+    pub from: hints::PublicKey,
+    pub to: hints::PublicKey,
+    pub amount: u64,
+    /// One more than the sender's previous transfer, starting from zero
+    pub nonce: u64,
+    /// The last view this transfer may be included in, if any
+    pub expires_after: Option<ViewNum>,
+}
+
+impl Transfer {
+    /// What the sender signs
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        let mut buf = TRANSFER_DOMAIN.to_vec();
+        self.serialize_compressed(&mut buf).unwrap();
+        buf
+    }
+
+    /// Signs with `key`, which must be the key of `from`
+    pub fn sign(self, key: &hints::SecretKey) -> SignedTransfer {
+        let signature = hints::sign(key, &self.signing_bytes());
+        SignedTransfer {
+            transfer: self,
+            signature,
+        }
+    }
+}
+
+#[derive(
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Debug,
+    Serialize,
+    Deserialize,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+)]
+pub struct SignedTransfer {
+    pub transfer: Transfer,
+    pub signature: hints::PartialSignature,
+}
+
+impl SignedTransfer {
+    /// Whether the sender signed this transfer
+    pub fn verify(&self, global: &hints::GlobalData) -> bool {
+        hints::verify_partial(
+            global,
+            &self.transfer.from,
+            &self.transfer.signing_bytes(),
+            &self.signature,
+        )
+    }
+}
+
+impl Transaction for SignedTransfer {
+    fn expires_after(&self) -> Option<ViewNum> {
+        self.transfer.expires_after
+    }
+}
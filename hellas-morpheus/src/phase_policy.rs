@@ -0,0 +1,85 @@
+//! When a process may enter the low throughput phase
+//!
+//! In the paper a process switches from the high to the low throughput phase
+//! of a view as soon as it can vote for a transaction block: the view has a
+//! leader block and all of its leader blocks are finalized. A [`PhasePolicy`]
+//! is consulted at that point and may hold the process in the high throughput
+//! phase for longer, e.g. while it is seeing heavy load and ordering through
+//! leader blocks is cheaper than finalizing each block directly.
+//!
+//! A policy can only delay the switch, never bring it forward, and once a
+//! process is in the low throughput phase of a view it stays there. Holding
+//! back transaction votes is always safe; a policy that never lets the switch
+//! happen just means transaction blocks are only ever finalized through
+//! leader blocks.
+
+use std::sync::Arc;
+
+use crate::*;
+
+/// What a policy may consult when the paper's conditions for the switch hold
+pub struct PhaseContext {
+    pub view: ViewNum,
+    pub n: u32,
+    /// Transaction blocks this process knows of that aren't finalized yet
+    pub unfinalized_tr: usize,
+    /// Transaction blocks waiting for our vote in this view
+    pub pending_tr_votes: usize,
+    /// How long this process has been in the view
+    pub time_in_view: u128,
+}
+
+pub trait PhasePolicy: Send + Sync {
+    /// Whether to vote for transaction blocks, entering the low throughput phase
+    fn enter_low(&self, ctx: &PhaseContext) -> bool;
+}
+
+/// Switches as soon as the paper allows; the default
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PaperPhase;
+
+impl PhasePolicy for PaperPhase {
+    fn enter_low(&self, _: &PhaseContext) -> bool {
+        true
+    }
+}
+
+/// Switches only while load is low: at most `max_unfinalized` transaction
+/// blocks left unfinalized
+#[derive(Clone, Copy, Debug)]
+pub struct LowLoadOnly {
+    pub max_unfinalized: usize,
+}
+
+impl PhasePolicy for LowLoadOnly {
+    fn enter_low(&self, ctx: &PhaseContext) -> bool {
+        ctx.unfinalized_tr <= self.max_unfinalized
+    }
+}
+
+pub(crate) fn default_phase_policy() -> Arc<dyn PhasePolicy> {
+    Arc::new(PaperPhase)
+}
+
+impl<Tr: Transaction> MorpheusProcess<Tr> {
+    /// Whether transaction votes may be cast in the current view, given the
+    /// paper's conditions hold; always true once in the low throughput phase
+    pub(crate) fn may_vote_tr(&self, pending_tr_votes: usize) -> bool {
+        if self.phase_i.get(&self.view_i) == Some(&Phase::Low) {
+            return true;
+        }
+        let ctx = PhaseContext {
+            view: self.view_i,
+            n: self.n,
+            unfinalized_tr: self
+                .index
+                .unfinalized
+                .keys()
+                .filter(|key| key.type_ == BlockType::Tr)
+                .count(),
+            pending_tr_votes,
+            time_in_view: self.current_time.saturating_sub(self.view_entry_time),
+        };
+        self.phase_policy.enter_low(&ctx)
+    }
+}
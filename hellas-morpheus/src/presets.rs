@@ -0,0 +1,122 @@
+//! Built-in scenarios for the simulation harness
+//!
+//! Each preset configures transaction generation policies and a schedule of
+//! faults on an existing [`MockHarness`], so the visualizer and tests can
+//! offer one-click demonstrations of the protocol's behaviour.
+
+use std::{
+    collections::BTreeSet,
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+use crate::test_harness::{MockHarness, NetworkFaults, ScenarioEvent, TxGenPolicy};
+
+/// Names of all the built-in presets, in the order they should be offered
+pub const PRESETS: &[&str] = &[
+    "happy-path",
+    "leader-crash",
+    "partition-and-heal",
+    "byzantine-equivocator",
+    "high-load-phase-switch",
+];
+
+/// Error returned when asked for a preset that doesn't exist
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownPreset(pub String);
+
+impl fmt::Display for UnknownPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Unknown preset {:?}, expected one of {:?}",
+            self.0, PRESETS
+        )
+    }
+}
+
+impl MockHarness {
+    /// Configure this harness with one of the built-in [`PRESETS`]
+    ///
+    /// Replaces all transaction generation policies and the event schedule,
+    /// and lifts any faults already injected. Event steps are relative to the
+    /// current step. An unknown name leaves the harness as it was.
+    pub fn load_preset(&mut self, name: &str) -> Result<(), UnknownPreset> {
+        if !PRESETS.contains(&name) {
+            return Err(UnknownPreset(name.to_string()));
+        }
+        let ids = self.processes.keys().cloned().collect::<Vec<_>>();
+        let now = self.steps;
+
+        self.tx_gen_policy.clear();
+        self.schedule.clear();
+        self.faults = NetworkFaults::default();
+
+        match name {
+            "happy-path" => {
+                for id in &ids {
+                    self.tx_gen_policy
+                        .insert(id.clone(), TxGenPolicy::EveryNSteps { n: 3 });
+                }
+            }
+            "leader-crash" => {
+                for id in &ids {
+                    self.tx_gen_policy
+                        .insert(id.clone(), TxGenPolicy::EveryNSteps { n: 3 });
+                }
+                // identities are sorted, so this is lead(0)
+                let leader = ids[0].clone();
+                self.schedule_event(now + 5, ScenarioEvent::Crash(leader));
+            }
+            "partition-and-heal" => {
+                for id in &ids {
+                    self.tx_gen_policy
+                        .insert(id.clone(), TxGenPolicy::EveryNSteps { n: 3 });
+                }
+                // the minority side can't form quorums until the network heals
+                let (minority, majority) = ids.split_at(ids.len() / 3);
+                self.schedule_event(
+                    now + 5,
+                    ScenarioEvent::Partition(vec![
+                        minority.iter().cloned().collect::<BTreeSet<_>>(),
+                        majority.iter().cloned().collect::<BTreeSet<_>>(),
+                    ]),
+                );
+                self.schedule_event(now + 30, ScenarioEvent::Heal);
+            }
+            "byzantine-equivocator" => {
+                for id in &ids {
+                    self.tx_gen_policy
+                        .insert(id.clone(), TxGenPolicy::EveryNSteps { n: 3 });
+                }
+                let byzantine = ids[ids.len() - 1].clone();
+                self.schedule_event(now, ScenarioEvent::Equivocate(byzantine));
+            }
+            "high-load-phase-switch" => {
+                // everyone produces constantly, then all but one stop so the
+                // single remaining producer can switch to the low throughput phase
+                for id in &ids {
+                    self.tx_gen_policy.insert(id.clone(), TxGenPolicy::Always);
+                }
+                for id in &ids[1..] {
+                    self.schedule_event(
+                        now + 20,
+                        ScenarioEvent::SetTxGenPolicy(id.clone(), TxGenPolicy::Never),
+                    );
+                }
+                self.schedule_event(
+                    now + 20,
+                    ScenarioEvent::SetTxGenPolicy(
+                        ids[0].clone(),
+                        TxGenPolicy::OncePerView {
+                            prev_view: Arc::new(RwLock::new(None)),
+                        },
+                    ),
+                );
+            }
+            _ => unreachable!("every name in PRESETS is matched"),
+        }
+
+        Ok(())
+    }
+}
@@ -1,8 +1,9 @@
 use std::{
     collections::{BTreeMap, BTreeSet},
-    sync::Arc,
+    sync::{Arc, Mutex},
 };
 
+use crate::events::Subscribers;
 use crate::state_tracking::{PendingVotes, StateIndex};
 use crate::*;
 use serde::{Deserialize, Serialize};
@@ -37,7 +38,7 @@ pub struct MorpheusProcess<Tr: Transaction> {
     /// Tracks which blocks this process has already voted for (voted_i(z,x,s,p_j) in pseudocode)
     /// "Initially 0" for all combinations of z, x, s, p_j
     /// Used to ensure process votes only once for each (z,x,s,p_j) combination
-    pub voted_i: BTreeSet<(u8, BlockType, SlotNum, Identity)>,
+    pub voted_i: BTreeSet<VoteKey>,
 
     /// Tracks the phase within each view (phase_i(v) in pseudocode)
     /// "Initially 0" for each view, represents high throughput (0) or low throughput (1) phase
@@ -70,6 +71,10 @@ pub struct MorpheusProcess<Tr: Transaction> {
     /// Used for timeout calculations (6Δ and 12Δ since entering view)
     pub view_entry_time: u128,
 
+    /// Backoff and churn of our view changes
+    #[serde(default)]
+    pub view_churn: ViewChurn,
+
     /// Current logical time
     pub current_time: u128,
 
@@ -90,8 +95,8 @@ pub struct MorpheusProcess<Tr: Transaction> {
     #[serde(with = "serde_json_any_key::any_key_map")]
     pub produced_lead_in_view: BTreeMap<ViewNum, bool>,
 
-    /// All messages received by this process
-    pub received_messages: BTreeSet<Message<Tr>>,
+    /// What we know of the messages this process handled
+    pub received_messages: ReceivedMessages<Tr>,
     pub qcs: BTreeSet<FinishedQC>,
 
     pub genesis: Arc<Signed<Block<Tr>>>,
@@ -99,11 +104,144 @@ pub struct MorpheusProcess<Tr: Transaction> {
     pub ready_transactions: Vec<Tr>,
 
     pub pending_votes: BTreeMap<ViewNum, PendingVotes>,
+
+    /// The most recent end-view certificates, by certified view, kept to
+    /// relay each only once and to serve peers still complaining about a view
+    #[serde(default, with = "serde_json_any_key::any_key_map")]
+    pub end_view_certs: BTreeMap<ViewNum, Arc<ThreshSigned<ViewNum>>>,
+
+    /// The latest progress each peer reported in its StartView messages
+    #[serde(default, with = "serde_json_any_key::any_key_map")]
+    pub peer_progress: BTreeMap<Identity, ProgressReport>,
+
+    /// Valid blocks held back until the blocks their prev pointers name
+    /// arrive, by each missing block
+    #[serde(default, with = "serde_json_any_key::any_key_map")]
+    pub awaiting_ancestors: BTreeMap<BlockKey, Vec<Arc<Signed<Block<Tr>>>>>,
+
+    /// Blocks we have asked everyone for with `GetBlock`
+    #[serde(default)]
+    pub requested_blocks: BTreeSet<BlockKey>,
+
+    /// Per-sender limits on the messages we handle, if any
+    #[serde(default)]
+    pub rate_limiter: Option<RateLimiter>,
+
+    /// Finalized protocol parameter changes, by activation view
+    #[serde(default)]
+    pub governance: Governance,
+
+    /// A committee of one that finalizes on every QC and never times out;
+    /// see `dev_mode.rs`
+    #[serde(default)]
+    pub dev_mode: bool,
+
+    /// Whether votes from their author skip their signature check, set with
+    /// [`Self::trust_links`]; see `link_auth.rs`
+    #[serde(default)]
+    pub vote_verification: VoteVerification,
+
+    /// Whether 1- and 2-votes are broadcast or collected by the view's leader
+    #[serde(default)]
+    pub vote_aggregation: VoteAggregation,
+
+    /// Our votes sent to an aggregator whose QC hasn't arrived yet, with when we sent them
+    #[serde(default, with = "serde_json_any_key::any_key_map")]
+    pub awaiting_aggregation: BTreeMap<VoteData, (u128, Arc<ThreshPartial<VoteData>>)>,
+
+    /// How the leader of each view is chosen
+    #[serde(default)]
+    pub leader_election: LeaderElection,
+
+    /// VRF proofs for recent views, so each is computed once
+    #[serde(skip)]
+    pub(crate) leader_proofs: LeaderCache,
+
+    /// Limits enforced on leader blocks we receive; none for a process
+    /// saved before budgets existed
+    #[serde(default)]
+    pub leader_budget: LeaderBudget,
+
+    /// Orders the tips our leader blocks reference
+    #[serde(skip, default = "crate::leader_policy::default_leader_policy")]
+    pub leader_policy: Arc<dyn LeaderPolicy>,
+
+    /// Decides whether to enter the low throughput phase once the paper allows it
+    #[serde(skip, default = "crate::phase_policy::default_phase_policy")]
+    pub phase_policy: Arc<dyn PhasePolicy>,
+
+    /// Fills and checks the extension slot of leader blocks
+    #[serde(skip, default = "crate::extensions::default_extension_policy")]
+    pub extension_policy: Arc<dyn ExtensionPolicy>,
+
+    /// Signs on our behalf when our key is held elsewhere; `None` signs with
+    /// `kb.me_sec_key`
+    #[serde(skip)]
+    pub signer: Option<Arc<dyn Signer>>,
+
+    /// Checks others' signatures when it isn't our `kb`; see [`Self::verifier`]
+    #[serde(skip)]
+    pub verifier: Option<Arc<dyn Verifier>>,
+
+    /// Consulted before every signature, so nothing conflicting is signed even
+    /// across restarts
+    #[serde(skip)]
+    pub sign_guard: Option<Arc<Mutex<SignGuard>>>,
+
+    /// Where our votes are logged before they are signed, so `voted_i`
+    /// survives restarts; see [`Self::attach_vote_wal`]
+    #[serde(skip)]
+    pub vote_wal: Option<Arc<Mutex<VoteWal>>>,
+
+    /// Keys we announced with `rotate_key`, by the view they take effect in;
+    /// like `kb.me_sec_key`, they are left out when serialized
+    #[serde(default)]
+    pub rotated_keys: BTreeMap<ViewNum, SecretKey>,
+
+    /// When each current tip first became a tip here, for `LeaderBudget::known_tip_delays`
+    #[serde(default, with = "serde_json_any_key::any_key_map")]
+    pub tips_seen_at: BTreeMap<QcKey, u128>,
+
+    /// How the number of tips changed over time, for `dag_stats`
+    #[serde(default)]
+    pub tip_history: TipHistory,
+
+    /// Which invariants are checked after handling each message (debug builds only)
+    #[serde(default)]
+    pub invariant_level: InvariantLevel,
+
+    /// Live event subscriptions, not part of the protocol state
+    #[serde(skip)]
+    pub(crate) subscribers: Subscribers,
 }
 
 impl<Tr: Transaction> MorpheusProcess<Tr> {
+    /// Member `id` of a committee of `n` tolerating `f` Byzantine members
+    ///
+    /// Panics if that committee isn't safe, see [`Self::try_new`]
     pub fn new(keybook: KeyBook, id: Identity, n: u32, f: u32) -> Self {
-        crate::tracing_setup::register_process(&id, n, f);
+        Self::try_new(keybook, id, n, f).unwrap_or_else(|e| panic!("unsafe committee: {}", e))
+    }
+
+    /// [`Self::new`], or why `n`, `f` and the keys don't make a safe committee
+    pub fn try_new(
+        keybook: KeyBook,
+        id: Identity,
+        n: u32,
+        f: u32,
+    ) -> Result<Self, QuorumConfigError> {
+        QuorumConfig::uniform(n, f).check()?;
+        if keybook.keys.len() != n as usize {
+            return Err(QuorumConfigError::CommitteeSizeMismatch {
+                n,
+                keys: keybook.keys.len(),
+            });
+        }
+        Ok(Self::unchecked(keybook, id, n, f))
+    }
+
+    fn unchecked(keybook: KeyBook, id: Identity, n: u32, f: u32) -> Self {
+        crate::tracing_setup::register_process(&id, &keybook.metadata.display_name(&id), n, f);
 
         let genesis_block = Arc::new(Signed {
             data: Block {
@@ -115,6 +253,7 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
                         for_which: GEN_BLOCK_KEY,
                     },
                     signature: hints::Signature::default(),
+                    signers: SignerBitmap::default(),
                 }),
                 data: BlockData::Genesis,
             },
@@ -128,6 +267,7 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
                 for_which: GEN_BLOCK_KEY,
             },
             signature: hints::Signature::default(),
+            signers: SignerBitmap::default(),
         });
 
         MorpheusProcess {
@@ -152,6 +292,7 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
             zero_qcs_sent: BTreeSet::new(),
             complained_qcs: BTreeSet::new(),
             view_entry_time: 0,
+            view_churn: ViewChurn::default(),
             current_time: 0,
 
             vote_tracker: QuorumTrack {
@@ -164,7 +305,7 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
                 map.insert(ViewNum(0), false);
                 map
             },
-            received_messages: BTreeSet::from([
+            received_messages: ReceivedMessages::from_iter([
                 Message::Block(genesis_block.clone()),
                 Message::QC(genesis_qc.clone()),
             ]),
@@ -173,6 +314,31 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
             genesis_qc: genesis_qc.clone(),
             ready_transactions: Vec::new(),
             pending_votes: BTreeMap::new(),
+            end_view_certs: BTreeMap::new(),
+            peer_progress: BTreeMap::new(),
+            awaiting_ancestors: BTreeMap::new(),
+            requested_blocks: BTreeSet::new(),
+            rate_limiter: None,
+            governance: Governance::default(),
+            dev_mode: false,
+            vote_verification: VoteVerification::EveryVote,
+            vote_aggregation: VoteAggregation::default(),
+            awaiting_aggregation: BTreeMap::new(),
+            leader_election: LeaderElection::default(),
+            leader_proofs: LeaderCache::default(),
+            leader_budget: LeaderBudget::for_committee(n),
+            leader_policy: crate::leader_policy::default_leader_policy(),
+            phase_policy: crate::phase_policy::default_phase_policy(),
+            extension_policy: crate::extensions::default_extension_policy(),
+            signer: None,
+            verifier: None,
+            sign_guard: None,
+            vote_wal: None,
+            rotated_keys: BTreeMap::new(),
+            tips_seen_at: BTreeMap::new(),
+            tip_history: TipHistory::default(),
+            invariant_level: InvariantLevel::default(),
+            subscribers: Subscribers::default(),
         }
     }
 }
@@ -0,0 +1,170 @@
+//! Checking that a committee's thresholds are safe
+//!
+//! Safety rests on quorum intersection: any two quorums must share at least
+//! f+1 weight, so that whatever the Byzantine members do, the two share an
+//! honest one, who votes for at most one of two conflicting blocks. Liveness
+//! rests on the honest members alone being able to form a quorum. Both are
+//! arithmetic on the weights and thresholds, and a committee that fails
+//! either should never start. [`QuorumConfig::check`] says whether one does,
+//! and why not.
+//!
+//! Every member currently has weight one, a QC needs n-f signatures and an
+//! end-view certificate f+1 ([`QuorumConfig::uniform`]); for those, the
+//! checks come down to n ≥ 3f+1.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::format::format_identity;
+use crate::*;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QuorumConfig {
+    /// Voting weight of each member
+    pub weights: BTreeMap<Identity, u64>,
+    /// The most weight that may be Byzantine
+    pub f: u64,
+    /// Weight a QC needs
+    pub quorum: u64,
+    /// Weight an end-view certificate needs, which must include an honest member
+    pub certificate: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QuorumConfigError {
+    NoMembers,
+    ZeroWeight(Identity),
+    /// Two quorums may share less than f+1 weight, and so no honest member
+    QuorumsMayNotIntersect {
+        quorum: u64,
+        total: u64,
+        f: u64,
+    },
+    /// The honest members alone can't form a quorum
+    QuorumUnreachable {
+        quorum: u64,
+        honest: u64,
+    },
+    /// A certificate may be made by the Byzantine members alone
+    CertificateTooSmall {
+        certificate: u64,
+        f: u64,
+    },
+    /// The honest members alone can't form a certificate
+    CertificateUnreachable {
+        certificate: u64,
+        honest: u64,
+    },
+    /// `n` doesn't match the committee the keys are for
+    CommitteeSizeMismatch {
+        n: u32,
+        keys: usize,
+    },
+}
+
+impl fmt::Display for QuorumConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoMembers => write!(f, "the committee has no members"),
+            Self::ZeroWeight(id) => write!(f, "{} has no voting weight", format_identity(id)),
+            Self::QuorumsMayNotIntersect {
+                quorum,
+                total,
+                f: faulty,
+            } => write!(
+                f,
+                "two quorums of {} out of {} may share only {}, fewer than the {} \
+                 needed to include an honest member with f = {}",
+                quorum,
+                total,
+                (2 * quorum).saturating_sub(*total),
+                faulty + 1,
+                faulty
+            ),
+            Self::QuorumUnreachable { quorum, honest } => write!(
+                f,
+                "a quorum needs {} but the honest members may only have {}",
+                quorum, honest
+            ),
+            Self::CertificateTooSmall {
+                certificate,
+                f: faulty,
+            } => write!(
+                f,
+                "a certificate of {} may be made by Byzantine members alone with f = {}",
+                certificate, faulty
+            ),
+            Self::CertificateUnreachable {
+                certificate,
+                honest,
+            } => write!(
+                f,
+                "a certificate needs {} but the honest members may only have {}",
+                certificate, honest
+            ),
+            Self::CommitteeSizeMismatch { n, keys } => {
+                write!(f, "n is {} but there are keys for {} members", n, keys)
+            }
+        }
+    }
+}
+
+impl std::error::Error for QuorumConfigError {}
+
+impl QuorumConfig {
+    /// The thresholds `MorpheusProcess` uses: members `1..=n` of weight one,
+    /// n-f for a QC and f+1 for a certificate
+    pub fn uniform(n: u32, f: u32) -> Self {
+        QuorumConfig {
+            weights: (1..=n).map(|i| (Identity(i), 1)).collect(),
+            f: f as u64,
+            quorum: n.saturating_sub(f) as u64,
+            certificate: f as u64 + 1,
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.weights.values().sum()
+    }
+
+    /// Whether these thresholds guarantee quorum intersection, and that the
+    /// honest members can form quorums and certificates without anyone else
+    pub fn check(&self) -> Result<(), QuorumConfigError> {
+        if self.weights.is_empty() {
+            return Err(QuorumConfigError::NoMembers);
+        }
+        if let Some((id, _)) = self.weights.iter().find(|(_, weight)| **weight == 0) {
+            return Err(QuorumConfigError::ZeroWeight(id.clone()));
+        }
+
+        let total = self.total();
+        let honest = total.saturating_sub(self.f);
+        // two quorums together hold 2q, of which at least 2q - total is shared
+        if (2 * self.quorum).saturating_sub(total) < self.f + 1 {
+            return Err(QuorumConfigError::QuorumsMayNotIntersect {
+                quorum: self.quorum,
+                total,
+                f: self.f,
+            });
+        }
+        if self.quorum > honest {
+            return Err(QuorumConfigError::QuorumUnreachable {
+                quorum: self.quorum,
+                honest,
+            });
+        }
+        if self.certificate < self.f + 1 {
+            return Err(QuorumConfigError::CertificateTooSmall {
+                certificate: self.certificate,
+                f: self.f,
+            });
+        }
+        if self.certificate > honest {
+            return Err(QuorumConfigError::CertificateUnreachable {
+                certificate: self.certificate,
+                honest,
+            });
+        }
+        Ok(())
+    }
+}
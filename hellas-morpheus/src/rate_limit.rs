@@ -0,0 +1,239 @@
+//! Limiting how many messages each peer gets handled
+//!
+//! Every message a process handles costs at least a signature check, so a
+//! peer sending as fast as it can would otherwise keep a process busy with
+//! nothing but its messages. A [`RateLimiter`] counts what each sender sent
+//! in fixed windows of a few Δ and drops whatever goes over the limit for its
+//! kind before anything is checked or recorded, so a dropped message can
+//! still be handled if it arrives again later. Messages we hand ourselves
+//! are never limited.
+//!
+//! A window in which a sender went over some limit is a violation. With
+//! `ban_after` set, a sender that racks up that many violations is banned for
+//! `ban_for` Δ, and everything it sends in the meantime is dropped. The
+//! counters in [`PeerRateStats`] are kept for every sender, for monitoring.
+//!
+//! Limits are off unless set with [`MorpheusProcess::set_rate_limits`].
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+/// How many messages of each kind one sender may have handled per window
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimits {
+    /// Length of a window, in Δ
+    pub window: u128,
+    /// Most messages of each kind handled per window; kinds not listed aren't limited
+    pub per_kind: BTreeMap<MessageKind, u32>,
+    /// Violations before a sender is banned; `None` never bans
+    pub ban_after: Option<u32>,
+    /// How long a ban lasts, in Δ
+    pub ban_for: u128,
+}
+
+impl RateLimits {
+    /// Generous limits for a committee of `n` processes: an honest member
+    /// sends a block or two, a few votes and QCs and a few requests for each
+    /// member's blocks per Δ, and at most one message of each view-change
+    /// kind per view; these allow several times that. Nobody is banned.
+    pub fn for_committee(n: u32) -> Self {
+        RateLimits {
+            window: 1,
+            per_kind: BTreeMap::from([
+                (MessageKind::Block, 16),
+                (MessageKind::BlockResponse, 16),
+                (MessageKind::NewVote, 16 * n),
+                (MessageKind::QC, 16 * n),
+                (MessageKind::GetBlock, 16 * n),
+                (MessageKind::EndView, 4),
+                (MessageKind::EndViewCert, 4),
+                (MessageKind::StartView, 4),
+                (MessageKind::KeyRotation, 4),
+            ]),
+            ban_after: None,
+            ban_for: 12,
+        }
+    }
+}
+
+/// What was done with the messages of one sender
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerRateStats {
+    pub accepted: u64,
+    /// Dropped for going over the limit of their kind
+    pub limited: u64,
+    /// Dropped because the sender was banned
+    pub dropped_while_banned: u64,
+    /// Windows in which the sender went over a limit
+    pub violations: u64,
+    pub bans: u64,
+    /// Until when the sender is banned, if it is
+    pub banned_until: Option<u128>,
+}
+
+/// Counts for one sender's current window
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct PeerWindow {
+    start: u128,
+    counts: BTreeMap<MessageKind, u32>,
+    violated: bool,
+    /// Violations since the sender was last banned
+    strikes: u32,
+    stats: PeerRateStats,
+}
+
+/// Whether a message from some sender may be handled
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Admission {
+    Accepted,
+    OverLimit,
+    Banned,
+}
+
+/// Per-sender counters against a set of [`RateLimits`]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RateLimiter {
+    pub limits: RateLimits,
+    #[serde(with = "serde_json_any_key::any_key_map")]
+    peers: BTreeMap<Identity, PeerWindow>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: RateLimits) -> Self {
+        RateLimiter {
+            limits,
+            peers: BTreeMap::new(),
+        }
+    }
+
+    /// Counts a message of `kind` from `sender` at time `now`, with windows
+    /// and bans measured in multiples of `delta`
+    pub fn admit(
+        &mut self,
+        sender: &Identity,
+        kind: MessageKind,
+        now: u128,
+        delta: u128,
+    ) -> Admission {
+        let limits = &self.limits;
+        let peer = self
+            .peers
+            .entry(sender.clone())
+            .or_insert_with(|| PeerWindow {
+                start: now,
+                ..PeerWindow::default()
+            });
+
+        if let Some(until) = peer.stats.banned_until {
+            if now < until {
+                peer.stats.dropped_while_banned += 1;
+                return Admission::Banned;
+            }
+            peer.stats.banned_until = None;
+        }
+
+        if now >= peer.start + limits.window * delta {
+            peer.start = now;
+            peer.counts.clear();
+            peer.violated = false;
+        }
+
+        let count = peer.counts.entry(kind).or_default();
+        if limits
+            .per_kind
+            .get(&kind)
+            .is_some_and(|limit| *count >= *limit)
+        {
+            peer.stats.limited += 1;
+            if !peer.violated {
+                peer.violated = true;
+                peer.stats.violations += 1;
+                peer.strikes += 1;
+                if limits.ban_after.is_some_and(|after| peer.strikes >= after) {
+                    peer.strikes = 0;
+                    peer.stats.bans += 1;
+                    peer.stats.banned_until = Some(now + limits.ban_for * delta);
+                }
+            }
+            return Admission::OverLimit;
+        }
+        *count += 1;
+        peer.stats.accepted += 1;
+        Admission::Accepted
+    }
+
+    pub fn peer_stats(&self, peer: &Identity) -> Option<PeerRateStats> {
+        self.peers.get(peer).map(|peer| peer.stats)
+    }
+
+    pub fn stats(&self) -> BTreeMap<Identity, PeerRateStats> {
+        self.peers
+            .iter()
+            .map(|(id, peer)| (id.clone(), peer.stats))
+            .collect()
+    }
+}
+
+impl<Tr: Transaction> MorpheusProcess<Tr> {
+    /// Starts limiting each sender's messages, with fresh counters, or stops
+    pub fn set_rate_limits(&mut self, limits: Option<RateLimits>) {
+        self.rate_limiter = limits.map(RateLimiter::new);
+    }
+
+    /// What was done with each sender's messages since limits were set
+    pub fn rate_limit_stats(&self) -> BTreeMap<Identity, PeerRateStats> {
+        self.rate_limiter
+            .as_ref()
+            .map(RateLimiter::stats)
+            .unwrap_or_default()
+    }
+
+    /// Senders banned right now
+    pub fn banned_peers(&self) -> Vec<Identity> {
+        self.rate_limit_stats()
+            .into_iter()
+            .filter(|(_, stats)| {
+                stats
+                    .banned_until
+                    .is_some_and(|until| self.current_time < until)
+            })
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// Whether a message of `kind` from `sender` is within its limits
+    pub(crate) fn admit_from(&mut self, sender: &Identity, kind: MessageKind) -> bool {
+        if sender == &self.id {
+            return true;
+        }
+        let Some(limiter) = &mut self.rate_limiter else {
+            return true;
+        };
+        match limiter.admit(sender, kind, self.current_time, self.delta) {
+            Admission::Accepted => true,
+            Admission::OverLimit => {
+                let stats = limiter.peer_stats(sender).expect("just counted");
+                tracing::debug!(
+                    target: "rate_limited",
+                    process_id = ?self.id,
+                    sender = ?sender,
+                    kind = ?kind,
+                );
+                if stats.banned_until.is_some() {
+                    tracing::warn!(
+                        target: "peer_banned",
+                        process_id = ?self.id,
+                        sender = ?sender,
+                        until = ?stats.banned_until,
+                        violations = stats.violations,
+                    );
+                }
+                false
+            }
+            Admission::Banned => false,
+        }
+    }
+}
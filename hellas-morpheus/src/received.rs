@@ -0,0 +1,94 @@
+//! What a process knows about the messages it handled
+//!
+//! Every message a process handled used to be kept whole, for the invariant
+//! checks, and the set only ever grew. Everything in it is already held
+//! elsewhere: blocks in the index, QCs in `qcs`, votes in `vote_tracker`. A
+//! [`ReceivedMessages`] keeps just what the checks and the tooling read back:
+//!
+//! - how many messages of each kind were handled,
+//! - who each vote we handled came from, without its signature, so the
+//!   invariants can compare it against `vote_tracker`,
+//! - the last [`RECEIVED_HISTORY`] messages whole, to catch duplicates and to
+//!   show what arrived lately.
+//!
+//! A duplicate older than the history is handed to the handlers again, which
+//! treat it as already known, as they do in release builds anyway.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+/// How many of the latest messages are kept whole
+pub const RECEIVED_HISTORY: usize = 1024;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReceivedMessages<Tr: Transaction> {
+    counts: BTreeMap<MessageKind, u64>,
+    /// Authors of the votes handled, for each vote
+    #[serde(with = "serde_json_any_key::any_key_map")]
+    voters: BTreeMap<VoteData, BTreeSet<Identity>>,
+    recent: VecDeque<Message<Tr>>,
+}
+
+impl<Tr: Transaction> Default for ReceivedMessages<Tr> {
+    fn default() -> Self {
+        ReceivedMessages {
+            counts: BTreeMap::new(),
+            voters: BTreeMap::new(),
+            recent: VecDeque::new(),
+        }
+    }
+}
+
+impl<Tr: Transaction> ReceivedMessages<Tr> {
+    pub fn insert(&mut self, message: Message<Tr>) {
+        *self.counts.entry(message.kind()).or_default() += 1;
+        if let Message::NewVote(vote) = &message {
+            self.voters
+                .entry(vote.data.clone())
+                .or_default()
+                .insert(vote.author.clone());
+        }
+        if self.recent.len() == RECEIVED_HISTORY {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(message);
+    }
+
+    /// Whether `message` is among the latest [`RECEIVED_HISTORY`] handled
+    pub fn contains(&self, message: &Message<Tr>) -> bool {
+        self.recent.contains(message)
+    }
+
+    /// Messages of `kind` handled
+    pub fn count(&self, kind: MessageKind) -> u64 {
+        self.counts.get(&kind).copied().unwrap_or(0)
+    }
+
+    /// Messages handled, of every kind
+    pub fn total(&self) -> u64 {
+        self.counts.values().sum()
+    }
+
+    /// Who each vote we handled came from
+    pub fn voters(&self) -> &BTreeMap<VoteData, BTreeSet<Identity>> {
+        &self.voters
+    }
+
+    /// The latest [`RECEIVED_HISTORY`] messages handled, oldest first
+    pub fn recent(&self) -> impl Iterator<Item = &Message<Tr>> {
+        self.recent.iter()
+    }
+}
+
+impl<Tr: Transaction> FromIterator<Message<Tr>> for ReceivedMessages<Tr> {
+    fn from_iter<I: IntoIterator<Item = Message<Tr>>>(messages: I) -> Self {
+        let mut received = ReceivedMessages::default();
+        for message in messages {
+            received.insert(message);
+        }
+        received
+    }
+}
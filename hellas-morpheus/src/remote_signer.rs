@@ -0,0 +1,212 @@
+//! Signing over a local socket, for keys held by another process.
+//!
+//! [`RemoteSigner`] forwards each request to a [`SignerService`], which holds
+//! the key. The protocol is one request per connection. A request is
+//! `id: u64 | len: u32 | payload | tag`, a response is
+//! `id: u64 | status: u8 | len: u32 | body | tag`, integers little-endian and
+//! `tag` an HMAC-SHA256 (under a token both sides share) of everything before
+//! it. The body of a successful response is the compressed partial signature.
+//!
+//! Retries reuse the request id, and the service answers a repeated id with
+//! the same signature and refuses to reuse an id for different data, so a
+//! retry can never produce a second signature.
+
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use sha2::{Digest, Sha256};
+
+use crate::{SecretKey, SignError, Signer};
+
+/// Largest payload or response body either side accepts
+pub const MAX_SIGN_PAYLOAD: u32 = 1 << 20;
+
+const STATUS_OK: u8 = 0;
+const STATUS_REFUSED: u8 = 1;
+
+/// Forwards sign requests to a [`SignerService`] listening on a Unix socket
+pub struct RemoteSigner {
+    pub path: PathBuf,
+    token: Vec<u8>,
+    /// Applies to each of connecting, writing the request and reading the answer
+    pub timeout: Duration,
+    /// Times a request is sent before giving up on a transient failure
+    pub attempts: u32,
+    next_id: AtomicU64,
+}
+
+impl RemoteSigner {
+    pub fn new(path: impl Into<PathBuf>, token: impl Into<Vec<u8>>) -> Self {
+        // ids must not repeat across restarts, or the service would refuse them
+        let start = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        RemoteSigner {
+            path: path.into(),
+            token: token.into(),
+            timeout: Duration::from_millis(500),
+            attempts: 3,
+            next_id: AtomicU64::new(start),
+        }
+    }
+
+    fn request(&self, id: u64, message: &[u8]) -> Result<hints::PartialSignature, SignError> {
+        let mut stream = UnixStream::connect(&self.path)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+
+        let mut request = Vec::with_capacity(12 + message.len() + 32);
+        request.extend_from_slice(&id.to_le_bytes());
+        request.extend_from_slice(&(message.len() as u32).to_le_bytes());
+        request.extend_from_slice(message);
+        let tag = hmac(&self.token, &request);
+        request.extend_from_slice(&tag);
+        stream.write_all(&request)?;
+
+        let (header, body) = read_frame(&mut stream, 9, &self.token)?;
+        if header[..8] != id.to_le_bytes() {
+            return Err(SignError::Unauthenticated);
+        }
+        match header[8] {
+            STATUS_OK => hints::PartialSignature::deserialize_compressed(&body[..])
+                .map_err(|e| SignError::Malformed(e.to_string())),
+            STATUS_REFUSED => Err(SignError::Refused(
+                String::from_utf8_lossy(&body).into_owned(),
+            )),
+            status => Err(SignError::Malformed(format!("unknown status {}", status))),
+        }
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn sign(&self, message: &[u8]) -> Result<hints::PartialSignature, SignError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut last = SignError::Timeout;
+        for attempt in 0..self.attempts.max(1) {
+            match self.request(id, message) {
+                Ok(signature) => return Ok(signature),
+                Err(e) if e.is_transient() => {
+                    tracing::warn!(target: "remote_signer", id, attempt, error = %e);
+                    last = e;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last)
+    }
+}
+
+/// The key-holding end of [`RemoteSigner`]
+pub struct SignerService {
+    key: SecretKey,
+    token: Vec<u8>,
+    /// Digest of the payload signed under each request id
+    answered: BTreeMap<u64, [u8; 32]>,
+}
+
+impl SignerService {
+    pub fn new(key: SecretKey, token: impl Into<Vec<u8>>) -> Self {
+        SignerService {
+            key,
+            token: token.into(),
+            answered: BTreeMap::new(),
+        }
+    }
+
+    /// Answers connections on `listener` until accepting fails
+    pub fn serve(&mut self, listener: &UnixListener) -> io::Result<()> {
+        loop {
+            let (mut stream, _) = listener.accept()?;
+            // a client that stalls mid-request mustn't block everyone else
+            stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+            if let Err(e) = self.handle(&mut stream) {
+                tracing::warn!(target: "signer_service", error = %e);
+            }
+        }
+    }
+
+    /// Answers the single request on `stream`
+    pub fn handle(&mut self, stream: &mut UnixStream) -> Result<(), SignError> {
+        let (header, payload) = read_frame(stream, 8, &self.token)?;
+        let id = u64::from_le_bytes(header.try_into().unwrap());
+
+        let digest: [u8; 32] = Sha256::digest(&payload).into();
+        let (status, body) = match self.answered.get(&id) {
+            Some(signed) if *signed != digest => (
+                STATUS_REFUSED,
+                b"request id reused for different data".to_vec(),
+            ),
+            _ => match self.key.expose() {
+                None => (STATUS_REFUSED, b"no secret key loaded".to_vec()),
+                Some(key) => {
+                    self.answered.insert(id, digest);
+                    let mut body = Vec::new();
+                    hints::sign(key, &payload)
+                        .serialize_compressed(&mut body)
+                        .map_err(|e| SignError::Malformed(e.to_string()))?;
+                    (STATUS_OK, body)
+                }
+            },
+        };
+
+        let mut response = Vec::with_capacity(13 + body.len() + 32);
+        response.extend_from_slice(&id.to_le_bytes());
+        response.push(status);
+        response.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        response.extend_from_slice(&body);
+        let tag = hmac(&self.token, &response);
+        response.extend_from_slice(&tag);
+        stream.write_all(&response)?;
+        Ok(())
+    }
+}
+
+/// Reads `header_len` bytes, a length-prefixed body and a tag over both,
+/// returning the header and body once the tag checks out
+fn read_frame(
+    stream: &mut UnixStream,
+    header_len: usize,
+    token: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>), SignError> {
+    let mut frame = vec![0; header_len + 4];
+    stream.read_exact(&mut frame)?;
+    let len = u32::from_le_bytes(frame[header_len..].try_into().unwrap());
+    if len > MAX_SIGN_PAYLOAD {
+        return Err(SignError::Malformed(format!("{} byte body", len)));
+    }
+    let mut body = vec![0; len as usize];
+    stream.read_exact(&mut body)?;
+    frame.extend_from_slice(&body);
+
+    let mut tag = [0; 32];
+    stream.read_exact(&mut tag)?;
+    if hmac(token, &frame) != tag {
+        return Err(SignError::Unauthenticated);
+    }
+    frame.truncate(header_len);
+    Ok((frame, body))
+}
+
+/// HMAC-SHA256 (RFC 2104)
+fn hmac(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; 64];
+    if key.len() > 64 {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let inner = Sha256::new()
+        .chain_update(block.map(|b| b ^ 0x36))
+        .chain_update(message)
+        .finalize();
+    Sha256::new()
+        .chain_update(block.map(|b| b ^ 0x5c))
+        .chain_update(inner)
+        .finalize()
+        .into()
+}
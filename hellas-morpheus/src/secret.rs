@@ -0,0 +1,110 @@
+//! Keeping signing keys out of logs and snapshots
+//!
+//! A process holds its signing key, any keys it rotated to and the
+//! committee's election key, and a process is routinely printed, serialized
+//! into snapshots and traces, and cloned. Each of those keys is held as a
+//! [`SecretKey`], which prints as `<redacted>`, serializes as `null`, and
+//! overwrites the key with zeroes when dropped.
+//!
+//! Deserializing gives an empty `SecretKey`: a process restored from a
+//! snapshot has no keys until they are loaded again from wherever the
+//! operator keeps them, and signing fails with `SignError::MissingKey` until
+//! then. The few places that must persist a key, such as a trace that will
+//! be replayed, opt in field by field with `#[serde(with = "exposed")]`.
+//!
+//! Zeroing on drop covers the copies a `SecretKey` owns. Copies the key
+//! library makes while signing, and stack copies left behind when a key is
+//! moved, are out of its reach.
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{Ordering, compiler_fence};
+
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+// zeroing the bytes in place is only sound for a key without drop glue,
+// which would otherwise run on the zeroes
+const _: () = assert!(!std::mem::needs_drop::<hints::SecretKey>());
+
+/// A signing key that doesn't leave the process by accident
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecretKey(Option<hints::SecretKey>);
+
+impl SecretKey {
+    pub fn new(key: hints::SecretKey) -> Self {
+        SecretKey(Some(key))
+    }
+
+    /// The key, unless this was restored from serialized state
+    pub fn expose(&self) -> Option<&hints::SecretKey> {
+        self.0.as_ref()
+    }
+
+    pub fn is_present(&self) -> bool {
+        self.0.is_some()
+    }
+}
+
+impl From<hints::SecretKey> for SecretKey {
+    fn from(key: hints::SecretKey) -> Self {
+        SecretKey::new(key)
+    }
+}
+
+impl Drop for SecretKey {
+    fn drop(&mut self) {
+        if let Some(key) = &mut self.0 {
+            let bytes = key as *mut hints::SecretKey as *mut u8;
+            for i in 0..std::mem::size_of::<hints::SecretKey>() {
+                // SAFETY: in bounds of `key`, which is never read again and
+                // has no drop glue to run on what we leave behind
+                unsafe { std::ptr::write_volatile(bytes.add(i), 0) };
+            }
+            compiler_fence(Ordering::SeqCst);
+        }
+    }
+}
+
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(_) => write!(f, "SecretKey(<redacted>)"),
+            None => write!(f, "SecretKey(<missing>)"),
+        }
+    }
+}
+
+/// Only whether there is a key, so hashes don't depend on it
+impl Hash for SecretKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.is_some().hash(state);
+    }
+}
+
+impl Serialize for SecretKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_none()
+    }
+}
+
+/// Always empty, whatever was stored
+impl<'de> Deserialize<'de> for SecretKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        IgnoredAny::deserialize(deserializer)?;
+        Ok(SecretKey::default())
+    }
+}
+
+/// Serializes the key itself, for fields that must persist it
+pub mod exposed {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(key: &SecretKey, serializer: S) -> Result<S::Ok, S::Error> {
+        key.0.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SecretKey, D::Error> {
+        Option::<hints::SecretKey>::deserialize(deserializer).map(SecretKey)
+    }
+}
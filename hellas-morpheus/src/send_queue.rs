@@ -0,0 +1,207 @@
+//! Per-peer outbound queues for a transport driver
+//!
+//! A driver that writes every outgoing message to one shared path lets a
+//! single slow peer hold up everyone else. [`SendQueues`] gives each peer its
+//! own bounded queue and hands messages out round robin across peers, so a
+//! backed-up peer only delays itself.
+//!
+//! When a queue is full, the oldest non-critical message in it is dropped to
+//! make room. Votes and end-view messages are non-critical: each is one of
+//! many that together form a certificate, and a view that stalls for want of
+//! them ends by timeout. Blocks and certificates are critical and are never
+//! dropped; if a queue holds nothing else, sending another critical message
+//! to that peer fails and the driver has to deal with the peer (typically by
+//! disconnecting it).
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::ops::Bound;
+
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+/// Queue length per peer unless configured otherwise
+pub const DEFAULT_SEND_QUEUE: usize = 1024;
+
+impl MessageKind {
+    /// Whether a full send queue must keep this message rather than drop it
+    pub fn is_critical(self) -> bool {
+        !matches!(self, MessageKind::NewVote | MessageKind::EndView)
+    }
+}
+
+/// Backlog and drop counts for one peer's queue
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerBacklog {
+    /// Messages waiting to be sent
+    pub queued: usize,
+    /// Of those, how many are critical
+    pub critical: usize,
+    /// Most messages ever waiting at once
+    pub high_water: usize,
+    pub sent: u64,
+    /// Non-critical messages dropped to make room
+    pub dropped: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SendQueueError {
+    /// The peer's queue is full of critical messages
+    Full { peer: Identity, kind: MessageKind },
+}
+
+impl fmt::Display for SendQueueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendQueueError::Full { peer, kind } => write!(
+                f,
+                "send queue to process {} is full of critical messages, can't queue {:?}",
+                peer.0, kind
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SendQueueError {}
+
+struct PeerQueue<Tr: Transaction> {
+    messages: VecDeque<Message<Tr>>,
+    backlog: PeerBacklog,
+}
+
+impl<Tr: Transaction> Default for PeerQueue<Tr> {
+    fn default() -> Self {
+        PeerQueue {
+            messages: VecDeque::new(),
+            backlog: PeerBacklog::default(),
+        }
+    }
+}
+
+pub struct SendQueues<Tr: Transaction> {
+    capacity: usize,
+    queues: BTreeMap<Identity, PeerQueue<Tr>>,
+    /// The peer served last; the next message comes from the one after it
+    last_served: Option<Identity>,
+}
+
+impl<Tr: Transaction> Default for SendQueues<Tr> {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEND_QUEUE)
+    }
+}
+
+impl<Tr: Transaction> SendQueues<Tr> {
+    pub fn new(capacity: usize) -> Self {
+        SendQueues {
+            capacity: capacity.max(1),
+            queues: BTreeMap::new(),
+            last_served: None,
+        }
+    }
+
+    /// Queues `message` for `peer`, returning the message dropped to make room, if any
+    pub fn enqueue(
+        &mut self,
+        peer: Identity,
+        message: Message<Tr>,
+    ) -> Result<Option<Message<Tr>>, SendQueueError> {
+        let queue = self.queues.entry(peer.clone()).or_default();
+        let critical = message.kind().is_critical();
+
+        let mut dropped = None;
+        if queue.messages.len() >= self.capacity {
+            match queue
+                .messages
+                .iter()
+                .position(|queued| !queued.kind().is_critical())
+            {
+                Some(oldest) => dropped = queue.messages.remove(oldest),
+                // nothing older may go, so a non-critical newcomer is what's dropped
+                None if !critical => {
+                    queue.backlog.dropped += 1;
+                    return Ok(Some(message));
+                }
+                None => {
+                    return Err(SendQueueError::Full {
+                        peer,
+                        kind: message.kind(),
+                    });
+                }
+            }
+            queue.backlog.dropped += 1;
+        }
+
+        queue.messages.push_back(message);
+        queue.backlog.queued = queue.messages.len();
+        queue.backlog.critical += critical as usize;
+        queue.backlog.high_water = queue.backlog.high_water.max(queue.backlog.queued);
+        Ok(dropped)
+    }
+
+    /// [`Self::enqueue`] for each of `peers`, returning those whose queue was full
+    pub fn broadcast<'a>(
+        &mut self,
+        peers: impl IntoIterator<Item = &'a Identity>,
+        message: &Message<Tr>,
+    ) -> Vec<SendQueueError> {
+        peers
+            .into_iter()
+            .filter_map(|peer| self.enqueue(peer.clone(), message.clone()).err())
+            .collect()
+    }
+
+    /// The next message to send, taking one from each peer with a backlog in turn
+    pub fn next(&mut self) -> Option<(Identity, Message<Tr>)> {
+        let after = match &self.last_served {
+            Some(last) => Bound::Excluded(last.clone()),
+            None => Bound::Unbounded,
+        };
+        let peer = self
+            .queues
+            .range((after, Bound::Unbounded))
+            .chain(self.queues.iter())
+            .find(|(_, queue)| !queue.messages.is_empty())
+            .map(|(peer, _)| peer.clone())?;
+
+        let queue = self.queues.get_mut(&peer).unwrap();
+        let message = queue.messages.pop_front().unwrap();
+        queue.backlog.queued = queue.messages.len();
+        queue.backlog.critical -= message.kind().is_critical() as usize;
+        queue.backlog.sent += 1;
+        self.last_served = Some(peer.clone());
+        Some((peer, message))
+    }
+
+    /// Up to `max` messages, in the order [`Self::next`] gives them
+    pub fn drain(&mut self, max: usize) -> Vec<(Identity, Message<Tr>)> {
+        std::iter::from_fn(|| self.next()).take(max).collect()
+    }
+
+    /// Forgets a disconnected peer, returning what was still queued for it
+    pub fn remove_peer(&mut self, peer: &Identity) -> Vec<Message<Tr>> {
+        self.queues
+            .remove(peer)
+            .map(|queue| queue.messages.into())
+            .unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queues.values().all(|queue| queue.messages.is_empty())
+    }
+
+    pub fn backlog(&self, peer: &Identity) -> PeerBacklog {
+        self.queues
+            .get(peer)
+            .map(|queue| queue.backlog)
+            .unwrap_or_default()
+    }
+
+    pub fn backlogs(&self) -> BTreeMap<Identity, PeerBacklog> {
+        self.queues
+            .iter()
+            .map(|(peer, queue)| (peer.clone(), queue.backlog))
+            .collect()
+    }
+}
@@ -0,0 +1,442 @@
+//! Double-sign protection that survives restarts.
+//!
+//! A [`SignGuard`] records, in its own file, what this process has signed at
+//! each position (a vote's slot, one of our block slots, a view we started or
+//! ended), much like Tendermint's `priv_validator_state.json`. Before any
+//! signature is produced the guard is asked whether the data may be signed:
+//! signing the exact same data at a position again is fine, anything else
+//! there is refused. Only the last [`GUARD_WINDOW`] positions of each stream
+//! are remembered; everything below them is refused outright.
+//!
+//! The record is written (and synced) before the signature is produced, and
+//! the guard holds an exclusive lock on `<path>.lock` for as long as it is
+//! open, so two nodes can't be pointed at the same state. The state carries
+//! a checksum: a guard refuses to open on a corrupt record rather than start
+//! over and forget what it signed. Other backends plug in as a
+//! [`GuardStore`].
+//!
+//! The state is stamped with its format version, [`GUARD_FORMAT`]. States
+//! written by older builds are migrated when the guard opens them (see
+//! `migrations.rs`): version 0 predates the checksum, version 1 predates
+//! the stamp.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::*;
+
+/// Positions remembered per stream before the floor is raised
+pub const GUARD_WINDOW: usize = 256;
+
+/// The version of the state this build writes
+pub const GUARD_FORMAT: u32 = 2;
+
+/// A sequence of positions of which each may be signed only once
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum SignStream {
+    /// Our `z`-votes on blocks of this type by `author`, by slot
+    Vote {
+        z: u8,
+        type_: BlockType,
+        author: Identity,
+    },
+    /// Our blocks of this type, by slot
+    Block(BlockType),
+    /// Our start-view messages, by view
+    StartView,
+    /// Our end-view messages, by view
+    EndView,
+    /// Our key rotations, by the view they take effect in
+    KeyRotation,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct SignPosition {
+    pub stream: SignStream,
+    pub at: i64,
+}
+
+impl fmt::Display for SignPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}@{}", self.stream, self.at)
+    }
+}
+
+/// Data a process signs, and where in its history that signature sits
+pub trait Positioned {
+    fn sign_position(&self) -> SignPosition;
+}
+
+impl Positioned for VoteData {
+    fn sign_position(&self) -> SignPosition {
+        let key = VoteKey::for_block(self.z, &self.for_which).expect("no votes on genesis");
+        SignPosition {
+            stream: SignStream::Vote {
+                z: key.z,
+                type_: key.type_,
+                author: key.author,
+            },
+            at: key.slot.0 as i64,
+        }
+    }
+}
+
+impl<Tr: Transaction> Positioned for Block<Tr> {
+    fn sign_position(&self) -> SignPosition {
+        SignPosition {
+            stream: SignStream::Block(self.key.type_),
+            at: self.key.slot.0 as i64,
+        }
+    }
+}
+
+impl Positioned for StartView {
+    fn sign_position(&self) -> SignPosition {
+        SignPosition {
+            stream: SignStream::StartView,
+            at: self.view.0,
+        }
+    }
+}
+
+impl Positioned for KeyRotation {
+    fn sign_position(&self) -> SignPosition {
+        SignPosition {
+            stream: SignStream::KeyRotation,
+            at: self.effective.0,
+        }
+    }
+}
+
+/// The only `ViewNum` we sign is the one in our end-view message
+impl Positioned for ViewNum {
+    fn sign_position(&self) -> SignPosition {
+        SignPosition {
+            stream: SignStream::EndView,
+            at: self.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GuardError {
+    /// Another guard holds the lock on this state
+    Locked(PathBuf),
+    Io(String),
+    Corrupt(String),
+    /// Different data was already signed at this position
+    Conflict(SignPosition),
+    /// The position is older than anything the guard still remembers
+    BelowFloor(SignPosition),
+}
+
+impl fmt::Display for GuardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GuardError::Locked(path) => write!(f, "{} is locked by another guard", path.display()),
+            GuardError::Io(e) => write!(f, "sign guard I/O failed: {}", e),
+            GuardError::Corrupt(e) => write!(f, "sign guard state is corrupt: {}", e),
+            GuardError::Conflict(position) => {
+                write!(f, "refusing to sign different data at {}", position)
+            }
+            GuardError::BelowFloor(position) => {
+                write!(
+                    f,
+                    "refusing to sign at {}, below the guard's floor",
+                    position
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for GuardError {}
+
+impl From<io::Error> for GuardError {
+    fn from(e: io::Error) -> Self {
+        GuardError::Io(e.to_string())
+    }
+}
+
+impl From<MigrationError> for GuardError {
+    fn from(e: MigrationError) -> Self {
+        match e {
+            MigrationError::Io(e) => GuardError::Io(e),
+            e => GuardError::Corrupt(e.to_string()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct StreamState {
+    /// Lowest position that may still be signed
+    floor: i64,
+    /// Digest of what was signed at each remembered position
+    signed: BTreeMap<i64, [u8; 32]>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct GuardState {
+    #[serde(with = "serde_json_any_key::any_key_map")]
+    streams: BTreeMap<SignStream, StreamState>,
+}
+
+/// What a store holds: the state, and a checksum so a corrupt read is
+/// refused instead of quietly forgetting what was signed
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Persisted {
+    version: u32,
+    state: GuardState,
+    checksum: [u8; 32],
+}
+
+impl GuardState {
+    fn checksum(&self) -> Result<[u8; 32], GuardError> {
+        let json = serde_json::to_vec(self).map_err(|e| GuardError::Corrupt(e.to_string()))?;
+        Ok(Sha256::digest(&json).into())
+    }
+
+    /// Reads a state of the current version
+    fn decode(bytes: &[u8]) -> Result<Self, GuardError> {
+        let persisted = serde_json::from_slice::<Persisted>(bytes)
+            .map_err(|e| GuardError::Corrupt(e.to_string()))?;
+        if persisted.state.checksum()? != persisted.checksum {
+            return Err(GuardError::Corrupt("checksum mismatch".to_string()));
+        }
+        Ok(persisted.state)
+    }
+
+    fn encode(&self) -> Result<Vec<u8>, GuardError> {
+        let persisted = Persisted {
+            version: GUARD_FORMAT,
+            checksum: self.checksum()?,
+            state: self.clone(),
+        };
+        serde_json::to_vec(&persisted).map_err(|e| GuardError::Corrupt(e.to_string()))
+    }
+}
+
+/// `path` with `suffix` added to its file name, so files next to a state
+/// named `node.guard` and a log named `node.wal` don't clash
+pub(crate) fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Where a [`SignGuard`] keeps its state
+pub trait GuardStore: Send {
+    /// The state last stored, or `None` if nothing has been yet
+    fn load(&mut self) -> io::Result<Option<Vec<u8>>>;
+
+    /// Replaces the state; once this returns `Ok`, it survives a crash
+    fn store(&mut self, bytes: &[u8]) -> io::Result<()>;
+}
+
+/// Keeps the state in a file, holding an exclusive lock on `<path>.lock`
+pub struct FileStore {
+    path: PathBuf,
+    /// Held (and locked) for as long as the store is open
+    _lock: File,
+}
+
+impl FileStore {
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, GuardError> {
+        let path = path.into();
+        let lock_path = with_suffix(&path, ".lock");
+        let lock = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)?;
+        if lock.try_lock().is_err() {
+            return Err(GuardError::Locked(lock_path));
+        }
+        Ok(FileStore { path, _lock: lock })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl GuardStore for FileStore {
+    fn load(&mut self) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(&self.path) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Writes the state next to its file and renames it into place
+    fn store(&mut self, bytes: &[u8]) -> io::Result<()> {
+        let tmp = with_suffix(&self.path, ".tmp");
+        let mut file = File::create(&tmp)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+        fs::rename(&tmp, &self.path)
+    }
+}
+
+/// Keeps the state in memory, shared by its clones, so a test can "restart"
+/// by opening a new guard on a clone
+#[derive(Clone, Default)]
+pub struct MemoryStore(Arc<Mutex<Option<Vec<u8>>>>);
+
+impl GuardStore for MemoryStore {
+    fn load(&mut self) -> io::Result<Option<Vec<u8>>> {
+        Ok(self.0.lock().unwrap().clone())
+    }
+
+    fn store(&mut self, bytes: &[u8]) -> io::Result<()> {
+        *self.0.lock().unwrap() = Some(bytes.to_vec());
+        Ok(())
+    }
+}
+
+pub struct SignGuard {
+    store: Box<dyn GuardStore>,
+    /// The file behind `store`, if it is a [`FileStore`]
+    path: Option<PathBuf>,
+    state: GuardState,
+}
+
+impl SignGuard {
+    /// Opens the state at `path`, creating it if it doesn't exist yet
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, GuardError> {
+        let store = FileStore::open(path)?;
+        let path = store.path().to_path_buf();
+        let mut guard = Self::with_store(store)?;
+        guard.path = Some(path);
+        Ok(guard)
+    }
+
+    /// Opens the state in `store`, starting empty if it holds none and
+    /// migrating it first if an older build wrote it
+    pub fn with_store(mut store: impl GuardStore + 'static) -> Result<Self, GuardError> {
+        let state = match store.load()? {
+            Some(bytes) => {
+                let (bytes, plan) =
+                    MigrationRegistry::builtin().migrate(StoreKind::SignGuard, bytes)?;
+                let state = GuardState::decode(&bytes)?;
+                if !plan.is_empty() {
+                    store.store(&bytes)?;
+                }
+                state
+            }
+            None => GuardState::default(),
+        };
+        Ok(SignGuard {
+            store: Box::new(store),
+            path: None,
+            state,
+        })
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Whether `message` may be signed at `position`, recording it durably if so
+    pub fn check(&mut self, position: &SignPosition, message: &[u8]) -> Result<(), GuardError> {
+        let digest: [u8; 32] = Sha256::digest(message).into();
+        let stream = self
+            .state
+            .streams
+            .entry(position.stream.clone())
+            .or_default();
+
+        if position.at < stream.floor {
+            return Err(GuardError::BelowFloor(position.clone()));
+        }
+        match stream.signed.get(&position.at) {
+            Some(signed) if *signed == digest => return Ok(()),
+            Some(_) => return Err(GuardError::Conflict(position.clone())),
+            None => {}
+        }
+
+        let mut updated = stream.clone();
+        updated.signed.insert(position.at, digest);
+        while updated.signed.len() > GUARD_WINDOW {
+            let (lowest, _) = updated.signed.pop_first().unwrap();
+            updated.floor = lowest + 1;
+        }
+
+        let previous = std::mem::replace(stream, updated);
+        if let Err(e) = self.persist() {
+            // not recorded, so not signed: forget it again
+            self.state.streams.insert(position.stream.clone(), previous);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    fn persist(&mut self) -> Result<(), GuardError> {
+        let bytes = self.state.encode()?;
+        self.store.store(&bytes)?;
+        Ok(())
+    }
+}
+
+/// The versions of the guard's state, for the [`MigrationRegistry`]
+pub(crate) fn schema() -> StoreSchema {
+    StoreSchema {
+        current: GUARD_FORMAT,
+        detect,
+        migrations: vec![
+            Migration {
+                from: 0,
+                description: "add a checksum of the state",
+                apply: add_checksum,
+            },
+            Migration {
+                from: 1,
+                description: "stamp the format version",
+                apply: stamp_version,
+            },
+        ],
+    }
+}
+
+fn detect(bytes: &[u8]) -> Result<u32, String> {
+    let value = serde_json::from_slice::<serde_json::Value>(bytes).map_err(|e| e.to_string())?;
+    let serde_json::Value::Object(fields) = value else {
+        return Err("not a JSON object".to_string());
+    };
+    match fields.get("version") {
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| format!("version {}", version)),
+        None if fields.contains_key("checksum") => Ok(1),
+        None => Ok(0),
+    }
+}
+
+/// Version 0 is the bare state
+fn add_checksum(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let state = serde_json::from_slice::<GuardState>(bytes).map_err(|e| e.to_string())?;
+    let checksum = state.checksum().map_err(|e| e.to_string())?;
+    serde_json::to_vec(&serde_json::json!({ "state": state, "checksum": checksum }))
+        .map_err(|e| e.to_string())
+}
+
+/// Version 1 is `{ state, checksum }`
+fn stamp_version(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let mut value =
+        serde_json::from_slice::<serde_json::Value>(bytes).map_err(|e| e.to_string())?;
+    value
+        .as_object_mut()
+        .ok_or("not a JSON object")?
+        .insert("version".to_string(), 2.into());
+    serde_json::to_vec(&value).map_err(|e| e.to_string())
+}
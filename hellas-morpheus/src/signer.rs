@@ -0,0 +1,208 @@
+//! Signing with keys held outside the process.
+//!
+//! By default a process signs with `KeyBook::me_sec_key`. Operators keeping
+//! the key in an HSM or a separate process instead give it a [`Signer`] (see
+//! `crypto.rs`), such as a `RemoteSigner` (see `remote_signer.rs`), and may
+//! likewise give it a [`Verifier`] in place of its `KeyBook`.
+//!
+//! Backends that may block indefinitely, such as an HSM behind a vendor
+//! library, can be wrapped in a [`WorkerSigner`]: it signs on a thread of its
+//! own and gives up after a deadline, so the protocol never waits on it for
+//! longer than that.
+//!
+//! A signer that fails may still have signed, so nothing that calls one may
+//! sign *different* data in its place: votes are marked in `voted_i` and block
+//! slots advanced before signing, and a failed signature just means that
+//! message is never sent. A `SignGuard` (see `sign_guard.rs`) extends this
+//! across restarts.
+
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::time::Duration;
+
+use ark_serialize::{CanonicalDeserialize, Valid};
+
+use crate::*;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SignError {
+    /// No answer within the signer's timeout; the request may have been signed
+    Timeout,
+    Io(String),
+    /// A response whose tag didn't verify, or that answered another request
+    Unauthenticated,
+    Malformed(String),
+    /// The service declined to sign
+    Refused(String),
+    /// The `SignGuard` refused, or couldn't record the signature
+    Guard(GuardError),
+    /// No key to sign with, as after restoring from a snapshot
+    MissingKey,
+}
+
+impl SignError {
+    /// Whether resending the same request might succeed
+    pub fn is_transient(&self) -> bool {
+        matches!(self, SignError::Timeout | SignError::Io(_))
+    }
+}
+
+impl fmt::Display for SignError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignError::Timeout => write!(f, "signer timed out"),
+            SignError::Io(e) => write!(f, "signer connection failed: {}", e),
+            SignError::Unauthenticated => write!(f, "signer response failed authentication"),
+            SignError::Malformed(e) => write!(f, "malformed signer message: {}", e),
+            SignError::Refused(reason) => write!(f, "signer refused: {}", reason),
+            SignError::Guard(e) => write!(f, "{}", e),
+            SignError::MissingKey => write!(f, "no secret key loaded"),
+        }
+    }
+}
+
+impl std::error::Error for SignError {}
+
+impl From<io::Error> for SignError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => SignError::Timeout,
+            _ => SignError::Io(e.to_string()),
+        }
+    }
+}
+
+type SignRequest = (
+    Vec<u8>,
+    SyncSender<Result<hints::PartialSignature, SignError>>,
+);
+
+/// Runs another signer on a worker thread, failing any request it hasn't
+/// answered within `timeout`
+///
+/// Requests are handled one at a time. While the backend is stuck on one,
+/// at most `backlog` more wait for it; anything beyond that fails at once
+/// with [`SignError::Timeout`].
+pub struct WorkerSigner {
+    requests: SyncSender<SignRequest>,
+    pub timeout: Duration,
+}
+
+impl WorkerSigner {
+    pub fn new(inner: Arc<dyn Signer>, timeout: Duration, backlog: usize) -> Self {
+        let (requests, incoming) = mpsc::sync_channel(backlog);
+        std::thread::spawn(move || Self::work(inner, incoming));
+        WorkerSigner { requests, timeout }
+    }
+
+    fn work(inner: Arc<dyn Signer>, incoming: Receiver<SignRequest>) {
+        for (message, reply) in incoming {
+            // the requester may have given up already; that's fine
+            let _ = reply.send(inner.sign(&message));
+        }
+    }
+}
+
+impl Signer for WorkerSigner {
+    fn sign(&self, message: &[u8]) -> Result<hints::PartialSignature, SignError> {
+        let (reply, answer) = mpsc::sync_channel(1);
+        match self.requests.try_send((message.to_vec(), reply)) {
+            Ok(()) => {}
+            Err(mpsc::TrySendError::Full(_)) => return Err(SignError::Timeout),
+            Err(mpsc::TrySendError::Disconnected(_)) => {
+                return Err(SignError::Io("signer worker stopped".to_string()));
+            }
+        }
+        match answer.recv_timeout(self.timeout) {
+            Ok(result) => result,
+            Err(mpsc::RecvTimeoutError::Timeout) => Err(SignError::Timeout),
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                Err(SignError::Io("signer worker stopped".to_string()))
+            }
+        }
+    }
+}
+
+impl<Tr: Transaction> MorpheusProcess<Tr> {
+    /// What checks other processes' signatures: `verifier` if set, else `kb`
+    pub fn verifier(&self) -> &dyn Verifier {
+        match &self.verifier {
+            Some(verifier) => verifier.as_ref(),
+            None => &self.kb,
+        }
+    }
+
+    fn sign_bytes<T: SigningPayload + Positioned>(
+        &self,
+        data: &T,
+        key: &SecretKey,
+    ) -> Result<hints::PartialSignature, SignError> {
+        // before the guard, so a missing key doesn't use up the position
+        let key = match &self.signer {
+            Some(_) => None,
+            None => Some(key.expose().ok_or(SignError::MissingKey)?),
+        };
+        let buf = signing_bytes(&self.kb.chain_id, data);
+        if let Some(guard) = &self.sign_guard {
+            guard
+                .lock()
+                .unwrap()
+                .check(&data.sign_position(), &buf)
+                .map_err(SignError::Guard)?;
+        }
+        match (&self.signer, key) {
+            (Some(signer), _) => signer.sign(&buf),
+            (None, Some(key)) => Ok(hints::sign(key, &buf)),
+            (None, None) => unreachable!(),
+        }
+    }
+
+    fn sign_with<T>(&self, data: T, key: &SecretKey) -> Option<(T, hints::PartialSignature)>
+    where
+        T: SigningPayload + Positioned,
+    {
+        match self.sign_bytes(&data, key) {
+            Ok(signature) => Some((data, signature)),
+            Err(e) => {
+                tracing::error!(target: "sign", process_id = ?self.id, error = %e);
+                None
+            }
+        }
+    }
+
+    /// Signs `data` as this process in the current view, or logs why it couldn't
+    ///
+    /// Uses the key announced for the current view with [`Self::rotate_key`],
+    /// if any; a `signer` is expected to rotate its key itself.
+    pub(crate) fn sign<T>(&self, data: T) -> Option<Signed<T>>
+    where
+        T: Valid + SigningPayload + CanonicalDeserialize + Positioned,
+    {
+        let key = self
+            .rotated_keys
+            .range(..=self.view_i)
+            .next_back()
+            .map_or(&self.kb.me_sec_key, |(_, key)| key);
+        self.sign_with(data, key).map(|(data, signature)| Signed {
+            data,
+            author: self.id.clone(),
+            signature,
+        })
+    }
+
+    /// [`Self::sign`], for a share of a threshold signature, which is always
+    /// made with `kb.me_sec_key`
+    pub(crate) fn sign_partial<T>(&self, data: T) -> Option<ThreshPartial<T>>
+    where
+        T: Valid + SigningPayload + CanonicalDeserialize + Positioned,
+    {
+        self.sign_with(data, &self.kb.me_sec_key)
+            .map(|(data, signature)| ThreshPartial {
+                data,
+                author: self.id.clone(),
+                signature,
+            })
+    }
+}
@@ -28,6 +28,15 @@ pub struct StateIndex<Tr: Transaction> {
     /// Implements part of "the set of all received messages"
     pub blocks: BTreeMap<BlockKey, Arc<Signed<Block<Tr>>>>,
 
+    /// The first block recorded at each position
+    #[serde(default)]
+    pub block_at_slot: BTreeMap<SlotKey, BlockKey>,
+
+    /// Positions at which more than one block was recorded, with all of them:
+    /// each is proof that the position's author equivocated
+    #[serde(default)]
+    pub equivocations: BTreeMap<SlotKey, BTreeSet<BlockKey>>,
+
     // === Performance optimization indexes ===
     /// Tracks which blocks point to which other blocks
     /// Used to efficiently determine the DAG structure
@@ -38,7 +47,7 @@ pub struct StateIndex<Tr: Transaction> {
 
     /// Tracks the maximum height block seen and its key
     /// Used for identifying the tallest block in the DAG
-    pub max_height: (usize, BlockKey),
+    pub max_height: (u64, BlockKey),
 
     /// Stores the maximum 1-QC seen by this process
     /// Used when entering a new view: "Send (v, q') signed by p_i to lead(v),
@@ -90,6 +99,8 @@ impl<Tr: Transaction> StateIndex<Tr> {
                 map.insert(GEN_BLOCK_KEY, genesis_block.clone());
                 map
             },
+            block_at_slot: BTreeMap::new(),
+            equivocations: BTreeMap::new(),
             block_pointed_by: BTreeMap::new(),
             unfinalized_2qc: BTreeSet::new(),
             finalized: BTreeSet::from([GEN_BLOCK_KEY]),
@@ -98,6 +109,66 @@ impl<Tr: Transaction> StateIndex<Tr> {
             unfinalized_lead_by_view: BTreeMap::new(),
         }
     }
+
+    /// Determines if one QC observes another according to the observes relation ⪰
+    ///
+    /// Implements the observes relation from the pseudocode:
+    /// "We define the 'observes' relation ⪰ on Q_i to be the minimal preordering satisfying (transitivity and):
+    /// • If q,q' ∈ Q_i, q.type = q'.type, q.auth = q'.auth and q.slot > q'.slot, then q ⪰ q'.
+    /// • If q,q' ∈ Q_i, q.type = q'.type, q.auth = q'.auth, q.slot = q'.slot, and q.z ≥ q'.z, then q ⪰ q'."
+    /// • If q,q' ∈ Q_i, q.b = b, q'.b = b', b ∈ M_i and b points to b', then q ⪰ q'."
+    ///
+    /// Implemented as a BFS on the points-to graph combined with a direct
+    /// observation check.
+    pub fn observes(&self, root: VoteData, needle: &VoteData) -> bool {
+        let mut observed = false;
+        let mut to_visit: VecDeque<VoteData> = vec![root].into();
+        while !to_visit.is_empty() {
+            let node = to_visit.pop_front().unwrap();
+            if self.directly_observes(&node, needle) {
+                observed = true;
+                break;
+            }
+            if let Some(block) = self.blocks.get(&node.for_which) {
+                for prev in &block.data.prev {
+                    to_visit.push_back(prev.data.clone());
+                }
+            } else {
+                tracing::warn!("Block not found for {:?}", node.for_which);
+            }
+        }
+        observed
+    }
+
+    /// Determines if one QC directly observes another (without transitivity)
+    ///
+    /// Implements the direct observation component of the observes relation ⪰
+    pub fn directly_observes(&self, looks: &VoteData, seen: &VoteData) -> bool {
+        if looks.for_which.type_ == seen.for_which.type_
+            && looks.for_which.author == seen.for_which.author
+            && looks.for_which.slot > seen.for_which.slot
+        {
+            return true;
+        }
+        if looks.for_which.type_ == seen.for_which.type_
+            && looks.for_which.author == seen.for_which.author
+            && looks.for_which.slot == seen.for_which.slot
+            && looks.z >= seen.z
+        {
+            return true;
+        }
+        if let Some(block) = self.blocks.get(&looks.for_which) {
+            if block
+                .data
+                .prev
+                .iter()
+                .any(|prev| prev.data.for_which == seen.for_which)
+            {
+                return true;
+            }
+        }
+        false
+    }
 }
 
 impl<Tr: Transaction> MorpheusProcess<Tr> {
@@ -117,11 +188,20 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
         if !self.qcs.insert(qc.clone()) {
             return;
         }
+        self.awaiting_aggregation.remove(&qc.data);
 
         if qc.data.for_which.type_ == BlockType::Genesis {
             return;
         }
 
+        self.emit(ProtocolEvent::QcFormed {
+            data: qc.data.clone(),
+        });
+
+        if qc.data.for_which.type_ == BlockType::Lead && qc.data.for_which.view == self.view_i {
+            self.view_churn.progressed = true;
+        }
+
         // maintain the (type, author, {slot,view}) -> qc index
         if let Some(author) = &qc.data.for_which.author {
             if author == &self.id
@@ -196,16 +276,35 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
                 tracing::debug!(target: "new_tip", reason = "new branch", qc = ?qc.data);
             }
         }
+        if self.index.tips.contains(&qc) {
+            self.tips_seen_at.insert(qc.data.clone(), self.current_time);
+        }
+        let tips = &self.index.tips;
+        self.tips_seen_at
+            .retain(|data, _| tips.iter().any(|tip| &tip.data == data));
+        self.sample_tips();
 
         // now find all the waiting 2-qcs that this qc can finalize
 
-        let finalized_here = self
+        let mut finalized_here = self
             .index
             .unfinalized_2qc
             .iter()
             .cloned()
             .filter(|unfinalized_2qc| self.observes(qc.data.clone(), &unfinalized_2qc.data))
             .collect::<BTreeSet<_>>();
+        // a committee of one can't certify a conflicting block, so there is
+        // nothing left to vote on either
+        if self.dev_mode {
+            finalized_here.insert(qc.clone());
+            if let Some(pending) = self.pending_votes.get_mut(&qc.data.for_which.view) {
+                let key = &qc.data.for_which;
+                pending.tr_1.remove(key);
+                pending.tr_2.remove(key);
+                pending.lead_1.remove(key);
+                pending.lead_2.remove(key);
+            }
+        }
 
         if qc.data.z == 2 {
             // IMPORTANT: a QC observes itself, so make sure we add it AFTER
@@ -217,6 +316,11 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
             .unfinalized_2qc
             .retain(|unfinalized_2qc| !finalized_here.contains(unfinalized_2qc));
 
+        let newly_final = finalized_here
+            .iter()
+            .map(|finalized| finalized.data.for_which.clone())
+            .collect::<Vec<_>>();
+
         // finalize the blocks
         for finalized in finalized_here {
             tracing::debug!(target: "finalized_block", cause_qc = ?finalized, key = ?finalized.data.for_which);
@@ -235,6 +339,13 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
                 .entry(finalized.data.for_which.view)
                 .or_default()
                 .dirty = true;
+
+            self.emit(ProtocolEvent::BlockFinalized {
+                key: finalized.data.for_which.clone(),
+            });
+        }
+        if !newly_final.is_empty() {
+            self.schedule_config_changes(newly_final);
         }
 
         // start watching for 2-votes
@@ -265,7 +376,24 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
             return;
         }
 
-        // max_height is needed for is_eligible_for_tr_2_vote
+        let key = &block.data.key;
+        let slot = SlotKey::from(key);
+        match self.index.block_at_slot.get(&slot) {
+            Some(first) if first != key => {
+                tracing::warn!(target: "equivocation", first = ?first, second = ?key);
+                self.index
+                    .equivocations
+                    .entry(slot)
+                    .or_insert_with(|| BTreeSet::from([first.clone()]))
+                    .insert(key.clone());
+            }
+            Some(_) => {}
+            None => {
+                self.index.block_at_slot.insert(slot, key.clone());
+            }
+        }
+
+        // max_height is needed for tr_2_vote_blocker
         if block.data.key.height > self.index.max_height.0 {
             tracing::debug!(target: "new_max_height", prev_height = ?self.index.max_height, key = ?block.data.key);
             self.index.max_height = (block.data.key.height, block.data.key.clone());
@@ -306,7 +434,7 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
             BlockType::Genesis => panic!("Why are we recording the genesis block?"),
         }
 
-        // track the points-to relationship for block_is_single_tip
+        // track the points-to relationship for tr_1_vote_blocker
         for qc in &block.data.prev {
             self.index
                 .block_pointed_by
@@ -320,104 +448,99 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
             self.record_qc(qc.clone())
         }
         self.record_qc(block.data.one.clone());
+
+        // its config changes, if it was finalized before it arrived
+        self.schedule_late_config_changes(&block_key);
     }
 
-    /// Determines if one QC observes another according to the observes relation ⪰
-    ///
-    /// Implements the observes relation from the pseudocode:
-    /// "We define the 'observes' relation ⪰ on Q_i to be the minimal preordering satisfying (transitivity and):
-    /// • If q,q' ∈ Q_i, q.type = q'.type, q.auth = q'.auth and q.slot > q'.slot, then q ⪰ q'.
-    /// • If q,q' ∈ Q_i, q.type = q'.type, q.auth = q'.auth, q.slot = q'.slot, and q.z ≥ q'.z, then q ⪰ q'."
-    /// • If q,q' ∈ Q_i, q.b = b, q'.b = b', b ∈ M_i and b points to b', then q ⪰ q'."
-    ///
-    /// Implemented as a BFS on the points-to graph combined with a direct
-    /// observation check.
+    /// Determines if one QC observes another according to the observes relation ⪰,
+    /// over the blocks this process holds; see [`StateIndex::observes`]
     pub fn observes(&self, root: VoteData, needle: &VoteData) -> bool {
-        let mut observed = false;
-        let mut to_visit: VecDeque<VoteData> = vec![root].into();
-        while !to_visit.is_empty() {
-            let node = to_visit.pop_front().unwrap();
-            if self.directly_observes(&node, needle) {
-                observed = true;
-                break;
-            }
-            if let Some(block) = self.index.blocks.get(&node.for_which) {
-                for prev in &block.data.prev {
-                    to_visit.push_back(prev.data.clone());
-                }
-            } else {
-                tracing::warn!("Block not found for {:?}", node.for_which);
-            }
-        }
-        observed
+        self.index.observes(root, needle)
     }
 
     /// Determines if one QC directly observes another (without transitivity)
-    ///
-    /// Implements the direct observation component of the observes relation ⪰
     pub fn directly_observes(&self, looks: &VoteData, seen: &VoteData) -> bool {
-        if looks.for_which.type_ == seen.for_which.type_
-            && looks.for_which.author == seen.for_which.author
-            && looks.for_which.slot > seen.for_which.slot
-        {
-            return true;
-        }
-        if looks.for_which.type_ == seen.for_which.type_
-            && looks.for_which.author == seen.for_which.author
-            && looks.for_which.slot == seen.for_which.slot
-            && looks.z >= seen.z
-        {
-            return true;
-        }
-        if let Some(block) = self.index.blocks.get(&looks.for_which) {
-            if block
-                .data
-                .prev
-                .iter()
-                .any(|prev| prev.data.for_which == seen.for_which)
-            {
-                return true;
+        self.index.directly_observes(looks, seen)
+    }
+
+    /// Returns every block this process regards as final
+    ///
+    /// `index.finalized` only holds blocks whose 2-QC has been observed; a
+    /// block is also final if it is observed by (is an ancestor of) such a
+    /// block, which is how transaction blocks are finalized through leader
+    /// blocks in the high throughput phase.
+    pub fn finalized_blocks(&self) -> BTreeSet<BlockKey> {
+        let mut finalized = BTreeSet::new();
+        let mut to_visit: Vec<BlockKey> = self.index.finalized.iter().cloned().collect();
+        while let Some(key) = to_visit.pop() {
+            if !finalized.insert(key.clone()) {
+                continue;
+            }
+            if let Some(block) = self.index.blocks.get(&key) {
+                to_visit.extend(block.data.prev.iter().map(|qc| qc.data.for_which.clone()));
             }
         }
-        false
+        finalized
     }
 
-    fn block_is_single_tip(&self, block_key: &BlockKey) -> bool {
-        if self.index.tips.len() != 1 {
-            return false;
+    /// Why `block_key` can't be 1-voted as a transaction block right now,
+    /// if it can't
+    pub(crate) fn tr_1_vote_blocker(&self, block_key: &BlockKey) -> Option<VoteBlocker> {
+        let [tip] = self.index.tips.as_slice() else {
+            return Some(VoteBlocker::TipCount {
+                tips: self.index.tips.len(),
+            });
+        };
+        let sole_successor = self
+            .index
+            .block_pointed_by
+            .get(&tip.data.for_which)
+            .is_some_and(|parents| parents.len() == 1 && parents.first().unwrap() == block_key);
+        if !sole_successor {
+            return Some(VoteBlocker::NotSoleSuccessorOfTip {
+                tip: tip.data.clone(),
+            });
         }
-        match self.index.tips.get(0) {
-            Some(tip) => self
-                .index
-                .block_pointed_by
-                .get(&tip.data.for_which)
-                .map_or(false, |parents| {
-                    parents.len() == 1 && parents.first().unwrap() == block_key
-                }),
-            None => false,
+
+        let Some(block) = self.index.blocks.get(block_key) else {
+            return Some(VoteBlocker::BlockMissing);
+        };
+        if block.data.one.data.compare_qc(&self.index.max_1qc.data) == Ordering::Less {
+            return Some(VoteBlocker::OneQcBelowMax {
+                one_qc: block.data.one.data.clone(),
+                max_1qc: self.index.max_1qc.data.clone(),
+            });
         }
+        None
     }
 
-    pub(crate) fn is_eligible_for_tr_1_vote(&self, block_key: &BlockKey) -> bool {
-        let has_single_tip = self.block_is_single_tip(block_key);
-
-        if !has_single_tip || !self.index.blocks.contains_key(block_key) {
-            return false;
+    /// Why `block_key` can't be 2-voted as a transaction block right now,
+    /// if it can't
+    pub(crate) fn tr_2_vote_blocker(&self, block_key: &BlockKey) -> Option<VoteBlocker> {
+        let [tip] = self.index.tips.as_slice() else {
+            return Some(VoteBlocker::TipCount {
+                tips: self.index.tips.len(),
+            });
+        };
+        if tip.data.z != 1 || &tip.data.for_which != block_key {
+            return Some(VoteBlocker::TipNotOneQcForBlock {
+                tip: tip.data.clone(),
+            });
         }
+        if self.index.max_height.0 > block_key.height {
+            return Some(VoteBlocker::HigherBlockSeen {
+                max_height: self.index.max_height.0,
+            });
+        }
+        None
+    }
 
-        let block = self.index.blocks.get(block_key).unwrap();
-
-        block.data.one.data.compare_qc(&self.index.max_1qc.data) != Ordering::Less
+    pub(crate) fn is_eligible_for_tr_1_vote(&self, block_key: &BlockKey) -> bool {
+        self.tr_1_vote_blocker(block_key).is_none()
     }
 
     pub(crate) fn is_eligible_for_tr_2_vote(&self, block_key: &BlockKey) -> bool {
-        let has_single_tip = self.index.tips.len() == 1
-            && self.index.tips.get(0).map_or(false, |tip| {
-                tip.data.z == 1 && tip.data.for_which.eq(block_key)
-            });
-
-        let no_higher_blocks = self.index.max_height.0 <= block_key.height;
-
-        has_single_tip && no_higher_blocks
+        self.tr_2_vote_blocker(block_key).is_none()
     }
 }
@@ -0,0 +1,126 @@
+//! Storage that fails on purpose
+//!
+//! Recovery code is only as good as the failures it has been run against.
+//! [`FaultyStore`] wraps a [`GuardStore`] or a [`WalStore`] and, following
+//! [`StorageFaults`], fails a share of writes, delays each sync, and flips a
+//! bit in a share of reads, so tests can check that a process whose storage
+//! misbehaves gives up signing rather than signing twice. A failed append to
+//! a log writes part of the record first, like a write cut short by a full
+//! disk. Faults are drawn from a seeded generator, so a failing run can be
+//! repeated exactly.
+
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+/// Which faults a [`FaultyStore`] injects, and how often
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageFaults {
+    /// Percentage of writes that fail without reaching the store
+    pub write_failures: u8,
+    /// How long each successful write waits for its sync
+    pub sync_delay: Duration,
+    /// Percentage of reads that come back with one bit flipped
+    pub corrupt_reads: u8,
+    pub seed: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FaultCounts {
+    pub failed_writes: u64,
+    pub corrupted_reads: u64,
+}
+
+pub struct FaultyStore<S> {
+    inner: S,
+    faults: StorageFaults,
+    state: u64,
+    counts: Arc<Mutex<FaultCounts>>,
+}
+
+impl<S> FaultyStore<S> {
+    pub fn new(inner: S, faults: StorageFaults) -> Self {
+        FaultyStore {
+            inner,
+            state: faults.seed,
+            faults,
+            counts: Arc::default(),
+        }
+    }
+
+    /// Faults injected so far, kept up to date after the store is handed
+    /// to a guard
+    pub fn counts(&self) -> Arc<Mutex<FaultCounts>> {
+        self.counts.clone()
+    }
+
+    /// splitmix64, plenty for picking faults
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn roll(&mut self, percent: u8) -> bool {
+        percent > 0 && self.next() % 100 < percent as u64
+    }
+}
+
+impl<S: GuardStore> GuardStore for FaultyStore<S> {
+    fn load(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut bytes = self.inner.load()?;
+        let len = bytes.as_ref().map_or(0, |bytes| bytes.len());
+        if len > 0 && self.roll(self.faults.corrupt_reads) {
+            let bit = self.next() as usize % (len * 8);
+            bytes.as_mut().unwrap()[bit / 8] ^= 1 << (bit % 8);
+            self.counts.lock().unwrap().corrupted_reads += 1;
+        }
+        Ok(bytes)
+    }
+
+    fn store(&mut self, bytes: &[u8]) -> io::Result<()> {
+        if self.roll(self.faults.write_failures) {
+            self.counts.lock().unwrap().failed_writes += 1;
+            return Err(io::Error::other("injected write failure"));
+        }
+        std::thread::sleep(self.faults.sync_delay);
+        self.inner.store(bytes)
+    }
+}
+
+impl<S: WalStore> WalStore for FaultyStore<S> {
+    fn load(&mut self) -> io::Result<Vec<u8>> {
+        let mut bytes = self.inner.load()?;
+        if !bytes.is_empty() && self.roll(self.faults.corrupt_reads) {
+            let bit = self.next() as usize % (bytes.len() * 8);
+            bytes[bit / 8] ^= 1 << (bit % 8);
+            self.counts.lock().unwrap().corrupted_reads += 1;
+        }
+        Ok(bytes)
+    }
+
+    fn append(&mut self, bytes: &[u8]) -> io::Result<()> {
+        if self.roll(self.faults.write_failures) {
+            self.counts.lock().unwrap().failed_writes += 1;
+            let written = self.next() as usize % bytes.len().max(1);
+            self.inner.append(&bytes[..written])?;
+            return Err(io::Error::other("injected write failure"));
+        }
+        std::thread::sleep(self.faults.sync_delay);
+        self.inner.append(bytes)
+    }
+
+    fn truncate(&mut self, len: usize) -> io::Result<()> {
+        self.inner.truncate(len)
+    }
+
+    fn replace(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.inner.replace(bytes)
+    }
+}
@@ -0,0 +1,234 @@
+//! Running a matrix of simulations and comparing them
+//!
+//! Tuning the protocol means asking how it behaves as the committee grows,
+//! Δ changes, the network drops messages or load rises, and each of those is
+//! one [`SimulationConfig`]. A [`SweepMatrix`] names the values to try along
+//! each axis and runs every combination, spread over threads, each for the
+//! same number of steps. Every run is summarized from the harness's own
+//! exports (its divergence report, the DAG stats of each process and what
+//! each process received) into a [`ScenarioStats`], and the [`SweepReport`]
+//! lays them out as one table.
+//!
+//! Every scenario builds its cluster from the matrix's seed, so a sweep run
+//! twice reports the same numbers whatever the thread count.
+
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{NetworkConfig, SimulationConfig, SimulationConfigError};
+use crate::test_harness::{MockHarness, TxGenPolicy};
+
+/// The values to try along each axis; every combination is run
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SweepMatrix {
+    /// Committee sizes
+    pub nodes: Vec<u32>,
+    /// Values of Δ, which is also the simulated time of a step
+    pub deltas: Vec<u128>,
+    /// Fractions of messages lost
+    pub loss_rates: Vec<f64>,
+    /// Every process submits a transaction every this many steps
+    pub tx_every: Vec<usize>,
+    /// How long each scenario runs
+    pub steps: usize,
+    /// What every scenario starts from before the axes above are applied
+    pub base: SimulationConfig,
+}
+
+impl Default for SweepMatrix {
+    fn default() -> Self {
+        SweepMatrix {
+            nodes: vec![4],
+            deltas: vec![100],
+            loss_rates: vec![0.0],
+            tx_every: vec![2],
+            steps: 300,
+            base: SimulationConfig::default(),
+        }
+    }
+}
+
+/// One point of a [`SweepMatrix`]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Scenario {
+    pub nodes: u32,
+    pub delta: u128,
+    pub loss_rate: f64,
+    pub tx_every: usize,
+}
+
+/// How one scenario went
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScenarioStats {
+    pub scenario: Scenario,
+    /// Blocks every process regards as final
+    pub finalized: usize,
+    /// `finalized` per step
+    pub finalized_per_step: f64,
+    /// The furthest view any process reached; each past 0 is a view change
+    pub max_view: i64,
+    /// Messages handled, summed over the processes
+    pub messages: u64,
+    /// Largest number of blocks at one height, over the processes
+    pub max_width: usize,
+    /// Conflicting finalized blocks, which must be 0
+    pub conflicts: usize,
+}
+
+/// Every scenario of a sweep, in the matrix's order
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SweepReport {
+    pub steps: usize,
+    pub results: Vec<ScenarioStats>,
+}
+
+impl SweepMatrix {
+    /// Every combination of the axes, committee size varying slowest
+    pub fn scenarios(&self) -> Vec<Scenario> {
+        let mut scenarios = Vec::new();
+        for &nodes in &self.nodes {
+            for &delta in &self.deltas {
+                for &loss_rate in &self.loss_rates {
+                    for &tx_every in &self.tx_every {
+                        scenarios.push(Scenario {
+                            nodes,
+                            delta,
+                            loss_rate,
+                            tx_every,
+                        });
+                    }
+                }
+            }
+        }
+        scenarios
+    }
+
+    /// The simulation `scenario` stands for
+    pub fn config(&self, scenario: &Scenario) -> SimulationConfig {
+        SimulationConfig {
+            num_processes: scenario.nodes,
+            f: None,
+            delta: scenario.delta,
+            default_tx_gen_policy: TxGenPolicy::EveryNSteps {
+                n: scenario.tx_every.max(1),
+            },
+            network: NetworkConfig {
+                loss_rate: scenario.loss_rate,
+                ..self.base.network.clone()
+            },
+            ..self.base.clone()
+        }
+    }
+
+    /// Runs every scenario, on up to `threads` threads at once
+    ///
+    /// Fails with the error of the first scenario, in matrix order, whose
+    /// config doesn't build.
+    pub fn run(&self, threads: usize) -> Result<SweepReport, SimulationConfigError> {
+        let scenarios = self.scenarios();
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(Vec::with_capacity(scenarios.len()));
+        std::thread::scope(|scope| {
+            for _ in 0..threads.clamp(1, scenarios.len().max(1)) {
+                scope.spawn(|| {
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(scenario) = scenarios.get(i) else {
+                            break;
+                        };
+                        let stats = self.run_scenario(scenario);
+                        results.lock().unwrap().push((i, stats));
+                    }
+                });
+            }
+        });
+
+        let mut results = results.into_inner().unwrap();
+        results.sort_by_key(|(i, _)| *i);
+        Ok(SweepReport {
+            steps: self.steps,
+            results: results
+                .into_iter()
+                .map(|(_, stats)| stats)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    fn run_scenario(&self, scenario: &Scenario) -> Result<ScenarioStats, SimulationConfigError> {
+        let mut harness = self.config(scenario).build()?;
+        harness.run(self.steps);
+        Ok(ScenarioStats::of(*scenario, &harness, self.steps))
+    }
+}
+
+impl ScenarioStats {
+    /// The statistics of `harness` after running `scenario` for `steps`
+    pub fn of(scenario: Scenario, harness: &MockHarness, steps: usize) -> Self {
+        let divergence = harness.divergence_report();
+        let dag = harness.dag_report();
+        ScenarioStats {
+            scenario,
+            finalized: divergence.common_prefix_len,
+            finalized_per_step: divergence.common_prefix_len as f64 / steps.max(1) as f64,
+            max_view: harness
+                .processes
+                .values()
+                .map(|process| process.view_i.0)
+                .max()
+                .unwrap_or(0),
+            messages: harness
+                .processes
+                .values()
+                .map(|process| process.received_messages.total())
+                .sum(),
+            max_width: dag
+                .values()
+                .map(|stats| stats.max_width())
+                .max()
+                .unwrap_or(0),
+            conflicts: divergence.conflicts.len(),
+        }
+    }
+}
+
+impl SweepReport {
+    /// One row per scenario, aligned for reading in a terminal
+    pub fn table(&self) -> String {
+        let mut table = format!(
+            "{:>5} {:>6} {:>6} {:>8} {:>9} {:>9} {:>8} {:>9} {:>9} {:>9}\n",
+            "nodes",
+            "delta",
+            "loss",
+            "tx every",
+            "finalized",
+            "per step",
+            "max view",
+            "messages",
+            "max width",
+            "conflicts",
+        );
+        for stats in &self.results {
+            let scenario = &stats.scenario;
+            writeln!(
+                table,
+                "{:>5} {:>6} {:>6.3} {:>8} {:>9} {:>9.3} {:>8} {:>9} {:>9} {:>9}",
+                scenario.nodes,
+                scenario.delta,
+                scenario.loss_rate,
+                scenario.tx_every,
+                stats.finalized,
+                stats.finalized_per_step,
+                stats.max_view,
+                stats.messages,
+                stats.max_width,
+                stats.conflicts,
+            )
+            .expect("writing to a String doesn't fail");
+        }
+        table
+    }
+}
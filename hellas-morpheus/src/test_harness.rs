@@ -7,24 +7,42 @@
 //! We process each message to completion, check timeouts, check block production eligibility, and finally advance the state of the simulation.
 
 use std::{
-    collections::{BTreeMap, VecDeque},
+    collections::{BTreeMap, BTreeSet, VecDeque},
     sync::Arc,
     sync::RwLock,
 };
 
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::{Rng, SeedableRng, rngs::StdRng};
 use ark_std::test_rng;
 
 use serde::{Deserialize, Serialize};
 
+use crate::topology::{TOPOLOGIES, Topology, UnknownTopology};
+use crate::trace::{ExecutionTrace, TraceInput, traced};
+use crate::transport::HarnessTransport;
 use crate::*;
 
 #[derive(
-    Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, CanonicalDeserialize, CanonicalSerialize,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Debug,
+    Hash,
+    CanonicalDeserialize,
+    CanonicalSerialize,
+    Serialize,
+    Deserialize,
 )]
 pub struct TestTransaction(pub Vec<u8>);
 
-impl Transaction for TestTransaction {}
+impl Transaction for TestTransaction {
+    fn config_change(&self) -> Option<ConfigChange> {
+        ConfigChange::decode(&self.0)
+    }
+}
 
 /// A basic simulation harness for MorpheusProcess
 #[derive(Clone)]
@@ -46,6 +64,231 @@ pub struct MockHarness {
 
     /// Policy for generating transactions
     pub tx_gen_policy: BTreeMap<Identity, TxGenPolicy>,
+
+    /// Number of delivery rounds so far; each round is one message delay
+    pub rounds: usize,
+
+    /// Creation and finalization times (in message delays) of every produced block
+    pub block_timings: BTreeMap<BlockKey, BlockTiming>,
+
+    /// Scenario events to apply, keyed by the step at which they take effect
+    pub schedule: BTreeMap<usize, Vec<ScenarioEvent>>,
+
+    /// Faults currently in effect
+    pub faults: NetworkFaults,
+
+    /// Failure domain (rack, region, ...) of each process; processes without
+    /// one only fail on their own
+    pub domains: BTreeMap<Identity, String>,
+
+    /// Version each process runs, for those not on [`CURRENT_VERSION`]
+    pub versions: BTreeMap<Identity, String>,
+
+    /// Where processes sit; `None` delivers everything in the next round
+    pub topology: Option<Topology>,
+
+    /// Messages still crossing the network under `topology`, by the round
+    /// they arrive in, one entry per recipient
+    pub in_transit: BTreeMap<usize, VecDeque<(Message<TestTransaction>, Identity, Identity)>>,
+
+    /// Messages each process was recently handed, so repeats are dropped
+    /// before it sees them
+    pub dedup: BTreeMap<Identity, DedupCache>,
+
+    /// Size of each process's `dedup` cache, or `None` to hand on every copy
+    pub dedup_capacity: Option<usize>,
+
+    /// Every delivery since `record_hops`, if recording
+    pub hops: Option<Vec<Hop>>,
+
+    /// Every input handed to a process since `start_trace`, if recording
+    pub trace: Option<ExecutionTrace<TestTransaction>>,
+
+    /// Messages delivered to processes outside the harness, waiting for them
+    /// to be received through a `HarnessTransport`
+    pub attached: BTreeMap<Identity, VecDeque<(Identity, Message<TestTransaction>)>>,
+}
+
+/// Something that happens to the simulated network at a scheduled step
+#[derive(Clone, Debug)]
+pub enum ScenarioEvent {
+    /// The process stops receiving messages, checking timeouts and producing blocks
+    Crash(Identity),
+    /// A crashed process resumes
+    Recover(Identity),
+    /// Messages are only delivered between processes in the same group
+    Partition(Vec<BTreeSet<Identity>>),
+    /// Removes any partition
+    Heal,
+    /// The process sends a conflicting copy of each transaction block to half its peers
+    Equivocate(Identity),
+    /// Replaces the transaction generation policy of a process
+    SetTxGenPolicy(Identity, TxGenPolicy),
+    /// Every process in the failure domain crashes at once
+    CrashDomain(String),
+    /// Every process in the failure domain recovers
+    RecoverDomain(String),
+    /// The process's operator asks it to leave the current view
+    ForceEndView(Identity),
+    /// The process is restarted on another version, keeping its state
+    Upgrade(Identity, ProcessProfile),
+    /// Moves processes onto another topology; messages already in flight
+    /// arrive as scheduled
+    SetTopology(Option<Topology>),
+}
+
+/// The protocol parameters and feature flags a process was built with,
+/// standing in for the version of the software it runs
+#[derive(Clone)]
+pub struct ProcessProfile {
+    pub version: String,
+    pub vote_aggregation: VoteAggregation,
+    pub leader_election: LeaderElection,
+    /// `None` keeps `LeaderBudget::for_committee`
+    pub leader_budget: Option<LeaderBudget>,
+    pub leader_policy: Arc<dyn LeaderPolicy>,
+    pub phase_policy: Arc<dyn PhasePolicy>,
+    pub extension_policy: Arc<dyn ExtensionPolicy>,
+}
+
+impl ProcessProfile {
+    /// What every process starts as
+    pub fn current() -> Self {
+        ProcessProfile {
+            version: CURRENT_VERSION.to_string(),
+            vote_aggregation: VoteAggregation::default(),
+            leader_election: LeaderElection::default(),
+            leader_budget: None,
+            leader_policy: crate::leader_policy::default_leader_policy(),
+            phase_policy: crate::phase_policy::default_phase_policy(),
+            extension_policy: crate::extensions::default_extension_policy(),
+        }
+    }
+
+    /// Reconfigures `process` in place, as if restarted on this version with its state intact
+    pub fn apply(&self, process: &mut MorpheusProcess<TestTransaction>) {
+        process.vote_aggregation = self.vote_aggregation;
+        process.leader_election = self.leader_election;
+        process.leader_budget = self
+            .leader_budget
+            .unwrap_or_else(|| LeaderBudget::for_committee(process.n));
+        process.leader_policy = self.leader_policy.clone();
+        process.phase_policy = self.phase_policy.clone();
+        process.extension_policy = self.extension_policy.clone();
+    }
+}
+
+impl std::fmt::Debug for ProcessProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProcessProfile")
+            .field("version", &self.version)
+            .field("vote_aggregation", &self.vote_aggregation)
+            .field("leader_election", &self.leader_election)
+            .field("leader_budget", &self.leader_budget)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Version label of [`ProcessProfile::current`]
+pub const CURRENT_VERSION: &str = "current";
+
+/// Faults currently injected into the simulated network
+#[derive(Clone, Debug, Default)]
+pub struct NetworkFaults {
+    pub crashed: BTreeSet<Identity>,
+    pub partition: Option<Vec<BTreeSet<Identity>>>,
+    pub equivocators: BTreeSet<Identity>,
+    /// Messages dropped at random on top of the above, if any
+    pub loss: Option<MessageLoss>,
+}
+
+/// Drops each message on its way to a recipient with probability `rate`,
+/// drawn from a seeded generator so a run can be repeated
+#[derive(Clone, Debug)]
+pub struct MessageLoss {
+    pub rate: f64,
+    rng: StdRng,
+}
+
+impl MessageLoss {
+    pub fn new(rate: f64, seed: u64) -> Self {
+        MessageLoss {
+            rate: rate.clamp(0.0, 1.0),
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Whether the next message is lost
+    pub fn drops(&mut self) -> bool {
+        self.rate > 0.0 && self.rng.gen_bool(self.rate)
+    }
+}
+
+impl NetworkFaults {
+    /// Whether a message from `from` currently reaches `to`
+    pub fn delivers(&self, from: &Identity, to: &Identity) -> bool {
+        if self.crashed.contains(to) {
+            return false;
+        }
+        match &self.partition {
+            Some(groups) => groups
+                .iter()
+                .any(|group| group.contains(from) && group.contains(to)),
+            None => true,
+        }
+    }
+}
+
+/// One message delivered from one process to another
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hop {
+    pub round: usize,
+    pub from: Identity,
+    pub to: Identity,
+    pub kind: MessageKind,
+    pub correlation: CorrelationId,
+}
+
+fn record_hop(
+    hops: &mut Option<Vec<Hop>>,
+    round: usize,
+    from: &Identity,
+    to: &Identity,
+    message: &Message<TestTransaction>,
+) {
+    if let Some(hops) = hops {
+        hops.push(Hop {
+            round,
+            from: from.clone(),
+            to: to.clone(),
+            kind: message.kind(),
+            correlation: message.correlation_id(),
+        });
+    }
+}
+
+/// Message-delay bookkeeping for a single block, used to measure finalization latency
+#[derive(Clone, Debug)]
+pub struct BlockTiming {
+    /// Round in which the author sent the block
+    pub created_round: usize,
+
+    /// Throughput phase the author was in when it produced the block
+    pub created_phase: Phase,
+
+    /// Round in which each process first regarded the block as final
+    pub finalized_round: BTreeMap<Identity, usize>,
+}
+
+impl BlockTiming {
+    /// Message delays between creation and the `k`-th process finalizing the block
+    pub fn kth_finality(&self, k: usize) -> Option<usize> {
+        let mut rounds = self.finalized_round.values().copied().collect::<Vec<_>>();
+        rounds.sort();
+        rounds
+            .get(k.checked_sub(1)?)
+            .map(|round| round - self.created_round)
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -60,44 +303,50 @@ pub enum TxGenPolicy {
     Never,
 }
 
-impl MockHarness {
-    pub fn create_test_setup(num_parties: usize) -> MockHarness {
-        let domain_max = (1 + num_parties).next_power_of_two();
-        let gd = hints::GlobalData::new(domain_max, &mut test_rng()).unwrap();
-        let privs = vec![hints::SecretKey::random(&mut test_rng()); domain_max - 1];
-        let pubkeys: Vec<hints::PublicKey> = privs.iter().map(|sk| sk.public(&gd)).collect();
-        let weights = vec![hints::F::from(1); domain_max - 1];
-
-        let hints = (0..domain_max - 1)
-            .map(|i| hints::generate_hint(&gd, &privs[i], domain_max, i).unwrap())
-            .collect::<Vec<_>>();
+/// Two processes finalized different blocks for the same [`SlotKey`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Divergence {
+    pub first: Identity,
+    pub first_key: BlockKey,
+    pub second: Identity,
+    pub second_key: BlockKey,
+}
 
-        let setup = hints::setup_universe(&gd, pubkeys.clone(), &hints, weights).unwrap();
+/// Comparison of the finalized histories of all simulated processes
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DivergenceReport {
+    /// Number of blocks each process regards as final
+    #[serde(with = "serde_json_any_key::any_key_map")]
+    pub finalized_counts: BTreeMap<Identity, usize>,
 
-        let keys: BTreeMap<Identity, hints::PublicKey> = (0..num_parties)
-            .map(|i| (Identity(i as u32 + 1), pubkeys[i].clone()))
-            .collect();
+    /// Number of blocks every process regards as final
+    ///
+    /// Finality covers a block's ancestors, so these blocks are a prefix of
+    /// every process's history, even where blocks of the same height were
+    /// finalized in a different order.
+    pub common_prefix_len: usize,
 
-        let identities: BTreeMap<hints::PublicKey, Identity> = (0..num_parties)
-            .map(|i| (pubkeys[i].clone(), Identity(i as u32 + 1)))
-            .collect();
+    /// Conflicting finalized blocks; any entry here is a safety violation
+    pub conflicts: Vec<Divergence>,
+}
+
+impl DivergenceReport {
+    pub fn is_consistent(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
 
-        // Create processes with different identities
-        let processes = (0..num_parties)
-            .map(|i| {
-                MorpheusProcess::new(
-                    KeyBook {
-                        keys: keys.clone(),
-                        identities: identities.clone(),
-                        me_identity: Identity(i as u32 + 1),
-                        me_pub_key: pubkeys[i].clone(),
-                        me_sec_key: privs[i].clone(),
-                        hints_setup: setup.clone(),
-                    },
-                    Identity(i as u32 + 1),
-                    num_parties as u32,
-                    (num_parties as u32 - 1) / 3,
-                )
+impl MockHarness {
+    /// Messages are delivered with the process that sent them, so the
+    /// simulated links are authenticated
+    pub const LINK_SECURITY: LinkSecurity = LinkSecurity::Authenticated;
+
+    pub fn create_test_setup(num_parties: usize) -> MockHarness {
+        let processes = KeyBook::committee_setup(num_parties, &mut test_rng())
+            .into_iter()
+            .map(|kb| {
+                let id = kb.me_identity.clone();
+                MorpheusProcess::new(kb, id, num_parties as u32, (num_parties as u32 - 1) / 3)
             })
             .collect();
 
@@ -122,64 +371,285 @@ impl MockHarness {
             time_step,
             steps: 0,
             tx_gen_policy: BTreeMap::new(),
+            rounds: 0,
+            block_timings: BTreeMap::new(),
+            schedule: BTreeMap::new(),
+            faults: NetworkFaults::default(),
+            domains: BTreeMap::new(),
+            versions: BTreeMap::new(),
+            topology: None,
+            in_transit: BTreeMap::new(),
+            dedup: BTreeMap::new(),
+            dedup_capacity: Some(DEFAULT_DEDUP_CAPACITY),
+            hops: None,
+            trace: None,
+            attached: BTreeMap::new(),
+        }
+    }
+
+    /// Record every delivery from now on into `self.hops`
+    pub fn record_hops(&mut self) {
+        self.hops.get_or_insert_with(Vec::new);
+    }
+
+    /// The recorded deliveries of messages about `correlation`, in delivery order
+    pub fn hops_for(&self, correlation: CorrelationId) -> Vec<&Hop> {
+        self.hops
+            .iter()
+            .flatten()
+            .filter(|hop| hop.correlation == correlation)
+            .collect()
+    }
+
+    /// Record every input from now on into `self.trace`, for [`crate::trace::replay`]
+    ///
+    /// Must be called before the first step, as replay starts from fresh processes.
+    pub fn start_trace(&mut self) {
+        assert_eq!(self.steps, 0, "tracing must start before the first step");
+        self.trace = Some(ExecutionTrace::new(self.processes.values()));
+    }
+
+    /// Choose which invariants every simulated process checks as it runs
+    ///
+    /// Large simulations can drop to [`InvariantLevel::Cheap`] to keep some
+    /// protection; [`InvariantLevel::Paranoid`] also checks after every
+    /// timeout check and block production round.
+    pub fn set_invariant_level(&mut self, level: InvariantLevel) {
+        for process in self.processes.values_mut() {
+            process.invariant_level = level;
+        }
+    }
+
+    pub fn set_leader_budget(&mut self, budget: LeaderBudget) {
+        for process in self.processes.values_mut() {
+            process.leader_budget = budget;
+        }
+    }
+
+    pub fn set_leader_policy(&mut self, policy: Arc<dyn LeaderPolicy>) {
+        for process in self.processes.values_mut() {
+            process.leader_policy = policy.clone();
+        }
+    }
+
+    pub fn set_phase_policy(&mut self, policy: Arc<dyn PhasePolicy>) {
+        for process in self.processes.values_mut() {
+            process.phase_policy = policy.clone();
+        }
+    }
+
+    pub fn set_extension_policy(&mut self, policy: Arc<dyn ExtensionPolicy>) {
+        for process in self.processes.values_mut() {
+            process.extension_policy = policy.clone();
+        }
+    }
+
+    pub fn set_vote_aggregation(&mut self, aggregation: VoteAggregation) {
+        for process in self.processes.values_mut() {
+            process.vote_aggregation = aggregation;
+        }
+    }
+
+    pub fn set_leader_election(&mut self, election: LeaderElection) {
+        for process in self.processes.values_mut() {
+            process.leader_election = election;
+        }
+    }
+
+    /// Give every simulated process the same view of identity metadata
+    pub fn set_metadata(&mut self, metadata: MetadataRegistry) {
+        for process in self.processes.values_mut() {
+            process.kb.metadata = metadata.clone();
         }
     }
 
     pub fn process_round(&mut self) -> bool {
         let mut made_progress = false;
 
-        let mut to_send = Vec::new();
         let mut next_round = Vec::new();
-        // Process all the messages from last round
-        while !self.pending_messages.is_empty() {
-            let (message, sender, dest) = self.pending_messages.pop_front().unwrap();
-
-            match dest {
-                Some(id) => {
-                    // Deliver to specific node
-                    if let Some(process) = self.processes.get_mut(&id) {
-                        let result = process.process_message(message, sender.clone(), &mut to_send);
-
-                        if result {
-                            made_progress = true;
-                        }
-                    }
+        // Process all the messages arriving this round
+        for (message, sender, to) in self.arrivals() {
+            if !self.faults.delivers(&sender, &to)
+                || self.faults.loss.as_mut().is_some_and(MessageLoss::drops)
+            {
+                continue;
+            }
+            let Some(process) = self.processes.get_mut(&to) else {
+                if let Some(inbox) = self.attached.get_mut(&to) {
+                    record_hop(&mut self.hops, self.rounds, &sender, &to, &message);
+                    inbox.push_back((sender, message));
                 }
-                None => {
-                    // Broadcast to all (other) nodes
-                    for (_, process) in self.processes.iter_mut() {
-                        if process.id == sender {
-                            continue;
-                        }
-                        let result =
-                            process.process_message(message.clone(), sender.clone(), &mut to_send);
-
-                        if result {
-                            made_progress = true;
-                        }
-                    }
+                continue;
+            };
+            if let Some(capacity) = self.dedup_capacity {
+                let cache = self
+                    .dedup
+                    .entry(to.clone())
+                    .or_insert_with(|| DedupCache::new(capacity));
+                if !cache.admit(&message) {
+                    continue;
                 }
             }
-
+            record_hop(&mut self.hops, self.rounds, &sender, &to, &message);
+            let mut to_send = Vec::new();
+            let input = TraceInput::Message { sender, message };
+            if traced(&mut self.trace, process, input, &mut to_send) {
+                made_progress = true;
+            }
             next_round.extend(
                 to_send
-                    .drain(..)
-                    .map(|(msg, dest)| (msg, sender.clone(), dest)),
+                    .into_iter()
+                    .map(|(msg, dest)| (msg, to.clone(), dest)),
             );
         }
 
         self.pending_messages.extend(next_round);
 
+        self.rounds += 1;
+        self.record_finalizations();
+
         made_progress
     }
 
+    /// The messages delivered this round, one per recipient
+    ///
+    /// Without a topology that is everything sent last round. With one, what
+    /// was sent last round sets off across the network, and what arrives is
+    /// whatever is due now, up to each link's bandwidth; the rest waits for
+    /// the next round.
+    fn arrivals(&mut self) -> Vec<(Message<TestTransaction>, Identity, Identity)> {
+        let ids = self
+            .processes
+            .keys()
+            .chain(self.attached.keys())
+            .cloned()
+            .collect::<Vec<_>>();
+        let sent = self
+            .pending_messages
+            .drain(..)
+            .flat_map(|(message, sender, dest)| {
+                let recipients = match dest {
+                    Some(id) => vec![id],
+                    None => ids.iter().filter(|id| **id != sender).cloned().collect(),
+                };
+                recipients
+                    .into_iter()
+                    .map(move |to| (message.clone(), sender.clone(), to))
+            });
+
+        let Some(topology) = &self.topology else {
+            return sent.collect();
+        };
+        for (message, sender, to) in sent {
+            let due = self.rounds + topology.delay(&sender, &to) - 1;
+            self.in_transit
+                .entry(due)
+                .or_default()
+                .push_back((message, sender, to));
+        }
+
+        let mut arriving = VecDeque::new();
+        while let Some(entry) = self.in_transit.first_entry() {
+            if *entry.key() > self.rounds {
+                break;
+            }
+            arriving.extend(entry.remove());
+        }
+        let mut carried: BTreeMap<(Identity, Identity), usize> = BTreeMap::new();
+        let mut arrivals = Vec::new();
+        for (message, sender, to) in arriving {
+            let count = carried.entry((sender.clone(), to.clone())).or_default();
+            if topology
+                .bandwidth(&sender, &to)
+                .is_some_and(|cap| *count >= cap)
+            {
+                self.in_transit
+                    .entry(self.rounds + 1)
+                    .or_default()
+                    .push_back((message, sender, to));
+                continue;
+            }
+            *count += 1;
+            arrivals.push((message, sender, to));
+        }
+        arrivals
+    }
+
+    /// Sets the size of each process's ingress dedup cache, or turns it off,
+    /// starting every cache afresh
+    pub fn set_dedup(&mut self, capacity: Option<usize>) {
+        self.dedup_capacity = capacity;
+        self.dedup.clear();
+    }
+
+    /// How often each process's dedup cache dropped a repeat
+    pub fn dedup_stats(&self) -> BTreeMap<Identity, DedupStats> {
+        self.dedup
+            .iter()
+            .map(|(id, cache)| (id.clone(), cache.stats()))
+            .collect()
+    }
+
+    /// Places processes on `topology`, or back on the default of next-round delivery
+    pub fn set_topology(&mut self, topology: Option<Topology>) {
+        self.topology = topology;
+    }
+
+    /// Places processes on one of the built-in [`TOPOLOGIES`]
+    pub fn load_topology(&mut self, name: &str) -> Result<(), UnknownTopology> {
+        let topology = Topology::preset(name, self.processes.keys().cloned())?;
+        self.set_topology(Some(topology));
+        Ok(())
+    }
+
+    /// Note the current round for every block that a process newly regards as final
+    ///
+    /// A block is final once it has an observed 2-QC, or once it is an
+    /// ancestor of such a block (this is how transaction blocks are finalized
+    /// through leader blocks in the high throughput phase).
+    fn record_finalizations(&mut self) {
+        for (id, process) in &self.processes {
+            for key in &process.index.finalized {
+                let mut to_visit = vec![key.clone()];
+                while let Some(key) = to_visit.pop() {
+                    let Some(timing) = self.block_timings.get_mut(&key) else {
+                        continue;
+                    };
+                    if timing.finalized_round.contains_key(id) {
+                        continue;
+                    }
+                    timing.finalized_round.insert(id.clone(), self.rounds);
+                    if let Some(block) = process.index.blocks.get(&key) {
+                        to_visit.extend(block.data.prev.iter().map(|qc| qc.data.for_which.clone()));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Finalization latencies, in message delays, of blocks of the given type
+    /// that were produced while their author was in `phase`.
+    ///
+    /// The latency of a block is measured until `k` processes regard it as final;
+    /// blocks that have not reached that point are skipped.
+    pub fn finalization_latencies(&self, type_: BlockType, phase: Phase, k: usize) -> Vec<usize> {
+        self.block_timings
+            .iter()
+            .filter(|(key, timing)| key.type_ == type_ && timing.created_phase == phase)
+            .filter_map(|(_, timing)| timing.kth_finality(k))
+            .collect()
+    }
+
     /// Check timeouts for all nodes
     pub fn check_all_timeouts(&mut self) -> bool {
         let mut made_progress = false;
 
         for (_, process) in self.processes.iter_mut() {
+            if self.faults.crashed.contains(&process.id) {
+                continue;
+            }
             let mut to_send = Vec::new();
-            process.check_timeouts(&mut to_send);
+            traced(&mut self.trace, process, TraceInput::Timeouts, &mut to_send);
 
             if !to_send.is_empty() {
                 made_progress = true;
@@ -196,7 +666,12 @@ impl MockHarness {
 
     /// Advance time by the configured step
     pub fn advance_time(&mut self) {
-        self.time += self.time_step;
+        self.set_time(self.time + self.time_step);
+    }
+
+    /// Moves the clock of the simulation and every process to `time`
+    pub fn set_time(&mut self, time: u128) {
+        self.time = time;
 
         // Update time for all processes
         for (_, process) in self.processes.iter_mut() {
@@ -209,6 +684,8 @@ impl MockHarness {
     /// 2. Check timeouts
     /// 3. Advance time
     pub fn step(&mut self) -> bool {
+        self.apply_scheduled_events();
+
         let processed = self.process_round();
         let timeouts = self.check_all_timeouts();
         let produced = self.produce_blocks();
@@ -228,7 +705,8 @@ impl MockHarness {
                 .iter()
                 .map(|qc| qc.data.clone())
                 .collect::<Vec<_>>();
-            tracing::info!(target: "process_state", process_id = ?process.id, time = self.time, steps = self.steps, tips = ?tips);
+            let name = process.kb.metadata.display_name(&process.id);
+            tracing::info!(target: "process_state", process_id = ?process.id, name = name, time = self.time, steps = self.steps, tips = ?tips);
         }
         made_progress
     }
@@ -236,7 +714,11 @@ impl MockHarness {
     /// Produce blocks for all nodes
     pub fn produce_blocks(&mut self) -> bool {
         let mut made_progress = false;
+        let peers = self.processes.keys().cloned().collect::<Vec<_>>();
         for (_, process) in self.processes.iter_mut() {
+            if self.faults.crashed.contains(&process.id) {
+                continue;
+            }
             let mut to_send = Vec::new();
             match self.tx_gen_policy.get(&process.id) {
                 Some(TxGenPolicy::EveryNSteps { n }) => {
@@ -263,16 +745,172 @@ impl MockHarness {
                     // Do nothing
                 }
             }
-            process.try_produce_blocks(&mut to_send);
+            let phase = *process.phase_i.get(&process.view_i).unwrap_or(&Phase::High);
+            let input = TraceInput::Produce {
+                ready: process.ready_transactions.clone(),
+            };
+            traced(&mut self.trace, process, input, &mut to_send);
             for (msg, dest) in to_send {
                 made_progress = true;
+                if let Message::Block(block) = &msg {
+                    if block.data.key.author.as_ref() == Some(&process.id) {
+                        self.block_timings
+                            .entry(block.data.key.clone())
+                            .or_insert(BlockTiming {
+                                created_round: self.rounds,
+                                created_phase: phase,
+                                finalized_round: BTreeMap::new(),
+                            });
+                    }
+                }
+                if self.faults.equivocators.contains(&process.id) {
+                    if let (Message::Block(block), None) = (&msg, &dest) {
+                        if block.data.key.type_ == BlockType::Tr {
+                            let conflicting = equivocate(block, &process.kb);
+                            for (i, peer) in peers.iter().filter(|p| **p != process.id).enumerate()
+                            {
+                                let copy = if i % 2 == 0 { &msg } else { &conflicting };
+                                self.pending_messages.push_back((
+                                    copy.clone(),
+                                    process.id.clone(),
+                                    Some(peer.clone()),
+                                ));
+                            }
+                            continue;
+                        }
+                    }
+                }
                 self.pending_messages
                     .push_back((msg, process.id.clone(), dest));
             }
         }
+        self.record_finalizations();
         made_progress
     }
 
+    /// Apply the scenario events scheduled for the current step
+    fn apply_scheduled_events(&mut self) {
+        for event in self.schedule.remove(&self.steps).unwrap_or_default() {
+            tracing::info!(target: "scenario_event", steps = self.steps, event = ?event);
+            match event {
+                ScenarioEvent::Crash(id) => {
+                    self.faults.crashed.insert(id);
+                }
+                ScenarioEvent::Recover(id) => {
+                    self.faults.crashed.remove(&id);
+                }
+                ScenarioEvent::Partition(groups) => self.faults.partition = Some(groups),
+                ScenarioEvent::Heal => self.faults.partition = None,
+                ScenarioEvent::Equivocate(id) => {
+                    self.faults.equivocators.insert(id);
+                }
+                ScenarioEvent::SetTxGenPolicy(id, policy) => {
+                    self.tx_gen_policy.insert(id, policy);
+                }
+                ScenarioEvent::CrashDomain(domain) => {
+                    let members = self.domain_members(&domain);
+                    self.faults.crashed.extend(members);
+                }
+                ScenarioEvent::RecoverDomain(domain) => {
+                    for id in self.domain_members(&domain) {
+                        self.faults.crashed.remove(&id);
+                    }
+                }
+                ScenarioEvent::Upgrade(id, profile) => self.set_profile(&id, &profile),
+                ScenarioEvent::SetTopology(topology) => self.set_topology(topology),
+                ScenarioEvent::ForceEndView(id) => {
+                    if self.faults.crashed.contains(&id) {
+                        continue;
+                    }
+                    let Some(process) = self.processes.get_mut(&id) else {
+                        continue;
+                    };
+                    let mut to_send = Vec::new();
+                    traced(
+                        &mut self.trace,
+                        process,
+                        TraceInput::ForceEndView,
+                        &mut to_send,
+                    );
+                    for (msg, dest) in to_send {
+                        self.pending_messages.push_back((msg, id.clone(), dest));
+                    }
+                }
+            }
+        }
+    }
+
+    /// [`MorpheusProcess::dag_stats`] of every process, for exporting a run's DAG shape
+    pub fn dag_report(&self) -> BTreeMap<Identity, DagStats> {
+        self.processes
+            .iter()
+            .map(|(id, process)| (id.clone(), process.dag_stats()))
+            .collect()
+    }
+
+    /// Runs `id` on `profile` from now on
+    pub fn set_profile(&mut self, id: &Identity, profile: &ProcessProfile) {
+        let Some(process) = self.processes.get_mut(id) else {
+            return;
+        };
+        profile.apply(process);
+        if profile.version == CURRENT_VERSION {
+            self.versions.remove(id);
+        } else {
+            self.versions.insert(id.clone(), profile.version.clone());
+        }
+    }
+
+    pub fn version_of(&self, id: &Identity) -> &str {
+        self.versions.get(id).map_or(CURRENT_VERSION, |v| v)
+    }
+
+    /// Schedules upgrading every process to `profile`, one every `interval`
+    /// steps starting at `start`, in identity order
+    pub fn schedule_rolling_upgrade(
+        &mut self,
+        start: usize,
+        interval: usize,
+        profile: &ProcessProfile,
+    ) {
+        let ids = self.processes.keys().cloned().collect::<Vec<_>>();
+        for (i, id) in ids.into_iter().enumerate() {
+            self.schedule_event(
+                start + i * interval,
+                ScenarioEvent::Upgrade(id, profile.clone()),
+            );
+        }
+    }
+
+    pub fn set_failure_domain(&mut self, id: Identity, domain: impl Into<String>) {
+        self.domains.insert(id, domain.into());
+    }
+
+    /// The processes placed in `domain`
+    pub fn domain_members(&self, domain: &str) -> BTreeSet<Identity> {
+        self.domains
+            .iter()
+            .filter(|(_, d)| d.as_str() == domain)
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+
+    /// Whether losing any single failure domain leaves liveness intact, that is,
+    /// no domain holds more than `f` processes
+    pub fn placement_tolerates_domain_loss(&self) -> bool {
+        let mut per_domain: BTreeMap<&str, u32> = BTreeMap::new();
+        for domain in self.domains.values() {
+            *per_domain.entry(domain.as_str()).or_default() += 1;
+        }
+        let f = self.processes.values().map(|p| p.f).next().unwrap_or(0);
+        per_domain.values().all(|&count| count <= f)
+    }
+
+    /// Schedule a scenario event to take effect at the start of `step`
+    pub fn schedule_event(&mut self, step: usize, event: ScenarioEvent) {
+        self.schedule.entry(step).or_default().push(event);
+    }
+
     /// Run the simulation for the specified number of steps
     pub fn run(&mut self, steps: usize) -> bool {
         let mut made_progress = false;
@@ -284,6 +922,110 @@ impl MockHarness {
         made_progress
     }
 
+    /// The blocks a process regards as final, ordered by height and then key
+    ///
+    /// Blocks are always taller than their predecessors, so this ordering lists
+    /// ancestors first and is the same for any two processes that agree.
+    pub fn finalized_prefix(&self, id: &Identity) -> Vec<BlockKey> {
+        let Some(process) = self.processes.get(id) else {
+            return Vec::new();
+        };
+        let mut keys = process.finalized_blocks().into_iter().collect::<Vec<_>>();
+        keys.sort_by(|a, b| a.height.cmp(&b.height).then_with(|| a.cmp(b)));
+        keys
+    }
+
+    /// Compare the finalized histories of every pair of processes
+    pub fn divergence_report(&self) -> DivergenceReport {
+        let prefixes = self
+            .processes
+            .keys()
+            .map(|id| (id.clone(), self.finalized_prefix(id)))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut report = DivergenceReport {
+            finalized_counts: prefixes
+                .iter()
+                .map(|(id, prefix)| (id.clone(), prefix.len()))
+                .collect(),
+            common_prefix_len: 0,
+            conflicts: Vec::new(),
+        };
+
+        // compare the blocks at each height as sets, since processes may
+        // finalize blocks of the same height in either order
+        let by_height = prefixes
+            .values()
+            .map(|prefix| {
+                let mut heights = BTreeMap::<_, BTreeSet<_>>::new();
+                for key in prefix {
+                    heights.entry(key.height).or_default().insert(key);
+                }
+                heights
+            })
+            .collect::<Vec<_>>();
+        if let Some((first, rest)) = by_height.split_first() {
+            report.common_prefix_len = first
+                .iter()
+                .map(|(height, keys)| {
+                    keys.iter()
+                        .filter(|key| {
+                            rest.iter().all(|other| {
+                                other.get(height).is_some_and(|keys| keys.contains(*key))
+                            })
+                        })
+                        .count()
+                })
+                .sum();
+        }
+
+        let by_position = prefixes
+            .iter()
+            .map(|(id, prefix)| {
+                let positions = prefix
+                    .iter()
+                    .map(|key| (SlotKey::from(key), key))
+                    .collect::<BTreeMap<_, _>>();
+                (id, positions)
+            })
+            .collect::<Vec<_>>();
+
+        for (i, (first, first_positions)) in by_position.iter().enumerate() {
+            for (second, second_positions) in &by_position[i + 1..] {
+                for (position, first_key) in first_positions {
+                    if let Some(second_key) = second_positions.get(position) {
+                        if first_key != second_key {
+                            report.conflicts.push(Divergence {
+                                first: (*first).clone(),
+                                first_key: (*first_key).clone(),
+                                second: (*second).clone(),
+                                second_key: (*second_key).clone(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        report
+    }
+
+    /// Lets a process outside the harness take part as `id`, in place of the
+    /// simulated one if any, receiving what the harness delivers to it
+    /// through [`Self::transport`]
+    ///
+    /// It has to keep its own clock in step with `time` and check its
+    /// timeouts and produce blocks itself, see `MorpheusProcess::tick_transport`.
+    pub fn attach(&mut self, id: Identity) {
+        self.processes.remove(&id);
+        self.attached.entry(id).or_default();
+    }
+
+    /// The network as seen by the attached process `id`
+    pub fn transport(&mut self, id: Identity) -> HarnessTransport<'_> {
+        HarnessTransport { harness: self, id }
+    }
+
     /// Add a message to the pending queue
     pub fn enqueue_message(
         &mut self,
@@ -295,3 +1037,10 @@ impl MockHarness {
             .push_back((message, sender, destination));
     }
 }
+
+/// Re-sign a transaction block at the same position with different contents
+fn equivocate(block: &Signed<Block<TestTransaction>>, kb: &KeyBook) -> Message<TestTransaction> {
+    let mut conflicting = block.data.clone();
+    conflicting.data = BlockData::tr(vec![TestTransaction(vec![0xba, 0xd])]);
+    Message::Block(Arc::new(Signed::from_data(conflicting.hashed(), kb)))
+}
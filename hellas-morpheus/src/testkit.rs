@@ -0,0 +1,176 @@
+//! Assertions about consensus properties of a simulated cluster
+//!
+//! These panic with a description of every offending process, which is a lot
+//! more useful than a bare `assert!` when a long simulation goes wrong.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use crate::format::*;
+use crate::test_harness::{MockHarness, TestTransaction};
+use crate::*;
+
+/// Asserts that no two processes finalized different blocks in the same position
+#[track_caller]
+pub fn assert_agreement(harness: &MockHarness) {
+    let report = harness.divergence_report();
+    if report.is_consistent() {
+        return;
+    }
+
+    let mut msg = String::from("processes disagree on finalized blocks\n");
+    for (id, count) in &report.finalized_counts {
+        writeln!(msg, "  {} finalized {} blocks", format_identity(id), count).unwrap();
+    }
+    writeln!(msg, "  common prefix: {} blocks", report.common_prefix_len).unwrap();
+    for conflict in &report.conflicts {
+        writeln!(
+            msg,
+            "  - {}: {}\n    + {}: {}",
+            format_identity(&conflict.first),
+            format_block_key(&conflict.first_key),
+            format_identity(&conflict.second),
+            format_block_key(&conflict.second_key)
+        )
+        .unwrap();
+    }
+    panic!("{}", msg);
+}
+
+/// Asserts that every process has finalized a block containing `tx`, and that
+/// block's view is at most `views` after the first view in which any process
+/// included `tx` in a block
+#[track_caller]
+pub fn assert_finalized_within(harness: &MockHarness, tx: &TestTransaction, views: i64) {
+    let contains_tx = |block: &Block<TestTransaction>| match &block.data {
+        BlockData::Tr { transactions, .. } => transactions.contains(tx),
+        _ => false,
+    };
+
+    let first_view = harness
+        .processes
+        .values()
+        .flat_map(|p| p.index.blocks.values())
+        .filter(|block| contains_tx(&block.data))
+        .map(|block| block.data.key.view)
+        .min();
+    let Some(first_view) = first_view else {
+        panic!(
+            "{} was never included in a block",
+            format_transaction(tx, false)
+        );
+    };
+    let deadline = ViewNum(first_view.0 + views);
+
+    let mut msg = String::new();
+    for (id, process) in &harness.processes {
+        let finalized = process.finalized_blocks();
+        let in_time = finalized.iter().any(|key| {
+            key.view <= deadline
+                && process
+                    .index
+                    .blocks
+                    .get(key)
+                    .is_some_and(|block| contains_tx(&block.data))
+        });
+        if !in_time {
+            writeln!(
+                msg,
+                "  {} (in {}) has not finalized it by {}",
+                format_identity(id),
+                format_view_num(&process.view_i),
+                format_view_num(&deadline)
+            )
+            .unwrap();
+        }
+    }
+    if !msg.is_empty() {
+        panic!(
+            "{} first included in {} was not finalized within {} views\n{}",
+            format_transaction(tx, false),
+            format_view_num(&first_view),
+            views,
+            msg
+        );
+    }
+}
+
+/// Asserts that, for every pair of versions running in the harness, some
+/// process on the first has finalized a block authored by one on the second
+#[track_caller]
+pub fn assert_versions_interoperate(harness: &MockHarness) {
+    let versions = harness
+        .processes
+        .keys()
+        .map(|id| harness.version_of(id))
+        .collect::<BTreeSet<_>>();
+
+    let mut msg = String::new();
+    for finalizer in &versions {
+        for author in &versions {
+            let interoperates = harness
+                .processes
+                .keys()
+                .filter(|id| harness.version_of(id) == *finalizer)
+                .flat_map(|id| harness.finalized_prefix(id))
+                .filter_map(|key| key.author)
+                .any(|by| harness.version_of(&by) == *author);
+            if !interoperates {
+                writeln!(
+                    msg,
+                    "  nothing by a {} process was finalized on {}",
+                    author, finalizer
+                )
+                .unwrap();
+            }
+        }
+    }
+    if !msg.is_empty() {
+        panic!(
+            "versions don't interoperate after {} steps\n{}",
+            harness.steps, msg
+        );
+    }
+}
+
+/// Asserts that no process has any internal invariant violations
+#[track_caller]
+pub fn assert_no_invariant_violations(harness: &MockHarness) {
+    let mut msg = String::new();
+    for (id, process) in &harness.processes {
+        for violation in process.check_invariants() {
+            writeln!(msg, "  {}: {}", format_identity(id), violation).unwrap();
+        }
+    }
+    if !msg.is_empty() {
+        panic!(
+            "invariant violations after {} steps\n{}",
+            harness.steps, msg
+        );
+    }
+}
+
+/// Asserts that no process has gone past view `max`
+#[track_caller]
+pub fn assert_view_le(harness: &MockHarness, max: ViewNum) {
+    let mut msg = String::new();
+    for (id, process) in &harness.processes {
+        if process.view_i > max {
+            writeln!(
+                msg,
+                "  {} is in {}",
+                format_identity(id),
+                format_view_num(&process.view_i)
+            )
+            .unwrap();
+        }
+    }
+    if !msg.is_empty() {
+        panic!(
+            "processes went past {} after {} steps\n{}",
+            format_view_num(&max),
+            harness.steps,
+            msg
+        );
+    }
+}
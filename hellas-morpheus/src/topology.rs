@@ -0,0 +1,190 @@
+//! Where simulated processes sit, and what the network between them costs
+//!
+//! By default the harness delivers every message in the round after it was
+//! sent, as if all processes shared a rack. A [`Topology`] places processes in
+//! regions instead, with one-way latencies between regions and a cap on how
+//! many messages a link between two regions carries per round, so the same
+//! scenario can be run over a single datacenter or across continents and the
+//! results compared.
+//!
+//! Latencies are given in milliseconds and rounded up to whole rounds of
+//! `round_ms`; a message is never delivered sooner than the next round.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::*;
+
+/// Names of the built-in topologies, in the order they should be offered
+pub const TOPOLOGIES: &[&str] = &["single-dc", "3-region", "global-9-region"];
+
+/// Error returned when asked for a topology that doesn't exist
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownTopology(pub String);
+
+impl fmt::Display for UnknownTopology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Unknown topology {:?}, expected one of {:?}",
+            self.0, TOPOLOGIES
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Topology {
+    pub name: String,
+    /// Region of each process; links to or from a process without one are
+    /// treated as local
+    pub regions: BTreeMap<Identity, String>,
+    /// One-way latency between two regions, stored once per pair with the
+    /// names in order
+    pub latency_ms: BTreeMap<(String, String), u32>,
+    /// Latency within a region, and between regions missing from `latency_ms`
+    pub local_ms: u32,
+    /// Simulated time a harness round stands for
+    pub round_ms: u32,
+    /// Most messages a link between two regions carries per round; links
+    /// within a region are never capped
+    pub wan_bandwidth: Option<usize>,
+}
+
+/// The `single-dc` topology has a single region, so no links between regions
+const ONE_REGION: &[(&str, &str, u32)] = &[];
+
+/// One-way latencies between the regions of the `3-region` topology
+const THREE_REGIONS: &[(&str, &str, u32)] = &[
+    ("us-east", "eu-west", 40),
+    ("us-east", "ap-southeast", 110),
+    ("eu-west", "ap-southeast", 85),
+];
+
+/// One-way latencies between the regions of the `global-9-region` topology
+const NINE_REGIONS: &[(&str, &str, u32)] = &[
+    ("us-east", "us-west", 35),
+    ("us-east", "sa-east", 60),
+    ("us-east", "eu-west", 40),
+    ("us-east", "eu-central", 45),
+    ("us-east", "af-south", 115),
+    ("us-east", "ap-south", 100),
+    ("us-east", "ap-northeast", 75),
+    ("us-east", "ap-southeast", 110),
+    ("us-west", "sa-east", 90),
+    ("us-west", "eu-west", 70),
+    ("us-west", "eu-central", 75),
+    ("us-west", "af-south", 145),
+    ("us-west", "ap-south", 115),
+    ("us-west", "ap-northeast", 55),
+    ("us-west", "ap-southeast", 85),
+    ("sa-east", "eu-west", 90),
+    ("sa-east", "eu-central", 100),
+    ("sa-east", "af-south", 170),
+    ("sa-east", "ap-south", 150),
+    ("sa-east", "ap-northeast", 130),
+    ("sa-east", "ap-southeast", 160),
+    ("eu-west", "eu-central", 12),
+    ("eu-west", "af-south", 80),
+    ("eu-west", "ap-south", 60),
+    ("eu-west", "ap-northeast", 110),
+    ("eu-west", "ap-southeast", 85),
+    ("eu-central", "af-south", 80),
+    ("eu-central", "ap-south", 55),
+    ("eu-central", "ap-northeast", 115),
+    ("eu-central", "ap-southeast", 80),
+    ("af-south", "ap-south", 130),
+    ("af-south", "ap-northeast", 175),
+    ("af-south", "ap-southeast", 135),
+    ("ap-south", "ap-northeast", 60),
+    ("ap-south", "ap-southeast", 30),
+    ("ap-northeast", "ap-southeast", 35),
+];
+
+impl Topology {
+    /// Builds one of the built-in [`TOPOLOGIES`], spreading `ids` over its
+    /// regions in turn
+    pub fn preset(
+        name: &str,
+        ids: impl IntoIterator<Item = Identity>,
+    ) -> Result<Self, UnknownTopology> {
+        let (regions, links, wan_bandwidth) = match name {
+            "single-dc" => (vec!["dc"], ONE_REGION, None),
+            "3-region" => (
+                vec!["us-east", "eu-west", "ap-southeast"],
+                THREE_REGIONS,
+                Some(64),
+            ),
+            "global-9-region" => (
+                vec![
+                    "us-east",
+                    "us-west",
+                    "sa-east",
+                    "eu-west",
+                    "eu-central",
+                    "af-south",
+                    "ap-south",
+                    "ap-northeast",
+                    "ap-southeast",
+                ],
+                NINE_REGIONS,
+                Some(32),
+            ),
+            _ => return Err(UnknownTopology(name.to_string())),
+        };
+
+        let mut topology = Topology {
+            name: name.to_string(),
+            regions: ids
+                .into_iter()
+                .zip(regions.iter().cycle())
+                .map(|(id, region)| (id, region.to_string()))
+                .collect(),
+            latency_ms: BTreeMap::new(),
+            local_ms: 1,
+            round_ms: 20,
+            wan_bandwidth,
+        };
+        for (a, b, ms) in links {
+            topology.set_latency(a, b, *ms);
+        }
+        Ok(topology)
+    }
+
+    pub fn set_latency(&mut self, a: &str, b: &str, ms: u32) {
+        let key = if a <= b { (a, b) } else { (b, a) };
+        self.latency_ms
+            .insert((key.0.to_string(), key.1.to_string()), ms);
+    }
+
+    pub fn region_of(&self, id: &Identity) -> Option<&str> {
+        self.regions.get(id).map(String::as_str)
+    }
+
+    fn regions_of(&self, from: &Identity, to: &Identity) -> Option<(&str, &str)> {
+        match (self.region_of(from), self.region_of(to)) {
+            (Some(a), Some(b)) if a != b => Some(if a <= b { (a, b) } else { (b, a) }),
+            _ => None,
+        }
+    }
+
+    /// One-way latency from `from` to `to`
+    pub fn latency(&self, from: &Identity, to: &Identity) -> u32 {
+        self.regions_of(from, to)
+            .and_then(|(a, b)| {
+                self.latency_ms
+                    .get(&(a.to_string(), b.to_string()))
+                    .copied()
+            })
+            .unwrap_or(self.local_ms)
+    }
+
+    /// Rounds a message from `from` takes to reach `to`, at least one
+    pub fn delay(&self, from: &Identity, to: &Identity) -> usize {
+        (self.latency(from, to).div_ceil(self.round_ms.max(1)) as usize).max(1)
+    }
+
+    /// Most messages from `from` that reach `to` in one round, if capped
+    pub fn bandwidth(&self, from: &Identity, to: &Identity) -> Option<usize> {
+        self.regions_of(from, to).and(self.wan_bandwidth)
+    }
+}
@@ -0,0 +1,265 @@
+//! Recorded executions, and replaying them against the current code.
+//!
+//! A trace is every input each process was given during a simulation (a
+//! delivered message, a timeout check or a chance to produce blocks), together
+//! with what the process sent and newly finalized in response. Replaying a
+//! trace feeds the same inputs to fresh processes built from the recorded
+//! setup and compares the responses, so a trace recorded before a refactor
+//! shows whether the refactor changed behaviour. The `replay-trace` binary runs
+//! this over trace files on disk.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+/// Enough to rebuild a process exactly as it was when recording started
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProcessSetup {
+    pub id: Identity,
+    pub kb: KeyBook,
+    /// `kb` is serialized without its secret keys, but a replay has to sign
+    /// what the recording signed
+    #[serde(default, with = "crate::secret::exposed")]
+    pub sec_key: SecretKey,
+    #[serde(default, with = "crate::secret::exposed")]
+    pub election_key: SecretKey,
+    pub n: u32,
+    pub f: u32,
+    pub delta: u128,
+    #[serde(default)]
+    pub vote_aggregation: VoteAggregation,
+    #[serde(default)]
+    pub leader_election: LeaderElection,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TraceInput<Tr: Transaction> {
+    Message {
+        sender: Identity,
+        message: Message<Tr>,
+    },
+    Timeouts,
+    /// `try_produce_blocks`, with these transactions ready
+    Produce {
+        ready: Vec<Tr>,
+    },
+    ForceEndView,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceEntry<Tr: Transaction> {
+    pub process: Identity,
+    pub now: u128,
+    pub input: TraceInput<Tr>,
+    pub outputs: Vec<(Message<Tr>, Option<Identity>)>,
+    /// Blocks that gained a 2-QC while handling `input`, in key order
+    pub finalized: Vec<BlockKey>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ExecutionTrace<Tr: Transaction> {
+    /// Version of this crate that recorded the trace
+    pub recorded_with: String,
+    pub processes: Vec<ProcessSetup>,
+    pub entries: Vec<TraceEntry<Tr>>,
+}
+
+impl<Tr: Transaction> ExecutionTrace<Tr> {
+    /// Starts a trace of `processes`, which must not have handled any input yet
+    pub fn new<'a>(processes: impl IntoIterator<Item = &'a MorpheusProcess<Tr>>) -> Self {
+        ExecutionTrace {
+            recorded_with: env!("CARGO_PKG_VERSION").to_string(),
+            processes: processes
+                .into_iter()
+                .map(|p| ProcessSetup {
+                    id: p.id.clone(),
+                    kb: p.kb.clone(),
+                    sec_key: p.kb.me_sec_key.clone(),
+                    election_key: p.kb.election_key.clone().unwrap_or_default(),
+                    n: p.n,
+                    f: p.f,
+                    delta: p.delta,
+                    vote_aggregation: p.vote_aggregation,
+                    leader_election: p.leader_election,
+                })
+                .collect(),
+            entries: Vec::new(),
+        }
+    }
+}
+
+/// Hands `input` to `process`, returning what the corresponding method returns
+pub fn apply_input<Tr: Transaction>(
+    process: &mut MorpheusProcess<Tr>,
+    input: &TraceInput<Tr>,
+    to_send: &mut Vec<(Message<Tr>, Option<Identity>)>,
+) -> bool {
+    match input {
+        TraceInput::Message { sender, message } => {
+            process.process_message(message.clone(), sender.clone(), to_send)
+        }
+        TraceInput::Timeouts => {
+            process.check_timeouts(to_send);
+            true
+        }
+        TraceInput::Produce { ready } => {
+            process.ready_transactions = ready.clone();
+            process.try_produce_blocks(to_send);
+            true
+        }
+        TraceInput::ForceEndView => process.force_end_view(to_send),
+    }
+}
+
+/// [`apply_input`], recording the input and its effects into `trace` if there is one
+pub(crate) fn traced<Tr: Transaction>(
+    trace: &mut Option<ExecutionTrace<Tr>>,
+    process: &mut MorpheusProcess<Tr>,
+    input: TraceInput<Tr>,
+    to_send: &mut Vec<(Message<Tr>, Option<Identity>)>,
+) -> bool {
+    let Some(trace) = trace else {
+        return apply_input(process, &input, to_send);
+    };
+
+    let start = to_send.len();
+    let finalized_before = process.index.finalized.clone();
+    let now = process.current_time;
+    let result = apply_input(process, &input, to_send);
+
+    trace.entries.push(TraceEntry {
+        process: process.id.clone(),
+        now,
+        input,
+        outputs: to_send[start..].to_vec(),
+        finalized: process
+            .index
+            .finalized
+            .difference(&finalized_before)
+            .cloned()
+            .collect(),
+    });
+    result
+}
+
+/// The first point at which a replayed process responded differently
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReplayDivergence<Tr: Transaction> {
+    Messages {
+        entry: usize,
+        process: Identity,
+        recorded: Vec<(Message<Tr>, Option<Identity>)>,
+        replayed: Vec<(Message<Tr>, Option<Identity>)>,
+    },
+    Finalization {
+        entry: usize,
+        process: Identity,
+        recorded: Vec<BlockKey>,
+        replayed: Vec<BlockKey>,
+    },
+    /// The trace mentions a process that isn't in its setup
+    UnknownProcess { entry: usize, process: Identity },
+}
+
+impl<Tr: Transaction> ReplayDivergence<Tr> {
+    pub fn process(&self) -> &Identity {
+        match self {
+            ReplayDivergence::Messages { process, .. }
+            | ReplayDivergence::Finalization { process, .. }
+            | ReplayDivergence::UnknownProcess { process, .. } => process,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ReplayReport<Tr: Transaction> {
+    pub recorded_with: String,
+    pub entries_replayed: usize,
+    /// At most one per process: once a process diverges, its later entries are skipped
+    pub divergences: Vec<ReplayDivergence<Tr>>,
+}
+
+impl<Tr: Transaction> ReplayReport<Tr> {
+    pub fn is_compatible(&self) -> bool {
+        self.divergences.is_empty()
+    }
+}
+
+/// Replays `trace` against fresh processes built with the current code
+pub fn replay<Tr: Transaction>(trace: &ExecutionTrace<Tr>) -> ReplayReport<Tr> {
+    let mut processes: BTreeMap<Identity, MorpheusProcess<Tr>> = trace
+        .processes
+        .iter()
+        .map(|setup| {
+            let mut kb = setup.kb.clone();
+            kb.me_sec_key = setup.sec_key.clone();
+            kb.election_key = Some(setup.election_key.clone()).filter(SecretKey::is_present);
+            let mut process = MorpheusProcess::new(kb, setup.id.clone(), setup.n, setup.f);
+            process.delta = setup.delta;
+            process.vote_aggregation = setup.vote_aggregation;
+            process.leader_election = setup.leader_election;
+            (setup.id.clone(), process)
+        })
+        .collect();
+
+    let mut diverged = BTreeSet::new();
+    let mut report = ReplayReport {
+        recorded_with: trace.recorded_with.clone(),
+        entries_replayed: 0,
+        divergences: Vec::new(),
+    };
+
+    for (i, entry) in trace.entries.iter().enumerate() {
+        if diverged.contains(&entry.process) {
+            continue;
+        }
+        let Some(process) = processes.get_mut(&entry.process) else {
+            diverged.insert(entry.process.clone());
+            report.divergences.push(ReplayDivergence::UnknownProcess {
+                entry: i,
+                process: entry.process.clone(),
+            });
+            continue;
+        };
+
+        process.set_now(entry.now);
+        let finalized_before = process.index.finalized.clone();
+        let mut outputs = Vec::new();
+        apply_input(process, &entry.input, &mut outputs);
+        report.entries_replayed += 1;
+
+        let finalized: Vec<_> = process
+            .index
+            .finalized
+            .difference(&finalized_before)
+            .cloned()
+            .collect();
+
+        let divergence = if outputs != entry.outputs {
+            Some(ReplayDivergence::Messages {
+                entry: i,
+                process: entry.process.clone(),
+                recorded: entry.outputs.clone(),
+                replayed: outputs,
+            })
+        } else if finalized != entry.finalized {
+            Some(ReplayDivergence::Finalization {
+                entry: i,
+                process: entry.process.clone(),
+                recorded: entry.finalized.clone(),
+                replayed: finalized,
+            })
+        } else {
+            None
+        };
+
+        if let Some(divergence) = divergence {
+            diverged.insert(entry.process.clone());
+            report.divergences.push(divergence);
+        }
+    }
+
+    report
+}
@@ -1,8 +1,140 @@
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
 use tracing::{debug, error, info};
 
+/// Environment variable [`Redaction::from_env`] reads
+pub const PAYLOAD_LOGGING_ENV: &str = "MORPHEUS_LOG_PAYLOADS";
+
+/// How much of a transaction may appear in logs
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PayloadLogging {
+    /// A short hash of the transaction, the prefix of its Merkle leaf hash
+    #[default]
+    HashOnly,
+    /// The transaction's `Debug` output
+    Full,
+}
+
+impl FromStr for PayloadLogging {
+    type Err = ParseRedactionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hash" => Ok(PayloadLogging::HashOnly),
+            "full" => Ok(PayloadLogging::Full),
+            _ => Err(ParseRedactionError(s.to_string())),
+        }
+    }
+}
+
+/// Which log targets may show transactions in full
+///
+/// Written like a log filter: a comma-separated list of `hash` or `full`,
+/// optionally prefixed with `target=`, where an entry without a target sets
+/// the default. `hash,duplicate_message=full` shows transactions only in
+/// duplicate message reports.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Redaction {
+    pub default: PayloadLogging,
+    pub targets: BTreeMap<String, PayloadLogging>,
+}
+
+/// A redaction spec entry that isn't `hash`, `full` or `target=` one of those
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseRedactionError(pub String);
+
+impl fmt::Display for ParseRedactionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid payload logging {:?}, expected \"hash\" or \"full\"",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseRedactionError {}
+
+impl FromStr for Redaction {
+    type Err = ParseRedactionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut redaction = Redaction::default();
+        for entry in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            match entry.split_once('=') {
+                Some((target, level)) => {
+                    redaction
+                        .targets
+                        .insert(target.trim().to_string(), level.trim().parse()?);
+                }
+                None => redaction.default = entry.parse()?,
+            }
+        }
+        Ok(redaction)
+    }
+}
+
+impl Redaction {
+    /// What may be logged under `target`, or outside any target
+    pub fn for_target(&self, target: Option<&str>) -> PayloadLogging {
+        target
+            .and_then(|target| self.targets.get(target))
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    /// The redaction given in [`PAYLOAD_LOGGING_ENV`], or the default if unset
+    pub fn from_env() -> Result<Self, ParseRedactionError> {
+        match std::env::var(PAYLOAD_LOGGING_ENV) {
+            Ok(spec) => spec.parse(),
+            Err(_) => Ok(Redaction::default()),
+        }
+    }
+}
+
+static REDACTION: RwLock<Redaction> = RwLock::new(Redaction {
+    default: PayloadLogging::HashOnly,
+    targets: BTreeMap::new(),
+});
+
+thread_local! {
+    static PAYLOAD_TARGET: Cell<Option<&'static str>> = const { Cell::new(None) };
+}
+
+/// Replaces the redaction applied to everything logged from now on
+pub fn set_redaction(redaction: Redaction) {
+    *REDACTION.write().unwrap() = redaction;
+}
+
+pub fn redaction() -> Redaction {
+    REDACTION.read().unwrap().clone()
+}
+
+/// Runs `f`, formatting transactions in it as allowed for `target`
+///
+/// Formatting doesn't know which log line it is for, so a log call that may
+/// include transactions runs inside this with its own target.
+pub fn in_target<R>(target: &'static str, f: impl FnOnce() -> R) -> R {
+    let outer = PAYLOAD_TARGET.with(|current| current.replace(Some(target)));
+    let result = f();
+    PAYLOAD_TARGET.with(|current| current.set(outer));
+    result
+}
+
+/// What may be logged of transactions here, see [`in_target`]
+pub fn payload_logging() -> PayloadLogging {
+    let target = PAYLOAD_TARGET.with(|current| current.get());
+    REDACTION.read().unwrap().for_target(target)
+}
+
 /// Register a new Morpheus process with tracing
-pub fn register_process(id: &crate::Identity, n: u32, f: u32) {
-    info!(target: "register_process", process_id = ?id, total_processes = n, max_faulty = f);
+pub fn register_process(id: &crate::Identity, name: &str, n: u32, f: u32) {
+    info!(target: "register_process", process_id = ?id, name = name, total_processes = n, max_faulty = f);
 }
 
 /// Track protocol transitions such as view changes
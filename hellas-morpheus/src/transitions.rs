@@ -0,0 +1,333 @@
+//! The message-handling state machine as data.
+//!
+//! Each arm of [`MorpheusProcess::process_message`](crate::MorpheusProcess::process_message)
+//! that leads somewhere distinct is annotated with a `// transition: <id>`
+//! comment, and [`TRANSITIONS`] describes each of those ids: which message
+//! kind it handles (none for the ones taken before the handler looks at the
+//! kind), the local predicate that selects it, what state it updates and what
+//! it sends. The `transition-table` binary prints the table as JSON
+//! (or markdown with `--markdown`) for the docs, the visualizer tooltips and
+//! the conformance suite; `tests/transition_table_tests.rs` fails if the
+//! annotations and the table drift apart.
+
+use serde::Serialize;
+
+use crate::MessageKind;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Transition {
+    /// Matches a `// transition: <id>` annotation in the handler
+    pub id: &'static str,
+    /// `None` for transitions any message can take
+    pub message: Option<MessageKind>,
+    /// Local predicate under which this transition is taken
+    pub guard: &'static str,
+    /// State written, by field name
+    pub updates: &'static [&'static str],
+    /// Messages sent, as `Kind -> recipients`
+    pub emits: &'static [&'static str],
+}
+
+/// Every transition of the message handler, grouped by message kind in
+/// declaration order
+pub const TRANSITIONS: &[Transition] = &[
+    Transition {
+        id: "rate-limited",
+        message: None,
+        guard: "sender over its limit for the kind, or banned (see rate_limit)",
+        updates: &["rate_limiter"],
+        emits: &[],
+    },
+    Transition {
+        id: "block-invalid",
+        message: Some(MessageKind::Block),
+        guard: "block_valid(block) fails",
+        updates: &["received_messages"],
+        emits: &[],
+    },
+    Transition {
+        id: "block-awaiting-ancestors",
+        message: Some(MessageKind::Block),
+        guard: "block_valid(block), some prev block not held, not our own block",
+        updates: &[
+            "received_messages",
+            "awaiting_ancestors",
+            "requested_blocks",
+        ],
+        emits: &["GetBlock -> all, for each missing block not already requested"],
+    },
+    Transition {
+        id: "block-accepted",
+        message: Some(MessageKind::Block),
+        guard: "block_valid(block), every prev block held or our own block",
+        updates: &[
+            "received_messages",
+            "voted_i",
+            "index.blocks",
+            "index.tips",
+            "awaiting_ancestors",
+        ],
+        emits: &[
+            "NewVote(z=0) -> block author, unless already voted",
+            "the same for each held-back block no longer missing ancestors",
+        ],
+    },
+    Transition {
+        id: "get-block-unknown",
+        message: Some(MessageKind::GetBlock),
+        guard: "block not held",
+        updates: &["received_messages"],
+        emits: &[],
+    },
+    Transition {
+        id: "get-block-served",
+        message: Some(MessageKind::GetBlock),
+        guard: "block held",
+        updates: &["received_messages"],
+        emits: &["BlockResponse -> sender"],
+    },
+    Transition {
+        id: "block-response-unneeded",
+        message: Some(MessageKind::BlockResponse),
+        guard: "block not requested, or already held",
+        updates: &["received_messages"],
+        emits: &[],
+    },
+    Transition {
+        id: "block-response-accepted",
+        message: Some(MessageKind::BlockResponse),
+        guard: "block requested and not held",
+        updates: &["received_messages"],
+        emits: &["whatever the block's own broadcast would"],
+    },
+    Transition {
+        id: "vote-invalid",
+        message: Some(MessageKind::NewVote),
+        guard: "vote signature checked and invalid",
+        updates: &["received_messages"],
+        emits: &[],
+    },
+    Transition {
+        id: "vote-recorded",
+        message: Some(MessageKind::NewVote),
+        guard: "vote signature valid, or the vote came from its author over a trusted link",
+        updates: &["received_messages", "vote_tracker", "index.qcs"],
+        emits: &["QC -> all, once n-f votes for the same data are recorded"],
+    },
+    Transition {
+        id: "qc-invalid",
+        message: Some(MessageKind::QC),
+        guard: "QC does not carry n-f signatures",
+        updates: &["received_messages"],
+        emits: &[],
+    },
+    Transition {
+        id: "qc-recorded",
+        message: Some(MessageKind::QC),
+        guard: "QC valid and index.max_view.0 <= view_i afterwards",
+        updates: &[
+            "received_messages",
+            "index.qcs",
+            "index.tips",
+            "index.max_1qc",
+            "index.max_view",
+            "index.finalized",
+        ],
+        emits: &[],
+    },
+    Transition {
+        id: "qc-advances-view",
+        message: Some(MessageKind::QC),
+        guard: "QC valid and index.max_view.0 > view_i afterwards",
+        updates: &[
+            "received_messages",
+            "index.qcs",
+            "index.max_view",
+            "view_i",
+            "view_entry_time",
+            "view_churn",
+            "phase_i",
+        ],
+        emits: &[
+            "QC -> all",
+            "QC for own tips -> lead(new view)",
+            "StartView -> lead(new view)",
+        ],
+    },
+    Transition {
+        id: "end-view-invalid",
+        message: Some(MessageKind::EndView),
+        guard: "end-view signature invalid",
+        updates: &["received_messages"],
+        emits: &[],
+    },
+    Transition {
+        id: "end-view-duplicate",
+        message: Some(MessageKind::EndView),
+        guard: "sender already sent an end-view for this view",
+        updates: &["received_messages"],
+        emits: &[],
+    },
+    Transition {
+        id: "end-view-recorded",
+        message: Some(MessageKind::EndView),
+        guard: "no cached certificate to serve, and view < view_i or fewer than f+1 end-views for the view",
+        updates: &["received_messages", "end_views"],
+        emits: &[],
+    },
+    Transition {
+        id: "end-view-serves-cert",
+        message: Some(MessageKind::EndView),
+        guard: "view < view_i and a certificate for the view is cached",
+        updates: &["received_messages", "end_views"],
+        emits: &["EndViewCert -> sender"],
+    },
+    Transition {
+        id: "end-view-cert-formed",
+        message: Some(MessageKind::EndView),
+        guard: "view >= view_i and at least f+1 end-views for the view",
+        updates: &[
+            "received_messages",
+            "end_views",
+            "end_view_certs",
+            "view_i",
+            "view_entry_time",
+            "view_churn",
+            "phase_i",
+        ],
+        emits: &[
+            "EndViewCert -> all",
+            "QC for own tips -> lead(new view)",
+            "StartView -> lead(new view)",
+        ],
+    },
+    Transition {
+        id: "end-view-cert-invalid",
+        message: Some(MessageKind::EndViewCert),
+        guard: "certificate does not carry f+1 signatures",
+        updates: &["received_messages"],
+        emits: &[],
+    },
+    Transition {
+        id: "end-view-cert-duplicate",
+        message: Some(MessageKind::EndViewCert),
+        guard: "a certificate for the view is already cached",
+        updates: &["received_messages"],
+        emits: &[],
+    },
+    Transition {
+        id: "end-view-cert-stale",
+        message: Some(MessageKind::EndViewCert),
+        guard: "certified view + 1 < view_i",
+        updates: &["received_messages", "end_view_certs"],
+        emits: &[],
+    },
+    Transition {
+        id: "end-view-cert-advances-view",
+        message: Some(MessageKind::EndViewCert),
+        guard: "certified view + 1 >= view_i",
+        updates: &[
+            "received_messages",
+            "end_view_certs",
+            "view_i",
+            "view_entry_time",
+            "view_churn",
+            "phase_i",
+        ],
+        emits: &[
+            "EndViewCert -> all",
+            "QC for own tips -> lead(new view)",
+            "StartView -> lead(new view)",
+        ],
+    },
+    Transition {
+        id: "start-view-invalid",
+        message: Some(MessageKind::StartView),
+        guard: "start-view signature invalid",
+        updates: &["received_messages"],
+        emits: &[],
+    },
+    Transition {
+        id: "start-view-not-1qc",
+        message: Some(MessageKind::StartView),
+        guard: "carried QC is not a 1-QC",
+        updates: &["received_messages"],
+        emits: &[],
+    },
+    Transition {
+        id: "start-view-stale",
+        message: Some(MessageKind::StartView),
+        guard: "for a view before view_i",
+        updates: &["received_messages"],
+        emits: &[],
+    },
+    Transition {
+        id: "start-view-duplicate",
+        message: Some(MessageKind::StartView),
+        guard: "already holding one from the author for its view",
+        updates: &["received_messages"],
+        emits: &[],
+    },
+    Transition {
+        id: "start-view-too-far-ahead",
+        message: Some(MessageKind::StartView),
+        guard: "for a view more than START_VIEW_WINDOW past view_i",
+        updates: &["received_messages", "peer_progress"],
+        emits: &[],
+    },
+    Transition {
+        id: "start-view-recorded",
+        message: Some(MessageKind::StartView),
+        guard: "signature valid, carried QC is a 1-QC, first from the author for a view within START_VIEW_WINDOW of view_i",
+        updates: &["received_messages", "start_views", "peer_progress"],
+        emits: &[],
+    },
+    Transition {
+        id: "key-rotation-invalid",
+        message: Some(MessageKind::KeyRotation),
+        guard: "not signed with the author's newest key, effective too early, conflicting, or possession proof invalid",
+        updates: &["received_messages"],
+        emits: &[],
+    },
+    Transition {
+        id: "key-rotation-recorded",
+        message: Some(MessageKind::KeyRotation),
+        guard: "rotation valid",
+        updates: &["received_messages", "kb.rotations"],
+        emits: &[],
+    },
+];
+
+/// The transitions a message of kind `message` can take, including those
+/// any message can
+pub fn transitions_for(message: MessageKind) -> impl Iterator<Item = &'static Transition> {
+    TRANSITIONS
+        .iter()
+        .filter(move |t| t.message.is_none_or(|kind| kind == message))
+}
+
+pub fn transition(id: &str) -> Option<&'static Transition> {
+    TRANSITIONS.iter().find(|t| t.id == id)
+}
+
+/// Renders the table for the docs
+pub fn to_markdown() -> String {
+    let mut out =
+        String::from("| id | message | guard | updates | emits |\n|---|---|---|---|---|\n");
+    for t in TRANSITIONS {
+        out.push_str(&format!(
+            "| `{}` | {} | {} | {} | {} |\n",
+            t.id,
+            t.message
+                .map_or("any".to_string(), |kind| format!("{kind:?}")),
+            t.guard,
+            t.updates.join(", "),
+            if t.emits.is_empty() {
+                "-".to_string()
+            } else {
+                t.emits.join("; ")
+            },
+        ));
+    }
+    out
+}
@@ -0,0 +1,109 @@
+//! Carrying a process's messages over some network
+//!
+//! A `MorpheusProcess` never touches the network itself: every handler
+//! appends what it wants sent to a `Vec<(Message, Option<Identity>)>`, with
+//! `None` meaning every other member. A [`NetworkTransport`] is what takes
+//! those messages out and brings the other members' messages in, so a node
+//! drives its process with [`MorpheusProcess::poll_transport`] and
+//! [`MorpheusProcess::tick_transport`] and never looks inside the queue.
+//!
+//! [`HarnessTransport`] connects a process to a running `MockHarness`: the
+//! harness delivers to it like to any of its own processes, and carries what
+//! it sends. A process built for a real network can be tested against a
+//! simulated cluster this way before any real backend exists.
+
+use crate::test_harness::{MockHarness, TestTransaction};
+use crate::*;
+
+pub trait NetworkTransport<Tr: Transaction> {
+    /// Sends `message` to `to` alone
+    fn send(&mut self, to: &Identity, message: Message<Tr>);
+
+    /// Sends `message` to every other member
+    fn broadcast(&mut self, message: Message<Tr>);
+
+    /// The next message that has arrived, with its sender, if any
+    fn receive(&mut self) -> Option<(Identity, Message<Tr>)>;
+
+    /// Whether the senders [`Self::receive`] names can be trusted
+    fn link_security(&self) -> LinkSecurity {
+        LinkSecurity::Unauthenticated
+    }
+}
+
+/// Hands everything in `to_send` to `transport`, leaving it empty
+pub fn flush<Tr: Transaction>(
+    transport: &mut (impl NetworkTransport<Tr> + ?Sized),
+    to_send: &mut Vec<(Message<Tr>, Option<Identity>)>,
+) {
+    for (message, dest) in to_send.drain(..) {
+        match dest {
+            Some(to) => transport.send(&to, message),
+            None => transport.broadcast(message),
+        }
+    }
+}
+
+impl<Tr: Transaction> MorpheusProcess<Tr> {
+    /// Handles every message that has arrived on `transport`, sending the
+    /// responses back over it
+    ///
+    /// Returns whether any of them made progress, as `process_message` does.
+    /// Votes are checked one by one if we trust links but `transport`
+    /// doesn't authenticate them.
+    pub fn poll_transport(&mut self, transport: &mut (impl NetworkTransport<Tr> + ?Sized)) -> bool {
+        let verification = self.vote_verification;
+        if let Err(e) = verification.validate(transport.link_security()) {
+            tracing::debug!(process_id = ?self.id, %e, "Checking every vote");
+            self.vote_verification = VoteVerification::EveryVote;
+        }
+        let mut made_progress = false;
+        let mut to_send = Vec::new();
+        while let Some((sender, message)) = transport.receive() {
+            made_progress |= self.process_message(message, sender, &mut to_send);
+            flush(transport, &mut to_send);
+        }
+        self.vote_verification = verification;
+        made_progress
+    }
+
+    /// Advances the clock to `now`, then checks timeouts and produces any
+    /// blocks that are due, sending the results over `transport`
+    pub fn tick_transport(
+        &mut self,
+        now: u128,
+        transport: &mut (impl NetworkTransport<Tr> + ?Sized),
+    ) {
+        self.set_now(now);
+        let mut to_send = Vec::new();
+        self.check_timeouts(&mut to_send);
+        self.try_produce_blocks(&mut to_send);
+        flush(transport, &mut to_send);
+    }
+}
+
+/// A process outside a `MockHarness` taking part in its simulation, see
+/// [`MockHarness::attach`]
+pub struct HarnessTransport<'a> {
+    pub harness: &'a mut MockHarness,
+    pub id: Identity,
+}
+
+impl NetworkTransport<TestTransaction> for HarnessTransport<'_> {
+    fn send(&mut self, to: &Identity, message: Message<TestTransaction>) {
+        self.harness
+            .enqueue_message(message, self.id.clone(), Some(to.clone()));
+    }
+
+    fn broadcast(&mut self, message: Message<TestTransaction>) {
+        self.harness.enqueue_message(message, self.id.clone(), None);
+    }
+
+    fn receive(&mut self) -> Option<(Identity, Message<TestTransaction>)> {
+        self.harness.attached.get_mut(&self.id)?.pop_front()
+    }
+
+    fn link_security(&self) -> LinkSecurity {
+        MockHarness::LINK_SECURITY
+    }
+}
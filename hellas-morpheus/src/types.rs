@@ -1,12 +1,17 @@
 use crate::Transaction;
 use crate::crypto::*;
 use crate::format;
+use crate::merkle::MerkleRoot;
 
 use ark_serialize::Valid;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::sync::Arc;
 
+/// Domain separator for block content hashes
+const BLOCK_HASH_DOMAIN: &[u8] = b"morpheus-block-v1";
+
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
 pub enum BlockType {
     Genesis,
@@ -95,6 +100,7 @@ impl SlotNum {
     }
 }
 
+/// SHA-256 of a block's contents, see [`Block::content_hash`]
 #[derive(
     Clone,
     PartialEq,
@@ -108,7 +114,7 @@ impl SlotNum {
     CanonicalSerialize,
     CanonicalDeserialize,
 )]
-pub struct BlockHash(pub u64);
+pub struct BlockHash(pub [u8; 32]);
 
 #[derive(
     Clone,
@@ -125,7 +131,7 @@ pub struct BlockHash(pub u64);
 pub struct BlockKey {
     pub type_: BlockType,
     pub view: ViewNum,
-    pub height: usize,
+    pub height: u64,
     pub author: Option<Identity>, // TODO: refactor genesis handling to make this mandatory
     pub slot: SlotNum,
     pub hash: Option<BlockHash>,
@@ -146,6 +152,136 @@ pub const GEN_BLOCK_KEY: BlockKey = BlockKey {
     hash: None,
 };
 
+/// A block's position: correct processes produce at most one block per
+/// (type, author, slot), so two finalized blocks with the same `SlotKey`
+/// mean agreement was lost
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub struct SlotKey {
+    pub type_: BlockType,
+    pub author: Option<Identity>,
+    pub slot: SlotNum,
+}
+
+impl From<&BlockKey> for SlotKey {
+    fn from(key: &BlockKey) -> Self {
+        SlotKey {
+            type_: key.type_,
+            author: key.author.clone(),
+            slot: key.slot,
+        }
+    }
+}
+
+/// Entry of `voted_i(z, x, s, p_j)`: whether we sent a `z`-vote for the
+/// block of type `x` at slot `s` by `p_j`
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+#[serde(into = "VoteKeyRepr", from = "VoteKeyRepr")]
+pub struct VoteKey {
+    pub z: u8,
+    pub type_: BlockType,
+    pub slot: SlotNum,
+    pub author: Identity,
+}
+
+impl VoteKey {
+    /// The entry for `z`-votes on `block`, or `None` for the genesis block,
+    /// which is never voted on
+    pub fn for_block(z: u8, block: &BlockKey) -> Option<Self> {
+        Some(VoteKey {
+            z,
+            type_: block.type_,
+            slot: block.slot,
+            author: block.author.clone()?,
+        })
+    }
+}
+
+impl From<(u8, BlockType, SlotNum, Identity)> for VoteKey {
+    fn from((z, type_, slot, author): (u8, BlockType, SlotNum, Identity)) -> Self {
+        VoteKey {
+            z,
+            type_,
+            slot,
+            author,
+        }
+    }
+}
+
+impl From<VoteKey> for (u8, BlockType, SlotNum, Identity) {
+    fn from(key: VoteKey) -> Self {
+        (key.z, key.type_, key.slot, key.author)
+    }
+}
+
+/// Serialized form of [`VoteKey`]: written as `V1`, and still read from the
+/// bare tuples `voted_i` used to hold
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum VoteKeyRepr {
+    V1 {
+        v: VoteKeyVersion,
+        z: u8,
+        type_: BlockType,
+        slot: SlotNum,
+        author: Identity,
+    },
+    Tuple(u8, BlockType, SlotNum, Identity),
+}
+
+/// Only ever serialized as 1
+#[derive(Serialize, Deserialize)]
+#[serde(try_from = "u8", into = "u8")]
+struct VoteKeyVersion;
+
+impl TryFrom<u8> for VoteKeyVersion {
+    type Error = String;
+
+    fn try_from(v: u8) -> Result<Self, Self::Error> {
+        match v {
+            1 => Ok(VoteKeyVersion),
+            v => Err(format!("unknown VoteKey encoding version {}", v)),
+        }
+    }
+}
+
+impl From<VoteKeyVersion> for u8 {
+    fn from(_: VoteKeyVersion) -> Self {
+        1
+    }
+}
+
+impl From<VoteKey> for VoteKeyRepr {
+    fn from(key: VoteKey) -> Self {
+        VoteKeyRepr::V1 {
+            v: VoteKeyVersion,
+            z: key.z,
+            type_: key.type_,
+            slot: key.slot,
+            author: key.author,
+        }
+    }
+}
+
+impl From<VoteKeyRepr> for VoteKey {
+    fn from(repr: VoteKeyRepr) -> Self {
+        match repr {
+            VoteKeyRepr::V1 {
+                z,
+                type_,
+                slot,
+                author,
+                ..
+            }
+            | VoteKeyRepr::Tuple(z, type_, slot, author) => VoteKey {
+                z,
+                type_,
+                slot,
+                author,
+            },
+        }
+    }
+}
+
 #[derive(
     Clone,
     PartialEq,
@@ -163,6 +299,9 @@ pub struct VoteData {
     pub for_which: BlockKey,
 }
 
+/// Key for maps indexed by QC: a QC is identified by what it certifies
+pub type QcKey = VoteData;
+
 pub type FinishedQC = Arc<ThreshSigned<VoteData>>;
 
 impl std::fmt::Debug for VoteData {
@@ -205,6 +344,31 @@ pub struct StartView {
     /// The maximal 1-QC seen by this process
     /// This is used by the new leader to determine which blocks to build upon
     pub qc: FinishedQC,
+
+    /// How far along the sender is, so a lagging leader notices it is behind
+    pub progress: Option<ProgressReport>,
+}
+
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    CanonicalDeserialize,
+    CanonicalSerialize,
+)]
+/// A process's own view of its progress, attached to (and signed with) its StartView
+pub struct ProgressReport {
+    /// The highest view of any QC the sender has seen
+    pub max_view: ViewNum,
+
+    /// The tallest block the sender regards as final
+    pub finalized_head: BlockKey,
 }
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
@@ -212,9 +376,13 @@ pub enum BlockData<Tr> {
     Genesis,
     Tr {
         transactions: Vec<Tr>,
+        /// Merkle root of `transactions`, see [`BlockData::tr`]
+        root: MerkleRoot,
     },
     Lead {
         justification: Vec<Arc<Signed<StartView>>>,
+        /// Application data, see the `extensions` module
+        extension: Option<BlockExtension>,
     },
 }
 
@@ -226,13 +394,18 @@ impl<Tr: CanonicalSerialize> CanonicalSerialize for BlockData<Tr> {
     ) -> Result<(), ark_serialize::SerializationError> {
         match self {
             BlockData::Genesis => u8::serialize_with_mode(&0, writer, compress),
-            BlockData::Tr { transactions } => {
+            BlockData::Tr { transactions, root } => {
                 u8::serialize_with_mode(&1, &mut writer, compress)?;
-                transactions.serialize_with_mode(writer, compress)
+                transactions.serialize_with_mode(&mut writer, compress)?;
+                root.serialize_with_mode(writer, compress)
             }
-            BlockData::Lead { justification } => {
+            BlockData::Lead {
+                justification,
+                extension,
+            } => {
                 u8::serialize_with_mode(&2, &mut writer, compress)?;
-                justification.serialize_with_mode(writer, compress)
+                justification.serialize_with_mode(&mut writer, compress)?;
+                extension.serialize_with_mode(writer, compress)
             }
         }
     }
@@ -240,8 +413,13 @@ impl<Tr: CanonicalSerialize> CanonicalSerialize for BlockData<Tr> {
     fn serialized_size(&self, compress: ark_serialize::Compress) -> usize {
         match self {
             BlockData::Genesis => 1,
-            BlockData::Tr { transactions } => 1 + transactions.serialized_size(compress),
-            BlockData::Lead { justification } => 1 + justification.serialized_size(compress),
+            BlockData::Tr { transactions, root } => {
+                1 + transactions.serialized_size(compress) + root.serialized_size(compress)
+            }
+            BlockData::Lead {
+                justification,
+                extension,
+            } => 1 + justification.serialized_size(compress) + extension.serialized_size(compress),
         }
     }
 }
@@ -262,10 +440,12 @@ impl<Tr: CanonicalDeserialize> CanonicalDeserialize for BlockData<Tr> {
         match b {
             0 => Ok(BlockData::Genesis),
             1 => Ok(BlockData::Tr {
-                transactions: Vec::deserialize_with_mode(reader, compress, validate)?,
+                transactions: Vec::deserialize_with_mode(&mut reader, compress, validate)?,
+                root: MerkleRoot::deserialize_with_mode(reader, compress, validate)?,
             }),
             2 => Ok(BlockData::Lead {
-                justification: Vec::deserialize_with_mode(reader, compress, validate)?,
+                justification: Vec::deserialize_with_mode(&mut reader, compress, validate)?,
+                extension: Option::deserialize_with_mode(reader, compress, validate)?,
             }),
             _ => Err(ark_serialize::SerializationError::InvalidData),
         }
@@ -291,12 +471,113 @@ pub struct Block<Tr: Transaction> {
     pub data: BlockData<Tr>,
 }
 
+impl<Tr: Transaction> Block<Tr> {
+    /// Hash of everything in the block except `key.hash` itself
+    ///
+    /// A block's key carries this hash, and votes and QCs name blocks by
+    /// their key, so two blocks with different contents at the same position
+    /// are told apart everywhere. Transactions are covered through their
+    /// Merkle root alone, so the hash can be checked without them.
+    pub fn content_hash(&self) -> BlockHash {
+        let key = BlockKey {
+            hash: None,
+            ..self.key.clone()
+        };
+        let mut buf = Vec::new();
+        key.serialize_compressed(&mut buf).unwrap();
+        self.prev.serialize_compressed(&mut buf).unwrap();
+        self.one.serialize_compressed(&mut buf).unwrap();
+        match &self.data {
+            BlockData::Tr { root, .. } => {
+                1u8.serialize_compressed(&mut buf).unwrap();
+                root.serialize_compressed(&mut buf).unwrap();
+            }
+            data => data.serialize_compressed(&mut buf).unwrap(),
+        }
+
+        let mut hasher = Sha256::new();
+        hasher.update(BLOCK_HASH_DOMAIN);
+        hasher.update(&buf);
+        BlockHash(hasher.finalize().into())
+    }
+
+    /// This block with `key.hash` set to its content hash
+    pub fn hashed(mut self) -> Self {
+        self.key.hash = Some(self.content_hash());
+        self
+    }
+}
+
 impl<Tr: Transaction> std::fmt::Debug for Block<Tr> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", format::format_block(self, true))
     }
 }
 
+/// A validator's announcement that it signs with `new_key` from view `effective` on
+///
+/// The announcement itself is signed, in view `announced`, with the key the
+/// validator held then. Only the keys behind `Signed` messages (blocks and
+/// start-views) rotate; the shares behind threshold signatures belong to the
+/// committee's hinTS universe and change only with a new committee setup.
+#[derive(
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Debug,
+    Serialize,
+    Deserialize,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+)]
+pub struct KeyRotation {
+    pub announced: ViewNum,
+    pub effective: ViewNum,
+    pub new_key: hints::PublicKey,
+    /// `new_key`'s signature over [`KeyRotation::possession_message`], so
+    /// nobody can announce a key they don't hold
+    pub possession: hints::PartialSignature,
+}
+
+impl KeyRotation {
+    pub fn new(
+        author: &Identity,
+        announced: ViewNum,
+        effective: ViewNum,
+        new_key: &hints::SecretKey,
+        global: &hints::GlobalData,
+    ) -> Self {
+        let public = new_key.public(global);
+        let possession = hints::sign(
+            new_key,
+            &Self::possession_message(author, announced, effective, &public),
+        );
+        KeyRotation {
+            announced,
+            effective,
+            new_key: public,
+            possession,
+        }
+    }
+
+    pub fn possession_message(
+        author: &Identity,
+        announced: ViewNum,
+        effective: ViewNum,
+        new_key: &hints::PublicKey,
+    ) -> Vec<u8> {
+        let mut buf = b"morpheus-key-rotation-v1".to_vec();
+        author.serialize_compressed(&mut buf).unwrap();
+        announced.serialize_compressed(&mut buf).unwrap();
+        effective.serialize_compressed(&mut buf).unwrap();
+        new_key.serialize_compressed(&mut buf).unwrap();
+        buf
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, PartialOrd, Hash, Ord, Serialize, Deserialize)]
 pub enum Message<Tr: Transaction> {
     Block(Arc<Signed<Block<Tr>>>),
@@ -305,6 +586,56 @@ pub enum Message<Tr: Transaction> {
     EndView(Arc<ThreshPartial<ViewNum>>),
     EndViewCert(Arc<ThreshSigned<ViewNum>>),
     StartView(Arc<Signed<StartView>>),
+    KeyRotation(Arc<Signed<KeyRotation>>),
+    /// Asks the recipient for a block we hold a QC or pointer for but never
+    /// received
+    GetBlock(BlockKey),
+    /// Answers a `GetBlock`
+    BlockResponse(Arc<Signed<Block<Tr>>>),
+}
+
+/// The variant of a [`Message`], without its payload
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum MessageKind {
+    Block,
+    NewVote,
+    QC,
+    EndView,
+    EndViewCert,
+    StartView,
+    KeyRotation,
+    GetBlock,
+    BlockResponse,
+}
+
+impl MessageKind {
+    pub const ALL: [MessageKind; 9] = [
+        MessageKind::Block,
+        MessageKind::NewVote,
+        MessageKind::QC,
+        MessageKind::EndView,
+        MessageKind::EndViewCert,
+        MessageKind::StartView,
+        MessageKind::KeyRotation,
+        MessageKind::GetBlock,
+        MessageKind::BlockResponse,
+    ];
+}
+
+impl<Tr: Transaction> Message<Tr> {
+    pub fn kind(&self) -> MessageKind {
+        match self {
+            Message::Block(_) => MessageKind::Block,
+            Message::NewVote(_) => MessageKind::NewVote,
+            Message::QC(_) => MessageKind::QC,
+            Message::EndView(_) => MessageKind::EndView,
+            Message::EndViewCert(_) => MessageKind::EndViewCert,
+            Message::StartView(_) => MessageKind::StartView,
+            Message::KeyRotation(_) => MessageKind::KeyRotation,
+            Message::GetBlock(_) => MessageKind::GetBlock,
+            Message::BlockResponse(_) => MessageKind::BlockResponse,
+        }
+    }
 }
 
 impl<Tr: Transaction> std::fmt::Debug for Message<Tr> {
@@ -313,7 +644,7 @@ impl<Tr: Transaction> std::fmt::Debug for Message<Tr> {
     }
 }
 
-#[derive(Copy, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Copy, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Phase {
     High = 0,
     Low = 1,
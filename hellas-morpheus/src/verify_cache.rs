@@ -0,0 +1,184 @@
+//! Skipping signature checks already made
+//!
+//! The same block, vote or QC reaches a process several times over, and the
+//! same QC is embedded in every block that points through it, so most
+//! signature checks repeat one made before. A [`CachingVerifier`] wraps
+//! another [`Verifier`] and remembers the most recent checks that passed,
+//! keyed by a hash of everything the check depends on: the kind of check,
+//! the author and view or the threshold, the signed bytes (which include the
+//! chain id) and the signature. A repeat of a check that passed passes
+//! without verifying anything; [`VerifyCacheStats`] counts how often.
+//!
+//! Failed checks aren't remembered, so garbage can't push valid entries
+//! out. The wrapped verifier is fixed when the cache is made: a process
+//! using one doesn't see keys rotated into its `KeyBook` afterwards.
+
+use std::sync::{Arc, Mutex};
+
+use ark_serialize::CanonicalSerialize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::*;
+
+/// Domain separator, so check hashes can't collide with message hashes
+const VERIFY_CACHE_DOMAIN: &[u8] = b"morpheus-verify-cache-v1";
+
+/// Checks remembered unless configured otherwise
+pub const DEFAULT_VERIFY_CACHE_CAPACITY: usize = 8192;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifyCacheStats {
+    /// Checks answered from the cache
+    pub hits: u64,
+    /// Checks handed to the wrapped verifier
+    pub misses: u64,
+    /// Passed checks forgotten to make room
+    pub evictions: u64,
+}
+
+impl VerifyCacheStats {
+    /// Fraction of checks answered from the cache, 0 before any were made
+    pub fn hit_rate(&self) -> f64 {
+        let checks = self.hits + self.misses;
+        if checks == 0 {
+            0.0
+        } else {
+            self.hits as f64 / checks as f64
+        }
+    }
+}
+
+struct Cache {
+    passed: DedupCache,
+    hits: u64,
+    misses: u64,
+}
+
+pub struct CachingVerifier {
+    inner: Arc<dyn Verifier>,
+    cache: Mutex<Cache>,
+}
+
+impl CachingVerifier {
+    pub fn new(inner: Arc<dyn Verifier>, capacity: usize) -> Self {
+        CachingVerifier {
+            inner,
+            cache: Mutex::new(Cache {
+                passed: DedupCache::new(capacity),
+                hits: 0,
+                misses: 0,
+            }),
+        }
+    }
+
+    pub fn stats(&self) -> VerifyCacheStats {
+        let cache = self.cache.lock().unwrap();
+        VerifyCacheStats {
+            hits: cache.hits,
+            misses: cache.misses,
+            evictions: cache.passed.stats().evictions,
+        }
+    }
+
+    /// The cached answer for the check hashing to `key`, or `verify`'s
+    fn check(&self, key: MessageHash, verify: impl FnOnce() -> bool) -> bool {
+        {
+            let mut cache = self.cache.lock().unwrap();
+            if cache.passed.contains_hash(&key) {
+                // refresh it, so checks that keep repeating stay cached
+                cache.passed.admit_hash(key);
+                cache.hits += 1;
+                return true;
+            }
+            cache.misses += 1;
+        }
+        // not holding the lock while verifying, which is the slow part
+        let valid = verify();
+        if valid {
+            self.cache.lock().unwrap().passed.admit_hash(key);
+        }
+        valid
+    }
+}
+
+/// What a check depends on, besides the signed bytes and the signature
+enum CheckKind<'a> {
+    Partial(&'a Identity),
+    PartialAt(&'a Identity, ViewNum),
+    Aggregate(u32),
+}
+
+fn check_key(kind: CheckKind, message: &[u8], signature: &impl CanonicalSerialize) -> MessageHash {
+    let mut hasher = Sha256::new();
+    hasher.update(VERIFY_CACHE_DOMAIN);
+    match kind {
+        CheckKind::Partial(author) => {
+            hasher.update([0]);
+            hasher.update(author.0.to_le_bytes());
+        }
+        CheckKind::PartialAt(author, view) => {
+            hasher.update([1]);
+            hasher.update(author.0.to_le_bytes());
+            hasher.update(view.0.to_le_bytes());
+        }
+        CheckKind::Aggregate(threshold) => {
+            hasher.update([2]);
+            hasher.update(threshold.to_le_bytes());
+        }
+    }
+    hasher.update((message.len() as u64).to_le_bytes());
+    hasher.update(message);
+    let mut buf = Vec::new();
+    signature.serialize_compressed(&mut buf).unwrap();
+    hasher.update(&buf);
+    MessageHash(hasher.finalize().into())
+}
+
+impl Verifier for CachingVerifier {
+    fn committee_size(&self) -> u32 {
+        self.inner.committee_size()
+    }
+
+    fn chain_id(&self) -> ChainId {
+        self.inner.chain_id()
+    }
+
+    fn verify_partial(
+        &self,
+        author: &Identity,
+        message: &[u8],
+        signature: &hints::PartialSignature,
+    ) -> bool {
+        let key = check_key(CheckKind::Partial(author), message, signature);
+        self.check(key, || {
+            self.inner.verify_partial(author, message, signature)
+        })
+    }
+
+    fn verify_partial_at(
+        &self,
+        author: &Identity,
+        view: ViewNum,
+        message: &[u8],
+        signature: &hints::PartialSignature,
+    ) -> bool {
+        let key = check_key(CheckKind::PartialAt(author, view), message, signature);
+        self.check(key, || {
+            self.inner
+                .verify_partial_at(author, view, message, signature)
+        })
+    }
+
+    fn verify_aggregate(
+        &self,
+        message: &[u8],
+        signature: &hints::Signature,
+        threshold: u32,
+    ) -> bool {
+        let key = check_key(CheckKind::Aggregate(threshold), message, signature);
+        self.check(key, || {
+            self.inner.verify_aggregate(message, signature, threshold)
+        })
+    }
+}
@@ -1,10 +1,53 @@
-use std::{cmp::Ordering, sync::Arc};
+use std::{cmp::Ordering, collections::VecDeque, sync::Arc};
+
+use serde::{Deserialize, Serialize};
 
 use crate::*;
 
 const COMPLAIN_TIMEOUT: u128 = 6;
 const END_VIEW_TIMEOUT: u128 = 12;
 
+/// Largest exponent of the backoff applied to the view timeouts (a factor of 8)
+pub const MAX_VIEW_BACKOFF: u32 = 3;
+
+/// View changes allowed within [`END_VIEW_TIMEOUT`]Δ before we stop starting
+/// new ones on timeout
+pub const MAX_VIEW_CHANGES_PER_WINDOW: usize = 3;
+
+/// How often, and how usefully, this process has been changing views
+///
+/// Each view that ends without any of its leader blocks gaining a QC raises
+/// `backoff`, stretching both view timeouts by `2^backoff`; a view that did
+/// see one lowers it by a single step, so one good view doesn't undo a run of
+/// bad ones. A process that has changed view [`MAX_VIEW_CHANGES_PER_WINDOW`]
+/// times within the last 12Δ also stops sending end-view messages on timeout
+/// (it still follows certificates). `recent_view_changes` and `unproductive`
+/// are what an operator watches to spot mis-tuned timeouts.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ViewChurn {
+    pub backoff: u32,
+    /// Whether a leader block of the current view has gained a QC
+    pub progressed: bool,
+    /// When we entered each view within the last 12Δ, oldest first
+    pub recent: VecDeque<u128>,
+    /// View changes since startup
+    pub total: u64,
+    /// View changes that left a view without progress
+    pub unproductive: u64,
+}
+
+/// How many views past our own we keep StartView messages for
+///
+/// A leader that far behind catches up from certificates rather than from
+/// the StartViews of a view it hasn't reached. With one StartView kept per
+/// author and view, and earlier views dropped as we leave them, `start_views`
+/// never holds more than `n * (START_VIEW_WINDOW + 1)` messages, however many
+/// a Byzantine member sends.
+pub const START_VIEW_WINDOW: i64 = 16;
+
+/// How many of the latest end-view certificates a process keeps
+const END_VIEW_CERT_CACHE: usize = 4;
+
 impl<Tr: Transaction> MorpheusProcess<Tr> {
     pub fn set_now(&mut self, now: u128) {
         self.current_time = now;
@@ -15,11 +58,61 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
     }
 
     pub fn verify_leader(&self, author: Identity, view: ViewNum) -> bool {
-        author.0 as u32 == 1 + (view.0 as u32 % self.n)
+        match self.leader_election {
+            LeaderElection::RoundRobin => author.0 as u32 == 1 + (view.0 as u32 % self.n),
+            // only proofs that check out against the election public key are returned
+            LeaderElection::Vrf => self
+                .leader_proof(view)
+                .is_some_and(|proof| proof.leader(self.n) == author),
+        }
     }
 
     pub fn lead(&self, view: ViewNum) -> Identity {
-        Identity((view.0 as u32 % self.n as u32) + 1) // identities are 1-indexed... ok
+        match self.leader_election {
+            LeaderElection::RoundRobin => Identity((view.0 as u32 % self.n as u32) + 1), // identities are 1-indexed... ok
+            LeaderElection::Vrf => self
+                .leader_proof(view)
+                .expect("LeaderElection::Vrf needs a valid kb.election_key")
+                .leader(self.n),
+        }
+    }
+
+    /// Summarizes how far along this process is, for peers to compare against
+    pub fn progress_report(&self) -> ProgressReport {
+        ProgressReport {
+            max_view: self.index.max_view.0,
+            finalized_head: self.finalized_head(),
+        }
+    }
+
+    /// The tallest block whose 2-QC this process has seen observed
+    pub fn finalized_head(&self) -> BlockKey {
+        self.index
+            .finalized
+            .iter()
+            .max_by_key(|key| key.height)
+            .cloned()
+            .unwrap_or(GEN_BLOCK_KEY)
+    }
+
+    /// Whether `progress` shows its sender is further along than us
+    pub fn is_behind(&self, progress: &ProgressReport) -> bool {
+        progress.max_view > self.index.max_view.0
+            || progress.finalized_head.height > self.finalized_head().height
+    }
+
+    /// Keeps `cert`, forgetting the oldest once more than
+    /// [`END_VIEW_CERT_CACHE`] views are cached
+    pub(crate) fn cache_end_view_cert(&mut self, cert: Arc<ThreshSigned<ViewNum>>) {
+        self.end_view_certs.insert(cert.data, cert);
+        while self.end_view_certs.len() > END_VIEW_CERT_CACHE {
+            self.end_view_certs.pop_first();
+        }
+    }
+
+    /// The cached certificate ending `view`, if we still have it
+    pub fn end_view_cert(&self, view: ViewNum) -> Option<&Arc<ThreshSigned<ViewNum>>> {
+        self.end_view_certs.get(&view)
     }
 
     pub(crate) fn end_view(
@@ -39,8 +132,14 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
 
         assert!(self.view_i <= new_view);
 
+        self.emit(ProtocolEvent::ViewChanged {
+            from: self.view_i,
+            to: new_view,
+        });
+        self.record_view_change();
         self.view_i = new_view;
         self.view_entry_time = self.current_time;
+        self.start_views = self.start_views.split_off(&new_view);
         self.phase_i.insert(new_view, Phase::High);
 
         // View changed, we need to re-evaluate pending votes
@@ -58,24 +157,102 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
                 );
             }
         }
-        self.send_msg(
-            to_send,
-            (
-                Message::StartView(Arc::new(Signed::from_data(
-                    StartView {
-                        view: new_view,
-                        qc: self.index.max_1qc.clone(),
-                    },
-                    &self.kb,
-                ))),
-                Some(self.lead(new_view)),
-            ),
-        );
+        let start_view = self.sign(StartView {
+            view: new_view,
+            qc: self.index.max_1qc.clone(),
+            progress: Some(self.progress_report()),
+        });
+        if let Some(start_view) = start_view {
+            self.send_msg(
+                to_send,
+                (
+                    Message::StartView(Arc::new(start_view)),
+                    Some(self.lead(new_view)),
+                ),
+            );
+        }
 
         // Re-evaluate any pending voting decisions after view change
         self.reevaluate_pending_votes(to_send);
     }
 
+    /// Updates `view_churn` as we leave the current view
+    fn record_view_change(&mut self) {
+        let churn = &mut self.view_churn;
+        if churn.progressed {
+            churn.backoff = churn.backoff.saturating_sub(1);
+        } else {
+            churn.backoff = (churn.backoff + 1).min(MAX_VIEW_BACKOFF);
+            churn.unproductive += 1;
+        }
+        churn.progressed = false;
+        churn.total += 1;
+        churn.recent.push_back(self.current_time);
+        self.prune_view_churn();
+    }
+
+    fn prune_view_churn(&mut self) {
+        let window = self.current_delta() * END_VIEW_TIMEOUT;
+        while let Some(&at) = self.view_churn.recent.front() {
+            if at + window > self.current_time {
+                break;
+            }
+            self.view_churn.recent.pop_front();
+        }
+    }
+
+    /// View changes within the last 12Δ, with the governed Δ multiplier
+    pub fn recent_view_changes(&self) -> usize {
+        let window = self.current_delta() * END_VIEW_TIMEOUT;
+        self.view_churn
+            .recent
+            .iter()
+            .filter(|&&at| at + window > self.current_time)
+            .count()
+    }
+
+    /// The complain and end-view timeouts, in that order, after backoff and
+    /// with the governed Δ multiplier
+    pub fn view_timeouts(&self) -> (u128, u128) {
+        let factor = 1 << self.view_churn.backoff;
+        (
+            self.current_delta() * COMPLAIN_TIMEOUT * factor,
+            self.current_delta() * END_VIEW_TIMEOUT * factor,
+        )
+    }
+
+    /// Whether we have already sent our end-view message for `view`
+    pub fn sent_end_view(&self, view: ViewNum) -> bool {
+        self.end_views
+            .votes
+            .get(&view)
+            .is_some_and(|votes| votes.contains_key(&self.id))
+    }
+
+    /// Sends our end-view message for the current view without waiting for
+    /// the 12Δ timeout, to rotate away from a degraded leader
+    ///
+    /// This is the same message the timeout would send, so it is always safe:
+    /// the view only changes once f+1 processes have sent one. Returns false,
+    /// sending nothing, if we already sent an end-view for this view or
+    /// couldn't sign one.
+    pub fn force_end_view(&mut self, to_send: &mut Vec<(Message<Tr>, Option<Identity>)>) -> bool {
+        if self.sent_end_view(self.view_i) {
+            return false;
+        }
+        tracing::info!(target: "force_end_view", process_id = ?self.id, view = ?self.view_i);
+        self.send_end_view(to_send)
+    }
+
+    /// Sends our end-view message for the current view, unless signing it fails
+    fn send_end_view(&mut self, to_send: &mut Vec<(Message<Tr>, Option<Identity>)>) -> bool {
+        let Some(end_view) = self.sign_partial(self.view_i) else {
+            return false;
+        };
+        self.send_msg(to_send, (Message::EndView(Arc::new(end_view)), None));
+        true
+    }
+
     /// Implements the "Complain" section from Algorithm 1
     ///
     /// Checks timeouts and sends complaints:
@@ -84,9 +261,16 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
     /// "If ∃q ∈ Q_i which has not been finalized for time 12Δ since entering view view_i:
     ///  Send the end-view message (view_i) signed by p_i to all processes;"
     pub fn check_timeouts(&mut self, to_send: &mut Vec<(Message<Tr>, Option<Identity>)>) {
+        if self.dev_mode {
+            return;
+        }
+        self.check_aggregator_timeouts(to_send);
+        self.prune_view_churn();
+
         let time_in_view = self.current_time - self.view_entry_time;
+        let (complain_timeout, end_view_timeout) = self.view_timeouts();
 
-        if time_in_view >= self.delta * COMPLAIN_TIMEOUT {
+        if time_in_view >= complain_timeout {
             let maximal_unfinalized = self
                 .index
                 .unfinalized
@@ -114,14 +298,20 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
         }
 
         // Second timeout - 12Δ, send end-view message
-        if time_in_view >= self.delta * END_VIEW_TIMEOUT && !self.index.unfinalized.is_empty() {
-            self.send_msg(
-                to_send,
-                (
-                    Message::EndView(Arc::new(ThreshPartial::from_data(self.view_i, &self.kb))),
-                    None,
-                ),
-            );
+        if time_in_view >= end_view_timeout && !self.index.unfinalized.is_empty() {
+            if self.recent_view_changes() >= MAX_VIEW_CHANGES_PER_WINDOW {
+                tracing::warn!(
+                    target: "view_churn",
+                    process_id = ?self.id,
+                    view = ?self.view_i,
+                    recent = self.recent_view_changes(),
+                    "not starting another view change yet"
+                );
+            } else {
+                self.send_end_view(to_send);
+            }
         }
+
+        self.assert_invariants(InvariantLevel::Paranoid);
     }
 }
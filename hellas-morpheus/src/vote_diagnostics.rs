@@ -0,0 +1,203 @@
+//! Why pending votes haven't been cast
+//!
+//! A block waits in `pending_votes` until a list of predicates all hold, and
+//! a vote that never leaves is one of the harder things to debug: the only
+//! outward sign is a map that doesn't shrink. [`PendingVotesSnapshot`] lists,
+//! for each view, every block waiting for a vote of each kind together with
+//! the first predicate it currently fails, as a [`VoteBlocker`] naming the
+//! state that fails it. It serializes to JSON, so it can be dumped from a
+//! test, a trace or a node's status page as is.
+//!
+//! The predicates are checked in the order `reevaluate_pending_votes` checks
+//! them: first those about the view, then those about the block.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+/// Which list of `PendingVotes` a block waits in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum PendingVoteKind {
+    Tr1,
+    Tr2,
+    Lead1,
+    Lead2,
+}
+
+/// Same names as the fields of `PendingVotes`
+impl fmt::Display for PendingVoteKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PendingVoteKind::Tr1 => write!(f, "tr_1"),
+            PendingVoteKind::Tr2 => write!(f, "tr_2"),
+            PendingVoteKind::Lead1 => write!(f, "lead_1"),
+            PendingVoteKind::Lead2 => write!(f, "lead_2"),
+        }
+    }
+}
+
+/// The first predicate a pending vote fails
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VoteBlocker {
+    /// Only the current view's pending votes are ever cast
+    NotCurrentView { current: ViewNum },
+    /// Transaction blocks are voted for once the view has a leader block
+    NoLeaderBlock,
+    /// ... and once all of the view's leader blocks are final
+    UnfinalizedLeaderBlocks { blocks: Vec<BlockKey> },
+    /// The phase policy isn't ready to leave the high throughput phase
+    DeferredByPhasePolicy { pending_tr: usize },
+    /// Leader blocks are only voted for in the high throughput phase
+    LowThroughputPhase,
+    /// The DAG doesn't have exactly one tip
+    TipCount { tips: usize },
+    /// The single tip is pointed to by other blocks than this one
+    NotSoleSuccessorOfTip { tip: VoteData },
+    /// We hold no block by this key
+    BlockMissing,
+    /// The block's 1-QC is below the greatest one we have seen
+    OneQcBelowMax { one_qc: VoteData, max_1qc: VoteData },
+    /// The single tip isn't a 1-QC for this block
+    TipNotOneQcForBlock { tip: VoteData },
+    /// We have seen a block higher than this one
+    HigherBlockSeen { max_height: u64 },
+}
+
+impl fmt::Display for VoteBlocker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotCurrentView { current } => write!(f, "not the current view {}", current.0),
+            Self::NoLeaderBlock => write!(f, "no leader block in this view yet"),
+            Self::UnfinalizedLeaderBlocks { blocks } => {
+                write!(f, "leader blocks {:?} are not final yet", blocks)
+            }
+            Self::DeferredByPhasePolicy { pending_tr } => write!(
+                f,
+                "phase policy defers voting on {} transaction blocks",
+                pending_tr
+            ),
+            Self::LowThroughputPhase => write!(f, "in the low throughput phase"),
+            Self::TipCount { tips } => write!(f, "{} tips instead of a single one", tips),
+            Self::NotSoleSuccessorOfTip { tip } => {
+                write!(f, "not the only block pointing to the single tip {:?}", tip)
+            }
+            Self::BlockMissing => write!(f, "block not received"),
+            Self::OneQcBelowMax { one_qc, max_1qc } => write!(
+                f,
+                "block's 1-QC {:?} is below the greatest 1-QC seen {:?}",
+                one_qc, max_1qc
+            ),
+            Self::TipNotOneQcForBlock { tip } => {
+                write!(f, "single tip {:?} is not a 1-QC for this block", tip)
+            }
+            Self::HigherBlockSeen { max_height } => {
+                write!(f, "a block of height {} has been seen", max_height)
+            }
+        }
+    }
+}
+
+/// A block waiting for one of our votes
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingCandidate {
+    pub block: BlockKey,
+    pub kind: PendingVoteKind,
+    /// `None` if the vote would be cast the next time the view is reevaluated
+    pub blocker: Option<VoteBlocker>,
+}
+
+/// The pending votes of one view
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingVotesSnapshot {
+    pub view: ViewNum,
+    /// Whether the view is due to be reevaluated; a candidate without a
+    /// blocker in a view that isn't waits for something to set this
+    pub dirty: bool,
+    pub candidates: Vec<PendingCandidate>,
+}
+
+impl<Tr: Transaction> MorpheusProcess<Tr> {
+    /// Every pending vote of every view, and what holds each one up
+    pub fn pending_votes_snapshot(&self) -> Vec<PendingVotesSnapshot> {
+        self.pending_votes
+            .iter()
+            .map(|(view, pending)| {
+                let lists = [
+                    (PendingVoteKind::Tr1, &pending.tr_1),
+                    (PendingVoteKind::Tr2, &pending.tr_2),
+                    (PendingVoteKind::Lead1, &pending.lead_1),
+                    (PendingVoteKind::Lead2, &pending.lead_2),
+                ];
+                let candidates = lists
+                    .into_iter()
+                    .flat_map(|(kind, list)| {
+                        list.keys().map(move |block| PendingCandidate {
+                            block: block.clone(),
+                            kind,
+                            blocker: self.pending_vote_blocker(*view, pending, kind, block),
+                        })
+                    })
+                    .collect();
+                PendingVotesSnapshot {
+                    view: *view,
+                    dirty: pending.dirty,
+                    candidates,
+                }
+            })
+            .collect()
+    }
+
+    fn pending_vote_blocker(
+        &self,
+        view: ViewNum,
+        pending: &PendingVotes,
+        kind: PendingVoteKind,
+        block: &BlockKey,
+    ) -> Option<VoteBlocker> {
+        if view != self.view_i {
+            return Some(VoteBlocker::NotCurrentView {
+                current: self.view_i,
+            });
+        }
+        match kind {
+            PendingVoteKind::Tr1 | PendingVoteKind::Tr2 => {
+                if !self
+                    .index
+                    .contains_lead_by_view
+                    .get(&view)
+                    .copied()
+                    .unwrap_or(false)
+                {
+                    return Some(VoteBlocker::NoLeaderBlock);
+                }
+                if let Some(blocks) = self
+                    .index
+                    .unfinalized_lead_by_view
+                    .get(&view)
+                    .filter(|blocks| !blocks.is_empty())
+                {
+                    return Some(VoteBlocker::UnfinalizedLeaderBlocks {
+                        blocks: blocks.iter().cloned().collect(),
+                    });
+                }
+                let pending_tr = pending.tr_1.len() + pending.tr_2.len();
+                if !self.may_vote_tr(pending_tr) {
+                    return Some(VoteBlocker::DeferredByPhasePolicy { pending_tr });
+                }
+                if kind == PendingVoteKind::Tr1 {
+                    self.tr_1_vote_blocker(block)
+                } else {
+                    self.tr_2_vote_blocker(block)
+                }
+            }
+            PendingVoteKind::Lead1 | PendingVoteKind::Lead2 => {
+                if self.phase_i.get(&view) == Some(&Phase::Low) {
+                    return Some(VoteBlocker::LowThroughputPhase);
+                }
+                None
+            }
+        }
+    }
+}
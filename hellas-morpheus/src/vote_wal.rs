@@ -0,0 +1,416 @@
+//! Remembering our votes across restarts
+//!
+//! `voted_i` is what stops a process from voting twice at a position, and
+//! it lives in memory: a process that crashes after voting and comes back
+//! with it empty would vote again, possibly for a different block. A
+//! [`VoteWal`] is an append-only log of the [`VoteKey`]s we voted at. Each
+//! vote is appended, and synced, before it is signed, and
+//! [`MorpheusProcess::attach_vote_wal`] replays the log into `voted_i` when
+//! the process starts.
+//!
+//! A record is `len: u32 LE | check: [u8; 4] | payload`, with the payload the
+//! key's JSON and `check` the start of its SHA-256. A crash while appending
+//! leaves at most the last record cut short or garbled; it is dropped on
+//! open, since the vote it was for was never signed. A bad record anywhere
+//! else means the log itself is damaged, and opening it fails rather than
+//! forgetting votes. Like the sign guard's file, a file-backed log holds an
+//! exclusive lock on `<path>.lock` while it is open.
+//!
+//! The records follow a header, `b"MWAL" | version: u32 LE`, naming the
+//! log's format version, [`WAL_FORMAT`]. Logs written before the header are
+//! version 0, and get one when they are opened (see `migrations.rs`).
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use sha2::{Digest, Sha256};
+
+use crate::*;
+
+/// The version of the log this build writes
+pub const WAL_FORMAT: u32 = 1;
+
+const WAL_MAGIC: [u8; 4] = *b"MWAL";
+
+/// Bytes before the first record
+const WAL_HEADER: usize = 8;
+
+/// Bytes before each record's payload
+const RECORD_HEADER: usize = 8;
+
+/// Longer than any vote key's JSON; a longer length was damaged, not cut off
+const MAX_RECORD: usize = 4096;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WalError {
+    /// Another log holds the lock on this file
+    Locked(PathBuf),
+    Io(String),
+    /// A damaged record before the last, at this byte offset
+    Corrupt {
+        offset: usize,
+        reason: String,
+    },
+}
+
+impl fmt::Display for WalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WalError::Locked(path) => write!(f, "{} is locked by another log", path.display()),
+            WalError::Io(e) => write!(f, "vote log I/O failed: {}", e),
+            WalError::Corrupt { offset, reason } => {
+                write!(f, "vote log is corrupt at byte {}: {}", offset, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for WalError {}
+
+impl From<io::Error> for WalError {
+    fn from(e: io::Error) -> Self {
+        WalError::Io(e.to_string())
+    }
+}
+
+impl From<MigrationError> for WalError {
+    fn from(e: MigrationError) -> Self {
+        match e {
+            MigrationError::Io(e) => WalError::Io(e),
+            e => WalError::Corrupt {
+                offset: 0,
+                reason: e.to_string(),
+            },
+        }
+    }
+}
+
+/// Where a [`VoteWal`] keeps its records
+pub trait WalStore: Send {
+    /// Everything appended so far, empty if nothing has been
+    fn load(&mut self) -> io::Result<Vec<u8>>;
+
+    /// Adds `bytes` at the end; once this returns `Ok`, they survive a crash.
+    /// On an error, part of them may have been written.
+    fn append(&mut self, bytes: &[u8]) -> io::Result<()>;
+
+    /// Cuts the log back to its first `len` bytes
+    fn truncate(&mut self, len: usize) -> io::Result<()>;
+
+    /// Swaps the whole log for `bytes` at once, so a crash leaves either
+    fn replace(&mut self, bytes: &[u8]) -> io::Result<()>;
+}
+
+/// Keeps the log in a file, holding an exclusive lock on `<path>.lock`
+pub struct FileWal {
+    path: PathBuf,
+    file: File,
+    /// Held (and locked) for as long as the log is open
+    _lock: File,
+}
+
+impl FileWal {
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, WalError> {
+        let path = path.into();
+        let lock_path = crate::sign_guard::with_suffix(&path, ".lock");
+        let lock = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)?;
+        if lock.try_lock().is_err() {
+            return Err(WalError::Locked(lock_path));
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .read(true)
+            .open(&path)?;
+        Ok(FileWal {
+            path,
+            file,
+            _lock: lock,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl WalStore for FileWal {
+    fn load(&mut self) -> io::Result<Vec<u8>> {
+        fs::read(&self.path)
+    }
+
+    fn append(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.file.write_all(bytes)?;
+        self.file.sync_data()
+    }
+
+    fn truncate(&mut self, len: usize) -> io::Result<()> {
+        self.file.set_len(len as u64)?;
+        self.file.sync_all()
+    }
+
+    /// Writes the log next to its file and renames it into place
+    fn replace(&mut self, bytes: &[u8]) -> io::Result<()> {
+        let tmp = crate::sign_guard::with_suffix(&self.path, ".tmp");
+        let mut file = File::create(&tmp)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+        fs::rename(&tmp, &self.path)?;
+        self.file = OpenOptions::new()
+            .append(true)
+            .read(true)
+            .open(&self.path)?;
+        Ok(())
+    }
+}
+
+/// Keeps the log in memory, shared by its clones, so a test can "restart"
+/// by opening a new log on a clone
+#[derive(Clone, Default)]
+pub struct MemoryWal(pub Arc<Mutex<Vec<u8>>>);
+
+impl WalStore for MemoryWal {
+    fn load(&mut self) -> io::Result<Vec<u8>> {
+        Ok(self.0.lock().unwrap().clone())
+    }
+
+    fn append(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.0.lock().unwrap().extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn truncate(&mut self, len: usize) -> io::Result<()> {
+        self.0.lock().unwrap().truncate(len);
+        Ok(())
+    }
+
+    fn replace(&mut self, bytes: &[u8]) -> io::Result<()> {
+        *self.0.lock().unwrap() = bytes.to_vec();
+        Ok(())
+    }
+}
+
+pub struct VoteWal {
+    store: Box<dyn WalStore>,
+    /// The file behind `store`, if it is a [`FileWal`]
+    path: Option<PathBuf>,
+    votes: BTreeSet<VoteKey>,
+    /// Bytes of the header and whole records in `store`
+    len: usize,
+    /// An append failed and what it wrote couldn't be cut off again
+    failed: bool,
+}
+
+impl VoteWal {
+    /// Opens the log at `path`, creating it if it doesn't exist yet
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, WalError> {
+        let store = FileWal::open(path)?;
+        let path = store.path().to_path_buf();
+        let mut wal = Self::with_store(store)?;
+        wal.path = Some(path);
+        Ok(wal)
+    }
+
+    /// Opens the log in `store`, migrating it first if an older build wrote
+    /// it, and dropping a last record a crash cut short
+    pub fn with_store(mut store: impl WalStore + 'static) -> Result<Self, WalError> {
+        let (bytes, plan) =
+            MigrationRegistry::builtin().migrate(StoreKind::VoteWal, store.load()?)?;
+        let (votes, valid) = decode(&bytes)?;
+        if valid < bytes.len() {
+            tracing::warn!(
+                target: "vote_wal",
+                dropped = bytes.len() - valid,
+                "Dropping an unfinished record",
+            );
+        }
+        let len = if bytes.is_empty() { WAL_HEADER } else { valid };
+        if bytes.is_empty() {
+            store.append(&header(WAL_FORMAT))?;
+        } else if !plan.is_empty() {
+            store.replace(&bytes[..valid])?;
+        } else if valid < bytes.len() {
+            store.truncate(valid)?;
+        }
+        Ok(VoteWal {
+            store: Box::new(store),
+            path: None,
+            votes,
+            len,
+            failed: false,
+        })
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Every vote the log holds
+    pub fn votes(&self) -> &BTreeSet<VoteKey> {
+        &self.votes
+    }
+
+    /// Records that we vote at `key`, durably once this returns `Ok`
+    ///
+    /// A failed append is cut off again, since the next record would follow
+    /// it and make the log unreadable. If that fails too, the log refuses
+    /// every later append until it is reopened, which drops the unfinished
+    /// record as it would after a crash.
+    pub fn append(&mut self, key: &VoteKey) -> Result<(), WalError> {
+        if self.votes.contains(key) {
+            return Ok(());
+        }
+        if self.failed {
+            return Err(WalError::Io(
+                "an earlier append could not be undone, reopen the log".to_string(),
+            ));
+        }
+        let record = encode(key);
+        if let Err(e) = self.store.append(&record) {
+            if let Err(undo) = self.store.truncate(self.len) {
+                tracing::error!(
+                    target: "vote_wal",
+                    error = %undo,
+                    "Could not cut off a failed append",
+                );
+                self.failed = true;
+            }
+            return Err(e.into());
+        }
+        self.len += record.len();
+        self.votes.insert(key.clone());
+        Ok(())
+    }
+}
+
+fn header(version: u32) -> [u8; WAL_HEADER] {
+    let mut header = [0; WAL_HEADER];
+    header[..4].copy_from_slice(&WAL_MAGIC);
+    header[4..].copy_from_slice(&version.to_le_bytes());
+    header
+}
+
+fn check(payload: &[u8]) -> [u8; 4] {
+    Sha256::digest(payload)[..4].try_into().unwrap()
+}
+
+fn encode(key: &VoteKey) -> Vec<u8> {
+    let payload = serde_json::to_vec(key).expect("vote keys serialize");
+    let mut record = Vec::with_capacity(RECORD_HEADER + payload.len());
+    record.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    record.extend_from_slice(&check(&payload));
+    record.extend_from_slice(&payload);
+    record
+}
+
+/// The votes in a log of the current version, and how many bytes of it
+/// hold the header and whole records
+fn decode(bytes: &[u8]) -> Result<(BTreeSet<VoteKey>, usize), WalError> {
+    let mut votes = BTreeSet::new();
+    if bytes.is_empty() {
+        return Ok((votes, 0));
+    }
+    if bytes.get(..WAL_HEADER) != Some(&header(WAL_FORMAT)[..]) {
+        return Err(WalError::Corrupt {
+            offset: 0,
+            reason: "bad header".to_string(),
+        });
+    }
+    let mut offset = WAL_HEADER;
+    while offset < bytes.len() {
+        let rest = &bytes[offset..];
+        if rest.len() < RECORD_HEADER {
+            break;
+        }
+        let len = u32::from_le_bytes(rest[..4].try_into().unwrap()) as usize;
+        if len > MAX_RECORD {
+            return Err(WalError::Corrupt {
+                offset,
+                reason: format!("record of {} bytes", len),
+            });
+        }
+        let Some(payload) = rest.get(RECORD_HEADER..RECORD_HEADER + len) else {
+            break;
+        };
+        let end = offset + RECORD_HEADER + len;
+        let record = if rest[4..RECORD_HEADER] == check(payload) {
+            serde_json::from_slice::<VoteKey>(payload).map_err(|e| e.to_string())
+        } else {
+            Err("checksum mismatch".to_string())
+        };
+        match record {
+            Ok(key) => {
+                votes.insert(key);
+            }
+            // the last record may have been cut off mid-write
+            Err(_) if end == bytes.len() => break,
+            Err(reason) => return Err(WalError::Corrupt { offset, reason }),
+        }
+        offset = end;
+    }
+    Ok((votes, offset))
+}
+
+/// The versions of the log, for the [`MigrationRegistry`]
+pub(crate) fn schema() -> StoreSchema {
+    StoreSchema {
+        current: WAL_FORMAT,
+        detect,
+        migrations: vec![Migration {
+            from: 0,
+            description: "add a header naming the format version",
+            apply: add_header,
+        }],
+    }
+}
+
+fn detect(bytes: &[u8]) -> Result<u32, String> {
+    match bytes.strip_prefix(&WAL_MAGIC) {
+        Some(rest) => rest
+            .get(..4)
+            .map(|version| u32::from_le_bytes(version.try_into().unwrap()))
+            .ok_or_else(|| "header cut short".to_string()),
+        // read as a record length, the magic is far above `MAX_RECORD`, so a
+        // log without it starts with a record
+        None => Ok(0),
+    }
+}
+
+/// Version 0 is the records alone
+fn add_header(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let mut log = header(1).to_vec();
+    log.extend_from_slice(bytes);
+    Ok(log)
+}
+
+impl<Tr: Transaction> MorpheusProcess<Tr> {
+    /// Records every vote in `wal` before signing it, after replaying the
+    /// votes it already holds into `voted_i`
+    pub fn attach_vote_wal(&mut self, wal: VoteWal) {
+        self.voted_i.extend(wal.votes().iter().cloned());
+        self.vote_wal = Some(Arc::new(Mutex::new(wal)));
+    }
+
+    /// Logs that we vote at `key`, or why we can't; a vote that isn't
+    /// logged must not be signed
+    pub(crate) fn log_vote(&self, key: &VoteKey) -> bool {
+        let Some(wal) = &self.vote_wal else {
+            return true;
+        };
+        match wal.lock().unwrap().append(key) {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::error!(target: "vote_wal", process_id = ?self.id, error = %e);
+                false
+            }
+        }
+    }
+}
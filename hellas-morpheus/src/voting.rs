@@ -1,4 +1,4 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::sync::Arc;
 
 use crate::*;
@@ -27,6 +27,24 @@ pub struct QuorumTrack<
     pub votes: BTreeMap<T, BTreeMap<Identity, Arc<ThreshPartial<T>>>>,
 }
 
+/// Where 1- and 2-votes are sent
+///
+/// 0-votes always go to the block's author, who broadcasts the 0-QC.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VoteAggregation {
+    /// Every process sends its votes to every process, and each forms QCs itself:
+    /// O(n²) messages per block
+    #[default]
+    Broadcast,
+    /// Votes go to the leader of the block's view, which broadcasts the QC once it
+    /// has a quorum: O(n) messages per block. If no QC shows up within
+    /// `AGGREGATOR_TIMEOUT` Δ, voters fall back to broadcasting their vote.
+    Leader,
+}
+
+/// How many Δ a voter waits for the aggregator's QC before broadcasting its vote
+pub const AGGREGATOR_TIMEOUT: u128 = 2;
+
 /// Error when attempting to record a duplicate vote from the same process
 #[derive(Debug, Serialize, Deserialize)]
 
@@ -63,9 +81,79 @@ impl<
         votes_now.insert(vote.author.clone(), vote);
         Ok(votes_now.len())
     }
+
+    /// The processes whose votes for `data` we hold, as an aggregate's bitmap
+    pub fn signers(&self, data: &T) -> SignerBitmap {
+        SignerBitmap::from_signers(
+            self.votes
+                .get(data)
+                .into_iter()
+                .flat_map(|votes| votes.keys()),
+        )
+    }
+}
+
+/// Where a block stands at one z-level
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LevelProgress {
+    /// Distinct votes we have seen, which stays 0 for levels aggregated elsewhere
+    pub votes: usize,
+    /// Whether we hold a QC at this level
+    pub qc: bool,
+    /// Whether we cast our own vote at this level
+    pub voted: bool,
+}
+
+/// Quorum progress of a block, for showing "7/10 votes collected"
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VoteProgress {
+    /// Votes needed for a QC (n - f)
+    pub quorum: usize,
+    /// Indexed by z
+    pub levels: [LevelProgress; 3],
 }
 
 impl<Tr: Transaction> MorpheusProcess<Tr> {
+    /// How many votes of each level we have seen for `block`, and whether we voted
+    pub fn vote_progress(&self, block: &BlockKey) -> VoteProgress {
+        let mut levels = [LevelProgress::default(); 3];
+        for (z, level) in levels.iter_mut().enumerate() {
+            let data = VoteData {
+                z: z as u8,
+                for_which: block.clone(),
+            };
+            level.votes = self.vote_tracker.votes.get(&data).map_or(0, |v| v.len());
+            level.voted =
+                VoteKey::for_block(z as u8, block).is_some_and(|key| self.voted_i.contains(&key));
+        }
+        for qc in &self.qcs {
+            if &qc.data.for_which == block {
+                levels[qc.data.z as usize].qc = true;
+            }
+        }
+
+        VoteProgress {
+            quorum: (self.n - self.f) as usize,
+            levels,
+        }
+    }
+
+    /// [`vote_progress`](Self::vote_progress) of every known block without a 2-QC yet
+    pub fn in_flight_vote_progress(&self) -> BTreeMap<BlockKey, VoteProgress> {
+        let certified: BTreeSet<&BlockKey> = self
+            .qcs
+            .iter()
+            .filter(|qc| qc.data.z == 2)
+            .map(|qc| &qc.data.for_which)
+            .collect();
+        self.index
+            .blocks
+            .keys()
+            .filter(|key| key.type_ != BlockType::Genesis && !certified.contains(key))
+            .map(|key| (key.clone(), self.vote_progress(key)))
+            .collect()
+    }
+
     pub fn try_vote(
         &mut self,
         z: u8,
@@ -75,29 +163,77 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
         to_send: &mut Vec<(Message<Tr>, Option<Identity>)>,
     ) -> bool {
         tracing::debug!(target: "try_vote", z = z, block = ?block, target = ?target);
-        let author = block.author.clone().expect("not voting for genesis block");
-
-        if !self
-            .voted_i
-            .contains(&(z, block.type_, block.slot, author.clone()))
-        {
-            self.voted_i
-                .insert((z, block.type_, block.slot, author.clone()));
-
-            let voted = Arc::new(ThreshPartial::from_data(
-                VoteData {
-                    z,
-                    for_which: block.clone(),
-                },
-                &self.kb,
-            ));
-            self.send_msg(to_send, (Message::NewVote(voted.clone()), target));
+        let key = VoteKey::for_block(z, block).expect("not voting for genesis block");
+
+        if self.voted_i.insert(key.clone()) {
+            // voted_i stays set if signing fails: the signer may have signed anyway,
+            // and a second attempt must not sign anything different
+            if !self.log_vote(&key) {
+                return false;
+            }
+            let Some(voted) = self.sign_partial(VoteData {
+                z,
+                for_which: block.clone(),
+            }) else {
+                return false;
+            };
+            let voted = Arc::new(voted);
+            match (target, self.aggregator_for(z, block)) {
+                (None, Some(aggregator)) if aggregator == self.id => {
+                    // we aggregate this one ourselves, nobody else needs our vote
+                    self.process_message(Message::NewVote(voted), self.id.clone(), to_send);
+                }
+                (None, Some(aggregator)) => {
+                    self.awaiting_aggregation
+                        .insert(voted.data.clone(), (self.current_time, voted.clone()));
+                    self.send_msg(to_send, (Message::NewVote(voted), Some(aggregator)));
+                }
+                (target, _) => self.send_msg(to_send, (Message::NewVote(voted), target)),
+            }
             true
         } else {
             false
         }
     }
 
+    /// The process that collects `z`-votes for `block`, if votes aren't broadcast
+    pub fn aggregator_for(&self, z: u8, block: &BlockKey) -> Option<Identity> {
+        match self.vote_aggregation {
+            VoteAggregation::Leader if z > 0 => Some(self.lead(block.view)),
+            _ => None,
+        }
+    }
+
+    /// Broadcasts our votes whose aggregator has been silent for too long
+    pub(crate) fn check_aggregator_timeouts(
+        &mut self,
+        to_send: &mut Vec<(Message<Tr>, Option<Identity>)>,
+    ) {
+        let deadline = self.current_delta() * AGGREGATOR_TIMEOUT;
+        let expired: Vec<_> = self
+            .awaiting_aggregation
+            .iter()
+            .filter(|(_, (sent_at, _))| self.current_time - sent_at >= deadline)
+            .map(|(data, _)| data.clone())
+            .collect();
+
+        for data in expired {
+            let (_, vote) = self.awaiting_aggregation.remove(&data).unwrap();
+            let aggregator = self.aggregator_for(data.z, &data.for_which);
+            tracing::warn!(
+                target: "aggregator_silent",
+                aggregator = ?aggregator,
+                vote_data = ?data,
+            );
+            // the aggregator already has it, everyone else gets it directly
+            for peer in (1..=self.n).map(Identity) {
+                if peer != self.id && Some(&peer) != aggregator.as_ref() {
+                    to_send.push((Message::NewVote(vote.clone()), Some(peer)));
+                }
+            }
+        }
+    }
+
     /// Returns false if the vote is a duplicate (sender already voted there)
     pub fn record_vote(
         &mut self,
@@ -118,8 +254,7 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
                         .map(|v| (v.author.0 as usize - 1, v.signature.clone()))
                         .collect::<Vec<_>>();
                     let agg = self.kb.hints_setup.aggregator();
-                    let mut data = Vec::new();
-                    vote_data.data.serialize_compressed(&mut data).unwrap();
+                    let data = signing_bytes(&self.kb.chain_id, &vote_data.data);
                     let signed = hints::sign_aggregate(
                         &agg,
                         hints::F::from((self.n - self.f) as u64),
@@ -130,7 +265,15 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
                     let quorum_formed = Arc::new(ThreshSigned {
                         data: vote_data.data.clone(),
                         signature: signed,
+                        signers: self.vote_tracker.signers(&vote_data.data),
                     });
+                    // some of the votes may not have been checked, see `link_auth.rs`
+                    if self.vote_verification == VoteVerification::AuthenticatedLinks
+                        && !quorum_formed.valid_signature(self.verifier(), self.n - self.f)
+                    {
+                        self.evict_invalid_votes(&vote_data.data);
+                        return true;
+                    }
 
                     // 0-QCs for our own blocks need to be broadcast
                     if vote_data.data.z == 0
@@ -145,6 +288,13 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
                         );
                         self.send_msg(to_send, (Message::QC(quorum_formed.clone()), None));
                     }
+                    // as aggregator, we're the only one who can form this QC
+                    if num_votes == (self.n - self.f) as usize
+                        && self.aggregator_for(vote_data.data.z, &vote_data.data.for_which)
+                            == Some(self.id.clone())
+                    {
+                        self.send_msg(to_send, (Message::QC(quorum_formed.clone()), None));
+                    }
                     self.record_qc(quorum_formed);
                 }
                 true
@@ -186,8 +336,12 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
             .get(&current_view)
             .map_or(true, |set| set.is_empty());
 
-        // Only process transaction block votes if we have leader blocks and no unfinalized leader blocks
-        if contains_lead && unfinalized_lead_empty {
+        // Only process transaction block votes if we have leader blocks and no unfinalized leader blocks,
+        // and the phase policy is ready to leave the high throughput phase
+        let pending_tr = pending.tr_1.len() + pending.tr_2.len();
+        let tr_allowed = contains_lead && unfinalized_lead_empty;
+        let deferred = tr_allowed && pending_tr > 0 && !self.may_vote_tr(pending_tr);
+        if tr_allowed && !deferred {
             // Process transaction block votes (1-votes and 2-votes)
             self.process_block_votes(
                 1,
@@ -225,7 +379,8 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
             );
         }
 
-        pending.dirty = false;
+        // the policy may change its mind as load changes, so look again next round
+        pending.dirty = deferred;
         self.pending_votes = all_pending;
     }
 
@@ -246,25 +401,37 @@ impl<Tr: Transaction> MorpheusProcess<Tr> {
 
         for block_key in pending_votes.keys().cloned() {
             if eligibility_check(self, &block_key) {
-                if self.try_vote(vote_level, &block_key, None, to_send) {
-                    if block_key.type_ == BlockType::Tr && phase_transition_reason.is_some() {
-                        // If we voted for a transaction block, transition to low throughput phase
-                        crate::tracing_setup::protocol_transition(
-                            &self.id,
-                            "throughput phase",
-                            &Phase::High,
-                            &Phase::Low,
-                            phase_transition_reason,
-                        );
-                        self.set_phase(Phase::Low);
-                    }
-                    processed_keys.push(block_key);
-                } else {
+                let key = VoteKey::for_block(vote_level, &block_key).expect("not genesis");
+                if self.voted_i.contains(&key) {
                     panic!(
                         "Already {}-voted {:?}, pending votes desync bug",
                         vote_level, block_key
                     );
                 }
+                if self.try_vote(vote_level, &block_key, None, to_send)
+                    && block_key.type_ == BlockType::Tr
+                    && phase_transition_reason.is_some()
+                {
+                    // If we voted for a transaction block, transition to low throughput phase
+                    crate::tracing_setup::protocol_transition(
+                        &self.id,
+                        "throughput phase",
+                        &Phase::High,
+                        &Phase::Low,
+                        phase_transition_reason,
+                    );
+                    if self.phase_i.get(&self.view_i) != Some(&Phase::Low) {
+                        self.emit(ProtocolEvent::PhaseChanged {
+                            view: self.view_i,
+                            from: Phase::High,
+                            to: Phase::Low,
+                        });
+                    }
+                    self.set_phase(Phase::Low);
+                }
+                // a vote we failed to sign is settled too: voted_i stays set,
+                // so it can never be cast
+                processed_keys.push(block_key);
             }
         }
 
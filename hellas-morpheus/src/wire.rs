@@ -0,0 +1,195 @@
+//! The binary encoding of [`Message`] between nodes
+//!
+//! Serde gives every message a JSON form, but what it produces follows the
+//! Rust types, so renaming a field or reordering an enum silently changes
+//! it. The wire encoding is pinned down instead:
+//!
+//! ```text
+//! version: u8 | kind: u8 | payload
+//! ```
+//!
+//! `version` is [`WIRE_VERSION`], `kind` is the message kind's
+//! [`MessageKind::wire_tag`], fixed once assigned, and `payload` is the
+//! message's contents in their `CanonicalSerialize` compressed encoding:
+//! little-endian fixed-width integers (`usize` as 8 bytes), length-prefixed
+//! sequences and compressed curve points. Decoding checks that every point
+//! is valid and that nothing follows the payload.
+//!
+//! Any change to what a message of some kind encodes to must bump
+//! [`WIRE_VERSION`]. A node receiving a version it doesn't speak gets
+//! [`WireError::UnsupportedVersion`] and can drop the peer cleanly rather
+//! than misread its messages.
+//!
+//! Block messages may also be sent compressed, to peers that accept it; see
+//! the `compression` module for how that is marked.
+
+use std::fmt;
+use std::sync::Arc;
+
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, SerializationError, Validate,
+};
+
+use crate::*;
+
+/// The version of the encoding [`Message::to_wire`] produces
+///
+/// 2 added the extension slot of leader blocks.
+pub const WIRE_VERSION: u8 = 2;
+
+/// Why bytes couldn't be decoded as a message
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WireError {
+    /// Not even a version byte
+    Empty,
+    UnsupportedVersion {
+        found: u8,
+        supported: u8,
+    },
+    /// A kind tag no version of the encoding assigns
+    UnknownKind(u8),
+    /// The payload didn't decode as the tagged kind
+    Malformed(String),
+    /// Bytes left over after the payload
+    TrailingBytes(usize),
+    /// A compressed message, to a build without the `compression` feature
+    CompressionUnsupported,
+}
+
+impl fmt::Display for WireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WireError::Empty => write!(f, "empty message"),
+            WireError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "wire version {} is not supported, expected {}",
+                found, supported
+            ),
+            WireError::UnknownKind(tag) => write!(f, "unknown message kind {}", tag),
+            WireError::Malformed(e) => write!(f, "malformed message: {}", e),
+            WireError::TrailingBytes(n) => write!(f, "{} bytes after the message", n),
+            WireError::CompressionUnsupported => {
+                write!(f, "compressed message, but compression isn't built in")
+            }
+        }
+    }
+}
+
+impl std::error::Error for WireError {}
+
+impl From<SerializationError> for WireError {
+    fn from(e: SerializationError) -> Self {
+        WireError::Malformed(e.to_string())
+    }
+}
+
+impl MessageKind {
+    /// The tag for this kind on the wire
+    pub fn wire_tag(self) -> u8 {
+        match self {
+            MessageKind::Block => 0,
+            MessageKind::NewVote => 1,
+            MessageKind::QC => 2,
+            MessageKind::EndView => 3,
+            MessageKind::EndViewCert => 4,
+            MessageKind::StartView => 5,
+            MessageKind::KeyRotation => 6,
+            MessageKind::GetBlock => 7,
+            MessageKind::BlockResponse => 8,
+        }
+    }
+
+    pub fn from_wire_tag(tag: u8) -> Option<Self> {
+        MessageKind::ALL
+            .into_iter()
+            .find(|kind| kind.wire_tag() == tag)
+    }
+}
+
+/// Reads one `T` from the front of `reader`, validating it
+fn read_canonical<T: CanonicalDeserialize>(reader: &mut &[u8]) -> Result<T, WireError> {
+    Ok(T::deserialize_with_mode(
+        reader,
+        Compress::Yes,
+        Validate::Yes,
+    )?)
+}
+
+impl<Tr: Transaction> Message<Tr> {
+    pub fn to_wire(&self) -> Vec<u8> {
+        self.to_wire_with(None)
+    }
+
+    /// The wire encoding, with a block's payload compressed if `compression`
+    /// is given and it comes out smaller
+    pub fn to_wire_with(&self, compression: Option<WireCompression>) -> Vec<u8> {
+        let tag = self.kind().wire_tag();
+        let payload = self.wire_payload();
+        if let Some(settings) = compression.filter(|_| compressible(self.kind())) {
+            if let Some(compressed) = compression::compress(&payload, settings.level) {
+                let mut buf = vec![WIRE_VERSION, tag | COMPRESSED];
+                buf.extend_from_slice(&compressed);
+                return buf;
+            }
+        }
+        let mut buf = vec![WIRE_VERSION, tag];
+        buf.extend_from_slice(&payload);
+        buf
+    }
+
+    fn wire_payload(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        match self {
+            Message::Block(block) => block.serialize_compressed(&mut buf),
+            Message::NewVote(vote) => vote.serialize_compressed(&mut buf),
+            Message::QC(qc) => qc.serialize_compressed(&mut buf),
+            Message::EndView(end_view) => end_view.serialize_compressed(&mut buf),
+            Message::EndViewCert(cert) => cert.serialize_compressed(&mut buf),
+            Message::StartView(start_view) => start_view.serialize_compressed(&mut buf),
+            Message::KeyRotation(rotation) => rotation.serialize_compressed(&mut buf),
+            Message::GetBlock(key) => key.serialize_compressed(&mut buf),
+            Message::BlockResponse(block) => block.serialize_compressed(&mut buf),
+        }
+        .expect("writing to a Vec doesn't fail");
+        buf
+    }
+
+    pub fn from_wire(bytes: &[u8]) -> Result<Self, WireError> {
+        let (&version, rest) = bytes.split_first().ok_or(WireError::Empty)?;
+        if version != WIRE_VERSION {
+            return Err(WireError::UnsupportedVersion {
+                found: version,
+                supported: WIRE_VERSION,
+            });
+        }
+        let (&tag, mut payload) = rest
+            .split_first()
+            .ok_or_else(|| WireError::Malformed("no message kind".to_string()))?;
+        let kind =
+            MessageKind::from_wire_tag(tag & !COMPRESSED).ok_or(WireError::UnknownKind(tag))?;
+        let decompressed;
+        if tag & COMPRESSED != 0 {
+            if !compressible(kind) {
+                return Err(WireError::UnknownKind(tag));
+            }
+            decompressed = compression::decompress(payload)?;
+            payload = &decompressed;
+        }
+        let reader = &mut payload;
+        let message = match kind {
+            MessageKind::Block => Message::Block(Arc::new(read_canonical(reader)?)),
+            MessageKind::NewVote => Message::NewVote(Arc::new(read_canonical(reader)?)),
+            MessageKind::QC => Message::QC(Arc::new(read_canonical(reader)?)),
+            MessageKind::EndView => Message::EndView(Arc::new(read_canonical(reader)?)),
+            MessageKind::EndViewCert => Message::EndViewCert(Arc::new(read_canonical(reader)?)),
+            MessageKind::StartView => Message::StartView(Arc::new(read_canonical(reader)?)),
+            MessageKind::KeyRotation => Message::KeyRotation(Arc::new(read_canonical(reader)?)),
+            MessageKind::GetBlock => Message::GetBlock(read_canonical(reader)?),
+            MessageKind::BlockResponse => Message::BlockResponse(Arc::new(read_canonical(reader)?)),
+        };
+        if !payload.is_empty() {
+            return Err(WireError::TrailingBytes(payload.len()));
+        }
+        Ok(message)
+    }
+}
@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use hellas_morpheus::test_harness::{MockHarness, TestTransaction, TxGenPolicy};
+use hellas_morpheus::*;
+
+fn synced_history() -> History<TestTransaction> {
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    harness.run(2 * 3 * 5);
+    let process = &harness.processes[&Identity(2)];
+    History {
+        blocks: process.index.blocks.values().cloned().collect(),
+        qcs: process.qcs.iter().cloned().collect(),
+        rotations: Vec::new(),
+    }
+}
+
+/// A process that has seen nothing, on the same committee as `synced_history`
+fn fresh() -> MorpheusProcess<TestTransaction> {
+    let mut harness = MockHarness::create_test_setup(4);
+    harness.processes.remove(&Identity(1)).unwrap()
+}
+
+#[test_log::test]
+fn test_history_verifies_in_batches() {
+    let history = synced_history();
+    let mut process = fresh();
+    let progress = process.subscribe(EventFilter::default().kinds([EventKind::BackfillProgress]));
+
+    let config = BackfillConfig {
+        batch_size: 3,
+        threads: 2,
+    };
+    let total = process.verify_history(&history, config).unwrap();
+    assert!(total > 6);
+
+    let events = progress.drain();
+    assert_eq!(events.len(), total.div_ceil(6));
+    let mut last = 0;
+    for event in &events {
+        let ProtocolEvent::BackfillProgress {
+            verified,
+            total: reported,
+            ..
+        } = event
+        else {
+            panic!("unexpected event {:?}", event);
+        };
+        assert!(*verified > last);
+        assert_eq!(*reported, total);
+        last = *verified;
+    }
+    assert_eq!(last, total);
+}
+
+#[test_log::test]
+fn test_history_with_tampered_qc_is_rejected() {
+    let mut history = synced_history();
+    let position = history
+        .qcs
+        .iter()
+        .position(|qc| qc.data.for_which.type_ != BlockType::Genesis)
+        .unwrap();
+    let mut tampered = (*history.qcs[position]).clone();
+    tampered.data.z = (tampered.data.z + 1) % 3;
+    let data = tampered.data.clone();
+    history.qcs[position] = Arc::new(tampered);
+
+    let mut process = fresh();
+    assert_eq!(
+        process.verify_history(&history, BackfillConfig::default()),
+        Err(BackfillError::InvalidQc(data))
+    );
+}
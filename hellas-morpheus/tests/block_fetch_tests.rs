@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use hellas_morpheus::test_harness::{MockHarness, TestTransaction, TxGenPolicy};
+use hellas_morpheus::*;
+
+/// A cluster of four that ran without process 4, and process 4 itself
+fn cluster_and_straggler() -> (MockHarness, MorpheusProcess<TestTransaction>) {
+    let mut harness = MockHarness::create_test_setup(4);
+    let straggler = harness.processes.remove(&Identity(4)).unwrap();
+    for i in 1..=3 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    harness.run(2 * 3 * 5);
+    (harness, straggler)
+}
+
+fn get_blocks(to_send: &[(Message<TestTransaction>, Option<Identity>)]) -> Vec<BlockKey> {
+    to_send
+        .iter()
+        .filter_map(|(message, dest)| match message {
+            Message::GetBlock(key) if dest.is_none() => Some(key.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test_log::test]
+fn test_missing_ancestors_are_fetched_before_accepting() {
+    let (mut harness, mut straggler) = cluster_and_straggler();
+    let block = harness.processes[&Identity(1)]
+        .index
+        .blocks
+        .values()
+        .filter(|block| block.data.key.type_ == BlockType::Tr)
+        .max_by_key(|block| block.data.key.height)
+        .cloned()
+        .unwrap();
+    assert!(block.data.key.height > 1);
+
+    let mut to_send = Vec::new();
+    let missing = straggler.missing_ancestors(&block);
+    assert!(!missing.is_empty());
+    assert!(!straggler.process_message(Message::Block(block.clone()), Identity(1), &mut to_send));
+    assert!(!straggler.index.blocks.contains_key(&block.data.key));
+    assert_eq!(get_blocks(&to_send), missing);
+
+    // answer every request from process 1 until nothing is missing
+    let mut rounds = 0;
+    let mut requests = get_blocks(&to_send);
+    while !requests.is_empty() {
+        rounds += 1;
+        assert!(rounds <= block.data.key.height, "fetching didn't converge");
+        let mut responses = Vec::new();
+        let peer = harness.processes.get_mut(&Identity(1)).unwrap();
+        for key in requests {
+            peer.process_message(Message::GetBlock(key), Identity(4), &mut responses);
+        }
+        let mut to_send = Vec::new();
+        for (response, dest) in responses {
+            assert_eq!(dest, Some(Identity(4)));
+            assert!(matches!(response, Message::BlockResponse(_)));
+            straggler.process_message(response, Identity(1), &mut to_send);
+        }
+        requests = get_blocks(&to_send);
+    }
+
+    assert!(straggler.index.blocks.contains_key(&block.data.key));
+    for key in missing {
+        assert!(straggler.index.blocks.contains_key(&key));
+    }
+    assert!(straggler.awaiting_ancestors.is_empty());
+}
+
+#[test_log::test]
+fn test_unrequested_block_responses_are_ignored() {
+    let (harness, mut straggler) = cluster_and_straggler();
+    let peer = &harness.processes[&Identity(1)];
+    let block = peer
+        .index
+        .blocks
+        .values()
+        .find(|block| block.data.key.height == 1)
+        .cloned()
+        .unwrap();
+
+    let mut to_send = Vec::new();
+    assert!(!straggler.process_message(
+        Message::BlockResponse(Arc::clone(&block)),
+        Identity(1),
+        &mut to_send
+    ));
+    assert!(!straggler.index.blocks.contains_key(&block.data.key));
+
+    // nor does anyone answer for a block it doesn't hold
+    assert!(!straggler.process_message(
+        Message::GetBlock(block.data.key.clone()),
+        Identity(1),
+        &mut to_send
+    ));
+    assert!(to_send.is_empty());
+}
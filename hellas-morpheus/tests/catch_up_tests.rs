@@ -0,0 +1,86 @@
+use hellas_morpheus::test_harness::{MockHarness, TestTransaction, TxGenPolicy};
+use hellas_morpheus::*;
+
+/// A cluster of four that ran without process 4, and process 4 itself
+fn cluster_and_straggler() -> (MockHarness, MorpheusProcess<TestTransaction>) {
+    let mut harness = MockHarness::create_test_setup(4);
+    let straggler = harness.processes.remove(&Identity(4)).unwrap();
+    for i in 1..=3 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    harness.run(2 * 3 * 5);
+    (harness, straggler)
+}
+
+#[test_log::test]
+fn test_straggler_catches_up_on_finalized_blocks() {
+    let (harness, mut straggler) = cluster_and_straggler();
+    let peer = &harness.processes[&Identity(1)];
+    assert!(peer.finalized_head().height > 0);
+    assert_eq!(straggler.sync_request(), None);
+
+    straggler
+        .peer_progress
+        .insert(Identity(1), peer.progress_report());
+    let (from, request) = straggler.sync_request().unwrap();
+    assert_eq!(from, Identity(1));
+    assert_eq!(
+        request,
+        SyncRequest {
+            from: 1,
+            to: peer.finalized_head().height,
+        }
+    );
+
+    let response = peer.serve_sync(&request);
+    assert!(!response.blocks.is_empty());
+    assert!(
+        response
+            .blocks
+            .windows(2)
+            .all(|w| w[0].data.key.height <= w[1].data.key.height)
+    );
+    let keys = response
+        .blocks
+        .iter()
+        .map(|block| block.data.key.clone())
+        .collect::<Vec<_>>();
+
+    let mut to_send = Vec::new();
+    let accepted = straggler.ingest_sync(&Identity(1), response.clone(), &mut to_send);
+    assert!(accepted >= keys.len());
+    for key in &keys {
+        assert!(straggler.index.blocks.contains_key(key));
+    }
+
+    // a repeated response adds nothing
+    assert_eq!(
+        straggler.ingest_sync(&Identity(1), response, &mut to_send),
+        0
+    );
+}
+
+#[test_log::test]
+fn test_sync_responses_are_bounded() {
+    let (harness, _) = cluster_and_straggler();
+    let peer = &harness.processes[&Identity(1)];
+    let head = peer.finalized_head().height;
+
+    let response = peer.serve_sync(&SyncRequest {
+        from: head + 1,
+        to: u64::MAX,
+    });
+    assert!(response.blocks.is_empty());
+    assert!(response.qcs.is_empty());
+
+    let response = peer.serve_sync(&SyncRequest { from: 1, to: 1 });
+    assert!(
+        response
+            .blocks
+            .iter()
+            .all(|block| block.data.key.height == 1)
+    );
+    assert!(response.qcs.iter().all(|qc| qc.data.for_which.height == 1));
+}
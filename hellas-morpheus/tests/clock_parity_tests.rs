@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+use hellas_morpheus::clock_parity::{ParityReport, check_parity, decisions};
+use hellas_morpheus::test_harness::{MockHarness, ScenarioEvent, TxGenPolicy};
+use hellas_morpheus::*;
+
+/// Steady load, then the view 0 leader crashes and timeouts take over
+fn leader_crash() -> MockHarness {
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    let leader = harness.processes[&Identity(1)].lead(ViewNum(0));
+    harness.schedule_event(20, ScenarioEvent::Crash(leader));
+    harness
+}
+
+#[test_log::test(tokio::test(start_paused = true))]
+async fn test_paused_clock_decides_as_simulated() {
+    let report = check_parity(leader_crash, 120, Duration::from_millis(1)).await;
+    assert!(report.simulated.values().any(|keys| !keys.is_empty()));
+    assert_eq!(report.first_mismatch(), None);
+    assert!(report.is_consistent());
+}
+
+#[test_log::test]
+fn test_first_mismatch_finds_divergence() {
+    let mut harness = leader_crash();
+    harness.run(40);
+    let simulated = decisions(&harness);
+    let (id, keys) = simulated
+        .iter()
+        .find(|(_, keys)| keys.len() > 1)
+        .map(|(id, keys)| (id.clone(), keys.clone()))
+        .unwrap();
+
+    // stopping short differs where it stops
+    let mut realtime = simulated.clone();
+    realtime.get_mut(&id).unwrap().pop();
+    let report = ParityReport {
+        simulated: simulated.clone(),
+        realtime,
+    };
+    assert_eq!(report.first_mismatch(), Some((id.clone(), keys.len() - 1)));
+
+    // as does deciding something else
+    let mut realtime = simulated.clone();
+    realtime.get_mut(&id).unwrap().swap(0, 1);
+    let report = ParityReport {
+        simulated,
+        realtime,
+    };
+    assert_eq!(report.first_mismatch(), Some((id, 0)));
+}
@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use hellas_morpheus::config::{NetworkConfig, SimulationConfig, SimulationConfigError};
+use hellas_morpheus::presets::UnknownPreset;
+use hellas_morpheus::test_harness::TxGenPolicy;
+use hellas_morpheus::topology::UnknownTopology;
+use hellas_morpheus::{Identity, QuorumConfigError};
+
+#[test_log::test]
+fn test_default_config_builds() {
+    let config = SimulationConfig::default();
+    let harness = config.build().unwrap();
+    assert_eq!(harness.processes.len(), 4);
+    assert_eq!(harness.time_step, 100);
+    assert!(
+        harness
+            .tx_gen_policy
+            .values()
+            .all(|policy| matches!(policy, TxGenPolicy::Never))
+    );
+    assert!(harness.topology.is_none());
+}
+
+#[test_log::test]
+fn test_config_round_trips_through_json() {
+    let config = SimulationConfig {
+        seed: 7,
+        num_processes: 7,
+        f: Some(2),
+        delta: 50,
+        default_tx_gen_policy: TxGenPolicy::EveryNSteps { n: 3 },
+        tx_gen_policy: BTreeMap::from([(Identity(2), TxGenPolicy::Always)]),
+        network: NetworkConfig {
+            topology: Some("3-region".to_string()),
+            crashed: [Identity(7)].into(),
+            ..NetworkConfig::default()
+        },
+        preset: None,
+        ..SimulationConfig::default()
+    };
+    let json = serde_json::to_string(&config).unwrap();
+    let parsed: SimulationConfig = serde_json::from_str(&json).unwrap();
+
+    let harness = parsed.build().unwrap();
+    assert_eq!(harness.time_step, 50);
+    assert!(harness.processes.values().all(|p| p.delta == 50));
+    assert!(matches!(
+        harness.tx_gen_policy[&Identity(2)],
+        TxGenPolicy::Always
+    ));
+    assert!(matches!(
+        harness.tx_gen_policy[&Identity(1)],
+        TxGenPolicy::EveryNSteps { n: 3 }
+    ));
+    assert_eq!(harness.topology.as_ref().unwrap().name, "3-region");
+    assert!(harness.faults.crashed.contains(&Identity(7)));
+}
+
+#[test_log::test]
+fn test_missing_fields_take_defaults() {
+    let config: SimulationConfig =
+        serde_json::from_str(r#"{"seed": 3, "num_processes": 5}"#).unwrap();
+    assert_eq!(config.effective_f(), 1);
+    assert_eq!(config.delta, 100);
+    assert_eq!(config.build().unwrap().processes.len(), 5);
+}
+
+#[test_log::test]
+fn test_same_seed_same_keys() {
+    let keys = |seed| {
+        let harness = SimulationConfig {
+            seed,
+            ..SimulationConfig::default()
+        }
+        .build()
+        .unwrap();
+        harness.processes[&Identity(1)].kb.me_pub_key.clone()
+    };
+    assert_eq!(keys(1), keys(1));
+    assert_ne!(keys(1), keys(2));
+}
+
+#[test_log::test]
+fn test_once_per_view_is_tracked_per_process() {
+    let harness = SimulationConfig {
+        default_tx_gen_policy: TxGenPolicy::OncePerView {
+            prev_view: Default::default(),
+        },
+        ..SimulationConfig::default()
+    }
+    .build()
+    .unwrap();
+    let views = harness
+        .tx_gen_policy
+        .values()
+        .map(|policy| match policy {
+            TxGenPolicy::OncePerView { prev_view } => prev_view.clone(),
+            _ => panic!("expected OncePerView"),
+        })
+        .collect::<Vec<_>>();
+    // one shared tracker would let only the first process produce each view
+    for (i, a) in views.iter().enumerate() {
+        for b in &views[i + 1..] {
+            assert!(!Arc::ptr_eq(a, b));
+        }
+    }
+}
+
+#[test_log::test]
+fn test_invalid_configs_are_refused() {
+    let build = |change: fn(&mut SimulationConfig)| {
+        let mut config = SimulationConfig::default();
+        change(&mut config);
+        config.build().err()
+    };
+    assert!(matches!(
+        build(|c| c.f = Some(2)),
+        Some(SimulationConfigError::Quorum(
+            QuorumConfigError::QuorumsMayNotIntersect { .. }
+        ))
+    ));
+    assert_eq!(
+        build(|c| c.delta = 0),
+        Some(SimulationConfigError::ZeroDelta)
+    );
+    assert_eq!(
+        build(|c| c.network.topology = Some("moon-base".to_string())),
+        Some(SimulationConfigError::UnknownTopology(UnknownTopology(
+            "moon-base".to_string()
+        )))
+    );
+    assert_eq!(
+        build(|c| c.preset = Some("no-such-preset".to_string())),
+        Some(SimulationConfigError::UnknownPreset(UnknownPreset(
+            "no-such-preset".to_string()
+        )))
+    );
+    assert_eq!(
+        build(|c| {
+            c.tx_gen_policy.insert(Identity(9), TxGenPolicy::Always);
+        }),
+        Some(SimulationConfigError::UnknownProcess(Identity(9)))
+    );
+}
@@ -0,0 +1,76 @@
+use hellas_morpheus::test_harness::{MockHarness, TestTransaction, TxGenPolicy};
+use hellas_morpheus::{
+    BlockKey, BlockType, ConsistencyIssue, GEN_BLOCK_KEY, Identity, MorpheusProcess, SlotNum,
+    ViewNum,
+};
+
+fn restored_process() -> MorpheusProcess<TestTransaction> {
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    harness.run(30);
+    harness.processes[&Identity(2)].clone()
+}
+
+#[test_log::test]
+fn test_startup_check_passes_healthy_state() {
+    let mut process = restored_process();
+    let report = process.startup_check().unwrap();
+    assert!(report.repaired.is_empty());
+    assert!(report.is_ok());
+}
+
+#[test_log::test]
+fn test_startup_check_restores_orphaned_qcs() {
+    let mut process = restored_process();
+    let tip = process
+        .index
+        .tips
+        .iter()
+        .find(|tip| tip.data.for_which != GEN_BLOCK_KEY)
+        .unwrap()
+        .clone();
+    process.qcs.remove(&tip);
+
+    let report = process.startup_check().unwrap();
+    assert_eq!(
+        report.repaired,
+        vec![ConsistencyIssue::OrphanedQc {
+            qc: tip.data.clone()
+        }]
+    );
+    assert!(process.qcs.contains(&tip));
+    assert!(process.startup_check().unwrap().repaired.is_empty());
+}
+
+#[test_log::test]
+fn test_startup_check_refuses_unjustified_finalization() {
+    let mut process = restored_process();
+    let bogus = BlockKey {
+        type_: BlockType::Tr,
+        view: ViewNum(0),
+        height: 1,
+        author: Some(Identity(3)),
+        slot: SlotNum(99),
+        hash: None,
+    };
+    process.index.finalized.insert(bogus.clone());
+
+    let report = process.startup_check().unwrap_err();
+    assert!(
+        report
+            .fatal
+            .contains(&ConsistencyIssue::UnjustifiedFinalization { key: bogus.clone() })
+    );
+    assert!(
+        report
+            .fatal
+            .contains(&ConsistencyIssue::MissingFinalizedBlock { key: bogus })
+    );
+    for issue in &report.fatal {
+        assert!(!issue.to_string().is_empty());
+    }
+}
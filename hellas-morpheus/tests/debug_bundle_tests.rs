@@ -0,0 +1,81 @@
+use std::collections::BTreeMap;
+use std::io::Read;
+
+use hellas_morpheus::test_harness::{MockHarness, TxGenPolicy};
+use hellas_morpheus::*;
+
+fn unpack(bundle: &[u8]) -> BTreeMap<String, Vec<u8>> {
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(bundle));
+    archive
+        .entries()
+        .unwrap()
+        .map(|entry| {
+            let mut entry = entry.unwrap();
+            let name = entry.path().unwrap().to_string_lossy().into_owned();
+            let mut contents = Vec::new();
+            entry.read_to_end(&mut contents).unwrap();
+            (name, contents)
+        })
+        .collect()
+}
+
+#[test_log::test]
+fn test_debug_bundle_holds_every_section() {
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    harness.run(2 * 3 * 5);
+    let process = &harness.processes[&Identity(1)];
+
+    let files = unpack(&process.debug_bundle());
+    assert_eq!(files.keys().cloned().collect::<Vec<_>>(), {
+        let mut names = DEBUG_SECTIONS
+            .map(|section| format!("{section}.json"))
+            .to_vec();
+        names.sort();
+        names
+    });
+
+    let digest: StateDigest = serde_json::from_slice(&files["digest.json"]).unwrap();
+    assert_eq!(digest, process.state_digest());
+    assert_eq!(digest.blocks, process.index.blocks.len());
+
+    let config: ProcessConfig = serde_json::from_slice(&files["config.json"]).unwrap();
+    assert_eq!((config.n, config.f), (4, 1));
+
+    let events: Vec<ProtocolEvent> = serde_json::from_slice(&files["events.json"]).unwrap();
+    assert!(!events.is_empty());
+    assert!(events.len() <= EVENT_HISTORY);
+    assert_eq!(events, process.recent_events());
+
+    let report = process.debug_report();
+    assert!(report.views.len() <= DEBUG_VIEWS);
+    assert!(report.views.contains_key(&process.view_i));
+    let blocks = report
+        .views
+        .values()
+        .flat_map(|summary| summary.blocks.values())
+        .sum::<usize>();
+    assert!(blocks > 0);
+}
+
+#[test_log::test]
+fn test_state_digest_tells_apart_differing_dags() {
+    let mut harness = MockHarness::create_test_setup(4);
+    let before = harness.processes[&Identity(1)].state_digest();
+    assert_eq!(
+        before.blocks_hash,
+        harness.processes[&Identity(2)].state_digest().blocks_hash
+    );
+
+    harness
+        .tx_gen_policy
+        .insert(Identity(1), TxGenPolicy::EveryNSteps { n: 1 });
+    harness.run(3);
+    let after = harness.processes[&Identity(1)].state_digest();
+    assert!(after.blocks > before.blocks);
+    assert_ne!(after.blocks_hash, before.blocks_hash);
+}
@@ -1,7 +1,7 @@
 use hellas_morpheus::{
-    Block, BlockData, BlockHash, BlockKey, BlockType, Identity, Message, Phase, Signed, SlotNum,
-    StartView, ThreshPartial, ThreshSigned, Transaction, ViewNum, VoteData,
-    test_harness::TestTransaction,
+    Block, BlockData, BlockHash, BlockKey, BlockType, GEN_BLOCK_KEY, Identity, Message, Phase,
+    Signed, SignerBitmap, SlotNum, StartView, ThreshPartial, ThreshSigned, Transaction, ViewNum,
+    VoteData, format::ParseError, test_harness::TestTransaction,
 };
 use std::sync::Arc;
 
@@ -11,7 +11,7 @@ fn test_format_functions() {
     let identity = Identity(42);
     let view_num = ViewNum(5);
     let slot_num = SlotNum(3);
-    let block_hash = BlockHash(0xCAFEBABE);
+    let block_hash = BlockHash([0xca; 32]);
 
     // Create a vote data
     let block_key = BlockKey {
@@ -37,6 +37,7 @@ fn test_format_functions() {
     let thresh_signed_vote = Arc::new(ThreshSigned {
         data: vote_data.clone(),
         signature: hints::Signature::default(),
+        signers: SignerBitmap::default(),
     });
 
     // Create a block
@@ -44,9 +45,7 @@ fn test_format_functions() {
         key: block_key.clone(),
         prev: vec![thresh_signed_vote.clone()],
         one: thresh_signed_vote.clone(),
-        data: BlockData::Tr {
-            transactions: vec![TestTransaction(vec![1, 2, 3, 4])],
-        },
+        data: BlockData::tr(vec![TestTransaction(vec![1, 2, 3, 4])]),
     };
 
     let signed_block = Arc::new(Signed {
@@ -68,11 +67,13 @@ fn test_format_functions() {
         Message::EndViewCert(Arc::new(ThreshSigned {
             data: view_num,
             signature: hints::Signature::default(),
+            signers: SignerBitmap::default(),
         })),
         Message::StartView(Arc::new(Signed {
             data: StartView {
                 view: view_num,
                 qc: thresh_signed_vote.clone(),
+                progress: None,
             },
             author: identity.clone(),
             signature: hints::PartialSignature::default(),
@@ -157,3 +158,82 @@ fn test_format_functions() {
     }
     hellas_morpheus::message_log!(&messages[0], true); // Verbose
 }
+
+#[test_log::test]
+fn test_text_round_trips() {
+    let mut key = BlockKey {
+        type_: BlockType::Tr,
+        view: ViewNum(2),
+        height: 4,
+        author: Some(Identity(3)),
+        slot: SlotNum(7),
+        hash: None,
+    };
+    assert_eq!(key.to_string(), "Tr:3:v2:s7:h4");
+    assert_eq!("Tr:3:v2:s7:h4".parse::<BlockKey>(), Ok(key.clone()));
+
+    key.type_ = BlockType::Lead;
+    key.hash = Some(BlockHash([0xca; 32]));
+    let text = key.to_string();
+    assert_eq!(text, format!("Lead:3:v2:s7:h4:{}", "ca".repeat(32)));
+    assert_eq!(text.parse::<BlockKey>(), Ok(key.clone()));
+
+    assert_eq!(GEN_BLOCK_KEY.to_string(), "Gen");
+    assert_eq!("Gen".parse::<BlockKey>(), Ok(GEN_BLOCK_KEY));
+
+    let vote = VoteData {
+        z: 1,
+        for_which: key.clone(),
+    };
+    assert_eq!(vote.to_string().parse::<VoteData>(), Ok(vote));
+    let genesis_vote = VoteData {
+        z: 0,
+        for_which: GEN_BLOCK_KEY,
+    };
+    assert_eq!(genesis_vote.to_string(), "0-Gen");
+    assert_eq!("0-Gen".parse::<VoteData>(), Ok(genesis_vote));
+
+    for view in [ViewNum(-1), ViewNum(0), ViewNum(12)] {
+        assert_eq!(view.to_string().parse::<ViewNum>(), Ok(view));
+    }
+}
+
+#[test_log::test]
+fn test_text_parse_errors() {
+    assert!(matches!(
+        "Tr:3:v2:s7".parse::<BlockKey>(),
+        Err(ParseError::Malformed { .. })
+    ));
+    assert!(matches!(
+        "Blk:3:v2:s7:h4".parse::<BlockKey>(),
+        Err(ParseError::Malformed { .. })
+    ));
+    assert!(matches!(
+        "Tr:3:2:s7:h4".parse::<BlockKey>(),
+        Err(ParseError::Malformed { .. })
+    ));
+    assert_eq!(
+        "Tr:x:v2:s7:h4".parse::<BlockKey>(),
+        Err(ParseError::BadNumber {
+            input: "x".to_string()
+        })
+    );
+    assert_eq!(
+        "Tr:3:v2:s7:h4:cafe".parse::<BlockKey>(),
+        Err(ParseError::BadHash {
+            input: "cafe".to_string()
+        })
+    );
+    assert!(matches!(
+        "Tr:3:v2:s7:h4".parse::<VoteData>(),
+        Err(ParseError::Malformed { .. })
+    ));
+    assert!(matches!(
+        "256-Gen".parse::<VoteData>(),
+        Err(ParseError::BadNumber { .. })
+    ));
+    assert!("2".parse::<ViewNum>().is_err());
+
+    let error = "v".parse::<ViewNum>().unwrap_err();
+    assert_eq!(error.to_string(), "\"\" is not a valid number");
+}
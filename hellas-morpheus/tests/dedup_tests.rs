@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use hellas_morpheus::test_harness::{MockHarness, TestTransaction, TxGenPolicy};
+use hellas_morpheus::testkit::{assert_agreement, assert_no_invariant_violations};
+use hellas_morpheus::{
+    BlockType, DedupCache, DedupStats, Identity, Message, MessageKind, ThreshPartial, ViewNum,
+};
+
+fn end_view(harness: &MockHarness, view: i64) -> Message<TestTransaction> {
+    Message::EndView(Arc::new(ThreshPartial::from_data(
+        ViewNum(view),
+        &harness.processes[&Identity(1)].kb,
+    )))
+}
+
+fn busy_harness() -> MockHarness {
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    harness
+}
+
+#[test_log::test]
+fn test_content_hash() {
+    let harness = MockHarness::create_test_setup(4);
+    let a = end_view(&harness, 1);
+    assert_eq!(a.content_hash(), a.clone().content_hash());
+    assert_ne!(a.content_hash(), end_view(&harness, 2).content_hash());
+    let qc = Message::<TestTransaction>::QC(harness.processes[&Identity(1)].genesis_qc.clone());
+    assert_ne!(a.content_hash(), qc.content_hash());
+}
+
+#[test_log::test]
+fn test_dedup_evicts_least_recently_seen() {
+    let harness = MockHarness::create_test_setup(4);
+    let mut cache = DedupCache::new(2);
+    assert!(cache.admit(&end_view(&harness, 1)));
+    assert!(cache.admit(&end_view(&harness, 2)));
+    assert!(!cache.admit(&end_view(&harness, 1)));
+
+    // 2 was seen least recently, so it makes room for 3
+    assert!(cache.admit(&end_view(&harness, 3)));
+    assert_eq!(cache.len(), 2);
+    assert!(cache.contains(&end_view(&harness, 1)));
+    assert!(!cache.contains(&end_view(&harness, 2)));
+    assert!(cache.admit(&end_view(&harness, 2)));
+
+    assert_eq!(
+        cache.stats(),
+        DedupStats {
+            hits: 1,
+            misses: 4,
+            evictions: 2,
+        }
+    );
+    assert_eq!(cache.stats().hit_rate(), 0.2);
+    assert_eq!(DedupStats::default().hit_rate(), 0.0);
+}
+
+#[test_log::test]
+fn test_requests_are_always_admitted() {
+    let harness = MockHarness::create_test_setup(4);
+    let mut cache = DedupCache::new(16);
+    let request = Message::<TestTransaction>::GetBlock(
+        harness.processes[&Identity(1)].genesis.data.key.clone(),
+    );
+    assert!(cache.admit(&request));
+    assert!(cache.admit(&request));
+    assert!(cache.is_empty());
+}
+
+#[test_log::test]
+fn test_harness_drops_repeats_before_delivery() {
+    let mut harness = busy_harness();
+    harness.run(20);
+    let block = harness.processes[&Identity(2)]
+        .index
+        .blocks
+        .values()
+        .find(|block| block.data.key.type_ == BlockType::Tr && block.author == Identity(1))
+        .expect("p2 has a block by p1")
+        .clone();
+    let message = Message::Block(block);
+    let delivered_to_2 = |harness: &MockHarness| {
+        harness
+            .hops_for(message.correlation_id())
+            .into_iter()
+            .filter(|hop| hop.kind == MessageKind::Block && hop.to == Identity(2))
+            .count()
+    };
+
+    // p2 has already handled the block, so copies of it never reach it
+    let before = harness.dedup_stats()[&Identity(2)];
+    harness.record_hops();
+    for _ in 0..2 {
+        harness
+            .pending_messages
+            .push_back((message.clone(), Identity(1), Some(Identity(2))));
+    }
+    harness.process_round();
+    assert_eq!(delivered_to_2(&harness), 0);
+    assert_eq!(harness.dedup_stats()[&Identity(2)].hits, before.hits + 2);
+
+    // without the cache, each copy is handed on, and rejected by p2 itself
+    harness.set_dedup(None);
+    for _ in 0..2 {
+        harness
+            .pending_messages
+            .push_back((message.clone(), Identity(1), Some(Identity(2))));
+    }
+    harness.process_round();
+    assert_eq!(delivered_to_2(&harness), 2);
+    assert!(harness.dedup_stats().is_empty());
+}
+
+#[test_log::test]
+fn test_dedup_keeps_agreement() {
+    let mut harness = busy_harness();
+    harness.set_dedup(Some(64));
+    harness.run(100);
+    for stats in harness.dedup_stats().values() {
+        assert!(stats.misses > 0);
+        assert!(stats.evictions > 0);
+    }
+    assert_agreement(&harness);
+    assert_no_invariant_violations(&harness);
+}
@@ -0,0 +1,41 @@
+use hellas_morpheus::config::{SimulationConfig, SimulationConfigError};
+use hellas_morpheus::test_harness::{MockHarness, TxGenPolicy};
+use hellas_morpheus::testkit::assert_no_invariant_violations;
+use hellas_morpheus::*;
+
+#[test_log::test]
+fn test_dev_process_finalizes_alone() {
+    let mut harness = MockHarness::new(vec![MorpheusProcess::dev(0)], 100);
+    harness
+        .tx_gen_policy
+        .insert(Identity(1), TxGenPolicy::EveryNSteps { n: 1 });
+    harness.run(10);
+    assert_no_invariant_violations(&harness);
+
+    let process = &harness.processes[&Identity(1)];
+    assert!(process.dev_mode);
+    // every block of ours is final as soon as we've voted for it
+    assert!(process.finalized_head().height >= 5);
+    let finalized = process.finalized_blocks();
+    for qc in &process.qcs {
+        assert!(finalized.contains(&qc.data.for_which));
+    }
+    // and nothing ever times out
+    assert_eq!(process.view_i, ViewNum(0));
+}
+
+#[test_log::test]
+fn test_dev_config() {
+    let mut harness = SimulationConfig::dev().build().unwrap();
+    harness.run(10);
+    assert!(harness.processes[&Identity(1)].finalized_head().height > 0);
+
+    let config = SimulationConfig {
+        num_processes: 4,
+        ..SimulationConfig::dev()
+    };
+    assert_eq!(
+        config.build().err(),
+        Some(SimulationConfigError::DevNeedsOneProcess(4))
+    );
+}
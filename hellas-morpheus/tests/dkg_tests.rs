@@ -0,0 +1,102 @@
+use ark_std::rand::{SeedableRng, rngs::StdRng};
+use ark_std::test_rng;
+use hellas_morpheus::test_harness::{MockHarness, TxGenPolicy};
+use hellas_morpheus::testkit::assert_agreement;
+use hellas_morpheus::*;
+use hints::GlobalData;
+
+fn setups(n: usize, gd: &GlobalData) -> Vec<KeySetup> {
+    (1..=n as u32)
+        .map(|i| KeySetup::new(Identity(i), n, gd, &mut StdRng::seed_from_u64(i as u64)).unwrap())
+        .collect()
+}
+
+#[test_log::test]
+fn test_key_setup_round_runs_consensus() {
+    let n = 4;
+    let gd = GlobalData::new((1 + n).next_power_of_two(), &mut test_rng()).unwrap();
+    let mut setups = setups(n, &gd);
+
+    // everyone broadcasts, and hears the others in reverse order
+    let announcements = setups
+        .iter()
+        .map(|s| s.announcement().clone())
+        .collect::<Vec<_>>();
+    for setup in &mut setups {
+        for announcement in announcements.iter().rev() {
+            setup
+                .receive(&announcement.from.clone(), announcement.clone())
+                .unwrap();
+        }
+        assert!(setup.is_complete());
+    }
+
+    let kbs = setups
+        .into_iter()
+        .map(|s| s.finish(&gd, DEFAULT_CHAIN_NAME).unwrap())
+        .collect::<Vec<_>>();
+    for kb in &kbs[1..] {
+        assert_eq!(kb.keys, kbs[0].keys);
+        assert_eq!(kb.hints_setup, kbs[0].hints_setup);
+        assert_eq!(kb.chain_id, kbs[0].chain_id);
+    }
+    assert!(kbs.iter().all(|kb| kb.election_key.is_none()));
+
+    let processes = kbs
+        .into_iter()
+        .map(|kb| {
+            let id = kb.me_identity.clone();
+            MorpheusProcess::new(kb, id, n as u32, 1)
+        })
+        .collect();
+    let mut harness = MockHarness::new(processes, 100);
+    harness
+        .tx_gen_policy
+        .insert(Identity(1), TxGenPolicy::EveryNSteps { n: 3 });
+    harness.run(2 * 3 * 5);
+
+    // QCs aggregated under the jointly built universe check out everywhere
+    assert_agreement(&harness);
+    for process in harness.processes.values() {
+        assert!(process.finalized_blocks().len() > 1);
+    }
+}
+
+#[test_log::test]
+fn test_key_setup_refuses_bad_announcements() {
+    let n = 4;
+    let gd = GlobalData::new((1 + n).next_power_of_two(), &mut test_rng()).unwrap();
+    let mut setups = setups(n, &gd);
+    let from_two = setups[1].announcement().clone();
+
+    assert_eq!(
+        setups[0].receive(&Identity(3), from_two.clone()),
+        Err(KeySetupError::WrongSender {
+            claimed: Identity(2),
+            sender: Identity(3),
+        })
+    );
+
+    setups[0].receive(&Identity(2), from_two.clone()).unwrap();
+    // hearing the same announcement twice is fine
+    setups[0].receive(&Identity(2), from_two.clone()).unwrap();
+
+    // a second key from the same member is not
+    let other = KeySetup::new(Identity(2), n, &gd, &mut StdRng::seed_from_u64(99)).unwrap();
+    assert_eq!(
+        setups[0].receive(&Identity(2), other.announcement().clone()),
+        Err(KeySetupError::Conflicting(Identity(2)))
+    );
+
+    assert_eq!(
+        KeySetup::new(Identity(5), n, &gd, &mut test_rng()).err(),
+        Some(KeySetupError::UnknownMember(Identity(5)))
+    );
+
+    assert_eq!(setups[0].missing(), vec![Identity(3), Identity(4)]);
+    let setup = setups.remove(0);
+    assert_eq!(
+        setup.finish(&gd, DEFAULT_CHAIN_NAME).err(),
+        Some(KeySetupError::Missing(vec![Identity(3), Identity(4)]))
+    );
+}
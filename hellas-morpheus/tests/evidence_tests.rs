@@ -0,0 +1,143 @@
+use std::sync::Arc;
+
+use hellas_morpheus::test_harness::{MockHarness, TestTransaction, TxGenPolicy};
+use hellas_morpheus::{
+    Block, BlockData, BlockKey, BlockType, EquivocationEvidence, EvidenceError, Identity, Message,
+    Signed, ThreshPartial, VoteData,
+};
+
+fn harness() -> MockHarness {
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    harness.run(20);
+    for process in harness.processes.values() {
+        assert_eq!(process.equivocation_evidence(), Vec::new());
+    }
+    harness
+}
+
+/// A transaction block by p1 as p2 holds it, and another at its position
+fn conflicting_blocks(
+    harness: &MockHarness,
+) -> (
+    Arc<Signed<Block<TestTransaction>>>,
+    Arc<Signed<Block<TestTransaction>>>,
+) {
+    let original = harness.processes[&Identity(2)]
+        .index
+        .blocks
+        .values()
+        .find(|block| block.data.key.type_ == BlockType::Tr && block.author == Identity(1))
+        .expect("p2 has a transaction block by p1")
+        .clone();
+    let mut altered = original.data.clone();
+    altered.data = BlockData::tr(vec![TestTransaction(vec![0xba, 0xd])]);
+    let conflicting = Arc::new(Signed::from_data(
+        altered.hashed(),
+        &harness.processes[&Identity(1)].kb,
+    ));
+    (original, conflicting)
+}
+
+#[test_log::test]
+fn test_conflicting_blocks_convict_their_author() {
+    let mut harness = harness();
+    let (original, conflicting) = conflicting_blocks(&harness);
+    let p2 = harness.processes.get_mut(&Identity(2)).unwrap();
+    assert!(p2.process_message(
+        Message::Block(conflicting.clone()),
+        Identity(1),
+        &mut Vec::new()
+    ));
+
+    let evidence = p2.equivocation_evidence();
+    assert_eq!(
+        evidence,
+        vec![EquivocationEvidence::Blocks {
+            first: original.clone(),
+            second: conflicting.clone(),
+        }]
+    );
+    let evidence = &evidence[0];
+    assert_eq!(evidence.offender(), &Identity(1));
+
+    // anyone holding the committee's keys can check it, after it has travelled
+    let json = serde_json::to_string(evidence).unwrap();
+    let received: EquivocationEvidence<TestTransaction> = serde_json::from_str(&json).unwrap();
+    assert_eq!(received.verify(&harness.processes[&Identity(4)].kb), Ok(()));
+
+    let kb = &harness.processes[&Identity(4)].kb;
+    let same = EquivocationEvidence::Blocks {
+        first: original.clone(),
+        second: original.clone(),
+    };
+    assert_eq!(same.verify(kb), Err(EvidenceError::SameBlock));
+
+    // a block signed by someone else can't frame p1
+    let framed = Arc::new(Signed {
+        author: Identity(1),
+        ..Signed::from_data(
+            conflicting.data.clone(),
+            &harness.processes[&Identity(3)].kb,
+        )
+    });
+    let framing = EquivocationEvidence::Blocks {
+        first: original.clone(),
+        second: framed,
+    };
+    assert_eq!(framing.verify(kb), Err(EvidenceError::InvalidSignature(1)));
+}
+
+#[test_log::test]
+fn test_conflicting_votes_convict_the_voter() {
+    let mut harness = harness();
+    let (original, conflicting) = conflicting_blocks(&harness);
+    let p3_kb = harness.processes[&Identity(3)].kb.clone();
+    let vote = |block: &BlockKey| {
+        Arc::new(ThreshPartial::from_data(
+            VoteData {
+                z: 0,
+                for_which: block.clone(),
+            },
+            &p3_kb,
+        ))
+    };
+
+    let p1 = harness.processes.get_mut(&Identity(1)).unwrap();
+    for block in [&original, &conflicting] {
+        p1.process_message(
+            Message::NewVote(vote(&block.data.key)),
+            Identity(3),
+            &mut Vec::new(),
+        );
+    }
+
+    let evidence: Vec<_> = p1
+        .equivocation_evidence()
+        .into_iter()
+        .filter(|evidence| evidence.offender() == &Identity(3))
+        .collect();
+    assert_eq!(evidence.len(), 1);
+    assert!(matches!(evidence[0], EquivocationEvidence::Votes { .. }));
+    assert_eq!(evidence[0].verify(&p1.kb), Ok(()));
+
+    // votes at different levels don't conflict
+    let different_levels = EquivocationEvidence::<TestTransaction>::Votes {
+        first: vote(&original.data.key),
+        second: Arc::new(ThreshPartial::from_data(
+            VoteData {
+                z: 1,
+                for_which: conflicting.data.key.clone(),
+            },
+            &p3_kb,
+        )),
+    };
+    assert_eq!(
+        different_levels.verify(&p1.kb),
+        Err(EvidenceError::DifferentPositions)
+    );
+}
@@ -0,0 +1,182 @@
+use std::sync::Arc;
+
+use hellas_morpheus::test_harness::{MockHarness, TestTransaction, TxGenPolicy};
+use hellas_morpheus::testkit::{assert_agreement, assert_no_invariant_violations};
+use hellas_morpheus::*;
+
+// Leader blocks attest to their own view, as version 1 of a made-up format
+struct AttestView;
+
+impl ExtensionPolicy for AttestView {
+    fn produce(&self, ctx: &ExtensionContext) -> Option<BlockExtension> {
+        Some(BlockExtension {
+            version: 1,
+            payload: ctx.view.0.to_le_bytes().to_vec(),
+        })
+    }
+
+    fn validate(&self, ctx: &ExtensionContext, extension: &BlockExtension) -> Result<(), String> {
+        if extension.version != 1 {
+            return Err(format!("unknown version {}", extension.version));
+        }
+        if extension.payload != ctx.view.0.to_le_bytes() {
+            return Err("attests to another view".to_string());
+        }
+        Ok(())
+    }
+}
+
+fn run_with_extensions() -> (MockHarness, Arc<Signed<Block<TestTransaction>>>) {
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    harness.set_extension_policy(Arc::new(AttestView));
+    harness.run(2 * 3 * 5);
+
+    let p2 = &harness.processes[&Identity(2)];
+    let lead = p2
+        .index
+        .blocks
+        .values()
+        .filter(|b| b.data.key.type_ == BlockType::Lead && b.data.key.view == p2.view_i)
+        .max_by_key(|b| b.data.key.slot)
+        .expect("leader produced a block")
+        .clone();
+    (harness, lead)
+}
+
+fn extension_of(block: &Block<TestTransaction>) -> Option<&BlockExtension> {
+    match &block.data {
+        BlockData::Lead { extension, .. } => extension.as_ref(),
+        _ => None,
+    }
+}
+
+#[test_log::test]
+fn test_leader_blocks_carry_extensions() {
+    let (harness, lead) = run_with_extensions();
+    assert_agreement(&harness);
+    assert_no_invariant_violations(&harness);
+
+    assert_eq!(
+        extension_of(&lead.data),
+        Some(&BlockExtension {
+            version: 1,
+            payload: lead.data.key.view.0.to_le_bytes().to_vec(),
+        })
+    );
+    // and they are finalized like any other
+    let p1 = &harness.processes[&Identity(1)];
+    assert!(p1.index.finalized.iter().any(|key| {
+        key.type_ == BlockType::Lead && extension_of(&p1.index.blocks[key].data).is_some()
+    }));
+}
+
+#[test_log::test]
+fn test_extensions_are_validated() {
+    let (mut harness, lead) = run_with_extensions();
+    let leader = lead.data.key.author.clone().unwrap();
+    let leader_kb = harness.processes[&leader].kb.clone();
+    let p2 = harness.processes.get_mut(&Identity(2)).unwrap();
+    assert_eq!(p2.block_valid(&lead), Ok(()));
+
+    // a process that doesn't understand extensions refuses them
+    p2.extension_policy = Arc::new(NoExtensions);
+    assert!(matches!(
+        p2.block_valid(&lead),
+        Err(BlockValidationError::InvalidExtension { version: 1, .. })
+    ));
+    p2.extension_policy = Arc::new(AttestView);
+
+    let with_extension = |extension: BlockExtension| {
+        let mut block = lead.data.clone();
+        let BlockData::Lead {
+            extension: slot, ..
+        } = &mut block.data
+        else {
+            unreachable!()
+        };
+        *slot = Some(extension);
+        Signed::from_data(block.hashed(), &leader_kb)
+    };
+
+    assert!(matches!(
+        p2.block_valid(&with_extension(BlockExtension {
+            version: 2,
+            payload: vec![],
+        })),
+        Err(BlockValidationError::InvalidExtension { version: 2, .. })
+    ));
+    assert_eq!(
+        p2.block_valid(&with_extension(BlockExtension {
+            version: 1,
+            payload: vec![0; MAX_EXTENSION_LEN + 1],
+        })),
+        Err(BlockValidationError::ExtensionTooLarge {
+            size: MAX_EXTENSION_LEN + 1,
+            max: MAX_EXTENSION_LEN,
+        })
+    );
+
+    // the extension is covered by the content hash
+    let mut swapped = lead.data.clone();
+    if let BlockData::Lead { extension, .. } = &mut swapped.data {
+        *extension = None;
+    }
+    assert!(matches!(
+        p2.block_valid(&Signed::from_data(swapped, &leader_kb)),
+        Err(BlockValidationError::ContentHashMismatch { .. })
+    ));
+}
+
+#[test_log::test]
+fn test_extensions_round_trip() {
+    let (_, lead) = run_with_extensions();
+    let message = Message::Block(lead);
+    assert_eq!(Message::from_wire(&message.to_wire()), Ok(message));
+}
+
+#[test_log::test]
+fn test_oversized_extensions_are_not_produced() {
+    struct Oversized;
+
+    impl ExtensionPolicy for Oversized {
+        fn produce(&self, _: &ExtensionContext) -> Option<BlockExtension> {
+            Some(BlockExtension {
+                version: 1,
+                payload: vec![0; MAX_EXTENSION_LEN + 1],
+            })
+        }
+
+        fn validate(&self, _: &ExtensionContext, _: &BlockExtension) -> Result<(), String> {
+            Ok(())
+        }
+    }
+
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    harness.set_extension_policy(Arc::new(Oversized));
+    harness.run(2 * 3 * 5);
+    assert_agreement(&harness);
+
+    let p1 = &harness.processes[&Identity(1)];
+    assert!(
+        p1.index
+            .blocks
+            .values()
+            .any(|b| b.data.key.type_ == BlockType::Lead)
+    );
+    assert!(
+        p1.index
+            .blocks
+            .values()
+            .all(|b| extension_of(&b.data).is_none())
+    );
+}
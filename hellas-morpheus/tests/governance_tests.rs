@@ -0,0 +1,202 @@
+use std::sync::Arc;
+
+use hellas_morpheus::test_harness::{MockHarness, TestTransaction, TxGenPolicy};
+use hellas_morpheus::testkit::assert_agreement;
+use hellas_morpheus::*;
+
+fn change_tx(param: &str, value: u64, activation: i64) -> TestTransaction {
+    TestTransaction(
+        ConfigChange {
+            param: param.to_string(),
+            value,
+            activation: ViewNum(activation),
+        }
+        .encode(),
+    )
+}
+
+#[test_log::test]
+fn test_config_change_validation() {
+    let change = ConfigChange::new(Parameter::DeltaMultiplier, 2, ViewNum(6));
+    assert_eq!(ConfigChange::decode(&change.encode()), Some(change.clone()));
+    assert_eq!(ConfigChange::decode(b"just some bytes"), None);
+
+    assert_eq!(change.validate(ViewNum(2)), Ok(Parameter::DeltaMultiplier));
+    assert_eq!(
+        change.validate(ViewNum(3)),
+        Err(GovernanceError::TooSoon {
+            activation: ViewNum(6),
+            earliest: ViewNum(3 + GOVERNANCE_MIN_LEAD),
+        })
+    );
+    assert_eq!(
+        ConfigChange::new(Parameter::DeltaMultiplier, 0, ViewNum(6)).validate(ViewNum(0)),
+        Err(GovernanceError::Unsafe {
+            param: Parameter::DeltaMultiplier,
+            value: 0,
+            min: 1,
+            max: 16,
+        })
+    );
+    assert_eq!(
+        ConfigChange::new(Parameter::LeaderMinTips, 1, ViewNum(6))
+            .validate(ViewNum(0))
+            .map_err(|error| error.to_string()),
+        Err("leader_min_tips = 1 is outside the safe range 2..=64".to_string())
+    );
+    let unknown = ConfigChange {
+        param: "quorum_size".to_string(),
+        value: 1,
+        activation: ViewNum(6),
+    };
+    assert_eq!(
+        unknown.validate(ViewNum(0)),
+        Err(GovernanceError::UnknownParameter("quorum_size".to_string()))
+    );
+}
+
+#[test_log::test]
+fn test_invalid_changes_are_never_included() {
+    let mut harness = MockHarness::create_test_setup(4);
+    let process = harness.processes.get_mut(&Identity(1)).unwrap();
+    process.submit(change_tx("quorum_size", 1, 8)).unwrap();
+    process.submit(change_tx("delta_multiplier", 2, 1)).unwrap();
+    process
+        .submit(change_tx("delta_multiplier", 100, 8))
+        .unwrap();
+    process.submit(TestTransaction(vec![1])).unwrap();
+    assert_eq!(process.evict_expired(), 3);
+    assert_eq!(process.ready_transactions, vec![TestTransaction(vec![1])]);
+}
+
+#[test_log::test]
+fn test_blocks_with_invalid_changes_are_rejected() {
+    let harness = MockHarness::create_test_setup(4);
+    let validator = &harness.processes[&Identity(1)];
+    let author = &harness.processes[&Identity(2)];
+
+    let block_with = |tx: TestTransaction| {
+        let block = Block {
+            key: BlockKey {
+                type_: BlockType::Tr,
+                view: ViewNum(0),
+                height: 1,
+                author: Some(Identity(2)),
+                slot: SlotNum(0),
+                hash: None,
+            },
+            prev: vec![validator.genesis_qc.clone()],
+            one: validator.genesis_qc.clone(),
+            data: BlockData::tr(vec![TestTransaction(vec![1]), tx]),
+        };
+        Arc::new(Signed::from_data(block.hashed(), &author.kb))
+    };
+
+    assert_eq!(
+        validator.block_valid(&block_with(change_tx("max_block_size", 1 << 20, 4))),
+        Ok(())
+    );
+    assert_eq!(
+        validator.block_valid(&block_with(change_tx("max_block_size", 1 << 20, 3))),
+        Err(BlockValidationError::InvalidConfigChange {
+            index: 1,
+            error: GovernanceError::TooSoon {
+                activation: ViewNum(3),
+                earliest: ViewNum(4),
+            },
+        })
+    );
+}
+
+#[test_log::test]
+fn test_finalized_change_is_scheduled_everywhere() {
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    let activation = ViewNum(GOVERNANCE_MIN_LEAD);
+    harness
+        .processes
+        .get_mut(&Identity(2))
+        .unwrap()
+        .submit(TestTransaction(
+            ConfigChange::new(Parameter::DeltaMultiplier, 3, activation).encode(),
+        ))
+        .unwrap();
+    harness.run(2 * 3 * 5);
+    assert_agreement(&harness);
+
+    for process in harness.processes.values_mut() {
+        assert_eq!(
+            process.governance.scheduled(),
+            [(activation, vec![(Parameter::DeltaMultiplier, 3)])].into()
+        );
+        assert!(process.view_i < activation);
+        assert_eq!(process.current_delta(), process.delta);
+        assert_eq!(process.governance.params_at(activation).delta_multiplier, 3);
+
+        let (complain, end_view) = process.view_timeouts();
+        process.view_i = activation;
+        assert_eq!(process.current_delta(), 3 * process.delta);
+        assert_eq!(process.view_timeouts(), (3 * complain, 3 * end_view));
+
+        // and so does the window view changes are counted in: 24Δ on, a view
+        // change is still within 12 governed Δ
+        process.view_churn.recent = [process.current_time].into();
+        process.set_now(process.current_time + 24 * process.delta);
+        assert_eq!(process.recent_view_changes(), 1);
+        process.set_now(process.current_time + 12 * process.delta);
+        assert_eq!(process.recent_view_changes(), 0);
+    }
+}
+
+#[test_log::test]
+fn test_change_is_scheduled_when_its_block_arrives_after_its_qc() {
+    let activation = ViewNum(GOVERNANCE_MIN_LEAD);
+    let change = ConfigChange::new(Parameter::MaxBlockSize, 1 << 20, activation);
+    let mut harness = MockHarness::new(vec![MorpheusProcess::dev(0)], 100);
+    harness
+        .processes
+        .get_mut(&Identity(1))
+        .unwrap()
+        .submit(TestTransaction(change.encode()))
+        .unwrap();
+    harness
+        .tx_gen_policy
+        .insert(Identity(1), TxGenPolicy::EveryNSteps { n: 1 });
+    harness.run(5);
+
+    let process = &harness.processes[&Identity(1)];
+    let scheduled = process.governance.scheduled();
+    assert_eq!(
+        scheduled,
+        [(activation, vec![(Parameter::MaxBlockSize, 1 << 20)])].into()
+    );
+    let block = process
+        .index
+        .blocks
+        .values()
+        .find(|block| match &block.data.data {
+            BlockData::Tr { transactions, .. } => {
+                transactions.contains(&TestTransaction(change.encode()))
+            }
+            _ => false,
+        })
+        .unwrap()
+        .clone();
+
+    // a process that finalizes the block from its QCs before holding it
+    let mut late = MorpheusProcess::<TestTransaction>::dev(0);
+    for qc in &process.qcs {
+        if qc.data.for_which == block.data.key {
+            late.record_qc(qc.clone());
+        }
+    }
+    assert!(late.index.finalized.contains(&block.data.key));
+    assert!(late.governance.scheduled().is_empty());
+
+    late.record_block(&block);
+    assert_eq!(late.governance.scheduled(), scheduled);
+}
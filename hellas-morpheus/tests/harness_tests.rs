@@ -1,12 +1,26 @@
-use ark_serialize::CanonicalSerialize;
 use ark_std::test_rng;
-use hellas_morpheus::test_harness::MockHarness;
+use hellas_morpheus::presets::{PRESETS, UnknownPreset};
+use hellas_morpheus::test_harness::{
+    CURRENT_VERSION, DivergenceReport, MockHarness, ProcessProfile, ScenarioEvent, TestTransaction,
+    TxGenPolicy,
+};
+use hellas_morpheus::testkit::{
+    assert_agreement, assert_finalized_within, assert_no_invariant_violations,
+    assert_versions_interoperate, assert_view_le,
+};
+use hellas_morpheus::topology::{TOPOLOGIES, Topology, UnknownTopology};
 use hellas_morpheus::{
-    BlockKey, BlockType, Identity, Message, MorpheusProcess, Signed, SlotNum, ThreshPartial,
-    ThreshSigned, ViewNum, VoteData,
+    AuthorRotation, Block, BlockData, BlockKey, BlockType, BlockValidationError, ChainId,
+    CorrelationId, DagStats, EventFilter, EventKind, GEN_BLOCK_KEY, Identity, IndexOrder,
+    InvariantLevel, KeyBook, LeaderBudget, LeaderElection, LeaderPolicy, LeaderProof, LocalSigner,
+    LowLoadOnly, MAX_VIEW_CHANGES_PER_WINDOW, Message, MessageKind, MorpheusProcess, PaperPhase,
+    Phase, PhaseContext, PhasePolicy, ProtocolEvent, ReceiptOrder, Signed, SignerBitmap, SlotKey,
+    SlotNum, ThreshPartial, ThreshSigned, TipContext, Verifier, ViewNum, VoteAggregation, VoteData,
+    signing_bytes,
 };
 use hints::{F, GlobalData};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::panic::AssertUnwindSafe;
 use std::sync::Arc;
 
 #[test_log::test]
@@ -48,7 +62,7 @@ fn test_multiple_rounds_end_view() {
             .get(&Identity(1))
             .unwrap()
             .received_messages
-            .len(),
+            .total(),
         3
     );
     assert_eq!(
@@ -57,7 +71,7 @@ fn test_multiple_rounds_end_view() {
             .get(&Identity(2))
             .unwrap()
             .received_messages
-            .len(),
+            .total(),
         5
     );
     assert_eq!(
@@ -66,7 +80,7 @@ fn test_multiple_rounds_end_view() {
             .get(&Identity(3))
             .unwrap()
             .received_messages
-            .len(),
+            .total(),
         7
     );
 }
@@ -120,7 +134,7 @@ fn test_complex_simulation() {
     );
     let p2_vote = ThreshPartial::from_data(
         vote_data.clone(),
-        &harness.processes.get(&Identity(1)).unwrap().kb,
+        &harness.processes.get(&Identity(2)).unwrap().kb,
     );
     let agg = harness
         .processes
@@ -129,18 +143,18 @@ fn test_complex_simulation() {
         .kb
         .hints_setup
         .aggregator();
-    let mut msg = Vec::new();
-    vote_data.serialize_compressed(&mut msg).unwrap();
+    let msg = signing_bytes(&harness.processes[&Identity(1)].kb.chain_id, &vote_data);
     // Create a QC message
     let qc_message = Message::QC(Arc::new(ThreshSigned {
         data: vote_data,
         signature: hints::sign_aggregate(
             &agg,
             hints::F::from(2),
-            &[(1, p1_vote.signature), (2, p2_vote.signature)],
+            &[(0, p1_vote.signature), (1, p2_vote.signature)],
             &msg,
         )
         .unwrap(),
+        signers: SignerBitmap::from_signers([Identity(1), Identity(2)]),
     }));
 
     // Broadcast the message
@@ -228,3 +242,1478 @@ fn test_step_sequence() {
     // Note: We don't make assertions about the queue size as it depends
     // on the internal implementation of process_message and processing behavior
 }
+
+#[test_log::test]
+fn test_low_throughput_phase_finalizes_in_fewer_message_delays() {
+    // everyone produces at first, then a single producer switches to the low
+    // throughput phase, so blocks are finalized in both
+    let harness = run_phase_switch(None);
+
+    // measure until a quorum (n - f) of processes regard the block as final
+    let quorum = 3;
+    let high = harness.finalization_latencies(BlockType::Tr, Phase::High, quorum);
+    let low = harness.finalization_latencies(BlockType::Tr, Phase::Low, quorum);
+    assert!(!high.is_empty(), "no high throughput block was finalized");
+    assert!(!low.is_empty(), "no low throughput block was finalized");
+
+    // every finalization needs at least a round of votes and a round of QCs
+    for latency in high.iter().chain(low.iter()) {
+        assert!(
+            *latency >= 2,
+            "finalized after only {} message delays",
+            latency
+        );
+    }
+
+    // the direct path of the low throughput phase skips the leader block round
+    // trip, so it takes at least one message delay less
+    let mean =
+        |latencies: &[usize]| latencies.iter().sum::<usize>() as f64 / latencies.len() as f64;
+    assert!(
+        mean(&low) + 1.0 <= mean(&high),
+        "low throughput mean latency {} is not a message delay below high throughput mean latency {}",
+        mean(&low),
+        mean(&high)
+    );
+    assert!(
+        low.iter().min() < high.iter().min(),
+        "the fastest low throughput finalization is no faster than the fastest high throughput one"
+    );
+}
+
+/// Holds every process in the high throughput phase
+struct NeverLow;
+
+impl PhasePolicy for NeverLow {
+    fn enter_low(&self, _: &PhaseContext) -> bool {
+        false
+    }
+}
+
+fn run_phase_switch(policy: Option<Arc<dyn PhasePolicy>>) -> MockHarness {
+    let mut harness = MockHarness::create_test_setup(4);
+    if let Some(policy) = policy {
+        harness.set_phase_policy(policy);
+    }
+    harness.load_preset("high-load-phase-switch").unwrap();
+    harness.run(120);
+    harness
+}
+
+#[test_log::test]
+fn test_default_phase_policy_is_paper() {
+    let default = run_phase_switch(None);
+    let paper = run_phase_switch(Some(Arc::new(PaperPhase)));
+
+    for (id, process) in &default.processes {
+        let other = &paper.processes[id];
+        assert_eq!(process.phase_i, other.phase_i);
+        assert_eq!(process.finalized_blocks(), other.finalized_blocks());
+    }
+    assert!(
+        default
+            .processes
+            .values()
+            .any(|p| p.phase_i.values().any(|phase| *phase == Phase::Low)),
+        "the scenario never reached the low throughput phase"
+    );
+}
+
+#[test_log::test]
+fn test_phase_policies_are_safe() {
+    let policies: Vec<Arc<dyn PhasePolicy>> = vec![
+        Arc::new(PaperPhase),
+        Arc::new(LowLoadOnly { max_unfinalized: 1 }),
+        Arc::new(NeverLow),
+    ];
+    for policy in policies {
+        let harness = run_phase_switch(Some(policy));
+        assert_agreement(&harness);
+        assert_no_invariant_violations(&harness);
+    }
+
+    // held in the high throughput phase, blocks are still finalized through leader blocks
+    let harness = run_phase_switch(Some(Arc::new(NeverLow)));
+    for process in harness.processes.values() {
+        assert!(process.phase_i.values().all(|phase| *phase == Phase::High));
+    }
+    assert!(
+        !harness
+            .finalization_latencies(BlockType::Tr, Phase::High, 1)
+            .is_empty()
+    );
+}
+
+#[test_log::test]
+fn test_beacon_agrees_across_processes() {
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+
+    for process in harness.processes.values() {
+        assert_eq!(process.beacon(ViewNum(0)), None);
+    }
+
+    // blocks keep finalizing, so view 0 doesn't end and its beacon isn't
+    // settled yet
+    harness.run(20);
+    for process in harness.processes.values() {
+        assert_eq!(process.beacon(ViewNum(0)), None);
+    }
+
+    harness.schedule_event(20, ScenarioEvent::ForceEndView(Identity(2)));
+    harness.schedule_event(20, ScenarioEvent::ForceEndView(Identity(3)));
+    harness.run(60);
+    let beacons = harness
+        .processes
+        .values()
+        .map(|p| p.beacon(ViewNum(0)))
+        .collect::<Vec<_>>();
+    assert!(beacons[0].is_some());
+    assert!(
+        beacons.windows(2).all(|w| w[0] == w[1]),
+        "processes disagree on the beacon for view 0"
+    );
+
+    // and it stays what it was as more blocks are finalized
+    harness.run(30);
+    for process in harness.processes.values() {
+        assert_eq!(process.beacon(ViewNum(0)), beacons[0]);
+    }
+}
+
+#[test_log::test]
+fn test_finalized_histories_agree() {
+    let mut harness = MockHarness::create_test_setup(3);
+
+    harness
+        .tx_gen_policy
+        .insert(Identity(2), TxGenPolicy::EveryNSteps { n: 3 });
+    harness
+        .tx_gen_policy
+        .insert(Identity(3), TxGenPolicy::EveryNSteps { n: 2 });
+
+    harness.run(2 * 3 * 5);
+
+    for id in harness.processes.keys() {
+        let prefix = harness.finalized_prefix(id);
+        assert_eq!(prefix.first(), Some(&GEN_BLOCK_KEY));
+        assert!(prefix.windows(2).all(|w| w[0].height <= w[1].height));
+    }
+
+    assert_agreement(&harness);
+    assert_no_invariant_violations(&harness);
+
+    let report = harness.divergence_report();
+    assert!(report.common_prefix_len >= 1);
+    assert_eq!(report.finalized_counts.len(), 3);
+
+    // the common prefix is every block all processes finalized, whatever
+    // order they finalized blocks of the same height in
+    let common = harness
+        .processes
+        .values()
+        .map(|process| process.finalized_blocks())
+        .reduce(|a, b| a.intersection(&b).cloned().collect())
+        .unwrap();
+    assert_eq!(report.common_prefix_len, common.len());
+
+    let json = serde_json::to_string(&report).unwrap();
+    assert_eq!(
+        serde_json::from_str::<DivergenceReport>(&json).unwrap(),
+        report
+    );
+}
+
+#[test_log::test]
+fn test_presets() {
+    let mut harness = MockHarness::create_test_setup(4);
+    for preset in PRESETS {
+        assert_eq!(harness.load_preset(preset), Ok(()));
+    }
+    assert_eq!(
+        harness.load_preset("no-such-preset"),
+        Err(UnknownPreset("no-such-preset".to_string()))
+    );
+
+    // every preset keeps the processes in agreement, partition healed
+    for preset in PRESETS {
+        let mut harness = MockHarness::create_test_setup(4);
+        harness.load_preset(preset).unwrap();
+        harness.run(60);
+        assert!(
+            harness.divergence_report().is_consistent(),
+            "{preset} diverged"
+        );
+    }
+}
+
+#[test_log::test]
+fn test_loading_a_preset_lifts_earlier_faults() {
+    let mut harness = MockHarness::create_test_setup(4);
+    harness.load_preset("leader-crash").unwrap();
+    harness.run(10);
+    assert!(!harness.faults.crashed.is_empty());
+
+    harness.load_preset("happy-path").unwrap();
+    assert!(harness.faults.crashed.is_empty());
+    assert!(harness.faults.partition.is_none());
+    assert!(harness.faults.equivocators.is_empty());
+}
+
+#[test_log::test]
+fn test_unknown_preset_leaves_the_harness_unchanged() {
+    let mut harness = MockHarness::create_test_setup(4);
+    harness.load_preset("partition-and-heal").unwrap();
+    harness.run(10);
+    let policies = harness.tx_gen_policy.keys().cloned().collect::<Vec<_>>();
+    let schedule = harness.schedule.keys().copied().collect::<Vec<_>>();
+    assert!(harness.faults.partition.is_some());
+    assert!(!schedule.is_empty());
+
+    assert!(harness.load_preset("no-such-preset").is_err());
+    assert_eq!(
+        harness.tx_gen_policy.keys().cloned().collect::<Vec<_>>(),
+        policies
+    );
+    assert_eq!(
+        harness.schedule.keys().copied().collect::<Vec<_>>(),
+        schedule
+    );
+    assert!(harness.faults.partition.is_some());
+}
+
+#[test_log::test]
+fn test_event_subscription_filters() {
+    let mut harness = MockHarness::create_test_setup(3);
+
+    harness
+        .tx_gen_policy
+        .insert(Identity(2), TxGenPolicy::EveryNSteps { n: 3 });
+    harness
+        .tx_gen_policy
+        .insert(Identity(3), TxGenPolicy::EveryNSteps { n: 2 });
+
+    let p2 = harness.processes.get_mut(&Identity(2)).unwrap();
+    let created = p2.subscribe(
+        EventFilter::default()
+            .kinds([EventKind::BlockCreated])
+            .block_author(Identity(2)),
+    );
+    let everything = p2.subscribe(EventFilter::default());
+    let dropped = p2.subscribe(EventFilter::default());
+    drop(dropped);
+
+    harness.run(2 * 3 * 5);
+
+    let created = created.drain();
+    assert!(!created.is_empty());
+    for event in &created {
+        assert_eq!(event.kind(), EventKind::BlockCreated);
+        assert_eq!(event.block_author(), Some(&Identity(2)));
+    }
+
+    let everything = everything.drain();
+    assert!(everything.len() > created.len());
+    assert!(
+        everything
+            .iter()
+            .any(|event| event.kind() == EventKind::QcFormed)
+    );
+}
+
+#[test_log::test]
+fn test_subscriptions_drop_their_oldest_events_when_full() {
+    let mut harness = MockHarness::create_test_setup(3);
+    for i in 1..=3 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    let p1 = harness.processes.get_mut(&Identity(1)).unwrap();
+    let small = p1.subscribe_with_capacity(EventFilter::default(), 2);
+    let everything = p1.subscribe(EventFilter::default());
+    harness.run(2 * 3 * 5);
+
+    let all = everything.drain();
+    assert!(all.len() > 2);
+    assert_eq!(everything.dropped(), 0);
+    // the small one kept the latest two
+    assert_eq!(small.drain(), all[all.len() - 2..]);
+    assert_eq!(small.dropped(), all.len() as u64 - 2);
+}
+
+#[test_log::test]
+fn test_cloned_process_has_no_subscribers() {
+    let mut harness = MockHarness::create_test_setup(3);
+    for i in 1..=3 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    let original = harness
+        .processes
+        .get_mut(&Identity(1))
+        .unwrap()
+        .subscribe(EventFilter::default());
+
+    // run a copy of the whole cluster; the original process never moves
+    let mut copy = harness.clone();
+    copy.run(2 * 3 * 5);
+    assert!(!copy.processes[&Identity(1)].recent_events().is_empty());
+    assert!(original.drain().is_empty());
+
+    harness.run(2 * 3 * 5);
+    assert!(!original.drain().is_empty());
+}
+
+#[test_log::test]
+fn test_testkit_assertions() {
+    let mut harness = MockHarness::create_test_setup(3);
+    assert_view_le(&harness, ViewNum(0));
+    assert_no_invariant_violations(&harness);
+    assert_agreement(&harness);
+
+    // move everyone past view 0
+    let message = Message::EndView(Arc::new(ThreshPartial::from_data(
+        ViewNum(0),
+        &harness.processes.get(&Identity(1)).unwrap().kb,
+    )));
+    harness.enqueue_message(message, Identity(1), None);
+    harness.run(3);
+
+    let result =
+        std::panic::catch_unwind(AssertUnwindSafe(|| assert_view_le(&harness, ViewNum(0))));
+    assert!(result.is_err());
+}
+
+// four processes producing transactions for a while, with `crashed` down
+// from the start, and a transaction every live process has finalized
+fn finalized_transaction(crashed: Option<Identity>) -> (MockHarness, TestTransaction) {
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    harness.faults.crashed.extend(crashed);
+    harness.run(60);
+
+    let finalized_by = |process: &MorpheusProcess<TestTransaction>| {
+        process
+            .finalized_blocks()
+            .into_iter()
+            .filter_map(|key| match &process.index.blocks.get(&key)?.data.data {
+                BlockData::Tr { transactions, .. } => Some(transactions.clone()),
+                _ => None,
+            })
+            .flatten()
+            .collect::<BTreeSet<_>>()
+    };
+    let tx = harness
+        .processes
+        .values()
+        .filter(|process| !harness.faults.crashed.contains(&process.id))
+        .map(finalized_by)
+        .reduce(|a, b| &a & &b)
+        .and_then(|common| common.into_iter().next())
+        .expect("some transaction was finalized by every live process");
+    (harness, tx)
+}
+
+#[test_log::test]
+fn test_assert_finalized_within_passes() {
+    let (harness, tx) = finalized_transaction(None);
+    assert_finalized_within(&harness, &tx, 10);
+}
+
+#[test_log::test]
+#[should_panic(expected = "has not finalized it")]
+fn test_assert_finalized_within_fails_on_a_process_left_behind() {
+    // the other three still finalize, but the crashed one never does
+    let (harness, tx) = finalized_transaction(Some(Identity(4)));
+    assert_finalized_within(&harness, &tx, 10);
+}
+
+#[test_log::test]
+fn test_invariant_levels() {
+    let mut harness = MockHarness::create_test_setup(3);
+    harness.set_invariant_level(InvariantLevel::Paranoid);
+
+    harness
+        .tx_gen_policy
+        .insert(Identity(2), TxGenPolicy::EveryNSteps { n: 3 });
+    harness
+        .tx_gen_policy
+        .insert(Identity(3), TxGenPolicy::EveryNSteps { n: 2 });
+    harness.run(10);
+
+    let process = harness.processes.get_mut(&Identity(1)).unwrap();
+    process.index.max_height.0 += 1;
+    assert!(process.check_invariants_at(InvariantLevel::Off).is_empty());
+    assert!(
+        !process
+            .check_invariants_at(InvariantLevel::Cheap)
+            .is_empty()
+    );
+}
+
+#[test_log::test]
+fn test_start_view_carries_progress() {
+    let mut harness = MockHarness::create_test_setup(3);
+    harness.load_preset("leader-crash").unwrap();
+    harness.run(200);
+
+    let reported: Vec<_> = harness
+        .processes
+        .values()
+        .flat_map(|process| process.peer_progress.values())
+        .collect();
+    assert!(!reported.is_empty());
+
+    for (id, process) in &harness.processes {
+        for (peer, progress) in &process.peer_progress {
+            assert_ne!(peer, id);
+            let peer_process = &harness.processes[peer];
+            assert!(progress.max_view <= peer_process.index.max_view.0);
+            assert!(progress.finalized_head.height <= peer_process.finalized_head().height);
+        }
+    }
+}
+
+#[test_log::test]
+fn test_process_saved_before_progress_and_budgets_restores() {
+    let harness = MockHarness::create_test_setup(3);
+    let mut json = serde_json::to_value(&harness.processes[&Identity(1)]).unwrap();
+    let fields = json.as_object_mut().unwrap();
+    assert!(fields.remove("peer_progress").is_some());
+    assert!(fields.remove("leader_budget").is_some());
+
+    let restored: MorpheusProcess<TestTransaction> = serde_json::from_value(json).unwrap();
+    assert!(restored.peer_progress.is_empty());
+    assert_eq!(restored.leader_budget, LeaderBudget::default());
+}
+
+#[test_log::test]
+fn test_leader_vote_aggregation() {
+    let mut harness = MockHarness::create_test_setup(4);
+    harness.set_vote_aggregation(VoteAggregation::Leader);
+    for i in 2..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 3 });
+    }
+
+    let mut broadcast_votes = 0;
+    for _ in 0..60 {
+        harness.step();
+        broadcast_votes += harness
+            .pending_messages
+            .iter()
+            .filter(|(message, _, dest)| {
+                matches!(message, Message::NewVote(vote) if vote.data.z > 0) && dest.is_none()
+            })
+            .count();
+    }
+
+    assert_eq!(broadcast_votes, 0);
+    assert!(harness.processes[&Identity(2)].finalized_blocks().len() > 1);
+    assert_agreement(&harness);
+    assert_no_invariant_violations(&harness);
+}
+
+#[test_log::test]
+fn test_vote_aggregation_survives_silent_leader() {
+    let mut harness = MockHarness::create_test_setup(4);
+    harness.set_vote_aggregation(VoteAggregation::Leader);
+    harness.load_preset("leader-crash").unwrap();
+    harness.run(200);
+
+    assert_agreement(&harness);
+    assert_no_invariant_violations(&harness);
+}
+
+#[test_log::test]
+fn test_vrf_leader_election() {
+    let mut harness = busy_harness();
+    harness.set_leader_election(LeaderElection::Vrf);
+
+    // every member draws the same leaders, and they don't just take turns
+    let p1 = &harness.processes[&Identity(1)];
+    let leaders: Vec<Identity> = (0..16).map(|v| p1.lead(ViewNum(v))).collect();
+    for process in harness.processes.values() {
+        for (v, leader) in leaders.iter().enumerate() {
+            assert_eq!(process.lead(ViewNum(v as i64)), *leader);
+            assert!(process.verify_leader(leader.clone(), ViewNum(v as i64)));
+        }
+    }
+    let round_robin: Vec<Identity> = (0..16).map(|v| Identity(v % 4 + 1)).collect();
+    assert_ne!(leaders, round_robin);
+
+    for (step, view) in [(10, 0), (40, 1), (70, 2)] {
+        // end-views from f+1 processes other than the next leader move everyone on
+        let next = leaders[view + 1].clone();
+        for id in (1..=4).map(Identity).filter(|id| *id != next).take(2) {
+            harness.schedule_event(step, ScenarioEvent::ForceEndView(id));
+        }
+    }
+    harness.run(120);
+
+    let p2 = &harness.processes[&Identity(2)];
+    assert!(p2.view_i >= ViewNum(3));
+    for block in p2.index.blocks.values() {
+        if block.data.key.type_ == BlockType::Lead {
+            assert_eq!(
+                block.data.key.author.as_ref(),
+                Some(&leaders[block.data.key.view.0 as usize])
+            );
+        }
+    }
+    assert_agreement(&harness);
+    assert_no_invariant_violations(&harness);
+}
+
+#[test_log::test]
+fn test_leader_proofs_checked_by_outsiders() {
+    let harness = MockHarness::create_test_setup(4);
+    let mut member = harness.processes[&Identity(1)].clone();
+    member.leader_election = LeaderElection::Vrf;
+    let proof = member.leader_proof(ViewNum(7)).unwrap();
+    let leader = member.lead(ViewNum(7));
+
+    // an outsider holds only the election public key
+    let mut outsider = harness.processes[&Identity(2)].clone();
+    outsider.kb.election_key = None;
+    outsider.leader_election = LeaderElection::Vrf;
+    assert!(outsider.leader_proof(ViewNum(7)).is_none());
+    assert!(outsider.verify_leader_proof(&leader, &proof));
+
+    let other = (1..=4).map(Identity).find(|id| *id != leader).unwrap();
+    assert!(!outsider.verify_leader_proof(&other, &proof));
+    let moved = LeaderProof {
+        view: ViewNum(8),
+        ..proof.clone()
+    };
+    assert!(!outsider.verify_leader_proof(&moved.leader(4), &moved));
+    let forged = LeaderProof::evaluate(ViewNum(7), member.kb.me_sec_key.expose().unwrap());
+    assert!(!outsider.verify_leader_proof(&forged.leader(4), &forged));
+}
+
+/// A harness run long enough for the view 0 leader to have produced blocks,
+/// and a leader block from the current view as p2 received it
+fn run_with_leader_block() -> (MockHarness, Arc<Signed<Block<TestTransaction>>>) {
+    let mut harness = MockHarness::create_test_setup(3);
+    for i in 1..=3 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    harness.run(30);
+
+    let p2 = &harness.processes[&Identity(2)];
+    let lead = p2
+        .index
+        .blocks
+        .values()
+        .filter(|b| b.data.key.type_ == BlockType::Lead && b.data.key.view == p2.view_i)
+        .max_by_key(|b| b.data.key.slot)
+        .expect("leader produced a block")
+        .clone();
+    (harness, lead)
+}
+
+#[test_log::test]
+fn test_block_content_hash() {
+    let (mut harness, _) = run_with_leader_block();
+    let p1_kb = harness.processes[&Identity(1)].kb.clone();
+    let p2 = harness.processes.get_mut(&Identity(2)).unwrap();
+    let original = p2
+        .index
+        .blocks
+        .values()
+        .find(|block| block.data.key.type_ == BlockType::Tr && block.author == Identity(1))
+        .expect("p2 has a transaction block by p1")
+        .clone();
+    assert_eq!(original.data.key.hash, Some(original.data.content_hash()));
+
+    // other contents under the same key don't pass for the original
+    let mut altered = original.data.clone();
+    altered.data = BlockData::tr(vec![TestTransaction(vec![0xba, 0xd])]);
+    let expected = altered.content_hash();
+    assert_eq!(
+        p2.block_valid(&Signed::from_data(altered.clone(), &p1_kb)),
+        Err(BlockValidationError::ContentHashMismatch {
+            key: original.data.key.clone(),
+            expected,
+        })
+    );
+
+    // hashed properly, they are a second block at the same position
+    let conflicting = Arc::new(Signed::from_data(altered.hashed(), &p1_kb));
+    assert_ne!(conflicting.data.key, original.data.key);
+    let position = SlotKey::from(&original.data.key);
+    assert_eq!(SlotKey::from(&conflicting.data.key), position);
+    assert!(p2.index.equivocations.is_empty());
+
+    let mut to_send = Vec::new();
+    assert!(p2.process_message(
+        Message::Block(conflicting.clone()),
+        Identity(1),
+        &mut to_send
+    ));
+    assert_eq!(
+        p2.index.equivocations[&position],
+        BTreeSet::from([original.data.key.clone(), conflicting.data.key.clone()])
+    );
+    // and p2 doesn't vote for the second one
+    assert!(
+        !to_send
+            .iter()
+            .any(|(message, _)| matches!(message, Message::NewVote(_)))
+    );
+}
+
+#[test_log::test]
+fn test_leader_block_budget() {
+    let (mut harness, lead) = run_with_leader_block();
+    let p2 = harness.processes.get_mut(&Identity(2)).unwrap();
+    let lenient = LeaderBudget::for_committee(3);
+    assert_eq!(lenient.known_tip_delays, None);
+
+    p2.leader_budget = lenient;
+    assert_eq!(p2.block_valid(&lead), Ok(()));
+
+    p2.leader_budget = LeaderBudget {
+        max_prev: lead.data.prev.len() - 1,
+        ..lenient
+    };
+    assert_eq!(
+        p2.block_valid(&lead),
+        Err(BlockValidationError::TooManyPrevPointers {
+            count: lead.data.prev.len(),
+            max: lead.data.prev.len() - 1,
+        })
+    );
+
+    p2.leader_budget = LeaderBudget {
+        max_size: 64,
+        ..lenient
+    };
+    assert!(matches!(
+        p2.block_valid(&lead),
+        Err(BlockValidationError::BlockTooLarge { max: 64, .. })
+    ));
+
+    let first = p2
+        .index
+        .blocks
+        .values()
+        .find(|b| b.data.key.type_ == BlockType::Lead && b.data.key.slot == SlotNum(0))
+        .unwrap()
+        .clone();
+    p2.leader_budget = LeaderBudget {
+        max_justification: 1,
+        ..lenient
+    };
+    assert!(matches!(
+        p2.block_valid(&first),
+        Err(BlockValidationError::TooManyJustifications { max: 1, .. })
+    ));
+}
+
+#[test_log::test]
+fn test_leader_block_must_observe_known_tips() {
+    let (mut harness, lead) = run_with_leader_block();
+    assert_no_invariant_violations(&harness);
+
+    // Long after the fact, p2 holds transaction tips that the leader had
+    // every chance to learn of, but this older block doesn't observe them.
+    let p2 = harness.processes.get_mut(&Identity(2)).unwrap();
+    let unobserved = p2
+        .index
+        .tips
+        .iter()
+        .filter(|tip| tip.data.for_which.type_ == BlockType::Tr)
+        .any(|tip| {
+            !lead
+                .data
+                .prev
+                .iter()
+                .any(|prev| p2.observes(prev.data.clone(), &tip.data))
+        });
+    assert!(unobserved);
+
+    // the check is opt-in
+    p2.set_now(p2.current_time + 100 * p2.delta);
+    assert_eq!(p2.block_valid(&lead), Ok(()));
+
+    p2.leader_budget.known_tip_delays = Some(2);
+    assert!(matches!(
+        p2.block_valid(&lead),
+        Err(BlockValidationError::OmitsKnownTip { .. })
+    ));
+
+    p2.leader_budget.known_tip_delays = None;
+    assert_eq!(p2.block_valid(&lead), Ok(()));
+}
+
+#[test_log::test]
+fn test_end_view_cert_relayed_once_and_served_to_laggards() {
+    let mut harness = MockHarness::create_test_setup(4);
+    let end_view = |harness: &MockHarness, id: u32| {
+        Message::EndView(Arc::new(ThreshPartial::from_data(
+            ViewNum(0),
+            &harness.processes[&Identity(id)].kb,
+        )))
+    };
+    let ev1 = end_view(&harness, 1);
+    let ev2 = end_view(&harness, 2);
+    let ev4 = end_view(&harness, 4);
+
+    // f+1 = 2 end-views let p3 certify view 0 and move to view 1
+    let p3 = harness.processes.get_mut(&Identity(3)).unwrap();
+    let mut to_send = Vec::new();
+    p3.process_message(ev1, Identity(1), &mut to_send);
+    p3.process_message(ev2, Identity(2), &mut to_send);
+    assert_eq!(p3.view_i, ViewNum(1));
+
+    let certs: Vec<_> = to_send
+        .iter()
+        .filter(|(message, _)| matches!(message, Message::EndViewCert(_)))
+        .collect();
+    assert_eq!(certs.len(), 1, "the certificate is relayed exactly once");
+    assert_eq!(certs[0].1, None);
+    let cert = certs[0].0.clone();
+    assert!(p3.end_view_cert(ViewNum(0)).is_some());
+
+    // the same certificate coming back from a peer is dropped
+    let mut to_send = Vec::new();
+    assert!(!p3.process_message(cert.clone(), Identity(1), &mut to_send));
+    assert!(to_send.is_empty());
+
+    // p4 is still complaining about view 0, so it gets the certificate
+    let mut to_send = Vec::new();
+    p3.process_message(ev4, Identity(4), &mut to_send);
+    assert_eq!(to_send, vec![(cert, Some(Identity(4)))]);
+}
+
+#[test_log::test]
+fn test_end_view_certs_not_rebroadcast() {
+    let mut harness = MockHarness::create_test_setup(4);
+    harness.load_preset("leader-crash").unwrap();
+
+    let mut relayed = BTreeMap::new();
+    for _ in 0..200 {
+        harness.step();
+        for (message, sender, dest) in &harness.pending_messages {
+            if let (Message::EndViewCert(cert), None) = (message, dest) {
+                *relayed.entry((sender.clone(), cert.data)).or_insert(0) += 1;
+            }
+        }
+    }
+
+    assert!(!relayed.is_empty());
+    assert!(relayed.values().all(|&count| count == 1), "{relayed:?}");
+    assert_agreement(&harness);
+}
+
+#[test_log::test]
+fn test_vote_progress() {
+    let mut harness = MockHarness::create_test_setup(4);
+    harness
+        .tx_gen_policy
+        .insert(Identity(2), TxGenPolicy::EveryNSteps { n: 100 });
+    harness
+        .tx_gen_policy
+        .insert(Identity(3), TxGenPolicy::EveryNSteps { n: 2 });
+
+    // p2 produces its first block and votes for it; nothing has been delivered yet
+    harness.step();
+    let p2 = &harness.processes[&Identity(2)];
+    let block = p2
+        .index
+        .blocks
+        .keys()
+        .find(|key| key.author == Some(Identity(2)))
+        .unwrap()
+        .clone();
+    let progress = p2.vote_progress(&block);
+    assert_eq!(progress.quorum, 3);
+    assert_eq!(progress.levels[0].votes, 1);
+    assert!(progress.levels[0].voted);
+    assert!(!progress.levels[0].qc);
+    assert!(p2.in_flight_vote_progress().contains_key(&block));
+
+    harness.run(30);
+    let p2 = &harness.processes[&Identity(2)];
+    assert!(p2.vote_progress(&block).levels[0].qc);
+
+    // blocks with a 2-QC are no longer in flight
+    let in_flight = p2.in_flight_vote_progress();
+    let certified: Vec<_> = p2
+        .qcs
+        .iter()
+        .filter(|qc| qc.data.z == 2 && qc.data.for_which.type_ != BlockType::Genesis)
+        .map(|qc| qc.data.for_which.clone())
+        .collect();
+    assert!(!certified.is_empty());
+    for key in certified {
+        assert!(p2.vote_progress(&key).levels[2].qc);
+        assert!(!in_flight.contains_key(&key));
+    }
+}
+
+#[test_log::test]
+fn test_submit_batch() {
+    let mut harness = MockHarness::create_test_setup(3);
+    let p1 = harness.processes.get_mut(&Identity(1)).unwrap();
+
+    let receipt = p1.submit_batch((0..5).map(|i| TestTransaction(vec![i])).collect());
+    assert_eq!(receipt.admitted, 5);
+    assert!(receipt.rejected.is_empty());
+
+    let receipt = p1.submit_stream((5..12).map(|i| TestTransaction(vec![i])), 3);
+    assert_eq!(receipt.admitted, 7);
+    p1.submit(TestTransaction(vec![12])).unwrap();
+
+    harness.produce_blocks();
+    let p1 = &harness.processes[&Identity(1)];
+    let transactions = p1
+        .index
+        .blocks
+        .values()
+        .find_map(|b| match &b.data.data {
+            BlockData::Tr { transactions, .. } if b.data.key.author == Some(Identity(1)) => {
+                Some(transactions.clone())
+            }
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(
+        transactions,
+        (0..13)
+            .map(|i| TestTransaction(vec![i]))
+            .collect::<Vec<_>>()
+    );
+    assert!(p1.ready_transactions.is_empty());
+}
+
+fn tip(author: u32, slot: u64) -> Arc<ThreshSigned<VoteData>> {
+    Arc::new(ThreshSigned {
+        data: VoteData {
+            z: 0,
+            for_which: BlockKey {
+                type_: BlockType::Tr,
+                view: ViewNum(0),
+                height: 1,
+                author: Some(Identity(author)),
+                slot: SlotNum(slot),
+                hash: None,
+            },
+        },
+        signature: Default::default(),
+        signers: Default::default(),
+    })
+}
+
+#[test_log::test]
+fn test_leader_policies_order_tips() {
+    let tips = vec![tip(1, 0), tip(2, 0), tip(3, 0), tip(4, 0)];
+    let authors = |tips: Vec<Arc<ThreshSigned<VoteData>>>| {
+        tips.iter()
+            .map(|tip| tip.data.for_which.author.clone().unwrap().0)
+            .collect::<Vec<_>>()
+    };
+
+    let seen_at = BTreeMap::from([
+        (tips[0].data.clone(), 30),
+        (tips[1].data.clone(), 10),
+        (tips[3].data.clone(), 20),
+    ]);
+    let ctx = TipContext {
+        view: ViewNum(6),
+        n: 4,
+        seen_at: &seen_at,
+    };
+
+    assert_eq!(
+        authors(IndexOrder.select_tips(tips.clone(), &ctx)),
+        [1, 2, 3, 4]
+    );
+    assert_eq!(
+        authors(ReceiptOrder.select_tips(tips.clone(), &ctx)),
+        [2, 4, 1, 3]
+    );
+    assert_eq!(
+        authors(AuthorRotation.select_tips(tips, &ctx)),
+        [3, 4, 1, 2]
+    );
+}
+
+#[test_log::test]
+fn test_leader_policy_used_for_leader_blocks() {
+    let mut harness = MockHarness::create_test_setup(3);
+    harness.set_leader_policy(Arc::new(AuthorRotation));
+    for i in 1..=3 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    harness.run(30);
+
+    let p2 = &harness.processes[&Identity(2)];
+    let lead_blocks: Vec<_> = p2
+        .index
+        .blocks
+        .values()
+        .filter(|b| b.data.key.type_ == BlockType::Lead)
+        .collect();
+    assert!(!lead_blocks.is_empty());
+    for block in lead_blocks {
+        // apart from the leader's own chain, which is appended after the
+        // policy runs, prev pointers go round the authors from lead(view)
+        let first = block.data.key.view.0.rem_euclid(3);
+        let order: Vec<_> = block
+            .data
+            .prev
+            .iter()
+            .filter(|qc| qc.data.for_which.type_ == BlockType::Tr)
+            .map(|qc| {
+                (qc.data.for_which.author.clone().unwrap().0 as i64 - 1 - first).rem_euclid(3)
+            })
+            .collect();
+        assert!(order.is_sorted(), "{order:?}");
+    }
+    assert_agreement(&harness);
+}
+
+/// Finalized block counts of the processes still running after `domain` fails
+/// at step 20, at the moment of failure, shortly after and much later
+fn run_domain_failure(placement: &[(u32, &str)], domain: &str) -> (MockHarness, [usize; 3]) {
+    let mut harness = MockHarness::create_test_setup(4);
+    for &(id, d) in placement {
+        harness.set_failure_domain(Identity(id), d);
+        harness
+            .tx_gen_policy
+            .insert(Identity(id), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    harness.schedule_event(20, ScenarioEvent::CrashDomain(domain.to_string()));
+
+    let survivors: Vec<_> = harness
+        .processes
+        .keys()
+        .filter(|id| !harness.domain_members(domain).contains(id))
+        .cloned()
+        .collect();
+    let finalized = |harness: &MockHarness| {
+        survivors
+            .iter()
+            .map(|id| harness.processes[id].finalized_blocks().len())
+            .min()
+            .unwrap()
+    };
+
+    harness.run(20);
+    let at_failure = finalized(&harness);
+    harness.run(10);
+    let settled = finalized(&harness);
+    harness.run(100);
+    let later = finalized(&harness);
+    (harness, [at_failure, settled, later])
+}
+
+#[test_log::test]
+fn test_domain_failure_within_tolerance_keeps_finalizing() {
+    let placement = [(1, "rack-a"), (2, "rack-b"), (3, "rack-c"), (4, "rack-d")];
+    let (harness, [at_failure, _, later]) = run_domain_failure(&placement, "rack-b");
+
+    assert!(harness.placement_tolerates_domain_loss());
+    assert!(harness.faults.crashed.contains(&Identity(2)));
+    assert!(later > at_failure, "{at_failure} -> {later}");
+    assert_agreement(&harness);
+    assert_no_invariant_violations(&harness);
+}
+
+#[test_log::test]
+fn test_correlated_failure_beyond_tolerance_stalls_safely() {
+    let placement = [
+        (1, "region-a"),
+        (2, "region-a"),
+        (3, "region-b"),
+        (4, "region-b"),
+    ];
+    let (harness, [_, settled, later]) = run_domain_failure(&placement, "region-a");
+
+    assert!(!harness.placement_tolerates_domain_loss());
+    assert_eq!(
+        harness.faults.crashed,
+        BTreeSet::from([Identity(1), Identity(2)])
+    );
+    // two of four processes cannot form an n-f quorum, so nothing more is finalized
+    assert_eq!(settled, later);
+    assert_agreement(&harness);
+    assert_no_invariant_violations(&harness);
+}
+
+#[test_log::test]
+fn test_correlation_ids_follow_a_finalization() {
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    harness.record_hops();
+    let finalized = harness
+        .processes
+        .get_mut(&Identity(3))
+        .unwrap()
+        .subscribe(EventFilter::default().kinds([EventKind::BlockFinalized]));
+    harness.run(30);
+
+    let event = finalized
+        .drain()
+        .into_iter()
+        .next()
+        .expect("something was finalized");
+    let ProtocolEvent::BlockFinalized { key } = &event else {
+        unreachable!()
+    };
+    let correlation = event.correlation_id().unwrap();
+    assert_eq!(correlation, CorrelationId::for_block(key));
+
+    // the block, its votes and its QCs all travelled under the same id
+    let hops = harness.hops_for(correlation);
+    let kinds: BTreeSet<_> = hops.iter().map(|hop| hop.kind).collect();
+    assert!(kinds.contains(&MessageKind::Block), "{kinds:?}");
+    assert!(kinds.contains(&MessageKind::NewVote), "{kinds:?}");
+    let receivers: BTreeSet<_> = hops.iter().map(|hop| hop.to.clone()).collect();
+    assert!(receivers.len() > 1);
+
+    // and nothing about other blocks did
+    let block = &harness.processes[&Identity(3)].index.blocks[key];
+    assert_eq!(Message::Block(block.clone()).correlation_id(), correlation);
+    let other = block.data.prev[0].data.for_which.clone();
+    assert_ne!(CorrelationId::for_block(&other), correlation);
+}
+
+#[test_log::test]
+fn test_forced_and_timeout_view_changes() {
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+
+    // one operator alone can't move the view: f+1 = 2 end-views are needed
+    harness.schedule_event(3, ScenarioEvent::ForceEndView(Identity(2)));
+    harness.run(5);
+    assert_view_le(&harness, ViewNum(0));
+    let p2 = harness.processes.get_mut(&Identity(2)).unwrap();
+    assert!(p2.sent_end_view(ViewNum(0)));
+    assert!(!p2.force_end_view(&mut Vec::new()), "only once per view");
+
+    harness.schedule_event(5, ScenarioEvent::ForceEndView(Identity(3)));
+    harness.run(3);
+    for process in harness.processes.values() {
+        assert_eq!(process.view_i, ViewNum(1));
+    }
+
+    // the new leader then crashes, and the timeout takes over
+    let leader = harness.processes[&Identity(1)].lead(ViewNum(1));
+    harness.schedule_event(8, ScenarioEvent::Crash(leader.clone()));
+    harness.run(100);
+    for (id, process) in &harness.processes {
+        if id != &leader {
+            assert!(
+                process.view_i >= ViewNum(2),
+                "{id:?} in {:?}",
+                process.view_i
+            );
+        }
+    }
+    assert_agreement(&harness);
+    assert_no_invariant_violations(&harness);
+}
+
+#[test_log::test]
+fn test_view_churn_backoff_and_cap() {
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+
+    // move to view 1, whose leader is down, so it ends without progress
+    let leader = harness.processes[&Identity(1)].lead(ViewNum(1));
+    harness.schedule_event(1, ScenarioEvent::Crash(leader.clone()));
+    harness.schedule_event(3, ScenarioEvent::ForceEndView(Identity(3)));
+    harness.schedule_event(3, ScenarioEvent::ForceEndView(Identity(4)));
+    harness.run(8);
+
+    let p3 = &harness.processes[&Identity(3)];
+    assert_eq!(p3.view_i, ViewNum(1));
+    assert_eq!(p3.recent_view_changes(), 1);
+    assert!(!p3.index.unfinalized.is_empty());
+
+    // too many recent view changes: the timeout doesn't start another one
+    let mut churned = p3.clone();
+    let now = churned.view_entry_time + churned.view_timeouts().1;
+    churned.set_now(now);
+    churned.view_churn.recent = vec![now; MAX_VIEW_CHANGES_PER_WINDOW].into();
+    let mut to_send = Vec::new();
+    churned.check_timeouts(&mut to_send);
+    assert!(
+        !to_send
+            .iter()
+            .any(|(m, _)| matches!(m, Message::EndView(_)))
+    );
+    churned.view_churn.recent.clear();
+    churned.check_timeouts(&mut to_send);
+    assert!(
+        to_send
+            .iter()
+            .any(|(m, _)| matches!(m, Message::EndView(_)))
+    );
+
+    // leaving view 1 without progress backs the timeouts off
+    harness.run(150);
+    let p3 = &harness.processes[&Identity(3)];
+    assert!(p3.view_i >= ViewNum(2));
+    assert!(p3.view_churn.unproductive >= 1);
+    assert!(p3.view_churn.total >= 2);
+    let (_, end_view_timeout) = p3.view_timeouts();
+    assert_eq!(
+        end_view_timeout,
+        p3.delta * 12 * (1 << p3.view_churn.backoff)
+    );
+    assert_agreement(&harness);
+}
+
+#[test_log::test]
+fn test_qcs_are_threshold_signatures() {
+    let kbs = KeyBook::committee_setup(4, &mut test_rng());
+    let distinct: BTreeSet<_> = kbs.iter().map(|kb| kb.me_pub_key.clone()).collect();
+    assert_eq!(distinct.len(), 4, "every member has its own key");
+
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    harness.run(20);
+
+    // a third party holding only the public setup can check a QC
+    let p1 = &harness.processes[&Identity(1)];
+    let setup = p1.kb.hints_setup.clone();
+    let qc = p1
+        .qcs
+        .iter()
+        .find(|qc| qc.data.for_which != GEN_BLOCK_KEY)
+        .unwrap();
+    let chain = p1.kb.chain_id;
+    assert!(qc.verify(&setup, &chain, p1.n - p1.f));
+
+    // shares from too few members, or from the wrong ones, don't make a QC
+    let msg = signing_bytes(&chain, &qc.data);
+    let shares = |ids: &[u32]| {
+        ids.iter()
+            .map(|&id| {
+                let kb = &harness.processes[&Identity(id)].kb;
+                let share = ThreshPartial::from_data(qc.data.clone(), kb);
+                (id as usize - 1, share.signature)
+            })
+            .collect::<Vec<_>>()
+    };
+    // None if aggregating refuses the shares outright
+    let verifies = |shares: &[(usize, hints::PartialSignature)], claimed: u64| {
+        hints::sign_aggregate(&setup.aggregator(), F::from(claimed), shares, &msg)
+            .ok()
+            .map(|signature| {
+                ThreshSigned {
+                    data: qc.data.clone(),
+                    signature,
+                    signers: SignerBitmap::from_signers(
+                        shares.iter().map(|(i, _)| Identity(*i as u32 + 1)),
+                    ),
+                }
+                .verify(&setup, &chain, 3)
+            })
+    };
+    assert_eq!(verifies(&shares(&[1, 2, 3]), 3), Some(true));
+    assert_ne!(verifies(&shares(&[1, 2]), 2), Some(true));
+    let mut misattributed = shares(&[1, 2, 3]);
+    misattributed[2].0 = 3;
+    assert_ne!(verifies(&misattributed, 3), Some(true));
+
+    // the bitmap names who signed, and must account for the threshold
+    assert!(qc.signers.count() >= p1.n - p1.f);
+    assert!(qc.signers.signers().all(|id| id.0 <= p1.n));
+    let mut unnamed = (**qc).clone();
+    unnamed.signers = SignerBitmap::from_signers(qc.signers.signers().skip(1));
+    assert!(!unnamed.valid_signature(&p1.kb, p1.n - p1.f));
+    let mut outsider = (**qc).clone();
+    outsider.signers = SignerBitmap::from_signers(qc.signers.signers().chain([Identity(9)]));
+    assert!(!outsider.valid_signature(&p1.kb, p1.n - p1.f));
+}
+
+/// Trusts the committee's keys except for one member's
+struct Distrusting {
+    kb: KeyBook,
+    distrusted: Identity,
+}
+
+impl Verifier for Distrusting {
+    fn committee_size(&self) -> u32 {
+        self.kb.committee_size()
+    }
+
+    fn chain_id(&self) -> ChainId {
+        self.kb.chain_id
+    }
+
+    fn verify_partial(
+        &self,
+        author: &Identity,
+        message: &[u8],
+        signature: &hints::PartialSignature,
+    ) -> bool {
+        *author != self.distrusted && self.kb.verify_partial(author, message, signature)
+    }
+
+    fn verify_aggregate(
+        &self,
+        message: &[u8],
+        signature: &hints::Signature,
+        threshold: u32,
+    ) -> bool {
+        self.kb.verify_aggregate(message, signature, threshold)
+    }
+}
+
+#[test_log::test]
+fn test_pluggable_verifier() {
+    let mut harness = busy_harness();
+    let p1 = harness.processes.get_mut(&Identity(1)).unwrap();
+    p1.verifier = Some(Arc::new(Distrusting {
+        kb: p1.kb.clone(),
+        distrusted: Identity(4),
+    }));
+    // a signer is just as pluggable; this one is what a process uses by default
+    p1.signer = Some(Arc::new(LocalSigner(p1.kb.me_sec_key.clone())));
+    harness.run(60);
+
+    let p1 = &harness.processes[&Identity(1)];
+    assert!(
+        p1.index
+            .blocks
+            .keys()
+            .all(|key| key.author != Some(Identity(4)))
+    );
+    let p2 = &harness.processes[&Identity(2)];
+    assert!(
+        p2.index
+            .blocks
+            .keys()
+            .any(|key| key.author == Some(Identity(4)))
+    );
+    assert!(
+        p2.index
+            .blocks
+            .keys()
+            .any(|key| key.author == Some(Identity(1)))
+    );
+    assert_agreement(&harness);
+}
+
+fn busy_harness() -> MockHarness {
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    harness
+}
+
+fn leader_aggregation() -> ProcessProfile {
+    ProcessProfile {
+        version: "leader-aggregation".to_string(),
+        vote_aggregation: VoteAggregation::Leader,
+        leader_policy: Arc::new(ReceiptOrder),
+        ..ProcessProfile::current()
+    }
+}
+
+#[test_log::test]
+fn test_mixed_versions_interoperate() {
+    let mut harness = busy_harness();
+    let upgraded = leader_aggregation();
+    harness.set_profile(&Identity(2), &upgraded);
+    harness.set_profile(&Identity(4), &upgraded);
+    assert_eq!(harness.version_of(&Identity(2)), "leader-aggregation");
+    assert_eq!(harness.version_of(&Identity(1)), CURRENT_VERSION);
+
+    harness.run(100);
+    assert_agreement(&harness);
+    assert_no_invariant_violations(&harness);
+    assert_versions_interoperate(&harness);
+}
+
+#[test_log::test]
+fn test_rolling_upgrade() {
+    let mut harness = busy_harness();
+    harness.schedule_rolling_upgrade(10, 15, &leader_aggregation());
+
+    // halfway through, two versions run side by side
+    harness.run(35);
+    let upgraded = (1..=4)
+        .filter(|&i| harness.version_of(&Identity(i)) != CURRENT_VERSION)
+        .count();
+    assert_eq!(upgraded, 2);
+    assert_agreement(&harness);
+
+    harness.run(100);
+    for process in harness.processes.values() {
+        assert_eq!(process.vote_aggregation, VoteAggregation::Leader);
+    }
+    assert_agreement(&harness);
+    assert_no_invariant_violations(&harness);
+    assert_versions_interoperate(&harness);
+}
+
+#[test_log::test]
+fn test_incompatible_version_fails_safely() {
+    let mut harness = busy_harness();
+    // a version whose validators reject any leader block pointing to more than one QC
+    let strict = ProcessProfile {
+        version: "strict-budget".to_string(),
+        leader_budget: Some(LeaderBudget {
+            max_prev: 1,
+            ..LeaderBudget::for_committee(4)
+        }),
+        ..ProcessProfile::current()
+    };
+    harness.set_profile(&Identity(3), &strict);
+    harness.set_profile(&Identity(4), &strict);
+
+    // progress may stall, but nothing panics and nobody disagrees
+    harness.run(150);
+    assert_agreement(&harness);
+    assert_no_invariant_violations(&harness);
+}
+
+#[test_log::test]
+fn test_dag_stats() {
+    let mut harness = busy_harness();
+    harness.run(40);
+
+    let report = harness.dag_report();
+    let stats = &report[&Identity(1)];
+    let p1 = &harness.processes[&Identity(1)];
+    assert_eq!(stats.width.values().sum::<usize>(), p1.index.blocks.len());
+    assert_eq!(stats.width[&0], 1, "only genesis sits at height 0");
+    assert_eq!(stats.depth, p1.index.max_height.0);
+    assert!(stats.mean_prev[&BlockType::Tr] > 0.0);
+    assert!(stats.mean_prev[&BlockType::Lead] > 0.0);
+    assert!(stats.orphans < p1.index.blocks.len());
+    assert!(!stats.tips_over_time.is_empty());
+    assert!(
+        stats
+            .tips_over_time
+            .windows(2)
+            .all(|w| w[0].0 <= w[1].0 && w[0].1 != w[1].1)
+    );
+
+    let json = serde_json::to_string(&report).unwrap();
+    let back: BTreeMap<Identity, DagStats> = serde_json::from_str(&json).unwrap();
+    assert_eq!(back[&Identity(1)].width, stats.width);
+}
+
+#[test_log::test]
+fn test_topology_presets() {
+    let ids = (1..=9).map(Identity).collect::<Vec<_>>();
+    for name in TOPOLOGIES {
+        let topology = Topology::preset(name, ids.clone()).unwrap();
+        assert_eq!(topology.regions.len(), 9);
+        for a in &ids {
+            for b in &ids {
+                assert_eq!(topology.delay(a, b), topology.delay(b, a));
+                assert!(topology.delay(a, b) >= 1);
+            }
+        }
+    }
+
+    let single = Topology::preset("single-dc", ids.clone()).unwrap();
+    assert!(ids.iter().all(|id| single.delay(&ids[0], id) == 1));
+    assert_eq!(single.bandwidth(&ids[0], &ids[1]), None);
+
+    // every pair of the nine regions has a latency of its own
+    let global = Topology::preset("global-9-region", ids.clone()).unwrap();
+    let regions: BTreeSet<_> = global.regions.values().collect();
+    assert_eq!(regions.len(), 9);
+    assert_eq!(global.latency_ms.len(), 9 * 8 / 2);
+
+    assert_eq!(
+        Topology::preset("moon-base", ids),
+        Err(UnknownTopology("moon-base".to_string()))
+    );
+}
+
+#[test_log::test]
+fn test_single_dc_topology_matches_default() {
+    let mut default = busy_harness();
+    let mut single = busy_harness();
+    single.load_topology("single-dc").unwrap();
+    default.run(60);
+    single.run(60);
+
+    for (id, process) in &default.processes {
+        assert_eq!(
+            process.finalized_blocks(),
+            single.processes[id].finalized_blocks()
+        );
+    }
+}
+
+#[test_log::test]
+fn test_topology_latency_and_bandwidth() {
+    let mut harness = MockHarness::create_test_setup(3);
+    // us-east, eu-west and ap-southeast, in identity order
+    harness.load_topology("3-region").unwrap();
+    harness.topology.as_mut().unwrap().wan_bandwidth = Some(1);
+    harness.record_hops();
+
+    let kb = harness.processes[&Identity(1)].kb.clone();
+    for view in 0..3 {
+        let message = Message::EndView(Arc::new(ThreshPartial::from_data(ViewNum(view), &kb)));
+        harness.enqueue_message(message, Identity(1), None);
+    }
+    for _ in 0..12 {
+        harness.process_round();
+    }
+
+    let arrivals = |to: u32| -> Vec<usize> {
+        harness
+            .hops
+            .iter()
+            .flatten()
+            .filter(|hop| {
+                hop.from == Identity(1)
+                    && hop.to == Identity(to)
+                    && hop.kind == MessageKind::EndView
+            })
+            .map(|hop| hop.round)
+            .collect()
+    };
+    // 40ms to eu-west is two rounds of 20ms, 110ms to ap-southeast six, and
+    // each link carries one message a round
+    assert_eq!(arrivals(2), [1, 2, 3]);
+    assert_eq!(arrivals(3), [5, 6, 7]);
+}
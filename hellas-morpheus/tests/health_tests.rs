@@ -0,0 +1,54 @@
+use hellas_morpheus::test_harness::MockHarness;
+use hellas_morpheus::{DEFAULT_MAX_VIEW_LAG, Identity, NotReady, ProgressReport, ViewNum};
+
+#[test_log::test]
+fn test_fresh_process_is_ready_with_a_quorum_of_peers() {
+    let harness = MockHarness::create_test_setup(4);
+    let process = &harness.processes[&Identity(1)];
+
+    let readiness = process.readiness(DEFAULT_MAX_VIEW_LAG, 2, true);
+    assert_eq!(readiness.peers_needed, 2);
+    assert_eq!(readiness.lag(), 0);
+    assert!(readiness.is_ready());
+
+    let readiness = process.readiness(DEFAULT_MAX_VIEW_LAG, 1, false);
+    assert_eq!(
+        readiness.problems(),
+        vec![
+            NotReady::StorageUnwritable,
+            NotReady::TooFewPeers {
+                connected: 1,
+                needed: 2
+            },
+        ]
+    );
+}
+
+#[test_log::test]
+fn test_peer_progress_ahead_makes_process_lag() {
+    let mut harness = MockHarness::create_test_setup(4);
+    let process = harness.processes.get_mut(&Identity(1)).unwrap();
+    let finalized_head = process.finalized_head();
+    process.peer_progress.insert(
+        Identity(2),
+        ProgressReport {
+            max_view: ViewNum(process.view_i.0 + 5),
+            finalized_head,
+        },
+    );
+
+    let readiness = process.readiness(DEFAULT_MAX_VIEW_LAG, 3, true);
+    assert_eq!(readiness.lag(), 5);
+    assert_eq!(
+        readiness.problems(),
+        vec![NotReady::Lagging {
+            lag: 5,
+            max_lag: DEFAULT_MAX_VIEW_LAG
+        }]
+    );
+    assert!(process.readiness(5, 3, true).is_ready());
+    assert_eq!(
+        NotReady::Lagging { lag: 5, max_lag: 2 }.to_string(),
+        "5 views behind the best known view, at most 2 allowed"
+    );
+}
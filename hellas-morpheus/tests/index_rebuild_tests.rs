@@ -0,0 +1,107 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+
+use hellas_morpheus::test_harness::{MockHarness, TestTransaction, TxGenPolicy};
+use hellas_morpheus::testkit::{assert_agreement, assert_no_invariant_violations};
+use hellas_morpheus::{BlockKey, Identity, MorpheusProcess, StateIndex, ViewNum};
+
+fn harness() -> MockHarness {
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    harness
+}
+
+fn rebuilt(process: &MorpheusProcess<TestTransaction>) -> StateIndex<TestTransaction> {
+    StateIndex::rebuild(
+        process.index.blocks.values().cloned(),
+        process.qcs.iter().cloned(),
+    )
+}
+
+fn lead_by_view(index: &StateIndex<TestTransaction>) -> BTreeMap<ViewNum, BTreeSet<BlockKey>> {
+    index
+        .unfinalized_lead_by_view
+        .iter()
+        .filter(|(_, blocks)| !blocks.is_empty())
+        .map(|(view, blocks)| (*view, blocks.clone()))
+        .collect()
+}
+
+#[track_caller]
+fn assert_same_index(
+    incremental: &StateIndex<TestTransaction>,
+    rebuilt: &StateIndex<TestTransaction>,
+) {
+    assert_eq!(
+        incremental.tips.iter().collect::<BTreeSet<_>>(),
+        rebuilt.tips.iter().collect::<BTreeSet<_>>()
+    );
+    assert_eq!(
+        incremental.blocks.keys().collect::<Vec<_>>(),
+        rebuilt.blocks.keys().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        incremental.block_at_slot.keys().collect::<Vec<_>>(),
+        rebuilt.block_at_slot.keys().collect::<Vec<_>>()
+    );
+    assert_eq!(incremental.equivocations, rebuilt.equivocations);
+    assert_eq!(incremental.block_pointed_by, rebuilt.block_pointed_by);
+    assert_eq!(incremental.max_view.0, rebuilt.max_view.0);
+    assert_eq!(incremental.max_height.0, rebuilt.max_height.0);
+    assert_eq!(
+        incremental.max_1qc.data.compare_qc(&rebuilt.max_1qc.data),
+        Ordering::Equal
+    );
+    assert_eq!(incremental.finalized, rebuilt.finalized);
+    assert_eq!(incremental.unfinalized, rebuilt.unfinalized);
+    assert_eq!(incremental.unfinalized_2qc, rebuilt.unfinalized_2qc);
+    assert_eq!(
+        incremental.contains_lead_by_view,
+        rebuilt.contains_lead_by_view
+    );
+    assert_eq!(lead_by_view(incremental), lead_by_view(rebuilt));
+}
+
+#[test_log::test]
+fn test_rebuilt_index_matches_incremental() {
+    let mut harness = harness();
+
+    // right after genesis, and then at a few points along the way
+    for process in harness.processes.values() {
+        assert_same_index(&process.index, &rebuilt(process));
+    }
+    for _ in 0..4 {
+        harness.run(15);
+        for process in harness.processes.values() {
+            assert_same_index(&process.index, &rebuilt(process));
+        }
+    }
+    assert!(
+        harness.processes[&Identity(1)].index.finalized.len() > 1,
+        "something was finalized"
+    );
+}
+
+#[test_log::test]
+fn test_process_recovers_from_rebuilt_index() {
+    let mut harness = harness();
+    harness.run(30);
+
+    for process in harness.processes.values_mut() {
+        let mut index = rebuilt(process);
+        index.latest_leader_1qc = process.index.latest_leader_1qc.clone();
+        index.latest_leader_qc = process.index.latest_leader_qc.clone();
+        index.latest_tr_qc = process.index.latest_tr_qc.clone();
+        process.index = index;
+    }
+    let finalized_before = harness.processes[&Identity(1)].index.finalized.len();
+    harness.run(30);
+
+    assert_agreement(&harness);
+    assert_no_invariant_violations(&harness);
+    assert!(harness.processes[&Identity(1)].index.finalized.len() > finalized_before);
+}
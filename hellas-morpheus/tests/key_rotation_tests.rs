@@ -0,0 +1,163 @@
+use std::sync::Arc;
+
+use ark_std::test_rng;
+use hellas_morpheus::test_harness::{MockHarness, ScenarioEvent, TxGenPolicy};
+use hellas_morpheus::testkit::{assert_agreement, assert_no_invariant_violations};
+use hellas_morpheus::{Identity, KeyRotation, KeyRotationError, Message, Signed, ViewNum};
+
+fn busy_harness() -> MockHarness {
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    harness
+}
+
+/// Has process 1 announce `new_key` from view `effective` on
+fn rotate(harness: &mut MockHarness, new_key: hints::SecretKey, effective: ViewNum) {
+    let p1 = harness.processes.get_mut(&Identity(1)).unwrap();
+    let mut to_send = Vec::new();
+    p1.rotate_key(new_key, effective, &mut to_send).unwrap();
+    for (message, dest) in to_send {
+        harness
+            .pending_messages
+            .push_back((message, Identity(1), dest));
+    }
+}
+
+#[test_log::test]
+fn test_rotated_key_takes_effect_at_view_boundary() {
+    let mut harness = busy_harness();
+    let mut rng = test_rng();
+    harness.run(6);
+    let old_key = harness.processes[&Identity(1)]
+        .kb
+        .me_sec_key
+        .expose()
+        .unwrap()
+        .public(&harness.processes[&Identity(1)].kb.hints_setup.global);
+
+    let new_key = hints::SecretKey::random(&mut rng);
+    rotate(&mut harness, new_key, ViewNum(1));
+    harness.run(2);
+    for (id, process) in &harness.processes {
+        assert!(
+            process.kb.rotations[&Identity(1)].contains_key(&ViewNum(1)),
+            "{id:?} didn't record the rotation"
+        );
+        assert!(process.kb.identity_key(&Identity(1), ViewNum(0)) == Some(&old_key));
+    }
+
+    let step = harness.steps;
+    for i in 1..=4 {
+        harness.schedule_event(step, ScenarioEvent::ForceEndView(Identity(i)));
+    }
+    harness.run(40);
+
+    let p2 = &harness.processes[&Identity(2)];
+    assert!(p2.view_i >= ViewNum(1));
+    let by_1: Vec<_> = p2
+        .index
+        .blocks
+        .values()
+        .filter(|block| block.author == Identity(1))
+        .collect();
+    assert!(by_1.iter().any(|block| block.data.key.view == ViewNum(0)));
+    assert!(by_1.iter().any(|block| block.data.key.view >= ViewNum(1)));
+    for block in by_1 {
+        // each block verifies against the key its view was signed with, and
+        // only that one
+        let view = block.data.key.view;
+        assert!(block.valid_signature_at(&p2.kb, view));
+        let other = if view >= ViewNum(1) {
+            ViewNum(0)
+        } else {
+            ViewNum(1)
+        };
+        assert!(!block.valid_signature_at(&p2.kb, other));
+    }
+    assert_agreement(&harness);
+    assert_no_invariant_violations(&harness);
+}
+
+#[test_log::test]
+fn test_retired_key_cannot_announce_rotation() {
+    let mut harness = busy_harness();
+    let mut rng = test_rng();
+    let old_kb = harness.processes[&Identity(1)].kb.clone();
+    rotate(&mut harness, hints::SecretKey::random(&mut rng), ViewNum(1));
+    harness.run(2);
+
+    // someone holding the retired key tries to replace the new one
+    let global = &old_kb.hints_setup.global;
+    let forged = Signed::from_data(
+        KeyRotation::new(
+            &Identity(1),
+            ViewNum(0),
+            ViewNum(5),
+            &hints::SecretKey::random(&mut rng),
+            global,
+        ),
+        &old_kb,
+    );
+    let p2 = harness.processes.get_mut(&Identity(2)).unwrap();
+    assert!(!p2.process_message(
+        Message::KeyRotation(Arc::new(forged)),
+        Identity(1),
+        &mut Vec::new()
+    ));
+    assert!(!p2.kb.rotations[&Identity(1)].contains_key(&ViewNum(5)));
+}
+
+#[test_log::test]
+fn test_rotation_requires_possession_of_new_key() {
+    let mut harness = busy_harness();
+    let mut rng = test_rng();
+    let kb = harness.processes[&Identity(1)].kb.clone();
+    let global = &kb.hints_setup.global;
+
+    // claims someone else's key, with a proof made by a key it does hold
+    let mut rotation = KeyRotation::new(
+        &Identity(1),
+        ViewNum(0),
+        ViewNum(2),
+        &hints::SecretKey::random(&mut rng),
+        global,
+    );
+    rotation.new_key = hints::SecretKey::random(&mut rng).public(global);
+    let p2 = harness.processes.get_mut(&Identity(2)).unwrap();
+    assert!(!p2.process_message(
+        Message::KeyRotation(Arc::new(Signed::from_data(rotation, &kb))),
+        Identity(1),
+        &mut Vec::new()
+    ));
+    assert!(!p2.kb.rotations.contains_key(&Identity(1)));
+}
+
+#[test_log::test]
+fn test_rotate_key_rejects_past_and_overlapping_views() {
+    let mut harness = busy_harness();
+    let mut rng = test_rng();
+    let p1 = harness.processes.get_mut(&Identity(1)).unwrap();
+    let mut to_send = Vec::new();
+    assert_eq!(
+        p1.rotate_key(hints::SecretKey::random(&mut rng), ViewNum(0), &mut to_send),
+        Err(KeyRotationError::NotInFuture {
+            effective: ViewNum(0),
+            view: ViewNum(0)
+        })
+    );
+    assert!(to_send.is_empty());
+
+    p1.rotate_key(hints::SecretKey::random(&mut rng), ViewNum(2), &mut to_send)
+        .unwrap();
+    assert_eq!(
+        p1.rotate_key(hints::SecretKey::random(&mut rng), ViewNum(3), &mut to_send),
+        Err(KeyRotationError::Pending {
+            effective: ViewNum(2)
+        })
+    );
+    assert_eq!(p1.rotated_keys.len(), 1);
+}
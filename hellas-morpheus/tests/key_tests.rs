@@ -0,0 +1,56 @@
+use std::collections::BTreeSet;
+
+use hellas_morpheus::{
+    BlockKey, BlockType, GEN_BLOCK_KEY, Identity, SlotKey, SlotNum, ViewNum, VoteKey,
+};
+
+fn tr_block(author: u32, slot: u64) -> BlockKey {
+    BlockKey {
+        type_: BlockType::Tr,
+        view: ViewNum(0),
+        height: 1,
+        author: Some(Identity(author)),
+        slot: SlotNum(slot),
+        hash: None,
+    }
+}
+
+#[test_log::test]
+fn test_vote_key_for_block() {
+    let block = tr_block(2, 3);
+    let key = VoteKey::for_block(1, &block).unwrap();
+    assert_eq!(
+        <(u8, BlockType, SlotNum, Identity)>::from(key.clone()),
+        (1, BlockType::Tr, SlotNum(3), Identity(2))
+    );
+    assert_eq!(
+        VoteKey::from((1, BlockType::Tr, SlotNum(3), Identity(2))),
+        key
+    );
+    assert!(VoteKey::for_block(0, &GEN_BLOCK_KEY).is_none());
+
+    // blocks at the same position share a SlotKey whatever their view or hash
+    let mut other = tr_block(2, 3);
+    other.view = ViewNum(4);
+    assert_eq!(SlotKey::from(&block), SlotKey::from(&other));
+    assert_ne!(SlotKey::from(&block), SlotKey::from(&tr_block(2, 4)));
+}
+
+#[test_log::test]
+fn test_vote_key_serde_versions() {
+    let key = VoteKey::for_block(2, &tr_block(1, 7)).unwrap();
+
+    let json = serde_json::to_string(&key).unwrap();
+    assert!(json.contains("\"v\":1"), "{}", json);
+    assert_eq!(serde_json::from_str::<VoteKey>(&json).unwrap(), key);
+
+    // voted_i sets written before VoteKey existed hold bare tuples
+    let legacy: BTreeSet<(u8, BlockType, SlotNum, Identity)> =
+        [(2, BlockType::Tr, SlotNum(7), Identity(1))].into();
+    let legacy_json = serde_json::to_string(&legacy).unwrap();
+    let migrated: BTreeSet<VoteKey> = serde_json::from_str(&legacy_json).unwrap();
+    assert_eq!(migrated, [key].into());
+
+    let future = json.replace("\"v\":1", "\"v\":2");
+    assert!(serde_json::from_str::<VoteKey>(&future).is_err());
+}
@@ -0,0 +1,172 @@
+use std::sync::Arc;
+
+use hellas_morpheus::config::{SimulationConfig, SimulationConfigError};
+use hellas_morpheus::test_harness::{MockHarness, TxGenPolicy};
+use hellas_morpheus::testkit::{assert_agreement, assert_no_invariant_violations};
+use hellas_morpheus::*;
+
+#[test_log::test]
+fn test_link_mac() {
+    let secret = [9; 32];
+    let chain = ChainId::default();
+    let mut alice = LinkMac::new(&secret, &chain, &Identity(1), &Identity(2));
+    let mut bob = LinkMac::new(&secret, &chain, &Identity(1), &Identity(2));
+
+    let first = alice.seal(b"vote");
+    let second = alice.seal(b"another vote");
+    assert_eq!(first.len(), 4 + LINK_MAC_OVERHEAD);
+    assert_eq!(bob.open(&first), Ok(&b"vote"[..]));
+    assert_eq!(bob.open(&second), Ok(&b"another vote"[..]));
+    assert_eq!(bob.open(&first), Err(MacError::Replayed { counter: 0 }));
+
+    let mut tampered = alice.seal(b"vote");
+    tampered[8] ^= 1;
+    assert_eq!(bob.open(&tampered), Err(MacError::BadTag));
+    assert_eq!(bob.open(&[0; 16]), Err(MacError::Truncated));
+
+    // the other direction has its own key, so frames can't be reflected
+    let mut reflected = LinkMac::new(&secret, &chain, &Identity(2), &Identity(1));
+    assert_eq!(reflected.open(&alice.seal(b"vote")), Err(MacError::BadTag));
+    let mut other_secret = LinkMac::new(&[8; 32], &chain, &Identity(1), &Identity(2));
+    assert_eq!(
+        other_secret.open(&alice.seal(b"vote")),
+        Err(MacError::BadTag)
+    );
+}
+
+#[test_log::test]
+fn test_trust_model_is_enforced() {
+    let mut harness = MockHarness::create_test_setup(4);
+    let process = harness.processes.get_mut(&Identity(1)).unwrap();
+    assert_eq!(
+        process.trust_links(
+            VoteVerification::AuthenticatedLinks,
+            LinkSecurity::Unauthenticated
+        ),
+        Err(TrustModelError::UnauthenticatedLinks)
+    );
+    assert_eq!(process.vote_verification, VoteVerification::EveryVote);
+    assert_eq!(
+        process.trust_links(
+            VoteVerification::AuthenticatedLinks,
+            LinkSecurity::Authenticated
+        ),
+        Ok(())
+    );
+
+    // only votes delivered by their own author are trusted
+    let trusting = VoteVerification::AuthenticatedLinks;
+    assert!(trusting.trusts(&Identity(2), &Identity(2)));
+    assert!(!trusting.trusts(&Identity(3), &Identity(2)));
+    assert!(!VoteVerification::EveryVote.trusts(&Identity(2), &Identity(2)));
+
+    let config = SimulationConfig {
+        vote_verification: VoteVerification::AuthenticatedLinks,
+        ..SimulationConfig::default()
+    };
+    let harness = config.build().unwrap();
+    assert!(
+        harness
+            .processes
+            .values()
+            .all(|p| p.vote_verification == VoteVerification::AuthenticatedLinks)
+    );
+    assert_eq!(
+        SimulationConfigError::TrustModel(TrustModelError::UnauthenticatedLinks).to_string(),
+        "trusting links for votes needs a transport that authenticates senders"
+    );
+}
+
+#[test_log::test]
+fn test_trusted_links_reach_agreement() {
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+        let process = harness.processes.get_mut(&Identity(i)).unwrap();
+        process
+            .trust_links(
+                VoteVerification::AuthenticatedLinks,
+                MockHarness::LINK_SECURITY,
+            )
+            .unwrap();
+    }
+    harness.run(2 * 3 * 5);
+    assert_agreement(&harness);
+    assert_no_invariant_violations(&harness);
+    assert!(harness.processes[&Identity(1)].finalized_blocks().len() > 1);
+}
+
+#[test_log::test]
+fn test_forged_vote_is_caught_by_the_aggregate() {
+    let harness = MockHarness::create_test_setup(4);
+    let mut process = harness.processes[&Identity(1)].clone();
+    process
+        .trust_links(
+            VoteVerification::AuthenticatedLinks,
+            MockHarness::LINK_SECURITY,
+        )
+        .unwrap();
+    let data = VoteData {
+        z: 0,
+        for_which: BlockKey {
+            type_: BlockType::Tr,
+            view: ViewNum(0),
+            height: 1,
+            author: Some(Identity(4)),
+            slot: SlotNum(0),
+            hash: None,
+        },
+    };
+    let vote_by = |id: u32| {
+        Arc::new(ThreshPartial::from_data(
+            data.clone(),
+            &harness.processes[&Identity(id)].kb,
+        ))
+    };
+    // Identity(2) sends a vote carrying someone else's signature
+    let forged = Arc::new(ThreshPartial {
+        author: Identity(2),
+        ..(*vote_by(3)).clone()
+    });
+
+    let mut to_send = Vec::new();
+    process.process_message(Message::NewVote(forged), Identity(2), &mut to_send);
+    process.process_message(Message::NewVote(vote_by(3)), Identity(3), &mut to_send);
+    process.process_message(Message::NewVote(vote_by(4)), Identity(4), &mut to_send);
+    // three votes, but the aggregate didn't verify, so the forged one is gone
+    assert!(!process.qcs.iter().any(|qc| qc.data == data));
+    let voters = process.vote_tracker.votes[&data]
+        .keys()
+        .cloned()
+        .collect::<Vec<_>>();
+    assert_eq!(voters, vec![Identity(3), Identity(4)]);
+
+    // and the real vote completes the QC
+    process.process_message(Message::NewVote(vote_by(2)), Identity(2), &mut to_send);
+    assert!(process.qcs.iter().any(|qc| qc.data == data));
+}
+
+#[test_log::test]
+fn test_peer_binding() {
+    let harness = MockHarness::create_test_setup(4);
+    let p1 = &harness.processes[&Identity(1)];
+    let p2 = &harness.processes[&Identity(2)];
+
+    let binding = p1.bind_peer(b"peer one").unwrap();
+    assert_eq!(binding.member_of(b"peer one", &p2.kb), Some(Identity(1)));
+    // it names one peer id only
+    assert_eq!(binding.member_of(b"peer two", &p2.kb), None);
+
+    // and can't be passed off as another member's
+    let forged = Signed {
+        author: Identity(3),
+        ..binding.clone()
+    };
+    assert_eq!(forged.member_of(b"peer one", &p2.kb), None);
+
+    // nor used on another chain
+    let other_chain = p2.kb.clone().on_chain("elsewhere");
+    assert_eq!(binding.member_of(b"peer one", &other_chain), None);
+}
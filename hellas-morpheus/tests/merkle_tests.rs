@@ -0,0 +1,105 @@
+use hellas_morpheus::test_harness::{MockHarness, TestTransaction, TxGenPolicy};
+use hellas_morpheus::{
+    BlockData, BlockType, BlockValidationError, Identity, MerkleProof, MerkleRoot, Signed,
+    merkle_root,
+};
+
+fn txs(n: u8) -> Vec<TestTransaction> {
+    (0..n).map(|i| TestTransaction(vec![i])).collect()
+}
+
+#[test_log::test]
+fn test_proofs_verify_for_every_position() {
+    for n in 1..=9 {
+        let transactions = txs(n);
+        let root = merkle_root(&transactions);
+        for (i, tx) in transactions.iter().enumerate() {
+            let proof = MerkleProof::new(&transactions, i).unwrap();
+            assert!(proof.verify(tx, &root), "{i} of {n}");
+
+            // the same transaction claimed at another position, or another
+            // transaction at this one
+            let other = &transactions[(i + 1) % transactions.len()];
+            assert_eq!(proof.verify(other, &root), n == 1);
+            let moved = MerkleProof {
+                index: (proof.index + 1) % proof.len,
+                ..proof.clone()
+            };
+            assert_eq!(moved.verify(tx, &root), n == 1);
+        }
+        assert!(MerkleProof::new(&transactions, n as usize).is_none());
+    }
+}
+
+#[test_log::test]
+fn test_root_commits_to_list() {
+    let root = merkle_root(&txs(4));
+    assert_ne!(root, merkle_root(&txs(3)));
+    assert_ne!(root, merkle_root(&txs(5)));
+    let mut reordered = txs(4);
+    reordered.swap(0, 1);
+    assert_ne!(root, merkle_root(&reordered));
+
+    // padding by repeating the last transaction doesn't give the same root
+    let mut padded = txs(3);
+    padded.push(padded[2].clone());
+    assert_ne!(merkle_root(&txs(3)), merkle_root(&padded));
+
+    // nor does passing an inner node off as a leaf
+    let proof = MerkleProof::new(&txs(4), 0).unwrap();
+    let tampered = MerkleProof {
+        siblings: vec![[0; 32]; proof.siblings.len()],
+        ..proof.clone()
+    };
+    assert!(!tampered.verify(&txs(4)[0], &root));
+    assert!(!proof.verify(&txs(4)[0], &MerkleRoot([0; 32])));
+}
+
+#[test_log::test]
+fn test_light_client_checks_inclusion_from_header() {
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    harness.run(20);
+    let p1_kb = harness.processes[&Identity(1)].kb.clone();
+    let p2 = &harness.processes[&Identity(2)];
+    let block = p2
+        .index
+        .blocks
+        .values()
+        .find(|block| block.data.key.type_ == BlockType::Tr && block.author == Identity(1))
+        .expect("p2 has a transaction block by p1")
+        .clone();
+    let BlockData::Tr { transactions, .. } = &block.data.data else {
+        unreachable!()
+    };
+
+    // the header alone still hashes to the key the QC names
+    let header = block.data.header();
+    assert_eq!(header.key.hash, Some(header.content_hash()));
+    let proof = block.data.tx_proof(0).unwrap();
+    assert!(proof.verify(&transactions[0], header.tx_root().unwrap()));
+
+    // but it is no block
+    assert_eq!(
+        p2.block_valid(&Signed::from_data(header, &p1_kb)),
+        Err(BlockValidationError::EmptyTransactions)
+    );
+
+    // other transactions under the same root keep the content hash, so it's
+    // the root that gives them away
+    let mut altered = block.data.clone();
+    if let BlockData::Tr { transactions, .. } = &mut altered.data {
+        transactions.clear();
+        transactions.push(TestTransaction(vec![0xba, 0xd]));
+    }
+    assert_eq!(
+        p2.block_valid(&Signed::from_data(altered, &p1_kb)),
+        Err(BlockValidationError::TxRootMismatch {
+            expected: merkle_root(&[TestTransaction(vec![0xba, 0xd])]),
+        })
+    );
+}
@@ -0,0 +1,177 @@
+use std::path::{Path, PathBuf};
+
+use hellas_morpheus::*;
+
+// a copy of a fixture written by an older build, to upgrade in place
+fn fixture(test: &str, name: &str) -> PathBuf {
+    let source = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name);
+    let path = std::env::temp_dir().join(format!(
+        "morpheus-migrate-{}-{}-{}",
+        test,
+        std::process::id(),
+        name
+    ));
+    let _ = std::fs::remove_file(&path);
+    std::fs::copy(source, &path).unwrap();
+    path
+}
+
+fn guard_vote_at(slot: i64) -> SignPosition {
+    SignPosition {
+        stream: SignStream::Vote {
+            z: 1,
+            type_: BlockType::Tr,
+            author: Identity(2),
+        },
+        at: slot,
+    }
+}
+
+fn wal_vote_at(slot: u64) -> VoteKey {
+    VoteKey {
+        z: 1,
+        type_: BlockType::Tr,
+        slot: SlotNum(slot),
+        author: Identity(2),
+    }
+}
+
+fn stamped_version(path: &Path) -> u64 {
+    let json: serde_json::Value = serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap();
+    json["version"].as_u64().unwrap()
+}
+
+#[test_log::test]
+fn test_guard_without_checksum_opens_after_upgrade() {
+    let registry = MigrationRegistry::builtin();
+    let path = fixture("guard-v0", "sign_guard_v0.json");
+    let plan = registry.plan_file(StoreKind::SignGuard, &path).unwrap();
+    assert_eq!((plan.from, plan.to), (0, GUARD_FORMAT));
+    assert_eq!(plan.steps.len(), 2);
+
+    // the fixture signed "a" at slot 3
+    let mut guard = SignGuard::open(&path).unwrap();
+    guard.check(&guard_vote_at(3), b"a").unwrap();
+    assert_eq!(
+        guard.check(&guard_vote_at(3), b"b"),
+        Err(GuardError::Conflict(guard_vote_at(3)))
+    );
+    drop(guard);
+
+    // and it was written back in the current format
+    assert_eq!(stamped_version(&path), GUARD_FORMAT as u64);
+    assert!(
+        registry
+            .plan_file(StoreKind::SignGuard, &path)
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test_log::test]
+fn test_guard_with_checksum_opens_after_upgrade() {
+    let registry = MigrationRegistry::builtin();
+    let path = fixture("guard-v1", "sign_guard_v1.json");
+    let plan = registry.plan_file(StoreKind::SignGuard, &path).unwrap();
+    assert_eq!((plan.from, plan.to), (1, GUARD_FORMAT));
+    assert_eq!(plan.steps.len(), 1);
+
+    let mut guard = SignGuard::open(&path).unwrap();
+    assert_eq!(
+        guard.check(&guard_vote_at(3), b"b"),
+        Err(GuardError::Conflict(guard_vote_at(3)))
+    );
+    guard.check(&guard_vote_at(4), b"b").unwrap();
+    drop(guard);
+
+    assert_eq!(stamped_version(&path), GUARD_FORMAT as u64);
+    let mut guard = SignGuard::open(&path).unwrap();
+    guard.check(&guard_vote_at(4), b"b").unwrap();
+    assert!(guard.check(&guard_vote_at(3), b"b").is_err());
+}
+
+#[test_log::test]
+fn test_log_without_header_opens_after_upgrade() {
+    let registry = MigrationRegistry::builtin();
+    let path = fixture("wal-v0", "vote_wal_v0.log");
+    let plan = registry.plan_file(StoreKind::VoteWal, &path).unwrap();
+    assert_eq!((plan.from, plan.to), (0, WAL_FORMAT));
+
+    let mut wal = VoteWal::open(&path).unwrap();
+    assert_eq!(wal.votes(), &(0..3).map(wal_vote_at).collect());
+    wal.append(&wal_vote_at(3)).unwrap();
+    drop(wal);
+
+    assert!(std::fs::read(&path).unwrap().starts_with(b"MWAL"));
+    assert!(
+        registry
+            .plan_file(StoreKind::VoteWal, &path)
+            .unwrap()
+            .is_empty()
+    );
+    let wal = VoteWal::open(&path).unwrap();
+    assert_eq!(wal.votes(), &(0..4).map(wal_vote_at).collect());
+}
+
+#[test_log::test]
+fn test_dry_run_leaves_stores_alone() {
+    let registry = MigrationRegistry::builtin();
+    for (store, name) in [
+        (StoreKind::SignGuard, "sign_guard_v0.json"),
+        (StoreKind::VoteWal, "vote_wal_v0.log"),
+    ] {
+        let path = fixture("dry-run", name);
+        let before = std::fs::read(&path).unwrap();
+        let plan = registry.plan_file(store, &path).unwrap();
+        assert!(!plan.is_empty());
+        assert_eq!(std::fs::read(&path).unwrap(), before);
+    }
+
+    // nothing written yet is nothing to migrate
+    let missing = std::env::temp_dir().join("morpheus-migrate-missing.json");
+    let plan = registry.plan_file(StoreKind::SignGuard, &missing).unwrap();
+    assert!(plan.is_empty());
+    assert_eq!(plan.to, GUARD_FORMAT);
+}
+
+#[test_log::test]
+fn test_store_from_a_newer_build_is_refused() {
+    let path = fixture("newer", "sign_guard_v1.json");
+    drop(SignGuard::open(&path).unwrap());
+    let mut json: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    json["version"] = (GUARD_FORMAT + 1).into();
+    std::fs::write(&path, serde_json::to_vec(&json).unwrap()).unwrap();
+
+    assert_eq!(
+        MigrationRegistry::builtin().plan_file(StoreKind::SignGuard, &path),
+        Err(MigrationError::TooNew {
+            store: StoreKind::SignGuard,
+            found: GUARD_FORMAT + 1,
+            supported: GUARD_FORMAT,
+        })
+    );
+    assert!(matches!(
+        SignGuard::open(&path),
+        Err(GuardError::Corrupt(_))
+    ));
+}
+
+#[test_log::test]
+fn test_gap_in_migrations_is_reported() {
+    let mut registry = MigrationRegistry::builtin();
+    let mut schema = registry.schema(StoreKind::SignGuard).unwrap().clone();
+    schema.current += 1;
+    registry.register(StoreKind::SignGuard, schema);
+
+    let path = fixture("gap", "sign_guard_v1.json");
+    assert_eq!(
+        registry.plan_file(StoreKind::SignGuard, &path),
+        Err(MigrationError::MissingStep {
+            store: StoreKind::SignGuard,
+            from: GUARD_FORMAT,
+        })
+    );
+}
@@ -0,0 +1,65 @@
+use std::time::{Duration, Instant};
+
+use hellas_morpheus::muchin_bridge::{NodeStatus, local_cluster};
+use hellas_morpheus::test_harness::{MockHarness, TestTransaction};
+use hellas_morpheus::*;
+
+#[test_log::test]
+fn test_cluster_finalizes_over_muchin_tcp() {
+    let harness = MockHarness::create_test_setup(4);
+    let processes = harness
+        .processes
+        .values()
+        .cloned()
+        .map(|mut process| {
+            // Δ in milliseconds of muchin's clock, with room for polling
+            process.delta = 100;
+            process
+        })
+        .collect();
+    let mut runner = local_cluster(processes, 19400);
+
+    let deadline = Instant::now() + Duration::from_secs(60);
+    let mut submitted = 0u8;
+    let mut last_submit = Instant::now();
+    loop {
+        assert!(!runner.step());
+        let nodes = &mut runner.state.substates;
+        if nodes
+            .iter()
+            .all(|node| node.node.process.finalized_blocks().len() > 8)
+        {
+            break;
+        }
+        assert!(Instant::now() < deadline, "no progress over muchin");
+
+        if last_submit.elapsed() > Duration::from_millis(50) {
+            last_submit = Instant::now();
+            submitted = submitted.wrapping_add(1);
+            for node in nodes.iter_mut() {
+                let id = node.node.process.id.0 as u8;
+                node.node
+                    .process
+                    .ready_transactions
+                    .push(TestTransaction(vec![id, submitted]));
+            }
+        }
+    }
+
+    let nodes = &runner.state.substates;
+    for node in nodes {
+        assert_eq!(node.node.status, NodeStatus::Running);
+        // every pair is connected, one connection each
+        assert_eq!(node.node.members.len(), 3);
+    }
+    // what each finalized is a prefix of what the others did
+    for a in nodes {
+        for b in nodes {
+            let (a, b) = (
+                a.node.process.finalized_blocks(),
+                b.node.process.finalized_blocks(),
+            );
+            assert!(a.is_subset(&b) || b.is_subset(&a));
+        }
+    }
+}
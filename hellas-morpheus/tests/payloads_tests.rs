@@ -0,0 +1,84 @@
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::test_rng;
+use hellas_morpheus::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Order {
+    market: String,
+    price: u64,
+    quantity: u32,
+}
+
+fn order() -> Order {
+    Order {
+        market: "ETH/USDC".to_string(),
+        price: 3_000,
+        quantity: 2,
+    }
+}
+
+#[test_log::test]
+fn test_cbor_payload_round_trips() {
+    let payload = CborPayload::new(&order());
+    assert_eq!(payload.decode(), order());
+
+    let mut bytes = Vec::new();
+    payload.serialize_compressed(&mut bytes).unwrap();
+    let decoded = CborPayload::<Order>::deserialize_compressed(&bytes[..]).unwrap();
+    assert_eq!(decoded, payload);
+    assert_eq!(decoded.decode(), order());
+
+    let json = serde_json::to_string(&payload).unwrap();
+    assert_eq!(
+        serde_json::from_str::<CborPayload<Order>>(&json).unwrap(),
+        payload
+    );
+}
+
+#[test_log::test]
+fn test_cbor_payload_of_wrong_shape_is_invalid() {
+    let other = CborPayload::new(&"not an order".to_string());
+    assert!(CborPayload::<Order>::from_bytes(other.as_bytes().to_vec()).is_err());
+
+    let mut bytes = Vec::new();
+    other.serialize_compressed(&mut bytes).unwrap();
+    assert!(CborPayload::<Order>::deserialize_compressed(&bytes[..]).is_err());
+    // without validation the bytes are taken as they are
+    assert!(CborPayload::<Order>::deserialize_compressed_unchecked(&bytes[..]).is_ok());
+}
+
+#[test_log::test]
+fn test_signed_transfer_verifies() {
+    let rng = &mut test_rng();
+    let global = hints::GlobalData::new(4, rng).unwrap();
+    let sender = hints::SecretKey::random(rng);
+    let receiver = hints::SecretKey::random(rng);
+
+    let transfer = Transfer {
+        from: sender.public(&global),
+        to: receiver.public(&global),
+        amount: 10,
+        nonce: 0,
+        expires_after: Some(ViewNum(5)),
+    };
+    let signed = transfer.clone().sign(&sender);
+    assert!(signed.verify(&global));
+    assert!(!signed.expired_at(ViewNum(5)));
+    assert!(signed.expired_at(ViewNum(6)));
+
+    // signed by someone other than the sender
+    assert!(!transfer.clone().sign(&receiver).verify(&global));
+
+    // altered after signing
+    let mut altered = signed.clone();
+    altered.transfer.amount = 1_000;
+    assert!(!altered.verify(&global));
+
+    let mut bytes = Vec::new();
+    signed.serialize_compressed(&mut bytes).unwrap();
+    assert_eq!(
+        SignedTransfer::deserialize_compressed(&bytes[..]).unwrap(),
+        signed
+    );
+}
@@ -0,0 +1,106 @@
+use std::collections::BTreeMap;
+
+use ark_std::test_rng;
+use hellas_morpheus::test_harness::TestTransaction;
+use hellas_morpheus::{Identity, KeyBook, MorpheusProcess, QuorumConfig, QuorumConfigError};
+
+#[test_log::test]
+fn test_uniform_committees_need_three_f_plus_one() {
+    for n in 0..=13 {
+        for f in 0..=4 {
+            assert_eq!(
+                QuorumConfig::uniform(n, f).check().is_ok(),
+                n >= 3 * f + 1,
+                "n = {n}, f = {f}"
+            );
+        }
+    }
+    assert_eq!(
+        QuorumConfig::uniform(6, 2).check(),
+        Err(QuorumConfigError::QuorumsMayNotIntersect {
+            quorum: 4,
+            total: 6,
+            f: 2,
+        })
+    );
+    assert_eq!(
+        QuorumConfig::uniform(6, 2).check().unwrap_err().to_string(),
+        "two quorums of 4 out of 6 may share only 2, fewer than the 3 needed to include an honest member with f = 2"
+    );
+}
+
+#[test_log::test]
+fn test_weighted_thresholds() {
+    let config = QuorumConfig {
+        weights: BTreeMap::from([
+            (Identity(1), 3),
+            (Identity(2), 1),
+            (Identity(3), 1),
+            (Identity(4), 1),
+        ]),
+        f: 1,
+        quorum: 4,
+        certificate: 2,
+    };
+    assert_eq!(config.total(), 6);
+    assert_eq!(config.check(), Ok(()));
+
+    let check = |change: fn(&mut QuorumConfig)| {
+        let mut config = config.clone();
+        change(&mut config);
+        config.check()
+    };
+    assert!(matches!(
+        check(|c| c.quorum = 3),
+        Err(QuorumConfigError::QuorumsMayNotIntersect { .. })
+    ));
+    assert_eq!(
+        check(|c| c.quorum = 6),
+        Err(QuorumConfigError::QuorumUnreachable {
+            quorum: 6,
+            honest: 5,
+        })
+    );
+    assert_eq!(
+        check(|c| c.certificate = 1),
+        Err(QuorumConfigError::CertificateTooSmall {
+            certificate: 1,
+            f: 1,
+        })
+    );
+    assert_eq!(
+        check(|c| {
+            c.weights.insert(Identity(5), 0);
+        }),
+        Err(QuorumConfigError::ZeroWeight(Identity(5)))
+    );
+    assert_eq!(
+        check(|c| c.weights.clear()),
+        Err(QuorumConfigError::NoMembers)
+    );
+}
+
+#[test_log::test]
+fn test_processes_refuse_unsafe_committees() {
+    let kbs = KeyBook::committee_setup(3, &mut test_rng());
+    let kb = kbs[0].clone();
+
+    let process = MorpheusProcess::<TestTransaction>::try_new(kb.clone(), Identity(1), 3, 1);
+    assert!(matches!(
+        process,
+        Err(QuorumConfigError::QuorumsMayNotIntersect { .. })
+    ));
+    let process = MorpheusProcess::<TestTransaction>::try_new(kb.clone(), Identity(1), 4, 1);
+    assert!(matches!(
+        process,
+        Err(QuorumConfigError::CommitteeSizeMismatch { n: 4, keys: 3 })
+    ));
+    assert!(MorpheusProcess::<TestTransaction>::try_new(kb, Identity(1), 3, 0).is_ok());
+}
+
+#[test_log::test]
+#[should_panic(expected = "unsafe committee")]
+fn test_new_panics_on_unsafe_committee() {
+    let kb = KeyBook::committee_setup(3, &mut test_rng()).remove(0);
+    MorpheusProcess::<TestTransaction>::new(kb, Identity(1), 3, 1);
+}
@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use hellas_morpheus::test_harness::{MockHarness, TxGenPolicy};
+use hellas_morpheus::testkit::assert_agreement;
+use hellas_morpheus::*;
+
+fn limits(votes: u32, ban_after: Option<u32>) -> RateLimits {
+    RateLimits {
+        window: 2,
+        per_kind: BTreeMap::from([(MessageKind::NewVote, votes)]),
+        ban_after,
+        ban_for: 5,
+    }
+}
+
+#[test_log::test]
+fn test_limits_reset_each_window() {
+    let mut limiter = RateLimiter::new(limits(2, None));
+    let peer = Identity(2);
+    let delta = 10;
+    for _ in 0..2 {
+        assert_eq!(
+            limiter.admit(&peer, MessageKind::NewVote, 0, delta),
+            Admission::Accepted
+        );
+    }
+    assert_eq!(
+        limiter.admit(&peer, MessageKind::NewVote, 5, delta),
+        Admission::OverLimit
+    );
+    assert_eq!(
+        limiter.admit(&peer, MessageKind::NewVote, 19, delta),
+        Admission::OverLimit
+    );
+    // other kinds and other senders aren't affected
+    assert_eq!(
+        limiter.admit(&peer, MessageKind::Block, 19, delta),
+        Admission::Accepted
+    );
+    assert_eq!(
+        limiter.admit(&Identity(3), MessageKind::NewVote, 19, delta),
+        Admission::Accepted
+    );
+
+    assert_eq!(
+        limiter.admit(&peer, MessageKind::NewVote, 20, delta),
+        Admission::Accepted
+    );
+
+    let stats = limiter.peer_stats(&peer).unwrap();
+    assert_eq!(stats.accepted, 4);
+    assert_eq!(stats.limited, 2);
+    // both drops fell in one window
+    assert_eq!(stats.violations, 1);
+    assert_eq!(stats.bans, 0);
+}
+
+#[test_log::test]
+fn test_repeat_violators_are_banned() {
+    let mut limiter = RateLimiter::new(limits(1, Some(2)));
+    let peer = Identity(2);
+    let delta = 10;
+    for window in 0..2 {
+        let now = window * 20;
+        assert_eq!(
+            limiter.admit(&peer, MessageKind::NewVote, now, delta),
+            Admission::Accepted
+        );
+        assert_eq!(
+            limiter.admit(&peer, MessageKind::NewVote, now, delta),
+            Admission::OverLimit
+        );
+    }
+    let stats = limiter.peer_stats(&peer).unwrap();
+    assert_eq!(stats.bans, 1);
+    assert_eq!(stats.banned_until, Some(20 + 5 * delta));
+
+    // banned from everything, until the ban runs out
+    assert_eq!(
+        limiter.admit(&peer, MessageKind::Block, 69, delta),
+        Admission::Banned
+    );
+    assert_eq!(
+        limiter.admit(&peer, MessageKind::NewVote, 70, delta),
+        Admission::Accepted
+    );
+    let stats = limiter.peer_stats(&peer).unwrap();
+    assert_eq!(stats.dropped_while_banned, 1);
+    assert_eq!(stats.banned_until, None);
+}
+
+#[test_log::test]
+fn test_messages_over_the_limit_are_not_recorded() {
+    let mut harness = MockHarness::create_test_setup(4);
+    let mut process = harness.processes.remove(&Identity(1)).unwrap();
+    process.set_rate_limits(Some(RateLimits {
+        window: 1,
+        per_kind: BTreeMap::from([(MessageKind::EndView, 1)]),
+        ban_after: Some(1),
+        ban_for: 10,
+    }));
+
+    let end_view = |view| {
+        let kb = &harness.processes[&Identity(2)].kb;
+        Message::EndView(Arc::new(ThreshPartial::from_data(ViewNum(view), kb)))
+    };
+    let mut to_send = Vec::new();
+    assert!(process.process_message(end_view(0), Identity(2), &mut to_send));
+    let over = end_view(1);
+    assert!(!process.process_message(over.clone(), Identity(2), &mut to_send));
+    assert!(!process.received_messages.contains(&over));
+    assert_eq!(process.banned_peers(), vec![Identity(2)]);
+    assert_eq!(process.rate_limit_stats()[&Identity(2)].limited, 1);
+
+    // our own messages are never limited
+    let ours = Message::EndView(Arc::new(ThreshPartial::from_data(ViewNum(1), &process.kb)));
+    assert!(process.process_message(ours, Identity(1), &mut to_send));
+}
+
+#[test_log::test]
+fn test_committee_limits_leave_consensus_alone() {
+    let mut harness = MockHarness::create_test_setup(4);
+    for (id, process) in harness.processes.iter_mut() {
+        process.set_rate_limits(Some(RateLimits::for_committee(4)));
+        harness
+            .tx_gen_policy
+            .insert(id.clone(), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    harness.run(2 * 3 * 5);
+
+    for process in harness.processes.values() {
+        assert!(process.finalized_head().height > 0);
+        assert!(
+            process
+                .rate_limit_stats()
+                .values()
+                .all(|stats| stats.bans == 0)
+        );
+    }
+    assert_agreement(&harness);
+}
@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use hellas_morpheus::test_harness::{MockHarness, TestTransaction, TxGenPolicy};
+use hellas_morpheus::testkit::assert_no_invariant_violations;
+use hellas_morpheus::{
+    Identity, Message, MessageKind, RECEIVED_HISTORY, ReceivedMessages, ThreshPartial, ViewNum,
+};
+
+#[test_log::test]
+fn test_history_is_bounded_but_counts_are_not() {
+    let harness = MockHarness::create_test_setup(4);
+    let kb = &harness.processes[&Identity(1)].kb;
+    let end_view = |view| -> Message<TestTransaction> {
+        Message::EndView(Arc::new(ThreshPartial::from_data(ViewNum(view), kb)))
+    };
+
+    let extra = 10;
+    let received = (0..(RECEIVED_HISTORY + extra) as i64)
+        .map(end_view)
+        .collect::<ReceivedMessages<_>>();
+    assert_eq!(received.recent().count(), RECEIVED_HISTORY);
+    assert_eq!(
+        received.count(MessageKind::EndView),
+        (RECEIVED_HISTORY + extra) as u64
+    );
+    assert_eq!(received.total(), (RECEIVED_HISTORY + extra) as u64);
+    assert_eq!(received.count(MessageKind::Block), 0);
+
+    // the oldest were forgotten, the latest weren't
+    assert!(!received.contains(&end_view(0)));
+    assert!(received.contains(&end_view((RECEIVED_HISTORY + extra - 1) as i64)));
+}
+
+#[test_log::test]
+fn test_voters_match_vote_tracker_after_a_run() {
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    harness.run(2 * 3 * 5);
+    assert_no_invariant_violations(&harness);
+
+    for process in harness.processes.values() {
+        let received = &process.received_messages;
+        assert!(!received.voters().is_empty());
+        let votes = received
+            .voters()
+            .values()
+            .map(|voters| voters.len() as u64)
+            .sum::<u64>();
+        assert_eq!(votes, received.count(MessageKind::NewVote));
+        for (data, voters) in received.voters() {
+            let tracked = &process.vote_tracker.votes[data];
+            assert!(voters.iter().all(|voter| tracked.contains_key(voter)));
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::BTreeMap;
+
+use hellas_morpheus::format::format_transaction;
+use hellas_morpheus::test_harness::TestTransaction;
+use hellas_morpheus::tracing_setup::{
+    ParseRedactionError, PayloadLogging, Redaction, in_target, set_redaction,
+};
+
+#[test_log::test]
+fn test_parse_redaction() {
+    assert_eq!("".parse::<Redaction>(), Ok(Redaction::default()));
+    assert_eq!(
+        "full, block_created = hash".parse::<Redaction>(),
+        Ok(Redaction {
+            default: PayloadLogging::Full,
+            targets: BTreeMap::from([("block_created".to_string(), PayloadLogging::HashOnly)]),
+        })
+    );
+    assert_eq!(
+        "hash,duplicate_message=verbose".parse::<Redaction>(),
+        Err(ParseRedactionError("verbose".to_string()))
+    );
+
+    let redaction: Redaction = "duplicate_message=full".parse().unwrap();
+    assert_eq!(redaction.for_target(None), PayloadLogging::HashOnly);
+    assert_eq!(
+        redaction.for_target(Some("duplicate_message")),
+        PayloadLogging::Full
+    );
+    assert_eq!(
+        redaction.for_target(Some("block_created")),
+        PayloadLogging::HashOnly
+    );
+}
+
+// the redaction is global, so everything that changes it is in this one test
+#[test_log::test]
+fn test_transactions_redacted_per_target() {
+    let tx = TestTransaction(b"secret payload".to_vec());
+
+    let hashed = format_transaction(&tx, true);
+    assert!(hashed.starts_with("Tx#"), "{}", hashed);
+    assert_eq!(hashed.len(), "Tx#".len() + 8, "{}", hashed);
+    // the hash is stable, so the same transaction can be followed through logs
+    assert_eq!(format_transaction(&tx, true), hashed);
+    assert_ne!(
+        format_transaction(&TestTransaction(b"other".to_vec()), true),
+        hashed
+    );
+
+    set_redaction("duplicate_message=full".parse().unwrap());
+    assert_eq!(format_transaction(&tx, true), hashed);
+    let full = in_target("duplicate_message", || format_transaction(&tx, true));
+    assert!(full.starts_with("Tx(TestTransaction("), "{}", full);
+    assert_eq!(
+        in_target("block_created", || format_transaction(&tx, true)),
+        hashed
+    );
+    // leaving the target restores the default
+    assert_eq!(format_transaction(&tx, true), hashed);
+
+    set_redaction("full".parse().unwrap());
+    assert_eq!(format_transaction(&tx, true), full);
+
+    set_redaction(Redaction::default());
+    assert_eq!(format_transaction(&tx, true), hashed);
+}
@@ -0,0 +1,56 @@
+use hellas_morpheus::test_harness::{MockHarness, TestTransaction};
+use hellas_morpheus::{Identity, Message, MorpheusProcess, SecretKey};
+
+#[test_log::test]
+fn test_keys_stay_out_of_debug_and_serde_output() {
+    let harness = MockHarness::create_test_setup(4);
+    let process = &harness.processes[&Identity(1)];
+    let secret = serde_json::to_string(process.kb.me_sec_key.expose().unwrap()).unwrap();
+    let election = serde_json::to_string(
+        process
+            .kb
+            .election_key
+            .as_ref()
+            .and_then(SecretKey::expose)
+            .unwrap(),
+    )
+    .unwrap();
+
+    let debug = format!("{:?}", process.kb);
+    assert!(debug.contains("SecretKey(<redacted>)"));
+    let json = serde_json::to_string(process).unwrap();
+    assert!(!json.contains(&secret));
+    assert!(!json.contains(&election));
+    let kb = serde_json::to_value(&process.kb).unwrap();
+    assert!(kb["me_sec_key"].is_null());
+}
+
+#[test_log::test]
+fn test_restored_process_signs_once_keys_are_loaded() {
+    let harness = MockHarness::create_test_setup(4);
+    let original = &harness.processes[&Identity(2)];
+    let json = serde_json::to_string(original).unwrap();
+    let restore = || -> MorpheusProcess<TestTransaction> { serde_json::from_str(&json).unwrap() };
+    let mut restored = restore();
+    assert!(!restored.kb.me_sec_key.is_present());
+    assert_eq!(restored.kb.election_key, None);
+    assert_eq!(
+        format!("{:?}", restored.kb.me_sec_key),
+        "SecretKey(<missing>)"
+    );
+
+    let produces_block = |process: &mut MorpheusProcess<TestTransaction>| {
+        process.ready_transactions = vec![TestTransaction(vec![1])];
+        let mut to_send = Vec::new();
+        process.try_produce_blocks(&mut to_send);
+        to_send
+            .iter()
+            .any(|(message, _)| matches!(message, Message::Block(_)))
+    };
+    assert!(!produces_block(&mut restored));
+
+    // the slot is used up either way, so load the key into a fresh copy
+    let mut restored = restore();
+    restored.kb.me_sec_key = original.kb.me_sec_key.clone();
+    assert!(produces_block(&mut restored));
+}
@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use hellas_morpheus::test_harness::{MockHarness, TestTransaction};
+use hellas_morpheus::{
+    Identity, Message, MessageKind, PeerBacklog, SendQueueError, SendQueues, ThreshPartial, ViewNum,
+};
+
+fn end_view(harness: &MockHarness, view: i64) -> Message<TestTransaction> {
+    Message::EndView(Arc::new(ThreshPartial::from_data(
+        ViewNum(view),
+        &harness.processes[&Identity(1)].kb,
+    )))
+}
+
+fn qc(harness: &MockHarness) -> Message<TestTransaction> {
+    Message::QC(harness.processes[&Identity(1)].genesis_qc.clone())
+}
+
+#[test_log::test]
+fn test_round_robin_across_peers() {
+    let harness = MockHarness::create_test_setup(4);
+    let mut queues = SendQueues::new(16);
+    // a slow peer with a long backlog doesn't hold up the others
+    for view in 0..5 {
+        queues
+            .enqueue(Identity(2), end_view(&harness, view))
+            .unwrap();
+    }
+    queues.enqueue(Identity(3), end_view(&harness, 0)).unwrap();
+    queues.enqueue(Identity(4), end_view(&harness, 0)).unwrap();
+
+    let order: Vec<u32> = queues
+        .drain(4)
+        .into_iter()
+        .map(|(peer, _)| peer.0)
+        .collect();
+    assert_eq!(order, [2, 3, 4, 2]);
+    assert_eq!(queues.backlog(&Identity(2)).queued, 3);
+    assert_eq!(queues.backlog(&Identity(3)).sent, 1);
+
+    queues.drain(usize::MAX);
+    assert!(queues.is_empty());
+    assert_eq!(queues.backlog(&Identity(2)).high_water, 5);
+}
+
+#[test_log::test]
+fn test_full_queue_drops_oldest_non_critical() {
+    let harness = MockHarness::create_test_setup(4);
+    let mut queues = SendQueues::new(3);
+    let peer = Identity(2);
+    queues.enqueue(peer.clone(), qc(&harness)).unwrap();
+    queues.enqueue(peer.clone(), end_view(&harness, 1)).unwrap();
+    queues.enqueue(peer.clone(), end_view(&harness, 2)).unwrap();
+
+    let dropped = queues.enqueue(peer.clone(), qc(&harness)).unwrap();
+    assert_eq!(dropped, Some(end_view(&harness, 1)));
+    let dropped = queues.enqueue(peer.clone(), end_view(&harness, 3)).unwrap();
+    assert_eq!(dropped, Some(end_view(&harness, 2)));
+    assert_eq!(
+        queues.backlog(&peer),
+        PeerBacklog {
+            queued: 3,
+            critical: 2,
+            high_water: 3,
+            sent: 0,
+            dropped: 2,
+        }
+    );
+
+    // with only critical messages left to keep, newcomers are dropped or refused
+    queues.enqueue(peer.clone(), qc(&harness)).unwrap();
+    let dropped = queues.enqueue(peer.clone(), end_view(&harness, 4)).unwrap();
+    assert_eq!(dropped, Some(end_view(&harness, 4)));
+    assert_eq!(
+        queues.enqueue(peer.clone(), qc(&harness)),
+        Err(SendQueueError::Full {
+            peer: peer.clone(),
+            kind: MessageKind::QC,
+        })
+    );
+
+    let kinds: Vec<MessageKind> = queues
+        .drain(usize::MAX)
+        .into_iter()
+        .map(|(_, message)| message.kind())
+        .collect();
+    assert_eq!(kinds, [MessageKind::QC; 3]);
+    assert_eq!(queues.backlog(&peer).critical, 0);
+}
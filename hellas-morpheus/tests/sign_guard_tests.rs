@@ -0,0 +1,190 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use hellas_morpheus::test_harness::{MockHarness, TestTransaction, TxGenPolicy};
+use hellas_morpheus::testkit::{assert_agreement, assert_no_invariant_violations};
+use hellas_morpheus::{
+    BlockType, FaultyStore, GUARD_WINDOW, GuardError, Identity, MemoryStore, Message, SignGuard,
+    SignPosition, SignStream, StorageFaults,
+};
+
+fn state_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "morpheus-guard-{}-{}.json",
+        name,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn vote_at(slot: i64) -> SignPosition {
+    SignPosition {
+        stream: SignStream::Vote {
+            z: 1,
+            type_: BlockType::Tr,
+            author: Identity(2),
+        },
+        at: slot,
+    }
+}
+
+#[test_log::test]
+fn test_sign_guard_survives_reopening() {
+    let path = state_path("reopen");
+    let mut guard = SignGuard::open(&path).unwrap();
+    guard.check(&vote_at(3), b"a").unwrap();
+    guard.check(&vote_at(3), b"a").unwrap();
+    assert_eq!(
+        guard.check(&vote_at(3), b"b"),
+        Err(GuardError::Conflict(vote_at(3)))
+    );
+
+    // while one guard has the state open, nobody else may use it
+    assert!(matches!(SignGuard::open(&path), Err(GuardError::Locked(_))));
+    drop(guard);
+
+    let mut guard = SignGuard::open(&path).unwrap();
+    guard.check(&vote_at(3), b"a").unwrap();
+    assert!(guard.check(&vote_at(3), b"b").is_err());
+    guard.check(&vote_at(2), b"b").unwrap();
+}
+
+#[test_log::test]
+fn test_sign_guard_floor() {
+    let path = state_path("floor");
+    let mut guard = SignGuard::open(&path).unwrap();
+    for slot in 0..=GUARD_WINDOW as i64 {
+        guard.check(&vote_at(slot), b"a").unwrap();
+    }
+    assert_eq!(
+        guard.check(&vote_at(0), b"a"),
+        Err(GuardError::BelowFloor(vote_at(0)))
+    );
+    guard.check(&vote_at(1), b"a").unwrap();
+}
+
+#[test_log::test]
+fn test_restarted_process_does_not_resign_a_slot() {
+    let path = state_path("restart");
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    let mut restarted = harness.processes[&Identity(1)].clone();
+    harness.processes.get_mut(&Identity(1)).unwrap().sign_guard =
+        Some(Arc::new(Mutex::new(SignGuard::open(&path).unwrap())));
+    harness.run(30);
+    assert!(
+        harness.processes[&Identity(2)]
+            .index
+            .finalized
+            .iter()
+            .any(|key| key.author == Some(Identity(1)))
+    );
+    drop(harness);
+
+    // coming back without its state, the process would build a different block at slot 0
+    restarted.sign_guard = Some(Arc::new(Mutex::new(SignGuard::open(&path).unwrap())));
+    restarted.ready_transactions = vec![TestTransaction(vec![0xff])];
+    let mut to_send = Vec::new();
+    restarted.try_produce_blocks(&mut to_send);
+    assert!(
+        !to_send
+            .iter()
+            .any(|(message, _)| matches!(message, Message::Block(_)))
+    );
+}
+
+#[test_log::test]
+fn test_failed_writes_never_allow_a_second_signature() {
+    let memory = MemoryStore::default();
+    let store = FaultyStore::new(
+        memory.clone(),
+        StorageFaults {
+            write_failures: 30,
+            sync_delay: Duration::from_millis(1),
+            ..Default::default()
+        },
+    );
+    let counts = store.counts();
+    let mut guard = SignGuard::with_store(store).unwrap();
+
+    let mut allowed = Vec::new();
+    for slot in 0..40 {
+        let message = [slot as u8];
+        match guard.check(&vote_at(slot), &message) {
+            Ok(()) => allowed.push(slot),
+            // not recorded, and so not to be signed
+            Err(GuardError::Io(_)) => {}
+            Err(e) => panic!("unexpected {e}"),
+        }
+    }
+    assert!(counts.lock().unwrap().failed_writes > 0);
+    assert!(!allowed.is_empty());
+    drop(guard);
+
+    // whatever was allowed made it to storage before the signature was given
+    let mut reopened = SignGuard::with_store(memory).unwrap();
+    for slot in allowed {
+        assert_eq!(
+            reopened.check(&vote_at(slot), b"other"),
+            Err(GuardError::Conflict(vote_at(slot)))
+        );
+    }
+}
+
+#[test_log::test]
+fn test_corrupt_state_is_refused() {
+    let memory = MemoryStore::default();
+    let mut guard = SignGuard::with_store(memory.clone()).unwrap();
+    for slot in 0..5 {
+        guard.check(&vote_at(slot), b"a").unwrap();
+    }
+    drop(guard);
+
+    for seed in 0..16 {
+        let store = FaultyStore::new(
+            memory.clone(),
+            StorageFaults {
+                corrupt_reads: 100,
+                seed,
+                ..Default::default()
+            },
+        );
+        let counts = store.counts();
+        assert!(matches!(
+            SignGuard::with_store(store),
+            Err(GuardError::Corrupt(_))
+        ));
+        assert_eq!(counts.lock().unwrap().corrupted_reads, 1);
+    }
+    SignGuard::with_store(memory).unwrap();
+}
+
+#[test_log::test]
+fn test_processes_with_failing_storage_stay_safe() {
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+        let store = FaultyStore::new(
+            MemoryStore::default(),
+            StorageFaults {
+                write_failures: 20,
+                seed: i as u64,
+                ..Default::default()
+            },
+        );
+        harness.processes.get_mut(&Identity(i)).unwrap().sign_guard =
+            Some(Arc::new(Mutex::new(SignGuard::with_store(store).unwrap())));
+    }
+    harness.run(60);
+
+    assert_agreement(&harness);
+    assert_no_invariant_violations(&harness);
+}
@@ -0,0 +1,191 @@
+#![cfg(unix)]
+
+use std::os::unix::net::UnixListener;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use hellas_morpheus::test_harness::{MockHarness, TxGenPolicy};
+use hellas_morpheus::{
+    BlockKey, BlockType, Identity, LocalSigner, RemoteSigner, SecretKey, SignError, Signer,
+    SignerService, SlotNum, ViewNum, VoteKey, WorkerSigner,
+};
+
+fn socket_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("morpheus-{}-{}.sock", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn spawn_service(path: &PathBuf, key: SecretKey, token: &[u8]) {
+    let listener = UnixListener::bind(path).unwrap();
+    let mut service = SignerService::new(key, token);
+    std::thread::spawn(move || service.serve(&listener));
+}
+
+#[test_log::test]
+fn test_remote_signer_drives_a_process() {
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+
+    let path = socket_path("drives");
+    let process = harness.processes.get_mut(&Identity(1)).unwrap();
+    let key = process.kb.me_sec_key.clone();
+    spawn_service(&path, key.clone(), b"token");
+    let signer = RemoteSigner::new(&path, b"token".to_vec());
+    assert_eq!(
+        signer.sign(b"hello").unwrap(),
+        hints::sign(key.expose().unwrap(), b"hello")
+    );
+    process.signer = Some(Arc::new(signer));
+
+    harness.run(40);
+
+    // the other processes accepted what process 1 signed remotely
+    let finalized_by_1 = harness.processes[&Identity(2)]
+        .index
+        .finalized
+        .iter()
+        .filter(|key| key.author == Some(Identity(1)))
+        .count();
+    assert!(finalized_by_1 > 0);
+}
+
+#[test_log::test]
+fn test_remote_signer_rejects_wrong_token() {
+    let path = socket_path("token");
+    let harness = MockHarness::create_test_setup(4);
+    spawn_service(
+        &path,
+        harness.processes[&Identity(1)].kb.me_sec_key.clone(),
+        b"token",
+    );
+
+    // the service drops requests it can't authenticate without answering
+    let mut signer = RemoteSigner::new(&path, b"guess".to_vec());
+    signer.attempts = 1;
+    assert!(matches!(signer.sign(b"hello"), Err(SignError::Io(_))));
+}
+
+#[test_log::test]
+fn test_remote_signer_times_out() {
+    let path = socket_path("timeout");
+    let listener = UnixListener::bind(&path).unwrap();
+    std::thread::spawn(move || {
+        // accept and hold connections, never answering
+        let streams: Vec<_> = listener.incoming().collect();
+        drop(streams);
+    });
+
+    let mut signer = RemoteSigner::new(&path, b"token".to_vec());
+    signer.timeout = Duration::from_millis(50);
+    signer.attempts = 2;
+    assert_eq!(signer.sign(b"hello"), Err(SignError::Timeout));
+}
+
+#[test_log::test]
+fn test_failed_signature_never_resigns() {
+    let mut harness = MockHarness::create_test_setup(4);
+    let process = harness.processes.get_mut(&Identity(1)).unwrap();
+    let mut signer = RemoteSigner::new(socket_path("missing"), b"token".to_vec());
+    signer.attempts = 1;
+    process.signer = Some(Arc::new(signer));
+
+    let block = BlockKey {
+        type_: BlockType::Tr,
+        view: ViewNum(0),
+        height: 1,
+        author: Some(Identity(2)),
+        slot: SlotNum(0),
+        hash: None,
+    };
+
+    let mut to_send = Vec::new();
+    assert!(!process.try_vote(0, &block, None, &mut to_send));
+    assert!(to_send.is_empty());
+    assert!(
+        process
+            .voted_i
+            .contains(&VoteKey::for_block(0, &block).unwrap())
+    );
+
+    // the vote may have been signed before the failure, so it is never retried
+    assert!(!process.try_vote(0, &block, None, &mut to_send));
+    assert!(to_send.is_empty());
+
+    assert!(!process.force_end_view(&mut to_send));
+    assert!(to_send.is_empty());
+}
+
+/// A backend that never answers, like an HSM whose library hangs
+struct Stuck;
+
+impl Signer for Stuck {
+    fn sign(&self, _: &[u8]) -> Result<hints::PartialSignature, SignError> {
+        std::thread::sleep(Duration::from_secs(3600));
+        unreachable!()
+    }
+}
+
+#[test_log::test]
+fn test_worker_signer_deadline() {
+    let harness = MockHarness::create_test_setup(4);
+    let key = harness.processes[&Identity(1)].kb.me_sec_key.clone();
+    let local = WorkerSigner::new(
+        Arc::new(LocalSigner(key.clone())),
+        Duration::from_secs(5),
+        4,
+    );
+    assert_eq!(
+        local.sign(b"hello").unwrap(),
+        hints::sign(key.expose().unwrap(), b"hello")
+    );
+
+    let stuck = WorkerSigner::new(Arc::new(Stuck), Duration::from_millis(20), 0);
+    assert_eq!(stuck.sign(b"hello"), Err(SignError::Timeout));
+    // the worker is still stuck on the first request, so this fails at once
+    let started = std::time::Instant::now();
+    assert_eq!(stuck.sign(b"again"), Err(SignError::Timeout));
+    assert!(started.elapsed() < Duration::from_secs(1));
+}
+
+#[test_log::test]
+fn test_stuck_signer_stops_block_production() {
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    let process = harness.processes.get_mut(&Identity(1)).unwrap();
+    process.signer = Some(Arc::new(WorkerSigner::new(
+        Arc::new(Stuck),
+        Duration::from_millis(20),
+        0,
+    )));
+    harness.run(40);
+
+    // process 1 never gets a signature, so nothing it authored goes out unsigned
+    // or otherwise; the rest of the committee carries on without it
+    for id in [Identity(2), Identity(3), Identity(4)] {
+        let process = &harness.processes[&id];
+        assert!(
+            process
+                .index
+                .blocks
+                .keys()
+                .all(|key| key.author != Some(Identity(1)))
+        );
+        assert!(
+            process
+                .index
+                .finalized
+                .iter()
+                .any(|key| key.type_ == BlockType::Tr)
+        );
+    }
+}
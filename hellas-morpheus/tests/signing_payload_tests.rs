@@ -0,0 +1,94 @@
+use std::collections::BTreeSet;
+
+use ark_serialize::CanonicalSerialize;
+use std::sync::Arc;
+
+use hellas_morpheus::test_harness::{MockHarness, TestTransaction, TxGenPolicy};
+use hellas_morpheus::{
+    Block, BlockType, BlockValidationError, ChainId, DEFAULT_CHAIN_NAME, Identity, KeyRotation,
+    Message, Signed, SigningPayload, StartView, ThreshPartial, ViewNum, VoteData, signing_bytes,
+};
+
+#[test_log::test]
+fn test_payload_domains_are_distinct() {
+    let domains = [
+        VoteData::DOMAIN,
+        Block::<TestTransaction>::DOMAIN,
+        ViewNum::DOMAIN,
+        StartView::DOMAIN,
+        KeyRotation::DOMAIN,
+    ];
+    assert_eq!(domains.iter().collect::<BTreeSet<_>>().len(), domains.len());
+
+    let bytes = signing_bytes(&ChainId::default(), &ViewNum(7));
+    let mut encoding = Vec::new();
+    ViewNum(7).serialize_compressed(&mut encoding).unwrap();
+    assert!(bytes.ends_with(&encoding));
+    assert_eq!(bytes[0] as usize, ViewNum::DOMAIN.len());
+    assert_eq!(&bytes[1..1 + ViewNum::DOMAIN.len()], ViewNum::DOMAIN);
+}
+
+#[test_log::test]
+fn test_signature_without_domain_is_rejected() {
+    let harness = MockHarness::create_test_setup(4);
+    let kb = &harness.processes[&Identity(1)].kb;
+    let end_view = ThreshPartial::from_data(ViewNum(7), kb);
+    assert!(end_view.valid_signature(kb));
+
+    // a signature over the bare encoding, as any other payload that happened
+    // to encode the same way would carry
+    let mut encoding = Vec::new();
+    ViewNum(7).serialize_compressed(&mut encoding).unwrap();
+    let replayed = ThreshPartial {
+        signature: hints::sign(kb.me_sec_key.expose().unwrap(), &encoding),
+        ..end_view.clone()
+    };
+    assert!(!replayed.valid_signature(kb));
+
+    // nor does one made under another payload's domain
+    let mut other_domain = vec![VoteData::DOMAIN.len() as u8];
+    other_domain.extend_from_slice(VoteData::DOMAIN);
+    other_domain.extend_from_slice(&kb.chain_id.0);
+    other_domain.extend_from_slice(&encoding);
+    let replayed = ThreshPartial {
+        signature: hints::sign(kb.me_sec_key.expose().unwrap(), &other_domain),
+        ..end_view
+    };
+    assert!(!replayed.valid_signature(kb));
+}
+
+#[test_log::test]
+fn test_messages_from_another_chain_are_rejected() {
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    harness.run(20);
+    let kb = harness.processes[&Identity(1)].kb.clone();
+    assert_eq!(kb.chain_id, ChainId::genesis(DEFAULT_CHAIN_NAME, &kb.keys));
+
+    // the same members, running a testnet
+    let testnet = kb.clone().on_chain("testnet");
+    assert_ne!(testnet.chain_id, kb.chain_id);
+
+    let p2 = harness.processes.get_mut(&Identity(2)).unwrap();
+    let block = p2
+        .index
+        .blocks
+        .values()
+        .find(|block| block.data.key.type_ == BlockType::Tr && block.author == Identity(1))
+        .expect("p2 has a transaction block by p1")
+        .clone();
+    assert_eq!(p2.block_valid(&block), Ok(()));
+    assert_eq!(
+        p2.block_valid(&Signed::from_data(block.data.clone(), &testnet)),
+        Err(BlockValidationError::InvalidSignature)
+    );
+
+    let view = p2.view_i.incr();
+    let end_view = |kb| Message::EndView(Arc::new(ThreshPartial::from_data(view, kb)));
+    assert!(!p2.process_message(end_view(&testnet), Identity(1), &mut Vec::new()));
+    assert!(p2.process_message(end_view(&kb), Identity(1), &mut Vec::new()));
+}
@@ -298,7 +298,7 @@ fn test_pending_votes_invariants() {
         height: 100,
         author: Some(Identity(1)),
         slot: SlotNum(5),
-        hash: Some(BlockHash(0x12345678)),
+        hash: Some(BlockHash([0x12; 32])),
     };
 
     // Add to pending votes
@@ -336,7 +336,7 @@ fn test_pending_votes_invariants() {
         height: 1,
         author: Some(Identity(1)),
         slot: SlotNum(1),
-        hash: Some(BlockHash(0xABCDEF)),
+        hash: Some(BlockHash([0xab; 32])),
     };
 
     // Generate a QC for the genesis block
@@ -349,6 +349,7 @@ fn test_pending_votes_invariants() {
     let gen_qc = Arc::new(ThreshSigned {
         data: gen_vote_data,
         signature: hints::Signature::default(),
+        signers: SignerBitmap::default(),
     });
 
     // Add this block to the process's state using proper constructors
@@ -357,9 +358,7 @@ fn test_pending_votes_invariants() {
             key: block_key.clone(),
             prev: vec![],
             one: gen_qc,
-            data: BlockData::Tr {
-                transactions: vec![],
-            },
+            data: BlockData::tr(vec![]),
         },
         &process.kb,
     );
@@ -0,0 +1,99 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use hellas_morpheus::test_harness::{MockHarness, TestTransaction, TxGenPolicy};
+use hellas_morpheus::testkit::assert_agreement;
+use hellas_morpheus::*;
+
+/// StartViews process 4 signs for every view up to `views` past the one it's
+/// in, two for each, told apart by the progress they claim and `round`
+fn flood(harness: &MockHarness, views: i64, round: i64) -> Vec<Message<TestTransaction>> {
+    let attacker = &harness.processes[&Identity(4)];
+    let mut messages = Vec::new();
+    for view in attacker.view_i.0..=attacker.view_i.0 + views {
+        for max_view in [view + 2 * round, view + 2 * round + 1] {
+            let start_view = StartView {
+                view: ViewNum(view),
+                qc: attacker.index.max_1qc.clone(),
+                progress: Some(ProgressReport {
+                    max_view: ViewNum(max_view),
+                    finalized_head: attacker.finalized_head(),
+                }),
+            };
+            messages.push(Message::StartView(Arc::new(Signed::from_data(
+                start_view,
+                &attacker.kb,
+            ))));
+        }
+    }
+    messages
+}
+
+#[test_log::test]
+fn test_start_view_flood_keeps_memory_bounded() {
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+
+    for step in 0..60 {
+        if step % 5 == 0 {
+            for message in flood(&harness, 2 * START_VIEW_WINDOW, step) {
+                harness.enqueue_message(message, Identity(4), None);
+            }
+        }
+        harness.step();
+    }
+
+    let bound = harness.processes.len() * (START_VIEW_WINDOW as usize + 1);
+    for (id, process) in &harness.processes {
+        let held = process.start_views.values().map(Vec::len).sum::<usize>();
+        assert!(held <= bound, "{id:?} holds {held} StartViews");
+        for (view, start_views) in &process.start_views {
+            assert!(*view >= process.view_i);
+            assert!(view.0 <= process.view_i.0 + START_VIEW_WINDOW);
+            let authors = start_views
+                .iter()
+                .map(|sv| sv.author.clone())
+                .collect::<BTreeSet<_>>();
+            assert_eq!(authors.len(), start_views.len(), "{id:?} in {view:?}");
+        }
+    }
+
+    // and consensus carried on regardless
+    assert!(
+        harness
+            .processes
+            .values()
+            .all(|process| process.finalized_head().height > 0)
+    );
+    assert_agreement(&harness);
+}
+
+#[test_log::test]
+fn test_start_views_are_dropped_on_leaving_a_view() {
+    let mut harness = MockHarness::create_test_setup(4);
+    let mut process = harness.processes.remove(&Identity(1)).unwrap();
+    let mut to_send = Vec::new();
+    for message in flood(&harness, 3, 0) {
+        process.process_message(message, Identity(4), &mut to_send);
+    }
+    assert_eq!(process.start_views.len(), 4);
+
+    // once past view 1, the StartViews for views 0 and 1 are of no use
+    for id in 2..=3 {
+        let end_view = ThreshPartial::from_data(ViewNum(1), &harness.processes[&Identity(id)].kb);
+        process.process_message(
+            Message::EndView(Arc::new(end_view)),
+            Identity(id),
+            &mut to_send,
+        );
+    }
+    assert_eq!(process.view_i, ViewNum(2));
+    assert_eq!(
+        process.start_views.keys().copied().collect::<Vec<_>>(),
+        vec![ViewNum(2), ViewNum(3)]
+    );
+}
@@ -0,0 +1,80 @@
+use hellas_morpheus::config::{NetworkConfig, SimulationConfig, SimulationConfigError};
+use hellas_morpheus::sweep::{Scenario, SweepMatrix};
+use hellas_morpheus::testkit::assert_agreement;
+
+fn small_matrix() -> SweepMatrix {
+    SweepMatrix {
+        nodes: vec![4],
+        deltas: vec![100, 200],
+        loss_rates: vec![0.0, 0.1],
+        tx_every: vec![2],
+        steps: 2 * 3 * 5,
+        ..SweepMatrix::default()
+    }
+}
+
+#[test_log::test]
+fn test_every_combination_is_a_scenario() {
+    let scenarios = small_matrix().scenarios();
+    assert_eq!(scenarios.len(), 4);
+    assert_eq!(
+        scenarios[1],
+        Scenario {
+            nodes: 4,
+            delta: 100,
+            loss_rate: 0.1,
+            tx_every: 2,
+        }
+    );
+
+    let config = small_matrix().config(&scenarios[3]);
+    assert_eq!(config.delta, 200);
+    assert_eq!(config.network.loss_rate, 0.1);
+}
+
+#[test_log::test]
+fn test_sweep_is_deterministic_across_threads() {
+    let matrix = small_matrix();
+    let serial = matrix.run(1).unwrap();
+    let parallel = matrix.run(4).unwrap();
+    assert_eq!(serial, parallel);
+
+    let scenarios = matrix.scenarios();
+    for (stats, scenario) in serial.results.iter().zip(&scenarios) {
+        assert_eq!(&stats.scenario, scenario);
+        assert_eq!(stats.conflicts, 0);
+    }
+    // without loss the cluster makes progress
+    assert!(serial.results[0].finalized > 0);
+    assert!(serial.results[0].messages > 0);
+
+    let table = serial.table();
+    assert_eq!(table.lines().count(), 1 + scenarios.len());
+    assert!(table.lines().next().unwrap().contains("finalized"));
+}
+
+#[test_log::test]
+fn test_bad_scenarios_fail_the_sweep() {
+    let matrix = SweepMatrix {
+        loss_rates: vec![0.0, 2.0],
+        steps: 1,
+        ..SweepMatrix::default()
+    };
+    assert_eq!(matrix.run(2), Err(SimulationConfigError::InvalidLossRate));
+}
+
+#[test_log::test]
+fn test_lossy_network_still_agrees() {
+    let mut harness = SimulationConfig {
+        network: NetworkConfig {
+            loss_rate: 0.05,
+            ..NetworkConfig::default()
+        },
+        ..SimulationConfig::default()
+    }
+    .build()
+    .unwrap();
+    assert!(harness.faults.loss.is_some());
+    harness.run(100);
+    assert_agreement(&harness);
+}
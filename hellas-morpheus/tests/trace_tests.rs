@@ -0,0 +1,63 @@
+use hellas_morpheus::Message;
+use hellas_morpheus::test_harness::{MockHarness, TestTransaction, TxGenPolicy};
+use hellas_morpheus::trace::{ExecutionTrace, ReplayDivergence, TraceInput, replay};
+
+fn recorded_run() -> ExecutionTrace<TestTransaction> {
+    let mut harness = MockHarness::create_test_setup(3);
+    for id in harness.processes.keys().cloned().collect::<Vec<_>>() {
+        harness
+            .tx_gen_policy
+            .insert(id, TxGenPolicy::EveryNSteps { n: 3 });
+    }
+    harness.start_trace();
+    harness.run(20);
+
+    let trace = harness.trace.take().unwrap();
+    assert!(trace.entries.iter().any(|e| !e.finalized.is_empty()));
+    trace
+}
+
+#[test_log::test]
+fn test_trace_replays_after_json_round_trip() {
+    let trace = recorded_run();
+
+    let json = serde_json::to_string(&trace).unwrap();
+    let loaded: ExecutionTrace<TestTransaction> = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded.entries, trace.entries);
+
+    let report = replay(&loaded);
+    assert!(report.is_compatible(), "{:?}", report.divergences);
+    assert_eq!(report.entries_replayed, trace.entries.len());
+}
+
+#[test_log::test]
+fn test_replay_reports_first_divergence() {
+    let mut trace = recorded_run();
+
+    let tampered = trace
+        .entries
+        .iter()
+        .position(|e| {
+            matches!(e.input, TraceInput::Message { .. })
+                && e.outputs
+                    .iter()
+                    .any(|(m, _)| matches!(m, Message::NewVote(_)))
+        })
+        .unwrap();
+    let process = trace.entries[tampered].process.clone();
+    trace.entries[tampered].outputs.clear();
+
+    let report = replay(&trace);
+    assert_eq!(report.divergences.len(), 1);
+    match &report.divergences[0] {
+        ReplayDivergence::Messages {
+            entry, recorded, ..
+        } => {
+            assert_eq!(*entry, tampered);
+            assert!(recorded.is_empty());
+        }
+        other => panic!("unexpected divergence {:?}", other),
+    }
+    assert_eq!(report.divergences[0].process(), &process);
+    assert!(report.entries_replayed < trace.entries.len());
+}
@@ -0,0 +1,70 @@
+use std::collections::BTreeSet;
+
+use hellas_morpheus::MessageKind;
+use hellas_morpheus::transitions::{TRANSITIONS, to_markdown, transition, transitions_for};
+
+const HANDLER_SOURCE: &str = include_str!("../src/message_handling.rs");
+
+fn annotated_ids() -> Vec<&'static str> {
+    HANDLER_SOURCE
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("// transition: "))
+        .map(str::trim)
+        .collect()
+}
+
+#[test_log::test]
+fn test_annotations_match_table() {
+    let annotated = annotated_ids();
+    let annotated_set: BTreeSet<_> = annotated.iter().copied().collect();
+    assert_eq!(
+        annotated.len(),
+        annotated_set.len(),
+        "a transition is annotated twice"
+    );
+
+    let table: BTreeSet<_> = TRANSITIONS.iter().map(|t| t.id).collect();
+    assert_eq!(
+        table.len(),
+        TRANSITIONS.len(),
+        "duplicate id in TRANSITIONS"
+    );
+
+    let missing: Vec<_> = annotated_set.difference(&table).collect();
+    assert!(
+        missing.is_empty(),
+        "annotated but not in TRANSITIONS: {missing:?}"
+    );
+    let unannotated: Vec<_> = table.difference(&annotated_set).collect();
+    assert!(
+        unannotated.is_empty(),
+        "in TRANSITIONS but not annotated in message_handling.rs: {unannotated:?}"
+    );
+}
+
+#[test_log::test]
+fn test_every_message_kind_has_transitions() {
+    for kind in MessageKind::ALL {
+        let transitions: Vec<_> = transitions_for(kind).collect();
+        assert!(!transitions.is_empty(), "{kind:?} has no transitions");
+        // the handler records what it received, whatever happens next,
+        // unless it was dropped before being looked at
+        for t in transitions.iter().filter(|t| t.message.is_some()) {
+            assert!(t.updates.contains(&"received_messages"), "{}", t.id);
+        }
+        assert!(transitions.iter().any(|t| t.id == "rate-limited"));
+    }
+
+    assert_eq!(
+        transition("block-accepted").unwrap().message,
+        Some(MessageKind::Block)
+    );
+    let limited = transition("rate-limited").unwrap();
+    assert!(!limited.updates.contains(&"received_messages"));
+    assert!(transition("no-such-transition").is_none());
+
+    let markdown = to_markdown();
+    assert_eq!(markdown.lines().count(), TRANSITIONS.len() + 2);
+    assert!(markdown.contains("`qc-advances-view`"));
+    assert!(markdown.contains("| `rate-limited` | any |"));
+}
@@ -0,0 +1,96 @@
+use std::collections::VecDeque;
+
+use hellas_morpheus::test_harness::{MockHarness, TestTransaction, TxGenPolicy};
+use hellas_morpheus::transport::{NetworkTransport, flush};
+use hellas_morpheus::*;
+
+/// Records what it is given, and hands out a fixed inbox
+#[derive(Default)]
+struct Recorder {
+    sent: Vec<(Option<Identity>, Message<TestTransaction>)>,
+    inbox: VecDeque<(Identity, Message<TestTransaction>)>,
+}
+
+impl NetworkTransport<TestTransaction> for Recorder {
+    fn send(&mut self, to: &Identity, message: Message<TestTransaction>) {
+        self.sent.push((Some(to.clone()), message));
+    }
+
+    fn broadcast(&mut self, message: Message<TestTransaction>) {
+        self.sent.push((None, message));
+    }
+
+    fn receive(&mut self) -> Option<(Identity, Message<TestTransaction>)> {
+        self.inbox.pop_front()
+    }
+}
+
+#[test_log::test]
+fn test_flush_routes_by_destination() {
+    let harness = MockHarness::create_test_setup(4);
+    let kb = &harness.processes[&Identity(1)].kb;
+    let end_view = Message::EndView(std::sync::Arc::new(ThreshPartial::from_data(
+        ViewNum(0),
+        kb,
+    )));
+
+    let mut transport = Recorder::default();
+    let mut to_send = vec![
+        (end_view.clone(), None),
+        (end_view.clone(), Some(Identity(3))),
+    ];
+    flush(&mut transport, &mut to_send);
+    assert!(to_send.is_empty());
+    assert_eq!(
+        transport.sent,
+        vec![(None, end_view.clone()), (Some(Identity(3)), end_view)]
+    );
+}
+
+#[test_log::test]
+fn test_attached_process_keeps_up_with_harness() {
+    let mut harness = MockHarness::create_test_setup(4);
+    for id in harness.processes.keys().cloned().collect::<Vec<_>>() {
+        harness
+            .tx_gen_policy
+            .insert(id, TxGenPolicy::EveryNSteps { n: 3 });
+    }
+
+    // p4 runs outside the harness, talking to it only through the transport
+    let mut outside = harness.processes[&Identity(4)].clone();
+    harness.attach(Identity(4));
+    assert!(!harness.processes.contains_key(&Identity(4)));
+
+    for step in 0..30 {
+        harness.step();
+        outside.poll_transport(&mut harness.transport(Identity(4)));
+        if step % 3 == 0 {
+            outside
+                .ready_transactions
+                .push(TestTransaction(vec![4, step as u8]));
+        }
+        let now = harness.time;
+        outside.tick_transport(now, &mut harness.transport(Identity(4)));
+    }
+
+    // the others finalize blocks p4 authored, and p4 finalizes along with them
+    assert!(
+        harness.processes[&Identity(1)]
+            .finalized_blocks()
+            .iter()
+            .any(|key| key.author == Some(Identity(4)))
+    );
+    let finalized = outside.finalized_blocks();
+    assert!(finalized.len() > 1);
+    for process in harness.processes.values() {
+        for key in process.finalized_blocks() {
+            assert!(
+                !finalized
+                    .iter()
+                    .any(|mine| SlotKey::from(mine) == SlotKey::from(&key) && *mine != key),
+                "{:?} conflicts with what p4 finalized",
+                key
+            );
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use std::sync::Arc;
+
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::test_rng;
+use hellas_morpheus::{
+    Block, BlockData, BlockKey, BlockType, BlockValidationError, Identity, KeyBook, Message,
+    MorpheusProcess, Signed, SlotNum, Transaction, ViewNum,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+    Serialize,
+    Deserialize,
+)]
+struct ExpiringTx {
+    payload: u64,
+    expires_after: Option<ViewNum>,
+}
+
+impl Transaction for ExpiringTx {
+    fn expires_after(&self) -> Option<ViewNum> {
+        self.expires_after
+    }
+}
+
+fn tx(payload: u64, expires_after: Option<i64>) -> ExpiringTx {
+    ExpiringTx {
+        payload,
+        expires_after: expires_after.map(ViewNum),
+    }
+}
+
+fn committee() -> Vec<MorpheusProcess<ExpiringTx>> {
+    KeyBook::committee_setup(4, &mut test_rng())
+        .into_iter()
+        .enumerate()
+        .map(|(i, kb)| MorpheusProcess::new(kb, Identity(i as u32 + 1), 4, 1))
+        .collect()
+}
+
+#[test_log::test]
+fn test_expired_transactions_are_evicted() {
+    let mut process = committee().remove(0);
+    assert_eq!(process.view_i, ViewNum(0));
+    process.submit(tx(1, Some(-1))).unwrap();
+    process.submit(tx(2, Some(0))).unwrap();
+    process.submit(tx(3, None)).unwrap();
+
+    assert_eq!(process.evict_expired(), 1);
+    assert_eq!(
+        process.ready_transactions,
+        vec![tx(2, Some(0)), tx(3, None)]
+    );
+
+    // only what hasn't expired goes into our next block
+    let mut to_send = Vec::new();
+    process.try_produce_blocks(&mut to_send);
+    let block = to_send
+        .iter()
+        .find_map(|(message, _)| match message {
+            Message::Block(block) => Some(block.clone()),
+            _ => None,
+        })
+        .expect("a transaction block");
+    assert_eq!(
+        block.data.data,
+        BlockData::tr(vec![tx(2, Some(0)), tx(3, None)])
+    );
+    assert!(process.ready_transactions.is_empty());
+
+    process.submit(tx(4, Some(0))).unwrap();
+    process.view_i = ViewNum(1);
+    assert_eq!(process.evict_expired(), 1);
+    assert!(process.ready_transactions.is_empty());
+}
+
+#[test_log::test]
+fn test_blocks_with_expired_transactions_are_rejected() {
+    let processes = committee();
+    let validator = &processes[0];
+    let author = &processes[1];
+
+    let block_in = |view: i64| {
+        let block = Block {
+            key: BlockKey {
+                type_: BlockType::Tr,
+                view: ViewNum(view),
+                height: 1,
+                author: Some(Identity(2)),
+                slot: SlotNum(0),
+                hash: None,
+            },
+            prev: vec![validator.genesis_qc.clone()],
+            one: validator.genesis_qc.clone(),
+            data: BlockData::tr(vec![tx(1, None), tx(2, Some(1))]),
+        };
+        Arc::new(Signed::from_data(block.hashed(), &author.kb))
+    };
+
+    assert_eq!(validator.block_valid(&block_in(1)), Ok(()));
+    assert_eq!(
+        validator.block_valid(&block_in(2)),
+        Err(BlockValidationError::ExpiredTransaction {
+            index: 1,
+            expired_after: ViewNum(1),
+            block_view: ViewNum(2),
+        })
+    );
+}
@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+use hellas_morpheus::test_harness::{MockHarness, TxGenPolicy};
+use hellas_morpheus::testkit::{assert_agreement, assert_no_invariant_violations};
+use hellas_morpheus::{
+    CachingVerifier, DEFAULT_VERIFY_CACHE_CAPACITY, Identity, Signed, ThreshPartial,
+    VerifyCacheStats, ViewNum,
+};
+
+#[test_log::test]
+fn test_repeated_checks_hit_the_cache() {
+    let harness = MockHarness::create_test_setup(4);
+    let kb = harness.processes[&Identity(1)].kb.clone();
+    let cache = CachingVerifier::new(Arc::new(kb.clone()), 2);
+
+    let end_view = Signed::from_data(ViewNum(3), &kb);
+    assert!(end_view.valid_signature(&cache));
+    assert!(end_view.valid_signature(&cache));
+    assert_eq!(
+        cache.stats(),
+        VerifyCacheStats {
+            hits: 1,
+            misses: 1,
+            evictions: 0,
+        }
+    );
+
+    // the same signature claimed by someone else, or checked against a
+    // rotatable key, is a check of its own
+    let misattributed = Signed {
+        author: Identity(2),
+        ..end_view.clone()
+    };
+    assert!(!misattributed.valid_signature(&cache));
+    assert!(!misattributed.valid_signature(&cache));
+    assert!(end_view.valid_signature_at(&cache, ViewNum(3)));
+    assert_eq!(cache.stats().misses, 5);
+
+    // failures take no room, so only the two passed checks are held
+    assert_eq!(cache.stats().evictions, 0);
+    let partial = ThreshPartial::from_data(ViewNum(4), &kb);
+    assert!(partial.valid_signature(&cache));
+    assert_eq!(cache.stats().evictions, 1);
+    assert_eq!(cache.stats().hit_rate(), 1.0 / 7.0);
+}
+
+#[test_log::test]
+fn test_cached_verifier_in_a_cluster() {
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    let p1 = harness.processes.get_mut(&Identity(1)).unwrap();
+    let cache = Arc::new(CachingVerifier::new(
+        Arc::new(p1.kb.clone()),
+        DEFAULT_VERIFY_CACHE_CAPACITY,
+    ));
+    p1.verifier = Some(cache.clone());
+    harness.run(60);
+
+    assert_agreement(&harness);
+    assert_no_invariant_violations(&harness);
+    assert!(harness.processes[&Identity(1)].index.finalized.len() > 1);
+
+    // QCs are checked again in every block that points through them
+    let stats = cache.stats();
+    assert!(stats.hits > 0, "{stats:?}");
+    assert!(stats.hit_rate() > 0.0);
+}
@@ -0,0 +1,86 @@
+use hellas_morpheus::test_harness::{MockHarness, TestTransaction, TxGenPolicy};
+use hellas_morpheus::{
+    BlockType, Identity, Message, PendingCandidate, PendingVoteKind, PendingVotesSnapshot,
+    VoteBlocker,
+};
+
+#[test_log::test]
+fn test_transaction_vote_waits_for_leader_block() {
+    let mut harness = MockHarness::create_test_setup(4);
+
+    // p2 isn't the leader of view 0, so all it makes is a transaction block
+    let p2 = harness.processes.get_mut(&Identity(2)).unwrap();
+    p2.submit(TestTransaction(vec![1])).unwrap();
+    let mut to_send = Vec::new();
+    p2.try_produce_blocks(&mut to_send);
+    let block = to_send
+        .into_iter()
+        .find_map(|(message, _)| match message {
+            Message::Block(block) if block.data.key.type_ == BlockType::Tr => Some(block),
+            _ => None,
+        })
+        .expect("a transaction block");
+
+    let p3 = harness.processes.get_mut(&Identity(3)).unwrap();
+    assert!(p3.process_message(Message::Block(block.clone()), Identity(2), &mut Vec::new()));
+    let snapshot = p3.pending_votes_snapshot();
+    let view = snapshot
+        .iter()
+        .find(|view| view.view == block.data.key.view)
+        .expect("the block's view has pending votes");
+    assert_eq!(
+        view.candidates,
+        vec![PendingCandidate {
+            block: block.data.key.clone(),
+            kind: PendingVoteKind::Tr1,
+            blocker: Some(VoteBlocker::NoLeaderBlock),
+        }]
+    );
+    assert_eq!(
+        VoteBlocker::NoLeaderBlock.to_string(),
+        "no leader block in this view yet"
+    );
+}
+
+#[test_log::test]
+fn test_snapshot_covers_every_pending_vote() {
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    harness.run(30);
+
+    for process in harness.processes.values() {
+        let snapshot = process.pending_votes_snapshot();
+        assert_eq!(snapshot.len(), process.pending_votes.len());
+        for view in &snapshot {
+            let pending = &process.pending_votes[&view.view];
+            let count = |kind| {
+                view.candidates
+                    .iter()
+                    .filter(|candidate| candidate.kind == kind)
+                    .count()
+            };
+            assert_eq!(count(PendingVoteKind::Tr1), pending.tr_1.len());
+            assert_eq!(count(PendingVoteKind::Tr2), pending.tr_2.len());
+            assert_eq!(count(PendingVoteKind::Lead1), pending.lead_1.len());
+            assert_eq!(count(PendingVoteKind::Lead2), pending.lead_2.len());
+
+            // only the current view's votes are ever cast
+            if view.view != process.view_i {
+                assert!(view.candidates.iter().all(|candidate| {
+                    candidate.blocker
+                        == Some(VoteBlocker::NotCurrentView {
+                            current: process.view_i,
+                        })
+                }));
+            }
+        }
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let parsed: Vec<PendingVotesSnapshot> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, snapshot);
+    }
+}
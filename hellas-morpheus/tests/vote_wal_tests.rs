@@ -0,0 +1,174 @@
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use hellas_morpheus::test_harness::{MockHarness, TxGenPolicy};
+use hellas_morpheus::testkit::assert_agreement;
+use hellas_morpheus::*;
+
+fn wal_path(name: &str) -> PathBuf {
+    let path =
+        std::env::temp_dir().join(format!("morpheus-wal-{}-{}.log", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn vote_at(slot: u64) -> VoteKey {
+    VoteKey {
+        z: 1,
+        type_: BlockType::Tr,
+        slot: SlotNum(slot),
+        author: Identity(2),
+    }
+}
+
+#[test_log::test]
+fn test_votes_survive_reopening() {
+    let path = wal_path("reopen");
+    let mut wal = VoteWal::open(&path).unwrap();
+    for slot in 0..5 {
+        wal.append(&vote_at(slot)).unwrap();
+    }
+    // appending a vote again doesn't grow the log
+    let len = std::fs::metadata(&path).unwrap().len();
+    wal.append(&vote_at(3)).unwrap();
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), len);
+
+    // while one log has the file open, nobody else may use it
+    assert!(matches!(VoteWal::open(&path), Err(WalError::Locked(_))));
+    drop(wal);
+
+    let wal = VoteWal::open(&path).unwrap();
+    assert_eq!(wal.votes(), &(0..5).map(vote_at).collect());
+}
+
+#[test_log::test]
+fn test_unfinished_record_is_dropped() {
+    let memory = MemoryWal::default();
+    let mut wal = VoteWal::with_store(memory.clone()).unwrap();
+    for slot in 0..3 {
+        wal.append(&vote_at(slot)).unwrap();
+    }
+    drop(wal);
+    let whole = memory.0.lock().unwrap().len();
+
+    // a crash in the middle of appending the fourth: its header made it,
+    // its payload only in part
+    memory
+        .0
+        .lock()
+        .unwrap()
+        .extend_from_slice(&[0x20, 0, 0, 0, 1, 2, 3, 4, b'{']);
+
+    let mut wal = VoteWal::with_store(memory.clone()).unwrap();
+    assert_eq!(wal.votes(), &(0..3).map(vote_at).collect());
+    assert_eq!(memory.0.lock().unwrap().len(), whole);
+
+    // and the log carries on after it
+    wal.append(&vote_at(3)).unwrap();
+    drop(wal);
+    let wal = VoteWal::with_store(memory).unwrap();
+    assert_eq!(wal.votes(), &(0..4).map(vote_at).collect());
+}
+
+#[test_log::test]
+fn test_log_and_guard_open_side_by_side() {
+    let dir = std::env::temp_dir().join(format!("morpheus-node-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    // each takes a lock named after its whole file name
+    let _guard = SignGuard::open(dir.join("node.guard")).unwrap();
+    let _wal = VoteWal::open(dir.join("node.wal")).unwrap();
+}
+
+#[test_log::test]
+fn test_failed_appends_leave_the_log_readable() {
+    let memory = MemoryWal::default();
+    drop(VoteWal::with_store(memory.clone()).unwrap());
+    let store = FaultyStore::new(
+        memory.clone(),
+        StorageFaults {
+            write_failures: 30,
+            ..Default::default()
+        },
+    );
+    let counts = store.counts();
+    let mut wal = VoteWal::with_store(store).unwrap();
+
+    let mut logged = BTreeSet::new();
+    for slot in 0..40 {
+        match wal.append(&vote_at(slot)) {
+            Ok(()) => {
+                logged.insert(vote_at(slot));
+            }
+            // not logged, and so not to be signed
+            Err(WalError::Io(_)) => {}
+            Err(e) => panic!("unexpected {e}"),
+        }
+    }
+    assert!(counts.lock().unwrap().failed_writes > 0);
+    assert!(!logged.is_empty());
+    drop(wal);
+
+    // what the failed appends wrote was cut off, so later records don't
+    // follow garbage and the log opens with exactly what was logged
+    let wal = VoteWal::with_store(memory).unwrap();
+    assert_eq!(wal.votes(), &logged);
+}
+
+#[test_log::test]
+fn test_damaged_log_is_refused() {
+    let memory = MemoryWal::default();
+    let mut wal = VoteWal::with_store(memory.clone()).unwrap();
+    for slot in 0..3 {
+        wal.append(&vote_at(slot)).unwrap();
+    }
+    drop(wal);
+
+    // a flipped bit in the first record's payload, past the 8-byte header
+    // of the log and the record's own
+    memory.0.lock().unwrap()[18] ^= 1;
+    assert!(matches!(
+        VoteWal::with_store(memory),
+        Err(WalError::Corrupt { offset: 8, .. })
+    ));
+}
+
+#[test_log::test]
+fn test_restarted_process_does_not_vote_twice() {
+    let memory = MemoryWal::default();
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    let mut restarted = harness.processes[&Identity(1)].clone();
+    harness
+        .processes
+        .get_mut(&Identity(1))
+        .unwrap()
+        .attach_vote_wal(VoteWal::with_store(memory.clone()).unwrap());
+    harness.run(30);
+    assert_agreement(&harness);
+
+    let voted = harness.processes[&Identity(1)].voted_i.clone();
+    assert!(!voted.is_empty());
+    drop(harness);
+
+    // coming back with nothing in memory, it still knows where it voted
+    assert!(restarted.voted_i.is_empty());
+    restarted.attach_vote_wal(VoteWal::with_store(memory).unwrap());
+    assert_eq!(restarted.voted_i, voted);
+
+    let key = voted.iter().next().unwrap();
+    let block = BlockKey {
+        type_: key.type_,
+        view: ViewNum(0),
+        height: 1,
+        author: Some(key.author.clone()),
+        slot: key.slot,
+        hash: Some(BlockHash([0xee; 32])),
+    };
+    let mut to_send = Vec::new();
+    assert!(!restarted.try_vote(key.z, &block, None, &mut to_send));
+    assert!(to_send.is_empty());
+}
@@ -0,0 +1,216 @@
+// These pin the encoding down byte for byte, so they hold on every target:
+// run them under `--target wasm32-wasip1` as well to check that the 32-bit
+// browser build and native nodes agree.
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use ark_serialize::CanonicalSerialize;
+use hellas_morpheus::test_harness::{MockHarness, TestTransaction, TxGenPolicy};
+use hellas_morpheus::*;
+
+fn seen_messages() -> Vec<Message<TestTransaction>> {
+    let mut harness = MockHarness::create_test_setup(4);
+    for i in 1..=4 {
+        harness
+            .tx_gen_policy
+            .insert(Identity(i), TxGenPolicy::EveryNSteps { n: 2 });
+    }
+    harness.run(2 * 3 * 5);
+    harness.processes[&Identity(1)]
+        .received_messages
+        .recent()
+        .cloned()
+        .collect()
+}
+
+#[test_log::test]
+fn test_messages_round_trip() {
+    let mut messages = seen_messages();
+    let block = messages
+        .iter()
+        .find_map(|message| match message {
+            Message::Block(block) => Some(block.clone()),
+            _ => None,
+        })
+        .unwrap();
+    messages.push(Message::GetBlock(block.data.key.clone()));
+    messages.push(Message::BlockResponse(block));
+    let kinds = messages.iter().map(Message::kind).collect::<BTreeSet<_>>();
+    assert!(kinds.contains(&MessageKind::Block));
+    assert!(kinds.contains(&MessageKind::NewVote));
+    assert!(kinds.contains(&MessageKind::QC));
+
+    for message in messages {
+        let bytes = message.to_wire();
+        assert_eq!(bytes[0], WIRE_VERSION);
+        assert_eq!(bytes[1], message.kind().wire_tag());
+        assert_eq!(Message::from_wire(&bytes), Ok(message));
+    }
+}
+
+#[test_log::test]
+fn test_wire_tags_are_distinct() {
+    let tags = MessageKind::ALL
+        .iter()
+        .map(|kind| kind.wire_tag())
+        .collect::<BTreeSet<_>>();
+    assert_eq!(tags.len(), MessageKind::ALL.len());
+    for kind in MessageKind::ALL {
+        assert_eq!(MessageKind::from_wire_tag(kind.wire_tag()), Some(kind));
+    }
+}
+
+#[test_log::test]
+fn test_bad_encodings_are_rejected() {
+    let message = seen_messages()
+        .into_iter()
+        .find(|message| message.kind() == MessageKind::QC)
+        .unwrap();
+    let bytes = message.to_wire();
+
+    assert_eq!(
+        Message::<TestTransaction>::from_wire(&[]),
+        Err(WireError::Empty)
+    );
+
+    let mut newer = bytes.clone();
+    newer[0] = WIRE_VERSION + 1;
+    assert_eq!(
+        Message::<TestTransaction>::from_wire(&newer),
+        Err(WireError::UnsupportedVersion {
+            found: WIRE_VERSION + 1,
+            supported: WIRE_VERSION,
+        })
+    );
+
+    let mut unknown = bytes.clone();
+    unknown[1] = 200;
+    assert_eq!(
+        Message::<TestTransaction>::from_wire(&unknown),
+        Err(WireError::UnknownKind(200))
+    );
+
+    assert!(matches!(
+        Message::<TestTransaction>::from_wire(&bytes[..bytes.len() - 1]),
+        Err(WireError::Malformed(_))
+    ));
+
+    let mut trailing = bytes.clone();
+    trailing.extend_from_slice(&[0, 0]);
+    assert_eq!(
+        Message::<TestTransaction>::from_wire(&trailing),
+        Err(WireError::TrailingBytes(2))
+    );
+}
+
+#[test_log::test]
+fn test_encoding_is_fixed_width() {
+    let key = BlockKey {
+        type_: BlockType::Tr,
+        view: ViewNum(3),
+        height: 2,
+        author: Some(Identity(1)),
+        slot: SlotNum(1),
+        hash: None,
+    };
+    let mut bytes = Vec::new();
+    key.serialize_compressed(&mut bytes).unwrap();
+    #[rustfmt::skip]
+    let expected = [
+        2, // type
+        3, 0, 0, 0, 0, 0, 0, 0, // view: i64
+        2, 0, 0, 0, 0, 0, 0, 0, // height: u64
+        1, 1, 0, 0, 0, // author: Some(u32)
+        1, 0, 0, 0, 0, 0, 0, 0, // slot: u64
+        0, // hash: None
+    ];
+    assert_eq!(bytes, expected);
+
+    let proof = MerkleProof {
+        index: 5,
+        len: 6,
+        siblings: vec![[7; 32]],
+    };
+    let mut bytes = Vec::new();
+    proof.serialize_compressed(&mut bytes).unwrap();
+    assert_eq!(bytes[..8], 5u64.to_le_bytes());
+    assert_eq!(bytes[8..16], 6u64.to_le_bytes());
+    // sequence lengths are 8 bytes too, whatever the width of `usize`
+    assert_eq!(bytes[16..24], 1u64.to_le_bytes());
+    assert_eq!(bytes[24..], [7; 32]);
+}
+
+fn repetitive_block() -> Message<TestTransaction> {
+    let harness = MockHarness::create_test_setup(4);
+    let author = &harness.processes[&Identity(1)];
+    let block = Block {
+        key: BlockKey {
+            type_: BlockType::Tr,
+            view: ViewNum(0),
+            height: 1,
+            author: Some(Identity(1)),
+            slot: SlotNum(0),
+            hash: None,
+        },
+        prev: vec![author.genesis_qc.clone()],
+        one: author.genesis_qc.clone(),
+        data: BlockData::tr(vec![TestTransaction(vec![7; 1024]); 16]),
+    };
+    Message::Block(Arc::new(Signed::from_data(block.hashed(), &author.kb)))
+}
+
+#[test_log::test]
+fn test_compression_is_negotiated() {
+    let ours = Some(WireCompression::new(9));
+    assert_eq!(negotiate(ours, false), None);
+    assert_eq!(negotiate(None, true), None);
+    assert_eq!(
+        negotiate(ours, true),
+        ours.filter(|_| WireCompression::available())
+    );
+    assert_eq!(WireCompression::new(100).level, 22);
+
+    // only blocks are ever compressed
+    let qc = seen_messages()
+        .into_iter()
+        .find(|message| message.kind() == MessageKind::QC)
+        .unwrap();
+    assert_eq!(qc.to_wire_with(ours), qc.to_wire());
+    let block = repetitive_block();
+    assert_eq!(block.to_wire_with(None), block.to_wire());
+}
+
+#[cfg(feature = "compression")]
+#[test_log::test]
+fn test_compressed_blocks_round_trip() {
+    let block = repetitive_block();
+    let bytes = block.to_wire_with(Some(WireCompression::default()));
+    assert_eq!(bytes[1], MessageKind::Block.wire_tag() | COMPRESSED);
+    assert!(bytes.len() < block.to_wire().len() / 4);
+    assert_eq!(Message::from_wire(&bytes), Ok(block));
+
+    let mut qc = seen_messages()
+        .into_iter()
+        .find(|message| message.kind() == MessageKind::QC)
+        .unwrap()
+        .to_wire();
+    qc[1] |= COMPRESSED;
+    assert_eq!(
+        Message::<TestTransaction>::from_wire(&qc),
+        Err(WireError::UnknownKind(
+            MessageKind::QC.wire_tag() | COMPRESSED
+        ))
+    );
+}
+
+#[cfg(not(feature = "compression"))]
+#[test_log::test]
+fn test_compressed_blocks_need_the_feature() {
+    let mut bytes = repetitive_block().to_wire();
+    bytes[1] |= COMPRESSED;
+    assert_eq!(
+        Message::<TestTransaction>::from_wire(&bytes),
+        Err(WireError::CompressionUnsupported)
+    );
+}
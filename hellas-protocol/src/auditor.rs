@@ -0,0 +1,126 @@
+//! Spot-checking of finalized jobs.
+//!
+//! Every auditor derives the same sample from the consensus beacon of the view
+//! the jobs were finalized in, so providers can't predict which jobs get
+//! checked and auditors can't be accused of cherry-picking. Sampled jobs are
+//! re-executed and the outcome is signed; failing verdicts are submitted as
+//! [`Transaction::Dispute`](crate::Transaction::Dispute) to trigger the
+//! penalty in the job's [`ExecutionPolicy`](crate::ExecutionPolicy).
+
+use crate::bf::{self, BfError};
+use crate::{AcceptedJobQuote, Collateral, Pubkey, Signature, Signed};
+
+pub type JobId = u64;
+
+/// A job whose result has been finalized on-chain
+pub struct FinalizedJob {
+    pub id: JobId,
+    pub accepted: AcceptedJobQuote,
+    pub input: Vec<u8>,
+    /// Output the provider claimed
+    pub output: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AuditOutcome {
+    /// Re-execution reproduced the claimed output
+    Valid,
+    /// The program ran but produced something else
+    OutputMismatch { expected: Vec<u8> },
+    /// The program can't have produced any output at all
+    ExecutionFailed(BfError),
+    /// The program hadn't halted when the auditor's fuel ran out
+    ///
+    /// The provider never agreed to the auditor's fuel limit, so this says
+    /// nothing about its output and is not a fault.
+    Inconclusive { fuel: u64 },
+}
+
+pub struct AuditVerdict {
+    pub job: JobId,
+    pub auditor: Pubkey,
+    pub outcome: AuditOutcome,
+}
+
+impl AuditVerdict {
+    pub fn is_fault(&self) -> bool {
+        matches!(
+            self.outcome,
+            AuditOutcome::OutputMismatch { .. } | AuditOutcome::ExecutionFailed(_)
+        )
+    }
+
+    /// The collateral the provider forfeits if this verdict stands
+    pub fn penalty<'a>(&self, job: &'a FinalizedJob) -> Option<&'a Collateral> {
+        if !self.is_fault() {
+            return None;
+        }
+        job.accepted.quote.requested.policy.invalidity.as_ref()
+    }
+}
+
+fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e3779b97f4a7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+/// Whether the job `id` is sampled under `beacon`, at `per_mille` out of 1000
+pub fn is_sampled(beacon: &[u8; 32], id: JobId, per_mille: u32) -> bool {
+    let seed = beacon.chunks_exact(8).fold(0u64, |acc, word| {
+        splitmix64(acc ^ u64::from_le_bytes(word.try_into().unwrap()))
+    });
+    splitmix64(seed ^ id) % 1000 < per_mille as u64
+}
+
+pub struct Auditor {
+    pub key: Pubkey,
+    /// Fraction of jobs to check, out of 1000
+    pub sample_per_mille: u32,
+    /// Fuel allowed when re-executing a job; jobs that need more are
+    /// inconclusive rather than faulty
+    pub fuel: u64,
+}
+
+impl Auditor {
+    pub fn sample<'a>(&self, beacon: &[u8; 32], jobs: &'a [FinalizedJob]) -> Vec<&'a FinalizedJob> {
+        jobs.iter()
+            .filter(|job| is_sampled(beacon, job.id, self.sample_per_mille))
+            .collect()
+    }
+
+    pub fn check(&self, job: &FinalizedJob) -> AuditVerdict {
+        let program = &job.accepted.quote.requested.job.program;
+        let outcome = match bf::execute(program, &job.input, self.fuel) {
+            Ok(execution) if execution.output == job.output => AuditOutcome::Valid,
+            Ok(execution) => AuditOutcome::OutputMismatch {
+                expected: execution.output,
+            },
+            Err(BfError::OutOfFuel) => AuditOutcome::Inconclusive { fuel: self.fuel },
+            Err(e) => AuditOutcome::ExecutionFailed(e),
+        };
+        AuditVerdict {
+            job: job.id,
+            auditor: self.key,
+            outcome,
+        }
+    }
+
+    /// Samples `jobs` under `beacon`, checks each one and signs the verdicts
+    pub fn audit(
+        &self,
+        beacon: &[u8; 32],
+        jobs: &[FinalizedJob],
+        mut sign: impl FnMut(&AuditVerdict) -> Signature,
+    ) -> Vec<Signed<AuditVerdict>> {
+        self.sample(beacon, jobs)
+            .into_iter()
+            .map(|job| {
+                let data = self.check(job);
+                let signature = sign(&data);
+                Signed { data, signature }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,135 @@
+//! Metered interpreter for the BF programs jobs carry.
+//!
+//! Providers run jobs with this, and auditors re-run them to check the result,
+//! so execution has to be fully deterministic: a fixed 30,000 cell tape of
+//! wrapping `u8`s, reads past the end of the input give 0, and every executed
+//! instruction costs one unit of fuel.
+
+use std::fmt;
+
+pub const TAPE_LEN: usize = 30_000;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BfError {
+    /// A `[` or `]` at this byte offset has no partner
+    UnbalancedBracket(usize),
+    /// The head moved off either end of the tape
+    TapeOverflow,
+    /// The program hadn't halted after spending all of its fuel
+    OutOfFuel,
+}
+
+impl fmt::Display for BfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BfError::UnbalancedBracket(at) => write!(f, "unbalanced bracket at offset {at}"),
+            BfError::TapeOverflow => write!(f, "tape head moved out of bounds"),
+            BfError::OutOfFuel => write!(f, "ran out of fuel"),
+        }
+    }
+}
+
+impl std::error::Error for BfError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Execution {
+    pub output: Vec<u8>,
+    /// Instructions executed, i.e. fuel spent
+    pub steps: u64,
+}
+
+/// Finds the partner of every bracket, so loops jump in O(1)
+fn match_brackets(program: &[u8]) -> Result<Vec<usize>, BfError> {
+    let mut jumps = vec![0; program.len()];
+    let mut open = Vec::new();
+    for (i, op) in program.iter().enumerate() {
+        match op {
+            b'[' => open.push(i),
+            b']' => {
+                let start = open.pop().ok_or(BfError::UnbalancedBracket(i))?;
+                jumps[start] = i;
+                jumps[i] = start;
+            }
+            _ => {}
+        }
+    }
+    match open.pop() {
+        Some(unclosed) => Err(BfError::UnbalancedBracket(unclosed)),
+        None => Ok(jumps),
+    }
+}
+
+/// Iterations assumed per loop when metering, since loop counts depend on input
+pub const ASSUMED_LOOP_ITERATIONS: u64 = 16;
+
+/// Statically estimates how many instructions `program` executes.
+///
+/// Straight-line code costs one per instruction; code nested `d` loops deep is
+/// assumed to run [`ASSUMED_LOOP_ITERATIONS`]`^d` times. This is a pricing
+/// heuristic, not a bound: actual execution is still capped by fuel.
+pub fn meter(program: &str) -> Result<u64, BfError> {
+    let program = program.as_bytes();
+    match_brackets(program)?;
+    let mut depth = 0u32;
+    let mut total = 0u64;
+    for op in program {
+        let weight = ASSUMED_LOOP_ITERATIONS.saturating_pow(depth);
+        match op {
+            b'[' => {
+                total = total.saturating_add(weight);
+                depth += 1;
+            }
+            b']' => {
+                depth -= 1;
+                total = total.saturating_add(ASSUMED_LOOP_ITERATIONS.saturating_pow(depth));
+            }
+            b'>' | b'<' | b'+' | b'-' | b'.' | b',' => total = total.saturating_add(weight),
+            _ => {}
+        }
+    }
+    Ok(total)
+}
+
+/// Runs `program` on `input`, spending at most `fuel` instructions
+pub fn execute(program: &str, input: &[u8], fuel: u64) -> Result<Execution, BfError> {
+    let program = program.as_bytes();
+    let jumps = match_brackets(program)?;
+    let mut tape = vec![0u8; TAPE_LEN];
+    let mut head = 0usize;
+    let mut pc = 0usize;
+    let mut input = input.iter();
+    let mut output = Vec::new();
+    let mut steps = 0u64;
+
+    while pc < program.len() {
+        let op = program[pc];
+        if !matches!(op, b'>' | b'<' | b'+' | b'-' | b'.' | b',' | b'[' | b']') {
+            // anything else is a comment and free
+            pc += 1;
+            continue;
+        }
+        if steps == fuel {
+            return Err(BfError::OutOfFuel);
+        }
+        steps += 1;
+        match op {
+            b'>' => {
+                head += 1;
+                if head == TAPE_LEN {
+                    return Err(BfError::TapeOverflow);
+                }
+            }
+            b'<' => head = head.checked_sub(1).ok_or(BfError::TapeOverflow)?,
+            b'+' => tape[head] = tape[head].wrapping_add(1),
+            b'-' => tape[head] = tape[head].wrapping_sub(1),
+            b'.' => output.push(tape[head]),
+            b',' => tape[head] = input.next().copied().unwrap_or(0),
+            b'[' if tape[head] == 0 => pc = jumps[pc],
+            b']' if tape[head] != 0 => pc = jumps[pc],
+            _ => {}
+        }
+        pc += 1;
+    }
+
+    Ok(Execution { output, steps })
+}
@@ -1,19 +1,24 @@
-mod provider;
-mod auditor;
-mod requestor;
+pub mod auditor;
+pub mod bf;
+pub mod pricing;
+pub mod provider;
+pub mod requestor;
+
+use auditor::AuditVerdict;
+use provider::ProviderProfile;
 
 pub struct Signature;
-pub struct Pubkey;
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Pubkey(pub [u8; 32]);
 pub struct TokenAmount(pub u64);
 
-
 pub struct Signed<T> {
     pub data: T,
     pub signature: Signature,
 }
 
 pub struct BFJob {
-    pub program: String
+    pub program: String,
 }
 
 pub struct ExecutionPolicy {
@@ -28,10 +33,10 @@ pub struct TimeoutConfig {
 
 pub enum Collateral {
     None,
-    BurnPerformanceBond { amount: u64 }
+    BurnPerformanceBond { amount: u64 },
 }
 
-pub struct QuoteRequest  {
+pub struct QuoteRequest {
     pub job: BFJob,
     pub policy: ExecutionPolicy,
 }
@@ -49,11 +54,14 @@ pub struct AcceptedJobQuote {
     pub requestor_signature: Signature,
 }
 
-pub enum Transaction { 
+pub enum Transaction {
     Increment,
-    Decrement
+    Decrement,
+    RegisterProvider(Signed<ProviderProfile>),
+    /// A failing audit verdict, claiming the provider's invalidity collateral
+    Dispute(Signed<AuditVerdict>),
 }
 
 pub struct Block {
     pub txns: Vec<Transaction>,
-}
\ No newline at end of file
+}
@@ -0,0 +1,90 @@
+//! How providers turn a [`QuoteRequest`] into a [`JobQuote`].
+//!
+//! Policies are pure functions of the request and their own parameters, so a
+//! requestor can recompute a quote to check it, and compose: wrap any policy in
+//! [`CongestionAdjusted`] or [`Bounded`] to adjust its output.
+
+use crate::bf::{self, BfError};
+use crate::provider::PricingCurve;
+use crate::{JobQuote, QuoteRequest};
+
+pub trait PricingPolicy {
+    fn price(&self, request: &QuoteRequest) -> Result<u64, BfError>;
+
+    fn quote(&self, request: QuoteRequest) -> Result<JobQuote, BfError> {
+        let price = self.price(&request)?;
+        Ok(JobQuote {
+            requested: request,
+            price,
+        })
+    }
+}
+
+/// The same price for every job
+pub struct Flat(pub u64);
+
+impl PricingPolicy for Flat {
+    fn price(&self, _request: &QuoteRequest) -> Result<u64, BfError> {
+        Ok(self.0)
+    }
+}
+
+impl PricingPolicy for PricingCurve {
+    fn price(&self, request: &QuoteRequest) -> Result<u64, BfError> {
+        Ok(self.price_for(request.job.program.len()))
+    }
+}
+
+/// `base + per_instruction * estimate`, with the estimate from [`bf::meter`]
+pub struct PerInstruction {
+    pub base: u64,
+    pub per_instruction: u64,
+}
+
+impl PricingPolicy for PerInstruction {
+    fn price(&self, request: &QuoteRequest) -> Result<u64, BfError> {
+        let estimate = bf::meter(&request.job.program)?;
+        Ok(self
+            .per_instruction
+            .saturating_mul(estimate)
+            .saturating_add(self.base))
+    }
+}
+
+/// Raises `inner`'s price linearly with load, by up to `max_surcharge_pct` at full capacity
+pub struct CongestionAdjusted<P> {
+    pub inner: P,
+    /// Jobs currently running
+    pub load: u32,
+    pub capacity: u32,
+    pub max_surcharge_pct: u32,
+}
+
+impl<P: PricingPolicy> PricingPolicy for CongestionAdjusted<P> {
+    fn price(&self, request: &QuoteRequest) -> Result<u64, BfError> {
+        let price = self.inner.price(request)?;
+        let load = self.load.min(self.capacity) as u128;
+        let surcharge_pct = match self.capacity {
+            0 => self.max_surcharge_pct as u128,
+            capacity => self.max_surcharge_pct as u128 * load / capacity as u128,
+        };
+        let adjusted = price as u128 * (100 + surcharge_pct) / 100;
+        Ok(adjusted.min(u64::MAX as u128) as u64)
+    }
+}
+
+/// Clamps `inner`'s price into `min..=max`
+pub struct Bounded<P> {
+    pub inner: P,
+    pub min: u64,
+    pub max: u64,
+}
+
+impl<P: PricingPolicy> PricingPolicy for Bounded<P> {
+    fn price(&self, request: &QuoteRequest) -> Result<u64, BfError> {
+        Ok(self
+            .inner
+            .price(request)?
+            .clamp(self.min, self.max.max(self.min)))
+    }
+}
@@ -0,0 +1,51 @@
+use crate::{BFJob, Pubkey};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum JobKind {
+    BF,
+}
+
+impl BFJob {
+    pub fn kind(&self) -> JobKind {
+        JobKind::BF
+    }
+}
+
+/// Price as a function of program size: `base + per_byte * len`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PricingCurve {
+    pub base: u64,
+    pub per_byte: u64,
+}
+
+impl PricingCurve {
+    pub fn price_for(&self, program_len: usize) -> u64 {
+        self.per_byte
+            .saturating_mul(program_len as u64)
+            .saturating_add(self.base)
+    }
+}
+
+/// What a provider offers, registered on-chain via `Transaction::RegisterProvider`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProviderProfile {
+    pub provider: Pubkey,
+    pub job_kinds: Vec<JobKind>,
+    pub max_program_size: usize,
+    pub pricing: PricingCurve,
+    /// How many jobs the provider is willing to run concurrently
+    pub capacity: u32,
+}
+
+impl ProviderProfile {
+    /// Whether this provider could take `job` at all, ignoring price
+    pub fn accepts(&self, job: &BFJob) -> bool {
+        self.capacity > 0
+            && self.job_kinds.contains(&job.kind())
+            && job.program.len() <= self.max_program_size
+    }
+
+    pub fn price_for(&self, job: &BFJob) -> u64 {
+        self.pricing.price_for(job.program.len())
+    }
+}
@@ -0,0 +1,37 @@
+use std::{cmp::Reverse, collections::BTreeSet};
+
+use crate::provider::ProviderProfile;
+use crate::QuoteRequest;
+
+/// A provider that can serve a request, with the price its profile implies
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Candidate<'a> {
+    pub profile: &'a ProviderProfile,
+    pub price: u64,
+}
+
+/// Selects up to `limit` providers able to serve `request`.
+///
+/// Candidates are ordered by price, then by larger capacity, then by pubkey, so
+/// every requestor looking at the same registry picks the same providers
+/// regardless of the order profiles were registered in.
+pub fn match_providers<'a>(
+    request: &QuoteRequest,
+    profiles: impl IntoIterator<Item = &'a ProviderProfile>,
+    limit: usize,
+) -> Vec<Candidate<'a>> {
+    let mut candidates: Vec<_> = profiles
+        .into_iter()
+        .filter(|profile| profile.accepts(&request.job))
+        .map(|profile| Candidate {
+            profile,
+            price: profile.price_for(&request.job),
+        })
+        .collect();
+    candidates.sort_by_key(|c| (c.price, Reverse(c.profile.capacity), c.profile.provider));
+    // a provider that registered several profiles only counts once, at its best offer
+    let mut seen = BTreeSet::new();
+    candidates.retain(|c| seen.insert(c.profile.provider));
+    candidates.truncate(limit);
+    candidates
+}
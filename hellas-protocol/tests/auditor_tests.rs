@@ -0,0 +1,78 @@
+use hellas_protocol::auditor::{AuditOutcome, Auditor, FinalizedJob};
+use hellas_protocol::bf::BfError;
+use hellas_protocol::{
+    AcceptedJobQuote, BFJob, Collateral, ExecutionPolicy, JobQuote, Pubkey, QuoteRequest, Signature,
+};
+
+fn finalized(program: &str, output: &[u8]) -> FinalizedJob {
+    FinalizedJob {
+        id: 1,
+        accepted: AcceptedJobQuote {
+            quote: JobQuote {
+                requested: QuoteRequest {
+                    job: BFJob {
+                        program: program.to_string(),
+                    },
+                    policy: ExecutionPolicy {
+                        invalidity: Some(Collateral::BurnPerformanceBond { amount: 10 }),
+                        timeout: None,
+                    },
+                },
+                price: 1,
+            },
+            provider: Pubkey([1; 32]),
+            requestor: Pubkey([2; 32]),
+            provider_signature: Signature,
+            requestor_signature: Signature,
+        },
+        input: Vec::new(),
+        output: output.to_vec(),
+    }
+}
+
+fn auditor(fuel: u64) -> Auditor {
+    Auditor {
+        key: Pubkey([3; 32]),
+        sample_per_mille: 1000,
+        fuel,
+    }
+}
+
+#[test]
+fn test_reproduced_output_is_valid() {
+    let job = finalized("+++.", &[3]);
+    let verdict = auditor(100).check(&job);
+    assert_eq!(verdict.outcome, AuditOutcome::Valid);
+    assert!(!verdict.is_fault());
+    assert!(verdict.penalty(&job).is_none());
+}
+
+#[test]
+fn test_wrong_output_is_a_fault() {
+    let job = finalized("+++.", &[4]);
+    let verdict = auditor(100).check(&job);
+    assert_eq!(
+        verdict.outcome,
+        AuditOutcome::OutputMismatch { expected: vec![3] }
+    );
+    assert!(verdict.is_fault());
+    assert!(verdict.penalty(&job).is_some());
+
+    let overflowing = finalized("<", &[]);
+    let verdict = auditor(100).check(&overflowing);
+    assert_eq!(
+        verdict.outcome,
+        AuditOutcome::ExecutionFailed(BfError::TapeOverflow)
+    );
+    assert!(verdict.is_fault());
+}
+
+#[test]
+fn test_running_out_of_auditor_fuel_is_not_a_fault() {
+    // the provider ran it to completion, the auditor gives up early
+    let job = finalized("+++.", &[3]);
+    let verdict = auditor(2).check(&job);
+    assert_eq!(verdict.outcome, AuditOutcome::Inconclusive { fuel: 2 });
+    assert!(!verdict.is_fault());
+    assert!(verdict.penalty(&job).is_none());
+}
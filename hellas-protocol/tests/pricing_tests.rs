@@ -0,0 +1,144 @@
+use hellas_protocol::bf::{self, BfError};
+use hellas_protocol::pricing::{Bounded, CongestionAdjusted, Flat, PerInstruction, PricingPolicy};
+use hellas_protocol::provider::PricingCurve;
+use hellas_protocol::{BFJob, ExecutionPolicy, QuoteRequest};
+
+fn request(program: &str) -> QuoteRequest {
+    QuoteRequest {
+        job: BFJob {
+            program: program.to_string(),
+        },
+        policy: ExecutionPolicy {
+            invalidity: None,
+            timeout: None,
+        },
+    }
+}
+
+const PROGRAMS: &[&str] = &[
+    "",
+    "+++.",
+    "++[>+<-]>.",
+    ",[.,]",
+    "+[[[[[[[[[[[[[[[[[[[[-]]]]]]]]]]]]]]]]]]]]",
+];
+
+#[test]
+fn test_metering() {
+    assert_eq!(bf::meter(""), Ok(0));
+    assert_eq!(bf::meter("+++. this is a comment"), Ok(4));
+    // the body runs ASSUMED_LOOP_ITERATIONS times per pass
+    assert_eq!(bf::meter("[+-]"), Ok(2 + 2 * bf::ASSUMED_LOOP_ITERATIONS));
+    assert_eq!(bf::meter("[[]"), Err(BfError::UnbalancedBracket(0)));
+    assert_eq!(bf::meter("]"), Err(BfError::UnbalancedBracket(0)));
+    // deep nesting saturates rather than overflowing
+    assert_eq!(bf::meter(PROGRAMS[4]), Ok(u64::MAX));
+}
+
+#[test]
+fn test_policies_are_deterministic() {
+    let policies: Vec<Box<dyn PricingPolicy>> = vec![
+        Box::new(Flat(7)),
+        Box::new(PricingCurve {
+            base: 3,
+            per_byte: 2,
+        }),
+        Box::new(PerInstruction {
+            base: 1,
+            per_instruction: 5,
+        }),
+        Box::new(CongestionAdjusted {
+            inner: PerInstruction {
+                base: 1,
+                per_instruction: 5,
+            },
+            load: 3,
+            capacity: 4,
+            max_surcharge_pct: 200,
+        }),
+    ];
+    for policy in &policies {
+        for program in PROGRAMS {
+            assert_eq!(
+                policy.price(&request(program)),
+                policy.price(&request(program))
+            );
+        }
+    }
+
+    let quote = Flat(7).quote(request("+.")).unwrap();
+    assert_eq!(quote.price, 7);
+    assert_eq!(quote.requested.job.program, "+.");
+}
+
+#[test]
+fn test_per_instruction_pricing() {
+    let policy = PerInstruction {
+        base: 10,
+        per_instruction: 3,
+    };
+    assert_eq!(policy.price(&request("")), Ok(10));
+    assert_eq!(policy.price(&request("+++.")), Ok(10 + 3 * 4));
+    assert!(policy.price(&request("[+]")).unwrap() > policy.price(&request("+++")).unwrap());
+    assert_eq!(policy.price(&request(PROGRAMS[4])), Ok(u64::MAX));
+    assert!(policy.price(&request("[")).is_err());
+    assert!(policy.quote(request("[")).is_err());
+}
+
+#[test]
+fn test_congestion_bounds() {
+    let at_load = |load| CongestionAdjusted {
+        inner: Flat(1000),
+        load,
+        capacity: 10,
+        max_surcharge_pct: 50,
+    };
+    let req = request("+");
+    assert_eq!(at_load(0).price(&req), Ok(1000));
+    assert_eq!(at_load(5).price(&req), Ok(1250));
+    assert_eq!(at_load(10).price(&req), Ok(1500));
+    // load beyond capacity doesn't raise the price any further
+    assert_eq!(at_load(100).price(&req), Ok(1500));
+
+    let mut previous = 0;
+    for load in 0..=10 {
+        let price = at_load(load).price(&req).unwrap();
+        assert!(price >= previous);
+        previous = price;
+    }
+
+    let saturated = CongestionAdjusted {
+        inner: Flat(u64::MAX),
+        load: 1,
+        capacity: 1,
+        max_surcharge_pct: 100,
+    };
+    assert_eq!(saturated.price(&req), Ok(u64::MAX));
+}
+
+#[test]
+fn test_bounded_clamps() {
+    let bounded = |inner| Bounded {
+        inner,
+        min: 100,
+        max: 200,
+    };
+    let req = request("+");
+    assert_eq!(bounded(Flat(5)).price(&req), Ok(100));
+    assert_eq!(bounded(Flat(150)).price(&req), Ok(150));
+    assert_eq!(bounded(Flat(u64::MAX)).price(&req), Ok(200));
+
+    for program in PROGRAMS {
+        let price = Bounded {
+            inner: PerInstruction {
+                base: 0,
+                per_instruction: 1,
+            },
+            min: 2,
+            max: 50,
+        }
+        .price(&request(program))
+        .unwrap();
+        assert!((2..=50).contains(&price));
+    }
+}
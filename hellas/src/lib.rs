@@ -0,0 +1,51 @@
+//! # Hellas
+//!
+//! One dependency for the pieces of the Hellas workspace, with a curated public
+//! API instead of every workspace member's internals. Each part sits behind a
+//! feature:
+//!
+//! - `consensus` (default): the Morpheus protocol from `hellas-morpheus`
+//! - `protocol` (default): job, quote, provider and audit types from `hellas-protocol`
+//! - `client`: the observer protocol a node serves to browser peers, from `native-node`
+//! - `testkit`: the simulated cluster, fault presets and consensus assertions
+
+#[cfg(feature = "consensus")]
+pub mod consensus {
+    //! The Morpheus consensus protocol.
+
+    pub use hellas_morpheus::{
+        format, Block, BlockData, BlockHash, BlockKey, BlockType, BlockValidationError,
+        EventFilter, EventKind, FinishedQC, Identity, IdentityMetadata, InvariantLevel,
+        InvariantViolation, KeyBook, Message, MetadataRegistry, MorpheusProcess, Phase,
+        ProgressReport, ProtocolEvent, Signed, SlotNum, StartView, Subscription, ThreshPartial,
+        ThreshSigned, Transaction, ViewNum, VoteData, GEN_BLOCK_KEY,
+    };
+}
+
+#[cfg(feature = "protocol")]
+pub mod protocol {
+    //! Types for requesting, quoting, running and auditing jobs.
+
+    pub use hellas_protocol::{
+        auditor, bf, pricing, provider, requestor, AcceptedJobQuote, BFJob, Collateral,
+        ExecutionPolicy, JobQuote, Pubkey, QuoteRequest, TimeoutConfig, TokenAmount,
+    };
+}
+
+#[cfg(feature = "client")]
+pub mod client {
+    //! The observer protocol nodes serve to read-only peers.
+
+    pub use native_node::observer::{ObserverUpdate, MAX_FRAME_LEN, OBSERVER_PROTOCOL};
+}
+
+#[cfg(feature = "testkit")]
+pub mod testkit {
+    //! A simulated cluster of Morpheus processes and assertions about it.
+
+    pub use hellas_morpheus::presets::{UnknownPreset, PRESETS};
+    pub use hellas_morpheus::test_harness::{
+        DivergenceReport, MockHarness, ScenarioEvent, TestTransaction, TxGenPolicy,
+    };
+    pub use hellas_morpheus::testkit::*;
+}
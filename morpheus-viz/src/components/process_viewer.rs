@@ -1,5 +1,5 @@
 use hellas_morpheus::{
-    test_harness::MockHarness, Block, BlockData, BlockKey, BlockType, Identity, Message, Phase, QuorumTrack, Signed, SlotNum, StartView, StateIndex, ThreshSigned, Transaction, ViewNum, VoteData
+    test_harness::MockHarness, Block, BlockData, BlockKey, BlockType, Identity, Message, MessageKind, Phase, QuorumTrack, Signed, SlotNum, StartView, StateIndex, ThreshSigned, Transaction, ViewNum, VoteData
 };
 use leptos::prelude::*;
 use std::{collections::BTreeMap, sync::Arc};
@@ -34,12 +34,12 @@ pub fn Block(block: Block) -> impl IntoView {
                     }}</span>
                     {
                         match block.data {
-                            hellas_morpheus::BlockData::Tr { transactions } => {
+                            hellas_morpheus::BlockData::Tr { transactions, .. } => {
                                 view! {
                                     <span>Transactions: {transactions.len()}</span>
                                 }.into_any()
                             },
-                            hellas_morpheus::BlockData::Lead { justification } => {
+                            hellas_morpheus::BlockData::Lead { justification, .. } => {
                                 view! {
                                     <ul>
                                         {
@@ -176,7 +176,7 @@ fn BlockDataComponent(data: BlockData) -> impl IntoView {
             }}</span>
             {
                 match data {
-                    BlockData::Tr { transactions } => {
+                    BlockData::Tr { transactions, .. } => {
                         view! {
                             <div class="transactions">
                                 <span>Transactions: {transactions.len()}</span>
@@ -184,7 +184,7 @@ fn BlockDataComponent(data: BlockData) -> impl IntoView {
                             </div>
                         }.into_any()
                     },
-                    BlockData::Lead { justification } => {
+                    BlockData::Lead { justification, .. } => {
                         view! {
                             <div class="justification">
                                 <span>Justification ({justification.len()} StartViews):</span>
@@ -360,6 +360,9 @@ fn MessageComponent(message: Message) -> impl IntoView {
         Message::EndView(ev) => view! { <div>EndView: <SignedComponent signed_data=ev render_data=|v_num| view! { <ViewNumComponent view=v_num /> }.into_any() /></div> }.into_any(),
         Message::EndViewCert(evc) => view! { <div>EndViewCert: <ThreshSignedComponent qc=evc render_data=|v_num| view! { <ViewNumComponent view=v_num /> }.into_any() /></div> }.into_any(),
         Message::StartView(sv) => view! { <div>StartView: <SignedComponent signed_data=sv render_data=|sv_data| view! { <StartView start_view=sv_data/> }.into_any() /></div> }.into_any(),
+        Message::KeyRotation(kr) => view! { <div>{format!("KeyRotation: process {} from view {}", kr.author.0, kr.data.effective.0)}</div> }.into_any(),
+        Message::GetBlock(key) => view! { <div>GetBlock: <BlockKeyComponent key=key/></div> }.into_any(),
+        Message::BlockResponse(b) => view! { <div>BlockResponse: <BlockComponent block=b/></div> }.into_any(),
     }
 }
 
@@ -483,19 +486,19 @@ pub fn ProcessViewer(harness: Signal<MockHarness>) -> impl IntoView {
                              <div class="process-section">
                                 <h3>Received Messages</h3>
                                 <details>
-                                    <summary>{p_clone.received_messages.len()} Total Messages</summary>
+                                    <summary>{p_clone.received_messages.total()} Total Messages</summary>
                                     // Basic breakdown (could be more detailed)
                                      <div class="message-counts">
-                                        <div class="field-row"><span>Blocks:</span> <span>{p_clone.received_messages.iter().filter(|m| matches!(m, Message::Block(_))).count()}</span></div>
-                                        <div class="field-row"><span>QCs:</span> <span>{p_clone.received_messages.iter().filter(|m| matches!(m, Message::QC(_))).count()}</span></div>
-                                        <div class="field-row"><span>Votes:</span> <span>{p_clone.received_messages.iter().filter(|m| matches!(m, Message::NewVote(_))).count()}</span></div>
-                                        <div class="field-row"><span>End Views:</span> <span>{p_clone.received_messages.iter().filter(|m| matches!(m, Message::EndView(_))).count()}</span></div>
-                                        <div class="field-row"><span>End View Certs:</span> <span>{p_clone.received_messages.iter().filter(|m| matches!(m, Message::EndViewCert(_))).count()}</span></div>
-                                        <div class="field-row"><span>Start Views:</span> <span>{p_clone.received_messages.iter().filter(|m| matches!(m, Message::StartView(_))).count()}</span></div>
+                                        <div class="field-row"><span>Blocks:</span> <span>{p_clone.received_messages.count(MessageKind::Block)}</span></div>
+                                        <div class="field-row"><span>QCs:</span> <span>{p_clone.received_messages.count(MessageKind::QC)}</span></div>
+                                        <div class="field-row"><span>Votes:</span> <span>{p_clone.received_messages.count(MessageKind::NewVote)}</span></div>
+                                        <div class="field-row"><span>End Views:</span> <span>{p_clone.received_messages.count(MessageKind::EndView)}</span></div>
+                                        <div class="field-row"><span>End View Certs:</span> <span>{p_clone.received_messages.count(MessageKind::EndViewCert)}</span></div>
+                                        <div class="field-row"><span>Start Views:</span> <span>{p_clone.received_messages.count(MessageKind::StartView)}</span></div>
                                     </div>
-                                    // Full list
+                                    // The latest ones
                                     <ul class="compact-list item-list message-list">
-                                        {p_clone.received_messages.iter().map(|msg| view! { <li><MessageComponent message=msg.clone() /></li> }).collect_view()}
+                                        {p_clone.received_messages.recent().map(|msg| view! { <li><MessageComponent message=msg.clone() /></li> }).collect_view()}
                                     </ul>
                                 </details>
                             </div>
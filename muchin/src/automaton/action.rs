@@ -208,6 +208,10 @@ impl Dispatcher {
         self.halt
     }
 
+    pub fn queue_len(&self) -> usize {
+        self.queue.len()
+    }
+
     pub fn next_action(&mut self) -> AnyAction {
         self.queue.pop_front().unwrap_or_else(|| {
             let mut any_action = (self.tick)();
@@ -221,6 +225,11 @@ impl Dispatcher {
         })
     }
 
+    /// Pops the next queued action without falling back to `tick`.
+    pub fn pop_queued(&mut self) -> Option<AnyAction> {
+        self.queue.pop_front()
+    }
+
     pub fn record(&mut self, filename: &str) {
         assert!(self.record_file.is_none());
         self.record_file = Some(BufWriter::new(
@@ -0,0 +1,66 @@
+use std::{collections::BTreeMap, time::Duration};
+
+/// Counters for the actions of a single model, keyed in `RunnerMetrics` by
+/// the model's action type name (every model has exactly one action type).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ModelMetrics {
+    pub pure: bool,
+    pub processed: u64,
+    pub total_time: Duration,
+    pub max_time: Duration,
+}
+
+impl ModelMetrics {
+    pub fn mean_time(&self) -> Duration {
+        if self.processed == 0 {
+            return Duration::ZERO;
+        }
+        self.total_time / self.processed as u32
+    }
+}
+
+/// Queue statistics of a single dispatcher (one per instance).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct QueueMetrics {
+    pub depth: usize,
+    pub max_depth: usize,
+    /// How many times the queue ran dry and the `tick` action was produced
+    pub ticks: u64,
+}
+
+/// Snapshot of what the runner has been doing since metrics were enabled.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RunnerMetrics {
+    pub models: BTreeMap<&'static str, ModelMetrics>,
+    pub queues: Vec<QueueMetrics>,
+}
+
+impl RunnerMetrics {
+    pub(super) fn new(instances: usize) -> Self {
+        Self {
+            models: BTreeMap::new(),
+            queues: vec![QueueMetrics::default(); instances],
+        }
+    }
+
+    pub(super) fn record_action(&mut self, type_name: &'static str, pure: bool, elapsed: Duration) {
+        let model = self.models.entry(type_name).or_default();
+        model.pure = pure;
+        model.processed += 1;
+        model.total_time += elapsed;
+        model.max_time = model.max_time.max(elapsed);
+    }
+
+    pub(super) fn record_queue(&mut self, instance: usize, depth: usize, ticked: bool) {
+        let queue = &mut self.queues[instance];
+        queue.depth = depth;
+        queue.max_depth = queue.max_depth.max(depth);
+        if ticked {
+            queue.ticks += 1;
+        }
+    }
+
+    pub fn total_processed(&self) -> u64 {
+        self.models.values().map(|model| model.processed).sum()
+    }
+}
@@ -1,9 +1,13 @@
 mod action;
+mod metrics;
 mod model;
 mod runner;
 mod state;
+mod testing;
 
 pub use action::*;
+pub use metrics::*;
 pub use model::*;
 pub use runner::*;
 pub use state::*;
+pub use testing::*;
@@ -1,9 +1,10 @@
 use super::{
     ActionKind, AnyAction, AnyModel, Dispatcher, Effectful, EffectfulModel, ModelState,
-    PrivateModel, Pure, PureModel, State,
+    PrivateModel, Pure, PureModel, RunnerMetrics, State,
 };
 
 use std::collections::BTreeMap;
+use std::time::Instant;
 use std::{env, io::Write};
 use type_uuid::TypeUuid;
 
@@ -17,6 +18,9 @@ pub struct Runner<Substate: ModelState> {
     pub models: BTreeMap<type_uuid::Bytes, AnyModel<Substate>>,
     pub state: State<Substate>,
     pub dispatchers: Vec<Dispatcher>,
+    /// Per-model and per-queue counters, collected only once enabled with
+    /// `enable_metrics` since timing every action isn't free.
+    pub metrics: Option<RunnerMetrics>,
 }
 
 /// Models should implement their own `register` function to register themselves
@@ -89,9 +93,24 @@ impl<Substate: ModelState> Runner<Substate> {
             models,
             state,
             dispatchers,
+            metrics: None,
         }
     }
 
+    /// Starts collecting metrics, discarding any collected so far.
+    pub fn enable_metrics(&mut self) {
+        self.metrics = Some(RunnerMetrics::new(self.dispatchers.len()));
+    }
+
+    pub fn disable_metrics(&mut self) {
+        self.metrics = None;
+    }
+
+    /// Returns a copy of the metrics collected so far, if enabled.
+    pub fn metrics_snapshot(&self) -> Option<RunnerMetrics> {
+        self.metrics.clone()
+    }
+
     /// State-machine main loop. If the runner contains more than one instance,
     /// it interleaves the processing of actions fairly for each instance.
     pub fn run(&mut self) {
@@ -117,21 +136,27 @@ impl<Substate: ModelState> Runner<Substate> {
                 return true;
             }
 
+            let ticked = dispatcher.queue_len() == 0;
             let action = dispatcher.next_action();
-            self.process_action(action, instance)
+            self.process_action(action, instance);
+
+            if let Some(metrics) = &mut self.metrics {
+                metrics.record_queue(instance, self.dispatchers[instance].queue_len(), ticked);
+            }
         }
         false
     }
 
-    pub fn dispatch<A: super::Action>(&mut self, action: A, instance: usize) 
+    pub fn dispatch<A: super::Action>(&mut self, action: A, instance: usize)
     where
         A: Sized + 'static,
-        super::IfPure<{ A::KIND as u8 }>: super::True, {
+        super::IfPure<{ A::KIND as u8 }>: super::True,
+    {
         let dispatcher = &mut self.dispatchers[instance];
         dispatcher.dispatch(action);
     }
 
-    fn process_action(&mut self, action: AnyAction, instance: usize) {
+    pub(super) fn process_action(&mut self, action: AnyAction, instance: usize) {
         let dispatcher = &mut self.dispatchers[instance];
         let model = self
             .models
@@ -149,10 +174,18 @@ impl<Substate: ModelState> Runner<Substate> {
             model.serialize_into(writer, &action)
         }
 
+        let type_name = action.type_name;
+        let pure = matches!(action.kind, ActionKind::Pure);
+        let started = self.metrics.as_ref().map(|_| Instant::now());
+
         match action.kind {
             ActionKind::Pure => model.process_pure(&mut self.state, action, dispatcher),
             ActionKind::Effectful => model.process_effectful(action, dispatcher),
         }
+
+        if let (Some(metrics), Some(started)) = (&mut self.metrics, started) {
+            metrics.record_action(type_name, pure, started.elapsed());
+        }
     }
 
     /// Run the state-machine main loop and record actions
@@ -0,0 +1,173 @@
+use super::{
+    Action, ActionKind, AnyAction, IfPure, ModelState, Redispatch, Runner, RunnerBuilder, True,
+};
+use std::collections::VecDeque;
+
+/// Upper bound on actions processed by a single `settle` call, so a model that
+/// keeps re-dispatching itself fails the test instead of hanging it.
+const MAX_SETTLE_ACTIONS: usize = 100_000;
+
+/// An effectful action that `ModelHarness` intercepted instead of executing.
+pub struct CapturedEffect {
+    pub instance: usize,
+    pub action: AnyAction,
+}
+
+impl CapturedEffect {
+    pub fn downcast_ref<A: Action>(&self) -> Option<&A> {
+        self.action.ptr.downcast_ref::<A>()
+    }
+}
+
+/// Drives pure models with scripted input actions, without touching the
+/// outside world.
+///
+/// Pure actions are processed as the `Runner` would. Effectful actions are
+/// never handed to their `EffectfulModel`: they are captured so the test can
+/// assert on them, and, if the model expects an answer, play the external
+/// world by calling `respond` with the effect's `Redispatch`. The `tick`
+/// action is never dispatched, so nothing happens between inputs unless the
+/// test asks for it.
+pub struct ModelHarness<Substate: ModelState> {
+    pub runner: Runner<Substate>,
+    pub captured: VecDeque<CapturedEffect>,
+}
+
+impl<Substate: ModelState> ModelHarness<Substate> {
+    pub fn new(builder: RunnerBuilder<Substate>) -> Self {
+        Self {
+            runner: builder.build(),
+            captured: VecDeque::new(),
+        }
+    }
+
+    /// Returns the state of type `T` for `instance`.
+    pub fn state<T: 'static>(&self, instance: usize) -> &T {
+        self.runner.state.substates[instance].state()
+    }
+
+    pub fn state_mut<T: 'static>(&mut self, instance: usize) -> &mut T {
+        self.runner.state.substates[instance].state_mut()
+    }
+
+    /// Queues `action` for `instance` and processes everything it leads to.
+    #[track_caller]
+    pub fn input<A: Action>(&mut self, instance: usize, action: A)
+    where
+        A: Sized + 'static,
+        IfPure<{ A::KIND as u8 }>: True,
+    {
+        self.runner.dispatchers[instance].dispatch(action);
+        self.settle(instance);
+    }
+
+    /// Feeds each action in turn, settling after every one.
+    #[track_caller]
+    pub fn script<A: Action>(&mut self, instance: usize, actions: impl IntoIterator<Item = A>)
+    where
+        A: Sized + 'static,
+        IfPure<{ A::KIND as u8 }>: True,
+    {
+        for action in actions {
+            self.input(instance, action);
+        }
+    }
+
+    /// Answers a captured effect as the external world would.
+    #[track_caller]
+    pub fn respond<R: Clone + 'static>(
+        &mut self,
+        instance: usize,
+        on_result: &Redispatch<R>,
+        result: R,
+    ) {
+        self.runner.dispatchers[instance].dispatch_back(on_result, result);
+        self.settle(instance);
+    }
+
+    /// Processes queued actions for `instance` until its queue is empty,
+    /// returning how many actions were processed (captured effects included).
+    pub fn settle(&mut self, instance: usize) -> usize {
+        let mut processed = 0;
+
+        while let Some(action) = self.runner.dispatchers[instance].pop_queued() {
+            processed += 1;
+            assert!(
+                processed <= MAX_SETTLE_ACTIONS,
+                "instance {} did not settle after {} actions",
+                instance,
+                MAX_SETTLE_ACTIONS
+            );
+
+            match action.kind {
+                ActionKind::Pure => {
+                    self.runner.state.set_current_instance(instance);
+                    self.runner.process_action(action, instance)
+                }
+                ActionKind::Effectful => {
+                    self.captured.push_back(CapturedEffect { instance, action })
+                }
+            }
+        }
+        processed
+    }
+
+    /// Removes and returns every captured effect of type `A`, in dispatch order.
+    pub fn take_effects<A: Action>(&mut self) -> Vec<(usize, A)> {
+        let mut taken = Vec::new();
+        let mut rest = VecDeque::with_capacity(self.captured.len());
+
+        for mut effect in self.captured.drain(..) {
+            match effect.action.ptr.downcast::<A>() {
+                Ok(action) => taken.push((effect.instance, *action)),
+                Err(ptr) => {
+                    effect.action.ptr = ptr;
+                    rest.push_back(effect);
+                }
+            }
+        }
+        self.captured = rest;
+        taken
+    }
+
+    /// Removes and returns the oldest captured effect of type `A`, panicking if
+    /// there is none.
+    #[track_caller]
+    pub fn expect_effect<A: Action>(&mut self) -> (usize, A) {
+        let Some(position) = self
+            .captured
+            .iter()
+            .position(|effect| effect.downcast_ref::<A>().is_some())
+        else {
+            panic!(
+                "expected an effect of type {}, captured: {:?}",
+                std::any::type_name::<A>(),
+                self.captured
+                    .iter()
+                    .map(|effect| effect.action.type_name)
+                    .collect::<Vec<_>>()
+            )
+        };
+
+        let effect = self.captured.remove(position).unwrap();
+        let action = effect
+            .action
+            .ptr
+            .downcast::<A>()
+            .unwrap_or_else(|_| unreachable!());
+        (effect.instance, *action)
+    }
+
+    /// Panics if any effect is still waiting to be asserted on.
+    #[track_caller]
+    pub fn assert_no_effects(&self) {
+        assert!(
+            self.captured.is_empty(),
+            "unexpected effects: {:?}",
+            self.captured
+                .iter()
+                .map(|effect| effect.action.type_name)
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -1,2 +1,3 @@
 pub(crate) mod mio;
+pub(crate) mod rng;
 pub(crate) mod time;
@@ -0,0 +1,26 @@
+use crate::automaton::{Action, ActionKind, Redispatch, Uid};
+use serde::{Deserialize, Serialize};
+use type_uuid::TypeUuid;
+
+#[derive(Clone, PartialEq, Eq, TypeUuid, Serialize, Deserialize, Debug)]
+#[uuid = "0b7e4f63-7c1d-4a8e-9f25-6d3a1c8b2e90"]
+pub enum RngEffectfulAction {
+    /// Random bytes, with a length drawn uniformly from `min_len..max_len`
+    RandomBytes {
+        uid: Uid,
+        min_len: usize,
+        max_len: usize,
+        on_result: Redispatch<(Uid, Vec<u8>)>,
+    },
+    /// A number drawn uniformly from `low..high`
+    RandomRange {
+        uid: Uid,
+        low: u64,
+        high: u64,
+        on_result: Redispatch<(Uid, u64)>,
+    },
+}
+
+impl Action for RngEffectfulAction {
+    const KIND: ActionKind = ActionKind::Effectful;
+}
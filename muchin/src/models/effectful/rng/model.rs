@@ -0,0 +1,63 @@
+use super::{action::RngEffectfulAction, state::RngState};
+use crate::automaton::{
+    Dispatcher, Effectful, EffectfulModel, ModelState, RegisterModel, RunnerBuilder,
+};
+
+// This is an `EffectfulModel` that hands out randomness to pure models.
+//
+// Randomness is an input from the outside world just like time, so pure
+// models request it through actions and get the result dispatched back. When
+// recording, the results end up in the recording with the callback action, so
+// a replay is deterministic regardless of the mode used to record it.
+//
+// By default the model runs in the mode chosen by `RngState::from_env`. Tests
+// that need a specific seed can override the registration after registering
+// their top-most model:
+//
+//   builder.model_effectful(Effectful(RngState::seeded(1337)))
+
+impl RegisterModel for RngState {
+    fn register<Substate: ModelState>(builder: RunnerBuilder<Substate>) -> RunnerBuilder<Substate> {
+        builder.model_effectful(Effectful::<Self>(Self::from_env()))
+    }
+}
+
+impl EffectfulModel for RngState {
+    type Action = RngEffectfulAction;
+
+    fn process_effectful(&mut self, action: Self::Action, dispatcher: &mut Dispatcher) {
+        match action {
+            RngEffectfulAction::RandomBytes {
+                uid,
+                min_len,
+                max_len,
+                on_result,
+            } => {
+                let data = if dispatcher.is_replayer() {
+                    Vec::new() // Ignored
+                } else {
+                    let len = self.random_range(min_len as u64, max_len as u64) as usize;
+                    let mut data = vec![0; len];
+                    self.fill_bytes(&mut data);
+                    data
+                };
+
+                dispatcher.dispatch_back(&on_result, (uid, data));
+            }
+            RngEffectfulAction::RandomRange {
+                uid,
+                low,
+                high,
+                on_result,
+            } => {
+                let value = if dispatcher.is_replayer() {
+                    0 // Ignored
+                } else {
+                    self.random_range(low, high)
+                };
+
+                dispatcher.dispatch_back(&on_result, (uid, value));
+            }
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use rand::{rngs::SmallRng, Rng, RngCore, SeedableRng};
+
+/// Environment variable that, when set to a `u64`, puts the registered
+/// `RngState` in seeded mode.
+pub const RNG_SEED_ENV: &str = "MUCHIN_RNG_SEED";
+
+pub enum RngState {
+    /// Deterministic, for tests and reproducible runs
+    Seeded(SmallRng),
+    /// Backed by the thread-local OS-seeded generator
+    Thread,
+}
+
+impl RngState {
+    pub fn seeded(seed: u64) -> Self {
+        Self::Seeded(SmallRng::seed_from_u64(seed))
+    }
+
+    pub fn thread() -> Self {
+        Self::Thread
+    }
+
+    /// Seeded if `MUCHIN_RNG_SEED` holds a valid seed, thread-backed otherwise.
+    pub fn from_env() -> Self {
+        match std::env::var(RNG_SEED_ENV)
+            .ok()
+            .and_then(|s| s.parse().ok())
+        {
+            Some(seed) => Self::seeded(seed),
+            None => Self::thread(),
+        }
+    }
+
+    pub fn random_range(&mut self, low: u64, high: u64) -> u64 {
+        match self {
+            Self::Seeded(rng) => rng.random_range(low..high),
+            Self::Thread => rand::rng().random_range(low..high),
+        }
+    }
+
+    pub fn fill_bytes(&mut self, data: &mut [u8]) {
+        match self {
+            Self::Seeded(rng) => rng.fill_bytes(data),
+            Self::Thread => rand::rng().fill_bytes(data),
+        }
+    }
+}
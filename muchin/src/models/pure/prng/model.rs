@@ -13,9 +13,8 @@ use super::{action::PRNGPureAction, state::PRNGState};
 // IMPORTANT: This implementation is designed for a fast and deterministic PRNG
 // primarily intended for testing purposes. It should NOT be used for
 // operations requiring cryptographic security due to its determinism and lack
-// of cryptographic strength.
-//
-// TODO: implement a safe RNG (`EffectfulModel`).
+// of cryptographic strength. Models that want OS-seeded randomness outside of
+// tests should request it from the `RngState` effectful model instead.
 
 impl RegisterModel for PRNGState {
     fn register<Substate: ModelState>(builder: RunnerBuilder<Substate>) -> RunnerBuilder<Substate> {
@@ -15,9 +15,11 @@ pub enum EchoClientAction {
     ConnectTimeout { connection: Uid },
     ConnectError { connection: Uid, error: String },
     CloseEvent { connection: Uid },
+    RandomData { uid: Uid, data: Vec<u8> },
     SendSuccess { uid: Uid },
     SendTimeout { uid: Uid },
     SendError { uid: Uid, error: String },
+    RandomRecvTimeout { uid: Uid, timeout: u64 },
     RecvSuccess { uid: Uid, data: Vec<u8> },
     RecvTimeout { uid: Uid, partial_data: Vec<u8> },
     RecvError { uid: Uid, error: String },
@@ -7,19 +7,18 @@ use crate::automaton::{
 };
 use crate::{
     callback,
+    models::effectful::rng::{action::RngEffectfulAction, state::RngState},
     models::pure::{
         net::{
             tcp::action::{TcpAction, TcpPollEvents},
             tcp_client::{action::TcpClientAction, state::TcpClientState},
         },
-        prng::state::PRNGState,
         tests::echo_client::state::EchoClientConfig,
         time::model::update_time,
     },
 };
 use core::panic;
 use log::{info, warn};
-use rand::{Rng, RngCore};
 
 // The `EchoClientState` acts as a simulated echo client, used for testing the
 // functionality of the state-machine and its related models (`TcpClientState`,
@@ -45,22 +44,22 @@ use rand::{Rng, RngCore};
 //   If this limit is exceeded, the client panics.
 //
 // - For each poll result the client sends random data to the echo server.
-//   The size and content of this data are randomly generated using the
-//   `PRNGState` model.
+//   The size and content of this data are requested from the `RngState`
+//   model.
 //
 // - After sending data, the client dispatches a receive action to read the
-//   server's response. A random timeout is generated using the `PRNGState`
+//   server's response. A random timeout is requested from the `RngState`
 //   model to simulate different network conditions.
 //
 // - When it receives data from the server, the client checks if the received
 //   data matches the sent data. If not, the client panics.
 //
 
-// This model depends on `PRNGState` and `TcpClientState`.
+// This model depends on `RngState` and `TcpClientState`.
 impl RegisterModel for EchoClientState {
     fn register<Substate: ModelState>(builder: RunnerBuilder<Substate>) -> RunnerBuilder<Substate> {
         builder
-            .register::<PRNGState>()
+            .register::<RngState>()
             .register::<TcpClientState>()
             .model_pure::<Self>()
     }
@@ -99,6 +98,7 @@ impl PureModel for EchoClientState {
                     }
                     EchoClientStatus::Connecting
                     | EchoClientStatus::Connected { .. }
+                    | EchoClientStatus::PreparingSend { .. }
                     | EchoClientStatus::Sending { .. }
                     | EchoClientStatus::Receiving { .. } => {
                         let timeout = Timeout::Millis(*poll_timeout);
@@ -208,22 +208,44 @@ impl PureModel for EchoClientState {
                 } = state.substate()
                 {
                     let connection = *connection;
-                    let max_send_size = *max_send_size;
+                    let max_send_size = *max_send_size as usize;
                     let request = state.new_uid();
-                    let prng: &mut PRNGState = state.substate_mut();
-                    let random_size = prng.rng.random_range(1..max_send_size) as usize;
-                    let mut data: Vec<u8> = vec![0; random_size];
 
-                    prng.rng.fill_bytes(&mut data[..]);
+                    state.substate_mut::<EchoClientState>().status =
+                        EchoClientStatus::PreparingSend {
+                            connection,
+                            request,
+                        };
+
+                    dispatcher.dispatch_effect(RngEffectfulAction::RandomBytes {
+                        uid: request,
+                        min_len: 1,
+                        max_len: max_send_size,
+                        on_result: callback!(|(uid: Uid, data: Vec<u8>)| EchoClientAction::RandomData { uid, data }),
+                    });
+                }
+            }
+            EchoClientAction::RandomData { uid, data } => {
+                if let EchoClientState {
+                    status:
+                        EchoClientStatus::PreparingSend {
+                            connection,
+                            request,
+                        },
+                    ..
+                } = state.substate()
+                {
+                    assert_eq!(uid, *request);
+                    let connection = *connection;
 
                     state.substate_mut::<EchoClientState>().status = EchoClientStatus::Sending {
                         connection,
-                        request,
+                        request: uid,
                         data: data.clone(),
                     };
 
                     dispatcher.dispatch(TcpClientAction::Send {
-                        uid: request,
+                        uid,
                         connection,
                         data: data.into(),
                         timeout: Timeout::Millis(200),
@@ -231,20 +253,21 @@ impl PureModel for EchoClientState {
                         on_timeout: callback!(|uid: Uid| EchoClientAction::SendTimeout { uid }),
                         on_error: callback!(|(uid: Uid, error: String)| EchoClientAction::SendError { uid, error })
                     });
+                } else {
+                    // The connection closed while we waited for the data
+                    info!(
+                        "|ECHO_CLIENT| dropping random data for stale request {:?}",
+                        uid
+                    );
                 }
             }
             EchoClientAction::PollError { uid, error } => {
                 panic!("Poll {:?} failed: {}", uid, error)
             }
             EchoClientAction::SendSuccess { uid } => {
-                // Receive back what we sent
+                // Receive back what we sent, once we know how long to wait for it
                 if let EchoClientState {
-                    status:
-                        EchoClientStatus::Sending {
-                            connection,
-                            request,
-                            data,
-                        },
+                    status: EchoClientStatus::Sending { request, .. },
                     config:
                         EchoClientConfig {
                             min_rnd_timeout,
@@ -253,16 +276,36 @@ impl PureModel for EchoClientState {
                         },
                     ..
                 } = state.substate()
+                {
+                    assert_eq!(uid, *request);
+
+                    // We randomize client's recv timeout to force it fail sometimes
+                    dispatcher.dispatch_effect(RngEffectfulAction::RandomRange {
+                        uid,
+                        low: *min_rnd_timeout,
+                        high: *max_rnd_timeout,
+                        on_result: callback!(|(uid: Uid, timeout: u64)| EchoClientAction::RandomRecvTimeout { uid, timeout }),
+                    });
+                } else {
+                    unreachable!()
+                }
+            }
+            EchoClientAction::RandomRecvTimeout { uid, timeout } => {
+                if let EchoClientState {
+                    status:
+                        EchoClientStatus::Sending {
+                            connection,
+                            request,
+                            data,
+                        },
+                    ..
+                } = state.substate()
                 {
                     assert_eq!(uid, *request);
                     let connection = *connection;
                     let sent_data = data.clone();
                     let count = data.len();
-
-                    // We randomize client's recv timeout to force it fail sometimes
-                    let timeout_range = *min_rnd_timeout..*max_rnd_timeout;
-                    let prng: &mut PRNGState = state.substate_mut();
-                    let timeout = Timeout::Millis(prng.rng.random_range(timeout_range));
+                    let timeout = Timeout::Millis(timeout);
 
                     let request = state.new_uid();
 
@@ -287,7 +330,11 @@ impl PureModel for EchoClientState {
                         on_error: callback!(|(uid: Uid, error: String)| EchoClientAction::RecvError { uid, error }),
                     });
                 } else {
-                    unreachable!()
+                    // The connection closed while we waited for the timeout
+                    info!(
+                        "|ECHO_CLIENT| dropping recv timeout for stale request {:?}",
+                        uid
+                    );
                 }
             }
             EchoClientAction::SendTimeout { uid } => {
@@ -19,6 +19,11 @@ pub enum EchoClientStatus {
     Connected {
         connection: Uid,
     },
+    /// Waiting for the random data to send
+    PreparingSend {
+        connection: Uid,
+        request: Uid,
+    },
     Sending {
         connection: Uid,
         request: Uid,
@@ -1,14 +1,14 @@
 use crate::{
-    automaton::{ModelState, RegisterModel, RunnerBuilder, Timeout},
+    automaton::{ModelHarness, ModelState, Redispatch, RegisterModel, RunnerBuilder, Timeout, Uid},
+    models::effectful::rng::action::RngEffectfulAction,
     models::pure::{
         net::tcp::state::TcpState,
         net::tcp_client::state::TcpClientState,
         net::tcp_server::state::TcpServerState,
-        prng::state::{PRNGConfig, PRNGState},
         tests::{
             echo_client::{
                 action::EchoClientAction,
-                state::{EchoClientConfig, EchoClientState},
+                state::{EchoClientConfig, EchoClientState, EchoClientStatus},
             },
             echo_server::{
                 action::EchoServerAction,
@@ -42,7 +42,6 @@ impl EchoServer {
 
 #[derive(ModelState, Debug)]
 pub struct EchoClient {
-    pub prng: PRNGState,
     pub time: TimeState,
     pub tcp: TcpState,
     pub tcp_client: TcpClientState,
@@ -52,7 +51,6 @@ pub struct EchoClient {
 impl EchoClient {
     pub fn from_config(config: EchoClientConfig) -> Self {
         Self {
-            prng: PRNGState::from_config(PRNGConfig { seed: 1337 }),
             time: TimeState::default(),
             tcp: TcpState::new(),
             tcp_client: TcpClientState::new(),
@@ -147,3 +145,94 @@ fn echo_server_50_clients() {
     // WARNING: this test probably needs an increase in the fd limit (ulimit -n 10000)
     echo_server_n_clients(50)
 }
+
+fn echo_client() -> ModelHarness<EchoClient> {
+    ModelHarness::new(
+        RunnerBuilder::<EchoClient>::new()
+            .register::<EchoClientState>()
+            .instance(
+                EchoClient::from_config(EchoClientConfig {
+                    connect_to_address: "127.0.0.1:8888".to_string(),
+                    connect_timeout: Timeout::Millis(1000),
+                    poll_timeout: 100,
+                    max_connection_attempts: 10,
+                    retry_interval_ms: 500,
+                    max_send_size: 1024,
+                    min_rnd_timeout: 1000,
+                    max_rnd_timeout: 10000,
+                }),
+                || EchoClientAction::Tick.into(),
+            ),
+    )
+}
+
+#[test]
+fn echo_client_asks_for_random_data_to_send() {
+    let mut harness = echo_client();
+    let connection = Uid::from(1u64);
+    harness.state_mut::<EchoClientState>(0).status = EchoClientStatus::Connected { connection };
+
+    harness.input(
+        0,
+        EchoClientAction::PollSuccess {
+            uid: Uid::from(2u64),
+            events: Vec::new(),
+        },
+    );
+    let (
+        0,
+        RngEffectfulAction::RandomBytes {
+            uid,
+            min_len: 1,
+            max_len: 1024,
+            ..
+        },
+    ) = harness.expect_effect::<RngEffectfulAction>()
+    else {
+        panic!("expected a request for random bytes")
+    };
+    harness.assert_no_effects();
+    assert!(matches!(
+        harness.state::<EchoClientState>(0).status,
+        EchoClientStatus::PreparingSend { connection: c, request } if c == connection && request == uid
+    ));
+}
+
+#[test]
+fn echo_client_drops_randomness_for_a_closed_connection() {
+    let mut harness = echo_client();
+    let connection = Uid::from(1u64);
+    harness.state_mut::<EchoClientState>(0).status = EchoClientStatus::Connected { connection };
+
+    harness.input(
+        0,
+        EchoClientAction::PollSuccess {
+            uid: Uid::from(2u64),
+            events: Vec::new(),
+        },
+    );
+    let (_, RngEffectfulAction::RandomBytes { uid, on_result, .. }) =
+        harness.expect_effect::<RngEffectfulAction>()
+    else {
+        panic!("expected a request for random bytes")
+    };
+
+    // the connection closes before the data arrives
+    harness.state_mut::<EchoClientState>(0).status = EchoClientStatus::Connecting;
+
+    // answer through the callback as a recording stores it, so it is looked
+    // up by name the way a replay would
+    let on_result: Redispatch<(Uid, Vec<u8>)> =
+        bincode::deserialize(&bincode::serialize(&on_result).unwrap()).unwrap();
+    harness.respond(0, &on_result, (uid, vec![7; 16]));
+    harness.input(
+        0,
+        EchoClientAction::RandomRecvTimeout { uid, timeout: 1000 },
+    );
+
+    harness.assert_no_effects();
+    assert!(matches!(
+        harness.state::<EchoClientState>(0).status,
+        EchoClientStatus::Connecting
+    ));
+}
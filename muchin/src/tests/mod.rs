@@ -1 +1,3 @@
-pub mod echo_network;
\ No newline at end of file
+pub mod echo_network;
+mod model_harness;
+mod rng;
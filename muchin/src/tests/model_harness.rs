@@ -0,0 +1,157 @@
+use crate::{
+    automaton::{
+        Action, ActionKind, Dispatcher, ModelHarness, ModelState, PureModel, Redispatch,
+        RegisterModel, RunnerBuilder, State,
+    },
+    callback,
+};
+use muchin_model_state_derive::ModelState;
+use serde::{Deserialize, Serialize};
+use type_uuid::TypeUuid;
+
+#[derive(Clone, PartialEq, Eq, TypeUuid, Serialize, Deserialize, Debug)]
+#[uuid = "5f0c2b7e-58a4-4d4c-9a43-0d8b1d6b3c11"]
+enum CounterAction {
+    Tick,
+    Add { value: u64 },
+    Fetch,
+}
+
+impl Action for CounterAction {
+    const KIND: ActionKind = ActionKind::Pure;
+}
+
+/// Stands in for an effectful model we never want to run in a unit test.
+#[derive(Clone, PartialEq, Eq, TypeUuid, Serialize, Deserialize, Debug)]
+#[uuid = "c8a2f0d4-6b1e-4f57-8d0a-2e9f5b7c4a22"]
+enum OutsideAction {
+    Report { total: u64 },
+    Lookup { on_result: Redispatch<u64> },
+}
+
+impl Action for OutsideAction {
+    const KIND: ActionKind = ActionKind::Effectful;
+}
+
+#[derive(Debug, Default)]
+struct CounterState {
+    total: u64,
+}
+
+impl RegisterModel for CounterState {
+    fn register<Substate: ModelState>(builder: RunnerBuilder<Substate>) -> RunnerBuilder<Substate> {
+        builder.model_pure::<Self>()
+    }
+}
+
+impl PureModel for CounterState {
+    type Action = CounterAction;
+
+    fn process_pure<Substate: ModelState>(
+        state: &mut State<Substate>,
+        action: Self::Action,
+        dispatcher: &mut Dispatcher,
+    ) {
+        let counter: &mut CounterState = state.substate_mut();
+
+        match action {
+            CounterAction::Tick => {}
+            CounterAction::Add { value } => {
+                counter.total += value;
+                dispatcher.dispatch_effect(OutsideAction::Report {
+                    total: counter.total,
+                });
+            }
+            CounterAction::Fetch => dispatcher.dispatch_effect(OutsideAction::Lookup {
+                on_result: callback!(|value: u64| CounterAction::Add { value }),
+            }),
+        }
+    }
+}
+
+#[derive(ModelState, Debug, Default)]
+struct Node {
+    counter: CounterState,
+}
+
+fn harness(instances: usize) -> ModelHarness<Node> {
+    let mut builder = RunnerBuilder::<Node>::new().register::<CounterState>();
+    for _ in 0..instances {
+        builder = builder.instance(Node::default(), || CounterAction::Tick.into());
+    }
+    ModelHarness::new(builder)
+}
+
+#[test]
+fn scripted_inputs_capture_effects() {
+    let mut harness = harness(2);
+
+    harness.script(
+        0,
+        [
+            CounterAction::Add { value: 2 },
+            CounterAction::Add { value: 3 },
+        ],
+    );
+    harness.input(1, CounterAction::Add { value: 7 });
+
+    assert_eq!(harness.state::<CounterState>(0).total, 5);
+    assert_eq!(harness.state::<CounterState>(1).total, 7);
+    assert_eq!(
+        harness.take_effects::<OutsideAction>(),
+        vec![
+            (0, OutsideAction::Report { total: 2 }),
+            (0, OutsideAction::Report { total: 5 }),
+            (1, OutsideAction::Report { total: 7 }),
+        ]
+    );
+    harness.assert_no_effects();
+}
+
+#[test]
+fn respond_to_captured_effect() {
+    let mut harness = harness(1);
+
+    harness.input(0, CounterAction::Fetch);
+    let (instance, OutsideAction::Lookup { on_result }) = harness.expect_effect::<OutsideAction>()
+    else {
+        panic!("expected a lookup")
+    };
+    assert_eq!(instance, 0);
+    harness.assert_no_effects();
+    assert_eq!(harness.state::<CounterState>(0).total, 0);
+
+    harness.respond(0, &on_result, 40);
+    assert_eq!(harness.state::<CounterState>(0).total, 40);
+    assert_eq!(
+        harness.expect_effect::<OutsideAction>(),
+        (0, OutsideAction::Report { total: 40 })
+    );
+}
+
+#[test]
+fn runner_metrics_count_actions() {
+    let mut harness = harness(2);
+    harness.runner.enable_metrics();
+
+    for _ in 0..3 {
+        harness.runner.step();
+    }
+
+    let metrics = harness.runner.metrics_snapshot().unwrap();
+    assert_eq!(metrics.queues.len(), 2);
+    // every step runs one tick per instance, since nothing else is queued
+    assert_eq!(metrics.total_processed(), 6);
+    let counter = &metrics.models[std::any::type_name::<CounterAction>()];
+    assert!(counter.pure);
+    assert_eq!(counter.processed, 6);
+    assert!(counter.max_time <= counter.total_time);
+    for queue in &metrics.queues {
+        assert_eq!(queue.ticks, 3);
+        assert_eq!(queue.depth, 0);
+    }
+
+    harness.runner.disable_metrics();
+    harness.runner.step();
+    assert!(harness.runner.metrics_snapshot().is_none());
+}
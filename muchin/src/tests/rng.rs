@@ -0,0 +1,32 @@
+use crate::models::effectful::rng::state::RngState;
+
+#[test]
+fn seeded_rng_is_deterministic() {
+    let mut a = RngState::seeded(1337);
+    let mut b = RngState::seeded(1337);
+
+    for _ in 0..100 {
+        let value = a.random_range(10, 20);
+        assert_eq!(value, b.random_range(10, 20));
+        assert!((10..20).contains(&value));
+    }
+
+    let (mut x, mut y) = ([0u8; 64], [0u8; 64]);
+    a.fill_bytes(&mut x);
+    b.fill_bytes(&mut y);
+    assert_eq!(x, y);
+
+    let mut c = RngState::seeded(1338);
+    let mut z = [0u8; 64];
+    c.random_range(10, 20);
+    c.fill_bytes(&mut z);
+    assert_ne!(x, z);
+}
+
+#[test]
+fn thread_rng_stays_in_range() {
+    let mut rng = RngState::thread();
+    for _ in 0..100 {
+        assert!((5..6).contains(&rng.random_range(5, 6)));
+    }
+}
@@ -0,0 +1,161 @@
+//! Operator endpoints beside the health checks.
+//!
+//! `/admin/debug-bundle` answers with the consensus process's
+//! `debug_bundle`: a gzipped tar archive of its state digest, recent views,
+//! tips, pending votes, latest events and config, to attach to bug reports.
+//! It holds no keys.
+//!
+//! `POST /admin/force-end-view` sends our end-view message for the current
+//! view without waiting for the timeout (see `force_end_view`), to rotate
+//! away from a degraded leader. It answers 409 if we already sent one for
+//! this view.
+//!
+//! The process lives in the daemon's event loop, so handlers send the loop
+//! an [`AdminRequest`] over a channel and wait for its answer; while the
+//! daemon runs no consensus, they answer 503.
+//!
+//! Admin endpoints expose the whole process state, so they are kept apart
+//! from the web UI: the daemon serves them on their own listener bound to
+//! localhost, without the web UI's CORS headers, and only when it is given
+//! a bearer token to require (`--admin-token-file`). Requests without that
+//! token are answered 401.
+
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{
+        header::{AUTHORIZATION, CONTENT_DISPOSITION, CONTENT_TYPE, WWW_AUTHENTICATE},
+        HeaderMap, StatusCode,
+    },
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Router,
+};
+use tokio::sync::{mpsc, oneshot};
+
+/// What the event loop is asked to do, with where to send its answer
+pub enum AdminRequest {
+    /// The process's debug bundle
+    DebugBundle(oneshot::Sender<Vec<u8>>),
+    /// Whether forcing an end-view sent one
+    ForceEndView(oneshot::Sender<bool>),
+}
+
+/// The HTTP server's side of the channel to the event loop
+#[derive(Clone)]
+pub struct Admin {
+    requests: mpsc::Sender<AdminRequest>,
+}
+
+impl Admin {
+    /// The handle for the HTTP server, and the requests the event loop answers
+    pub fn new() -> (Self, mpsc::Receiver<AdminRequest>) {
+        let (requests, received) = mpsc::channel(4);
+        (Admin { requests }, received)
+    }
+
+    /// The admin endpoints, answering only requests that carry `token`
+    pub fn routes(&self, token: &str) -> Router {
+        Router::new()
+            .route("/admin/debug-bundle", get(debug_bundle))
+            .route("/admin/force-end-view", post(force_end_view))
+            .route_layer(middleware::from_fn_with_state(
+                Arc::<str>::from(token),
+                require_token,
+            ))
+            .with_state(self.clone())
+    }
+
+    /// Sends the event loop the request `request` builds, and waits for its
+    /// answer; `None` if no process is running to answer
+    async fn ask<T>(&self, request: impl FnOnce(oneshot::Sender<T>) -> AdminRequest) -> Option<T> {
+        let (reply, answer) = oneshot::channel();
+        self.requests.send(request(reply)).await.ok()?;
+        answer.await.ok()
+    }
+}
+
+/// Reads the bearer token admin requests have to carry, the file's contents
+/// without surrounding whitespace
+pub fn load_token(path: &Path) -> io::Result<String> {
+    let token = std::fs::read_to_string(path)?.trim().to_string();
+    if token.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the admin token file is empty",
+        ));
+    }
+    Ok(token)
+}
+
+/// Whether `headers` carry `token` as their bearer token
+pub fn authorized(headers: &HeaderMap, token: &str) -> bool {
+    let Some(given) = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+    else {
+        return false;
+    };
+    // compared in full whatever the first difference, so timing doesn't
+    // reveal how much of a guess was right
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+async fn require_token(State(token): State<Arc<str>>, request: Request, next: Next) -> Response {
+    if !authorized(request.headers(), &token) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            [(WWW_AUTHENTICATE, "Bearer")],
+            "missing or wrong bearer token\n",
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
+
+async fn debug_bundle(State(admin): State<Admin>) -> Response {
+    match admin.ask(AdminRequest::DebugBundle).await {
+        Some(bundle) => (
+            [
+                (CONTENT_TYPE, "application/gzip"),
+                (
+                    CONTENT_DISPOSITION,
+                    "attachment; filename=\"morpheus-debug.tar.gz\"",
+                ),
+            ],
+            bundle,
+        )
+            .into_response(),
+        None => no_process(),
+    }
+}
+
+async fn force_end_view(State(admin): State<Admin>) -> Response {
+    match admin.ask(AdminRequest::ForceEndView).await {
+        Some(true) => "sent an end-view message for the current view\n".into_response(),
+        Some(false) => (
+            StatusCode::CONFLICT,
+            "already sent an end-view message for the current view\n",
+        )
+            .into_response(),
+        None => no_process(),
+    }
+}
+
+fn no_process() -> Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        "no consensus process running\n",
+    )
+        .into_response()
+}
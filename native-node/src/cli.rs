@@ -1,5 +1,11 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+
 use argh::FromArgs;
 
+use crate::consensus::Channel;
+
 #[derive(FromArgs, PartialEq, Debug)]
 /// Top-level command.
 pub struct TopLevel {
@@ -26,4 +32,136 @@ pub struct RunDaemon {
     #[argh(option, default = "17272")]
     /// listen port for the webui (default none)
     pub webui_listen: u16,
+    #[argh(option, default = "17274")]
+    /// port of the admin endpoints, served on localhost only (default 17274)
+    pub admin_listen: u16,
+    #[argh(option)]
+    /// file holding the bearer token admin requests must carry; without it the admin endpoints are off
+    pub admin_token_file: Option<String>,
+    #[argh(option, default = "20")]
+    /// max updates per second pushed to each browser observer (default 20)
+    pub observer_rate: u32,
+    #[argh(option, default = "50")]
+    /// burst allowance for each browser observer (default 50)
+    pub observer_burst: u32,
+    #[argh(option, default = "String::from(\".\")")]
+    /// directory the node keeps its state in, checked by /readyz (default .)
+    pub data_dir: String,
+    #[argh(option)]
+    /// take part in consensus as this member of the committee (default none)
+    pub member: Option<u32>,
+    #[argh(switch)]
+    /// run a committee of one with instant finality and no timeouts, for local development
+    pub dev: bool,
+    #[argh(option, default = "4")]
+    /// number of committee members (default 4)
+    pub committee_size: u32,
+    #[argh(option, default = "0")]
+    /// seed the development committee's keys are dealt from (default 0)
+    pub committee_seed: u64,
+    #[argh(option)]
+    /// JSON file with the names, regions and contacts of the committee's members, shown in logs (default none)
+    pub committee_metadata: Option<String>,
+    #[argh(option, default = "1000")]
+    /// consensus timeout unit in milliseconds (default 1000)
+    pub delta_ms: u64,
+    #[argh(option)]
+    /// multiaddress of a peer to dial at startup, may be repeated
+    pub peer: Vec<String>,
+    #[argh(option, default = "Transports::default()")]
+    /// transports to listen on: webrtc, quic, or both as webrtc,quic (default webrtc)
+    pub transports: Transports,
+    #[argh(option, default = "17273")]
+    /// QUIC port, when listening on QUIC (default 17273)
+    pub quic_port: u16,
+    #[argh(option)]
+    /// zstd level, 1 to 22, to compress blocks at for peers that accept it (default off)
+    pub compression_level: Option<i32>,
+    #[argh(option, default = "Channels::default()")]
+    /// consensus channels to follow when not a member: blocks, votes, view-change, keys, comma-separated (default all)
+    pub channels: Channels,
+}
+
+/// The consensus channels the daemon subscribes to
+///
+/// Members need every channel; a node only following the chain can leave
+/// out the vote and view-change traffic.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Channels(pub BTreeSet<Channel>);
+
+impl Default for Channels {
+    fn default() -> Self {
+        Channels(Channel::ALL.into())
+    }
+}
+
+impl FromStr for Channels {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(|name| name.trim().parse())
+            .collect::<Result<_, _>>()
+            .map(Channels)
+    }
+}
+
+impl fmt::Display for Channels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = self.0.iter().map(|c| c.name()).collect::<Vec<_>>();
+        write!(f, "{}", names.join(","))
+    }
+}
+
+/// Which transports the daemon listens on
+///
+/// Browsers can only reach WebRTC-direct; QUIC is for links between
+/// servers. Either can be dialed whichever are listened on.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Transports {
+    pub webrtc: bool,
+    pub quic: bool,
+}
+
+impl Default for Transports {
+    fn default() -> Self {
+        Transports {
+            webrtc: true,
+            quic: false,
+        }
+    }
+}
+
+impl FromStr for Transports {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut transports = Transports {
+            webrtc: false,
+            quic: false,
+        };
+        for name in s.split(',').map(str::trim) {
+            match name {
+                "webrtc" => transports.webrtc = true,
+                "quic" => transports.quic = true,
+                _ => {
+                    return Err(format!(
+                        "unknown transport {name:?}, expected webrtc or quic"
+                    ))
+                }
+            }
+        }
+        Ok(transports)
+    }
+}
+
+impl fmt::Display for Transports {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.webrtc, self.quic) {
+            (true, true) => write!(f, "webrtc,quic"),
+            (true, false) => write!(f, "webrtc"),
+            (false, true) => write!(f, "quic"),
+            (false, false) => write!(f, "none"),
+        }
+    }
 }
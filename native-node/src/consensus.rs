@@ -0,0 +1,571 @@
+//! Running a Morpheus process over libp2p gossipsub.
+//!
+//! Every member subscribes to the consensus topics and publishes everything
+//! its process sends there, point-to-point messages included: each goes out
+//! as an [`Envelope`] naming its recipient, and the others drop it on
+//! arrival. Gossipsub already relays each message to every subscriber, so
+//! this costs little for a committee-sized mesh and saves keeping a second
+//! protocol for direct sends.
+//!
+//! Messages are split over one topic per [`Channel`] under
+//! [`CONSENSUS_TOPIC`]: blocks, votes and QCs, view changes, and key
+//! rotations. Members need all of them, but a node that only follows the
+//! chain can subscribe to blocks alone and leave the vote traffic, by far the
+//! most frequent, to the committee. A message published on a topic other
+//! than its own channel's is dropped.
+//!
+//! Gossipsub only drops repeats of the same envelope. The same block or QC
+//! also arrives in envelopes from several members, since QCs are formed and
+//! certificates relayed by whoever collects the votes, so the transport keeps
+//! a [`DedupCache`] of what it delivered and drops a message it has already
+//! handed to the process before it is decoded any further or validated.
+//!
+//! Gossipsub signs each message with its publisher's libp2p key, and every
+//! envelope carries its sender's `PeerBinding`, the peer id it runs under
+//! signed with its consensus key (see the `handshake` module). A binding is
+//! checked the first time a peer id publishes and remembered from then on,
+//! so members we only reach through relays are known as well as the ones we
+//! shook hands with. An envelope is only delivered if the member behind its
+//! publisher's peer id is the sender it names, so [`GossipTransport`] claims
+//! authenticated links, and a process trusting them can skip checking the
+//! votes members send it directly. An envelope from a publisher without a
+//! valid binding is dropped.
+//!
+//! An envelope is `from: u32 BE | to: u32 BE | binding_len: u16 BE | binding
+//! | message`, with `to` zero for messages to every member, `binding` the
+//! sender's compressed `Signed<PeerBinding>` (empty if it has none) and
+//! `message` in the versioned encoding of `Message::to_wire`, so nodes built
+//! at different commits reject each other's messages cleanly instead of
+//! misreading them.
+//!
+//! Blocks may go out zstd-compressed when the daemon is given a compression
+//! level. Whether a node accepts compressed messages is announced in its
+//! identify agent version, see [`agent_version`], and in its handshake
+//! hello. Gossipsub relays the bytes we publish to every subscriber
+//! unchanged, including ones we aren't connected to, so a message is only
+//! compressed once every other committee member has announced in its hello
+//! that it accepts compression, and every peer we are connected to has too.
+//! Nodes that only follow the chain and reach us through relays aren't
+//! known to us; they have to accept compression to follow a committee that
+//! compresses, which every build with the `compression` feature does.
+
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fmt;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use hellas_morpheus::test_harness::TestTransaction;
+use hellas_morpheus::transport::{flush, NetworkTransport};
+use hellas_morpheus::{
+    BlockKey, DedupCache, DedupStats, EventFilter, EventKind, Identity, KeyBook, LinkSecurity,
+    Message, MessageKind, MetadataRegistry, MorpheusProcess, PeerBinding, ProtocolEvent, Signed,
+    Subscription, Verifier, WireCompression, WireError, DEFAULT_DEDUP_CAPACITY,
+};
+use libp2p::{gossipsub, PeerId};
+use rand::{rngs::StdRng, SeedableRng};
+
+/// The prefix of the gossipsub topics consensus messages are published on
+pub const CONSENSUS_TOPIC: &str = "/hellas/morpheus/0.1.0";
+
+/// Which topic a consensus message is published on, by what it carries
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Channel {
+    /// Blocks, and the requests and responses that fetch missing ones
+    Blocks,
+    /// Votes and the QCs they form
+    Votes,
+    /// End-view messages, their certificates and start-view messages
+    ViewChange,
+    /// Announced signing keys
+    Keys,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 4] = [
+        Channel::Blocks,
+        Channel::Votes,
+        Channel::ViewChange,
+        Channel::Keys,
+    ];
+
+    /// The channel messages of `kind` go on
+    pub fn of(kind: MessageKind) -> Self {
+        match kind {
+            MessageKind::Block | MessageKind::GetBlock | MessageKind::BlockResponse => {
+                Channel::Blocks
+            }
+            MessageKind::NewVote | MessageKind::QC => Channel::Votes,
+            MessageKind::EndView | MessageKind::EndViewCert | MessageKind::StartView => {
+                Channel::ViewChange
+            }
+            MessageKind::KeyRotation => Channel::Keys,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Channel::Blocks => "blocks",
+            Channel::Votes => "votes",
+            Channel::ViewChange => "view-change",
+            Channel::Keys => "keys",
+        }
+    }
+
+    pub fn topic(self) -> gossipsub::IdentTopic {
+        gossipsub::IdentTopic::new(format!("{}/{}", CONSENSUS_TOPIC, self.name()))
+    }
+
+    /// The channel published on `topic`, if it is one of ours
+    pub fn from_topic(topic: &gossipsub::TopicHash) -> Option<Self> {
+        Channel::ALL
+            .into_iter()
+            .find(|channel| channel.topic().hash() == *topic)
+    }
+}
+
+impl fmt::Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Channel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Channel::ALL
+            .into_iter()
+            .find(|channel| channel.name() == s)
+            .ok_or_else(|| {
+                format!("unknown channel {s:?}, expected blocks, votes, view-change or keys")
+            })
+    }
+}
+
+/// What the daemon's process orders; payloads are opaque bytes for now
+pub type NodeTransaction = TestTransaction;
+
+/// Marks an agent version whose node accepts compressed messages
+const ACCEPTS_ZSTD: &str = "+zstd";
+
+/// The agent version announced over identify
+pub fn agent_version() -> String {
+    let mut version = format!("hellas-node/{}", env!("CARGO_PKG_VERSION"));
+    if WireCompression::available() {
+        version.push_str(ACCEPTS_ZSTD);
+    }
+    version
+}
+
+/// Whether a peer announcing `agent_version` accepts compressed messages
+pub fn accepts_compression(agent_version: &str) -> bool {
+    agent_version.ends_with(ACCEPTS_ZSTD)
+}
+
+/// One consensus message as published on the topic
+#[derive(Clone, Debug)]
+pub struct Envelope {
+    pub from: Identity,
+    /// `None` for messages to every member
+    pub to: Option<Identity>,
+    /// The sender's binding to the peer id it publishes under
+    pub binding: Option<Signed<PeerBinding>>,
+    pub message: Message<NodeTransaction>,
+}
+
+impl Envelope {
+    const HEADER_LEN: usize = 10;
+
+    pub fn encode(&self) -> Vec<u8> {
+        self.encode_with(None)
+    }
+
+    /// The encoding with blocks compressed as `compression` says, see
+    /// `Message::to_wire_with`
+    pub fn encode_with(&self, compression: Option<WireCompression>) -> Vec<u8> {
+        let to = self.to.as_ref().map_or(0, |to| to.0);
+        let mut binding = Vec::new();
+        if let Some(signed) = &self.binding {
+            signed
+                .serialize_compressed(&mut binding)
+                .expect("bindings serialize");
+        }
+        let mut bytes = Vec::with_capacity(Self::HEADER_LEN + binding.len());
+        bytes.extend_from_slice(&self.from.0.to_be_bytes());
+        bytes.extend_from_slice(&to.to_be_bytes());
+        bytes.extend_from_slice(&(binding.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(&binding);
+        bytes.extend_from_slice(&self.message.to_wire_with(compression));
+        bytes
+    }
+
+    /// Inverse of [`Envelope::encode`]
+    pub fn decode(bytes: &[u8]) -> Result<Self, WireError> {
+        if bytes.len() < Self::HEADER_LEN {
+            return Err(WireError::Malformed("truncated envelope".to_string()));
+        }
+        let from = u32::from_be_bytes(bytes[..4].try_into().unwrap());
+        let to = u32::from_be_bytes(bytes[4..8].try_into().unwrap());
+        let binding_len = u16::from_be_bytes(bytes[8..10].try_into().unwrap()) as usize;
+        let Some(mut binding) = bytes.get(Self::HEADER_LEN..Self::HEADER_LEN + binding_len) else {
+            return Err(WireError::Malformed("truncated binding".to_string()));
+        };
+        let binding = match binding_len {
+            0 => None,
+            _ => Some(
+                Signed::<PeerBinding>::deserialize_compressed(&mut binding)
+                    .map_err(|e| WireError::Malformed(format!("bad binding: {}", e)))?,
+            ),
+        };
+        Ok(Envelope {
+            from: Identity(from),
+            to: (to != 0).then_some(Identity(to)),
+            binding,
+            message: Message::from_wire(&bytes[Self::HEADER_LEN + binding_len..])?,
+        })
+    }
+}
+
+/// Gossipsub settings for the consensus topic
+///
+/// Messages are identified by their contents, so the same vote relayed by
+/// two peers is only delivered once.
+pub fn gossipsub_config() -> Result<gossipsub::Config, gossipsub::ConfigBuilderError> {
+    gossipsub::ConfigBuilder::default()
+        .heartbeat_interval(Duration::from_secs(1))
+        .validation_mode(gossipsub::ValidationMode::Strict)
+        .message_id_fn(|message| {
+            let mut hasher = DefaultHasher::new();
+            message.data.hash(&mut hasher);
+            gossipsub::MessageId::from(hasher.finish().to_be_bytes().to_vec())
+        })
+        .build()
+}
+
+/// Member `member`'s key book in a committee of `size` dealt from `seed`
+///
+/// Every member deals the whole committee from the same seed and keeps its
+/// own book, so anyone who knows the seed holds every key. This is for
+/// development clusters only; a real committee runs
+/// `hellas_morpheus::KeySetup` instead.
+pub fn dev_keybook(seed: u64, size: u32, member: u32) -> anyhow::Result<KeyBook> {
+    if member == 0 || member > size {
+        anyhow::bail!("member {} is not in a committee of {}", member, size);
+    }
+    let mut books = KeyBook::committee_setup(size as usize, &mut StdRng::seed_from_u64(seed));
+    Ok(books.swap_remove(member as usize - 1))
+}
+
+/// Reads the committee's `MetadataRegistry` from the JSON file at `path`
+pub fn load_metadata(path: &Path) -> anyhow::Result<MetadataRegistry> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| anyhow::anyhow!("Could not read {}: {}", path.display(), e))?;
+    serde_json::from_slice(&bytes)
+        .map_err(|e| anyhow::anyhow!("Could not parse {}: {}", path.display(), e))
+}
+
+/// Builds member `member`'s process for a dev committee, see [`dev_keybook`]
+pub fn dev_process(
+    seed: u64,
+    size: u32,
+    member: u32,
+    delta: Duration,
+) -> anyhow::Result<MorpheusProcess<NodeTransaction>> {
+    let kb = dev_keybook(seed, size, member)?;
+    let mut process = MorpheusProcess::try_new(kb, Identity(member), size, (size - 1) / 3)
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    process.delta = delta.as_millis();
+    Ok(process)
+}
+
+/// Builds the process of a single-node dev committee, see
+/// [`MorpheusProcess::dev`]
+pub fn dev_single_process(seed: u64, delta: Duration) -> MorpheusProcess<NodeTransaction> {
+    let mut process = MorpheusProcess::dev(seed);
+    process.delta = delta.as_millis();
+    process
+}
+
+/// Carries a process's messages between it and the gossipsub topic
+///
+/// The swarm is driven elsewhere: it pushes what arrives on the topic with
+/// [`GossipTransport::deliver`] and publishes what
+/// [`GossipTransport::take_outbound`] returns.
+pub struct GossipTransport {
+    pub me: Identity,
+    inbound: VecDeque<(Identity, Message<NodeTransaction>)>,
+    outbound: Vec<Envelope>,
+    /// Messages recently queued, whoever sent them
+    dedup: DedupCache,
+    /// Our binding, carried by every envelope we send
+    binding: Option<Signed<PeerBinding>>,
+    /// Checks the bindings envelopes carry
+    verifier: Box<dyn Verifier>,
+    /// The member behind each peer id whose binding has been checked
+    bound: HashMap<PeerId, Identity>,
+}
+
+impl GossipTransport {
+    /// A transport for the member `me` of the committee `verifier` checks
+    /// signatures for
+    pub fn new(me: Identity, verifier: impl Verifier + 'static) -> Self {
+        GossipTransport {
+            me,
+            inbound: VecDeque::new(),
+            outbound: Vec::new(),
+            dedup: DedupCache::new(DEFAULT_DEDUP_CAPACITY),
+            binding: None,
+            verifier: Box::new(verifier),
+            bound: HashMap::new(),
+        }
+    }
+
+    /// Sends `binding` along with every envelope from now on
+    pub fn set_binding(&mut self, binding: Signed<PeerBinding>) {
+        self.binding = Some(binding);
+    }
+
+    /// The member behind `peer`, as remembered or as `binding` proves
+    fn member_of(
+        &mut self,
+        peer: &PeerId,
+        binding: Option<&Signed<PeerBinding>>,
+    ) -> Option<Identity> {
+        if let Some(member) = self.bound.get(peer) {
+            return Some(member.clone());
+        }
+        let member = binding?.member_of(&peer.to_bytes(), self.verifier.as_ref())?;
+        self.bound.insert(*peer, member.clone());
+        Some(member)
+    }
+
+    /// Queues a message received on `channel`'s topic, published by the
+    /// peer `source`, unless it is our own, addressed to someone else, one
+    /// queued recently, one that belongs on another channel, or one whose
+    /// envelope names a sender other than the member behind `source`
+    ///
+    /// Returns whether it was queued.
+    pub fn deliver(&mut self, channel: Channel, source: Option<&PeerId>, data: &[u8]) -> bool {
+        let envelope = match Envelope::decode(data) {
+            Ok(envelope) => envelope,
+            Err(e) => {
+                tracing::debug!(len = data.len(), %e, "Dropping undecodable consensus message");
+                return false;
+            }
+        };
+        if Channel::of(envelope.message.kind()) != channel {
+            tracing::debug!(
+                from = envelope.from.0,
+                %channel,
+                kind = ?envelope.message.kind(),
+                "Dropping consensus message published on the wrong channel"
+            );
+            return false;
+        }
+        if envelope.from == self.me || envelope.to.as_ref().is_some_and(|to| *to != self.me) {
+            return false;
+        }
+        let publisher = source.and_then(|source| self.member_of(source, envelope.binding.as_ref()));
+        if publisher.as_ref() != Some(&envelope.from) {
+            tracing::debug!(
+                from = envelope.from.0,
+                publisher = ?publisher,
+                "Dropping consensus message published by someone other than its sender"
+            );
+            return false;
+        }
+        if !self.dedup.admit(&envelope.message) {
+            tracing::trace!(
+                from = envelope.from.0,
+                "Dropping repeated consensus message"
+            );
+            return false;
+        }
+        self.inbound.push_back((envelope.from, envelope.message));
+        true
+    }
+
+    pub fn dedup_stats(&self) -> DedupStats {
+        self.dedup.stats()
+    }
+
+    /// Everything the process sent since the last call
+    pub fn take_outbound(&mut self) -> Vec<Envelope> {
+        std::mem::take(&mut self.outbound)
+    }
+}
+
+impl NetworkTransport<NodeTransaction> for GossipTransport {
+    fn send(&mut self, to: &Identity, message: Message<NodeTransaction>) {
+        self.outbound.push(Envelope {
+            from: self.me.clone(),
+            to: Some(to.clone()),
+            binding: self.binding.clone(),
+            message,
+        });
+    }
+
+    fn broadcast(&mut self, message: Message<NodeTransaction>) {
+        self.outbound.push(Envelope {
+            from: self.me.clone(),
+            to: None,
+            binding: self.binding.clone(),
+            message,
+        });
+    }
+
+    fn receive(&mut self) -> Option<(Identity, Message<NodeTransaction>)> {
+        self.inbound.pop_front()
+    }
+
+    fn link_security(&self) -> LinkSecurity {
+        LinkSecurity::Authenticated
+    }
+}
+
+/// A process together with its gossip transport, driven by the daemon's
+/// event loop
+pub struct ConsensusNode {
+    pub process: MorpheusProcess<NodeTransaction>,
+    transport: GossipTransport,
+    finalized: Subscription,
+    views: Subscription,
+    started: Instant,
+    /// What we compress blocks with, if at all
+    compression: Option<WireCompression>,
+    /// Whether each connected peer accepts compressed messages
+    peer_compression: HashMap<PeerId, bool>,
+}
+
+impl ConsensusNode {
+    pub fn new(mut process: MorpheusProcess<NodeTransaction>) -> Self {
+        let finalized =
+            process.subscribe(EventFilter::default().kinds([EventKind::BlockFinalized]));
+        let views = process.subscribe(EventFilter::default().kinds([EventKind::ViewChanged]));
+        ConsensusNode {
+            transport: GossipTransport::new(process.id.clone(), process.kb.clone()),
+            process,
+            finalized,
+            views,
+            started: Instant::now(),
+            compression: None,
+            peer_compression: HashMap::new(),
+        }
+    }
+
+    /// Compresses blocks with `compression` towards peers that accept it
+    pub fn with_compression(mut self, compression: Option<WireCompression>) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Sends `binding`, ours to the peer id we publish under, with every
+    /// message, so members that only see them relayed can check who sent them
+    pub fn set_binding(&mut self, binding: Signed<PeerBinding>) {
+        self.transport.set_binding(binding);
+    }
+
+    /// Records what `peer` announced over identify
+    pub fn on_identify(&mut self, peer: PeerId, agent_version: &str) {
+        self.peer_compression
+            .insert(peer, accepts_compression(agent_version));
+    }
+
+    /// Forgets `peer` once its last connection closes
+    pub fn on_disconnect(&mut self, peer: &PeerId) {
+        self.peer_compression.remove(peer);
+    }
+
+    /// The bytes to publish `envelope` as
+    ///
+    /// Every subscriber is relayed the same bytes, so blocks are compressed
+    /// only if each other committee member is among `members_accepting`
+    /// (from their hellos) and every connected peer accepts it too. A peer we
+    /// haven't identified yet counts as one that doesn't.
+    pub fn encode(
+        &self,
+        envelope: &Envelope,
+        connected: &[PeerId],
+        members_accepting: &BTreeSet<Identity>,
+    ) -> Vec<u8> {
+        let committee_accepts = (1..=self.process.n)
+            .map(Identity)
+            .filter(|member| *member != self.process.id)
+            .all(|member| members_accepting.contains(&member));
+        let peers_accept = connected
+            .iter()
+            .all(|peer| self.peer_compression.get(peer).copied().unwrap_or(false));
+        envelope.encode_with(hellas_morpheus::negotiate(
+            self.compression,
+            committee_accepts && peers_accept,
+        ))
+    }
+
+    /// Handles a message the peer `source` published on `channel`'s topic,
+    /// returning what to publish in response
+    pub fn on_gossip(
+        &mut self,
+        channel: Channel,
+        source: Option<&PeerId>,
+        data: &[u8],
+    ) -> Vec<Envelope> {
+        if self.transport.deliver(channel, source, data) {
+            self.process.poll_transport(&mut self.transport);
+        }
+        self.transport.take_outbound()
+    }
+
+    /// Checks timeouts and produces due blocks, returning what to publish
+    ///
+    /// The process's clock is milliseconds since the node started, the unit
+    /// `delta` is given in.
+    pub fn on_tick(&mut self) -> Vec<Envelope> {
+        let now = self.started.elapsed().as_millis();
+        self.process.tick_transport(now, &mut self.transport);
+        self.transport.take_outbound()
+    }
+
+    /// Sends our end-view message for the current view now, returning what
+    /// to publish, or `None` if we already sent one
+    ///
+    /// See `MorpheusProcess::force_end_view`.
+    pub fn force_end_view(&mut self) -> Option<Vec<Envelope>> {
+        let mut to_send = Vec::new();
+        if !self.process.force_end_view(&mut to_send) {
+            return None;
+        }
+        flush(&mut self.transport, &mut to_send);
+        Some(self.transport.take_outbound())
+    }
+
+    /// How many repeated messages were dropped before reaching the process
+    pub fn dedup_stats(&self) -> DedupStats {
+        self.transport.dedup_stats()
+    }
+
+    /// Blocks finalized since the last call
+    pub fn take_finalized(&self) -> Vec<BlockKey> {
+        self.finalized
+            .drain()
+            .into_iter()
+            .filter_map(|event| match event {
+                ProtocolEvent::BlockFinalized { key } => Some(key),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Whether the process changed view since the last call
+    pub fn take_view_changed(&self) -> bool {
+        !self.views.drain().is_empty()
+    }
+
+    /// What observers are sent to start from: the process's state digest,
+    /// as JSON
+    pub fn snapshot(&self) -> Vec<u8> {
+        serde_json::to_vec(&self.process.state_digest()).expect("state digests serialize")
+    }
+}
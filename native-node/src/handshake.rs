@@ -0,0 +1,218 @@
+//! Agreeing on chain and protocol version before consensus traffic flows.
+//!
+//! Whenever a connection opens, each side sends the other a [`Hello`] over
+//! [`HANDSHAKE_PROTOCOL`], a request-response protocol with JSON bodies,
+//! and answers the other's with its own. A hello names the chain the node
+//! runs, the wire versions it speaks (see `hellas_morpheus::WIRE_VERSION`)
+//! and, for committee members, which member it is. Two nodes are compatible
+//! if they run the same chain, speak a wire version in common and don't
+//! run the same member; they then use the highest version they share.
+//! Nodes speak a single wire version today, so negotiating means checking
+//! it's the same one, but a node that learns a second can keep talking to
+//! peers that only speak either.
+//!
+//! Gossip relayed by a peer is only delivered once the peer's hello has
+//! been accepted, and a peer whose hello is rejected is disconnected. Peers
+//! that don't speak the handshake at all, such as browsers, stay connected
+//! for the observer protocol but are never delivered consensus messages from.
+//!
+//! A member proves which member it is with a `PeerBinding`: its libp2p
+//! peer id, signed with its consensus key. libp2p authenticates the peer id
+//! of every connection and every gossipsub publisher, so once a member's
+//! binding has been checked, anything published under that peer id is known
+//! to come from that member. A hello whose binding names another peer id or
+//! doesn't verify is rejected.
+//!
+//! A hello also says whether the node decodes compressed blocks, so a member
+//! knows when the whole committee does (see `ConsensusNode::encode`).
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+use hellas_morpheus::{
+    ChainId, Identity, PeerBinding, Signed, Verifier, WireCompression, WIRE_VERSION,
+};
+use libp2p::request_response::{self, ProtocolSupport};
+use libp2p::{PeerId, StreamProtocol};
+use serde::{Deserialize, Serialize};
+
+pub const HANDSHAKE_PROTOCOL: StreamProtocol = StreamProtocol::new("/hellas/handshake/0.1.0");
+
+/// What a node tells each peer it connects to
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hello {
+    pub chain: ChainId,
+    /// Every wire version the node can encode and decode
+    pub wire_versions: Vec<u8>,
+    /// The committee member the node runs, if any, bound to its peer id
+    pub binding: Option<Signed<PeerBinding>>,
+    /// Whether the node decodes compressed blocks; absent from the hellos
+    /// of nodes built before compression
+    #[serde(default)]
+    pub accepts_compression: bool,
+}
+
+/// Why a peer's [`Hello`] was rejected
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Rejection {
+    OtherChain {
+        ours: ChainId,
+        theirs: ChainId,
+    },
+    NoCommonVersion {
+        ours: Vec<u8>,
+        theirs: Vec<u8>,
+    },
+    /// A binding for another peer id, or not signed by the member it names
+    InvalidBinding(u32),
+    /// The member we run ourselves
+    ClaimsOurMember(u32),
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::OtherChain { ours, theirs } => write!(
+                f,
+                "runs chain {} instead of {}",
+                hex::encode(theirs.0),
+                hex::encode(ours.0)
+            ),
+            Rejection::NoCommonVersion { ours, theirs } => write!(
+                f,
+                "speaks wire versions {:?}, none of ours {:?}",
+                theirs, ours
+            ),
+            Rejection::InvalidBinding(member) => {
+                write!(f, "claims member {} without a valid binding", member)
+            }
+            Rejection::ClaimsOurMember(member) => {
+                write!(f, "claims member {}, which we run", member)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Rejection {}
+
+pub type Behaviour = request_response::json::Behaviour<Hello, Hello>;
+
+pub fn behaviour() -> Behaviour {
+    request_response::json::Behaviour::new(
+        [(HANDSHAKE_PROTOCOL, ProtocolSupport::Full)],
+        request_response::Config::default(),
+    )
+}
+
+/// A peer whose hello we accepted
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Accepted {
+    /// The highest wire version both sides speak
+    pub wire_version: u8,
+    pub member: Option<Identity>,
+    pub accepts_compression: bool,
+}
+
+/// Our side of the handshake, and the peers it has accepted
+pub struct Handshake {
+    hello: Hello,
+    /// Checks the bindings of the committee's members
+    verifier: Box<dyn Verifier>,
+    peers: HashMap<PeerId, Accepted>,
+}
+
+impl Handshake {
+    /// A node on the chain `verifier` checks signatures for, running the
+    /// member `binding` names if it is one
+    pub fn new(verifier: impl Verifier + 'static, binding: Option<Signed<PeerBinding>>) -> Self {
+        Handshake {
+            hello: Hello {
+                chain: verifier.chain_id(),
+                wire_versions: vec![WIRE_VERSION],
+                binding,
+                accepts_compression: WireCompression::available(),
+            },
+            verifier: Box::new(verifier),
+            peers: HashMap::new(),
+        }
+    }
+
+    /// What we send every peer
+    pub fn hello(&self) -> Hello {
+        self.hello.clone()
+    }
+
+    /// Whether `peer` announcing `theirs` is compatible with us, and if so
+    /// on what terms
+    pub fn check(&self, peer: &PeerId, theirs: &Hello) -> Result<Accepted, Rejection> {
+        let ours = &self.hello;
+        if theirs.chain != ours.chain {
+            return Err(Rejection::OtherChain {
+                ours: ours.chain,
+                theirs: theirs.chain,
+            });
+        }
+        let Some(wire_version) = ours
+            .wire_versions
+            .iter()
+            .filter(|version| theirs.wire_versions.contains(version))
+            .max()
+            .copied()
+        else {
+            return Err(Rejection::NoCommonVersion {
+                ours: ours.wire_versions.clone(),
+                theirs: theirs.wire_versions.clone(),
+            });
+        };
+        let member = match &theirs.binding {
+            Some(binding) => {
+                let member = binding
+                    .member_of(&peer.to_bytes(), self.verifier.as_ref())
+                    .ok_or(Rejection::InvalidBinding(binding.author.0))?;
+                if ours.binding.as_ref().map(|ours| &ours.author) == Some(&member) {
+                    return Err(Rejection::ClaimsOurMember(member.0));
+                }
+                Some(member)
+            }
+            None => None,
+        };
+        Ok(Accepted {
+            wire_version,
+            member,
+            accepts_compression: theirs.accepts_compression,
+        })
+    }
+
+    /// Checks the hello `peer` sent, remembering the peer if it is accepted
+    pub fn on_hello(&mut self, peer: PeerId, theirs: &Hello) -> Result<&Accepted, Rejection> {
+        let accepted = self.check(&peer, theirs)?;
+        self.peers.insert(peer, accepted);
+        Ok(&self.peers[&peer])
+    }
+
+    /// What we agreed with `peer`, if its hello was accepted
+    pub fn accepted(&self, peer: &PeerId) -> Option<&Accepted> {
+        self.peers.get(peer)
+    }
+
+    /// The member `peer` proved it runs, if any
+    pub fn member_of(&self, peer: &PeerId) -> Option<&Identity> {
+        self.peers.get(peer)?.member.as_ref()
+    }
+
+    /// The members we shook hands with whose hello said they decode
+    /// compressed blocks
+    pub fn members_accepting_compression(&self) -> BTreeSet<Identity> {
+        self.peers
+            .values()
+            .filter(|accepted| accepted.accepts_compression)
+            .filter_map(|accepted| accepted.member.clone())
+            .collect()
+    }
+
+    /// Forgets `peer` once its last connection closes, so it shakes hands
+    /// again when it comes back
+    pub fn on_disconnect(&mut self, peer: &PeerId) {
+        self.peers.remove(peer);
+    }
+}
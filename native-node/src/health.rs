@@ -0,0 +1,74 @@
+//! Liveness and readiness endpoints for orchestrators.
+//!
+//! `/healthz` answers as long as the daemon's HTTP server is up. `/readyz`
+//! answers 200 only once the consensus process reports itself ready (see
+//! `hellas_morpheus::Readiness`): synced to within a few views of the best
+//! known one, storage writable, and a quorum of peers connected. Until a
+//! process publishes its readiness, which it doesn't while the daemon runs
+//! no consensus, `/readyz` answers 503.
+
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use axum::{extract::State, http::StatusCode, routing::get, Router};
+use hellas_morpheus::Readiness;
+
+/// The latest readiness reported by the consensus process, shared with the
+/// HTTP server
+#[derive(Clone, Default)]
+pub struct Health {
+    readiness: Arc<RwLock<Option<Readiness>>>,
+}
+
+impl Health {
+    pub fn update(&self, readiness: Readiness) {
+        *self.readiness.write().unwrap() = Some(readiness);
+    }
+
+    pub fn readiness(&self) -> Option<Readiness> {
+        self.readiness.read().unwrap().clone()
+    }
+
+    pub fn routes(&self) -> Router {
+        Router::new()
+            .route("/healthz", get(healthz))
+            .route("/readyz", get(readyz))
+            .with_state(self.clone())
+    }
+}
+
+async fn healthz() -> &'static str {
+    "ok"
+}
+
+async fn readyz(State(health): State<Health>) -> (StatusCode, String) {
+    let Some(readiness) = health.readiness() else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "no consensus process running\n".to_string(),
+        );
+    };
+    let problems = readiness.problems();
+    if problems.is_empty() {
+        return (StatusCode::OK, "ready\n".to_string());
+    }
+    let body = problems
+        .iter()
+        .map(|problem| format!("{}\n", problem))
+        .collect();
+    (StatusCode::SERVICE_UNAVAILABLE, body)
+}
+
+/// Whether a file can be created, written and synced in `dir`
+pub fn storage_writable(dir: &Path) -> bool {
+    let probe = dir.join(".readyz-probe");
+    let written = fs::File::create(&probe)
+        .and_then(|mut file| {
+            std::io::Write::write_all(&mut file, b"ok")?;
+            file.sync_all()
+        })
+        .is_ok();
+    let _ = fs::remove_file(&probe);
+    written
+}
@@ -1 +1,7 @@
+pub mod admin;
 pub mod cli;
+pub mod consensus;
+pub mod handshake;
+pub mod health;
+pub mod observer;
+pub mod peer_exchange;
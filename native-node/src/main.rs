@@ -1,6 +1,7 @@
 #![allow(non_upper_case_globals)]
 
 use std::net::{Ipv4Addr, SocketAddr};
+use std::time::Duration;
 
 use anyhow::Result;
 use axum::{
@@ -11,20 +12,45 @@ use axum::{
     Router,
 };
 use futures::StreamExt;
+use hellas_morpheus::{MetadataRegistry, WireCompression};
 use libp2p::identity::Keypair;
 use libp2p::{
     core::{muxing::StreamMuxerBox, Transport},
+    gossipsub, identify,
     multiaddr::{Multiaddr, Protocol},
-    ping,
-    swarm::SwarmEvent,
+    ping, request_response,
+    swarm::{NetworkBehaviour, SwarmEvent},
+    Swarm,
 };
 use libp2p_webrtc as webrtc;
 use tokio::net::TcpListener;
 use tower_http::cors::{Any, CorsLayer};
 
-use native_node::cli::{self, Subcommands, TopLevel};
+use native_node::admin::{self, Admin, AdminRequest};
+use native_node::cli::{self, Channels, Subcommands, TopLevel};
+use native_node::consensus::{self, Channel, ConsensusNode, Envelope};
+use native_node::handshake::{self, Handshake};
+use native_node::health::{storage_writable, Health};
+use native_node::observer::ObserverBridge;
+use native_node::peer_exchange::{self, AddressBook, PeerRequest, ADDRESS_BOOK_FILE};
 use tracing_subscriber::EnvFilter;
 
+#[derive(NetworkBehaviour)]
+struct Behaviour {
+    ping: ping::Behaviour,
+    observer: libp2p_stream::Behaviour,
+    gossipsub: gossipsub::Behaviour,
+    identify: identify::Behaviour,
+    peer_exchange: peer_exchange::Behaviour,
+    handshake: handshake::Behaviour,
+}
+
+/// How often the health endpoints' view of the process is refreshed
+const HEALTH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Protocol version announced over identify
+const IDENTIFY_PROTOCOL: &str = "/hellas/0.1.0";
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let _ = tracing_subscriber::fmt()
@@ -39,8 +65,27 @@ async fn main() -> anyhow::Result<()> {
             privkey,
             port,
             webui_listen,
+            admin_listen,
+            admin_token_file,
+            observer_rate,
+            observer_burst,
+            data_dir,
+            member,
+            dev,
+            committee_size,
+            committee_seed,
+            committee_metadata,
+            delta_ms,
+            peer,
+            transports,
+            quic_port,
+            compression_level,
+            channels,
         }) => {
-            tracing::info!("Running daemon");
+            tracing::info!(%transports, "Running daemon");
+            if !transports.webrtc && !transports.quic {
+                anyhow::bail!("At least one transport has to be enabled");
+            }
             let keybytes =
                 hex::decode(privkey).map_err(|e| anyhow::anyhow!("Invalid privkey hex: {}", e))?;
 
@@ -48,6 +93,7 @@ async fn main() -> anyhow::Result<()> {
 
             let mut swarm = libp2p::SwarmBuilder::with_existing_identity(me)
                 .with_tokio()
+                .with_quic()
                 .with_other_transport(|id_keys| {
                     Ok(webrtc::tokio::Transport::new(
                         id_keys.clone(),
@@ -55,15 +101,112 @@ async fn main() -> anyhow::Result<()> {
                     )
                     .map(|(peer_id, conn), _| (peer_id, StreamMuxerBox::new(conn))))
                 })?
-                .with_behaviour(|_| ping::Behaviour::default())?
+                .with_behaviour(|key| {
+                    let gossipsub = gossipsub::Behaviour::new(
+                        gossipsub::MessageAuthenticity::Signed(key.clone()),
+                        consensus::gossipsub_config()?,
+                    )?;
+                    Ok::<_, Box<dyn std::error::Error + Send + Sync>>(Behaviour {
+                        ping: ping::Behaviour::default(),
+                        observer: libp2p_stream::Behaviour::new(),
+                        gossipsub,
+                        identify: identify::Behaviour::new(
+                            identify::Config::new(IDENTIFY_PROTOCOL.to_string(), key.public())
+                                .with_agent_version(consensus::agent_version()),
+                        ),
+                        peer_exchange: peer_exchange::behaviour(),
+                        handshake: handshake::behaviour(),
+                    })
+                })?
                 .build();
 
-            let address_webrtc = Multiaddr::from(Ipv4Addr::UNSPECIFIED)
-                .with(Protocol::Udp(port))
-                .with(Protocol::WebRTCDirect);
+            // Browser peers observe over a stream protocol; snapshots and
+            // finalized blocks are published into the bridge when the daemon
+            // runs a consensus process.
+            let observers = ObserverBridge::new(observer_rate, observer_burst);
+            tokio::spawn(
+                observers
+                    .clone()
+                    .run(swarm.behaviour().observer.new_control()),
+            );
 
-            swarm.listen_on(address_webrtc.clone())?;
+            let delta = Duration::from_millis(delta_ms.max(1));
+            let metadata = match &committee_metadata {
+                Some(path) => consensus::load_metadata(std::path::Path::new(path))?,
+                None => MetadataRegistry::default(),
+            };
+            let node = match member {
+                Some(_) if dev => {
+                    anyhow::bail!("--dev runs its own committee of one, drop --member")
+                }
+                None if dev => {
+                    tracing::info!("Running a single-node dev committee");
+                    let mut process = consensus::dev_single_process(committee_seed, delta);
+                    process.kb.metadata = metadata;
+                    Some(ConsensusNode::new(process))
+                }
+                Some(member) => {
+                    let mut process =
+                        consensus::dev_process(committee_seed, committee_size, member, delta)?;
+                    process.kb.metadata = metadata;
+                    tracing::info!(
+                        member,
+                        name = %process.kb.metadata.display_name(&process.id),
+                        committee_size,
+                        "Taking part in consensus"
+                    );
+                    Some(ConsensusNode::new(process))
+                }
+                None => None,
+            };
+            let compression = compression_level.map(WireCompression::new);
+            if let Some(compression) = compression {
+                tracing::info!(level = compression.level, "Compressing blocks");
+            }
+            let mut node = node.map(|node| node.with_compression(compression));
+            if node.is_some() && channels != Channels::default() {
+                anyhow::bail!("Taking part in consensus needs every channel, drop --channels");
+            }
+            for channel in &channels.0 {
+                swarm
+                    .behaviour_mut()
+                    .gossipsub
+                    .subscribe(&channel.topic())?;
+            }
+            tracing::info!(%channels, "Following consensus channels");
 
+            // a node following the chain checks members' bindings against
+            // the dev committee's keys, like the members themselves
+            let mut handshake = match &mut node {
+                Some(node) => {
+                    let binding = node
+                        .process
+                        .bind_peer(&swarm.local_peer_id().to_bytes())
+                        .map_err(|e| anyhow::anyhow!("Could not bind our peer id: {}", e))?;
+                    node.set_binding(binding.clone());
+                    Handshake::new(node.process.kb.clone(), Some(binding))
+                }
+                None => Handshake::new(
+                    consensus::dev_keybook(committee_seed, committee_size, 1)?,
+                    None,
+                ),
+            };
+
+            if transports.webrtc {
+                let address_webrtc = Multiaddr::from(Ipv4Addr::UNSPECIFIED)
+                    .with(Protocol::Udp(port))
+                    .with(Protocol::WebRTCDirect);
+                swarm.listen_on(address_webrtc)?;
+            }
+            if transports.quic {
+                let address_quic = Multiaddr::from(Ipv4Addr::UNSPECIFIED)
+                    .with(Protocol::Udp(quic_port))
+                    .with(Protocol::QuicV1);
+                swarm.listen_on(address_quic)?;
+            }
+
+            // The web UI hands browsers the WebRTC address, so wait for that one
+            // if we listen on it; QUIC addresses are only logged.
             let address = loop {
                 if let SwarmEvent::NewListenAddr { address, .. } = swarm.select_next_some().await {
                     if address
@@ -78,19 +221,229 @@ async fn main() -> anyhow::Result<()> {
 
                     tracing::info!(%address, "Listening");
 
+                    if transports.webrtc && !address.iter().any(|e| e == Protocol::WebRTCDirect) {
+                        continue;
+                    }
                     break address;
                 }
             };
 
             let addr = address.with(Protocol::P2p(*swarm.local_peer_id()));
 
+            for peer in &peer {
+                let peer: Multiaddr = peer
+                    .parse()
+                    .map_err(|e| anyhow::anyhow!("Invalid peer address {}: {}", peer, e))?;
+                swarm.dial(peer)?;
+            }
+
+            let data_dir = std::path::PathBuf::from(data_dir);
+            let book_path = data_dir.join(ADDRESS_BOOK_FILE);
+            let mut address_book = AddressBook::load(&book_path).unwrap_or_else(|e| {
+                tracing::warn!(path = %book_path.display(), ?e, "Starting with an empty address book");
+                AddressBook::new(&book_path)
+            });
+            tracing::info!(
+                peers = address_book.len(),
+                path = %address_book.path().display(),
+                "Loaded address book"
+            );
+            for addr in address_book.dial_addrs() {
+                dial(&mut swarm, addr);
+            }
+
+            // Without a consensus process nothing reports readiness, so /readyz
+            // stays unavailable; `data_dir` is what its storage check probes.
+            let health = Health::default();
+            if !storage_writable(&data_dir) {
+                tracing::warn!(data_dir = %data_dir.display(), "Data directory is not writable");
+            }
+
+            let (admin, mut admin_requests) = Admin::new();
+            match &admin_token_file {
+                Some(path) => {
+                    let token = admin::load_token(std::path::Path::new(path)).map_err(|e| {
+                        anyhow::anyhow!("Could not read the admin token from {}: {}", path, e)
+                    })?;
+                    tokio::spawn(serve_admin(admin.routes(&token), admin_listen));
+                }
+                None => tracing::info!("No --admin-token-file given, admin endpoints are off"),
+            }
+
             // Serve .wasm, .js and server multiaddress over HTTP on this address.
-            tokio::spawn(serve(addr, webui_listen));
+            tokio::spawn(serve(addr, webui_listen, health.clone()));
+
+            // several ticks per delta, so timeouts fire close to when they're due
+            let mut ticks = tokio::time::interval(delta / 4);
+            let mut health_checks = tokio::time::interval(HEALTH_INTERVAL);
 
             loop {
                 tokio::select! {
-                    swarm_event = swarm.next() => {
-                        tracing::trace!(?swarm_event)
+                    swarm_event = swarm.next() => match swarm_event {
+                        Some(SwarmEvent::Behaviour(BehaviourEvent::Gossipsub(
+                            gossipsub::Event::Message { propagation_source, message, .. },
+                        ))) => {
+                            let channel = Channel::from_topic(&message.topic);
+                            if handshake.accepted(&propagation_source).is_none() {
+                                tracing::debug!(
+                                    peer = %propagation_source,
+                                    "Dropping gossip from a peer we haven't shaken hands with"
+                                );
+                            } else if let (Some(node), Some(channel)) = (&mut node, channel) {
+                                let outbound =
+                                    node.on_gossip(channel, message.source.as_ref(), &message.data);
+                                publish(&mut swarm, node, &handshake, outbound);
+                            }
+                        }
+                        Some(SwarmEvent::NewListenAddr { address, .. }) => {
+                            tracing::info!(%address, "Listening");
+                        }
+                        Some(SwarmEvent::ConnectionEstablished { peer_id, endpoint, .. }) => {
+                            if endpoint.is_dialer()
+                                && address_book
+                                    .insert(peer_id, endpoint.get_remote_address().clone())
+                            {
+                                save(&address_book);
+                            }
+                            swarm
+                                .behaviour_mut()
+                                .handshake
+                                .send_request(&peer_id, handshake.hello());
+                            swarm
+                                .behaviour_mut()
+                                .peer_exchange
+                                .send_request(&peer_id, PeerRequest);
+                        }
+                        Some(SwarmEvent::ConnectionClosed { peer_id, num_established: 0, .. }) => {
+                            handshake.on_disconnect(&peer_id);
+                            if let Some(node) = &mut node {
+                                node.on_disconnect(&peer_id);
+                            }
+                        }
+                        Some(SwarmEvent::Behaviour(BehaviourEvent::Identify(
+                            identify::Event::Received { peer_id, info, .. },
+                        ))) => {
+                            if let Some(node) = &mut node {
+                                node.on_identify(peer_id, &info.agent_version);
+                            }
+                            let mut changed = false;
+                            for addr in info.listen_addrs {
+                                changed |= address_book.insert(peer_id, addr);
+                            }
+                            if changed {
+                                save(&address_book);
+                            }
+                        }
+                        Some(SwarmEvent::Behaviour(BehaviourEvent::Handshake(
+                            request_response::Event::Message { peer, message, .. },
+                        ))) => {
+                            let theirs = match message {
+                                request_response::Message::Request { request, channel, .. } => {
+                                    // answered even when rejected, so the peer sees why
+                                    if swarm
+                                        .behaviour_mut()
+                                        .handshake
+                                        .send_response(channel, handshake.hello())
+                                        .is_err()
+                                    {
+                                        tracing::debug!(%peer, "Peer left before our hello reached it");
+                                    }
+                                    request
+                                }
+                                request_response::Message::Response { response, .. } => response,
+                            };
+                            // each side both asks and answers, so each hello arrives twice
+                            let known = handshake.accepted(&peer).is_some();
+                            match handshake.on_hello(peer, &theirs) {
+                                Ok(_) if known => {}
+                                Ok(accepted) => tracing::info!(
+                                    %peer,
+                                    wire_version = accepted.wire_version,
+                                    member = ?accepted.member,
+                                    "Shook hands"
+                                ),
+                                Err(rejection) => {
+                                    tracing::warn!(%peer, %rejection, "Disconnecting incompatible peer");
+                                    let _ = swarm.disconnect_peer_id(peer);
+                                }
+                            }
+                        }
+                        Some(SwarmEvent::Behaviour(BehaviourEvent::PeerExchange(
+                            request_response::Event::Message { peer, message, .. },
+                        ))) => match message {
+                            request_response::Message::Request { channel, .. } => {
+                                let records =
+                                    address_book.records(peer_exchange::MAX_SHARED_PEERS);
+                                if swarm
+                                    .behaviour_mut()
+                                    .peer_exchange
+                                    .send_response(channel, records)
+                                    .is_err()
+                                {
+                                    tracing::debug!(%peer, "Peer left before our address book reached it");
+                                }
+                            }
+                            request_response::Message::Response { response, .. } => {
+                                let me = *swarm.local_peer_id();
+                                let learned = address_book.merge(response, Some(&me));
+                                if !learned.is_empty() {
+                                    tracing::info!(%peer, learned = learned.len(), "Learned peer addresses");
+                                    save(&address_book);
+                                }
+                                for addr in learned {
+                                    dial(&mut swarm, addr);
+                                }
+                            }
+                        },
+                        swarm_event => tracing::trace!(?swarm_event),
+                    },
+                    _ = ticks.tick(), if node.is_some() => {
+                        let node = node.as_mut().unwrap();
+                        let outbound = node.on_tick();
+                        publish(&mut swarm, node, &handshake, outbound);
+                        for key in node.take_finalized() {
+                            observers.publish_finalized(
+                                serde_json::to_vec(&key).expect("block key serializes"),
+                            );
+                        }
+                        if node.take_view_changed() {
+                            observers.publish_snapshot(node.snapshot());
+                        }
+                    },
+                    _ = observers.subscribed(), if node.is_some() => {
+                        observers.publish_snapshot(node.as_ref().unwrap().snapshot());
+                    },
+                    _ = health_checks.tick(), if node.is_some() => {
+                        let node = node.as_ref().unwrap();
+                        let peers = swarm.behaviour().gossipsub.all_peers().count();
+                        health.update(node.process.readiness(
+                            hellas_morpheus::DEFAULT_MAX_VIEW_LAG,
+                            peers,
+                            storage_writable(&data_dir),
+                        ));
+                        let dedup = node.dedup_stats();
+                        tracing::debug!(
+                            hits = dedup.hits,
+                            misses = dedup.misses,
+                            hit_rate = dedup.hit_rate(),
+                            "Inbound dedup"
+                        );
+                    },
+                    Some(request) = admin_requests.recv() => {
+                        // dropping the reply without a process answers 503
+                        let Some(node) = &mut node else {
+                            continue;
+                        };
+                        match request {
+                            AdminRequest::DebugBundle(reply) => {
+                                let _ = reply.send(node.process.debug_bundle());
+                            }
+                            AdminRequest::ForceEndView(reply) => {
+                                let outbound = node.force_end_view();
+                                let _ = reply.send(outbound.is_some());
+                                publish(&mut swarm, node, &handshake, outbound.unwrap_or_default());
+                            }
+                        }
                     },
                     _ = tokio::signal::ctrl_c() => {
                         break;
@@ -103,12 +456,55 @@ async fn main() -> anyhow::Result<()> {
     }
 }
 
+/// Dials `addr` unless already connected to its peer, logging rather than
+/// failing: addresses from the book or from other peers may be stale
+fn dial(swarm: &mut Swarm<Behaviour>, addr: Multiaddr) {
+    if let Some(Protocol::P2p(peer)) = addr.iter().last() {
+        if peer == *swarm.local_peer_id() || swarm.is_connected(&peer) {
+            return;
+        }
+    }
+    if let Err(e) = swarm.dial(addr.clone()) {
+        tracing::debug!(%addr, ?e, "Could not dial known peer");
+    }
+}
+
+fn save(address_book: &AddressBook) {
+    if let Err(e) = address_book.save() {
+        tracing::warn!(
+            path = %address_book.path().display(),
+            ?e,
+            "Could not save address book"
+        );
+    }
+}
+
+/// Publishes a process's outbound messages on the consensus topic
+fn publish(
+    swarm: &mut Swarm<Behaviour>,
+    node: &ConsensusNode,
+    handshake: &Handshake,
+    outbound: Vec<Envelope>,
+) {
+    let connected = swarm.connected_peers().copied().collect::<Vec<_>>();
+    let members_accepting = handshake.members_accepting_compression();
+    for envelope in outbound {
+        if let Err(e) = swarm.behaviour_mut().gossipsub.publish(
+            Channel::of(envelope.message.kind()).topic(),
+            node.encode(&envelope, &connected, &members_accepting),
+        ) {
+            // mostly `InsufficientPeers` before the mesh forms; timeouts resend
+            tracing::debug!(?e, "Could not publish consensus message");
+        }
+    }
+}
+
 #[derive(rust_embed::RustEmbed)]
 #[folder = "$CARGO_MANIFEST_DIR/static"]
 struct StaticFiles;
 
 /// Serve the Multiaddr we are listening on and the host files.
-pub(crate) async fn serve(libp2p_transport: Multiaddr, port: u16) {
+pub(crate) async fn serve(libp2p_transport: Multiaddr, port: u16, health: Health) {
     for path in StaticFiles::iter() {
         println!("available files: {}", path)
     }
@@ -122,6 +518,7 @@ pub(crate) async fn serve(libp2p_transport: Multiaddr, port: u16) {
         .route("/index.html", get(get_index))
         .route("/:path", get(get_static_file))
         .with_state(Libp2pEndpoint(libp2p_transport))
+        .merge(health.routes())
         .layer(
             // allow cors
             CorsLayer::new()
@@ -141,6 +538,20 @@ pub(crate) async fn serve(libp2p_transport: Multiaddr, port: u16) {
     .unwrap();
 }
 
+/// Serves the admin endpoints on localhost, apart from the web UI and its
+/// CORS headers, so neither peers nor web pages can reach them
+async fn serve_admin(routes: Router, port: u16) {
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    tracing::info!(url=%format!("http://{addr}"), "Serving admin endpoints");
+
+    axum::serve(
+        TcpListener::bind(addr).await.unwrap(),
+        routes.into_make_service(),
+    )
+    .await
+    .unwrap();
+}
+
 #[derive(Clone)]
 struct Libp2pEndpoint(Multiaddr);
 
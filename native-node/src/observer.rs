@@ -0,0 +1,224 @@
+//! Read-only observer protocol for browser peers.
+//!
+//! Browsers connecting over WebRTC don't take part in consensus. Instead they
+//! open an [`OBSERVER_PROTOCOL`] stream and the daemon pushes them the latest
+//! snapshot followed by every finalization event, each as a length-prefixed
+//! frame. Every observer connection gets its own [`RateLimiter`]; frames over
+//! budget are dropped rather than queued, so a slow browser can't hold memory
+//! on the daemon.
+//!
+//! The daemon publishes a fresh snapshot whenever an observer connects (see
+//! [`ObserverBridge::subscribed`]) and whenever its process changes view. A
+//! new observer is sent the latest snapshot straight away, then the fresh
+//! one once the daemon gets to it.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures::{AsyncWriteExt, StreamExt};
+use libp2p::{PeerId, StreamProtocol};
+use tokio::sync::{broadcast, watch, Notify};
+
+pub const OBSERVER_PROTOCOL: StreamProtocol = StreamProtocol::new("/hellas/observer/0.1.0");
+
+/// Largest frame we are willing to send to a browser
+pub const MAX_FRAME_LEN: usize = 1 << 20;
+
+/// How many events may queue up for a lagging observer before it starts missing them
+const EVENT_BACKLOG: usize = 256;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ObserverUpdate {
+    /// Opaque serialized state, sent first to every new observer
+    Snapshot(Vec<u8>),
+    /// Opaque serialized finalization event
+    Finalized(Vec<u8>),
+}
+
+impl ObserverUpdate {
+    const SNAPSHOT_TAG: u8 = 0;
+    const FINALIZED_TAG: u8 = 1;
+
+    /// Encodes as `len: u32 BE | tag: u8 | payload`, where `len` covers the tag and payload
+    pub fn encode(&self) -> Vec<u8> {
+        let (tag, payload) = match self {
+            ObserverUpdate::Snapshot(payload) => (Self::SNAPSHOT_TAG, payload),
+            ObserverUpdate::Finalized(payload) => (Self::FINALIZED_TAG, payload),
+        };
+        let mut frame = Vec::with_capacity(5 + payload.len());
+        frame.extend_from_slice(&(payload.len() as u32 + 1).to_be_bytes());
+        frame.push(tag);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    /// Inverse of [`ObserverUpdate::encode`] for a single complete frame
+    pub fn decode(frame: &[u8]) -> Option<Self> {
+        let len = u32::from_be_bytes(frame.get(..4)?.try_into().ok()?) as usize;
+        let body = frame.get(4..)?;
+        if len == 0 || body.len() != len {
+            return None;
+        }
+        let payload = body[1..].to_vec();
+        match body[0] {
+            Self::SNAPSHOT_TAG => Some(ObserverUpdate::Snapshot(payload)),
+            Self::FINALIZED_TAG => Some(ObserverUpdate::Finalized(payload)),
+            _ => None,
+        }
+    }
+}
+
+/// Token bucket limiting how many frames one observer connection receives
+#[derive(Clone, Debug)]
+pub struct RateLimiter {
+    per_sec: f64,
+    burst: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(per_sec: u32, burst: u32) -> Self {
+        Self::new_at(per_sec, burst, Instant::now())
+    }
+
+    pub fn new_at(per_sec: u32, burst: u32, now: Instant) -> Self {
+        let burst = burst.max(1) as f64;
+        RateLimiter {
+            per_sec: per_sec as f64,
+            burst,
+            tokens: burst,
+            last_refill: now,
+        }
+    }
+
+    /// Takes one token if available
+    pub fn try_acquire_at(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.per_sec).min(self.burst);
+        self.last_refill = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn try_acquire(&mut self) -> bool {
+        self.try_acquire_at(Instant::now())
+    }
+}
+
+/// Where the daemon publishes state for observers to pick up
+#[derive(Clone)]
+pub struct ObserverBridge {
+    events: broadcast::Sender<ObserverUpdate>,
+    snapshot: watch::Sender<Option<Vec<u8>>>,
+    /// Woken when an observer connects
+    subscribed: Arc<Notify>,
+    per_sec: u32,
+    burst: u32,
+}
+
+impl ObserverBridge {
+    pub fn new(per_sec: u32, burst: u32) -> Self {
+        ObserverBridge {
+            events: broadcast::channel(EVENT_BACKLOG).0,
+            snapshot: watch::channel(None).0,
+            subscribed: Arc::new(Notify::new()),
+            per_sec,
+            burst,
+        }
+    }
+
+    /// Replaces the snapshot new observers start from, and pushes it to current ones
+    pub fn publish_snapshot(&self, snapshot: Vec<u8>) {
+        self.snapshot.send_replace(Some(snapshot.clone()));
+        let _ = self.events.send(ObserverUpdate::Snapshot(snapshot));
+    }
+
+    pub fn publish_finalized(&self, event: Vec<u8>) {
+        let _ = self.events.send(ObserverUpdate::Finalized(event));
+    }
+
+    pub fn observer_count(&self) -> usize {
+        self.events.receiver_count()
+    }
+
+    /// Waits until an observer has connected since the last call, for the
+    /// daemon to publish it a fresh snapshot
+    pub async fn subscribed(&self) {
+        self.subscribed.notified().await;
+    }
+
+    /// Accepts observer streams forever, spawning one forwarding task per stream
+    pub async fn run(self, mut control: libp2p_stream::Control) {
+        let mut incoming = match control.accept(OBSERVER_PROTOCOL) {
+            Ok(incoming) => incoming,
+            Err(e) => {
+                tracing::error!(?e, "Observer protocol already registered");
+                return;
+            }
+        };
+
+        while let Some((peer, stream)) = incoming.next().await {
+            tracing::info!(%peer, "Observer connected");
+            let bridge = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = bridge.serve_observer(peer, stream).await {
+                    tracing::debug!(%peer, ?e, "Observer stream ended");
+                }
+            });
+        }
+    }
+
+    async fn serve_observer(
+        &self,
+        peer: PeerId,
+        mut stream: libp2p::Stream,
+    ) -> std::io::Result<()> {
+        // subscribe before reading the snapshot so nothing published in between is lost
+        let mut events = self.events.subscribe();
+        self.subscribed.notify_one();
+        let mut limiter = RateLimiter::new(self.per_sec, self.burst);
+        let mut dropped = 0usize;
+
+        let snapshot = self.snapshot.borrow().clone();
+        if let Some(snapshot) = snapshot {
+            send(&mut stream, &ObserverUpdate::Snapshot(snapshot)).await?;
+        }
+
+        loop {
+            let update = match events.recv().await {
+                Ok(update) => update,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    dropped += n as usize;
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            if !limiter.try_acquire() {
+                dropped += 1;
+                if dropped % 100 == 1 {
+                    tracing::warn!(%peer, dropped, "Observer over rate limit, dropping updates");
+                }
+                continue;
+            }
+            send(&mut stream, &update).await?;
+        }
+
+        stream.close().await
+    }
+}
+
+async fn send(stream: &mut libp2p::Stream, update: &ObserverUpdate) -> std::io::Result<()> {
+    let frame = update.encode();
+    if frame.len() > MAX_FRAME_LEN {
+        tracing::warn!(len = frame.len(), "Skipping oversized observer frame");
+        return Ok(());
+    }
+    tokio::time::timeout(Duration::from_secs(10), stream.write_all(&frame))
+        .await
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "observer write"))?
+}
@@ -0,0 +1,193 @@
+//! Sharing known peer addresses, and remembering them across restarts.
+//!
+//! Each node keeps an [`AddressBook`] of the addresses it has reached peers
+//! at or heard them listen on, saved as JSON in its data directory. On
+//! startup it dials everything in the book, so a restarted node rejoins
+//! without being given `--peer` again.
+//!
+//! Whenever a connection opens, the node asks the peer for its book over
+//! [`PEER_EXCHANGE_PROTOCOL`], a request-response protocol with JSON bodies,
+//! and dials any peer it learns about that it isn't connected to. The
+//! addresses are as the peer reported them: nothing checks that they belong
+//! to committee members, only that they parse.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use libp2p::multiaddr::Protocol;
+use libp2p::request_response::{self, ProtocolSupport};
+use libp2p::{Multiaddr, PeerId, StreamProtocol};
+use serde::{Deserialize, Serialize};
+
+pub const PEER_EXCHANGE_PROTOCOL: StreamProtocol = StreamProtocol::new("/hellas/pex/0.1.0");
+
+/// Name of the address book in the data directory
+pub const ADDRESS_BOOK_FILE: &str = "address_book.json";
+
+/// Most peers shared in one response
+pub const MAX_SHARED_PEERS: usize = 64;
+
+/// Most addresses kept for one peer; the oldest are dropped first
+pub const MAX_ADDRS_PER_PEER: usize = 8;
+
+/// Asks for the peer's address book
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PeerRequest;
+
+/// One peer and the addresses it can be reached at
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerRecord {
+    pub peer: String,
+    pub addrs: Vec<String>,
+}
+
+pub type Behaviour = request_response::json::Behaviour<PeerRequest, Vec<PeerRecord>>;
+
+pub fn behaviour() -> Behaviour {
+    request_response::json::Behaviour::new(
+        [(PEER_EXCHANGE_PROTOCOL, ProtocolSupport::Full)],
+        request_response::Config::default(),
+    )
+}
+
+/// Known addresses of other peers, kept in a file
+#[derive(Debug)]
+pub struct AddressBook {
+    path: PathBuf,
+    peers: BTreeMap<PeerId, Vec<Multiaddr>>,
+}
+
+impl AddressBook {
+    /// An empty book, saved to `path`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        AddressBook {
+            path: path.into(),
+            peers: BTreeMap::new(),
+        }
+    }
+
+    /// Reads the book at `path`, or starts an empty one if there is none yet
+    ///
+    /// Entries that no longer parse are skipped, so a book written by another
+    /// version doesn't keep the node from starting.
+    pub fn load(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let mut book = AddressBook::new(path);
+        let bytes = match std::fs::read(&book.path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(book),
+            Err(e) => return Err(e),
+        };
+        let records: Vec<PeerRecord> = serde_json::from_slice(&bytes)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        book.merge(records, None);
+        Ok(book)
+    }
+
+    /// Writes the book out, replacing the file only once the new one is complete
+    pub fn save(&self) -> io::Result<()> {
+        let bytes =
+            serde_json::to_vec_pretty(&self.records(usize::MAX)).expect("peer records serialize");
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, bytes)?;
+        std::fs::rename(&tmp, &self.path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn len(&self) -> usize {
+        self.peers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+
+    /// Notes that `peer` can be reached at `addr`, returning whether that's new
+    ///
+    /// Addresses that can't be dialed, such as `0.0.0.0`, are left out.
+    pub fn insert(&mut self, peer: PeerId, addr: Multiaddr) -> bool {
+        let addr = without_peer_id(addr);
+        if !dialable(&addr) {
+            return false;
+        }
+        let addrs = self.peers.entry(peer).or_default();
+        if addrs.contains(&addr) {
+            return false;
+        }
+        if addrs.len() == MAX_ADDRS_PER_PEER {
+            addrs.remove(0);
+        }
+        addrs.push(addr);
+        true
+    }
+
+    /// Adds what a peer shared, skipping ourselves, and returns the addresses
+    /// newly learned, ready to dial
+    pub fn merge(&mut self, records: Vec<PeerRecord>, me: Option<&PeerId>) -> Vec<Multiaddr> {
+        let mut learned = Vec::new();
+        for record in records.into_iter().take(MAX_SHARED_PEERS) {
+            let Ok(peer) = record.peer.parse::<PeerId>() else {
+                continue;
+            };
+            if Some(&peer) == me {
+                continue;
+            }
+            for addr in record.addrs.iter().take(MAX_ADDRS_PER_PEER) {
+                let Ok(addr) = addr.parse::<Multiaddr>() else {
+                    continue;
+                };
+                if self.insert(peer, addr.clone()) {
+                    learned.push(with_peer_id(addr, peer));
+                }
+            }
+        }
+        learned
+    }
+
+    /// Up to `limit` peers with their addresses, as shared with others
+    pub fn records(&self, limit: usize) -> Vec<PeerRecord> {
+        self.peers
+            .iter()
+            .take(limit)
+            .map(|(peer, addrs)| PeerRecord {
+                peer: peer.to_string(),
+                addrs: addrs.iter().map(Multiaddr::to_string).collect(),
+            })
+            .collect()
+    }
+
+    /// Every known address, each ending in its peer's id
+    pub fn dial_addrs(&self) -> Vec<Multiaddr> {
+        self.peers
+            .iter()
+            .flat_map(|(peer, addrs)| {
+                addrs
+                    .iter()
+                    .map(move |addr| with_peer_id(addr.clone(), *peer))
+            })
+            .collect()
+    }
+}
+
+fn dialable(addr: &Multiaddr) -> bool {
+    match addr.iter().next() {
+        Some(Protocol::Ip4(ip)) => !ip.is_unspecified(),
+        Some(Protocol::Ip6(ip)) => !ip.is_unspecified(),
+        Some(Protocol::Dns(_) | Protocol::Dns4(_) | Protocol::Dns6(_)) => true,
+        _ => false,
+    }
+}
+
+fn without_peer_id(mut addr: Multiaddr) -> Multiaddr {
+    if let Some(Protocol::P2p(_)) = addr.iter().last() {
+        addr.pop();
+    }
+    addr
+}
+
+fn with_peer_id(addr: Multiaddr, peer: PeerId) -> Multiaddr {
+    addr.with(Protocol::P2p(peer))
+}
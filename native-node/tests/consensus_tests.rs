@@ -0,0 +1,188 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use hellas_morpheus::transport::NetworkTransport;
+use hellas_morpheus::{
+    BlockKey, BlockType, Identity, Message, PeerBinding, Signed, SlotNum, StateDigest,
+    ThreshPartial, ViewNum,
+};
+use libp2p::identity::Keypair;
+use libp2p::PeerId;
+use native_node::consensus::{
+    dev_keybook, dev_process, dev_single_process, load_metadata, Channel, ConsensusNode, Envelope,
+    GossipTransport, NodeTransaction,
+};
+
+const SEED: u64 = 7;
+
+fn peer() -> PeerId {
+    Keypair::generate_ed25519().public().to_peer_id()
+}
+
+// member `member`'s binding to `peer`
+fn binding(member: u32, peer: &PeerId) -> Signed<PeerBinding> {
+    dev_process(SEED, 4, member, Duration::from_millis(100))
+        .unwrap()
+        .bind_peer(&peer.to_bytes())
+        .unwrap()
+}
+
+fn end_view(member: u32, view: i64) -> Message<NodeTransaction> {
+    Message::EndView(Arc::new(ThreshPartial::from_data(
+        ViewNum(view),
+        &dev_keybook(SEED, 4, member).unwrap(),
+    )))
+}
+
+fn envelope(from: u32, to: Option<u32>, binding: Option<Signed<PeerBinding>>) -> Envelope {
+    Envelope {
+        from: Identity(from),
+        to: to.map(Identity),
+        binding,
+        message: end_view(from, 1),
+    }
+}
+
+// member 1's transport
+fn transport() -> GossipTransport {
+    GossipTransport::new(Identity(1), dev_keybook(SEED, 4, 1).unwrap())
+}
+
+#[test]
+fn test_envelope_roundtrip() {
+    let alice = peer();
+    for original in [
+        envelope(2, None, None),
+        envelope(2, Some(3), Some(binding(2, &alice))),
+    ] {
+        let decoded = Envelope::decode(&original.encode()).unwrap();
+        assert_eq!(decoded.from, original.from);
+        assert_eq!(decoded.to, original.to);
+        assert_eq!(decoded.binding, original.binding);
+        assert_eq!(decoded.message, original.message);
+    }
+
+    // a binding running past the end, or no header at all
+    let bytes = envelope(2, None, Some(binding(2, &alice))).encode();
+    assert!(Envelope::decode(&bytes[..20]).is_err());
+    assert!(Envelope::decode(&bytes[..6]).is_err());
+}
+
+#[test]
+fn test_deliver_checks_the_publisher() {
+    let bob = peer();
+    let mut transport = transport();
+
+    // nobody vouches for an unknown peer without a binding
+    let unbound = envelope(2, None, None).encode();
+    assert!(!transport.deliver(Channel::ViewChange, Some(&bob), &unbound));
+    assert!(!transport.deliver(Channel::ViewChange, None, &unbound));
+
+    // a binding for another peer, or naming another sender, proves nothing
+    let stolen = envelope(2, None, Some(binding(2, &peer()))).encode();
+    assert!(!transport.deliver(Channel::ViewChange, Some(&bob), &stolen));
+    let impersonating = envelope(3, None, Some(binding(2, &bob))).encode();
+    assert!(!transport.deliver(Channel::ViewChange, Some(&bob), &impersonating));
+
+    let bound = envelope(2, None, Some(binding(2, &bob)));
+    assert!(transport.deliver(Channel::ViewChange, Some(&bob), &bound.encode()));
+    assert_eq!(
+        transport.receive(),
+        Some((Identity(2), bound.message.clone()))
+    );
+
+    // once checked, the binding is remembered for the peer id
+    let later = Envelope {
+        message: end_view(2, 2),
+        ..envelope(2, None, None)
+    };
+    assert!(transport.deliver(Channel::ViewChange, Some(&bob), &later.encode()));
+    assert_eq!(transport.receive(), Some((Identity(2), later.message)));
+}
+
+#[test]
+fn test_deliver_filters() {
+    let bob = peer();
+    let mut transport = transport();
+    let from_bob = |to| envelope(2, to, Some(binding(2, &bob))).encode();
+
+    // published on the wrong channel
+    assert!(!transport.deliver(Channel::Votes, Some(&bob), &from_bob(None)));
+    // addressed to another member
+    assert!(!transport.deliver(Channel::ViewChange, Some(&bob), &from_bob(Some(3))));
+    // our own, relayed back to us
+    let me = peer();
+    let own = envelope(1, None, Some(binding(1, &me))).encode();
+    assert!(!transport.deliver(Channel::ViewChange, Some(&me), &own));
+    // not an envelope at all
+    assert!(!transport.deliver(Channel::ViewChange, Some(&bob), b"junk"));
+    assert_eq!(transport.receive(), None);
+
+    assert!(transport.deliver(Channel::ViewChange, Some(&bob), &from_bob(Some(1))));
+    // the same message again, even addressed to everyone
+    assert!(!transport.deliver(Channel::ViewChange, Some(&bob), &from_bob(None)));
+    assert!(transport.receive().is_some());
+    assert_eq!(transport.receive(), None);
+    assert_eq!(transport.dedup_stats().hits, 1);
+}
+
+#[test]
+fn test_requests_are_not_deduplicated() {
+    let bob = peer();
+    let mut transport = transport();
+    let request = Envelope {
+        message: Message::GetBlock(BlockKey {
+            type_: BlockType::Tr,
+            view: ViewNum(1),
+            height: 1,
+            author: Some(Identity(2)),
+            slot: SlotNum(0),
+            hash: None,
+        }),
+        ..envelope(2, Some(1), Some(binding(2, &bob)))
+    }
+    .encode();
+    assert!(transport.deliver(Channel::Blocks, Some(&bob), &request));
+    assert!(transport.deliver(Channel::Blocks, Some(&bob), &request));
+}
+
+#[test]
+fn test_outbound_carries_our_binding() {
+    let me = peer();
+    let mut transport = transport();
+    transport.broadcast(end_view(1, 1));
+    transport.set_binding(binding(1, &me));
+    transport.send(&Identity(2), end_view(1, 2));
+
+    let [before, after] = transport.take_outbound().try_into().unwrap();
+    assert_eq!(before.binding, None);
+    assert_eq!(after.binding, Some(binding(1, &me)));
+    assert_eq!(after.to, Some(Identity(2)));
+}
+
+#[test]
+fn test_view_changes_are_reported_once() {
+    let mut node = ConsensusNode::new(dev_single_process(SEED, Duration::from_millis(100)));
+    assert!(!node.take_view_changed());
+    let before: StateDigest = serde_json::from_slice(&node.snapshot()).unwrap();
+
+    // a committee of one moves on with its own end-view
+    assert!(node.force_end_view().is_some());
+    assert!(node.take_view_changed());
+    assert!(!node.take_view_changed());
+    let after: StateDigest = serde_json::from_slice(&node.snapshot()).unwrap();
+    assert!(after.view_i > before.view_i);
+}
+
+#[test]
+fn test_metadata_loads_from_json() {
+    let path = std::env::temp_dir().join(format!("node-metadata-{}.json", std::process::id()));
+    std::fs::write(&path, r#"{"entries": {"2": {"name": "node-eu-1"}}}"#).unwrap();
+    let metadata = load_metadata(&path).unwrap();
+    assert_eq!(metadata.display_name(&Identity(2)), "node-eu-1");
+    assert_eq!(metadata.get(&Identity(2)).unwrap().region, None);
+
+    std::fs::write(&path, "not json").unwrap();
+    assert!(load_metadata(&path).is_err());
+    assert!(load_metadata(&path.with_extension("missing")).is_err());
+}
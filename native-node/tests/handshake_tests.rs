@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+use hellas_morpheus::{ChainId, Identity, WIRE_VERSION};
+use libp2p::identity::Keypair;
+use libp2p::PeerId;
+use native_node::consensus::{dev_keybook, dev_process};
+use native_node::handshake::{Handshake, Hello, Rejection};
+
+const SEED: u64 = 7;
+
+fn peer() -> PeerId {
+    Keypair::generate_ed25519().public().to_peer_id()
+}
+
+// the handshake of member `member` running under `peer`
+fn member(member: u32, peer: &PeerId) -> Handshake {
+    let process = dev_process(SEED, 4, member, Duration::from_millis(100)).unwrap();
+    let binding = process.bind_peer(&peer.to_bytes()).unwrap();
+    Handshake::new(process.kb.clone(), Some(binding))
+}
+
+fn follower() -> Handshake {
+    Handshake::new(dev_keybook(SEED, 4, 1).unwrap(), None)
+}
+
+#[test]
+fn test_members_accept_each_other() {
+    let (alice, bob) = (peer(), peer());
+    let accepted = member(1, &alice)
+        .check(&bob, &member(2, &bob).hello())
+        .unwrap();
+    assert_eq!(accepted.wire_version, WIRE_VERSION);
+    assert_eq!(accepted.member, Some(Identity(2)));
+
+    // and followers, which run no member
+    let accepted = member(1, &alice)
+        .check(&peer(), &follower().hello())
+        .unwrap();
+    assert_eq!(accepted.member, None);
+}
+
+#[test]
+fn test_other_chain_is_rejected() {
+    let mut theirs = follower().hello();
+    let ours = theirs.chain;
+    theirs.chain = ChainId([1; 32]);
+    assert_eq!(
+        follower().check(&peer(), &theirs),
+        Err(Rejection::OtherChain {
+            ours,
+            theirs: ChainId([1; 32]),
+        })
+    );
+}
+
+#[test]
+fn test_no_common_version_is_rejected() {
+    let theirs = Hello {
+        wire_versions: vec![WIRE_VERSION + 1],
+        ..follower().hello()
+    };
+    assert_eq!(
+        follower().check(&peer(), &theirs),
+        Err(Rejection::NoCommonVersion {
+            ours: vec![WIRE_VERSION],
+            theirs: vec![WIRE_VERSION + 1],
+        })
+    );
+
+    // a newer node that still speaks our version is fine
+    let theirs = Hello {
+        wire_versions: vec![WIRE_VERSION, WIRE_VERSION + 1],
+        ..follower().hello()
+    };
+    assert_eq!(
+        follower().check(&peer(), &theirs).unwrap().wire_version,
+        WIRE_VERSION
+    );
+}
+
+#[test]
+fn test_binding_for_another_peer_is_rejected() {
+    let (bob, mallory) = (peer(), peer());
+    let hello = member(2, &bob).hello();
+    assert_eq!(
+        follower().check(&mallory, &hello),
+        Err(Rejection::InvalidBinding(2))
+    );
+
+    // nor does a binding signed on another chain verify
+    let foreign = Handshake::new(dev_keybook(SEED + 1, 4, 1).unwrap(), None);
+    let mut hello = hello;
+    hello.chain = foreign.hello().chain;
+    assert_eq!(
+        foreign.check(&bob, &hello),
+        Err(Rejection::InvalidBinding(2))
+    );
+}
+
+#[test]
+fn test_claiming_our_member_is_rejected() {
+    let (alice, impostor) = (peer(), peer());
+    assert_eq!(
+        member(1, &alice).check(&impostor, &member(1, &impostor).hello()),
+        Err(Rejection::ClaimsOurMember(1))
+    );
+}
+
+#[test]
+fn test_accepted_peers_are_forgotten_on_disconnect() {
+    let (alice, bob) = (peer(), peer());
+    let mut handshake = member(1, &alice);
+    let hello = member(2, &bob).hello();
+    handshake.on_hello(bob, &hello).unwrap();
+    assert_eq!(handshake.member_of(&bob), Some(&Identity(2)));
+
+    handshake.on_disconnect(&bob);
+    assert_eq!(handshake.accepted(&bob), None);
+}
@@ -0,0 +1,58 @@
+use std::time::{Duration, Instant};
+
+use native_node::observer::{ObserverUpdate, RateLimiter};
+
+#[test]
+fn test_frame_roundtrip() {
+    for update in [
+        ObserverUpdate::Snapshot(b"state".to_vec()),
+        ObserverUpdate::Finalized(Vec::new()),
+    ] {
+        let frame = update.encode();
+        assert_eq!(
+            u32::from_be_bytes(frame[..4].try_into().unwrap()) as usize,
+            frame.len() - 4
+        );
+        assert_eq!(ObserverUpdate::decode(&frame), Some(update));
+    }
+}
+
+#[test]
+fn test_malformed_frames_are_rejected() {
+    let frame = ObserverUpdate::Finalized(b"event".to_vec()).encode();
+    // cut short, with bytes left over, or missing the tag
+    assert_eq!(ObserverUpdate::decode(&frame[..frame.len() - 1]), None);
+    assert_eq!(
+        ObserverUpdate::decode(&[frame.as_slice(), &[0]].concat()),
+        None
+    );
+    assert_eq!(ObserverUpdate::decode(&[0, 0, 0, 0]), None);
+    assert_eq!(ObserverUpdate::decode(&[0, 0]), None);
+    // an unknown tag
+    assert_eq!(ObserverUpdate::decode(&[0, 0, 0, 1, 9]), None);
+}
+
+#[test]
+fn test_rate_limiter_allows_a_burst_then_refills() {
+    let start = Instant::now();
+    let mut limiter = RateLimiter::new_at(2, 3, start);
+    assert!((0..3).all(|_| limiter.try_acquire_at(start)));
+    assert!(!limiter.try_acquire_at(start));
+
+    // two tokens a second, one every half second
+    assert!(!limiter.try_acquire_at(start + Duration::from_millis(400)));
+    assert!(limiter.try_acquire_at(start + Duration::from_millis(600)));
+    assert!(!limiter.try_acquire_at(start + Duration::from_millis(600)));
+
+    // idle time refills no more than the burst
+    let later = start + Duration::from_secs(60);
+    assert_eq!((0..10).filter(|_| limiter.try_acquire_at(later)).count(), 3);
+}
+
+#[test]
+fn test_rate_limiter_allows_at_least_one() {
+    let start = Instant::now();
+    let mut limiter = RateLimiter::new_at(0, 0, start);
+    assert!(limiter.try_acquire_at(start));
+    assert!(!limiter.try_acquire_at(start + Duration::from_secs(60)));
+}